@@ -0,0 +1,127 @@
+// Block processing benchmarks: validating the CDR batch proofs of a block one by one against
+// verifying them as one aggregate, and MDBX block write throughput against block size
+//
+//     cargo bench --bench block_processing
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Groth16;
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use sp_cdr_reconciliation_bc::blockchain::block::{CDRTransaction, CDRType, Transaction, TransactionData};
+use sp_cdr_reconciliation_bc::blockchain::{Block, MicroBlock, MicroBody, MicroHeader};
+use sp_cdr_reconciliation_bc::primitives::{Blake2bHash, NetworkId};
+use sp_cdr_reconciliation_bc::storage::{ChainStore, MdbxChainStore};
+use sp_cdr_reconciliation_bc::zkp::circuits::{CDRBatchCircuit, CDRBatchRecord, CDR_BATCH_SIZE};
+use sp_cdr_reconciliation_bc::zkp::{AlbatrossZKProver, AlbatrossZKVerifier, CDRBatchProof};
+
+fn key_bytes<T: CanonicalSerialize>(key: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    key.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// Full batch proofs over `batches * CDR_BATCH_SIZE` records, with a verifier for them
+fn batch_proofs(batches: usize) -> (AlbatrossZKVerifier, Vec<CDRBatchProof>) {
+    let mut rng = ark_std::test_rng();
+    let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(CDRBatchCircuit::<Fr>::empty(), &mut rng).unwrap();
+    let mut prover = AlbatrossZKProver::new();
+    prover.load_cdr_batch_proving_key(&key_bytes(&pk)).unwrap();
+    let mut verifier = AlbatrossZKVerifier::new();
+    verifier.load_cdr_batch_verifying_key(&key_bytes(&vk)).unwrap();
+
+    let records: Vec<CDRBatchRecord> = (0..(batches * CDR_BATCH_SIZE) as u64).map(|i| CDRBatchRecord {
+        call_minutes: i % 120,
+        data_mb: 0,
+        sms_count: 0,
+        call_rate_cents: 15,
+        data_rate_cents: 0,
+        sms_rate_cents: 0,
+        remainder_cents: 0,
+        total_charges_cents: (i % 120) * 15,
+    }).collect();
+    let proofs = prover.generate_cdr_batch_proofs(&mut rng, &records, 1704067200, 42).unwrap();
+    (verifier, proofs)
+}
+
+fn block_validation(c: &mut Criterion) {
+    let (verifier, proofs) = batch_proofs(16);
+
+    let mut group = c.benchmark_group("block_validation");
+    for count in [1, 4, 16] {
+        let proofs = &proofs[..count];
+        let aggregate = verifier.aggregate_cdr_batch_proofs(proofs).unwrap();
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("individual", count), proofs, |b, proofs| {
+            b.iter(|| assert!(proofs.iter().all(|proof| verifier.verify_cdr_batch_proof(proof).unwrap())))
+        });
+        group.bench_with_input(BenchmarkId::new("aggregate", count), &aggregate, |b, aggregate| {
+            b.iter(|| assert!(verifier.verify_aggregate_proof(aggregate).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+/// Micro block of `transactions` CDR records with 1 KiB encrypted payloads
+fn cdr_block(block_number: u32, transactions: usize) -> Block {
+    let transactions = (0..transactions as u64).map(|nonce| Transaction {
+        sender: Blake2bHash::from_data(b"Vodafone-UK"),
+        recipient: Blake2bHash::from_data(b"T-Mobile-DE"),
+        value: 0,
+        fee: 1,
+        nonce,
+        validity_start_height: 0,
+        data: TransactionData::CDRRecord(CDRTransaction {
+            record_type: CDRType::DataSession,
+            home_network: "T-Mobile-DE".to_string(),
+            visited_network: "Vodafone-UK".to_string(),
+            encrypted_data: vec![nonce as u8; 1024],
+            zk_proof: vec![],
+        }),
+        signature: vec![1; 96],
+        signature_proof: vec![],
+    }).collect();
+
+    Block::Micro(MicroBlock {
+        header: MicroHeader {
+            network: NetworkId::SPConsortium,
+            version: 1,
+            block_number,
+            timestamp: block_number as u64,
+            parent_hash: Blake2bHash::zero(),
+            seed: Blake2bHash::zero(),
+            extra_data: vec![],
+            state_root: Blake2bHash::zero(),
+            body_root: Blake2bHash::zero(),
+            history_root: Blake2bHash::zero(),
+        },
+        body: MicroBody { transactions },
+    })
+}
+
+fn mdbx_write(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(MdbxChainStore::new(dir.path()).unwrap());
+    let block_number = AtomicU32::new(1);
+
+    let mut group = c.benchmark_group("mdbx_write");
+    for transactions in [0, 100, 1000] {
+        group.throughput(Throughput::Elements(transactions.max(1) as u64));
+        group.bench_function(BenchmarkId::from_parameter(transactions), |b| {
+            b.to_async(&runtime).iter_batched(
+                || cdr_block(block_number.fetch_add(1, Ordering::Relaxed), transactions),
+                |block| {
+                    let store = store.clone();
+                    async move { store.put_block(&block).await.unwrap() }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, block_validation, mdbx_write);
+criterion_main!(benches);
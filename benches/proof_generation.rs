@@ -0,0 +1,106 @@
+// Proof generation benchmarks: CDR privacy proof latency, batch proving latency against the number
+// of records proven, and settlement proof time against the number of operators settled
+//
+//     cargo bench --bench proof_generation
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Groth16;
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sp_cdr_reconciliation_bc::primitives::Blake2bHash;
+use sp_cdr_reconciliation_bc::zkp::circuits::{
+    CDRBatchCircuit, CDRBatchRecord, CDRCharges, CDRPrivacyCircuit, ServiceCharge,
+    SettlementCalculationCircuit, CDR_BATCH_SIZE, SETTLEMENT_MAX_OPERATORS,
+};
+use sp_cdr_reconciliation_bc::zkp::AlbatrossZKProver;
+
+const PERIOD_HASH: u64 = 1704067200;
+const NETWORK_PAIR_HASH: u64 = 42;
+
+fn key_bytes<T: CanonicalSerialize>(key: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    key.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// Roaming records with a realistic spread of usage
+fn records(count: usize) -> Vec<CDRBatchRecord> {
+    (0..count as u64).map(|i| {
+        let call_minutes = i % 120;
+        let data_mb = (i * 37) % 2048;
+        CDRBatchRecord {
+            call_minutes,
+            data_mb,
+            sms_count: 1,
+            call_rate_cents: 15,
+            data_rate_cents: 5,
+            sms_rate_cents: i % 10,
+            remainder_cents: 0,
+            total_charges_cents: call_minutes * 15 + data_mb * 5 + i % 10,
+        }
+    }).collect()
+}
+
+fn cdr_privacy_proof(c: &mut Criterion) {
+    let mut rng = ark_std::test_rng();
+    let (pk, _) = Groth16::<Bn254>::circuit_specific_setup(CDRPrivacyCircuit::<Fr>::empty(), &mut rng).unwrap();
+    let mut prover = AlbatrossZKProver::new();
+    prover.load_cdr_privacy_proving_key(&key_bytes(&pk)).unwrap();
+
+    let charges = CDRCharges {
+        voice: ServiceCharge::of(45, 675),
+        data: ServiceCharge::of(512, 2560),
+        sms: ServiceCharge::of(3, 30),
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("cdr_privacy_proof");
+    group.sample_size(10);
+    group.bench_function("single_record", |b| {
+        b.iter(|| prover.generate_cdr_privacy_proof(&mut rng, &charges, PERIOD_HASH, NETWORK_PAIR_HASH).unwrap())
+    });
+    group.finish();
+}
+
+fn cdr_batch_proof(c: &mut Criterion) {
+    let mut rng = ark_std::test_rng();
+    let (pk, _) = Groth16::<Bn254>::circuit_specific_setup(CDRBatchCircuit::<Fr>::empty(), &mut rng).unwrap();
+    let mut prover = AlbatrossZKProver::new();
+    prover.load_cdr_batch_proving_key(&key_bytes(&pk)).unwrap();
+
+    // A partial batch costs a full circuit, more records than a batch split into parallel proofs
+    let mut group = c.benchmark_group("cdr_batch_proof");
+    group.sample_size(10);
+    for count in [1, CDR_BATCH_SIZE / 2, CDR_BATCH_SIZE, CDR_BATCH_SIZE * 4] {
+        let records = records(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &records, |b, records| {
+            b.iter(|| prover.generate_cdr_batch_proofs(&mut rng, records, PERIOD_HASH, NETWORK_PAIR_HASH).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn settlement_proof(c: &mut Criterion) {
+    let mut rng = ark_std::test_rng();
+    let (pk, _) = Groth16::<Bn254>::circuit_specific_setup(SettlementCalculationCircuit::<Fr>::empty(), &mut rng).unwrap();
+    let mut prover = AlbatrossZKProver::new();
+    prover.load_settlement_proving_key(&key_bytes(&pk)).unwrap();
+    let period_commitment = Blake2bHash::from_data(b"2024-01");
+
+    let mut group = c.benchmark_group("settlement_proof");
+    group.sample_size(10);
+    for operators in [2, 4, 8, SETTLEMENT_MAX_OPERATORS] {
+        // bilateral[i][j] = what operator j owes operator i, every pair trading
+        let bilateral: Vec<Vec<u64>> = (0..operators)
+            .map(|i| (0..operators).map(|j| if i == j { 0 } else { 10_000 + (i * operators + j) as u64 * 137 }).collect())
+            .collect();
+        group.bench_with_input(BenchmarkId::from_parameter(operators), &bilateral, |b, bilateral| {
+            b.iter(|| prover.generate_settlement_proof(&mut rng, period_commitment, bilateral).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cdr_privacy_proof, cdr_batch_proof, settlement_proof);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+// Generates the gRPC bindings from proto/sp_cdr.proto when the `grpc` feature is on,
+// so default builds need neither tonic nor protoc
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/sp_cdr.proto");
+        tonic_build::compile_protos("proto/sp_cdr.proto")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// Fuzz target for the multilateral netting solver: arbitrary obligation graphs must net without
+// panicking, conserve every participant's net position, only reduce existing obligations and
+// never save less when longer cycles are allowed
+//
+//     cargo +nightly fuzz run netting
+#![no_main]
+
+use std::collections::BTreeMap;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use sp_cdr_reconciliation_bc::network::{MultilateralNettingSolver, NettingConfig};
+use sp_cdr_reconciliation_bc::primitives::NetworkId;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    max_cycle_length: u8,
+    obligations: Vec<(u8, u8, u64)>,
+}
+
+fuzz_target!(|input: Input| {
+    // Operators are drawn from a small pool so the graph is dense enough to have cycles
+    let operator = |index: u8| NetworkId::new(&format!("OP{}", index % 8), "EU");
+    let bilateral: Vec<(NetworkId, NetworkId, u64)> = input.obligations.iter()
+        .map(|(from, to, amount)| (operator(*from), operator(*to), *amount))
+        .collect();
+    let max_cycle_length = (input.max_cycle_length % 8) as usize;
+
+    let solver = MultilateralNettingSolver::new(NettingConfig { max_cycle_length, ..Default::default() });
+    let result = match solver.solve(&bilateral) {
+        Ok(result) => result,
+        // Too short a cycle length, or amounts that overflow, are rejected rather than netted
+        Err(_) => return,
+    };
+    assert!(max_cycle_length >= 2);
+
+    let mut expected: BTreeMap<String, i128> = BTreeMap::new();
+    for (from, to, amount) in bilateral.iter().filter(|(from, to, _)| from != to) {
+        *expected.entry(from.to_string()).or_insert(0) -= *amount as i128;
+        *expected.entry(to.to_string()).or_insert(0) += *amount as i128;
+    }
+    for (network, position) in &result.net_positions {
+        assert_eq!(expected.get(&network.to_string()).copied().unwrap_or(0), *position as i128);
+    }
+
+    let gross = result.obligation_matrix(&bilateral);
+    let residual = result.residual_matrix();
+    for (i, row) in residual.iter().enumerate() {
+        for (j, amount) in row.iter().enumerate() {
+            assert!(*amount <= gross[i][j]);
+        }
+    }
+    let edges = gross.iter().flatten().filter(|amount| **amount > 0).count();
+    assert!(result.cancelled_cycles.len() <= edges);
+    assert!(result.residual_total <= result.gross_total);
+
+    if max_cycle_length < 7 {
+        let longer = MultilateralNettingSolver::new(NettingConfig { max_cycle_length: max_cycle_length + 1, ..Default::default() });
+        assert!(longer.solve(&bilateral).unwrap().residual_total <= result.residual_total);
+    }
+});
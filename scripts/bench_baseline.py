@@ -0,0 +1,79 @@
+#!/usr/bin/env python3
+"""Benchmark baseline tracking
+
+Collects the mean time of every criterion benchmark from target/criterion into a JSON baseline,
+and compares a later run against it to catch regressions.
+
+    cargo bench
+    scripts/bench_baseline.py save benches/baseline.json
+    ...
+    cargo bench
+    scripts/bench_baseline.py compare benches/baseline.json --threshold 10
+"""
+import argparse
+import json
+import sys
+from pathlib import Path
+
+CRITERION_DIR = Path("target/criterion")
+
+
+def collect(criterion_dir):
+    """Mean nanoseconds per iteration of the latest run, keyed by benchmark id"""
+    results = {}
+    for estimates in sorted(criterion_dir.glob("**/new/estimates.json")):
+        benchmark = json.loads((estimates.parent / "benchmark.json").read_text())
+        mean = json.loads(estimates.read_text())["mean"]
+        results[benchmark["full_id"]] = {
+            "mean_ns": mean["point_estimate"],
+            "lower_ns": mean["confidence_interval"]["lower_bound"],
+            "upper_ns": mean["confidence_interval"]["upper_bound"],
+        }
+    return results
+
+
+def save(args):
+    results = collect(args.criterion_dir)
+    if not results:
+        sys.exit(f"No benchmark results under {args.criterion_dir}, run cargo bench first")
+    args.baseline.parent.mkdir(parents=True, exist_ok=True)
+    args.baseline.write_text(json.dumps(results, indent=2, sort_keys=True) + "\n")
+    print(f"Saved {len(results)} benchmarks to {args.baseline}")
+
+
+def compare(args):
+    baseline = json.loads(args.baseline.read_text())
+    results = collect(args.criterion_dir)
+    regressions = 0
+    for benchmark, result in sorted(results.items()):
+        if benchmark not in baseline:
+            print(f"  new        {benchmark}: {result['mean_ns'] / 1e6:.3f} ms")
+            continue
+        before = baseline[benchmark]["mean_ns"]
+        change = (result["mean_ns"] - before) / before * 100
+        # A change within the confidence interval of the new run is noise
+        regressed = change > args.threshold and result["lower_ns"] > before
+        regressions += regressed
+        status = "REGRESSED" if regressed else "ok"
+        print(f"  {status:<10} {benchmark}: {before / 1e6:.3f} ms -> {result['mean_ns'] / 1e6:.3f} ms ({change:+.1f}%)")
+    if regressions:
+        sys.exit(f"{regressions} benchmarks regressed by more than {args.threshold}%")
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--criterion-dir", type=Path, default=CRITERION_DIR)
+    commands = parser.add_subparsers(dest="command", required=True)
+    save_parser = commands.add_parser("save", help="write the latest results as the baseline")
+    save_parser.add_argument("baseline", type=Path)
+    save_parser.set_defaults(run=save)
+    compare_parser = commands.add_parser("compare", help="compare the latest results to the baseline")
+    compare_parser.add_argument("baseline", type=Path)
+    compare_parser.add_argument("--threshold", type=float, default=10.0, help="regression threshold in percent")
+    compare_parser.set_defaults(run=compare)
+    args = parser.parse_args()
+    args.run(args)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,109 @@
+// Bearer token authentication of finance operators: each token maps to a named operator and a
+// role deciding which settlement endpoints it may call. Only SHA-256 hashes of the tokens are
+// configured, so the token file does not hold usable credentials
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+
+/// What a finance operator may do, each role allowing what the ones before it do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinanceRole {
+    /// Lists settlements waiting for approval
+    Viewer,
+    /// Approves and rejects them
+    Approver,
+}
+
+/// Configured token of one finance operator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiTokenEntry {
+    /// Reviewer name recorded in the audit log
+    pub name: String,
+    pub role: FinanceRole,
+    /// Hex SHA-256 of the bearer token
+    pub token_hash: String,
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// No token, or one that is not configured
+    Unauthenticated,
+    /// A valid token without the role the endpoint needs
+    Forbidden,
+}
+
+/// Finance operator tokens by hash
+#[derive(Debug, Clone, Default)]
+pub struct ApiTokens {
+    tokens: HashMap<Blake2bHash, (String, FinanceRole)>,
+}
+
+impl ApiTokens {
+    pub fn new(entries: Vec<ApiTokenEntry>) -> Result<Self> {
+        let mut tokens = HashMap::new();
+        for entry in entries {
+            let hash = Blake2bHash::from_hex(&entry.token_hash).ok_or_else(|| BlockchainError::InvalidOperation(
+                format!("Token hash of {} is not 64 hex characters", entry.name)
+            ))?;
+            tokens.insert(hash, (entry.name, entry.role));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Load tokens from a JSON list of name, role and token hash
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read API tokens {}: {}", path.display(), e)))?;
+        let entries: Vec<ApiTokenEntry> = serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid API tokens {}: {}", path.display(), e)))?;
+        Self::new(entries)
+    }
+
+    /// Name of the operator an `Authorization: Bearer <token>` header is from, if its role is at least `role`
+    pub fn authorize(&self, authorization: Option<&str>, role: FinanceRole) -> std::result::Result<String, AuthError> {
+        let token = authorization
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or(AuthError::Unauthenticated)?;
+        let (name, granted) = self.tokens.get(&Blake2bHash::from_data(token.trim().as_bytes()))
+            .ok_or(AuthError::Unauthenticated)?;
+        if *granted < role {
+            return Err(AuthError::Forbidden);
+        }
+        Ok(name.clone())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_of_bearer_tokens() {
+        let entry = |name: &str, role, token: &str| ApiTokenEntry {
+            name: name.to_string(),
+            role,
+            token_hash: Blake2bHash::from_data(token.as_bytes()).to_hex(),
+        };
+        let tokens = ApiTokens::new(vec![
+            entry("alice", FinanceRole::Approver, "alice-secret"),
+            entry("bob", FinanceRole::Viewer, "bob-secret"),
+        ]).unwrap();
+
+        assert_eq!(tokens.authorize(Some("Bearer alice-secret"), FinanceRole::Approver), Ok("alice".to_string()));
+        assert_eq!(tokens.authorize(Some("Bearer bob-secret"), FinanceRole::Viewer), Ok("bob".to_string()));
+        assert_eq!(tokens.authorize(Some("Bearer bob-secret"), FinanceRole::Approver), Err(AuthError::Forbidden));
+        assert_eq!(tokens.authorize(Some("Bearer mallory"), FinanceRole::Viewer), Err(AuthError::Unauthenticated));
+        assert_eq!(tokens.authorize(Some("alice-secret"), FinanceRole::Viewer), Err(AuthError::Unauthenticated));
+        assert_eq!(tokens.authorize(None, FinanceRole::Viewer), Err(AuthError::Unauthenticated));
+
+        assert!(ApiTokens::new(vec![ApiTokenEntry { token_hash: "abc".to_string(), ..entry("carol", FinanceRole::Viewer, "") }]).is_err());
+    }
+}
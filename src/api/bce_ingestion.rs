@@ -1,7 +1,13 @@
 // BCE Record Ingestion API
 // Provides HTTP endpoints for receiving BCE records from operator billing systems
 
-use crate::bce_pipeline::{BCERecord, BCEPipeline};
+use crate::bce_pipeline::{BCERecord, BCEPipeline, approvals::{ApprovalDecision, PendingApproval}, commitment::RecordDisclosure};
+use crate::api::auth::{ApiTokens, AuthError, FinanceRole};
+use crate::blockchain::block::Transaction;
+use crate::primitives::{Blake2bHash, BlockchainError};
+use crate::smart_contracts::EventFilter;
+use crate::storage::ExportFormat;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -12,6 +18,8 @@ use tracing::{info, warn, error};
 pub struct BCEIngestAPI {
     pipeline: Arc<Mutex<BCEPipeline>>,
     port: u16,
+    /// Finance operators allowed on the settlement approval endpoints
+    tokens: Arc<ApiTokens>,
 }
 
 /// BCE record submission request
@@ -29,6 +37,84 @@ pub struct BCEResponse {
     pub batch_id: Option<String>,
 }
 
+/// Seconds a saturated node asks ingestion clients to wait before retrying
+const RETRY_AFTER_SECONDS: &str = "30";
+
+/// Settlement waiting for approval, as listed to finance operators
+#[derive(Debug, Serialize)]
+pub struct PendingApprovalView {
+    pub proposal_id: String,
+    pub creditor: String,
+    pub debtor: String,
+    pub amount_cents: u64,
+    pub requested_at: u64,
+    pub due_at: u64,
+}
+
+impl From<&PendingApproval> for PendingApprovalView {
+    fn from(approval: &PendingApproval) -> Self {
+        Self {
+            proposal_id: approval.proposal_id.to_hex(),
+            creditor: approval.creditor.to_string(),
+            debtor: approval.debtor.to_string(),
+            amount_cents: approval.amount_cents,
+            requested_at: approval.requested_at,
+            due_at: approval.due_at,
+        }
+    }
+}
+
+/// Reason a finance operator gives for rejecting a settlement
+#[derive(Debug, Deserialize)]
+pub struct RejectionRequest {
+    pub reason: String,
+}
+
+/// Payment a finance operator saw arrive for an escrowed settlement
+#[derive(Debug, Deserialize)]
+pub struct PaymentConfirmationRequest {
+    /// Reference of the transfer at the payment rail
+    pub transaction_ref: Option<String>,
+}
+
+/// Settlement file asked for by a billing system
+#[derive(Debug, Deserialize)]
+pub struct SettlementExportQuery {
+    /// Settlement period, or the prefix of the periods, e.g. `2024-03`
+    pub period: String,
+    /// Counterparty operator, `name:country`
+    pub counterparty: String,
+    /// `tap` or `bce-json`
+    #[serde(default = "default_export_format")]
+    pub format: String,
+}
+
+fn default_export_format() -> String {
+    "bce-json".to_string()
+}
+
+/// Contract events asked for, unset criteria matching any event
+#[derive(Debug, Default, Deserialize)]
+pub struct ContractEventQuery {
+    /// Emitting contract, hex, ignored by routes taking it from the path
+    pub contract: Option<String>,
+    /// Topic the events carry, hex
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub from_block: u32,
+}
+
+impl ContractEventQuery {
+    fn filter(&self) -> Result<EventFilter, &'static str> {
+        let parse = |hex: &Option<String>, message| hex.as_deref().map(|hex| Blake2bHash::from_hex(hex).ok_or(message)).transpose();
+        Ok(EventFilter {
+            contract: parse(&self.contract, "Expected a 64 character hex contract address")?,
+            topic: parse(&self.topic, "Expected a 64 character hex topic")?,
+            from_block: self.from_block,
+        })
+    }
+}
+
 /// Batch processing status
 #[derive(Debug, Serialize)]
 pub struct BatchStatus {
@@ -40,7 +126,13 @@ pub struct BatchStatus {
 
 impl BCEIngestAPI {
     pub fn new(pipeline: Arc<Mutex<BCEPipeline>>, port: u16) -> Self {
-        Self { pipeline, port }
+        Self { pipeline, port, tokens: Arc::new(ApiTokens::default()) }
+    }
+
+    /// Finance operator tokens of the approval endpoints, which refuse every request without them
+    pub fn with_api_tokens(mut self, tokens: ApiTokens) -> Self {
+        self.tokens = Arc::new(tokens);
+        self
     }
 
     /// Start the BCE ingestion API server
@@ -75,6 +167,163 @@ impl BCEIngestAPI {
             .and(with_pipeline(pipeline.clone()))
             .and_then(get_pipeline_stats);
 
+        // GET /api/v1/receipts/{tx_hash} - Contract receipt of a transaction
+        let receipt = warp::path!("api" / "v1" / "receipts" / String)
+            .and(warp::get())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_receipt);
+
+        // GET /api/v1/contracts/{address}/receipts - Receipts of a contract's transactions
+        let contract_receipts = warp::path!("api" / "v1" / "contracts" / String / "receipts")
+            .and(warp::get())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_contract_receipts);
+
+        // GET /api/v1/contracts/{address}/events?topic=&from_block= - Events a contract emitted
+        let contract_events = warp::path!("api" / "v1" / "contracts" / String / "events")
+            .and(warp::get())
+            .and(warp::query::<ContractEventQuery>())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_contract_events);
+
+        // GET /api/v1/contracts/{address}/archive/{key} - Archival proof resurrecting an expired storage slot
+        let archival_proof = warp::path!("api" / "v1" / "contracts" / String / "archive" / String)
+            .and(warp::get())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_archival_proof);
+
+        // GET /api/v1/events/stream?contract=&topic= - Server-sent contract events as their blocks commit
+        let event_stream = warp::path!("api" / "v1" / "events" / "stream")
+            .and(warp::get())
+            .and(warp::query::<ContractEventQuery>())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(stream_contract_events);
+
+        // GET /api/v1/bce/batch/{batch_id}/records/{record_id}/proof - Disclose a record with its inclusion proof
+        let record_proof = warp::path!("api" / "v1" / "bce" / "batch" / String / "records" / String / "proof")
+            .and(warp::get())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_record_proof);
+
+        // POST /api/v1/bce/disclosures/verify - Check a disclosed record against its batch commitment
+        let verify_disclosure = warp::path!("api" / "v1" / "bce" / "disclosures" / "verify")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(verify_record_disclosure);
+
+        // GET /api/v1/bce/commitments/{commitment_id} - Aggregated CDR commitment on chain
+        let commitment = warp::path!("api" / "v1" / "bce" / "commitments" / String)
+            .and(warp::get())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_commitment);
+
+        // POST /api/v1/bce/commitments/{commitment_id}/records/{index}/challenge - Demand a committed record be revealed
+        let challenge = warp::path!("api" / "v1" / "bce" / "commitments" / String / "records" / u32 / "challenge")
+            .and(warp::post())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(challenge_commitment);
+
+        // GET /api/v1/positions - Net settlement positions of every operator pair
+        let positions = warp::path!("api" / "v1" / "positions")
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_positions);
+
+        // GET /api/v1/positions/{operator} - Net settlement positions of one operator
+        let operator_positions = warp::path!("api" / "v1" / "positions" / String)
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_operator_positions);
+
+        // GET /api/v1/reputation - Reputation scores of every operator with a settlement history
+        let reputation = warp::path!("api" / "v1" / "reputation")
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_reputation);
+
+        // GET /api/v1/reputation/{operator} - Reputation score of one operator, named name:country
+        let operator_reputation = warp::path!("api" / "v1" / "reputation" / String)
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_operator_reputation);
+
+        // GET /api/v1/analytics/netting-projection - Projected netting savings of the open settlement period
+        let netting_projection = warp::path!("api" / "v1" / "analytics" / "netting-projection")
+            .and(warp::get())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(get_netting_projection);
+
+        // POST /api/v1/fees/estimate - Fee a transaction should pay to make the next block
+        let fee_estimate = warp::path!("api" / "v1" / "fees" / "estimate")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(estimate_fee);
+
+        // GET /api/v1/settlements/pending - Settlements waiting for approval
+        let pending_settlements = warp::path!("api" / "v1" / "settlements" / "pending")
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(list_pending_settlements);
+
+        // POST /api/v1/settlements/{id}/approve - Approve a settlement above the auto-accept threshold
+        let approve_settlement = warp::path!("api" / "v1" / "settlements" / String / "approve")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(|proposal_id, authorization, tokens, pipeline| {
+                decide_settlement(proposal_id, ApprovalDecision::Approve, authorization, tokens, pipeline)
+            });
+
+        // POST /api/v1/settlements/{id}/reject - Reject a settlement above the auto-accept threshold
+        let reject_settlement = warp::path!("api" / "v1" / "settlements" / String / "reject")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::json())
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(|proposal_id, authorization, rejection: RejectionRequest, tokens, pipeline| {
+                decide_settlement(proposal_id, ApprovalDecision::Reject { reason: rejection.reason }, authorization, tokens, pipeline)
+            });
+
+        // GET /api/v1/settlements/export?period=&counterparty=&format= - Settlement file for a billing system
+        let settlement_export = warp::path!("api" / "v1" / "settlements" / "export")
+            .and(warp::get())
+            .and(warp::query::<SettlementExportQuery>())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(export_settlements);
+
+        // GET /api/v1/settlements/{tx_hash}/instruction - Signed pain.001 document of a finalized settlement
+        let payment_instruction = warp::path!("api" / "v1" / "settlements" / String / "instruction")
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(payment_instruction);
+
+        // POST /api/v1/settlements/{tx_hash}/payment-confirmation - Confirm an escrowed settlement was paid
+        let payment_confirmation = warp::path!("api" / "v1" / "settlements" / String / "payment-confirmation")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::json())
+            .and(with_tokens(self.tokens.clone()))
+            .and(with_pipeline(pipeline.clone()))
+            .and_then(confirm_payment);
+
         // Health check endpoint
         let health = warp::path!("health")
             .and(warp::get())
@@ -84,8 +333,32 @@ impl BCEIngestAPI {
             .or(batch_status)
             .or(batch_submit)
             .or(stats)
-            .or(health)
-            .with(warp::cors().allow_any_origin().allow_headers(vec!["content-type"]).allow_methods(vec!["GET", "POST"]));
+            .or(receipt)
+            .or(contract_receipts)
+            .or(contract_events)
+            .or(archival_proof)
+            .or(event_stream)
+            .or(record_proof)
+            .or(verify_disclosure)
+            .or(commitment)
+            .or(challenge)
+            .or(positions)
+            .or(operator_positions)
+            .or(reputation)
+            .or(operator_reputation)
+            .or(netting_projection)
+            .or(fee_estimate)
+            .or(pending_settlements)
+            .or(approve_settlement)
+            .or(reject_settlement)
+            .or(settlement_export)
+            .or(payment_instruction)
+            .or(payment_confirmation)
+            .or(health);
+        #[cfg(feature = "explorer")]
+        let routes = routes.or(super::explorer::routes(pipeline.clone()));
+        let routes = routes
+            .with(warp::cors().allow_any_origin().allow_headers(vec!["content-type", "authorization"]).allow_methods(vec!["GET", "POST"]));
 
         info!("✅ BCE API ready - accepting BCE records from operator billing systems");
         info!("📡 Endpoints:");
@@ -93,7 +366,30 @@ impl BCEIngestAPI {
         info!("   POST /api/v1/bce/batch/submit - Submit BCE record batch");
         info!("   GET  /api/v1/bce/batch/{{batch_id}}/status - Check batch status");
         info!("   GET  /api/v1/bce/stats - Pipeline statistics");
+        info!("   GET  /api/v1/receipts/{{tx_hash}} - Transaction receipt");
+        info!("   GET  /api/v1/contracts/{{address}}/receipts - Contract receipts");
+        info!("   GET  /api/v1/contracts/{{address}}/events - Contract events");
+        info!("   GET  /api/v1/contracts/{{address}}/archive/{{key}} - Archival proof of expired state");
+        info!("   GET  /api/v1/events/stream - Stream of contract events");
+        info!("   GET  /api/v1/bce/batch/{{batch_id}}/records/{{record_id}}/proof - Record disclosure");
+        info!("   POST /api/v1/bce/disclosures/verify - Verify record disclosure");
+        info!("   GET  /api/v1/bce/commitments/{{commitment_id}} - Aggregated CDR commitment");
+        info!("   POST /api/v1/bce/commitments/{{commitment_id}}/records/{{index}}/challenge - Challenge a committed record");
+        info!("   GET  /api/v1/positions - Net settlement positions");
+        info!("   GET  /api/v1/positions/{{operator}} - Net settlement positions of an operator");
+        info!("   GET  /api/v1/reputation - Operator reputation scores");
+        info!("   GET  /api/v1/reputation/{{operator}} - Reputation of an operator");
+        info!("   GET  /api/v1/analytics/netting-projection - Projected netting savings");
+        info!("   POST /api/v1/fees/estimate - Estimate a transaction fee");
+        info!("   GET  /api/v1/settlements/pending - Settlements waiting for approval");
+        info!("   POST /api/v1/settlements/{{id}}/approve - Approve a pending settlement");
+        info!("   POST /api/v1/settlements/{{id}}/reject - Reject a pending settlement");
+        info!("   GET  /api/v1/settlements/export - TAP-out or BCE JSON settlement file of a period");
+        info!("   GET  /api/v1/settlements/{{tx_hash}}/instruction - Signed pain.001 document of a settlement");
+        info!("   POST /api/v1/settlements/{{tx_hash}}/payment-confirmation - Confirm an escrowed settlement was paid");
         info!("   GET  /health - Health check");
+        #[cfg(feature = "explorer")]
+        info!("   GET  /explorer - Block explorer");
 
         warp::serve(routes)
             .run(([0, 0, 0, 0], self.port))
@@ -107,7 +403,7 @@ impl BCEIngestAPI {
 async fn submit_bce_record(
     request: BCERecordRequest,
     pipeline: Arc<Mutex<BCEPipeline>>
-) -> Result<impl Reply, warp::Rejection> {
+) -> Result<warp::reply::Response, warp::Rejection> {
     info!("📋 Received BCE record: {} from PLMN {}->{}",
           request.record.record_id,
           request.record.home_plmn,
@@ -124,7 +420,11 @@ async fn submit_bce_record(
             };
 
             info!("✅ BCE record processed: {}", request.record.record_id);
-            Ok(warp::reply::json(&response))
+            Ok(warp::reply::json(&response).into_response())
+        }
+        Err(BlockchainError::Saturated(message)) => {
+            warn!("🚰 BCE record {} refused: {}", request.record.record_id, message);
+            Ok(saturated_reply(&message))
         }
         Err(e) => {
             error!("❌ Failed to process BCE record {}: {:?}", request.record.record_id, e);
@@ -133,7 +433,7 @@ async fn submit_bce_record(
                 message: format!("Failed to process BCE record: {}", e),
                 batch_id: None,
             };
-            Ok(warp::reply::json(&response))
+            Ok(warp::reply::json(&response).into_response())
         }
     }
 }
@@ -142,22 +442,26 @@ async fn submit_bce_record(
 async fn submit_bce_batch(
     records: Vec<BCERecordRequest>,
     pipeline: Arc<Mutex<BCEPipeline>>
-) -> Result<impl Reply, warp::Rejection> {
+) -> Result<warp::reply::Response, warp::Rejection> {
     info!("📦 Received BCE batch with {} records", records.len());
 
     let mut pipeline = pipeline.lock().await;
-    let mut successful = 0;
-    let mut failed = 0;
-
-    for record_request in records {
-        match pipeline.process_bce_record(record_request.record.clone()).await {
-            Ok(()) => successful += 1,
-            Err(e) => {
-                warn!("Failed to process BCE record {}: {:?}", record_request.record.record_id, e);
-                failed += 1;
-            }
+    let total = records.len();
+
+    // One batch ZK proof per CDR_BATCH_SIZE records instead of one per record
+    let (successful, failed) = match pipeline.process_bce_records_batch(
+        records.into_iter().map(|r| r.record).collect()
+    ).await {
+        Ok(processed) => (processed, total - processed),
+        Err(BlockchainError::Saturated(message)) => {
+            warn!("🚰 BCE batch of {} records refused: {}", total, message);
+            return Ok(saturated_reply(&message));
         }
-    }
+        Err(e) => {
+            warn!("Failed to process BCE batch: {:?}", e);
+            (0, total)
+        }
+    };
 
     let response = BCEResponse {
         success: failed == 0,
@@ -166,7 +470,7 @@ async fn submit_bce_batch(
     };
 
     info!("✅ BCE batch processed: {} successful, {} failed", successful, failed);
-    Ok(warp::reply::json(&response))
+    Ok(warp::reply::json(&response).into_response())
 }
 
 /// Get batch processing status
@@ -197,6 +501,484 @@ async fn get_pipeline_stats(
     Ok(warp::reply::json(stats))
 }
 
+/// Get the contract receipt of a transaction
+async fn get_receipt(
+    tx_hash: String,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let tx_hash = match Blake2bHash::from_hex(&tx_hash) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex transaction hash")),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.get_receipt(&tx_hash).await {
+        Ok(Some(receipt)) => Ok(warp::reply::with_status(warp::reply::json(&receipt), warp::http::StatusCode::OK)),
+        Ok(None) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, "No receipt for transaction")),
+        Err(e) => {
+            error!("❌ Receipt lookup failed for {}: {:?}", tx_hash, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Get the receipts of every transaction that executed a contract
+async fn get_contract_receipts(
+    address: String,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let address = match Blake2bHash::from_hex(&address) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex contract address")),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.get_contract_receipts(&address).await {
+        Ok(receipts) => Ok(warp::reply::with_status(warp::reply::json(&receipts), warp::http::StatusCode::OK)),
+        Err(e) => {
+            error!("❌ Receipt lookup failed for contract {}: {:?}", address, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Proof for a `StateResurrection` transaction restoring an expired storage slot
+async fn get_archival_proof(
+    address: String,
+    key: String,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let (contract, key) = match (Blake2bHash::from_hex(&address), Blake2bHash::from_hex(&key)) {
+        (Some(contract), Some(key)) => (contract, key),
+        _ => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex contract address and storage key")),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.archival_proof(&contract, &key).await {
+        Ok(Some(resurrection)) => Ok(warp::reply::with_status(warp::reply::json(&resurrection), warp::http::StatusCode::OK)),
+        Ok(None) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, "Slot is not expired or its archive was pruned here")),
+        Err(e) => {
+            error!("❌ Archival proof lookup failed for slot {} of contract {}: {:?}", key, contract, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Events a contract emitted, by topic and from a block on
+async fn get_contract_events(
+    address: String,
+    query: ContractEventQuery,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let contract = match Blake2bHash::from_hex(&address) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex contract address")),
+    };
+    let filter = match query.filter() {
+        Ok(filter) => EventFilter { contract: Some(contract), ..filter },
+        Err(message) => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, message)),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.get_contract_events(&contract, filter.topic.as_ref()).await {
+        Ok(events) => {
+            let events: Vec<_> = events.into_iter().filter(|event| filter.matches(event)).collect();
+            Ok(warp::reply::with_status(warp::reply::json(&events), warp::http::StatusCode::OK))
+        }
+        Err(e) => {
+            error!("❌ Event lookup failed for contract {}: {:?}", contract, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Contract events matching the query as server-sent events, from the next committed block on
+async fn stream_contract_events(
+    query: ContractEventQuery,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(message) => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, message).into_response()),
+    };
+    let receiver = pipeline.lock().await.subscribe_contract_events();
+
+    // Subscribers that fall behind skip the events they missed rather than being cut off
+    let events = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Contract event subscriber lagging, skipped {} events", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter(move |event| futures::future::ready(filter.matches(event)))
+    .map(|event| warp::sse::Event::default().event("contract_event").json_data(&event));
+
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(events)).into_response())
+}
+
+/// Disclose one record of a frozen batch with its Merkle inclusion proof
+async fn get_record_proof(
+    batch_id: String,
+    record_id: String,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let batch_id = match Blake2bHash::from_hex(&batch_id) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex batch id")),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.disclose_record(&batch_id, &record_id) {
+        Ok(disclosure) => Ok(warp::reply::with_status(warp::reply::json(&disclosure), warp::http::StatusCode::OK)),
+        Err(e) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &e.to_string())),
+    }
+}
+
+/// Check a disclosed record against the Merkle root its batch was committed with on chain
+async fn verify_record_disclosure(
+    disclosure: RecordDisclosure,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let pipeline = pipeline.lock().await;
+    let response = match pipeline.verify_disclosure(&disclosure) {
+        Ok(()) => serde_json::json!({"valid": true, "batch_id": disclosure.batch_id.to_string(), "record_id": disclosure.record.record_id}),
+        Err(e) => serde_json::json!({"valid": false, "batch_id": disclosure.batch_id.to_string(), "error": e.to_string()}),
+    };
+    Ok(warp::reply::json(&response))
+}
+
+/// Aggregated CDR commitment as recorded on chain, with whether it stood up to its challenges
+async fn get_commitment(
+    commitment_id: String,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let commitment_id = match Blake2bHash::from_hex(&commitment_id) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex commitment id")),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.cdr_commitment(&commitment_id) {
+        Some(commitment) => Ok(warp::reply::with_status(warp::reply::json(&commitment), warp::http::StatusCode::OK)),
+        None => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &format!("No commitment {} on chain", commitment_id))),
+    }
+}
+
+/// Queue a challenge making the committer reveal one committed record on chain
+async fn challenge_commitment(
+    commitment_id: String,
+    record_index: u32,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let commitment_id = match Blake2bHash::from_hex(&commitment_id) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex commitment id")),
+    };
+
+    let mut pipeline = pipeline.lock().await;
+    match pipeline.challenge_commitment(commitment_id, record_index) {
+        Ok(tx_hash) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"tx_hash": tx_hash.to_hex(), "commitment_id": commitment_id.to_hex(), "record_index": record_index})),
+            warp::http::StatusCode::ACCEPTED,
+        )),
+        Err(BlockchainError::NotFound(message)) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &message)),
+        Err(e) => Ok(error_reply(warp::http::StatusCode::CONFLICT, &e.to_string())),
+    }
+}
+
+/// Net settlement positions of every operator pair, per period and currency
+async fn get_positions(
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e));
+    }
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.settlement_positions(None).await {
+        Ok(positions) => Ok(warp::reply::with_status(warp::reply::json(&positions), warp::http::StatusCode::OK)),
+        Err(e) => {
+            error!("❌ Settlement position lookup failed: {:?}", e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Net settlement positions of one operator towards each counterparty, per period and currency
+async fn get_operator_positions(
+    operator: String,
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e));
+    }
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.settlement_positions(Some(&operator)).await {
+        Ok(positions) => Ok(warp::reply::with_status(warp::reply::json(&positions), warp::http::StatusCode::OK)),
+        Err(e) => {
+            error!("❌ Settlement position lookup failed for {}: {:?}", operator, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Reputation scores of every operator with a settlement history, as of the last macro block
+async fn get_reputation(
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e));
+    }
+
+    let pipeline = pipeline.lock().await;
+    Ok(warp::reply::with_status(warp::reply::json(pipeline.reputation()), warp::http::StatusCode::OK))
+}
+
+/// Reputation of one operator, named `name:country`
+async fn get_operator_reputation(
+    operator: String,
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e));
+    }
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.reputation().operator(&operator) {
+        Some(reputation) => Ok(warp::reply::with_status(warp::reply::json(reputation), warp::http::StatusCode::OK)),
+        None => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &format!("No settlement history of {}", operator))),
+    }
+}
+
+/// Savings bilateral, triangular and multilateral netting would make over the pending balances
+async fn get_netting_projection(
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let pipeline = pipeline.lock().await;
+    match pipeline.netting_projection() {
+        Ok(projection) => Ok(warp::reply::with_status(warp::reply::json(&projection), warp::http::StatusCode::OK)),
+        Err(e) => {
+            error!("❌ Netting projection failed: {:?}", e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Minimum and suggested fee of a transaction, from the congestion of the transaction queue
+async fn estimate_fee(
+    transaction: Transaction,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    let pipeline = pipeline.lock().await;
+    Ok(warp::reply::json(&pipeline.estimate_fee(&transaction)))
+}
+
+/// Settlements above the auto-accept threshold waiting for a finance operator, the first due first
+async fn list_pending_settlements(
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e));
+    }
+
+    let pipeline = pipeline.lock().await;
+    let pending: Vec<PendingApprovalView> = pipeline.pending_approvals().into_iter().map(PendingApprovalView::from).collect();
+    Ok(warp::reply::with_status(warp::reply::json(&pending), warp::http::StatusCode::OK))
+}
+
+/// Approve or reject a settlement waiting for approval, as the finance operator the token is from
+async fn decide_settlement(
+    proposal_id: String,
+    decision: ApprovalDecision,
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    let reviewer = match tokens.authorize(authorization.as_deref(), FinanceRole::Approver) {
+        Ok(reviewer) => reviewer,
+        Err(e) => return Ok(auth_error_reply(e)),
+    };
+    let proposal_id = match Blake2bHash::from_hex(&proposal_id) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex settlement id")),
+    };
+
+    let mut pipeline = pipeline.lock().await;
+    match pipeline.decide_settlement(&proposal_id, decision.clone(), &reviewer).await {
+        Ok(()) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"proposal_id": proposal_id.to_hex(), "decision": decision, "reviewer": reviewer})),
+            warp::http::StatusCode::OK,
+        )),
+        Err(BlockchainError::NotFound(message)) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &message)),
+        Err(e) => {
+            error!("❌ Decision on settlement {} by {} failed: {:?}", proposal_id, reviewer, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// Settlement file of the periods matching `period` with one counterparty, in TAP-out or BCE JSON form
+async fn export_settlements(
+    query: SettlementExportQuery,
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e).into_response());
+    }
+    let format: ExportFormat = match query.format.parse() {
+        Ok(format) => format,
+        Err(e) => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, &e.to_string()).into_response()),
+    };
+
+    let pipeline = pipeline.lock().await;
+    let exports = match pipeline.settlement_exports(&query.period).await {
+        Ok(exports) => exports,
+        Err(e) => {
+            error!("❌ Settlement export of {} failed: {:?}", query.period, e);
+            return Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()).into_response());
+        }
+    };
+    // One file per period, so a prefix matching several periods is refused
+    let mut matching = exports.iter().filter(|export| export.counterparty == query.counterparty);
+    let export = match (matching.next(), matching.next()) {
+        (Some(export), None) => export,
+        (None, _) => return Ok(error_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            &format!("No finalized settlement period {} with {}", query.period, query.counterparty),
+        ).into_response()),
+        (Some(_), Some(_)) => return Ok(error_reply(
+            warp::http::StatusCode::BAD_REQUEST,
+            &format!("{} matches several settlement periods, name one", query.period),
+        ).into_response()),
+    };
+
+    match export.render(format) {
+        Ok(body) => {
+            let content_type = match format {
+                ExportFormat::TapOut => "text/plain",
+                ExportFormat::BceJson => "application/json",
+            };
+            let disposition = format!("attachment; filename=\"{}\"", export.file_name(format));
+            let reply = warp::reply::with_header(body, "content-type", content_type);
+            Ok(warp::reply::with_header(reply, "content-disposition", disposition).into_response())
+        }
+        Err(e) => Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()).into_response()),
+    }
+}
+
+/// Signed pain.001 document of a finalized settlement for the banking gateway, the coordinator's
+/// key and signature and whether the document hash is anchored on chain in the headers
+async fn payment_instruction(
+    settlement_tx: String,
+    authorization: Option<String>,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(e) = tokens.authorize(authorization.as_deref(), FinanceRole::Viewer) {
+        return Ok(auth_error_reply(e).into_response());
+    }
+    let settlement_tx = match Blake2bHash::from_hex(&settlement_tx) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex transaction hash").into_response()),
+    };
+
+    let pipeline = pipeline.lock().await;
+    match pipeline.payment_document(&settlement_tx).await {
+        Ok(Some((document, anchored))) => {
+            let disposition = format!("attachment; filename=\"pain001-{}.xml\"", &settlement_tx.to_hex()[..32]);
+            let reply = warp::reply::with_header(document.xml.clone(), "content-type", "application/xml");
+            let reply = warp::reply::with_header(reply, "content-disposition", disposition);
+            let reply = warp::reply::with_header(reply, "x-document-schema", document.schema.clone());
+            let reply = warp::reply::with_header(reply, "x-document-hash", document.document_hash().to_hex());
+            let reply = warp::reply::with_header(reply, "x-coordinator-key", hex::encode(&document.signer));
+            let reply = warp::reply::with_header(reply, "x-coordinator-signature", hex::encode(&document.signature));
+            Ok(warp::reply::with_header(reply, "x-anchored", anchored.to_string()).into_response())
+        }
+        Ok(None) => Ok(error_reply(
+            warp::http::StatusCode::NOT_FOUND,
+            &format!("No payment document issued for settlement {}", settlement_tx),
+        ).into_response()),
+        Err(e) => {
+            error!("❌ Payment document of settlement {} could not be read: {:?}", settlement_tx, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()).into_response())
+        }
+    }
+}
+
+/// Confirm the payment of an escrowed settlement owed to an operator of this node, releasing the escrow
+async fn confirm_payment(
+    settlement_tx: String,
+    authorization: Option<String>,
+    request: PaymentConfirmationRequest,
+    tokens: Arc<ApiTokens>,
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    let reviewer = match tokens.authorize(authorization.as_deref(), FinanceRole::Approver) {
+        Ok(reviewer) => reviewer,
+        Err(e) => return Ok(auth_error_reply(e)),
+    };
+    let settlement_tx = match Blake2bHash::from_hex(&settlement_tx) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex transaction hash")),
+    };
+
+    let mut pipeline = pipeline.lock().await;
+    match pipeline.confirm_settlement_payment(&settlement_tx, request.transaction_ref).await {
+        Ok(tx_hash) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"settlement_tx": settlement_tx.to_hex(), "transaction_hash": tx_hash.to_hex(), "reviewer": reviewer})),
+            warp::http::StatusCode::ACCEPTED,
+        )),
+        Err(BlockchainError::NotFound(message)) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &message)),
+        Err(BlockchainError::InvalidState(message)) => Ok(error_reply(warp::http::StatusCode::CONFLICT, &message)),
+        Err(e) => {
+            error!("❌ Payment confirmation of settlement {} by {} failed: {:?}", settlement_tx, reviewer, e);
+            Ok(error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+        }
+    }
+}
+
+/// 401 for a missing or unknown token, 403 for one without the role needed
+fn auth_error_reply(error: AuthError) -> warp::reply::WithStatus<warp::reply::Json> {
+    match error {
+        AuthError::Unauthenticated => error_reply(warp::http::StatusCode::UNAUTHORIZED, "Missing or unknown bearer token"),
+        AuthError::Forbidden => error_reply(warp::http::StatusCode::FORBIDDEN, "Token does not allow this operation"),
+    }
+}
+
+/// JSON error body with a status code
+fn error_reply(status: warp::http::StatusCode, message: &str) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&serde_json::json!({"error": message})), status)
+}
+
+/// 503 telling the billing system to back off and resend later
+fn saturated_reply(message: &str) -> warp::reply::Response {
+    let response = BCEResponse {
+        success: false,
+        message: message.to_string(),
+        batch_id: None,
+    };
+    let reply = warp::reply::with_status(warp::reply::json(&response), warp::http::StatusCode::SERVICE_UNAVAILABLE);
+    warp::reply::with_header(reply, "retry-after", RETRY_AFTER_SECONDS).into_response()
+}
+
 /// Warp filter to pass pipeline to handlers
 fn with_pipeline(
     pipeline: Arc<Mutex<BCEPipeline>>
@@ -204,6 +986,13 @@ fn with_pipeline(
     warp::any().map(move || pipeline.clone())
 }
 
+/// Warp filter to pass the finance operator tokens to handlers
+fn with_tokens(
+    tokens: Arc<ApiTokens>
+) -> impl Filter<Extract = (Arc<ApiTokens>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || tokens.clone())
+}
+
 /// Example curl commands for testing
 pub fn print_curl_examples(port: u16) {
     println!("📡 BCE API Curl Examples:");
@@ -257,7 +1046,21 @@ pub fn print_curl_examples(port: u16) {
     println!("curl http://localhost:{}/api/v1/bce/stats", port);
     println!("");
 
-    println!("4️⃣ Health check:");
+    println!("4️⃣ Look up a transaction receipt:");
+    println!("curl http://localhost:{}/api/v1/receipts/<tx_hash>", port);
+    println!("");
+
+    println!("5️⃣ Net settlement positions of an operator:");
+    println!("curl http://localhost:{}/api/v1/positions/T-Mobile \\", port);
+    println!("  -H \"Authorization: Bearer <finance operator token>\"");
+    println!("");
+
+    println!("6️⃣ Approve a settlement above the auto-accept threshold:");
+    println!("curl -X POST http://localhost:{}/api/v1/settlements/<settlement_id>/approve \\", port);
+    println!("  -H \"Authorization: Bearer <finance operator token>\"");
+    println!("");
+
+    println!("7️⃣ Health check:");
     println!("curl http://localhost:{}/health", port);
     println!("");
 }
\ No newline at end of file
@@ -0,0 +1,474 @@
+// Block explorer served by the node behind the `explorer` feature: a single page app at
+// `/explorer` over read-only JSON endpoints for recent blocks, transactions, the validator set
+// and settlement period reports. Transactions are summarized from what the chain commits to, so
+// CDR payloads and revealed subscriber data never leave the node through the explorer
+use crate::bce_pipeline::BCEPipeline;
+use crate::blockchain::Block;
+use crate::blockchain::block::{Transaction, TransactionData};
+use crate::primitives::{Blake2bHash, Height, Policy, Result};
+use crate::smart_contracts::EscrowTransaction;
+use crate::storage::{ChainStore, MdbxChainStore, SettlementReport};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::{Filter, Reply};
+use tracing::error;
+
+/// The explorer page, its script and styles inline
+const EXPLORER_PAGE: &str = include_str!("explorer/index.html");
+
+/// Blocks listed when a request asks for none or too many
+const DEFAULT_BLOCK_LIMIT: usize = 20;
+const MAX_BLOCK_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct BlockListQuery {
+    /// List blocks below this height, from the head if unset
+    pub before: Option<Height>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeriodQuery {
+    /// Settlement period or its prefix, e.g. `2024-03` for every period starting in March 2024
+    pub period: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockSummary {
+    pub block_number: Height,
+    pub hash: Blake2bHash,
+    /// `micro`, `macro` or `election`
+    pub kind: String,
+    pub timestamp: u64,
+    pub transaction_count: usize,
+}
+
+impl From<&Block> for BlockSummary {
+    fn from(block: &Block) -> Self {
+        let kind = match block {
+            Block::Micro(_) => "micro",
+            Block::Macro(_) if Policy::is_election_block(block.block_number()) => "election",
+            Block::Macro(_) => "macro",
+        };
+        Self {
+            block_number: block.block_number(),
+            hash: block.hash(),
+            kind: kind.to_string(),
+            timestamp: block.timestamp(),
+            transaction_count: block.transactions().len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockView {
+    #[serde(flatten)]
+    pub summary: BlockSummary,
+    pub parent_hash: Blake2bHash,
+    pub state_root: Blake2bHash,
+    pub transactions: Vec<TransactionSummary>,
+}
+
+/// Transaction with its payload reduced to labelled fields fit to show any consortium member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionSummary {
+    pub hash: Blake2bHash,
+    pub kind: String,
+    pub sender: Blake2bHash,
+    pub recipient: Blake2bHash,
+    pub value: u64,
+    pub fee: u64,
+    pub nonce: u64,
+    pub details: Vec<(String, String)>,
+}
+
+impl From<&Transaction> for TransactionSummary {
+    fn from(transaction: &Transaction) -> Self {
+        let (kind, details) = payload_summary(&transaction.data);
+        Self {
+            hash: transaction.hash(),
+            kind: kind.to_string(),
+            sender: transaction.sender,
+            recipient: transaction.recipient,
+            value: transaction.value,
+            fee: transaction.fee,
+            nonce: transaction.nonce,
+            details: details.into_iter().map(|(label, value)| (label.to_string(), value)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionView {
+    pub block_number: Height,
+    pub block_hash: Blake2bHash,
+    pub index: u32,
+    #[serde(flatten)]
+    pub summary: TransactionSummary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorView {
+    pub address: Blake2bHash,
+    pub reward_address: Blake2bHash,
+    pub stake: u64,
+    pub jailed_from: Option<Height>,
+    pub inactive_from: Option<Height>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorSetView {
+    pub epoch: u32,
+    pub election_block: Height,
+    pub total_stake: u64,
+    pub validators: Vec<ValidatorView>,
+}
+
+/// Kind of a transaction and the fields shown for it: record types, networks, amounts and sizes,
+/// never encrypted payloads, proofs or the contents of revealed records
+pub fn payload_summary(data: &TransactionData) -> (&'static str, Vec<(&'static str, String)>) {
+    match data {
+        TransactionData::Basic => ("Transfer", vec![]),
+        TransactionData::CDRRecord(cdr) => ("CDR record", vec![
+            ("Record type", format!("{:?}", cdr.record_type)),
+            ("Home network", cdr.home_network.clone()),
+            ("Visited network", cdr.visited_network.clone()),
+            ("Encrypted payload", format!("{} bytes", cdr.encrypted_data.len())),
+            ("ZK proof", format!("{} bytes", cdr.zk_proof.len())),
+        ]),
+        TransactionData::PurgedCDRRecord(purged) => ("CDR record (payload purged)", vec![
+            ("Record type", format!("{:?}", purged.record_type)),
+            ("Home network", purged.home_network.clone()),
+            ("Visited network", purged.visited_network.clone()),
+            ("Purged", format!("{} bytes at {}", purged.purged_bytes, purged.purged_at)),
+        ]),
+        TransactionData::Settlement(settlement) => ("Settlement", vec![
+            ("Creditor", settlement.creditor_network.clone()),
+            ("Debtor", settlement.debtor_network.clone()),
+            ("Amount", format!("{} {}", settlement.amount, settlement.currency)),
+            ("Period", settlement.period.clone()),
+            ("Batches", settlement.batch_ids.len().to_string()),
+        ]),
+        TransactionData::BridgedSettlement(bridged) => {
+            let mut details = vec![
+                ("Source consortium", bridged.source_network.to_string()),
+                ("Source transaction", bridged.source_transaction().to_hex()),
+            ];
+            if let Ok(settlement) = bridged.settlement() {
+                details.push(("Creditor", settlement.creditor_network.clone()));
+                details.push(("Debtor", settlement.debtor_network.clone()));
+                details.push(("Amount", format!("{} {}", settlement.amount, settlement.currency)));
+                details.push(("Period", settlement.period.clone()));
+            }
+            ("Bridged settlement", details)
+        }
+        TransactionData::ValidatorUpdate(update) => ("Validator update", vec![
+            ("Action", format!("{:?}", update.action)),
+            ("Validator", update.validator_address.to_hex()),
+            ("Stake", update.stake.to_string()),
+        ]),
+        TransactionData::ProofAggregate(aggregate) => ("Proof aggregate", vec![
+            ("Circuit", aggregate.circuit_id.clone()),
+            ("Proofs", aggregate.len().to_string()),
+        ]),
+        TransactionData::ContractUpgrade(upgrade) => ("Contract upgrade", vec![
+            ("Contract", upgrade.contract_address.to_hex()),
+            ("Version", upgrade.version.to_string()),
+            ("Code hash", upgrade.code_hash().to_hex()),
+        ]),
+        TransactionData::RateTable(signed) => ("Rate table", vec![
+            ("Operator", signed.table.operator.clone()),
+            ("Partner", signed.table.partner.clone()),
+            ("Effective from", signed.table.effective_from.to_string()),
+            ("Rates", format!("{} ({})", signed.table.rates.len(), signed.table.currency)),
+        ]),
+        TransactionData::OperatorRegistration(registration) => ("Operator registration", vec![
+            ("Operator", registration.record.network_id().to_string()),
+            ("PLMN codes", registration.record.plmn_codes.join(", ")),
+            ("Approvals", registration.approvals.len().to_string()),
+        ]),
+        TransactionData::NetworkJoin(join) => ("Network join application", vec![
+            ("Operator", join.record.network_id().to_string()),
+            ("PLMN codes", join.record.plmn_codes.join(", ")),
+        ]),
+        TransactionData::FraudFlag(flag) => ("Fraud flag", vec![
+            ("Batch", flag.batch_id.to_hex()),
+            ("Home network", flag.home_network.clone()),
+            ("Visited network", flag.visited_network.clone()),
+            ("Score", flag.score.to_string()),
+            ("Action", if flag.quarantine { "quarantine" } else { "release" }.to_string()),
+        ]),
+        TransactionData::BatchCommitment(commitment) => ("Batch commitment", vec![
+            ("Batch", commitment.batch_id.to_hex()),
+            ("Home network", commitment.home_network.clone()),
+            ("Visited network", commitment.visited_network.clone()),
+            ("Records", commitment.record_count.to_string()),
+            ("Merkle root", commitment.merkle_root.to_hex()),
+        ]),
+        TransactionData::PeriodClose(close) => ("Settlement period close", vec![
+            ("Period", close.period.clone()),
+            ("Frozen batches", close.frozen_batches.len().to_string()),
+            ("Balances", close.balances.len().to_string()),
+        ]),
+        TransactionData::RewardPayout(payout) => ("Reward payout", vec![
+            ("Election block", payout.election_block.to_string()),
+            ("Pool", payout.pool.to_string()),
+            ("Validators paid", payout.payouts.len().to_string()),
+        ]),
+        TransactionData::Governance(governance) => ("Governance", vec![
+            ("Validator", governance.validator.to_hex()),
+            ("Action", format!("{:?}", governance.action)),
+        ]),
+        TransactionData::StateResurrection(resurrection) => ("Contract state resurrection", vec![
+            ("Contract", resurrection.slot.contract.to_hex()),
+            ("Expired at", resurrection.expired_at.to_string()),
+        ]),
+        TransactionData::CDRCommitment(commitment) => ("CDR commitment", vec![
+            ("Commitment", commitment.commitment_id().to_hex()),
+            ("Period", commitment.period.clone()),
+            ("Home network", commitment.home_network.clone()),
+            ("Visited network", commitment.visited_network.clone()),
+            ("Records", commitment.record_count.to_string()),
+            ("Total", format!("{} cents", commitment.total_charges_cents)),
+        ]),
+        TransactionData::CommitmentChallenge(challenge) => ("CDR commitment challenge", vec![
+            ("Commitment", challenge.commitment_id.to_hex()),
+            ("Record index", challenge.record_index.to_string()),
+        ]),
+        // The revealed record is on chain for the challenger, not for every explorer visitor
+        TransactionData::CommitmentResponse(response) => ("CDR commitment response", vec![
+            ("Commitment", response.commitment_id.to_hex()),
+            ("Record index", response.record_index().to_string()),
+        ]),
+        // The document itself carries bank accounts, only its hash is on chain
+        TransactionData::PaymentDocument(document) => ("Payment document", vec![
+            ("Settlement", document.settlement_tx.to_hex()),
+            ("Schema", document.schema.clone()),
+            ("Document hash", document.document_hash.to_hex()),
+        ]),
+        TransactionData::Escrow(EscrowTransaction::Lock(lock)) => ("Escrow lock", vec![
+            ("Settlement", lock.settlement_tx.to_hex()),
+            ("Creditor", lock.creditor.clone()),
+            ("Debtor", lock.debtor.clone()),
+            ("Amount", format!("{} {}", lock.amount, lock.currency)),
+        ]),
+        TransactionData::Escrow(EscrowTransaction::Confirm(confirmation)) => ("Payment confirmation", vec![
+            ("Settlement", confirmation.settlement_tx.to_hex()),
+            ("Confirmer", confirmation.confirmer.clone()),
+            ("Confirmation", format!("{:?}", confirmation.confirmation_type)),
+        ]),
+    }
+}
+
+/// Explorer page and its JSON endpoints
+pub fn routes(
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    // GET /explorer - The explorer page
+    let page = warp::path!("explorer")
+        .and(warp::get())
+        .map(|| warp::reply::html(EXPLORER_PAGE));
+
+    // GET /api/v1/explorer/blocks?before=&limit= - Latest blocks, newest first
+    let blocks = warp::path!("api" / "v1" / "explorer" / "blocks")
+        .and(warp::get())
+        .and(warp::query::<BlockListQuery>())
+        .and(with_store(pipeline.clone()))
+        .and_then(list_blocks);
+
+    // GET /api/v1/explorer/blocks/{number} - Block with its transaction summaries
+    let block = warp::path!("api" / "v1" / "explorer" / "blocks" / Height)
+        .and(warp::get())
+        .and(with_store(pipeline.clone()))
+        .and_then(get_block);
+
+    // GET /api/v1/explorer/transactions/{tx_hash} - Transaction summary and where it was included
+    let transaction = warp::path!("api" / "v1" / "explorer" / "transactions" / String)
+        .and(warp::get())
+        .and(with_store(pipeline.clone()))
+        .and_then(get_transaction);
+
+    // GET /api/v1/explorer/validators - Validator set of the current epoch
+    let validators = warp::path!("api" / "v1" / "explorer" / "validators")
+        .and(warp::get())
+        .and(with_store(pipeline.clone()))
+        .and_then(get_validators);
+
+    // GET /api/v1/explorer/periods?period= - Settlement report of the matching periods
+    let period = warp::path!("api" / "v1" / "explorer" / "periods")
+        .and(warp::get())
+        .and(warp::query::<PeriodQuery>())
+        .and(with_store(pipeline))
+        .and_then(get_period);
+
+    page.or(blocks).or(block).or(transaction).or(validators).or(period)
+}
+
+/// Height of the chain head, zero on an empty store
+async fn head_number(store: &MdbxChainStore) -> Result<Height> {
+    let head_hash = store.get_head_hash().await?;
+    Ok(store.get_block(&head_hash).await?.map_or(0, |head| head.block_number()))
+}
+
+async fn list_blocks(
+    query: BlockListQuery,
+    store: MdbxChainStore
+) -> std::result::Result<impl Reply, warp::Rejection> {
+    let head = match head_number(&store).await {
+        Ok(head) => head,
+        Err(e) => return Ok(internal_error("Head lookup", e)),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_BLOCK_LIMIT).clamp(1, MAX_BLOCK_LIMIT);
+    let top = query.before.map_or(head, |before| before.saturating_sub(1).min(head));
+
+    let mut summaries = Vec::with_capacity(limit);
+    for block_number in (0..=top).rev().take(limit) {
+        match store.get_block_at(block_number).await {
+            Ok(Some(block)) => summaries.push(BlockSummary::from(&block)),
+            Ok(None) => continue,
+            Err(e) => return Ok(internal_error("Block lookup", e)),
+        }
+    }
+    Ok(warp::reply::with_status(warp::reply::json(&summaries), warp::http::StatusCode::OK))
+}
+
+async fn get_block(
+    block_number: Height,
+    store: MdbxChainStore
+) -> std::result::Result<impl Reply, warp::Rejection> {
+    match store.get_block_at(block_number).await {
+        Ok(Some(block)) => {
+            let view = BlockView {
+                summary: BlockSummary::from(&block),
+                parent_hash: *block.parent_hash(),
+                state_root: *block.state_root(),
+                transactions: block.transactions().iter().map(TransactionSummary::from).collect(),
+            };
+            Ok(warp::reply::with_status(warp::reply::json(&view), warp::http::StatusCode::OK))
+        }
+        Ok(None) => Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &format!("No block {}", block_number))),
+        Err(e) => Ok(internal_error("Block lookup", e)),
+    }
+}
+
+async fn get_transaction(
+    tx_hash: String,
+    store: MdbxChainStore
+) -> std::result::Result<impl Reply, warp::Rejection> {
+    let tx_hash = match Blake2bHash::from_hex(&tx_hash) {
+        Some(hash) => hash,
+        None => return Ok(error_reply(warp::http::StatusCode::BAD_REQUEST, "Expected a 64 character hex transaction hash")),
+    };
+
+    let (transaction, location) = match store.get_transaction(&tx_hash).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &format!("No transaction {}", tx_hash))),
+        Err(e) => return Ok(internal_error("Transaction lookup", e)),
+    };
+    let block_number = match store.get_block(&location.block_hash).await {
+        Ok(block) => block.map_or(0, |block| block.block_number()),
+        Err(e) => return Ok(internal_error("Block lookup", e)),
+    };
+    let view = TransactionView {
+        block_number,
+        block_hash: location.block_hash,
+        index: location.index,
+        summary: TransactionSummary::from(&transaction),
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&view), warp::http::StatusCode::OK))
+}
+
+async fn get_validators(
+    store: MdbxChainStore
+) -> std::result::Result<impl Reply, warp::Rejection> {
+    let head = match head_number(&store).await {
+        Ok(head) => head,
+        Err(e) => return Ok(internal_error("Head lookup", e)),
+    };
+    // Blocks after an election are voted on by the set it elected, the election block by the one before
+    let epoch = head.saturating_sub(1) / Policy::ELECTION_BLOCK_INTERVAL;
+    let validators = match store.validator_set(epoch).await {
+        Ok(Some(validators)) => validators,
+        Ok(None) => return Ok(error_reply(warp::http::StatusCode::NOT_FOUND, &format!("No validator set stored for epoch {}", epoch))),
+        Err(e) => return Ok(internal_error("Validator set lookup", e)),
+    };
+
+    let view = ValidatorSetView {
+        epoch,
+        election_block: epoch * Policy::ELECTION_BLOCK_INTERVAL,
+        total_stake: validators.iter().map(|validator| validator.stake).sum(),
+        validators: validators.into_iter().map(|validator| ValidatorView {
+            address: validator.address,
+            reward_address: validator.reward_address,
+            stake: validator.stake,
+            jailed_from: validator.jailed_from,
+            inactive_from: validator.inactive_from,
+        }).collect(),
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&view), warp::http::StatusCode::OK))
+}
+
+async fn get_period(
+    query: PeriodQuery,
+    store: MdbxChainStore
+) -> std::result::Result<impl Reply, warp::Rejection> {
+    match SettlementReport::build(&store, &query.period).await {
+        Ok(report) => Ok(warp::reply::with_status(warp::reply::json(&report), warp::http::StatusCode::OK)),
+        Err(e) => Ok(internal_error("Settlement report", e)),
+    }
+}
+
+fn error_reply(status: warp::http::StatusCode, message: &str) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(warp::reply::json(&serde_json::json!({"error": message})), status)
+}
+
+fn internal_error(what: &str, e: crate::primitives::BlockchainError) -> warp::reply::WithStatus<warp::reply::Json> {
+    error!("❌ Explorer {} failed: {:?}", what.to_lowercase(), e);
+    error_reply(warp::http::StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+}
+
+/// Warp filter passing the pipeline's chain store, so explorer reads do not hold the pipeline
+fn with_store(
+    pipeline: Arc<Mutex<BCEPipeline>>
+) -> impl Filter<Extract = (MdbxChainStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().then(move || {
+        let pipeline = pipeline.clone();
+        async move { pipeline.lock().await.chain_store() }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::block::{CDRTransaction, CDRType};
+
+    #[test]
+    fn test_cdr_summary_hides_payload() {
+        let transaction = Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: 0,
+            fee: 1,
+            nonce: 7,
+            validity_start_height: 0,
+            data: TransactionData::CDRRecord(CDRTransaction {
+                record_type: CDRType::DataSession,
+                home_network: "T-Mobile-DE".to_string(),
+                visited_network: "Orange-FR".to_string(),
+                encrypted_data: b"262011234567890".to_vec(),
+                zk_proof: vec![0; 192],
+            }),
+            signature: vec![1],
+            signature_proof: vec![],
+        };
+
+        let summary = TransactionSummary::from(&transaction);
+        assert_eq!(summary.kind, "CDR record");
+        assert_eq!(summary.hash, transaction.hash());
+        assert!(summary.details.contains(&("Encrypted payload".to_string(), "15 bytes".to_string())));
+        assert!(summary.details.contains(&("ZK proof".to_string(), "192 bytes".to_string())));
+        let json = serde_json::to_string(&summary).unwrap();
+        assert!(!json.contains("262011234567890"));
+    }
+}
@@ -0,0 +1,358 @@
+// gRPC interface for operator BSS/OSS integration
+// Serves the `OperatorIntegration` service of proto/sp_cdr.proto over the shared BCE pipeline
+
+use crate::bce_pipeline::{BCEPipeline, BCERecord, SettlementProposal, SettlementStatus};
+use crate::network::{NetworkEvent, SPNetworkMessage};
+use crate::primitives::{Blake2bHash, BlockchainError};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use tracing::{info, warn};
+
+pub mod proto {
+    tonic::include_proto!("sp_cdr.v1");
+}
+
+use proto::operator_integration_server::{OperatorIntegration, OperatorIntegrationServer};
+
+/// Streamed records are handed to the pipeline in chunks of this many, one batch proof each
+const INGEST_CHUNK_SIZE: usize = 100;
+
+/// gRPC server for operator billing and operations systems
+pub struct GrpcAPI {
+    pipeline: Arc<Mutex<BCEPipeline>>,
+    port: u16,
+}
+
+impl GrpcAPI {
+    pub fn new(pipeline: Arc<Mutex<BCEPipeline>>, port: u16) -> Self {
+        Self { pipeline, port }
+    }
+
+    /// Start the gRPC server
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("🌐 Starting operator gRPC API on port {}", self.port);
+        let service = OperatorIntegrationService { pipeline: self.pipeline.clone() };
+
+        Server::builder()
+            .add_service(OperatorIntegrationServer::new(service))
+            .serve(([0, 0, 0, 0], self.port).into())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// `OperatorIntegration` implementation over the pipeline
+pub struct OperatorIntegrationService {
+    pipeline: Arc<Mutex<BCEPipeline>>,
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl OperatorIntegration for OperatorIntegrationService {
+    async fn stream_bce_records(
+        &self,
+        request: Request<Streaming<proto::BceRecord>>,
+    ) -> Result<Response<proto::IngestSummary>, Status> {
+        let mut stream = request.into_inner();
+        let mut summary = proto::IngestSummary::default();
+        let mut chunk = Vec::with_capacity(INGEST_CHUNK_SIZE);
+
+        while let Some(record) = stream.message().await? {
+            summary.received += 1;
+            chunk.push(BCERecord::from(record));
+            if chunk.len() == INGEST_CHUNK_SIZE {
+                self.ingest(std::mem::take(&mut chunk), &mut summary).await?;
+            }
+        }
+        if !chunk.is_empty() {
+            self.ingest(chunk, &mut summary).await?;
+        }
+
+        info!("✅ gRPC BCE stream closed: {} processed, {} failed", summary.processed, summary.failed);
+        Ok(Response::new(summary))
+    }
+
+    async fn get_settlement_status(
+        &self,
+        request: Request<proto::SettlementStatusRequest>,
+    ) -> Result<Response<proto::SettlementStatusReply>, Status> {
+        let settlement_id = parse_hash(&request.get_ref().settlement_id)?;
+        let pipeline = self.pipeline.lock().await;
+        let proposal = pipeline.settlement_proposal(&settlement_id)
+            .ok_or_else(|| Status::not_found(format!("No settlement {}", settlement_id)))?;
+
+        Ok(Response::new(settlement_status(proposal)))
+    }
+
+    async fn list_pending_disputes(
+        &self,
+        _request: Request<proto::ListPendingDisputesRequest>,
+    ) -> Result<Response<proto::ListPendingDisputesReply>, Status> {
+        let pipeline = self.pipeline.lock().await;
+
+        let quarantined = pipeline.quarantined_batches().map(|batch| proto::PendingDispute {
+            id: batch.batch_id.to_hex(),
+            kind: proto::DisputeKind::QuarantinedBatch as i32,
+            home_network: batch.home_network.to_string(),
+            visited_network: batch.visited_network.to_string(),
+            amount_cents: batch.total_charges_cents,
+            reason: "quarantined by fraud detection, pending review".to_string(),
+        });
+        let rejected = pipeline.settlement_proposals().filter_map(|proposal| match &proposal.status {
+            SettlementStatus::Rejected(reason) => Some(proto::PendingDispute {
+                id: proposal.proposal_id.to_hex(),
+                kind: proto::DisputeKind::RejectedSettlement as i32,
+                home_network: proposal.creditor.to_string(),
+                visited_network: proposal.debtor.to_string(),
+                amount_cents: proposal.amount_cents,
+                reason: reason.clone(),
+            }),
+            _ => None,
+        });
+
+        Ok(Response::new(proto::ListPendingDisputesReply {
+            disputes: quarantined.chain(rejected).collect(),
+        }))
+    }
+
+    type SubscribeEventsStream = EventStream;
+
+    async fn subscribe_events(
+        &self,
+        _request: Request<proto::SubscribeEventsRequest>,
+    ) -> Result<Response<Self::SubscribeEventsStream>, Status> {
+        let receiver = self.pipeline.lock().await.subscribe_network_events();
+
+        // Subscribers that fall behind skip the events they missed rather than being cut off
+        let events = BroadcastStream::new(receiver).filter_map(|event| match event {
+            Ok(event) => to_event(event).map(Ok),
+            Err(e) => {
+                warn!("gRPC event subscriber lagging: {}", e);
+                None
+            }
+        });
+
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+impl OperatorIntegrationService {
+    /// Hand records to the pipeline, failing the stream once the node is saturated
+    async fn ingest(&self, records: Vec<BCERecord>, summary: &mut proto::IngestSummary) -> Result<(), Status> {
+        let total = records.len() as u64;
+        let mut pipeline = self.pipeline.lock().await;
+        match pipeline.process_bce_records_batch(records).await {
+            Ok(processed) => {
+                summary.processed += processed as u64;
+                summary.failed += total - processed as u64;
+            }
+            Err(BlockchainError::Saturated(message)) => {
+                return Err(Status::resource_exhausted(format!(
+                    "{} ({} records processed before)", message, summary.processed
+                )));
+            }
+            Err(e) => {
+                warn!("Failed to process streamed BCE records: {:?}", e);
+                summary.failed += total;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<proto::BceRecord> for BCERecord {
+    fn from(record: proto::BceRecord) -> Self {
+        Self {
+            record_id: record.record_id,
+            record_type: record.record_type,
+            imsi: record.imsi,
+            home_plmn: record.home_plmn,
+            visited_plmn: record.visited_plmn,
+            session_duration: record.session_duration,
+            bytes_uplink: record.bytes_uplink,
+            bytes_downlink: record.bytes_downlink,
+            wholesale_charge: record.wholesale_charge,
+            retail_charge: record.retail_charge,
+            currency: record.currency,
+            timestamp: record.timestamp,
+            charging_id: record.charging_id,
+        }
+    }
+}
+
+fn parse_hash(hex: &str) -> Result<Blake2bHash, Status> {
+    Blake2bHash::from_hex(hex)
+        .ok_or_else(|| Status::invalid_argument("Expected a 64 character hex id"))
+}
+
+fn settlement_status(proposal: &SettlementProposal) -> proto::SettlementStatusReply {
+    let (status, rejection_reason) = match &proposal.status {
+        SettlementStatus::Proposed => (proto::SettlementStatus::Proposed, String::new()),
+        SettlementStatus::Accepted => (proto::SettlementStatus::Accepted, String::new()),
+        SettlementStatus::Rejected(reason) => (proto::SettlementStatus::Rejected, reason.clone()),
+        SettlementStatus::Finalized => (proto::SettlementStatus::Finalized, String::new()),
+    };
+    proto::SettlementStatusReply {
+        settlement_id: proposal.proposal_id.to_hex(),
+        creditor: proposal.creditor.to_string(),
+        debtor: proposal.debtor.to_string(),
+        amount_cents: proposal.amount_cents,
+        period: proposal.period.clone(),
+        status: status as i32,
+        rejection_reason,
+    }
+}
+
+/// Event pushed to subscribers for a network event, `None` for events BSS/OSS systems do not follow
+fn to_event(event: NetworkEvent) -> Option<proto::Event> {
+    use proto::event::Event;
+
+    let message = match event {
+        NetworkEvent::MessageReceived { message, .. } | NetworkEvent::GossipReceived { message, .. } => message,
+        _ => return None,
+    };
+    let event = match message {
+        SPNetworkMessage::SettlementProposal { creditor, debtor, amount_cents, period_hash, .. } => {
+            Event::SettlementProposed(proto::SettlementProposed {
+                creditor: creditor.to_string(),
+                debtor: debtor.to_string(),
+                amount_cents,
+                period_hash: period_hash.to_hex(),
+            })
+        }
+        SPNetworkMessage::SettlementAccept { proposal_hash, .. } => {
+            Event::SettlementAccepted(proto::SettlementAccepted { settlement_id: proposal_hash.to_hex() })
+        }
+        SPNetworkMessage::SettlementReject { proposal_hash, reason } => {
+            Event::SettlementRejected(proto::SettlementRejected { settlement_id: proposal_hash.to_hex(), reason })
+        }
+        SPNetworkMessage::CDRBatchReady { batch_id, network_pair, record_count, total_amount, merkle_root } => {
+            Event::BatchReady(proto::BatchReady {
+                batch_id: batch_id.to_hex(),
+                home_network: network_pair.0.to_string(),
+                visited_network: network_pair.1.to_string(),
+                record_count,
+                total_amount,
+                merkle_root: merkle_root.to_hex(),
+            })
+        }
+        SPNetworkMessage::FraudAlert { batch_id, record_id, network_pair, score, reasons } => {
+            Event::FraudAlert(proto::FraudAlert {
+                batch_id: batch_id.to_hex(),
+                record_id,
+                home_network: network_pair.0.to_string(),
+                visited_network: network_pair.1.to_string(),
+                score,
+                reasons,
+            })
+        }
+        _ => return None,
+    };
+    Some(proto::Event { event: Some(event) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::NetworkId;
+    use libp2p::PeerId;
+
+    #[test]
+    fn test_bce_record_from_proto() {
+        let record = BCERecord::from(proto::BceRecord {
+            record_id: "rec-1".to_string(),
+            record_type: "DATA_SESSION_CDR".to_string(),
+            imsi: "262011234567890".to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: "23415".to_string(),
+            session_duration: 60,
+            bytes_uplink: 1_000,
+            bytes_downlink: 2_000,
+            wholesale_charge: 150,
+            retail_charge: 300,
+            currency: "EUR".to_string(),
+            timestamp: 1_700_000_000,
+            charging_id: 7,
+        });
+        assert_eq!(record.record_id, "rec-1");
+        assert_eq!((record.home_plmn.as_str(), record.visited_plmn.as_str()), ("26201", "23415"));
+        assert_eq!((record.bytes_uplink, record.bytes_downlink), (1_000, 2_000));
+        assert_eq!((record.wholesale_charge, record.retail_charge), (150, 300));
+        assert_eq!(record.charging_id, 7);
+    }
+
+    #[test]
+    fn test_settlement_status_reply() {
+        let hash = Blake2bHash::from_data(b"settlement");
+        assert_eq!(parse_hash(&hash.to_hex()).unwrap(), hash);
+        assert_eq!(parse_hash("not-a-hash").unwrap_err().code(), tonic::Code::InvalidArgument);
+
+        let mut proposal = SettlementProposal {
+            proposal_id: hash,
+            creditor: NetworkId::new("T-Mobile", "DE"),
+            debtor: NetworkId::new("Vodafone", "UK"),
+            amount_cents: 12_345,
+            service_breakdown: Default::default(),
+            period: "2024-01-01/2024-01-16".to_string(),
+            period_hash: Blake2bHash::from_data(b"period"),
+            cdr_batch_proofs: vec![],
+            batch_ids: vec![],
+            proposed_at: 0,
+            status: SettlementStatus::Accepted,
+            settlement_tx: None,
+        };
+        let reply = settlement_status(&proposal);
+        assert_eq!(reply.settlement_id, hash.to_hex());
+        assert_eq!((reply.creditor.as_str(), reply.debtor.as_str()), ("T-Mobile:DE", "Vodafone:UK"));
+        assert_eq!(reply.status, proto::SettlementStatus::Accepted as i32);
+        assert!(reply.rejection_reason.is_empty());
+
+        proposal.status = SettlementStatus::Rejected("amount mismatch".to_string());
+        let reply = settlement_status(&proposal);
+        assert_eq!(reply.status, proto::SettlementStatus::Rejected as i32);
+        assert_eq!(reply.rejection_reason, "amount mismatch");
+    }
+
+    #[test]
+    fn test_network_events_forwarded_to_subscribers() {
+        use proto::event::Event;
+
+        let batch_id = Blake2bHash::from_data(b"batch");
+        let gossip = NetworkEvent::GossipReceived {
+            topic: "cdr-batches".to_string(),
+            message: SPNetworkMessage::CDRBatchReady {
+                batch_id,
+                network_pair: (NetworkId::new("T-Mobile", "DE"), NetworkId::new("Vodafone", "UK")),
+                record_count: 10,
+                total_amount: 5_000,
+                merkle_root: Blake2bHash::from_data(b"root"),
+            },
+            source: PeerId::random(),
+        };
+        match to_event(gossip).and_then(|event| event.event) {
+            Some(Event::BatchReady(ready)) => {
+                assert_eq!(ready.batch_id, batch_id.to_hex());
+                assert_eq!(ready.home_network, "T-Mobile:DE");
+                assert_eq!((ready.record_count, ready.total_amount), (10, 5_000));
+            }
+            other => panic!("expected a batch ready event, got {:?}", other),
+        }
+
+        let rejected = NetworkEvent::MessageReceived {
+            peer: PeerId::random(),
+            message: SPNetworkMessage::SettlementReject { proposal_hash: batch_id, reason: "disputed".to_string() },
+        };
+        assert!(matches!(
+            to_event(rejected).and_then(|event| event.event),
+            Some(Event::SettlementRejected(proto::SettlementRejected { reason, .. })) if reason == "disputed"
+        ));
+
+        // Peer events are not followed by BSS/OSS systems
+        assert!(to_event(NetworkEvent::PeerConnected(PeerId::random())).is_none());
+    }
+}
@@ -1,6 +1,12 @@
 // BCE Record Ingestion API
 // RESTful endpoints for receiving BCE records from operator billing systems
 
+pub mod auth;
 pub mod bce_ingestion;
+#[cfg(feature = "explorer")]
+pub mod explorer;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 
+pub use auth::{ApiTokens, FinanceRole};
 pub use bce_ingestion::*;
\ No newline at end of file
@@ -0,0 +1,145 @@
+// Aggregation of proven BCE records into on-chain commitments: records are accumulated per
+// network pair and settlement period and posted periodically as one Merkle root with their count,
+// total and proofs instead of one transaction each. Committed records are kept here, so any one of
+// them can be revealed on chain when a counterparty challenges it
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::blockchain::{CDRCommitmentTransaction, CommitmentResponseTransaction, MerkleProof};
+use crate::primitives::{Blake2bHash, BlockchainError, NetworkId, Result};
+use super::commitment::{batch_root, record_leaf};
+use super::BCERecord;
+
+/// How often accumulated records are committed and open challenges answered
+pub const COMMITMENT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Records of one network pair and period not committed yet
+#[derive(Debug, Default)]
+struct Accumulation {
+    records: Vec<BCERecord>,
+    total_charges_cents: u64,
+    zk_proofs: Vec<Vec<u8>>,
+}
+
+/// Records waiting for their commitment and those committed from this node
+#[derive(Debug, Default)]
+pub struct CommitmentAccumulator {
+    pending: HashMap<(String, NetworkId, NetworkId), Accumulation>,
+    /// Committed records by commitment id, in committed order
+    committed: HashMap<Blake2bHash, Vec<BCERecord>>,
+}
+
+impl CommitmentAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulate a record proven by `zk_proof` for the next commitment of its pair and period
+    pub fn add(&mut self, period: &str, home_network: &NetworkId, visited_network: &NetworkId, record: &BCERecord, zk_proof: &[u8]) {
+        let accumulation = self.pending.entry((period.to_string(), home_network.clone(), visited_network.clone())).or_default();
+        accumulation.records.push(record.clone());
+        accumulation.total_charges_cents += record.wholesale_charge;
+        // Records proven together share their batch proof, it is committed once
+        if accumulation.zk_proofs.last().map(Vec::as_slice) != Some(zk_proof) {
+            accumulation.zk_proofs.push(zk_proof.to_vec());
+        }
+    }
+
+    /// Records accumulated since the last commitment
+    pub fn pending_records(&self) -> usize {
+        self.pending.values().map(|accumulation| accumulation.records.len()).sum()
+    }
+
+    /// Commitments to every accumulated pair and period, by period then pair
+    pub fn flush(&mut self) -> Vec<CDRCommitmentTransaction> {
+        let mut pending: Vec<_> = self.pending.drain().collect();
+        pending.sort_by_key(|((period, home, visited), _)| (period.clone(), home.to_string(), visited.to_string()));
+
+        let mut commitments = Vec::with_capacity(pending.len());
+        for ((period, home_network, visited_network), accumulation) in pending {
+            let commitment = CDRCommitmentTransaction {
+                period,
+                home_network: home_network.to_string(),
+                visited_network: visited_network.to_string(),
+                record_count: accumulation.records.len() as u32,
+                total_charges_cents: accumulation.total_charges_cents,
+                merkle_root: batch_root(&accumulation.records),
+                zk_proofs: accumulation.zk_proofs,
+            };
+            self.committed.insert(commitment.commitment_id(), accumulation.records);
+            commitments.push(commitment);
+        }
+        commitments
+    }
+
+    /// Whether the commitment was made from this node
+    pub fn is_committed_here(&self, commitment_id: &Blake2bHash) -> bool {
+        self.committed.contains_key(commitment_id)
+    }
+
+    /// Reveal the committed record at `record_index` with its inclusion proof
+    pub fn respond(&self, commitment_id: &Blake2bHash, record_index: u32) -> Result<CommitmentResponseTransaction> {
+        let records = self.committed.get(commitment_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Commitment {} was not made here", commitment_id)))?;
+        let leaves: Vec<Blake2bHash> = records.iter().map(record_leaf).collect();
+        let proof = MerkleProof::new(&leaves, record_index as usize)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Commitment {} has no record {}", commitment_id, record_index)))?;
+        Ok(CommitmentResponseTransaction {
+            commitment_id: *commitment_id,
+            record: records[record_index as usize].clone(),
+            proof,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(record_id: &str, wholesale_charge: u64) -> BCERecord {
+        BCERecord {
+            record_id: record_id.to_string(),
+            record_type: "SMS_CDR".to_string(),
+            imsi: "262011234567890".to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: "20801".to_string(),
+            session_duration: 0,
+            bytes_uplink: 0,
+            bytes_downlink: 0,
+            wholesale_charge,
+            retail_charge: wholesale_charge * 2,
+            currency: "EUR".to_string(),
+            timestamp: 1_700_000_000,
+            charging_id: 1,
+        }
+    }
+
+    #[test]
+    fn test_records_are_committed_per_pair_and_period() {
+        let (home, visited) = (NetworkId::new("T-Mobile-DE", "Germany"), NetworkId::new("Orange-FR", "France"));
+        let mut accumulator = CommitmentAccumulator::new();
+        // Three records share one batch proof, the fourth is proven alone and the fifth is another pair's
+        for i in 0..3 {
+            accumulator.add("2024-01", &home, &visited, &record(&format!("CDR-{}", i), 10), b"batch-proof");
+        }
+        accumulator.add("2024-01", &home, &visited, &record("CDR-3", 25), b"single-proof");
+        accumulator.add("2024-01", &visited, &home, &record("CDR-4", 40), b"other-proof");
+        assert_eq!(accumulator.pending_records(), 5);
+
+        let commitments = accumulator.flush();
+        assert_eq!(commitments.len(), 2);
+        assert_eq!(accumulator.pending_records(), 0);
+        let commitment = commitments.iter().find(|commitment| commitment.home_network == home.to_string()).unwrap();
+        assert_eq!((commitment.record_count, commitment.total_charges_cents), (4, 55));
+        assert_eq!(commitment.zk_proofs, vec![b"batch-proof".to_vec(), b"single-proof".to_vec()]);
+
+        // Any committed record can be revealed against the root, records beyond the commitment cannot
+        let commitment_id = commitment.commitment_id();
+        assert!(accumulator.is_committed_here(&commitment_id));
+        let response = accumulator.respond(&commitment_id, 3).unwrap();
+        assert_eq!(response.record.record_id, "CDR-3");
+        assert!(response.verify(&commitment.merkle_root).is_ok());
+        assert!(accumulator.respond(&commitment_id, 4).is_err());
+        assert!(accumulator.respond(&Blake2bHash::from_data(b"elsewhere"), 0).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+// Netting what-if analytics: the pending bilateral balances of the open settlement period are netted
+// under each netting scheme, so operators see what multilateral netting would save them before
+// agreeing to it
+use serde::{Deserialize, Serialize};
+
+use crate::network::multilateral_netting::{MultilateralNettingSolver, NettingConfig};
+use crate::primitives::{NetworkId, Result};
+use super::BCEBatch;
+
+/// Netting scheme a projection is computed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NettingScenario {
+    /// Only opposite obligations of each operator pair offset
+    Bilateral,
+    /// Obligation cycles of up to three operators cancel
+    Triangular,
+    /// Obligation cycles of any length cancel
+    Multilateral,
+}
+
+impl NettingScenario {
+    pub const ALL: [NettingScenario; 3] = [Self::Bilateral, Self::Triangular, Self::Multilateral];
+
+    pub fn max_cycle_length(&self) -> usize {
+        match self {
+            Self::Bilateral => 2,
+            Self::Triangular => 3,
+            Self::Multilateral => usize::MAX,
+        }
+    }
+}
+
+impl std::fmt::Display for NettingScenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Bilateral => write!(f, "bilateral"),
+            Self::Triangular => write!(f, "triangular"),
+            Self::Multilateral => write!(f, "multilateral"),
+        }
+    }
+}
+
+/// What settling the balances under one scenario would take
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioProjection {
+    pub scenario: NettingScenario,
+    /// Payments left to make
+    pub settlements: usize,
+    pub settled_cents: u64,
+    /// Gross obligations netting offsets
+    pub eliminated_cents: u64,
+    pub savings_percentage: u32,
+    pub cycles_cancelled: usize,
+}
+
+/// Projected netting savings over pending bilateral balances
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NettingProjection {
+    /// Span of the balances, Unix seconds
+    pub period_start: u64,
+    pub period_end: u64,
+    pub operators: usize,
+    /// Operator pairs with a balance, counted per direction
+    pub obligations: usize,
+    pub gross_cents: u64,
+    pub scenarios: Vec<ScenarioProjection>,
+}
+
+impl NettingProjection {
+    /// Project netting of `balances`, each an amount the first network owes the second
+    pub fn project(balances: &[(NetworkId, NetworkId, u64)], period_start: u64, period_end: u64) -> Result<Self> {
+        let mut projection = Self { period_start, period_end, operators: 0, obligations: 0, gross_cents: 0, scenarios: Vec::new() };
+        for scenario in NettingScenario::ALL {
+            let solver = MultilateralNettingSolver::new(NettingConfig {
+                max_cycle_length: scenario.max_cycle_length(),
+                ..Default::default()
+            });
+            let result = solver.solve(balances)?;
+            projection.operators = result.participants.len();
+            projection.obligations = result.obligation_matrix(balances).iter().flatten().filter(|amount| **amount > 0).count();
+            projection.gross_cents = result.gross_total;
+            projection.scenarios.push(ScenarioProjection {
+                scenario,
+                settlements: result.residual_obligations.len(),
+                settled_cents: result.residual_total,
+                eliminated_cents: result.gross_total - result.residual_total,
+                savings_percentage: result.savings_percentage(),
+                cycles_cancelled: result.cancelled_cycles.len(),
+            });
+        }
+        Ok(projection)
+    }
+
+    /// Project netting of the charges of `batches`, the visited network of each billing its home network
+    pub fn of_batches<'a>(batches: impl IntoIterator<Item = &'a BCEBatch>) -> Result<Self> {
+        let mut balances = Vec::new();
+        let (mut period_start, mut period_end) = (u64::MAX, 0);
+        for batch in batches {
+            balances.push((batch.visited_network.clone(), batch.home_network.clone(), batch.total_charges_cents));
+            period_start = period_start.min(batch.period_start);
+            period_end = period_end.max(batch.period_end);
+        }
+        Self::project(&balances, period_start.min(period_end), period_end)
+    }
+
+    pub fn scenario(&self, scenario: NettingScenario) -> Option<&ScenarioProjection> {
+        self.scenarios.iter().find(|projection| projection.scenario == scenario)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Blake2bHash;
+
+    fn batch(home: &str, visited: &str, total_charges_cents: u64) -> BCEBatch {
+        BCEBatch {
+            batch_id: Blake2bHash::from_data(format!("{}-{}", home, visited).as_bytes()),
+            home_network: NetworkId::new(home, "EU"),
+            visited_network: NetworkId::new(visited, "EU"),
+            records: vec![],
+            period_start: 1_704_067_200,
+            period_end: 1_705_363_200,
+            total_charges_cents,
+            service_breakdown: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_projection_per_scenario() {
+        // A pair billing each other, a triangle and a four-operator ring
+        let batches = vec![
+            batch("A", "B", 10_000),
+            batch("B", "A", 4_000),
+            batch("B", "C", 3_000),
+            batch("C", "A", 3_000),
+            batch("C", "D", 2_000),
+            batch("D", "E", 2_000),
+            batch("E", "F", 2_000),
+            batch("F", "C", 2_000),
+        ];
+        let projection = NettingProjection::of_batches(&batches).unwrap();
+        assert_eq!((projection.operators, projection.obligations, projection.gross_cents), (6, 8, 28_000));
+        assert_eq!((projection.period_start, projection.period_end), (1_704_067_200, 1_705_363_200));
+
+        let bilateral = projection.scenario(NettingScenario::Bilateral).unwrap();
+        assert_eq!((bilateral.eliminated_cents, bilateral.settlements), (8_000, 7));
+        let triangular = projection.scenario(NettingScenario::Triangular).unwrap();
+        assert_eq!(triangular.eliminated_cents, 8_000 + 9_000);
+        let multilateral = projection.scenario(NettingScenario::Multilateral).unwrap();
+        assert_eq!((multilateral.eliminated_cents, multilateral.settled_cents), (25_000, 3_000));
+        assert_eq!(multilateral.savings_percentage, 89);
+
+        let empty = NettingProjection::of_batches(std::iter::empty::<&BCEBatch>()).unwrap();
+        assert_eq!(empty.gross_cents, 0);
+        assert!(empty.scenarios.iter().all(|scenario| scenario.settlements == 0));
+    }
+}
@@ -0,0 +1,146 @@
+// Human approval of settlements above the auto-accept threshold: proposals wait in a queue for
+// a finance operator to approve or reject them, and are rejected automatically once their due
+// date passes without a decision. The queue is persisted with the rest of the pipeline state
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::primitives::{Blake2bHash, NetworkId};
+
+/// Time finance operators have to decide on a settlement before it is rejected
+pub const DEFAULT_APPROVAL_WINDOW: Duration = Duration::from_secs(72 * 3_600);
+
+/// Settlement proposal waiting for a human decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub proposal_id: Blake2bHash,
+    pub creditor: NetworkId,
+    pub debtor: NetworkId,
+    pub amount_cents: u64,
+    pub period_hash: Blake2bHash,
+    pub requested_at: u64,
+    /// Rejected automatically when no decision is made by then
+    pub due_at: u64,
+}
+
+/// What a finance operator decided on a pending settlement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approve,
+    Reject { reason: String },
+}
+
+/// Change to the approval queue finance operators are notified of
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ApprovalEvent {
+    Requested(PendingApproval),
+    Decided { approval: PendingApproval, decision: ApprovalDecision, reviewer: String },
+    /// The due date passed without a decision, the settlement was rejected
+    Expired(PendingApproval),
+}
+
+/// Settlements waiting for approval, by proposal id
+#[derive(Debug, Clone)]
+pub struct ApprovalQueue {
+    window: Duration,
+    pending: HashMap<Blake2bHash, PendingApproval>,
+}
+
+impl ApprovalQueue {
+    pub fn new(window: Duration, pending: Vec<PendingApproval>) -> Self {
+        Self {
+            window,
+            pending: pending.into_iter().map(|approval| (approval.proposal_id, approval)).collect(),
+        }
+    }
+
+    /// Queue a proposal received at `now`, `None` if it already waits
+    pub fn request(
+        &mut self,
+        proposal_id: Blake2bHash,
+        creditor: NetworkId,
+        debtor: NetworkId,
+        amount_cents: u64,
+        period_hash: Blake2bHash,
+        now: u64,
+    ) -> Option<PendingApproval> {
+        if self.pending.contains_key(&proposal_id) {
+            return None;
+        }
+        let approval = PendingApproval {
+            proposal_id,
+            creditor,
+            debtor,
+            amount_cents,
+            period_hash,
+            requested_at: now,
+            due_at: now + self.window.as_secs(),
+        };
+        self.pending.insert(proposal_id, approval.clone());
+        Some(approval)
+    }
+
+    /// Pending settlements, the first due first
+    pub fn pending(&self) -> Vec<&PendingApproval> {
+        let mut pending: Vec<_> = self.pending.values().collect();
+        pending.sort_by_key(|approval| (approval.due_at, approval.proposal_id.0));
+        pending
+    }
+
+    pub fn get(&self, proposal_id: &Blake2bHash) -> Option<&PendingApproval> {
+        self.pending.get(proposal_id)
+    }
+
+    /// Take a settlement out of the queue to decide on it
+    pub fn take(&mut self, proposal_id: &Blake2bHash) -> Option<PendingApproval> {
+        self.pending.remove(proposal_id)
+    }
+
+    /// Take out the settlements due before `now`
+    pub fn take_expired(&mut self, now: u64) -> Vec<PendingApproval> {
+        let expired: Vec<Blake2bHash> = self.pending.values()
+            .filter(|approval| approval.due_at <= now)
+            .map(|approval| approval.proposal_id)
+            .collect();
+        let mut expired: Vec<_> = expired.iter().filter_map(|proposal_id| self.pending.remove(proposal_id)).collect();
+        expired.sort_by_key(|approval| approval.due_at);
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approvals_expire_at_their_due_date() {
+        let mut queue = ApprovalQueue::new(Duration::from_secs(100), vec![]);
+        let creditor = NetworkId::new("Vodafone", "UK");
+        let debtor = NetworkId::new("Orange", "FR");
+        let first = Blake2bHash::from_data(b"first");
+        let second = Blake2bHash::from_data(b"second");
+
+        let approval = queue.request(first, creditor.clone(), debtor.clone(), 250_000, Blake2bHash::default(), 1_000).unwrap();
+        assert_eq!(approval.due_at, 1_100);
+        // Redelivered proposals are queued once
+        assert!(queue.request(first, creditor.clone(), debtor.clone(), 250_000, Blake2bHash::default(), 1_010).is_none());
+        queue.request(second, creditor, debtor, 400_000, Blake2bHash::default(), 1_050);
+        assert_eq!(queue.pending().iter().map(|approval| approval.proposal_id).collect::<Vec<_>>(), vec![first, second]);
+
+        assert!(queue.take_expired(1_099).is_empty());
+        assert_eq!(queue.take_expired(1_100), vec![approval]);
+        assert!(queue.take(&first).is_none());
+        assert_eq!(queue.take(&second).map(|approval| approval.amount_cents), Some(400_000));
+        assert!(queue.is_empty());
+    }
+}
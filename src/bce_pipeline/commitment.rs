@@ -0,0 +1,101 @@
+// Merkle commitments to BCE batches: each batch is committed on chain as the root of a
+// Merkle tree over its canonicalized records, so a disputed record can later be disclosed
+// to an arbitrator with an inclusion proof without revealing the rest of the batch
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::light_client::{merkle_root, MerkleProof};
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::{BCEBatch, BCERecord};
+
+/// Leaf hash of a record, over its canonical encoding
+/// Bincode writes the fields in declaration order with fixed-width integers, so equal records
+/// always hash equally whatever JSON they were submitted as
+pub fn record_leaf(record: &BCERecord) -> Blake2bHash {
+    let mut data = b"sp-cdr-bce-record".to_vec();
+    data.extend_from_slice(&bincode::serialize(record).expect("BCE records are serializable"));
+    Blake2bHash::from_data(&data)
+}
+
+/// Merkle root committing to the records of a batch, in batch order
+pub fn batch_root(records: &[BCERecord]) -> Blake2bHash {
+    merkle_root(&records.iter().map(record_leaf).collect::<Vec<_>>())
+}
+
+/// One record of a committed batch with the proof it belongs there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordDisclosure {
+    pub batch_id: Blake2bHash,
+    pub record: BCERecord,
+    pub proof: MerkleProof,
+}
+
+impl RecordDisclosure {
+    /// Disclose the record `record_id` of `batch`
+    pub fn new(batch: &BCEBatch, record_id: &str) -> Result<Self> {
+        let index = batch.records.iter().position(|record| record.record_id == record_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Record {} is not in batch {}", record_id, batch.batch_id)))?;
+        let leaves: Vec<Blake2bHash> = batch.records.iter().map(record_leaf).collect();
+        let proof = MerkleProof::new(&leaves, index).expect("index is within the batch");
+        Ok(Self { batch_id: batch.batch_id, record: batch.records[index].clone(), proof })
+    }
+
+    /// Check the record is in the batch committed to by `root`
+    pub fn verify(&self, root: &Blake2bHash) -> Result<()> {
+        match self.proof.root(&record_leaf(&self.record)) {
+            Some(proven) if proven == *root => Ok(()),
+            _ => Err(BlockchainError::InvalidTransaction(format!(
+                "Record {} is not committed to in batch {}", self.record.record_id, self.batch_id
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::NetworkId;
+
+    fn record(record_id: &str, wholesale_charge: u64) -> BCERecord {
+        BCERecord {
+            record_id: record_id.to_string(),
+            record_type: "DATA_SESSION_CDR".to_string(),
+            imsi: "262011234567890".to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: "20801".to_string(),
+            session_duration: 600,
+            bytes_uplink: 1_048_576,
+            bytes_downlink: 4_194_304,
+            wholesale_charge,
+            retail_charge: wholesale_charge * 2,
+            currency: "EUR".to_string(),
+            timestamp: 1_700_000_000,
+            charging_id: 1,
+        }
+    }
+
+    #[test]
+    fn test_selective_disclosure() {
+        let records: Vec<BCERecord> = (0..5).map(|i| record(&format!("CDR-{}", i), 100 + i)).collect();
+        let batch = BCEBatch {
+            batch_id: Blake2bHash::from_data(b"batch"),
+            home_network: NetworkId::new("T-Mobile-DE", "Germany"),
+            visited_network: NetworkId::new("Orange-FR", "France"),
+            total_charges_cents: records.iter().map(|record| record.wholesale_charge).sum(),
+            records,
+            period_start: 1_700_000_000,
+            period_end: 1_700_000_000,
+            service_breakdown: Default::default(),
+        };
+        let root = batch_root(&batch.records);
+
+        let disclosure = RecordDisclosure::new(&batch, "CDR-3").unwrap();
+        assert!(disclosure.verify(&root).is_ok());
+        assert!(RecordDisclosure::new(&batch, "CDR-9").is_err());
+
+        // A record altered after the commitment no longer verifies
+        let mut altered = disclosure.clone();
+        altered.record.wholesale_charge += 1;
+        assert!(altered.verify(&root).is_err());
+        assert!(disclosure.verify(&batch_root(&batch.records[..4])).is_err());
+    }
+}
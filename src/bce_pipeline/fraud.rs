@@ -0,0 +1,223 @@
+// Fraud detection for roaming traffic: BCE records are scored for anomalies
+// before they are settled, suspicious ones put their batch into quarantine
+use std::collections::HashMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use super::BCERecord;
+
+/// Anomaly a record was flagged for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FraudIndicator {
+    /// Session longer than any real session lasts
+    ImpossibleDuration { seconds: u64 },
+    /// Charge far above the running average of its route and record type
+    ChargeSpike { charge_cents: u64, baseline_cents: u64 },
+    /// IMSI seen in another country too recently to have travelled there
+    SimultaneousCountries { imsi: String, other_plmn: String, seconds_apart: u64 },
+}
+
+impl FraudIndicator {
+    /// Points the indicator adds to a record's fraud score
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::ImpossibleDuration { .. } => 60,
+            Self::ChargeSpike { .. } => 40,
+            Self::SimultaneousCountries { .. } => 80,
+        }
+    }
+}
+
+impl fmt::Display for FraudIndicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImpossibleDuration { seconds } => write!(f, "session of {} s", seconds),
+            Self::ChargeSpike { charge_cents, baseline_cents } =>
+                write!(f, "charge of {} cents against a baseline of {} cents", charge_cents, baseline_cents),
+            Self::SimultaneousCountries { imsi, other_plmn, seconds_apart } =>
+                write!(f, "IMSI {} seen in PLMN {} {} s apart", imsi, other_plmn, seconds_apart),
+        }
+    }
+}
+
+/// Fraud detection thresholds
+#[derive(Debug, Clone)]
+pub struct FraudConfig {
+    /// Sessions longer than this are impossible
+    pub max_session_seconds: u64,
+    /// A charge this many times the baseline is a spike
+    pub spike_factor: u64,
+    /// Records a baseline needs before spikes are scored against it
+    pub baseline_min_samples: u64,
+    /// Sightings of one IMSI in two countries closer than this are impossible travel
+    pub min_travel_seconds: u64,
+    /// Score from which a record quarantines its batch
+    pub quarantine_score: u32,
+}
+
+impl Default for FraudConfig {
+    fn default() -> Self {
+        Self {
+            max_session_seconds: 86_400,
+            spike_factor: 10,
+            baseline_min_samples: 20,
+            min_travel_seconds: 3_600,
+            quarantine_score: 50,
+        }
+    }
+}
+
+/// Fraud score of one record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FraudScore {
+    pub record_id: String,
+    pub score: u32,
+    pub indicators: Vec<FraudIndicator>,
+}
+
+impl FraudScore {
+    pub fn reasons(&self) -> Vec<String> {
+        self.indicators.iter().map(ToString::to_string).collect()
+    }
+}
+
+/// Running average wholesale charge of a route and record type
+#[derive(Debug, Clone, Copy, Default)]
+struct ChargeBaseline {
+    samples: u64,
+    total_cents: u64,
+}
+
+impl ChargeBaseline {
+    fn mean(&self) -> u64 {
+        self.total_cents / self.samples.max(1)
+    }
+}
+
+/// Scores BCE records against baselines built from the records seen before them
+#[derive(Debug, Default)]
+pub struct FraudDetector {
+    config: FraudConfig,
+    /// Baselines by (home PLMN, visited PLMN, record type)
+    baselines: HashMap<(String, String, String), ChargeBaseline>,
+    /// Last visited PLMN and timestamp per IMSI
+    last_seen: HashMap<String, (String, u64)>,
+}
+
+/// Mobile country code of a PLMN, its first three digits
+fn country_code(plmn: &str) -> &str {
+    plmn.get(..3).unwrap_or(plmn)
+}
+
+impl FraudDetector {
+    pub fn new(config: FraudConfig) -> Self {
+        Self { config, ..Default::default() }
+    }
+
+    pub fn config(&self) -> &FraudConfig {
+        &self.config
+    }
+
+    /// Score a record and learn from it; flagged charges are kept out of the baseline
+    pub fn score(&mut self, record: &BCERecord) -> FraudScore {
+        let mut indicators = Vec::new();
+
+        if record.session_duration > self.config.max_session_seconds {
+            indicators.push(FraudIndicator::ImpossibleDuration { seconds: record.session_duration });
+        }
+
+        let route = (record.home_plmn.clone(), record.visited_plmn.clone(), record.record_type.clone());
+        let baseline = self.baselines.get(&route).copied().unwrap_or_default();
+        let spike = baseline.samples >= self.config.baseline_min_samples
+            && record.wholesale_charge > baseline.mean().saturating_mul(self.config.spike_factor);
+        if spike {
+            indicators.push(FraudIndicator::ChargeSpike {
+                charge_cents: record.wholesale_charge,
+                baseline_cents: baseline.mean(),
+            });
+        } else {
+            let entry = self.baselines.entry(route).or_default();
+            entry.samples += 1;
+            entry.total_cents += record.wholesale_charge;
+        }
+
+        if let Some((other_plmn, seen_at)) = self.last_seen.get(&record.imsi) {
+            let seconds_apart = record.timestamp.abs_diff(*seen_at);
+            if country_code(other_plmn) != country_code(&record.visited_plmn)
+                && seconds_apart < self.config.min_travel_seconds
+            {
+                indicators.push(FraudIndicator::SimultaneousCountries {
+                    imsi: record.imsi.clone(),
+                    other_plmn: other_plmn.clone(),
+                    seconds_apart,
+                });
+            }
+        }
+        let latest = self.last_seen.get(&record.imsi).map_or(true, |(_, seen_at)| record.timestamp >= *seen_at);
+        if latest {
+            self.last_seen.insert(record.imsi.clone(), (record.visited_plmn.clone(), record.timestamp));
+        }
+
+        FraudScore {
+            record_id: record.record_id.clone(),
+            score: indicators.iter().map(FraudIndicator::weight).sum(),
+            indicators,
+        }
+    }
+
+    /// Whether a score puts the record's batch into quarantine
+    pub fn is_suspicious(&self, score: &FraudScore) -> bool {
+        score.score >= self.config.quarantine_score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, imsi: &str, visited_plmn: &str, duration: u64, charge: u64, timestamp: u64) -> BCERecord {
+        BCERecord {
+            record_id: id.to_string(),
+            record_type: "VOICE_CALL_CDR".to_string(),
+            imsi: imsi.to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: visited_plmn.to_string(),
+            session_duration: duration,
+            bytes_uplink: 0,
+            bytes_downlink: 0,
+            wholesale_charge: charge,
+            retail_charge: charge,
+            currency: "EUR".to_string(),
+            timestamp,
+            charging_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_fraud_scoring() {
+        let mut detector = FraudDetector::new(FraudConfig::default());
+
+        // Ordinary calls build the baseline without being flagged
+        for i in 0..20 {
+            let score = detector.score(&record(&format!("R{}", i), &format!("2620100000{:05}", i), "23410", 300, 100, 1_000 + i));
+            assert!(score.indicators.is_empty());
+        }
+
+        let long = detector.score(&record("LONG", "262010000100000", "23410", 100_000, 100, 2_000));
+        assert_eq!(long.indicators, vec![FraudIndicator::ImpossibleDuration { seconds: 100_000 }]);
+        assert!(detector.is_suspicious(&long));
+
+        let spike = detector.score(&record("SPIKE", "262010000100001", "23410", 300, 5_000, 2_000));
+        assert_eq!(spike.indicators, vec![FraudIndicator::ChargeSpike { charge_cents: 5_000, baseline_cents: 100 }]);
+        assert!(!detector.is_suspicious(&spike));
+
+        // The same IMSI in France ten minutes after a call in the UK
+        let imsi = "262010000100002";
+        assert!(detector.score(&record("UK", imsi, "23410", 60, 100, 10_000)).indicators.is_empty());
+        let france = detector.score(&record("FR", imsi, "20801", 60, 100, 10_600));
+        assert!(matches!(france.indicators[..], [FraudIndicator::SimultaneousCountries { seconds_apart: 600, .. }]));
+        assert!(detector.is_suspicious(&france));
+
+        // A day later the trip is plausible
+        assert!(detector.score(&record("UK2", imsi, "23410", 60, 100, 100_000)).indicators.is_empty());
+    }
+}
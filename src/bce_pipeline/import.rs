@@ -0,0 +1,315 @@
+// Bulk import of BCE exports from operator mediation systems: CSV, ASN.1 BER and JSON files
+// are mapped to `BCERecord`s, and records failing validation are reported instead of imported
+//
+// The ASN.1 layout, all fields IMPLICIT context tagged, integers non-negative:
+//   BCERecordFile ::= SEQUENCE OF BCERecord
+//   BCERecord ::= SEQUENCE {
+//     recordId [0] UTF8String, recordType [1] UTF8String, imsi [2] UTF8String,
+//     homePlmn [3] UTF8String, visitedPlmn [4] UTF8String, sessionDuration [5] INTEGER,
+//     bytesUplink [6] INTEGER, bytesDownlink [7] INTEGER, wholesaleCharge [8] INTEGER,
+//     retailCharge [9] INTEGER, currency [10] UTF8String, timestamp [11] INTEGER,
+//     chargingId [12] INTEGER, ... }
+use crate::blockchain::operator_registry::is_plmn_code;
+use crate::primitives::{BlockchainError, Result};
+use super::BCERecord;
+
+/// File formats mediation systems export BCE records in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BceFileFormat {
+    /// Header row naming the `BCERecord` fields, one record per row
+    Csv,
+    /// DER or definite-length BER encoded `BCERecordFile`
+    Asn1,
+    /// Array of records, or one record per line
+    Json,
+}
+
+impl std::str::FromStr for BceFileFormat {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(BceFileFormat::Csv),
+            "asn1" => Ok(BceFileFormat::Asn1),
+            "json" => Ok(BceFileFormat::Json),
+            _ => Err(BlockchainError::InvalidOperation(format!("Unknown BCE file format: {}. Use: csv, asn1, json", s))),
+        }
+    }
+}
+
+/// Records of a file, with the entries that could not be imported
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    pub records: Vec<BCERecord>,
+    /// Position of the entry in the file, counted from 1, and why it was rejected
+    pub rejected: Vec<(usize, String)>,
+}
+
+impl ParsedFile {
+    fn push(&mut self, entry: usize, record: Result<BCERecord>) {
+        match record.and_then(|record| validate_record(&record).map(|()| record)) {
+            Ok(record) => self.records.push(record),
+            Err(e) => self.rejected.push((entry, e.to_string())),
+        }
+    }
+}
+
+/// Parse a BCE export, failing only if the file as a whole is unreadable
+pub fn parse_bce_file(data: &[u8], format: BceFileFormat) -> Result<ParsedFile> {
+    match format {
+        BceFileFormat::Csv => parse_csv(data),
+        BceFileFormat::Asn1 => parse_asn1(data),
+        BceFileFormat::Json => parse_json(data),
+    }
+}
+
+/// Check a record's PLMN codes, IMSI and charges
+pub fn validate_record(record: &BCERecord) -> Result<()> {
+    let invalid = |reason: String| Err(BlockchainError::InvalidTransaction(format!("Record {}: {}", record.record_id, reason)));
+
+    if record.record_id.is_empty() {
+        return invalid("missing record id".to_string());
+    }
+    for plmn in [&record.home_plmn, &record.visited_plmn] {
+        if !is_plmn_code(plmn) {
+            return invalid(format!("{} is not a PLMN code", plmn));
+        }
+    }
+    if !(6..=15).contains(&record.imsi.len()) || !record.imsi.chars().all(|c| c.is_ascii_digit()) {
+        return invalid(format!("{} is not an IMSI", record.imsi));
+    }
+    if record.currency.len() != 3 || !record.currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return invalid(format!("{} is not an ISO 4217 currency code", record.currency));
+    }
+    if record.wholesale_charge > record.retail_charge {
+        return invalid(format!("wholesale charge {} above retail charge {}", record.wholesale_charge, record.retail_charge));
+    }
+    Ok(())
+}
+
+fn parse_csv(data: &[u8]) -> Result<ParsedFile> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(data);
+    let mut parsed = ParsedFile::default();
+    for (index, row) in reader.deserialize::<BCERecord>().enumerate() {
+        parsed.push(index + 1, row.map_err(|e| BlockchainError::Serialization(format!("Invalid CSV row: {}", e))));
+    }
+    Ok(parsed)
+}
+
+fn parse_json(data: &[u8]) -> Result<ParsedFile> {
+    let text = std::str::from_utf8(data)
+        .map_err(|e| BlockchainError::Serialization(format!("BCE file is not UTF-8: {}", e)))?;
+    let mut parsed = ParsedFile::default();
+
+    if text.trim_start().starts_with('[') {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(text)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid JSON array: {}", e)))?;
+        for (index, entry) in entries.into_iter().enumerate() {
+            parsed.push(index + 1, serde_json::from_value(entry)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid record: {}", e))));
+        }
+    } else {
+        for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            parsed.push(index + 1, serde_json::from_str(line)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid record: {}", e))));
+        }
+    }
+    Ok(parsed)
+}
+
+const SEQUENCE: u8 = 0x30;
+
+/// One BER element: identifier octet and contents
+struct Tlv<'a> {
+    tag: u8,
+    value: &'a [u8],
+}
+
+/// Split the next element off `data`
+fn read_tlv<'a>(data: &mut &'a [u8]) -> Result<Tlv<'a>> {
+    let malformed = |reason: &str| BlockchainError::Serialization(format!("Malformed ASN.1: {}", reason));
+
+    let (&tag, rest) = data.split_first().ok_or_else(|| malformed("truncated element"))?;
+    if tag & 0x1f == 0x1f {
+        return Err(malformed("high tag numbers are not supported"));
+    }
+    let (&first, mut rest) = rest.split_first().ok_or_else(|| malformed("missing length"))?;
+    let length = match first {
+        0x80 => return Err(malformed("indefinite lengths are not supported")),
+        short if short < 0x80 => short as usize,
+        long => {
+            let octets = (long & 0x7f) as usize;
+            if octets > 4 || rest.len() < octets {
+                return Err(malformed("invalid length"));
+            }
+            let length = rest[..octets].iter().fold(0usize, |length, &octet| (length << 8) | octet as usize);
+            rest = &rest[octets..];
+            length
+        }
+    };
+    if rest.len() < length {
+        return Err(malformed("element longer than its enclosing data"));
+    }
+    let (value, rest) = rest.split_at(length);
+    *data = rest;
+    Ok(Tlv { tag, value })
+}
+
+fn parse_asn1(data: &[u8]) -> Result<ParsedFile> {
+    let mut data = data;
+    let file = read_tlv(&mut data)?;
+    if file.tag != SEQUENCE {
+        return Err(BlockchainError::Serialization("ASN.1 BCE file is not a SEQUENCE OF BCERecord".to_string()));
+    }
+
+    let mut parsed = ParsedFile::default();
+    let mut records = file.value;
+    let mut index = 0;
+    while !records.is_empty() {
+        index += 1;
+        let record = read_tlv(&mut records)?;
+        if record.tag != SEQUENCE {
+            parsed.rejected.push((index, format!("Entry is not a BCERecord SEQUENCE (tag {:#04x})", record.tag)));
+            continue;
+        }
+        parsed.push(index, decode_record(record.value));
+    }
+    Ok(parsed)
+}
+
+fn decode_record(mut fields: &[u8]) -> Result<BCERecord> {
+    let mut strings: [Option<String>; 13] = Default::default();
+    let mut integers: [Option<u64>; 13] = Default::default();
+
+    while !fields.is_empty() {
+        let field = read_tlv(&mut fields)?;
+        // Context-specific primitive [n], fields added by newer schema versions are skipped
+        if field.tag & 0xe0 != 0x80 || field.tag & 0x1f > 12 {
+            continue;
+        }
+        let number = (field.tag & 0x1f) as usize;
+        match number {
+            0..=4 | 10 => strings[number] = Some(String::from_utf8(field.value.to_vec())
+                .map_err(|_| BlockchainError::Serialization(format!("Field [{}] is not UTF-8", number)))?),
+            _ => integers[number] = Some(decode_integer(field.value)
+                .ok_or_else(|| BlockchainError::Serialization(format!("Field [{}] is not a non-negative 64-bit INTEGER", number)))?),
+        }
+    }
+
+    let missing = |number: usize| BlockchainError::Serialization(format!("Missing field [{}]", number));
+    let mut string = |number: usize| strings[number].take().ok_or_else(|| missing(number));
+    let (record_id, record_type, imsi, home_plmn, visited_plmn, currency) =
+        (string(0)?, string(1)?, string(2)?, string(3)?, string(4)?, string(10)?);
+    let integer = |number: usize| integers[number].ok_or_else(|| missing(number));
+
+    Ok(BCERecord {
+        record_id,
+        record_type,
+        imsi,
+        home_plmn,
+        visited_plmn,
+        session_duration: integer(5)?,
+        bytes_uplink: integer(6)?,
+        bytes_downlink: integer(7)?,
+        wholesale_charge: integer(8)?,
+        retail_charge: integer(9)?,
+        currency,
+        timestamp: integer(11)?,
+        charging_id: integer(12)?,
+    })
+}
+
+/// Two's complement big-endian INTEGER contents, `None` if negative or wider than 64 bits
+fn decode_integer(value: &[u8]) -> Option<u64> {
+    let (&first, _) = value.split_first()?;
+    if first & 0x80 != 0 {
+        return None;
+    }
+    let value = if first == 0 { &value[1..] } else { value };
+    if value.len() > 8 {
+        return None;
+    }
+    Some(value.iter().fold(0u64, |integer, &octet| (integer << 8) | octet as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![tag];
+        if value.len() < 0x80 {
+            encoded.push(value.len() as u8);
+        } else {
+            encoded.extend_from_slice(&[0x82, (value.len() >> 8) as u8, value.len() as u8]);
+        }
+        encoded.extend_from_slice(value);
+        encoded
+    }
+
+    fn integer(tag: u8, value: u64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let start = bytes.iter().position(|&octet| octet != 0).unwrap_or(7);
+        let mut contents = if bytes[start] & 0x80 != 0 { vec![0] } else { vec![] };
+        contents.extend_from_slice(&bytes[start..]);
+        tlv(tag, &contents)
+    }
+
+    fn asn1_record(record_id: &str, visited_plmn: &str, wholesale_charge: u64) -> Vec<u8> {
+        let fields = [
+            tlv(0x80, record_id.as_bytes()),
+            tlv(0x81, b"DATA_SESSION_CDR"),
+            tlv(0x82, b"262011234567890"),
+            tlv(0x83, b"26201"),
+            tlv(0x84, visited_plmn.as_bytes()),
+            integer(0x85, 600),
+            integer(0x86, 1_048_576),
+            integer(0x87, 4_194_304),
+            integer(0x88, wholesale_charge),
+            integer(0x89, 500),
+            tlv(0x8a, b"EUR"),
+            integer(0x8b, 1_700_000_000),
+            integer(0x8c, 200),
+            // Unknown extension field
+            tlv(0x8d, b"ignored"),
+        ].concat();
+        tlv(SEQUENCE, &fields)
+    }
+
+    #[test]
+    fn test_asn1_import() {
+        let file = tlv(SEQUENCE, &[
+            asn1_record("CDR-1", "20801", 250),
+            asn1_record("CDR-2", "2080X", 250),
+            asn1_record("CDR-3", "20801", 900),
+        ].concat());
+
+        let parsed = parse_bce_file(&file, BceFileFormat::Asn1).unwrap();
+        assert_eq!(parsed.records.len(), 1);
+        let record = &parsed.records[0];
+        assert_eq!(record.record_id, "CDR-1");
+        assert_eq!((record.bytes_uplink, record.wholesale_charge, record.charging_id), (1_048_576, 250, 200));
+        assert_eq!(parsed.rejected.iter().map(|(entry, _)| *entry).collect::<Vec<_>>(), vec![2, 3]);
+
+        // A truncated file is rejected as a whole
+        assert!(parse_bce_file(&file[..file.len() - 1], BceFileFormat::Asn1).is_err());
+    }
+
+    #[test]
+    fn test_csv_and_json_import() {
+        let csv = "record_id,record_type,imsi,home_plmn,visited_plmn,session_duration,bytes_uplink,bytes_downlink,wholesale_charge,retail_charge,currency,timestamp,charging_id\n\
+                   CDR-1, VOICE_CALL_CDR, 262011234567890, 26201, 20801, 120, 0, 0, 30, 60, EUR, 1700000000, 1\n\
+                   CDR-2, VOICE_CALL_CDR, 262011234567890, 26201, 20801, 120, 0, 0, -30, 60, EUR, 1700000000, 2\n\
+                   CDR-3, VOICE_CALL_CDR, 262011234567890, 26201, 20801, 120, 0, 0, 30, 60, euro, 1700000000, 3\n";
+        let parsed = parse_bce_file(csv.as_bytes(), BceFileFormat::Csv).unwrap();
+        assert_eq!(parsed.records.len(), 1);
+        assert_eq!(parsed.records[0].session_duration, 120);
+        assert_eq!(parsed.rejected.len(), 2);
+
+        let json = serde_json::to_string(&parsed.records[0]).unwrap();
+        let parsed = parse_bce_file(format!("{}\n\n{}\n", json, json).as_bytes(), BceFileFormat::Json).unwrap();
+        assert_eq!(parsed.records.len(), 2);
+        let parsed = parse_bce_file(format!("[{}, {{}}]", json).as_bytes(), BceFileFormat::Json).unwrap();
+        assert_eq!((parsed.records.len(), parsed.rejected.len()), (1, 1));
+    }
+}
@@ -0,0 +1,299 @@
+// Bounded ingestion queue: pending BCE batches are written through to the pipeline store, those
+// beyond a resident limit are kept in memory as totals only, and ingestion is refused once pending
+// records reach the high watermark, until settlement drains them below the low watermark
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::blockchain::tariff::ServiceBreakdown;
+use crate::metrics::metrics;
+use crate::primitives::{Blake2bHash, BlockchainError, NetworkId, Result};
+use super::recovery::PipelineStore;
+use super::{BCEBatch, BCERecord};
+
+/// Watermarks of the pending batch queue
+#[derive(Debug, Clone, Copy)]
+pub struct IngestLimits {
+    /// Pending records at which ingestion is refused
+    pub high_watermark: usize,
+    /// Pending records below which ingestion resumes
+    pub low_watermark: usize,
+    /// Batches kept in memory, later ones are read back from disk when settled
+    pub max_resident_batches: usize,
+}
+
+impl Default for IngestLimits {
+    fn default() -> Self {
+        Self {
+            high_watermark: 5_000_000,
+            low_watermark: 4_000_000,
+            max_resident_batches: 10_000,
+        }
+    }
+}
+
+/// What stays in memory of a spilled batch, enough to total settlements without its records
+#[derive(Debug, Clone)]
+pub struct SpilledBatch {
+    pub batch_id: Blake2bHash,
+    pub home_network: NetworkId,
+    pub visited_network: NetworkId,
+    pub period_end: u64,
+    pub total_charges_cents: u64,
+    pub service_breakdown: ServiceBreakdown,
+    pub record_count: usize,
+}
+
+impl SpilledBatch {
+    fn of(batch: &BCEBatch) -> Self {
+        Self {
+            batch_id: batch.batch_id,
+            home_network: batch.home_network.clone(),
+            visited_network: batch.visited_network.clone(),
+            period_end: batch.period_end,
+            total_charges_cents: batch.total_charges_cents,
+            service_breakdown: batch.service_breakdown.clone(),
+            record_count: batch.records.len(),
+        }
+    }
+}
+
+/// BCE batches waiting for their settlement period to close
+#[derive(Clone)]
+pub struct PendingBatches {
+    store: PipelineStore,
+    limits: IngestLimits,
+    resident: HashMap<Blake2bHash, BCEBatch>,
+    spilled: HashMap<Blake2bHash, SpilledBatch>,
+    record_count: usize,
+    saturated: bool,
+}
+
+impl PendingBatches {
+    /// Queue of `batches`, as recovered from `store`, keeping those beyond the resident limit on disk only
+    pub fn new(store: PipelineStore, limits: IngestLimits, batches: Vec<BCEBatch>) -> Self {
+        let mut queue = Self {
+            store,
+            limits,
+            record_count: batches.iter().map(|batch| batch.records.len()).sum(),
+            resident: batches.into_iter().map(|batch| (batch.batch_id, batch)).collect(),
+            spilled: HashMap::new(),
+            saturated: false,
+        };
+        queue.spill_overflow();
+        queue.update_metrics();
+        queue
+    }
+
+    /// Number of pending batches, in memory or on disk
+    pub fn len(&self) -> usize {
+        self.resident.len() + self.spilled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    pub fn is_saturated(&self) -> bool {
+        self.saturated
+    }
+
+    /// Refuse new records while the queue is saturated
+    pub fn check_capacity(&mut self) -> Result<()> {
+        if self.saturated && self.record_count < self.limits.low_watermark {
+            info!("🚰 {} pending BCE records, accepting records again", self.record_count);
+            self.saturated = false;
+        } else if !self.saturated && self.record_count >= self.limits.high_watermark {
+            warn!("🚰 {} pending BCE records, refusing records until below {}", self.record_count, self.limits.low_watermark);
+            self.saturated = true;
+        }
+        if self.saturated {
+            return Err(BlockchainError::Saturated(format!(
+                "{} BCE records pending settlement, retry later", self.record_count
+            )));
+        }
+        Ok(())
+    }
+
+    /// Add a record to its batch, created by `new_batch` if not pending, let `update` account for it
+    /// and write the batch through
+    pub async fn add_record(
+        &mut self,
+        batch_id: Blake2bHash,
+        record: &BCERecord,
+        new_batch: impl FnOnce() -> BCEBatch,
+        update: impl FnOnce(&mut BCEBatch),
+    ) -> Result<()> {
+        let mut batch = match self.take_resident(&batch_id).await? {
+            Some(batch) => batch,
+            None => new_batch(),
+        };
+        batch.records.push(record.clone());
+        update(&mut batch);
+        self.insert(batch).await
+    }
+
+    /// Add a batch, replacing any pending one of the same id
+    pub async fn insert(&mut self, batch: BCEBatch) -> Result<()> {
+        self.store.put_batch(&batch).await?;
+        if let Some(replaced) = self.spilled.remove(&batch.batch_id) {
+            self.record_count -= replaced.record_count;
+        }
+        self.record_count += batch.records.len();
+        if let Some(replaced) = self.resident.insert(batch.batch_id, batch) {
+            self.record_count -= replaced.records.len();
+        }
+        self.update_metrics();
+        Ok(())
+    }
+
+    /// Remove a batch, reading it back from disk if it was spilled
+    pub async fn take(&mut self, batch_id: &Blake2bHash) -> Result<Option<BCEBatch>> {
+        let batch = self.take_resident(batch_id).await?;
+        if batch.is_some() {
+            self.store.delete_batch(batch_id).await?;
+            self.update_metrics();
+        }
+        Ok(batch)
+    }
+
+    /// Remove a batch from the queue, leaving it on disk
+    async fn take_resident(&mut self, batch_id: &Blake2bHash) -> Result<Option<BCEBatch>> {
+        if let Some(batch) = self.resident.remove(batch_id) {
+            self.record_count -= batch.records.len();
+            return Ok(Some(batch));
+        }
+        let spilled = match self.spilled.remove(batch_id) {
+            Some(spilled) => spilled,
+            None => return Ok(None),
+        };
+        let batch = self.store.batch(batch_id).await?
+            .ok_or_else(|| BlockchainError::Storage(format!("Spilled batch {} is missing", batch_id)))?;
+        self.record_count -= spilled.record_count;
+        Ok(Some(batch))
+    }
+
+    /// Ids of the batches whose last record is from before `cutoff`, in id order
+    pub fn ended_before(&self, cutoff: u64) -> Vec<Blake2bHash> {
+        let mut batch_ids: Vec<Blake2bHash> = self.resident.values().map(|batch| (batch.batch_id, batch.period_end))
+            .chain(self.spilled.values().map(|batch| (batch.batch_id, batch.period_end)))
+            .filter(|(_, period_end)| *period_end < cutoff)
+            .map(|(batch_id, _)| batch_id)
+            .collect();
+        batch_ids.sort_by_key(|batch_id| batch_id.0);
+        batch_ids
+    }
+
+    /// Network pair, total and service split of every pending batch
+    pub fn totals(&self) -> impl Iterator<Item = (&NetworkId, &NetworkId, u64, &ServiceBreakdown)> {
+        self.batches().map(|(_, home_network, visited_network, total, breakdown)| (home_network, visited_network, total, breakdown))
+    }
+
+    /// Id, network pair, total and service split of every pending batch
+    pub fn batches(&self) -> impl Iterator<Item = (Blake2bHash, &NetworkId, &NetworkId, u64, &ServiceBreakdown)> {
+        self.resident.values()
+            .map(|batch| (batch.batch_id, &batch.home_network, &batch.visited_network, batch.total_charges_cents, &batch.service_breakdown))
+            .chain(self.spilled.values()
+                .map(|batch| (batch.batch_id, &batch.home_network, &batch.visited_network, batch.total_charges_cents, &batch.service_breakdown)))
+    }
+
+    /// Drop the batches beyond the resident limit from memory, the latest ending first as they settle last
+    /// They are on disk already, having been written through
+    pub fn spill_overflow(&mut self) {
+        let overflow = self.resident.len().saturating_sub(self.limits.max_resident_batches);
+        if overflow == 0 {
+            return;
+        }
+        let mut latest: Vec<(u64, Blake2bHash)> = self.resident.values().map(|batch| (batch.period_end, batch.batch_id)).collect();
+        latest.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.0.cmp(&b.1.0)));
+
+        for (_, batch_id) in latest.into_iter().take(overflow) {
+            let batch = self.resident.remove(&batch_id).expect("batch is resident");
+            self.spilled.insert(batch_id, SpilledBatch::of(&batch));
+        }
+        info!("💾 Spilled {} pending BCE batches to disk, {} on disk in total", overflow, self.spilled.len());
+        self.update_metrics();
+    }
+
+    fn update_metrics(&self) {
+        metrics().pending_bce_records.set(self.record_count as i64);
+        metrics().spilled_bce_batches.set(self.spilled.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MdbxChainStore;
+
+    fn batch(id: u8, records: usize, period_end: u64) -> BCEBatch {
+        let record = BCERecord {
+            record_id: format!("CDR-{}", id),
+            record_type: "VOICE_CALL_CDR".to_string(),
+            imsi: "262011234567890".to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: "20801".to_string(),
+            session_duration: 60,
+            bytes_uplink: 0,
+            bytes_downlink: 0,
+            wholesale_charge: 10,
+            retail_charge: 20,
+            currency: "EUR".to_string(),
+            timestamp: period_end,
+            charging_id: id as u64,
+        };
+        BCEBatch {
+            batch_id: Blake2bHash::from_data(&[id]),
+            home_network: NetworkId::new("T-Mobile-DE", "Germany"),
+            visited_network: NetworkId::new("Orange-FR", "France"),
+            records: vec![record; records],
+            period_start: period_end,
+            period_end,
+            total_charges_cents: 10 * records as u64,
+            service_breakdown: ServiceBreakdown::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow_spills_and_saturates() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PipelineStore::new(MdbxChainStore::new(dir.path()).unwrap());
+        let limits = IngestLimits { high_watermark: 10, low_watermark: 5, max_resident_batches: 2 };
+        let mut queue = PendingBatches::new(store.clone(), limits, vec![]);
+
+        for (id, period_end) in [(1, 100), (2, 300), (3, 200)] {
+            queue.insert(batch(id, 3, period_end)).await.unwrap();
+        }
+        queue.spill_overflow();
+        assert_eq!((queue.resident.len(), queue.spilled.len(), queue.len()), (2, 1, 3));
+        assert!(queue.spilled.contains_key(&batch(2, 0, 0).batch_id));
+        assert_eq!(queue.totals().map(|(_, _, total, _)| total).sum::<u64>(), 90);
+        assert!(queue.check_capacity().is_ok());
+
+        // One more record reaches the high watermark
+        let record = batch(2, 1, 300).records.remove(0);
+        queue.add_record(batch(2, 0, 0).batch_id, &record, || unreachable!(), |batch| batch.total_charges_cents += 10).await.unwrap();
+        assert_eq!(queue.record_count(), 10);
+        assert!(matches!(queue.check_capacity(), Err(BlockchainError::Saturated(_))));
+
+        // Everything pending is on disk, a restarted queue holds only the resident limit in memory
+        let restarted = PendingBatches::new(store.clone(), limits, store.load().await.unwrap().pending_batches);
+        assert_eq!((restarted.resident.len(), restarted.len(), restarted.record_count()), (2, 3, 10));
+
+        // Draining below the high watermark is not enough, the low one has to be passed
+        let ended = queue.ended_before(250);
+        assert_eq!(ended.len(), 2);
+        queue.take(&ended[0]).await.unwrap().unwrap();
+        assert!(queue.check_capacity().is_err());
+        queue.take(&ended[1]).await.unwrap().unwrap();
+        assert!(queue.check_capacity().is_ok());
+
+        // The batch read back from disk kept its records
+        assert_eq!(queue.take(&batch(2, 0, 0).batch_id).await.unwrap().unwrap().records.len(), 4);
+        assert!(queue.is_empty());
+        assert!(store.load().await.unwrap().pending_batches.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+// Net bilateral settlement positions for finance dashboards: settlements finalized on chain and
+// proposals still being agreed are netted per operator pair, period and currency
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::SettlementTransaction;
+use super::{SettlementProposal, SettlementStatus};
+
+/// One settlement counted into a position
+#[derive(Debug, Clone)]
+pub struct PositionEntry {
+    pub creditor: String,
+    pub debtor: String,
+    pub amount_cents: u64,
+    pub currency: String,
+    pub period: String,
+    /// Finalized on chain, otherwise still pending agreement
+    pub settled: bool,
+}
+
+impl From<&SettlementTransaction> for PositionEntry {
+    fn from(settlement: &SettlementTransaction) -> Self {
+        Self {
+            creditor: settlement.creditor_network.clone(),
+            debtor: settlement.debtor_network.clone(),
+            amount_cents: settlement.amount,
+            currency: settlement.currency.clone(),
+            period: settlement.period.clone(),
+            settled: true,
+        }
+    }
+}
+
+impl PositionEntry {
+    /// Entry of a proposal still in agreement, `None` once it was rejected or finalized
+    /// Finalized proposals are counted from their settlement transaction on chain instead
+    pub fn pending(proposal: &SettlementProposal) -> Option<Self> {
+        match proposal.status {
+            SettlementStatus::Proposed | SettlementStatus::Accepted => Some(Self {
+                // Named the way `finalize_settlement` names them on chain
+                creditor: format!("{:?}", proposal.creditor),
+                debtor: format!("{:?}", proposal.debtor),
+                amount_cents: proposal.amount_cents,
+                currency: "EUR".to_string(),
+                period: proposal.period.clone(),
+                settled: false,
+            }),
+            SettlementStatus::Rejected(_) | SettlementStatus::Finalized => None,
+        }
+    }
+}
+
+/// Net position of `operator` towards `counterparty` in one period and currency
+/// Amounts are positive when `counterparty` owes `operator`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementPosition {
+    pub operator: String,
+    pub counterparty: String,
+    pub period: String,
+    pub currency: String,
+    pub settled_cents: i64,
+    pub pending_cents: i64,
+    pub net_cents: i64,
+}
+
+/// Whether `network` names `operator`, by its operator name or in full
+/// Settlement transactions name networks by the `Debug` form of their `NetworkId`, batch
+/// commitments by its `name:country` display form
+pub fn is_operator(network: &str, operator: &str) -> bool {
+    network == operator
+        || network.starts_with(&format!("{}:", operator))
+        || network.starts_with(&format!("Operator {{ name: {:?},", operator))
+}
+
+/// Net `entries` into one position per operator pair, period and currency
+/// Every pair is reported once, from the side of the lower named operator, unless `operator` is
+/// given: then only its positions are reported, from its side
+pub fn net_positions(entries: impl IntoIterator<Item = PositionEntry>, operator: Option<&str>) -> Vec<SettlementPosition> {
+    let mut positions: BTreeMap<(String, String, String, String), SettlementPosition> = BTreeMap::new();
+    for entry in entries {
+        let (from, to, sign) = match operator {
+            Some(operator) if is_operator(&entry.creditor, operator) => (entry.creditor, entry.debtor, 1),
+            Some(operator) if is_operator(&entry.debtor, operator) => (entry.debtor, entry.creditor, -1),
+            Some(_) => continue,
+            None if entry.creditor <= entry.debtor => (entry.creditor, entry.debtor, 1),
+            None => (entry.debtor, entry.creditor, -1),
+        };
+        let amount = sign * entry.amount_cents as i64;
+        let position = positions
+            .entry((entry.period.clone(), entry.currency.clone(), from.clone(), to.clone()))
+            .or_insert_with(|| SettlementPosition {
+                operator: from,
+                counterparty: to,
+                period: entry.period,
+                currency: entry.currency,
+                settled_cents: 0,
+                pending_cents: 0,
+                net_cents: 0,
+            });
+        if entry.settled {
+            position.settled_cents += amount;
+        } else {
+            position.pending_cents += amount;
+        }
+        position.net_cents += amount;
+    }
+    positions.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(creditor: &str, debtor: &str, amount_cents: u64, currency: &str, settled: bool) -> PositionEntry {
+        PositionEntry {
+            creditor: creditor.to_string(),
+            debtor: debtor.to_string(),
+            amount_cents,
+            currency: currency.to_string(),
+            period: "2024-01-01/2024-01-16".to_string(),
+            settled,
+        }
+    }
+
+    #[test]
+    fn test_positions_net_per_pair_and_currency() {
+        let tmobile = format!("{:?}", crate::primitives::NetworkId::new("T-Mobile", "DE"));
+        let vodafone = format!("{:?}", crate::primitives::NetworkId::new("Vodafone", "UK"));
+        let entries = vec![
+            entry(&tmobile, &vodafone, 10_000, "EUR", true),
+            entry(&vodafone, &tmobile, 4_000, "EUR", true),
+            entry(&vodafone, &tmobile, 1_500, "EUR", false),
+            entry(&tmobile, &vodafone, 700, "GBP", true),
+        ];
+
+        let positions = net_positions(entries.clone(), None);
+        assert_eq!(positions.len(), 2);
+        let eur = positions.iter().find(|position| position.currency == "EUR").unwrap();
+        assert_eq!((eur.operator.as_str(), eur.counterparty.as_str()), (tmobile.as_str(), vodafone.as_str()));
+        assert_eq!((eur.settled_cents, eur.pending_cents, eur.net_cents), (6_000, -1_500, 4_500));
+
+        // From Vodafone's side the same positions are payables
+        let positions = net_positions(entries.clone(), Some("Vodafone"));
+        assert_eq!(positions.len(), 2);
+        let eur = positions.iter().find(|position| position.currency == "EUR").unwrap();
+        assert_eq!(eur.operator, vodafone);
+        assert_eq!(eur.net_cents, -4_500);
+
+        assert!(net_positions(entries, Some("Orange")).is_empty());
+    }
+}
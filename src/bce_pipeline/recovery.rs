@@ -0,0 +1,239 @@
+// Crash-safe pipeline state: pending and quarantined batches, settlement proposals, approvals and stats
+// are written to their own MDBX tables as they change, so a node killed mid-settlement resumes
+// where it stopped. Proposals are reconciled against the chain when they are loaded
+use std::collections::HashSet;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use crate::settlement_execution::PaymentDocument;
+use crate::storage::{MdbxChainStore, PipelineTable};
+use super::{BCEBatch, PipelineStats, SettlementProposal, SettlementStatus};
+use super::approvals::PendingApproval;
+
+const STATS_KEY: &[u8] = b"stats";
+
+/// Pipeline state as last written
+#[derive(Debug, Default)]
+pub struct RecoveredState {
+    pub pending_batches: Vec<BCEBatch>,
+    pub settlement_proposals: Vec<SettlementProposal>,
+    pub quarantined_batches: Vec<BCEBatch>,
+    pub pending_approvals: Vec<PendingApproval>,
+    pub stats: PipelineStats,
+}
+
+/// Write-through store of the pipeline's state
+#[derive(Clone)]
+pub struct PipelineStore {
+    store: MdbxChainStore,
+}
+
+impl PipelineStore {
+    pub fn new(store: MdbxChainStore) -> Self {
+        Self { store }
+    }
+
+    async fn put<T: Serialize>(&self, table: PipelineTable, key: &[u8], value: &T) -> Result<()> {
+        let data = bincode::serialize(value).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        self.store.put_pipeline_state(table, key, &data).await
+    }
+
+    async fn entries<T: DeserializeOwned>(&self, table: PipelineTable) -> Result<Vec<T>> {
+        self.store.pipeline_state(table).await?
+            .into_iter()
+            .map(|(_, data)| bincode::deserialize(&data).map_err(|e| BlockchainError::Serialization(e.to_string())))
+            .collect()
+    }
+
+    pub async fn put_batch(&self, batch: &BCEBatch) -> Result<()> {
+        self.put(PipelineTable::PendingBatches, batch.batch_id.as_bytes(), batch).await
+    }
+
+    /// Pending batch, as spilled batches are read back
+    pub async fn batch(&self, batch_id: &Blake2bHash) -> Result<Option<BCEBatch>> {
+        match self.store.get_pipeline_state(PipelineTable::PendingBatches, batch_id.as_bytes()).await? {
+            Some(data) => bincode::deserialize(&data).map(Some).map_err(|e| BlockchainError::Serialization(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn delete_batch(&self, batch_id: &Blake2bHash) -> Result<()> {
+        self.store.delete_pipeline_state(PipelineTable::PendingBatches, batch_id.as_bytes()).await
+    }
+
+    pub async fn put_proposal(&self, proposal: &SettlementProposal) -> Result<()> {
+        self.put(PipelineTable::SettlementProposals, proposal.proposal_id.as_bytes(), proposal).await
+    }
+
+    pub async fn delete_proposal(&self, proposal_id: &Blake2bHash) -> Result<()> {
+        self.store.delete_pipeline_state(PipelineTable::SettlementProposals, proposal_id.as_bytes()).await
+    }
+
+    pub async fn put_quarantined(&self, batch: &BCEBatch) -> Result<()> {
+        self.put(PipelineTable::QuarantinedBatches, batch.batch_id.as_bytes(), batch).await
+    }
+
+    pub async fn delete_quarantined(&self, batch_id: &Blake2bHash) -> Result<()> {
+        self.store.delete_pipeline_state(PipelineTable::QuarantinedBatches, batch_id.as_bytes()).await
+    }
+
+    pub async fn put_approval(&self, approval: &PendingApproval) -> Result<()> {
+        self.put(PipelineTable::PendingApprovals, approval.proposal_id.as_bytes(), approval).await
+    }
+
+    pub async fn delete_approval(&self, proposal_id: &Blake2bHash) -> Result<()> {
+        self.store.delete_pipeline_state(PipelineTable::PendingApprovals, proposal_id.as_bytes()).await
+    }
+
+    pub async fn put_payment_document(&self, document: &PaymentDocument) -> Result<()> {
+        self.put(PipelineTable::PaymentDocuments, document.settlement_tx.as_bytes(), document).await
+    }
+
+    /// Payment document issued for a settlement, kept for the banking gateway to fetch
+    pub async fn payment_document(&self, settlement_tx: &Blake2bHash) -> Result<Option<PaymentDocument>> {
+        match self.store.get_pipeline_state(PipelineTable::PaymentDocuments, settlement_tx.as_bytes()).await? {
+            Some(data) => bincode::deserialize(&data).map(Some).map_err(|e| BlockchainError::Serialization(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn put_stats(&self, stats: &PipelineStats) -> Result<()> {
+        self.put(PipelineTable::PipelineMeta, STATS_KEY, stats).await
+    }
+
+    /// Everything written so far
+    pub async fn load(&self) -> Result<RecoveredState> {
+        let stats = match self.store.get_pipeline_state(PipelineTable::PipelineMeta, STATS_KEY).await? {
+            Some(data) => bincode::deserialize(&data).map_err(|e| BlockchainError::Serialization(e.to_string()))?,
+            None => PipelineStats::default(),
+        };
+        Ok(RecoveredState {
+            pending_batches: self.entries(PipelineTable::PendingBatches).await?,
+            settlement_proposals: self.entries(PipelineTable::SettlementProposals).await?,
+            quarantined_batches: self.entries(PipelineTable::QuarantinedBatches).await?,
+            pending_approvals: self.entries(PipelineTable::PendingApprovals).await?,
+            stats,
+        })
+    }
+}
+
+/// What reconciling recovered proposals against the chain changed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Finalized proposals whose settlement transaction is on chain, dropped from the pipeline
+    pub settled: Vec<Blake2bHash>,
+    /// Finalized proposals whose transaction was lost before reaching the chain, accepted again
+    pub resubmit: Vec<Blake2bHash>,
+}
+
+/// Drop recovered proposals the chain already settled, and put back to accepted the ones whose
+/// settlement transaction is neither on chain in `on_chain` nor waiting in `queued`
+pub fn reconcile(
+    proposals: &mut Vec<SettlementProposal>,
+    on_chain: &HashSet<Blake2bHash>,
+    queued: &HashSet<Blake2bHash>,
+) -> Reconciliation {
+    let mut reconciliation = Reconciliation::default();
+    proposals.retain_mut(|proposal| {
+        if !matches!(proposal.status, SettlementStatus::Finalized) {
+            return true;
+        }
+        match proposal.settlement_tx {
+            Some(tx_hash) if on_chain.contains(&tx_hash) => {
+                reconciliation.settled.push(proposal.proposal_id);
+                false
+            }
+            Some(tx_hash) if queued.contains(&tx_hash) => true,
+            _ => {
+                proposal.status = SettlementStatus::Accepted;
+                proposal.settlement_tx = None;
+                reconciliation.resubmit.push(proposal.proposal_id);
+                true
+            }
+        }
+    });
+    reconciliation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::tariff::ServiceBreakdown;
+    use crate::primitives::NetworkId;
+
+    fn proposal(name: &[u8], status: SettlementStatus, settlement_tx: Option<Blake2bHash>) -> SettlementProposal {
+        SettlementProposal {
+            proposal_id: Blake2bHash::from_data(name),
+            creditor: NetworkId::new("T-Mobile-DE", "Germany"),
+            debtor: NetworkId::new("Orange-FR", "France"),
+            amount_cents: 12_500,
+            service_breakdown: ServiceBreakdown::default(),
+            period: "2024-01-01/2024-01-16".to_string(),
+            period_hash: Blake2bHash::from_data(b"2024-01-01/2024-01-16"),
+            cdr_batch_proofs: vec![],
+            batch_ids: vec![],
+            proposed_at: 1_704_067_200,
+            status,
+            settlement_tx,
+        }
+    }
+
+    fn batch(name: &[u8]) -> BCEBatch {
+        BCEBatch {
+            batch_id: Blake2bHash::from_data(name),
+            home_network: NetworkId::new("T-Mobile-DE", "Germany"),
+            visited_network: NetworkId::new("Orange-FR", "France"),
+            records: vec![],
+            period_start: 1_704_067_200,
+            period_end: 1_704_067_200,
+            total_charges_cents: 12_500,
+            service_breakdown: ServiceBreakdown::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restart_mid_settlement() {
+        let dir = tempfile::tempdir().unwrap();
+        let settled_tx = Blake2bHash::from_data(b"settled");
+        let lost_tx = Blake2bHash::from_data(b"lost");
+        let queued_tx = Blake2bHash::from_data(b"queued");
+        {
+            let store = PipelineStore::new(MdbxChainStore::new(dir.path()).unwrap());
+            store.put_batch(&batch(b"pending")).await.unwrap();
+            store.put_batch(&batch(b"frozen")).await.unwrap();
+            store.delete_batch(&batch(b"frozen").batch_id).await.unwrap();
+            store.put_quarantined(&batch(b"flagged")).await.unwrap();
+            for proposal in [
+                proposal(b"open", SettlementStatus::Proposed, None),
+                proposal(b"settled", SettlementStatus::Finalized, Some(settled_tx)),
+                proposal(b"lost", SettlementStatus::Finalized, Some(lost_tx)),
+                proposal(b"queued", SettlementStatus::Finalized, Some(queued_tx)),
+            ] {
+                store.put_proposal(&proposal).await.unwrap();
+            }
+            store.put_stats(&PipelineStats { settlements_finalized: 3, ..Default::default() }).await.unwrap();
+            // Killed without a shutdown or sync
+        }
+
+        let store = PipelineStore::new(MdbxChainStore::new(dir.path()).unwrap());
+        let mut recovered = store.load().await.unwrap();
+        assert_eq!(recovered.pending_batches.iter().map(|batch| batch.batch_id).collect::<Vec<_>>(), vec![batch(b"pending").batch_id]);
+        assert_eq!(recovered.quarantined_batches.len(), 1);
+        assert_eq!(recovered.stats.settlements_finalized, 3);
+        assert_eq!(recovered.settlement_proposals.len(), 4);
+
+        let reconciliation = reconcile(
+            &mut recovered.settlement_proposals,
+            &HashSet::from([settled_tx]),
+            &HashSet::from([queued_tx]),
+        );
+        assert_eq!(reconciliation, Reconciliation {
+            settled: vec![Blake2bHash::from_data(b"settled")],
+            resubmit: vec![Blake2bHash::from_data(b"lost")],
+        });
+        assert_eq!(recovered.settlement_proposals.len(), 3);
+        let lost = recovered.settlement_proposals.iter().find(|proposal| proposal.proposal_id == Blake2bHash::from_data(b"lost")).unwrap();
+        assert!(matches!(lost.status, SettlementStatus::Accepted));
+        assert_eq!(lost.settlement_tx, None);
+    }
+}
@@ -0,0 +1,142 @@
+// Data retention: detailed CDR data is deleted once its retention period ends, settlement
+// summaries stay. Encrypted payloads of on-chain CDR records are purged from finalized blocks,
+// leaving stubs that hash as the records did, and frozen batches lose their records, leaving
+// their totals and the commitment to them on chain
+use std::time::Duration;
+use crate::blockchain::block::CDRType;
+use crate::primitives::{BlockchainError, Result, Timestamp};
+use super::BCEBatch;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// How often expired data is looked for
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(3_600);
+
+/// Days each type of record is kept, `None` keeping it indefinitely
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub voice_call_days: Option<u32>,
+    pub data_session_days: Option<u32>,
+    pub sms_days: Option<u32>,
+    pub roaming_days: Option<u32>,
+}
+
+impl std::str::FromStr for RetentionPolicy {
+    type Err = BlockchainError;
+
+    /// Comma-separated `<type>=<days>` with types voice, data, sms and roaming
+    fn from_str(s: &str) -> Result<Self> {
+        let mut policy = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let invalid = || BlockchainError::InvalidOperation(format!(
+                "Invalid retention {}. Use: voice=<days>,data=<days>,sms=<days>,roaming=<days>", entry
+            ));
+            let (record_type, days) = entry.split_once('=').ok_or_else(invalid)?;
+            let days = days.trim().parse::<u32>().ok().filter(|days| *days > 0).ok_or_else(invalid)?;
+            match record_type.trim() {
+                "voice" => policy.voice_call_days = Some(days),
+                "data" => policy.data_session_days = Some(days),
+                "sms" => policy.sms_days = Some(days),
+                "roaming" => policy.roaming_days = Some(days),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+impl RetentionPolicy {
+    pub fn days(&self, record_type: &CDRType) -> Option<u32> {
+        match record_type {
+            CDRType::VoiceCall => self.voice_call_days,
+            CDRType::DataSession => self.data_session_days,
+            CDRType::SMS => self.sms_days,
+            CDRType::Roaming => self.roaming_days,
+        }
+    }
+
+    /// Records of `record_type` older than this at `now` are expired
+    pub fn cutoff(&self, record_type: &CDRType, now: Timestamp) -> Option<Timestamp> {
+        self.days(record_type).map(|days| now.saturating_sub(days as u64 * SECONDS_PER_DAY))
+    }
+
+    /// Cutoff of every record type with a retention period
+    pub fn cutoffs(&self, now: Timestamp) -> Vec<(CDRType, Timestamp)> {
+        [CDRType::VoiceCall, CDRType::DataSession, CDRType::SMS, CDRType::Roaming]
+            .into_iter()
+            .filter_map(|record_type| self.cutoff(&record_type, now).map(|cutoff| (record_type, cutoff)))
+            .collect()
+    }
+
+    /// Whether every record of `batch` is expired. Disclosing a record takes the whole batch, so
+    /// batches are purged as a whole once their last record expires
+    pub fn batch_expired(&self, batch: &BCEBatch, now: Timestamp) -> bool {
+        !batch.records.is_empty() && batch.records.iter().all(|record| {
+            self.cutoff(&record.cdr_type(), now).is_some_and(|cutoff| record.timestamp < cutoff)
+        })
+    }
+}
+
+/// What a purge run deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PurgeReport {
+    pub payloads_purged: usize,
+    pub payload_bytes: u64,
+    pub batches_purged: usize,
+    pub records_purged: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bce_pipeline::BCERecord;
+    use crate::primitives::{Blake2bHash, NetworkId};
+
+    fn record(record_type: &str, timestamp: u64) -> BCERecord {
+        BCERecord {
+            record_id: format!("{}_{}", record_type, timestamp),
+            record_type: record_type.to_string(),
+            imsi: "262010123456789".to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: "23415".to_string(),
+            session_duration: 60,
+            bytes_uplink: 0,
+            bytes_downlink: 0,
+            wholesale_charge: 10,
+            retail_charge: 20,
+            currency: "EUR".to_string(),
+            timestamp,
+            charging_id: timestamp,
+        }
+    }
+
+    #[test]
+    fn test_retention_policy() {
+        let policy: RetentionPolicy = "voice=180, sms=90".parse().unwrap();
+        assert_eq!(policy, RetentionPolicy { voice_call_days: Some(180), sms_days: Some(90), ..Default::default() });
+        assert!("voice=0".parse::<RetentionPolicy>().is_err());
+        assert!("fax=30".parse::<RetentionPolicy>().is_err());
+
+        let now = 200 * SECONDS_PER_DAY;
+        assert_eq!(policy.cutoffs(now), vec![(CDRType::VoiceCall, 20 * SECONDS_PER_DAY), (CDRType::SMS, 110 * SECONDS_PER_DAY)]);
+        assert_eq!(policy.cutoff(&CDRType::DataSession, now), None);
+
+        let mut batch = BCEBatch {
+            batch_id: Blake2bHash::from_data(b"batch"),
+            home_network: NetworkId::new("T-Mobile", "DE"),
+            visited_network: NetworkId::new("Vodafone", "UK"),
+            records: vec![record("VOICE_CALL_CDR", 10 * SECONDS_PER_DAY), record("SMS_CDR", 100 * SECONDS_PER_DAY)],
+            period_start: 10 * SECONDS_PER_DAY,
+            period_end: 100 * SECONDS_PER_DAY,
+            total_charges_cents: 20,
+            service_breakdown: Default::default(),
+        };
+        assert!(policy.batch_expired(&batch, now));
+
+        // Data sessions are kept indefinitely, and with them their batch
+        batch.records.push(record("DATA_SESSION_CDR", SECONDS_PER_DAY));
+        assert!(!policy.batch_expired(&batch, now));
+        batch.records.clear();
+        assert!(!policy.batch_expired(&batch, now));
+    }
+}
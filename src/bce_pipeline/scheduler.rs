@@ -0,0 +1,285 @@
+// Pipeline task schedule: batch processing runs on wall clock boundaries of its interval and
+// settlement on a fixed interval or a cron schedule in UTC, each node offset by a stable jitter
+// so consortium nodes do not all hit the network at the same instant
+use std::time::Duration;
+use chrono::{Datelike, TimeZone, Timelike, Utc};
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+
+/// Tasks the processing loop runs on schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledTask {
+    ProcessBatches,
+    Settle,
+}
+
+/// When settlement runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettlementSchedule {
+    /// On wall clock multiples of the interval
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl std::str::FromStr for SettlementSchedule {
+    type Err = BlockchainError;
+
+    /// `<n>s`, `<n>m` or `<n>h` for an interval, a 5-field cron expression otherwise
+    fn from_str(s: &str) -> Result<Self> {
+        if s.contains(' ') {
+            return s.parse().map(SettlementSchedule::Cron);
+        }
+        let (amount, unit) = s.split_at(s.len().saturating_sub(1));
+        let seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            _ => 0,
+        };
+        amount.parse::<u64>().ok()
+            .filter(|amount| *amount > 0 && seconds > 0)
+            .map(|amount| SettlementSchedule::Every(Duration::from_secs(amount * seconds)))
+            .ok_or_else(|| BlockchainError::InvalidOperation(format!(
+                "Unknown settlement schedule: {}. Use: <n>s, <n>m, <n>h or a cron expression", s
+            )))
+    }
+}
+
+/// Schedule of the pipeline's periodic work
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineSchedule {
+    pub batch_interval: Duration,
+    pub settlement: SettlementSchedule,
+    /// Upper bound of the per-node offset added to every run
+    pub jitter: Duration,
+}
+
+impl Default for PipelineSchedule {
+    fn default() -> Self {
+        Self {
+            batch_interval: Duration::from_secs(30),
+            settlement: SettlementSchedule::Every(Duration::from_secs(60)),
+            jitter: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Cron expression of minute, hour, day of month, month and day of week (0 is Sunday)
+/// Fields take `*`, values, ranges `a-b`, lists `a,b` and steps `*/n` or `a-b/n`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days: Vec<u32>,
+    months: Vec<u32>,
+    weekdays: Vec<u32>,
+    /// Day of month and day of week restricted both, a day matching either runs
+    day_or_weekday: bool,
+}
+
+/// Days searched for the next run before a schedule like `0 0 31 2 *` is given up on
+const MAX_CRON_SEARCH_DAYS: i64 = 366 * 4;
+
+impl std::str::FromStr for CronSchedule {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(BlockchainError::InvalidOperation(format!("Cron expression {} does not have 5 fields", s)));
+        }
+        Ok(Self {
+            minutes: cron_field(fields[0], 0, 59)?,
+            hours: cron_field(fields[1], 0, 23)?,
+            days: cron_field(fields[2], 1, 31)?,
+            months: cron_field(fields[3], 1, 12)?,
+            // 7 is Sunday too
+            weekdays: {
+                let mut weekdays: Vec<u32> = cron_field(fields[4], 0, 7)?.into_iter().map(|day| day % 7).collect();
+                weekdays.sort_unstable();
+                weekdays.dedup();
+                weekdays
+            },
+            day_or_weekday: fields[2] != "*" && fields[4] != "*",
+        })
+    }
+}
+
+fn cron_field(field: &str, min: u32, max: u32) -> Result<Vec<u32>> {
+    let invalid = || BlockchainError::InvalidOperation(format!("Invalid cron field {}", field));
+    let mut values = Vec::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+    values.sort_unstable();
+    values.dedup();
+    Ok(values)
+}
+
+impl CronSchedule {
+    /// First matching minute strictly after `timestamp`, in seconds
+    pub fn next_after(&self, timestamp: u64) -> Option<u64> {
+        let start = Utc.timestamp_opt(timestamp as i64 / 60 * 60 + 60, 0).single()?;
+        let mut day = start.date_naive();
+
+        for _ in 0..MAX_CRON_SEARCH_DAYS {
+            if self.matches_day(day) {
+                let from = if day == start.date_naive() { (start.hour(), start.minute()) } else { (0, 0) };
+                let time = self.hours.iter()
+                    .flat_map(|hour| self.minutes.iter().map(move |minute| (*hour, *minute)))
+                    .find(|time| *time >= from);
+                if let Some((hour, minute)) = time {
+                    let run = day.and_hms_opt(hour, minute, 0)?.and_utc();
+                    return Some(run.timestamp() as u64);
+                }
+            }
+            day = day.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_day(&self, day: chrono::NaiveDate) -> bool {
+        if !self.months.contains(&day.month()) {
+            return false;
+        }
+        let day_matches = self.days.contains(&day.day());
+        let weekday_matches = self.weekdays.contains(&day.weekday().num_days_from_sunday());
+        if self.day_or_weekday { day_matches || weekday_matches } else { day_matches && weekday_matches }
+    }
+}
+
+/// Next run times of the scheduled tasks, in unix milliseconds
+#[derive(Debug, Clone)]
+pub struct TaskScheduler {
+    schedule: PipelineSchedule,
+    /// This node's share of the jitter
+    offset_ms: u64,
+    next_batches: u64,
+    next_settlement: u64,
+}
+
+impl TaskScheduler {
+    /// Scheduler for the node seeded with `node_seed`, which fixes its jitter offset
+    pub fn new(schedule: PipelineSchedule, node_seed: &[u8], now_ms: u64) -> Self {
+        let jitter_ms = schedule.jitter.as_millis() as u64;
+        let seed = Blake2bHash::from_data(node_seed);
+        let offset_ms = match jitter_ms {
+            0 => 0,
+            jitter_ms => u64::from_le_bytes(seed.as_bytes()[..8].try_into().expect("hash is 32 bytes")) % jitter_ms,
+        };
+        let mut scheduler = Self { schedule, offset_ms, next_batches: 0, next_settlement: 0 };
+        scheduler.next_batches = scheduler.next_run(ScheduledTask::ProcessBatches, now_ms);
+        scheduler.next_settlement = scheduler.next_run(ScheduledTask::Settle, now_ms);
+        scheduler
+    }
+
+    /// Task due first and when
+    pub fn next(&self) -> (ScheduledTask, u64) {
+        if self.next_settlement < self.next_batches {
+            (ScheduledTask::Settle, self.next_settlement)
+        } else {
+            (ScheduledTask::ProcessBatches, self.next_batches)
+        }
+    }
+
+    /// Record `task` as run at `now_ms`, runs missed meanwhile are skipped rather than caught up on
+    pub fn complete(&mut self, task: ScheduledTask, now_ms: u64) {
+        let next = self.next_run(task, now_ms);
+        match task {
+            ScheduledTask::ProcessBatches => self.next_batches = next,
+            ScheduledTask::Settle => self.next_settlement = next,
+        }
+    }
+
+    /// First run of `task` after `now_ms`
+    fn next_run(&self, task: ScheduledTask, now_ms: u64) -> u64 {
+        // The offset shifts the whole schedule, so compare against the unshifted time
+        let base = now_ms.saturating_sub(self.offset_ms);
+        let next = match (task, &self.schedule.settlement) {
+            (ScheduledTask::ProcessBatches, _) => next_multiple(base, self.schedule.batch_interval),
+            (ScheduledTask::Settle, SettlementSchedule::Every(interval)) => next_multiple(base, *interval),
+            (ScheduledTask::Settle, SettlementSchedule::Cron(cron)) => cron.next_after(base / 1_000)
+                .map_or(u64::MAX - self.offset_ms, |seconds| seconds * 1_000),
+        };
+        next + self.offset_ms
+    }
+}
+
+/// First multiple of `interval` after `time_ms`
+fn next_multiple(time_ms: u64, interval: Duration) -> u64 {
+    let interval_ms = (interval.as_millis() as u64).max(1);
+    (time_ms / interval_ms + 1) * interval_ms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap().timestamp() as u64
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        // 02:30 UTC on the 1st and 16th, as a semi-monthly settlement run
+        let cron: CronSchedule = "30 2 1,16 * *".parse().unwrap();
+        assert_eq!(cron.next_after(at(2024, 1, 1, 2, 29)), Some(at(2024, 1, 1, 2, 30)));
+        assert_eq!(cron.next_after(at(2024, 1, 1, 2, 30)), Some(at(2024, 1, 16, 2, 30)));
+        assert_eq!(cron.next_after(at(2024, 12, 20, 0, 0)), Some(at(2025, 1, 1, 2, 30)));
+
+        // Every 15 minutes on weekdays, 2024-01-06 is a Saturday
+        let cron: CronSchedule = "*/15 * * * 1-5".parse().unwrap();
+        assert_eq!(cron.next_after(at(2024, 1, 5, 23, 50)), Some(at(2024, 1, 8, 0, 0)));
+
+        assert!(cron.next_after(0).is_some());
+        assert!("0 0 31 2 *".parse::<CronSchedule>().unwrap().next_after(0).is_none());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("* * *".parse::<CronSchedule>().is_err());
+    }
+
+    #[test]
+    fn test_tasks_run_on_jittered_boundaries() {
+        let schedule = PipelineSchedule {
+            batch_interval: Duration::from_secs(30),
+            settlement: "1m".parse().unwrap(),
+            jitter: Duration::from_secs(5),
+        };
+        let now = 1_700_000_020_000;
+        let mut scheduler = TaskScheduler::new(schedule.clone(), b"node-a", now);
+        let offset = scheduler.offset_ms;
+        assert!(offset < 5_000);
+
+        // Batches first, on the next 30s boundary, then settlement on the minute
+        assert_eq!(scheduler.next(), (ScheduledTask::ProcessBatches, 1_700_000_040_000 + offset));
+        let (_, due) = scheduler.next();
+        scheduler.complete(ScheduledTask::ProcessBatches, due);
+        assert_eq!(scheduler.next(), (ScheduledTask::Settle, due));
+
+        // Runs do not drift when a task completes late, missed runs are skipped
+        scheduler.complete(ScheduledTask::ProcessBatches, due + 95_000);
+        assert_eq!(scheduler.next_batches, due + 110_000);
+
+        // Other nodes spread over the jitter window, each keeping its own offset
+        let offsets: std::collections::HashSet<u64> = (0..20u8)
+            .map(|node| TaskScheduler::new(schedule.clone(), &[node], now).offset_ms)
+            .collect();
+        assert!(offsets.len() > 1);
+    }
+}
@@ -0,0 +1,175 @@
+// Settlement periods: the billing cycles roaming usage is settled in,
+// closed automatically once their cutoff passes
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Length of a billing cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettlementCycle {
+    /// Fixed number of days counted from the Unix epoch
+    Days(u32),
+    /// The 1st to the 15th and the 16th to the end of each month (UTC), as in GSMA TAP periods
+    SemiMonthly,
+    /// Calendar months (UTC)
+    Monthly,
+}
+
+impl std::str::FromStr for SettlementCycle {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "semi-monthly" => Ok(SettlementCycle::SemiMonthly),
+            "monthly" => Ok(SettlementCycle::Monthly),
+            _ => s.strip_suffix('d')
+                .and_then(|days| days.parse().ok())
+                .filter(|days| *days > 0)
+                .map(SettlementCycle::Days)
+                .ok_or_else(|| BlockchainError::InvalidOperation(format!(
+                    "Unknown settlement cycle: {}. Use: <days>d, semi-monthly, monthly", s
+                ))),
+        }
+    }
+}
+
+fn utc(timestamp: u64) -> DateTime<Utc> {
+    Utc.timestamp_opt(timestamp as i64, 0).single().unwrap_or_default()
+}
+
+fn month_start(year: i32, month: u32) -> u64 {
+    let (year, month) = if month > 12 { (year + 1, 1) } else { (year, month) };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single().map_or(0, |date| date.timestamp() as u64)
+}
+
+/// One billing period, usage from `start` up to but excluding `cutoff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementPeriod {
+    pub start: u64,
+    pub cutoff: u64,
+}
+
+impl SettlementPeriod {
+    /// Period of `cycle` covering `timestamp`
+    pub fn containing(cycle: SettlementCycle, timestamp: u64) -> Self {
+        match cycle {
+            SettlementCycle::Days(days) => {
+                let length = days.max(1) as u64 * SECONDS_PER_DAY;
+                let start = timestamp - timestamp % length;
+                Self { start, cutoff: start + length }
+            }
+            SettlementCycle::SemiMonthly => {
+                let date = utc(timestamp);
+                let first = month_start(date.year(), date.month());
+                let sixteenth = first + 15 * SECONDS_PER_DAY;
+                if timestamp < sixteenth {
+                    Self { start: first, cutoff: sixteenth }
+                } else {
+                    Self { start: sixteenth, cutoff: month_start(date.year(), date.month() + 1) }
+                }
+            }
+            SettlementCycle::Monthly => {
+                let date = utc(timestamp);
+                Self {
+                    start: month_start(date.year(), date.month()),
+                    cutoff: month_start(date.year(), date.month() + 1),
+                }
+            }
+        }
+    }
+
+    /// Period following this one
+    pub fn next(&self, cycle: SettlementCycle) -> Self {
+        Self::containing(cycle, self.cutoff)
+    }
+
+    pub fn contains(&self, timestamp: u64) -> bool {
+        timestamp >= self.start && timestamp < self.cutoff
+    }
+
+    /// Identifier settlements of the period are recorded under, e.g. `2024-01-01/2024-01-16`
+    pub fn id(&self) -> String {
+        format!("{}/{}", utc(self.start).format("%Y-%m-%d"), utc(self.cutoff).format("%Y-%m-%d"))
+    }
+
+    pub fn period_hash(&self) -> Blake2bHash {
+        Blake2bHash::from_data(format!("settlement-period:{}:{}", self.start, self.cutoff).as_bytes())
+    }
+}
+
+/// Tracks the open settlement period and hands out periods as their cutoff passes
+#[derive(Debug, Clone)]
+pub struct SettlementPeriodScheduler {
+    cycle: SettlementCycle,
+    current: SettlementPeriod,
+}
+
+impl SettlementPeriodScheduler {
+    pub fn new(cycle: SettlementCycle, now: u64) -> Self {
+        Self { cycle, current: SettlementPeriod::containing(cycle, now) }
+    }
+
+    pub fn cycle(&self) -> SettlementCycle {
+        self.cycle
+    }
+
+    /// Period usage is currently settled in
+    pub fn current(&self) -> &SettlementPeriod {
+        &self.current
+    }
+
+    /// Periods whose cutoff passed by `now`, oldest first; the open period moves past them
+    pub fn close_due(&mut self, now: u64) -> Vec<SettlementPeriod> {
+        let mut closed = Vec::new();
+        while now >= self.current.cutoff {
+            closed.push(self.current);
+            self.current = self.current.next(self.cycle);
+        }
+        closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_cutoffs() {
+        // 2024-01-10 12:00 UTC
+        let jan_10 = 1_704_888_000;
+
+        let semi = SettlementPeriod::containing(SettlementCycle::SemiMonthly, jan_10);
+        assert_eq!(semi.id(), "2024-01-01/2024-01-16");
+        assert_eq!(semi.next(SettlementCycle::SemiMonthly).id(), "2024-01-16/2024-02-01");
+
+        let month = SettlementPeriod::containing(SettlementCycle::Monthly, jan_10);
+        assert_eq!(month.id(), "2024-01-01/2024-02-01");
+        let december = SettlementPeriod::containing(SettlementCycle::Monthly, 1_734_000_000);
+        assert_eq!(december.id(), "2024-12-01/2025-01-01");
+
+        let fifteen_days = SettlementPeriod::containing(SettlementCycle::Days(15), jan_10);
+        assert!(fifteen_days.contains(jan_10));
+        assert_eq!(fifteen_days.cutoff - fifteen_days.start, 15 * SECONDS_PER_DAY);
+
+        assert_eq!("15d".parse::<SettlementCycle>().unwrap(), SettlementCycle::Days(15));
+        assert_eq!("semi-monthly".parse::<SettlementCycle>().unwrap(), SettlementCycle::SemiMonthly);
+        assert!("0d".parse::<SettlementCycle>().is_err());
+        assert!("weekly".parse::<SettlementCycle>().is_err());
+    }
+
+    #[test]
+    fn test_scheduler_closes_due_periods() {
+        let jan_10 = 1_704_888_000;
+        let mut scheduler = SettlementPeriodScheduler::new(SettlementCycle::SemiMonthly, jan_10);
+        assert!(scheduler.close_due(jan_10 + SECONDS_PER_DAY).is_empty());
+
+        // A node that was down over two cutoffs closes both periods in order
+        let feb_3 = jan_10 + 24 * SECONDS_PER_DAY;
+        let closed = scheduler.close_due(feb_3);
+        assert_eq!(closed.iter().map(SettlementPeriod::id).collect::<Vec<_>>(),
+                   vec!["2024-01-01/2024-01-16", "2024-01-16/2024-02-01"]);
+        assert_eq!(scheduler.current().id(), "2024-02-01/2024-02-16");
+    }
+}
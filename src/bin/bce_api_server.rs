@@ -3,12 +3,12 @@
 
 use sp_cdr_reconciliation_bc::{
     bce_pipeline::*,
-    api::bce_ingestion::*,
+    api::{bce_ingestion::*, ApiTokens},
     primitives::primitives::NetworkId,
 };
 use std::{sync::Arc, path::PathBuf};
 use tokio::sync::Mutex;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -22,6 +22,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let api_port = 9090;
     let network_port = 9000;
     let keys_dir = PathBuf::from("./api_zkp_keys");
+    // Finance operators approving settlements above the auto-accept threshold
+    let tokens_file = PathBuf::from("./api_tokens.json");
 
     // Create BCE pipeline configuration
     let config = PipelineConfig {
@@ -31,6 +33,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         auto_accept_threshold_cents: 50000, // €500 auto-accept
         enable_triangular_netting: true,
         is_bootstrap: true,
+        bootnodes: vec![],
+        pruning_mode: sp_cdr_reconciliation_bc::storage::PruningMode::Archive,
+        settlement_cycle: sp_cdr_reconciliation_bc::bce_pipeline::settlement_period::SettlementCycle::Days(15),
+        failover: None,
+        ingest_limits: sp_cdr_reconciliation_bc::bce_pipeline::ingest_queue::IngestLimits::default(),
+        schedule: sp_cdr_reconciliation_bc::bce_pipeline::scheduler::PipelineSchedule::default(),
+        key_fetch: sp_cdr_reconciliation_bc::network::KeyFetchConfig::default(),
+        ceremony_participants: vec![],
+        role: sp_cdr_reconciliation_bc::bce_pipeline::NodeRole::Full,
+        connection_limits: sp_cdr_reconciliation_bc::network::ConnectionLimits::default(),
+        network: sp_cdr_reconciliation_bc::network::NetworkConfig::default(),
+        approval_window: sp_cdr_reconciliation_bc::bce_pipeline::approvals::DEFAULT_APPROVAL_WINDOW,
+        bridge: None,
+        retention: None,
+        tenancy: None,
+        bank_accounts: None,
     };
 
     // Initialize BCE pipeline (simplified for API server)
@@ -49,7 +67,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let pipeline = Arc::new(Mutex::new(pipeline));
 
     // Create and start BCE ingestion API
-    let api_server = BCEIngestAPI::new(pipeline.clone(), api_port);
+    let mut api_server = BCEIngestAPI::new(pipeline.clone(), api_port);
+    if tokens_file.exists() {
+        api_server = api_server.with_api_tokens(ApiTokens::load(&tokens_file)?);
+        info!("🔑 Finance operator tokens loaded from {}", tokens_file.display());
+    } else {
+        warn!("⚠️  No {} - settlement approval endpoints refuse every request", tokens_file.display());
+    }
 
     // Print curl examples for testing
     print_curl_examples(api_port);
@@ -57,10 +81,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("🌐 Starting BCE API server on port {}...", api_port);
     info!("📡 Ready to receive BCE records from operator billing systems");
 
-    // Start the API server (this will run indefinitely)
-    if let Err(e) = api_server.start().await {
-        error!("❌ Failed to start BCE API server: {:?}", e);
-        return Err(e);
+    // Run the API server until Ctrl+C, then flush in-flight batches before exiting
+    tokio::select! {
+        result = api_server.start() => {
+            if let Err(e) = result {
+                error!("❌ Failed to start BCE API server: {:?}", e);
+                return Err(e);
+            }
+        }
+        _ = tokio::signal::ctrl_c() => {
+            info!("Shutdown signal received...");
+            pipeline.lock().await.shutdown().await?;
+        }
     }
 
     Ok(())
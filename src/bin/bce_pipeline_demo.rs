@@ -23,6 +23,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         auto_accept_threshold_cents: 5000, // €50 auto-accept
         enable_triangular_netting: true,
         is_bootstrap: true, // Demo runs as bootstrap node
+        bootnodes: vec![],
+        pruning_mode: sp_cdr_reconciliation_bc::storage::PruningMode::Archive,
+        settlement_cycle: sp_cdr_reconciliation_bc::bce_pipeline::settlement_period::SettlementCycle::Days(15),
+        failover: None,
+        ingest_limits: sp_cdr_reconciliation_bc::bce_pipeline::ingest_queue::IngestLimits::default(),
+        schedule: sp_cdr_reconciliation_bc::bce_pipeline::scheduler::PipelineSchedule::default(),
+        key_fetch: sp_cdr_reconciliation_bc::network::KeyFetchConfig::default(),
+        ceremony_participants: vec![],
+        role: sp_cdr_reconciliation_bc::bce_pipeline::NodeRole::Full,
+        connection_limits: sp_cdr_reconciliation_bc::network::ConnectionLimits::default(),
+        network: sp_cdr_reconciliation_bc::network::NetworkConfig::default(),
+        approval_window: sp_cdr_reconciliation_bc::bce_pipeline::approvals::DEFAULT_APPROVAL_WINDOW,
+        bridge: None,
+        retention: None,
+        tenancy: None,
+        bank_accounts: None,
     };
 
     // Simulate T-Mobile DE operator
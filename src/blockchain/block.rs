@@ -1,6 +1,8 @@
 // Block structures following Albatross patterns
 use serde::{Deserialize, Serialize};
-use crate::primitives::{Blake2bHash, Height, Timestamp, NetworkId, hash_json};
+use crate::primitives::{Blake2bHash, BlockchainError, Height, Timestamp, NetworkId, Policy, Result, hash_canonical};
+use crate::crypto::{BLSPrivateKey, BLSPublicKey, BLSSignature, Signer};
+use super::light_client::MacroCertificate;
 
 /// Block types following Albatross micro/macro pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,8 +14,8 @@ pub enum Block {
 impl Block {
     pub fn hash(&self) -> Blake2bHash {
         match self {
-            Block::Micro(block) => hash_json(&block.header),
-            Block::Macro(block) => hash_json(&block.header),
+            Block::Micro(block) => hash_canonical(&block.header),
+            Block::Macro(block) => hash_canonical(&block.header),
         }
     }
 
@@ -48,6 +50,18 @@ impl Block {
     pub fn height(&self) -> Height {
         self.block_number()
     }
+
+    pub fn state_root(&self) -> &Blake2bHash {
+        match self {
+            Block::Micro(block) => &block.header.state_root,
+            Block::Macro(block) => &block.header.state_root,
+        }
+    }
+
+    /// Gas reserved by the block's transactions, at most `Policy::BLOCK_GAS_LIMIT` in a valid block
+    pub fn gas_limit(&self) -> u64 {
+        self.transactions().iter().map(Transaction::gas_limit).sum()
+    }
 }
 
 /// Micro block for CDR transactions (following Albatross micro blocks)
@@ -81,6 +95,9 @@ pub struct MicroBody {
 pub struct MacroBlock {
     pub header: MacroHeader,
     pub body: MacroBody,
+    /// Aggregated precommits finalizing the block, attached once it is final and not part of its hash
+    #[serde(default)]
+    pub justification: Option<MacroCertificate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,12 +131,21 @@ pub struct Transaction {
     pub recipient: Blake2bHash,
     pub value: u64,
     pub fee: u64,
+    /// Position in the sender's transaction sequence, equal to its account nonce when applied
+    pub nonce: u64,
     pub validity_start_height: Height,
     pub data: TransactionData,
     pub signature: Vec<u8>,
     pub signature_proof: Vec<u8>,
 }
 
+/// Address of the account a BLS key signs for
+pub fn account_address(public_key: &BLSPublicKey) -> Blake2bHash {
+    let mut data = b"sp-cdr-account".to_vec();
+    data.extend_from_slice(public_key.to_bytes());
+    Blake2bHash::from_data(&data)
+}
+
 /// CDR-specific transaction data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransactionData {
@@ -127,6 +153,43 @@ pub enum TransactionData {
     CDRRecord(CDRTransaction),
     Settlement(SettlementTransaction),
     ValidatorUpdate(ValidatorTransaction),
+    /// Aggregated ZK proofs, verified with one multi-pairing per aggregate
+    ProofAggregate(crate::zkp::aggregation::AggregateProof),
+    /// New code for a deployed contract, signed by its counterpart operators
+    ContractUpgrade(crate::smart_contracts::ContractUpgrade),
+    /// Inter-operator tariff published by the visited network
+    RateTable(super::tariff::SignedRateTable),
+    /// Admission of an operator to the registry, or an update of its PLMN codes and keys
+    OperatorRegistration(super::operator_registry::OperatorRegistration),
+    /// Quarantine of a BCE batch flagged by fraud detection, or its release after review
+    FraudFlag(FraudFlagTransaction),
+    /// Merkle root over the records of a frozen BCE batch, for later selective disclosure
+    BatchCommitment(BatchCommitmentTransaction),
+    /// Close of a settlement period, only valid in macro blocks
+    PeriodClose(PeriodCloseTransaction),
+    /// Fees of an election period paid to its validators, first transaction of every election block
+    RewardPayout(RewardPayoutTransaction),
+    /// Parameter-change proposal or vote of an elected validator
+    Governance(super::governance::GovernanceTransaction),
+    /// Application of a new operator, admitted to the registry once the validators accept it
+    NetworkJoin(super::transaction::NetworkJoinTransaction),
+    /// Settlement finalized on another consortium's chain, an obligation on this one once its proof checks out
+    BridgedSettlement(crate::bridge::BridgedSettlementTransaction),
+    /// CDR record whose encrypted payload was deleted at the end of its retention period, only
+    /// found in stored blocks
+    PurgedCDRRecord(PurgedCDRTransaction),
+    /// Expired contract storage slot restored with its archival proof
+    StateResurrection(crate::smart_contracts::StateResurrection),
+    /// Merkle root, count and total of the CDR records a network pair accumulated in a period
+    CDRCommitment(super::cdr_commitment::CDRCommitmentTransaction),
+    /// Demand to reveal one record of a CDR commitment
+    CommitmentChallenge(super::cdr_commitment::CommitmentChallengeTransaction),
+    /// Challenged record revealed by its committer with its inclusion proof
+    CommitmentResponse(super::cdr_commitment::CommitmentResponseTransaction),
+    /// Hash of the signed payment document a settlement's coordinator issued for its banking gateway
+    PaymentDocument(PaymentDocumentTransaction),
+    /// Settlement obligation locked in the built-in escrow contract, or the confirmation of its payment
+    Escrow(crate::smart_contracts::EscrowTransaction),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,7 +201,20 @@ pub struct CDRTransaction {
     pub zk_proof: Vec<u8>, // Zero-knowledge proof
 }
 
+/// What is left of a CDR record once its payload is purged, hashing as the record did
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgedCDRTransaction {
+    /// Hash the record was included under
+    pub transaction_hash: Blake2bHash,
+    pub record_type: CDRType,
+    pub home_network: String,
+    pub visited_network: String,
+    /// Size of the encrypted payload and proof removed
+    pub purged_bytes: u64,
+    pub purged_at: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CDRType {
     VoiceCall,
     DataSession, 
@@ -153,6 +229,77 @@ pub struct SettlementTransaction {
     pub amount: u64,
     pub currency: String,
     pub period: String,
+    /// Voice, data and SMS split of `amount`
+    #[serde(default)]
+    pub breakdown: super::tariff::ServiceBreakdown,
+    /// BCE batches the settlement covers, each one is settled once
+    #[serde(default)]
+    pub batch_ids: Vec<Blake2bHash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FraudFlagTransaction {
+    pub batch_id: Blake2bHash,
+    pub home_network: String,
+    pub visited_network: String,
+    pub score: u32,
+    pub reasons: Vec<String>,
+    /// `true` quarantines the batch, `false` releases it
+    pub quarantine: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchCommitmentTransaction {
+    pub batch_id: Blake2bHash,
+    pub home_network: String,
+    pub visited_network: String,
+    pub record_count: u32,
+    pub merkle_root: Blake2bHash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentDocumentTransaction {
+    pub settlement_tx: Blake2bHash,
+    /// ISO 20022 message definition of the document, e.g. `pain.001.001.03`
+    pub schema: String,
+    pub document_hash: Blake2bHash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodCloseTransaction {
+    /// Period identifier, e.g. `2024-01-01/2024-01-16`
+    pub period: String,
+    pub start: Timestamp,
+    pub cutoff: Timestamp,
+    /// Batches settled in the period, no longer open to new records
+    pub frozen_batches: Vec<Blake2bHash>,
+    /// Network pairs the period closed with a non-zero balance
+    pub balances: Vec<PeriodBalance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PeriodBalance {
+    pub home_network: String,
+    pub visited_network: String,
+    pub amount_cents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardPayoutTransaction {
+    pub election_block: Height,
+    /// Fees accrued since the previous payout, left-over rounding stays in the pool
+    pub pool: u64,
+    pub payouts: Vec<RewardPayout>,
+}
+
+/// Reward of one validator, proportional to the macro blocks it helped finalize
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardPayout {
+    pub validator: Blake2bHash,
+    pub reward_address: Blake2bHash,
+    /// Macro blocks of the period whose certificate included the validator's precommit
+    pub participation: u64,
+    pub amount: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -162,12 +309,23 @@ pub struct ValidatorTransaction {
     pub stake: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidatorAction {
     CreateValidator,
     UpdateValidator,
     DeactivateValidator,
     ReactivateValidator,
+    /// Bond `stake` to the validator, counted from the next election block
+    Bond,
+    /// Start unbonding `stake`, released after `Policy::UNBONDING_PERIOD`
+    Unbond,
+    /// Announce a new BLS signing key, taking effect at the next election block
+    RotateSigningKey {
+        /// Compressed BLS public key
+        new_signing_key: Vec<u8>,
+        /// Signature by the new key over `key_rotation_message`
+        proof_of_possession: Vec<u8>,
+    },
 }
 
 /// Validator info following Albatross patterns
@@ -180,15 +338,205 @@ pub struct ValidatorInfo {
     pub signal_data: Option<Vec<u8>>,
     pub inactive_from: Option<Height>,
     pub jailed_from: Option<Height>,
+    /// Stake bonded when the validator was elected, its voting power for the epoch
+    #[serde(default)]
+    pub stake: u64,
 }
 
 impl Transaction {
     pub fn hash(&self) -> Blake2bHash {
-        hash_json(self)
+        match &self.data {
+            // Block bodies and batch roots keep committing to the record as it was included
+            TransactionData::PurgedCDRRecord(purged) => purged.transaction_hash,
+            _ => hash_canonical(self),
+        }
+    }
+
+    /// Copy of a CDR record without its encrypted payload and proof, `None` for other transactions
+    pub fn purge_payload(&self, purged_at: Timestamp) -> Option<Transaction> {
+        let TransactionData::CDRRecord(cdr) = &self.data else {
+            return None;
+        };
+        Some(Transaction {
+            data: TransactionData::PurgedCDRRecord(PurgedCDRTransaction {
+                transaction_hash: self.hash(),
+                record_type: cdr.record_type.clone(),
+                home_network: cdr.home_network.clone(),
+                visited_network: cdr.visited_network.clone(),
+                purged_bytes: (cdr.encrypted_data.len() + cdr.zk_proof.len()) as u64,
+                purged_at,
+            }),
+            signature: vec![],
+            signature_proof: vec![],
+            ..self.clone()
+        })
     }
     
     pub fn is_valid(&self) -> bool {
         // Basic validation
         !self.signature.is_empty() && self.fee > 0
     }
-}
\ No newline at end of file
+
+    /// Gas the transaction may spend in contract execution, zero if it executes no contract
+    pub fn gas_limit(&self) -> u64 {
+        match self.data {
+            TransactionData::CDRRecord(_) => Policy::CDR_TRANSACTION_GAS_LIMIT,
+            TransactionData::Settlement(_) => Policy::SETTLEMENT_TRANSACTION_GAS_LIMIT,
+            _ => 0,
+        }
+    }
+
+    /// Whether the transaction is created by the block producer rather than sent by an account
+    /// System transactions carry no nonce and no signature
+    pub fn is_system(&self) -> bool {
+        self.sender == Blake2bHash::zero()
+    }
+
+    /// Bytes the sender signs, every field but the signature ones
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let fields = (&self.sender, &self.recipient, self.value, self.fee, self.nonce, self.validity_start_height, &self.data);
+        let mut payload = b"sp-cdr-transaction".to_vec();
+        payload.extend_from_slice(hash_canonical(&fields).as_bytes());
+        payload
+    }
+
+    /// Sign as the account of `key`, which becomes the sender
+    /// The signature proof carries the public key the sender address is derived from
+    pub fn sign(&mut self, key: &BLSPrivateKey) -> Result<()> {
+        let public_key = key.public_key();
+        self.sender = account_address(&public_key);
+        self.signature = key.sign(&self.signing_payload())?.to_bytes().to_vec();
+        self.signature_proof = public_key.to_bytes().to_vec();
+        Ok(())
+    }
+
+    /// Sign as the account of `signer`, wherever it keeps its key
+    pub async fn sign_with(&mut self, signer: &dyn Signer) -> Result<()> {
+        let public_key = signer.public_key();
+        self.sender = account_address(&public_key);
+        self.signature = signer.sign(&self.signing_payload()).await?.to_bytes().to_vec();
+        self.signature_proof = public_key.to_bytes().to_vec();
+        Ok(())
+    }
+
+    /// Check the transaction is signed by the account it is sent from
+    /// Only reward payouts are sent by the system, unsigned
+    pub fn verify_signature(&self) -> Result<()> {
+        // A purged record claims the hash of a record it no longer carries
+        if let TransactionData::PurgedCDRRecord(_) = self.data {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} is a purged CDR record", self.hash())));
+        }
+        if self.is_system() {
+            return match self.data {
+                TransactionData::RewardPayout(_) if self.signature.is_empty() => Ok(()),
+                _ => Err(BlockchainError::InvalidTransaction(format!("Transaction {} is sent by the system", self.hash()))),
+            };
+        }
+        if self.signature.is_empty() {
+            return Err(BlockchainError::InvalidTransaction(format!("Transaction {} is not signed", self.hash())));
+        }
+        let public_key = BLSPublicKey::from_bytes(&self.signature_proof)?;
+        if account_address(&public_key) != self.sender {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Transaction {} is signed by another account than {}", self.hash(), self.sender
+            )));
+        }
+        if !BLSSignature::from_bytes(&self.signature)?.verify(&public_key, &self.signing_payload())? {
+            return Err(BlockchainError::InvalidTransaction(format!("Invalid signature on transaction {}", self.hash())));
+        }
+        Ok(())
+    }
+
+    /// Whether the transaction runs through the contract VM when its block executes
+    pub fn executes_contract(&self) -> bool {
+        matches!(self.data,
+            TransactionData::CDRRecord(_) | TransactionData::Settlement(_)
+            | TransactionData::ContractUpgrade(_) | TransactionData::RateTable(_)
+            | TransactionData::OperatorRegistration(_) | TransactionData::StateResurrection(_)
+            | TransactionData::Escrow(_))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settlement() -> Transaction {
+        Transaction {
+            sender: Blake2bHash::zero(),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 12_500,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Settlement(SettlementTransaction {
+                creditor_network: "T-Mobile-DE".to_string(),
+                debtor_network: "Vodafone-UK".to_string(),
+                amount: 12_500,
+                currency: "EUR".to_string(),
+                period: "2024-01".to_string(),
+                breakdown: Default::default(),
+                batch_ids: vec![],
+            }),
+            signature: vec![],
+            signature_proof: vec![],
+        }
+    }
+
+    #[test]
+    fn test_transaction_signature() {
+        let key = BLSPrivateKey::generate().unwrap();
+        let mut transaction = settlement();
+        assert!(transaction.verify_signature().is_err());
+        transaction.sign(&key).unwrap();
+        assert_eq!(transaction.sender, account_address(&key.public_key()));
+        transaction.verify_signature().unwrap();
+
+        // Every field but the signature ones is covered
+        let mut tampered = transaction.clone();
+        tampered.value += 1;
+        assert!(tampered.verify_signature().is_err());
+        let mut tampered = transaction.clone();
+        if let TransactionData::Settlement(settlement) = &mut tampered.data {
+            settlement.amount += 1;
+        }
+        assert!(tampered.verify_signature().is_err());
+
+        // Signed by another key than the sender's account, with or without its public key
+        let other = BLSPrivateKey::generate().unwrap();
+        let mut wrong_key = transaction.clone();
+        wrong_key.signature = other.sign(&transaction.signing_payload()).unwrap().to_bytes().to_vec();
+        assert!(wrong_key.verify_signature().is_err());
+        wrong_key.signature_proof = other.public_key().to_bytes().to_vec();
+        assert!(wrong_key.verify_signature().is_err());
+
+        // Only reward payouts are sent by the system
+        let mut system = settlement();
+        system.signature = transaction.signature.clone();
+        assert!(system.verify_signature().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_signed_by_hosted_signer() {
+        let own_key = BLSPrivateKey::generate().unwrap();
+        let hosted: std::sync::Arc<dyn Signer> = std::sync::Arc::new(BLSPrivateKey::generate().unwrap());
+
+        // A hosted identity sends from the account of its own key, not the node's
+        let mut transaction = settlement();
+        transaction.sign_with(hosted.as_ref()).await.unwrap();
+        assert_eq!(transaction.sender, account_address(&hosted.public_key()));
+        assert_ne!(transaction.sender, account_address(&own_key.public_key()));
+        transaction.verify_signature().unwrap();
+
+        let mut tampered = transaction.clone();
+        tampered.nonce += 1;
+        assert!(tampered.verify_signature().is_err());
+
+        // Signing through the trait and with the key directly agree
+        let mut direct = settlement();
+        direct.sign(&own_key).unwrap();
+        let mut through_signer = settlement();
+        through_signer.sign_with(&own_key).await.unwrap();
+        assert_eq!(direct.signature, through_signer.signature);
+        assert_eq!(direct.sender, through_signer.sender);
+    }
+}
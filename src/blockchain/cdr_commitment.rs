@@ -0,0 +1,97 @@
+// Aggregated CDR commitments: instead of one transaction per record, an operator commits the
+// records of a network pair in a settlement period as one Merkle root with their count and total.
+// Any account may challenge a committed record; the committer answers by revealing that record on
+// chain with its inclusion proof within the response window, or the commitment becomes contested
+use serde::{Deserialize, Serialize};
+
+use crate::bce_pipeline::{commitment::record_leaf, BCERecord};
+use crate::primitives::{hash_canonical, Blake2bHash, BlockchainError, Height, Result};
+use super::light_client::MerkleProof;
+
+/// Records of a network pair accumulated in a settlement period, committed as one Merkle root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CDRCommitmentTransaction {
+    /// Period identifier, e.g. `2024-01-01/2024-01-16`
+    pub period: String,
+    pub home_network: String,
+    pub visited_network: String,
+    pub record_count: u32,
+    /// Wholesale charges of the committed records
+    pub total_charges_cents: u64,
+    /// Root over the record leaves, in the order they were accumulated
+    pub merkle_root: Blake2bHash,
+    /// ZK privacy proofs covering the records, one per proven batch
+    pub zk_proofs: Vec<Vec<u8>>,
+}
+
+impl CDRCommitmentTransaction {
+    /// Identifier challenges and responses refer to the commitment by
+    pub fn commitment_id(&self) -> Blake2bHash {
+        hash_canonical(&(&self.period, &self.home_network, &self.visited_network, &self.merkle_root))
+    }
+}
+
+/// Demand that the committer reveal one committed record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentChallengeTransaction {
+    pub commitment_id: Blake2bHash,
+    pub record_index: u32,
+}
+
+/// Challenged record revealed with its proof of inclusion in the commitment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentResponseTransaction {
+    pub commitment_id: Blake2bHash,
+    pub record: BCERecord,
+    /// Inclusion proof, its index is the challenged record's
+    pub proof: MerkleProof,
+}
+
+impl CommitmentResponseTransaction {
+    pub fn record_index(&self) -> u32 {
+        self.proof.index
+    }
+
+    /// Check the record is committed to at its index under `root`
+    pub fn verify(&self, root: &Blake2bHash) -> Result<()> {
+        match self.proof.root(&record_leaf(&self.record)) {
+            Some(proven) if proven == *root => Ok(()),
+            _ => Err(BlockchainError::InvalidTransaction(format!(
+                "Record {} is not committed to at index {} of commitment {}",
+                self.record.record_id, self.record_index(), self.commitment_id
+            ))),
+        }
+    }
+}
+
+/// Whether a commitment stood up to its challenges so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitmentStatus {
+    Committed,
+    /// A challenge went unanswered, the commitment's records cannot be relied on
+    Contested,
+}
+
+/// Commitment as recorded in the state trie
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentRecord {
+    /// Account that committed, the only one that may answer challenges
+    pub committer: Blake2bHash,
+    pub period: String,
+    pub home_network: String,
+    pub visited_network: String,
+    pub record_count: u32,
+    pub total_charges_cents: u64,
+    pub merkle_root: Blake2bHash,
+    pub status: CommitmentStatus,
+}
+
+/// Challenge waiting for the committer's response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenChallenge {
+    pub commitment_id: Blake2bHash,
+    pub record_index: u32,
+    pub challenger: Blake2bHash,
+    /// Last block the response may be included in
+    pub respond_by: Height,
+}
@@ -0,0 +1,194 @@
+// Transaction fee market: every sent transaction pays at least a minimum fee for its size and the
+// gas it reserves, block producers take the best paying transactions first and the fees accrue to
+// the validator reward pool, paid out in the next election block
+use std::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{BlockchainError, Policy, Result, to_canonical_bytes};
+use super::block::Transaction;
+
+/// Bytes a transaction is charged for: its signed fields, signatures have a fixed size
+pub fn charged_size(transaction: &Transaction) -> u64 {
+    let fields = (
+        &transaction.sender, &transaction.recipient, transaction.value, transaction.fee,
+        transaction.nonce, transaction.validity_start_height, &transaction.data,
+    );
+    to_canonical_bytes(&fields).expect("transactions have a canonical encoding").len() as u64
+}
+
+/// Least fee a transaction is included with, zero for system transactions
+pub fn min_fee(transaction: &Transaction) -> u64 {
+    if transaction.is_system() {
+        return 0;
+    }
+    (charged_size(transaction) * Policy::MIN_FEE_PER_KILOBYTE).div_ceil(1_000)
+        + (transaction.gas_limit() * Policy::MIN_FEE_PER_MEGAGAS).div_ceil(1_000_000)
+}
+
+/// Refuse a sent transaction paying less than its minimum fee
+pub fn check_fee(transaction: &Transaction) -> Result<()> {
+    let required = min_fee(transaction);
+    if transaction.fee < required {
+        return Err(BlockchainError::InvalidTransaction(format!(
+            "Transaction {} pays fee {}, at least {} required", transaction.hash(), transaction.fee, required
+        )));
+    }
+    Ok(())
+}
+
+/// Fee a transaction pays relative to its minimum, how block producers rank transactions
+/// System transactions rank above every sent one
+#[derive(Debug, Clone, Copy)]
+pub struct FeeRate {
+    pub fee: u64,
+    pub min_fee: u64,
+}
+
+impl FeeRate {
+    pub fn of(transaction: &Transaction) -> Self {
+        Self { fee: transaction.fee, min_fee: min_fee(transaction) }
+    }
+
+    /// Least fee a transaction with minimum fee `min_fee` ranks strictly above this rate with
+    fn outbid(&self, min_fee: u64) -> u64 {
+        (self.fee as u128 * min_fee as u128 / self.min_fee.max(1) as u128) as u64 + 1
+    }
+}
+
+impl Ord for FeeRate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.min_fee, other.min_fee) {
+            (0, 0) => Ordering::Equal,
+            (0, _) => Ordering::Greater,
+            (_, 0) => Ordering::Less,
+            _ => (self.fee as u128 * other.min_fee as u128).cmp(&(other.fee as u128 * self.min_fee as u128)),
+        }
+    }
+}
+
+impl PartialOrd for FeeRate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FeeRate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FeeRate {}
+
+/// Fee a transaction should pay to make the next block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    pub min_fee: u64,
+    /// Outbids the queued transactions that would otherwise fill the next block's gas
+    pub suggested_fee: u64,
+    pub pending_transactions: usize,
+    pub pending_gas: u64,
+    pub block_gas_limit: u64,
+}
+
+/// Estimate the fee of `transaction` with `pending` queued for blocks of `block_gas_limit` gas
+/// Only gas is scarce in a block, transactions reserving none always fit at their minimum fee
+pub fn estimate_fee(transaction: &Transaction, pending: &[Transaction], block_gas_limit: u64) -> FeeEstimate {
+    let min_fee = min_fee(transaction);
+    let pending_gas = pending.iter().map(Transaction::gas_limit).sum();
+
+    let mut suggested_fee = min_fee;
+    let gas = transaction.gas_limit();
+    if gas > 0 {
+        let mut ranked: Vec<(FeeRate, u64)> = pending.iter()
+            .filter(|queued| queued.gas_limit() > 0)
+            .map(|queued| (FeeRate::of(queued), queued.gas_limit()))
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0));
+
+        // The first queued transaction that would push this one out of the block has to be outbid
+        let mut reserved = gas;
+        for (rate, queued_gas) in ranked {
+            reserved += queued_gas;
+            if reserved > block_gas_limit {
+                suggested_fee = suggested_fee.max(rate.outbid(min_fee));
+                break;
+            }
+        }
+    }
+
+    FeeEstimate {
+        min_fee,
+        suggested_fee,
+        pending_transactions: pending.len(),
+        pending_gas,
+        block_gas_limit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::block::{SettlementTransaction, TransactionData};
+    use crate::primitives::Blake2bHash;
+
+    fn settlement(fee: u64) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"T-Mobile-DE"),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 12_500,
+            fee,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Settlement(SettlementTransaction {
+                creditor_network: "T-Mobile-DE".to_string(),
+                debtor_network: "Vodafone-UK".to_string(),
+                amount: 12_500,
+                currency: "EUR".to_string(),
+                period: "2024-01".to_string(),
+                breakdown: Default::default(),
+                batch_ids: vec![],
+            }),
+            signature: vec![],
+            signature_proof: vec![],
+        }
+    }
+
+    #[test]
+    fn test_min_fee_covers_size_and_gas() {
+        let transaction = settlement(0);
+        let size_fee = (charged_size(&transaction) * Policy::MIN_FEE_PER_KILOBYTE).div_ceil(1_000);
+        let gas_fee = Policy::SETTLEMENT_TRANSACTION_GAS_LIMIT * Policy::MIN_FEE_PER_MEGAGAS / 1_000_000;
+        assert_eq!(min_fee(&transaction), size_fee + gas_fee);
+        assert!(check_fee(&transaction).is_err());
+        assert!(check_fee(&settlement(min_fee(&transaction))).is_ok());
+
+        // The fee is fixed-width, paying more does not make a transaction bigger
+        assert_eq!(charged_size(&settlement(1)), charged_size(&settlement(u64::MAX)));
+
+        let mut system = settlement(0);
+        system.sender = Blake2bHash::zero();
+        assert_eq!(min_fee(&system), 0);
+        assert!(FeeRate::of(&system) > FeeRate::of(&settlement(1_000_000)));
+    }
+
+    #[test]
+    fn test_estimate_outbids_full_block() {
+        let transaction = settlement(0);
+        let min = min_fee(&transaction);
+        let pending = vec![settlement(min), settlement(min * 3), settlement(min * 2)];
+        let gas = Policy::SETTLEMENT_TRANSACTION_GAS_LIMIT;
+
+        // Room for everything, the minimum fee does
+        let estimate = estimate_fee(&transaction, &pending, gas * 4);
+        assert_eq!(estimate.suggested_fee, min);
+        assert_eq!(estimate.pending_gas, gas * 3);
+
+        // Room for two: outbid the one paying twice the minimum
+        let estimate = estimate_fee(&transaction, &pending, gas * 2);
+        assert_eq!(estimate.suggested_fee, min * 2 + 1);
+        let mut outbidding = settlement(estimate.suggested_fee);
+        outbidding.nonce = 1;
+        assert!(FeeRate::of(&outbidding) > FeeRate::of(&pending[2]));
+    }
+}
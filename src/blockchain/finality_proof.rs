@@ -0,0 +1,168 @@
+// Settlement finality proofs for external auditors: a trusted election block, the certified macro
+// headers leading from it to the macro block that finalized a transaction, and the Merkle proof
+// that the block's batch included it. The proof is a self-contained JSON file, verified with the
+// light client rules and nothing else, so auditors need no node and no chain data
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::primitives::{hash_canonical, Blake2bHash, BlockchainError, Height, Result};
+use super::light_client::{CertifiedMacroHeader, InclusionProof, LightClient};
+
+/// Version of the finality proof file format
+pub const FINALITY_PROOF_VERSION: u16 = 1;
+
+/// Proof that a finalized macro block included a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalityProof {
+    pub version: u16,
+    /// Election block the proof starts from, auditors compare its hash with a published one
+    pub checkpoint: CertifiedMacroHeader,
+    /// Election blocks after the checkpoint, then the macro block finalizing the transaction
+    pub headers: Vec<CertifiedMacroHeader>,
+    pub inclusion: InclusionProof,
+}
+
+/// What a verified finality proof establishes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FinalizedTransaction {
+    pub transaction_hash: Blake2bHash,
+    pub macro_block_number: Height,
+    pub macro_block_hash: Blake2bHash,
+    pub checkpoint_block_number: Height,
+    pub checkpoint_hash: Blake2bHash,
+    /// Whether the checkpoint was matched against a trusted hash; if not, the proof only shows the
+    /// transaction is final on the chain its own checkpoint starts
+    pub trusted: bool,
+}
+
+impl FinalityProof {
+    pub fn new(checkpoint: CertifiedMacroHeader, headers: Vec<CertifiedMacroHeader>, inclusion: InclusionProof) -> Self {
+        Self { version: FINALITY_PROOF_VERSION, checkpoint, headers, inclusion }
+    }
+
+    /// Check the header chain from the checkpoint, the certificates on it and the inclusion of the
+    /// transaction. Without a `trusted_checkpoint` the embedded checkpoint is taken as it is and the
+    /// result is not `trusted`
+    pub fn verify(&self, trusted_checkpoint: Option<&Blake2bHash>) -> Result<FinalizedTransaction> {
+        let checkpoint_hash = self.checkpoint.block_hash();
+        if let Some(trusted) = trusted_checkpoint {
+            if *trusted != checkpoint_hash {
+                return Err(BlockchainError::BlockValidation(format!(
+                    "Proof starts from election block {}, not the trusted {}", checkpoint_hash, trusted
+                )));
+            }
+        }
+
+        // The checkpoint itself is not certified, the finalizing block has to come after it
+        let macro_block_number = self.inclusion.macro_block_number;
+        if macro_block_number <= self.checkpoint.header.block_number {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Macro block {} is not after checkpoint {}", macro_block_number, self.checkpoint.header.block_number
+            )));
+        }
+
+        let mut client = LightClient::from_checkpoint(&self.checkpoint)?;
+        for certified in &self.headers {
+            client.apply(certified.clone())?;
+        }
+        client.verify_inclusion(&self.inclusion)?;
+
+        let header = client.header(macro_block_number).ok_or_else(|| BlockchainError::NotFound(format!(
+            "Macro block {} missing from the proof", macro_block_number
+        )))?;
+        Ok(FinalizedTransaction {
+            transaction_hash: self.inclusion.transaction.hash(),
+            macro_block_number,
+            macro_block_hash: hash_canonical(header),
+            checkpoint_block_number: self.checkpoint.header.block_number,
+            checkpoint_hash,
+            trusted: trusted_checkpoint.is_some(),
+        })
+    }
+
+    /// Write the proof as JSON
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| BlockchainError::Serialization(format!("Finality proof serialize failed: {}", e)))?;
+        std::fs::write(path, json)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to write finality proof {}: {}", path.display(), e)))
+    }
+
+    /// Read a proof file, without verifying it
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read finality proof {}: {}", path.display(), e)))?;
+        let proof: Self = serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid finality proof {}: {}", path.display(), e)))?;
+        if proof.version != FINALITY_PROOF_VERSION {
+            return Err(BlockchainError::InvalidOperation(format!("Unsupported finality proof version {}", proof.version)));
+        }
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::block::{Transaction, TransactionData};
+    use crate::blockchain::light_client::{merkle_root, MerkleProof};
+    use crate::blockchain::test_fixtures::{certified, validators};
+    use crate::crypto::bls::BLSPrivateKey;
+    use crate::primitives::{NetworkId, Policy};
+
+    #[test]
+    fn test_finality_proof_round_trip() {
+        let network = NetworkId::SPConsortium;
+        let keys: Vec<BLSPrivateKey> = (0..3).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let next_keys: Vec<BLSPrivateKey> = (0..3).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let settlement = Transaction {
+            sender: Blake2bHash::from_data(b"Orange-FR"),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 125_000,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Basic,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        let batch = vec![settlement.hash(), Blake2bHash::from_data(b"other")];
+
+        // The settlement is finalized in the epoch after the next election
+        let checkpoint = certified(&network, 0, Blake2bHash::zero(), Blake2bHash::zero(), Some(validators(&keys)), &[]);
+        let election = Policy::ELECTION_BLOCK_INTERVAL;
+        let elected = certified(&network, election, checkpoint.block_hash(), Blake2bHash::zero(), Some(validators(&next_keys)), &[(0, &keys[0]), (1, &keys[1]), (2, &keys[2])]);
+        let finalizing = certified(&network, election + Policy::EPOCH_LENGTH, elected.block_hash(), merkle_root(&batch), None, &[(0, &next_keys[0]), (2, &next_keys[2]), (1, &next_keys[1])]);
+        let inclusion = InclusionProof {
+            transaction: settlement.clone(),
+            macro_block_number: finalizing.header.block_number,
+            proof: MerkleProof::new(&batch, 0).unwrap(),
+        };
+        let proof = FinalityProof::new(checkpoint.clone(), vec![elected.clone(), finalizing.clone()], inclusion);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("finality.json");
+        proof.write_to(&path).unwrap();
+        let proof = FinalityProof::read_from(&path).unwrap();
+
+        let finalized = proof.verify(Some(&checkpoint.block_hash())).unwrap();
+        assert_eq!(finalized.transaction_hash, settlement.hash());
+        assert_eq!(finalized.macro_block_hash, finalizing.block_hash());
+        assert!(finalized.trusted);
+        assert!(proof.verify(Some(&Blake2bHash::from_data(b"published elsewhere"))).is_err());
+
+        // Without a published hash the proof verifies against its own checkpoint only
+        let untrusted = proof.verify(None).unwrap();
+        assert!(!untrusted.trusted);
+        assert_eq!(untrusted.transaction_hash, finalized.transaction_hash);
+
+        // Skipping the election leaves the finalizing block without the validators that signed it
+        let skipped = FinalityProof { headers: vec![finalizing], ..proof.clone() };
+        assert!(skipped.verify(None).is_err());
+
+        // Signed by the old validators instead of the elected ones
+        let forged = certified(&network, election + Policy::EPOCH_LENGTH, elected.block_hash(), merkle_root(&batch), None, &[(0, &keys[0]), (1, &keys[1]), (2, &keys[2])]);
+        let forged = FinalityProof { headers: vec![elected, forged], ..proof };
+        assert!(forged.verify(None).is_err());
+    }
+}
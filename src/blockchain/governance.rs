@@ -0,0 +1,237 @@
+// Consortium governance: validators propose parameter changes in `Governance` transactions,
+// vote on them weighted by stake over a fixed window, and accepted changes become part of
+// the active parameter set in the state trie at their activation height. Join applications of
+// new operators are voted on the same way and admit the operator to the registry when accepted
+// Parameters the block schedule is derived from, like the epoch length, stay `Policy` constants
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{Blake2bHash, BlockchainError, Height, Policy, Result, to_canonical_bytes};
+use super::block::ValidatorInfo;
+use super::staking::{quorum, voting_power};
+use super::transaction::NetworkJoinTransaction;
+
+/// Parameters governance can change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChainParameter {
+    /// Settlement amount in cents below which proposals are accepted without review
+    AutoAcceptThreshold,
+    /// Maximum gas the transactions of one block may reserve
+    BlockGasLimit,
+    /// Epochs a contract storage slot may go unwritten before it expires, 0 keeping slots indefinitely
+    StateExpiryEpochs,
+}
+
+/// Parameter set in effect, kept in the state trie
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainParameters {
+    /// `None` until governance sets it, operators then use their configured threshold
+    pub auto_accept_threshold_cents: Option<u64>,
+    pub block_gas_limit: u64,
+    pub state_expiry_epochs: u64,
+}
+
+impl Default for ChainParameters {
+    fn default() -> Self {
+        Self {
+            auto_accept_threshold_cents: None,
+            block_gas_limit: Policy::BLOCK_GAS_LIMIT,
+            state_expiry_epochs: 0,
+        }
+    }
+}
+
+impl ChainParameters {
+    pub fn set(&mut self, parameter: ChainParameter, value: u64) {
+        match parameter {
+            ChainParameter::AutoAcceptThreshold => self.auto_accept_threshold_cents = Some(value),
+            ChainParameter::BlockGasLimit => self.block_gas_limit = value,
+            ChainParameter::StateExpiryEpochs => self.state_expiry_epochs = value,
+        }
+    }
+}
+
+/// What a governance transaction does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GovernanceAction {
+    /// Propose setting `parameter` to `value` from `activation_height` on
+    Propose {
+        parameter: ChainParameter,
+        value: u64,
+        activation_height: Height,
+    },
+    /// Vote on an open proposal or join application, the last vote of a validator counts
+    Vote {
+        proposal: Blake2bHash,
+        approve: bool,
+    },
+}
+
+/// Governance action of an elected validator, signed with its BLS signing key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GovernanceTransaction {
+    pub validator: Blake2bHash,
+    pub action: GovernanceAction,
+    pub signature: Vec<u8>,
+}
+
+impl GovernanceTransaction {
+    /// Bytes the validator signs
+    pub fn signing_payload(validator: &Blake2bHash, action: &GovernanceAction) -> Vec<u8> {
+        let mut payload = b"sp-cdr-governance".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(&(validator, action)).expect("governance actions have a canonical encoding"));
+        payload
+    }
+
+    /// Check the signature against the signing key of the elected validator it names
+    pub fn verify(&self, validators: &[ValidatorInfo]) -> Result<()> {
+        let validator = validators.iter().find(|validator| validator.address == self.validator)
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("{} is not an elected validator", self.validator)))?;
+        let public_key = crate::crypto::BLSPublicKey::from_bytes(&validator.signing_key)?;
+        let signature = crate::crypto::BLSSignature::from_bytes(&self.signature)?;
+        if !signature.verify(&public_key, &Self::signing_payload(&self.validator, &self.action))? {
+            return Err(BlockchainError::InvalidTransaction(format!("Invalid governance signature of {}", self.validator)));
+        }
+        Ok(())
+    }
+
+    /// Id of the proposal a `Propose` action opens
+    pub fn proposal_id(&self) -> Blake2bHash {
+        Blake2bHash::from_data(&Self::signing_payload(&self.validator, &self.action))
+    }
+}
+
+/// Stage of a proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProposalStatus {
+    Voting,
+    /// Accepted, waiting for its activation height
+    Accepted,
+}
+
+/// Open proposal as recorded in the state trie
+/// Rejected proposals and activated ones are removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    pub id: Blake2bHash,
+    pub proposer: Blake2bHash,
+    pub parameter: ChainParameter,
+    pub value: u64,
+    pub voting_ends: Height,
+    pub activation_height: Height,
+    pub status: ProposalStatus,
+    /// Validators and how they voted
+    pub votes: Vec<(Blake2bHash, bool)>,
+}
+
+impl Proposal {
+    /// Proposal opened at `block_number`, `None` if it would activate before its vote ends
+    pub fn open(id: Blake2bHash, proposer: Blake2bHash, parameter: ChainParameter, value: u64, activation_height: Height, block_number: Height) -> Option<Self> {
+        let voting_ends = block_number + Policy::GOVERNANCE_VOTING_PERIOD;
+        if activation_height < voting_ends {
+            return None;
+        }
+        Some(Self {
+            id,
+            proposer,
+            parameter,
+            value,
+            voting_ends,
+            activation_height,
+            status: ProposalStatus::Voting,
+            votes: vec![(proposer, true)],
+        })
+    }
+
+    pub fn vote(&mut self, validator: Blake2bHash, approve: bool) {
+        self.votes.retain(|(voter, _)| *voter != validator);
+        self.votes.push((validator, approve));
+    }
+
+    /// Whether approving votes of `validators` reach a two-thirds quorum of their stake
+    /// Votes of validators no longer elected do not count
+    pub fn is_accepted(&self, validators: &[ValidatorInfo]) -> bool {
+        reaches_quorum(&self.votes, validators)
+    }
+}
+
+/// Join application of an operator in its vote, as recorded in the state trie
+/// Accepted applications are removed once their operator is registered, rejected ones when their vote ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Application {
+    pub id: Blake2bHash,
+    pub join: NetworkJoinTransaction,
+    pub voting_ends: Height,
+    /// Validators and how they voted
+    pub votes: Vec<(Blake2bHash, bool)>,
+}
+
+impl Application {
+    /// Application submitted at `block_number`, the applicant is not a validator and casts no vote
+    pub fn open(join: NetworkJoinTransaction, block_number: Height) -> Self {
+        Self {
+            id: join.application_id(),
+            join,
+            voting_ends: block_number + Policy::GOVERNANCE_VOTING_PERIOD,
+            votes: vec![],
+        }
+    }
+
+    pub fn vote(&mut self, validator: Blake2bHash, approve: bool) {
+        self.votes.retain(|(voter, _)| *voter != validator);
+        self.votes.push((validator, approve));
+    }
+
+    /// Whether approving votes of `validators` reach a two-thirds quorum of their stake
+    pub fn is_accepted(&self, validators: &[ValidatorInfo]) -> bool {
+        reaches_quorum(&self.votes, validators)
+    }
+}
+
+/// Whether the approving `votes` of `validators` carry a two-thirds quorum of their voting power
+fn reaches_quorum(votes: &[(Blake2bHash, bool)], validators: &[ValidatorInfo]) -> bool {
+    let total_stake = validators.iter().map(|validator| validator.stake).sum();
+    let power = |validator: &ValidatorInfo| voting_power(validator.stake, total_stake);
+    let total_power: u64 = validators.iter().map(power).sum();
+    let approving: u64 = validators.iter()
+        .filter(|validator| votes.contains(&(validator.address, true)))
+        .map(power)
+        .sum();
+    approving >= quorum(total_power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator(name: &[u8], stake: u64) -> ValidatorInfo {
+        ValidatorInfo {
+            address: Blake2bHash::from_data(name),
+            signing_key: vec![],
+            voting_key: vec![],
+            reward_address: Blake2bHash::from_data(name),
+            signal_data: None,
+            inactive_from: None,
+            jailed_from: None,
+            stake,
+        }
+    }
+
+    #[test]
+    fn test_votes_are_weighted_by_stake() {
+        let validators = vec![validator(b"T-Mobile-DE", 700), validator(b"Vodafone-UK", 200), validator(b"Orange-FR", 100)];
+        let id = Blake2bHash::from_data(b"proposal");
+
+        // Activation has to wait for the end of the vote
+        assert!(Proposal::open(id, validators[1].address, ChainParameter::BlockGasLimit, 50_000_000, 10, 10).is_none());
+        let mut proposal = Proposal::open(id, validators[1].address, ChainParameter::BlockGasLimit, 50_000_000,
+                                          10 + Policy::GOVERNANCE_VOTING_PERIOD, 10).unwrap();
+        proposal.vote(validators[2].address, true);
+        assert!(!proposal.is_accepted(&validators));
+
+        // The majority stake holder carries it, and can change its mind
+        proposal.vote(validators[0].address, true);
+        assert!(proposal.is_accepted(&validators));
+        proposal.vote(validators[0].address, false);
+        assert!(!proposal.is_accepted(&validators));
+    }
+}
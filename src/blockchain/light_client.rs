@@ -0,0 +1,380 @@
+// Light client for operators too small to run a validator, e.g. MVNOs
+// Follows macro block headers and their finality certificates only, and checks
+// settlement transactions through Merkle inclusion proofs against them
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::crypto::bls::{aggregate_public_keys, aggregate_signatures, BLSPublicKey, BLSSignature};
+use crate::primitives::{hash_canonical, Blake2bHash, BlockchainError, Height, Policy, Result};
+use super::block::{MacroHeader, Transaction, ValidatorInfo};
+use super::staking;
+
+fn merkle_node(left: &Blake2bHash, right: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"sp-cdr-merkle-node".to_vec();
+    data.extend_from_slice(left.as_bytes());
+    data.extend_from_slice(right.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Root of the binary Merkle tree over `leaves`, zero for no leaves
+/// An odd node at the end of a level is carried up unchanged
+pub fn merkle_root(leaves: &[Blake2bHash]) -> Blake2bHash {
+    if leaves.is_empty() {
+        return Blake2bHash::zero();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => merkle_node(left, right),
+                [single] => *single,
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Merkle root over the hashes of `transactions`
+pub fn transactions_root(transactions: &[Transaction]) -> Blake2bHash {
+    merkle_root(&transactions.iter().map(Transaction::hash).collect::<Vec<_>>())
+}
+
+/// Body root of a macro block, committing to its transactions, the validator set it elects
+/// and the validators that lose their reward for it
+pub fn macro_body_root(
+    validators: &Option<Vec<ValidatorInfo>>,
+    lost_reward_set: &[Blake2bHash],
+    transactions_root: &Blake2bHash,
+) -> Blake2bHash {
+    hash_canonical(&(validators, lost_reward_set, transactions_root))
+}
+
+/// Path from a leaf to the Merkle root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub index: u32,
+    pub leaf_count: u32,
+    /// Sibling hashes from the leaf level up; levels where the node is carried up have none
+    pub siblings: Vec<Blake2bHash>,
+}
+
+impl MerkleProof {
+    /// Proof for the leaf at `index`
+    pub fn new(leaves: &[Blake2bHash], index: usize) -> Option<Self> {
+        if index >= leaves.len() {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut position = index;
+        while level.len() > 1 {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                siblings.push(level[sibling]);
+            }
+            level = level.chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => merkle_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect();
+            position /= 2;
+        }
+        Some(Self { index: index as u32, leaf_count: leaves.len() as u32, siblings })
+    }
+
+    /// Root the proof leads to from `leaf`, `None` if the proof is malformed
+    pub fn root(&self, leaf: &Blake2bHash) -> Option<Blake2bHash> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let (mut hash, mut position, mut width) = (*leaf, self.index, self.leaf_count);
+        while width > 1 {
+            let sibling = position ^ 1;
+            if sibling < width {
+                let sibling = siblings.next()?;
+                hash = if position % 2 == 0 { merkle_node(&hash, sibling) } else { merkle_node(sibling, &hash) };
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none().then_some(hash)
+    }
+}
+
+/// Message validators sign when precommitting a macro block in a round, so precommits of one
+/// round cannot be replayed in another
+pub fn certificate_message(block_hash: &Blake2bHash, round: u32) -> Vec<u8> {
+    let mut message = b"sp-cdr-macro-certificate".to_vec();
+    message.extend_from_slice(block_hash.as_bytes());
+    message.extend_from_slice(&round.to_le_bytes());
+    message
+}
+
+/// Aggregated BLS signature of the validators that finalized a macro block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroCertificate {
+    pub block_hash: Blake2bHash,
+    /// Round the precommits were cast in
+    pub round: u32,
+    /// Bit `i` is set when validator `i` of the block's epoch signed, least significant bit first
+    pub signer_bitmap: Vec<u8>,
+    pub signature: BLSSignature,
+}
+
+impl MacroCertificate {
+    /// Aggregate precommit signatures of validators by index
+    pub fn aggregate(block_hash: Blake2bHash, round: u32, mut signatures: Vec<(u16, BLSSignature)>) -> Result<Self> {
+        signatures.sort_by_key(|(index, _)| *index);
+        signatures.dedup_by_key(|(index, _)| *index);
+        let signature = aggregate_signatures(&signatures.iter().map(|(_, signature)| signature.clone()).collect::<Vec<_>>())?;
+
+        let mut signer_bitmap = vec![0u8; signatures.last().map_or(0, |(index, _)| *index as usize / 8 + 1)];
+        for (index, _) in &signatures {
+            signer_bitmap[*index as usize / 8] |= 1 << (index % 8);
+        }
+        Ok(Self { block_hash, round, signer_bitmap, signature })
+    }
+
+    /// Indices of the signers in the validator set of the block's epoch, ascending
+    pub fn signers(&self) -> Vec<u16> {
+        (0..self.signer_bitmap.len() * 8)
+            .filter(|index| self.signer_bitmap[index / 8] & (1 << (index % 8)) != 0)
+            .map(|index| index as u16)
+            .collect()
+    }
+
+    /// Check that validators with more than two thirds of the voting power signed the block
+    pub fn verify(&self, validators: &[ValidatorInfo]) -> Result<()> {
+        let signers = self.signers();
+        if signers.last().is_some_and(|index| *index as usize >= validators.len()) {
+            return Err(BlockchainError::Crypto(format!("Certificate of {} names signers beyond the validator set", self.block_hash)));
+        }
+
+        let powers = staking::voting_powers(validators);
+        let required = staking::quorum(powers.iter().sum());
+        let signed: u64 = signers.iter().filter_map(|index| powers.get(*index as usize)).sum();
+        if signed < required {
+            return Err(BlockchainError::Crypto(format!(
+                "Certificate of {} has {} voting power, {} required",
+                self.block_hash, signed, required
+            )));
+        }
+
+        let public_keys = signers.iter()
+            .map(|index| validators.get(*index as usize)
+                .ok_or_else(|| BlockchainError::Crypto(format!("Certificate signer {} is not a validator", index)))
+                .and_then(|validator| BLSPublicKey::from_bytes(&validator.signing_key)))
+            .collect::<Result<Vec<_>>>()?;
+        let aggregate_key = aggregate_public_keys(&public_keys)?;
+
+        if !self.signature.verify(&aggregate_key, &certificate_message(&self.block_hash, self.round))? {
+            return Err(BlockchainError::Crypto(format!("Invalid certificate signature on {}", self.block_hash)));
+        }
+        Ok(())
+    }
+}
+
+/// Macro block header with what a light client needs to verify it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertifiedMacroHeader {
+    pub header: MacroHeader,
+    /// Merkle root of the macro block's own transactions, part of its body root
+    pub transactions_root: Blake2bHash,
+    /// Validators missing from the previous macro block's certificate
+    #[serde(default)]
+    pub lost_reward_set: Vec<Blake2bHash>,
+    /// Next validator set, election blocks only
+    pub validators: Option<Vec<ValidatorInfo>>,
+    pub certificate: MacroCertificate,
+}
+
+impl CertifiedMacroHeader {
+    pub fn block_hash(&self) -> Blake2bHash {
+        hash_canonical(&self.header)
+    }
+}
+
+/// Settlement transaction with the proof that a finalized batch included it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub transaction: Transaction,
+    /// Macro block closing the batch, its history root commits to every transaction of the batch
+    pub macro_block_number: Height,
+    pub proof: MerkleProof,
+}
+
+/// Chain view of a light client: finalized macro headers and the validators that sign the next ones
+#[derive(Debug)]
+pub struct LightClient {
+    validators: Vec<ValidatorInfo>,
+    election_head: Blake2bHash,
+    headers: BTreeMap<Height, MacroHeader>,
+}
+
+impl LightClient {
+    /// Trust an election block and the validator set it elected, the client verifies everything after it
+    pub fn from_checkpoint(checkpoint: &CertifiedMacroHeader) -> Result<Self> {
+        let validators = checkpoint.validators.clone().ok_or_else(|| BlockchainError::InvalidOperation(format!(
+            "Checkpoint {} is not an election block", checkpoint.header.block_number
+        )))?;
+        Self::check_body_root(checkpoint)?;
+
+        let mut headers = BTreeMap::new();
+        headers.insert(checkpoint.header.block_number, checkpoint.header.clone());
+        Ok(Self { validators, election_head: checkpoint.block_hash(), headers })
+    }
+
+    pub fn validators(&self) -> &[ValidatorInfo] {
+        &self.validators
+    }
+
+    /// Latest finalized macro block number
+    pub fn head_block_number(&self) -> Height {
+        self.headers.keys().next_back().copied().unwrap_or_default()
+    }
+
+    pub fn header(&self, block_number: Height) -> Option<&MacroHeader> {
+        self.headers.get(&block_number)
+    }
+
+    fn check_body_root(certified: &CertifiedMacroHeader) -> Result<()> {
+        if macro_body_root(&certified.validators, &certified.lost_reward_set, &certified.transactions_root) != certified.header.body_root {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Body root of macro block {} does not match its validators", certified.header.block_number
+            )));
+        }
+        Ok(())
+    }
+
+    /// Verify the next macro header against the current validators and follow it
+    pub fn apply(&mut self, certified: CertifiedMacroHeader) -> Result<()> {
+        let block_number = certified.header.block_number;
+        if block_number <= self.head_block_number() || !Policy::is_macro_block(block_number) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} is not a macro block after {}", block_number, self.head_block_number()
+            )));
+        }
+        if certified.header.parent_election_hash != self.election_head {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Macro block {} belongs to another election", block_number
+            )));
+        }
+        if certified.validators.is_some() != Policy::is_election_block(block_number) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Macro block {} must carry validators exactly when it is an election block", block_number
+            )));
+        }
+        Self::check_body_root(&certified)?;
+
+        let block_hash = certified.block_hash();
+        if certified.certificate.block_hash != block_hash {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Certificate is for {}, not macro block {}", certified.certificate.block_hash, block_number
+            )));
+        }
+        certified.certificate.verify(&self.validators)?;
+
+        if let Some(validators) = certified.validators {
+            info!("🗳️  Light client followed election {} to {} validators", block_number, validators.len());
+            self.validators = validators;
+            self.election_head = block_hash;
+        }
+        self.headers.insert(block_number, certified.header);
+        Ok(())
+    }
+
+    /// Check that a finalized macro block's batch included the proven transaction
+    pub fn verify_inclusion(&self, proof: &InclusionProof) -> Result<()> {
+        let header = self.headers.get(&proof.macro_block_number).ok_or_else(|| BlockchainError::NotFound(format!(
+            "Macro block {} not synced", proof.macro_block_number
+        )))?;
+        match proof.proof.root(&proof.transaction.hash()) {
+            Some(root) if root == header.history_root => Ok(()),
+            _ => Err(BlockchainError::BlockValidation(format!(
+                "Transaction {} is not in the batch of macro block {}",
+                proof.transaction.hash(), proof.macro_block_number
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::block::TransactionData;
+    use crate::blockchain::test_fixtures::{certified, validators};
+    use crate::crypto::bls::BLSPrivateKey;
+    use crate::primitives::NetworkId;
+
+    #[test]
+    fn test_merkle_inclusion_proofs() {
+        let leaves: Vec<Blake2bHash> = (0..5u8).map(|i| Blake2bHash::from_data(&[i])).collect();
+        let root = merkle_root(&leaves);
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = MerkleProof::new(&leaves, index).unwrap();
+            assert_eq!(proof.root(leaf), Some(root));
+        }
+
+        let proof = MerkleProof::new(&leaves, 1).unwrap();
+        assert_ne!(proof.root(&leaves[2]), Some(root));
+        assert!(MerkleProof::new(&leaves, 5).is_none());
+        assert_eq!(merkle_root(&[]), Blake2bHash::zero());
+    }
+
+    #[test]
+    fn test_certificate_signer_bitmap() {
+        let key = BLSPrivateKey::generate().unwrap();
+        let block_hash = Blake2bHash::from_data(b"macro");
+        let signature = key.sign(&certificate_message(&block_hash, 0)).unwrap();
+        let signatures = [9, 0, 9].into_iter().map(|index| (index, signature.clone())).collect();
+
+        let certificate = MacroCertificate::aggregate(block_hash, 0, signatures).unwrap();
+        assert_eq!(certificate.signer_bitmap, vec![0b0000_0001, 0b0000_0010]);
+        assert_eq!(certificate.signers(), vec![0, 9]);
+    }
+
+    #[test]
+    fn test_light_client_follows_certified_headers() {
+        let network = NetworkId::new("Lycamobile", "UK");
+        let keys: Vec<BLSPrivateKey> = (0..4).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let genesis_validators = validators(&keys);
+        let checkpoint = certified(&network, 0, Blake2bHash::zero(), Blake2bHash::zero(), Some(genesis_validators), &[(0, &keys[0])]);
+        let mut client = LightClient::from_checkpoint(&checkpoint).unwrap();
+
+        let settlement = Transaction {
+            sender: Blake2bHash::from_data(b"Lycamobile-UK"),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 4_200,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Basic,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        let batch = vec![Blake2bHash::from_data(b"other"), settlement.hash()];
+        let epoch = Policy::EPOCH_LENGTH;
+
+        // Two of four validators are not a quorum
+        let weak = certified(&network, epoch, checkpoint.block_hash(), merkle_root(&batch), None, &[(0, &keys[0]), (1, &keys[1])]);
+        assert!(client.apply(weak).is_err());
+
+        // A signer claiming someone else's index breaks the aggregate
+        let forged = certified(&network, epoch, checkpoint.block_hash(), merkle_root(&batch), None, &[(0, &keys[0]), (1, &keys[1]), (2, &keys[0])]);
+        assert!(client.apply(forged).is_err());
+
+        let header = certified(&network, epoch, checkpoint.block_hash(), merkle_root(&batch), None, &[(0, &keys[0]), (1, &keys[1]), (3, &keys[3])]);
+        client.apply(header).unwrap();
+        assert_eq!(client.head_block_number(), epoch);
+
+        let proof = InclusionProof { transaction: settlement.clone(), macro_block_number: epoch, proof: MerkleProof::new(&batch, 1).unwrap() };
+        client.verify_inclusion(&proof).unwrap();
+        let wrong_position = InclusionProof { proof: MerkleProof::new(&batch, 0).unwrap(), ..proof };
+        assert!(client.verify_inclusion(&wrong_position).is_err());
+    }
+}
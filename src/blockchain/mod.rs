@@ -5,9 +5,28 @@ pub mod block;
 pub mod chain;
 pub mod transaction;
 pub mod validator_set;
+pub mod tariff;
+pub mod light_client;
+pub mod finality_proof;
+pub mod staking;
+pub mod rewards;
+pub mod operator_registry;
+pub mod governance;
+pub mod fees;
+pub mod cdr_commitment;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
 
 // Specific imports to avoid conflicts
 pub use block::{Block, MicroBlock, MacroBlock, MicroHeader, MacroHeader, MicroBody, MacroBody};
 pub use chain::{ChainInfo, ChainState};
 pub use transaction::{Transaction, CDRTransaction, SettlementTransaction, NetworkJoinTransaction};
-pub use validator_set::{ValidatorInfo, ValidatorSet};
\ No newline at end of file
+pub use validator_set::{ValidatorInfo, ValidatorSet};
+pub use light_client::{LightClient, CertifiedMacroHeader, MacroCertificate, InclusionProof, MerkleProof};
+pub use finality_proof::{FinalityProof, FinalizedTransaction};
+pub use staking::{ValidatorStake, ValidatorRecord, Unbonding};
+pub use tariff::{RateTable, ServiceBreakdown, SignedRateTable, TariffRate, TariffService, TimeBand};
+pub use operator_registry::{OperatorApproval, OperatorRecord, OperatorRegistration};
+pub use governance::{Application, ChainParameter, ChainParameters, GovernanceAction, GovernanceTransaction, Proposal};
+pub use fees::FeeEstimate;
+pub use cdr_commitment::{CDRCommitmentTransaction, CommitmentChallengeTransaction, CommitmentRecord, CommitmentResponseTransaction, CommitmentStatus, OpenChallenge};
@@ -0,0 +1,116 @@
+// On-chain operator registry: PLMN codes, country, display name and keys of consortium
+// operators, registered by governance transactions and kept in contract storage
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::EncryptionPublicKey;
+use crate::primitives::{Blake2bHash, BlockchainError, NetworkId, Result, to_canonical_bytes};
+
+/// Registry entry of one operator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorRecord {
+    /// Name the operator is addressed by on chain, as in `T-Mobile-DE`
+    pub name: String,
+    pub display_name: String,
+    pub country: String,
+    /// MCC+MNC codes of the operator's networks
+    pub plmn_codes: Vec<String>,
+    /// BLS key the operator signs rate tables, contract upgrades and registrations with
+    pub signing_key: Vec<u8>,
+    /// X25519 key CDR payloads for the operator are encrypted to
+    pub encryption_key: Option<EncryptionPublicKey>,
+    /// Raised by every update of the record
+    pub version: u64,
+}
+
+impl OperatorRecord {
+    pub fn network_id(&self) -> NetworkId {
+        NetworkId::new(&self.name, &self.country)
+    }
+
+    /// Check the PLMN codes are 5 or 6 digit MCC+MNC codes
+    pub fn validate(&self) -> Result<()> {
+        if self.plmn_codes.is_empty() {
+            return Err(BlockchainError::InvalidTransaction(format!("{} registers no PLMN codes", self.name)));
+        }
+        if let Some(plmn) = self.plmn_codes.iter().find(|plmn| !is_plmn_code(plmn)) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} is not a PLMN code", plmn)));
+        }
+        Ok(())
+    }
+}
+
+/// Approval of a registration by an operator already in the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorApproval {
+    pub operator: String,
+    pub signature: Vec<u8>,
+}
+
+/// Governance transaction registering an operator or updating its record
+/// New operators are admitted by a two-thirds quorum of the registered ones, updates by the operator itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorRegistration {
+    pub record: OperatorRecord,
+    /// Signature of the record's signing key, proving the operator holds it
+    pub signature: Vec<u8>,
+    pub approvals: Vec<OperatorApproval>,
+}
+
+impl OperatorRegistration {
+    /// Bytes the operator and the approving operators sign
+    pub fn signing_payload(record: &OperatorRecord) -> Vec<u8> {
+        let mut payload = b"sp-cdr-operator-registration".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(record).expect("operator records have a canonical encoding"));
+        payload
+    }
+}
+
+/// Whether `plmn` is a 5 or 6 digit MCC+MNC code
+pub(crate) fn is_plmn_code(plmn: &str) -> bool {
+    (5..=6).contains(&plmn.len()) && plmn.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Contract storage address the registry is kept under, no code runs there
+pub fn operator_registry_address() -> Blake2bHash {
+    crate::primitives::primitives::hash_data(b"operator-registry")
+}
+
+/// Key of an operator's record
+pub fn operator_key(name: &str) -> Blake2bHash {
+    crate::primitives::primitives::hash_data(format!("operator:{}", name).as_bytes())
+}
+
+/// Key of the name of the operator a PLMN code belongs to
+pub fn plmn_key(plmn: &str) -> Blake2bHash {
+    crate::primitives::primitives::hash_data(format!("operator-plmn:{}", plmn).as_bytes())
+}
+
+/// Key of the names of all registered operators
+pub fn operator_members_key() -> Blake2bHash {
+    crate::primitives::primitives::hash_data(b"operator-members")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plmn_codes_are_validated() {
+        let mut record = OperatorRecord {
+            name: "Telenor-NO".to_string(),
+            display_name: "Telenor Norge".to_string(),
+            country: "Norway".to_string(),
+            plmn_codes: vec!["24201".to_string()],
+            signing_key: vec![],
+            encryption_key: None,
+            version: 1,
+        };
+        assert!(record.validate().is_ok());
+        assert_eq!(record.network_id(), NetworkId::new("Telenor-NO", "Norway"));
+
+        record.plmn_codes.push("2420A".to_string());
+        assert!(record.validate().is_err());
+        record.plmn_codes.clear();
+        assert!(record.validate().is_err());
+    }
+}
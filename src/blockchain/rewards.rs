@@ -0,0 +1,79 @@
+// Validator rewards: transaction fees accrue in the state trie over an election period
+// and are paid out in the next election block, proportionally to finality participation
+use crate::primitives::{Blake2bHash, Height};
+use super::block::{RewardPayout, RewardPayoutTransaction, Transaction, TransactionData, ValidatorInfo};
+
+/// Split `pool` among `validators` by the macro blocks each helped finalize
+/// Integer rounding leaves a remainder in the pool for the next period
+pub fn distribute(election_block: Height, pool: u64, validators: &[ValidatorInfo], participation: &[u64]) -> RewardPayoutTransaction {
+    let total: u128 = participation.iter().map(|count| *count as u128).sum();
+    let payouts = validators.iter().zip(participation)
+        .map(|(validator, count)| RewardPayout {
+            validator: validator.address,
+            reward_address: validator.reward_address,
+            participation: *count,
+            amount: if total == 0 { 0 } else { (pool as u128 * *count as u128 / total) as u64 },
+        })
+        .collect();
+    RewardPayoutTransaction { election_block, pool, payouts }
+}
+
+/// Payout transaction as carried in the election block
+pub fn payout_transaction(payout: RewardPayoutTransaction) -> Transaction {
+    Transaction {
+        sender: Blake2bHash::zero(),
+        recipient: Blake2bHash::zero(),
+        value: payout.payouts.iter().map(|payout| payout.amount).sum(),
+        fee: 0,
+        nonce: 0,
+        validity_start_height: payout.election_block,
+        data: TransactionData::RewardPayout(payout),
+        signature: vec![],
+        signature_proof: vec![],
+    }
+}
+
+/// Validators whose precommit is missing from a certificate signed by `signers`, by index into `validators`
+pub fn lost_reward_set(validators: &[ValidatorInfo], signers: &[u16]) -> Vec<Blake2bHash> {
+    validators.iter().enumerate()
+        .filter(|(index, _)| !signers.contains(&(*index as u16)))
+        .map(|(_, validator)| validator.address)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator(name: &[u8]) -> ValidatorInfo {
+        ValidatorInfo {
+            address: Blake2bHash::from_data(name),
+            signing_key: vec![],
+            voting_key: vec![],
+            reward_address: Blake2bHash::from_data(&[name, b"-rewards"].concat()),
+            signal_data: None,
+            inactive_from: None,
+            jailed_from: None,
+            stake: 0,
+        }
+    }
+
+    #[test]
+    fn test_rewards_follow_participation() {
+        let validators = vec![validator(b"T-Mobile-DE"), validator(b"Vodafone-UK"), validator(b"Orange-FR")];
+
+        // Orange missed the certificate of one macro block
+        assert_eq!(lost_reward_set(&validators, &[0, 1]), vec![validators[2].address]);
+
+        let payout = distribute(256, 1_000, &validators, &[8, 8, 4]);
+        let amounts: Vec<u64> = payout.payouts.iter().map(|payout| payout.amount).collect();
+        assert_eq!(amounts, vec![400, 400, 200]);
+        assert_eq!(payout.payouts[2].reward_address, validators[2].reward_address);
+
+        // Rounding never pays out more than the pool
+        let payout = distribute(256, 100, &validators, &[1, 1, 1]);
+        assert_eq!(payout.payouts.iter().map(|payout| payout.amount).sum::<u64>(), 99);
+
+        assert!(distribute(256, 100, &validators, &[0, 0, 0]).payouts.iter().all(|payout| payout.amount == 0));
+    }
+}
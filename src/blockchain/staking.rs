@@ -0,0 +1,109 @@
+// Validator staking: stake is bonded and unbonded with `ValidatorUpdate` transactions,
+// kept in the state trie and turned into voting power at election blocks
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{Blake2bHash, Height, Policy};
+use super::block::ValidatorInfo;
+
+/// Stake on its way out, withdrawable once the unbonding period passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unbonding {
+    pub amount: u64,
+    pub release_at: Height,
+}
+
+/// Stake of one validator as recorded in the state trie
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorStake {
+    /// Stake the next election weighs the validator with
+    pub bonded: u64,
+    pub unbonding: Vec<Unbonding>,
+}
+
+impl ValidatorStake {
+    pub fn bond(&mut self, amount: u64) {
+        self.bonded = self.bonded.saturating_add(amount);
+    }
+
+    /// Start unbonding up to `amount` of the bonded stake at `block_number`, returns the amount unbonded
+    pub fn unbond(&mut self, amount: u64, block_number: Height) -> u64 {
+        let amount = amount.min(self.bonded);
+        if amount > 0 {
+            self.bonded -= amount;
+            self.unbonding.push(Unbonding { amount, release_at: block_number + Policy::UNBONDING_PERIOD });
+        }
+        amount
+    }
+
+    /// Drop unbondings whose period passed by `block_number`, returns the stake released
+    pub fn release(&mut self, block_number: Height) -> u64 {
+        let released = self.unbonding.iter()
+            .filter(|unbonding| unbonding.release_at <= block_number)
+            .map(|unbonding| unbonding.amount)
+            .sum();
+        self.unbonding.retain(|unbonding| unbonding.release_at > block_number);
+        released
+    }
+
+    /// Whether nothing is bonded or unbonding
+    pub fn is_empty(&self) -> bool {
+        self.bonded == 0 && self.unbonding.is_empty()
+    }
+}
+
+/// Registration of a validator created with a `CreateValidator` transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorRecord {
+    /// Account that created the validator, the only one allowed to update, deactivate or unbond it
+    pub controller: Blake2bHash,
+    pub registered_at: Height,
+    /// Inactive validators are left out from the next election block on
+    pub active: bool,
+}
+
+/// Voting power of `stake` in a set with `total_stake` bonded
+/// Before anyone bonds, every validator counts equally
+pub fn voting_power(stake: u64, total_stake: u64) -> u64 {
+    if total_stake == 0 { 1 } else { stake }
+}
+
+/// Voting power of each elected validator, in set order
+pub fn voting_powers(validators: &[ValidatorInfo]) -> Vec<u64> {
+    let total_stake = validators.iter().map(|validator| validator.stake).sum();
+    validators.iter().map(|validator| voting_power(validator.stake, total_stake)).collect()
+}
+
+/// Voting power needed for a two-thirds quorum of `total_power`
+pub fn quorum(total_power: u64) -> u64 {
+    total_power * 2 / 3 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unbonding_period() {
+        let mut stake = ValidatorStake::default();
+        stake.bond(1_000);
+        assert_eq!(stake.unbond(1_500, 10), 1_000);
+        assert_eq!(stake.bonded, 0);
+
+        assert_eq!(stake.release(10 + Policy::UNBONDING_PERIOD - 1), 0);
+        assert_eq!(stake.release(10 + Policy::UNBONDING_PERIOD), 1_000);
+        assert!(stake.is_empty());
+    }
+
+    #[test]
+    fn test_stake_weighted_quorum() {
+        // Equal weights until stake is bonded
+        assert_eq!(voting_power(0, 0), 1);
+        assert_eq!(quorum(3), 3);
+
+        // A validator with 70% of the stake cannot finalize alone
+        let total = 700 + 200 + 100;
+        assert_eq!(quorum(total), 667);
+        assert!(voting_power(700, total) < quorum(total));
+        assert!(voting_power(700, total) + voting_power(100, total) >= quorum(total));
+    }
+}
@@ -0,0 +1,243 @@
+// Inter-Operator Tariff (IOT) rate tables published on chain
+use serde::{Deserialize, Serialize};
+use crate::primitives::{Blake2bHash, BlockchainError, Result, to_canonical_bytes};
+
+/// Service a wholesale rate applies to
+/// Voice is rated per minute, data per megabyte and SMS per message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TariffService {
+    Voice,
+    Data,
+    Sms,
+}
+
+impl TariffService {
+    /// Service of a BCE record type, `None` for record types that are not rated
+    pub fn from_record_type(record_type: &str) -> Option<Self> {
+        match record_type {
+            "VOICE_CALL_CDR" => Some(Self::Voice),
+            "DATA_SESSION_CDR" => Some(Self::Data),
+            "SMS_CDR" => Some(Self::Sms),
+            _ => None,
+        }
+    }
+
+    /// Service name settlement contracts read usage under, as in `forward.voice`
+    pub fn contract_name(&self) -> &'static str {
+        match self {
+            Self::Voice => "voice",
+            Self::Data => "data",
+            Self::Sms => "sms",
+        }
+    }
+}
+
+/// Usage and wholesale charges split per service
+/// Records of types that are not rated per service (e.g. 5G slice usage) count under `other_cents`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceBreakdown {
+    pub voice_minutes: u64,
+    pub voice_cents: u64,
+    pub data_mb: u64,
+    pub data_cents: u64,
+    pub sms_count: u64,
+    pub sms_cents: u64,
+    pub other_cents: u64,
+}
+
+impl ServiceBreakdown {
+    /// Add one record's usage and charge
+    pub fn add_usage(&mut self, service: Option<TariffService>, units: u64, cents: u64) {
+        match service {
+            Some(TariffService::Voice) => {
+                self.voice_minutes += units;
+                self.voice_cents += cents;
+            }
+            Some(TariffService::Data) => {
+                self.data_mb += units;
+                self.data_cents += cents;
+            }
+            Some(TariffService::Sms) => {
+                self.sms_count += units;
+                self.sms_cents += cents;
+            }
+            None => self.other_cents += cents,
+        }
+    }
+
+    pub fn merge(&mut self, other: &ServiceBreakdown) {
+        self.voice_minutes += other.voice_minutes;
+        self.voice_cents += other.voice_cents;
+        self.data_mb += other.data_mb;
+        self.data_cents += other.data_cents;
+        self.sms_count += other.sms_count;
+        self.sms_cents += other.sms_cents;
+        self.other_cents += other.other_cents;
+    }
+
+    pub fn total_cents(&self) -> u64 {
+        self.voice_cents + self.data_cents + self.sms_cents + self.other_cents
+    }
+
+    /// Usage units and charge of a rated service
+    pub fn service(&self, service: TariffService) -> (u64, u64) {
+        match service {
+            TariffService::Voice => (self.voice_minutes, self.voice_cents),
+            TariffService::Data => (self.data_mb, self.data_cents),
+            TariffService::Sms => (self.sms_count, self.sms_cents),
+        }
+    }
+}
+
+/// Hours of the day (UTC) a rate applies in, wrapping past midnight when `start_hour > end_hour`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeBand {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+impl TimeBand {
+    pub fn contains(&self, timestamp: u64) -> bool {
+        let hour = ((timestamp % 86_400) / 3_600) as u8;
+        if self.start_hour <= self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// One wholesale rate; rates without a destination or time band apply to all of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TariffRate {
+    pub service: TariffService,
+    pub destination: Option<String>,
+    pub time_band: Option<TimeBand>,
+    pub cents_per_unit: u64,
+}
+
+/// Usage of one roaming record, priced against a rate table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TariffUsage {
+    pub service: TariffService,
+    pub units: u64,
+    pub timestamp: u64,
+    pub destination: Option<String>,
+}
+
+/// Rates a visited network charges a home network for its roaming subscribers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateTable {
+    /// Visited network publishing and charging the rates
+    pub operator: String,
+    /// Home network the rates are charged to
+    pub partner: String,
+    pub currency: String,
+    pub effective_from: u64,
+    pub rates: Vec<TariffRate>,
+}
+
+impl RateTable {
+    /// Most specific rate for a service at `timestamp`, a matching destination ranks above a matching time band
+    pub fn lookup(&self, service: TariffService, timestamp: u64, destination: Option<&str>) -> Option<&TariffRate> {
+        self.rates.iter()
+            .filter(|rate| rate.service == service)
+            .filter(|rate| rate.destination.as_deref().map_or(true, |d| Some(d) == destination))
+            .filter(|rate| rate.time_band.map_or(true, |band| band.contains(timestamp)))
+            .max_by_key(|rate| (rate.destination.is_some(), rate.time_band.is_some()))
+    }
+
+    /// Wholesale charge the table sets for `usage`
+    pub fn expected_charge(&self, usage: &TariffUsage) -> Result<u64> {
+        let rate = self.lookup(usage.service, usage.timestamp, usage.destination.as_deref())
+            .ok_or_else(|| BlockchainError::NotFound(format!(
+                "{} publishes no {:?} rate for {}", self.operator, usage.service, self.partner
+            )))?;
+        Ok(usage.units.saturating_mul(rate.cents_per_unit))
+    }
+
+    pub fn table_key(&self) -> Blake2bHash {
+        rate_table_key(&self.operator, &self.partner)
+    }
+}
+
+/// Rate table signed by the BLS key of its publishing operator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedRateTable {
+    pub table: RateTable,
+    pub signature: Vec<u8>,
+}
+
+impl SignedRateTable {
+    /// Bytes the publishing operator signs
+    pub fn signing_payload(table: &RateTable) -> Vec<u8> {
+        let mut payload = b"sp-cdr-iot-rate-table".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(table).expect("rate tables have a canonical encoding"));
+        payload
+    }
+}
+
+/// Contract storage address rate tables are kept under, no code runs there
+pub fn tariff_registry_address() -> Blake2bHash {
+    crate::primitives::primitives::hash_data(b"iot-tariff-registry")
+}
+
+/// Key of the rate table `operator` charges `partner` with
+pub fn rate_table_key(operator: &str, partner: &str) -> Blake2bHash {
+    crate::primitives::primitives::hash_data(format!("iot-rate-table:{}:{}", operator, partner).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_lookup() {
+        let peak = TimeBand { start_hour: 8, end_hour: 20 };
+        let table = RateTable {
+            operator: "Vodafone-UK".to_string(),
+            partner: "T-Mobile-DE".to_string(),
+            currency: "EUR".to_string(),
+            effective_from: 0,
+            rates: vec![
+                TariffRate { service: TariffService::Voice, destination: None, time_band: None, cents_per_unit: 10 },
+                TariffRate { service: TariffService::Voice, destination: None, time_band: Some(peak), cents_per_unit: 15 },
+                TariffRate { service: TariffService::Voice, destination: Some("international".to_string()), time_band: None, cents_per_unit: 40 },
+                TariffRate { service: TariffService::Data, destination: None, time_band: None, cents_per_unit: 2 },
+            ],
+        };
+
+        let noon = 12 * 3_600;
+        let night = 23 * 3_600;
+        assert_eq!(table.lookup(TariffService::Voice, noon, None).unwrap().cents_per_unit, 15);
+        assert_eq!(table.lookup(TariffService::Voice, night, None).unwrap().cents_per_unit, 10);
+        assert_eq!(table.lookup(TariffService::Voice, noon, Some("international")).unwrap().cents_per_unit, 40);
+        assert!(table.lookup(TariffService::Sms, noon, None).is_none());
+
+        // Bands wrap past midnight
+        assert!(TimeBand { start_hour: 20, end_hour: 8 }.contains(night));
+        assert!(!TimeBand { start_hour: 20, end_hour: 8 }.contains(noon));
+
+        let usage = TariffUsage { service: TariffService::Data, units: 500, timestamp: noon, destination: None };
+        assert_eq!(table.expected_charge(&usage).unwrap(), 1_000);
+        let sms = TariffUsage { service: TariffService::Sms, units: 1, timestamp: noon, destination: None };
+        assert!(table.expected_charge(&sms).is_err());
+    }
+
+    #[test]
+    fn test_service_breakdown() {
+        let mut batch = ServiceBreakdown::default();
+        batch.add_usage(TariffService::from_record_type("VOICE_CALL_CDR"), 5, 75);
+        batch.add_usage(TariffService::from_record_type("DATA_SESSION_CDR"), 100, 200);
+        batch.add_usage(TariffService::from_record_type("SMS_CDR"), 1, 10);
+        batch.add_usage(TariffService::from_record_type("SLICE_5G_CDR"), 0, 500);
+        assert_eq!(batch.service(TariffService::Voice), (5, 75));
+        assert_eq!(batch.other_cents, 500);
+        assert_eq!(batch.total_cents(), 785);
+
+        let mut period = batch;
+        period.merge(&batch);
+        assert_eq!(period.service(TariffService::Data), (200, 400));
+        assert_eq!(period.total_cents(), 1_570);
+    }
+}
@@ -0,0 +1,58 @@
+// Certified macro headers signed by throwaway validator keys, shared by the light client,
+// finality proof and bridge tests
+use crate::crypto::bls::BLSPrivateKey;
+use crate::primitives::{hash_canonical, Blake2bHash, Height, NetworkId};
+use super::block::{MacroHeader, ValidatorInfo};
+use super::light_client::{certificate_message, macro_body_root, CertifiedMacroHeader, MacroCertificate};
+
+/// Validators signing with `keys`, in order
+pub(crate) fn validators(keys: &[BLSPrivateKey]) -> Vec<ValidatorInfo> {
+    keys.iter().map(|key| ValidatorInfo {
+        address: Blake2bHash::from_data(key.public_key().to_bytes()),
+        signing_key: key.public_key().to_bytes().to_vec(),
+        voting_key: vec![],
+        reward_address: Blake2bHash::zero(),
+        signal_data: None,
+        inactive_from: None,
+        jailed_from: None,
+        stake: 0,
+    }).collect()
+}
+
+/// Macro header of `network` electing `elected`, if any, certified in round 0 by `signers` at their
+/// validator indices
+pub(crate) fn certified(
+    network: &NetworkId,
+    block_number: Height,
+    parent_election_hash: Blake2bHash,
+    history_root: Blake2bHash,
+    elected: Option<Vec<ValidatorInfo>>,
+    signers: &[(u16, &BLSPrivateKey)],
+) -> CertifiedMacroHeader {
+    let transactions_root = Blake2bHash::zero();
+    let header = MacroHeader {
+        network: network.clone(),
+        version: 1,
+        block_number,
+        round: 0,
+        timestamp: 0,
+        parent_hash: Blake2bHash::zero(),
+        parent_election_hash,
+        seed: Blake2bHash::zero(),
+        extra_data: vec![],
+        state_root: Blake2bHash::zero(),
+        body_root: macro_body_root(&elected, &[], &transactions_root),
+        history_root,
+    };
+    let block_hash = hash_canonical(&header);
+    let signatures = signers.iter()
+        .map(|(index, key)| (*index, key.sign(&certificate_message(&block_hash, 0)).unwrap()))
+        .collect();
+    CertifiedMacroHeader {
+        header,
+        transactions_root,
+        lost_reward_set: vec![],
+        validators: elected,
+        certificate: MacroCertificate::aggregate(block_hash, 0, signatures).unwrap(),
+    }
+}
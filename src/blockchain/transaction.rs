@@ -2,6 +2,8 @@
 use serde::{Deserialize, Serialize};
 use crate::primitives::primitives::{Blake2bHash, Timestamp};
 use crate::primitives::cdr::{CDRBatch, CDRStatus};
+use crate::primitives::{BlockchainError, Result};
+use super::operator_registry::{OperatorRecord, OperatorRegistration};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Transaction {
@@ -42,11 +44,35 @@ pub struct SettlementTransaction {
     pub timestamp: Timestamp,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Application of a prospective operator to join the consortium, voted on by the elected
+/// validators with governance votes naming its id; once accepted its record enters the registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NetworkJoinTransaction {
-    pub network_name: String,
-    pub public_key: Vec<u8>,
-    pub country_code: String,
+    /// Record the operator is registered with: name, country, PLMN codes and keys
+    pub record: OperatorRecord,
+    /// Licence of the national regulator, for members to review before they vote
     pub operator_license: Vec<u8>,
+    /// Signature of the record's signing key, proving the applicant holds it
+    pub signature: Vec<u8>,
     pub timestamp: Timestamp,
-}
\ No newline at end of file
+}
+
+impl NetworkJoinTransaction {
+    /// Id governance votes on the application name
+    pub fn application_id(&self) -> Blake2bHash {
+        crate::primitives::hash_canonical(&(b"sp-cdr-network-join", &self.record, &self.operator_license, self.timestamp))
+    }
+
+    /// Check the record's PLMN codes and that its signing key signed it
+    pub fn verify(&self) -> Result<()> {
+        self.record.validate()?;
+        let public_key = crate::crypto::BLSPublicKey::from_bytes(&self.record.signing_key)?;
+        let signature = crate::crypto::BLSSignature::from_bytes(&self.signature)?;
+        if !signature.verify(&public_key, &OperatorRegistration::signing_payload(&self.record))? {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Join application of {} is not signed by its key", self.record.name
+            )));
+        }
+        Ok(())
+    }
+}
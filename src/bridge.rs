@@ -0,0 +1,293 @@
+// Cross-consortium bridge for operators in several regional consortia (EU, APAC): settlements
+// finalized on a source consortium's chain are relayed to this chain with a finality proof, which
+// every validator checks with the light client rules against the source's election blocks it
+// trusts, and become settlement obligations here. The first proof from a source starts from a
+// configured checkpoint, later ones from any election block an earlier bridged proof went through
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::blockchain::{Block, FinalityProof, FinalizedTransaction};
+use crate::blockchain::block::{SettlementTransaction, TransactionData};
+use crate::primitives::{Blake2bHash, BlockchainError, Height, NetworkId, Policy, Result};
+use crate::storage::{ChainStore, MdbxChainStore};
+use crate::SPCDRBlockchain;
+
+/// Settlement finalized on another consortium's chain, with the proof of its finality there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgedSettlementTransaction {
+    /// Consortium chain the settlement was finalized on
+    pub source_network: NetworkId,
+    pub proof: FinalityProof,
+}
+
+impl BridgedSettlementTransaction {
+    /// The settlement the proof shows finalized
+    pub fn settlement(&self) -> Result<&SettlementTransaction> {
+        match &self.proof.inclusion.transaction.data {
+            TransactionData::Settlement(settlement) => Ok(settlement),
+            _ => Err(BlockchainError::InvalidTransaction(format!(
+                "Bridged transaction {} from {} is not a settlement", self.source_transaction(), self.source_network
+            ))),
+        }
+    }
+
+    /// Hash of the settlement transaction on the source chain
+    pub fn source_transaction(&self) -> Blake2bHash {
+        self.proof.inclusion.transaction.hash()
+    }
+
+    /// Check the proof starts from an election block `link` trusts, that every header in it is
+    /// one of the source chain's, and that it shows the settlement finalized
+    pub fn verify(&self, link: &BridgeLink) -> Result<FinalizedTransaction> {
+        self.settlement()?;
+        let checkpoint = self.proof.checkpoint.block_hash();
+        if !link.trusts(&checkpoint) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Bridged settlement {} starts from election block {} of {}, which the bridge does not trust",
+                self.source_transaction(), checkpoint, self.source_network
+            )));
+        }
+        let mut headers = std::iter::once(&self.proof.checkpoint).chain(&self.proof.headers);
+        if let Some(certified) = headers.find(|certified| certified.header.network != self.source_network) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Bridged settlement {} carries block {} of {}, not of {}",
+                self.source_transaction(), certified.header.block_number, certified.header.network, self.source_network
+            )));
+        }
+        self.proof.verify(Some(&checkpoint))
+    }
+}
+
+/// Election blocks of a source chain this chain trusts, kept in the state trie
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeLink {
+    pub elections: Vec<Blake2bHash>,
+}
+
+impl BridgeLink {
+    pub fn from_checkpoint(checkpoint: Blake2bHash) -> Self {
+        Self { elections: vec![checkpoint] }
+    }
+
+    pub fn trusts(&self, election_hash: &Blake2bHash) -> bool {
+        self.elections.contains(election_hash)
+    }
+
+    /// Trust the election blocks a verified proof went through, each hands the signing over to
+    /// the validators the next proofs are certified by
+    pub fn extend(&mut self, proof: &FinalityProof) {
+        for certified in &proof.headers {
+            let hash = certified.block_hash();
+            if Policy::is_election_block(certified.header.block_number) && !self.trusts(&hash) {
+                self.elections.push(hash);
+            }
+        }
+    }
+}
+
+/// Bridge setup of a node, loaded from JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// Consortium chain this node runs, named in the blocks it produces and never a source
+    pub consortium: NetworkId,
+    pub sources: Vec<BridgeSource>,
+}
+
+/// Consortium whose settlements are bridged onto this chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeSource {
+    pub network: NetworkId,
+    /// Data directory of this operator's node on the source consortium
+    pub data_dir: PathBuf,
+    /// Hash of the source election block, hex, the first bridged proof starts from
+    pub trusted_checkpoint: String,
+}
+
+impl BridgeConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read bridge config {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid bridge config {}: {}", path.display(), e)))?;
+        if let Some(source) = config.sources.iter().find(|source| source.network == config.consortium) {
+            return Err(BlockchainError::InvalidOperation(format!("{} cannot bridge settlements from itself", source.network)));
+        }
+        Ok(config)
+    }
+
+    /// Configured checkpoint of every source, see `SPCDRBlockchain::with_bridge_checkpoints`
+    pub fn trusted_checkpoints(&self) -> Result<HashMap<NetworkId, Blake2bHash>> {
+        self.sources.iter()
+            .map(|source| Ok((source.network.clone(), source.trusted_checkpoint()?)))
+            .collect()
+    }
+}
+
+impl BridgeSource {
+    pub fn trusted_checkpoint(&self) -> Result<Blake2bHash> {
+        Blake2bHash::from_hex(&self.trusted_checkpoint).ok_or_else(|| BlockchainError::InvalidOperation(format!(
+            "Invalid trusted checkpoint {} for {}", self.trusted_checkpoint, self.network
+        )))
+    }
+}
+
+/// Reads a source consortium's chain from its node's store and proves the settlements it finalized
+#[derive(Clone)]
+pub struct BridgeRelay {
+    source: BridgeSource,
+    store: Arc<MdbxChainStore>,
+    /// Source blocks whose settlements were all relayed or found bridged
+    relayed_to: Height,
+}
+
+impl BridgeRelay {
+    /// Open the source node's store, which keeps being written by that node
+    pub fn open(source: BridgeSource) -> Result<Self> {
+        let store = MdbxChainStore::new(source.data_dir.join("blockchain"))?;
+        Ok(Self { source, store: Arc::new(store), relayed_to: 0 })
+    }
+
+    pub fn source_network(&self) -> &NetworkId {
+        &self.source.network
+    }
+
+    /// Settlements the source chain finalized that `destination` has not bridged yet, each proven
+    /// from the latest election block before it that the destination trusts. Stops at the first
+    /// settlement whose macro block is not certified yet, to pick it up on the next call
+    pub async fn poll(&mut self, destination: &SPCDRBlockchain) -> Result<Vec<BridgedSettlementTransaction>> {
+        let network = self.source.network.clone();
+        // The heads move as the source node commits, so the chain is opened afresh
+        let source = SPCDRBlockchain::open(self.store.clone(), vec![]).await?;
+        let macro_head = source.macro_head_async().await.block_number();
+
+        let link = match destination.bridge_link(&network) {
+            Some(link) => link,
+            None => BridgeLink::from_checkpoint(self.source.trusted_checkpoint()?),
+        };
+        let mut trusted = Vec::new();
+        for hash in &link.elections {
+            if let Some(block) = self.store.get_block(hash).await? {
+                trusted.push(block.block_number());
+            }
+        }
+        trusted.sort_unstable();
+
+        let mut bridged = Vec::new();
+        for block_number in self.relayed_to + 1..=macro_head {
+            let Some(block) = self.store.get_block_at(block_number).await? else {
+                self.relayed_to = block_number;
+                continue;
+            };
+            let finalizing = block_number.div_ceil(Policy::EPOCH_LENGTH) * Policy::EPOCH_LENGTH;
+            for transaction in settlements(&block) {
+                let hash = transaction.hash();
+                if destination.is_bridged(&network, &hash) {
+                    continue;
+                }
+                let Some(&checkpoint) = trusted.iter().rev().find(|election| **election < finalizing) else {
+                    warn!("🌉 Settlement {} of {} is finalized before any trusted election block", hash, network);
+                    continue;
+                };
+                let proof = match source.inclusion_proof(&hash).await? {
+                    Some(inclusion) => source.finality_proof_from(inclusion, checkpoint).await?,
+                    None => None,
+                };
+                let Some(proof) = proof else {
+                    debug!("🌉 Settlement {} of {} is not certified yet", hash, network);
+                    return Ok(bridged);
+                };
+                bridged.push(BridgedSettlementTransaction { source_network: network.clone(), proof });
+            }
+            self.relayed_to = block_number;
+        }
+        Ok(bridged)
+    }
+}
+
+fn settlements(block: &Block) -> impl Iterator<Item = &crate::blockchain::block::Transaction> {
+    block.transactions().iter().filter(|transaction| matches!(transaction.data, TransactionData::Settlement(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{InclusionProof, MerkleProof};
+    use crate::blockchain::block::Transaction;
+    use crate::blockchain::light_client::merkle_root;
+    use crate::blockchain::test_fixtures::{certified, validators};
+    use crate::crypto::bls::BLSPrivateKey;
+    use crate::storage::StateTrie;
+
+    fn bridged<'a>(network: &NetworkId, checkpoint_keys: &'a [BLSPrivateKey], next_keys: &'a [BLSPrivateKey], data: TransactionData) -> BridgedSettlementTransaction {
+        let transaction = Transaction {
+            sender: Blake2bHash::from_data(b"Telstra-AU"),
+            recipient: Blake2bHash::zero(),
+            value: 0,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        let batch = vec![Blake2bHash::from_data(b"other"), transaction.hash()];
+
+        let checkpoint = certified(network, 0, Blake2bHash::zero(), Blake2bHash::zero(), Some(validators(checkpoint_keys)), &[]);
+        let election = Policy::ELECTION_BLOCK_INTERVAL;
+        let signers = |keys: &'a [BLSPrivateKey]| keys.iter().enumerate().map(|(index, key)| (index as u16, key)).collect::<Vec<_>>();
+        let elected = certified(network, election, checkpoint.block_hash(), Blake2bHash::zero(), Some(validators(next_keys)), &signers(checkpoint_keys));
+        let finalizing = certified(network, election + Policy::EPOCH_LENGTH, elected.block_hash(), merkle_root(&batch), None, &signers(next_keys));
+        let inclusion = InclusionProof {
+            transaction,
+            macro_block_number: finalizing.header.block_number,
+            proof: MerkleProof::new(&batch, 1).unwrap(),
+        };
+        BridgedSettlementTransaction {
+            source_network: network.clone(),
+            proof: FinalityProof::new(checkpoint, vec![elected, finalizing], inclusion),
+        }
+    }
+
+    #[test]
+    fn test_bridged_settlement_mints_obligation() {
+        let apac = NetworkId::new("APAC-Roaming", "SG");
+        let keys: Vec<BLSPrivateKey> = (0..3).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let next_keys: Vec<BLSPrivateKey> = (0..3).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let settlement = SettlementTransaction {
+            creditor_network: "Telstra:AU".to_string(),
+            debtor_network: "Orange:FR".to_string(),
+            amount: 48_000,
+            currency: "EUR".to_string(),
+            period: "2024-03".to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![Blake2bHash::from_data(b"apac-batch")],
+        };
+        let bridged_settlement = bridged(&apac, &keys, &next_keys, TransactionData::Settlement(settlement.clone()));
+
+        // Only the configured checkpoint is trusted before the first bridged settlement
+        let link = BridgeLink::from_checkpoint(bridged_settlement.proof.checkpoint.block_hash());
+        let finalized = bridged_settlement.verify(&link).unwrap();
+        assert_eq!(finalized.transaction_hash, bridged_settlement.source_transaction());
+        assert!(bridged_settlement.verify(&BridgeLink::from_checkpoint(Blake2bHash::from_data(b"elsewhere"))).is_err());
+
+        // Headers of another consortium's chain do not prove anything about this one
+        let relabelled = BridgedSettlementTransaction { source_network: NetworkId::new("EU-Roaming", "BE"), ..bridged_settlement.clone() };
+        assert!(relabelled.verify(&link).is_err());
+
+        // Only settlements are bridged
+        let basic = bridged(&apac, &keys, &next_keys, TransactionData::Basic);
+        assert!(basic.verify(&BridgeLink::from_checkpoint(basic.proof.checkpoint.block_hash())).is_err());
+
+        // The obligation is minted and the election the proof went through is trusted from then on
+        let mut state_trie = StateTrie::new();
+        state_trie.apply_bridged_settlement(&bridged_settlement);
+        assert_eq!(state_trie.settlement_balance("Telstra:AU", "Orange:FR", "EUR"), 48_000);
+        assert!(state_trie.is_bridged(&apac, &bridged_settlement.source_transaction()));
+        assert!(!state_trie.is_batch_settled(&settlement.batch_ids[0]));
+        let link = state_trie.bridge_link(&apac).unwrap();
+        assert_eq!(link.elections, vec![bridged_settlement.proof.checkpoint.block_hash(), bridged_settlement.proof.headers[0].block_hash()]);
+    }
+}
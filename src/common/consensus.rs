@@ -149,7 +149,7 @@ pub struct TendermintVote {
     pub signature: Vec<u8>,
 }
 
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum TendermintStep {
     Propose = 1,
     Prevote = 2,  
@@ -7,10 +7,10 @@ use blst::{
 };
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
-use crate::primitives::{Blake2bHash, Result, BlockchainError};
+use crate::primitives::{Blake2bHash, Height, Result, BlockchainError};
 
 // Domain Separation Tag for SP consortium
-const DST: &[u8] = b"SP_CDR_CONSORTIUM_BLS_SIG";
+pub(crate) const DST: &[u8] = b"SP_CDR_CONSORTIUM_BLS_SIG";
 
 /// Real BLS private key using blst
 #[derive(Clone, Debug)]
@@ -179,22 +179,117 @@ impl BLSSignature {
     }
 }
 
+/// Signing key of an operator together with the block heights it is valid for
+#[derive(Clone, Debug)]
+pub struct KeyValidityWindow {
+    pub public_key: BLSPublicKey,
+    /// First height the key signs for
+    pub valid_from: Height,
+    /// First height the key no longer signs for, `None` while it is the latest key
+    pub valid_until: Option<Height>,
+}
+
+impl KeyValidityWindow {
+    pub fn is_valid_at(&self, height: Height) -> bool {
+        height >= self.valid_from && self.valid_until.map_or(true, |until| height < until)
+    }
+}
+
+/// Message a new signing key signs to prove possession during rotation
+/// Binding the validator address stops a key from being claimed by another validator
+pub fn key_rotation_message(validator_address: &Blake2bHash, new_key: &BLSPublicKey) -> Vec<u8> {
+    let mut message = b"SP_CDR_KEY_ROTATION".to_vec();
+    message.extend_from_slice(validator_address.as_bytes());
+    message.extend_from_slice(new_key.to_bytes());
+    message
+}
+
 /// BLS Verifier for SP consortium operations
+#[derive(Clone)]
 pub struct BLSVerifier {
-    /// Known public keys for SP operators
-    sp_operators: HashMap<String, BLSPublicKey>,
+    /// Key validity windows for SP operators, ordered by `valid_from`
+    sp_operators: HashMap<String, Vec<KeyValidityWindow>>,
+    /// Height used by the height-less verification methods
+    current_height: Height,
 }
 
 impl BLSVerifier {
     pub fn new() -> Self {
         Self {
             sp_operators: HashMap::new(),
+            current_height: 0,
         }
     }
 
-    /// Register an SP operator's public key
+    /// Register an SP operator's public key, valid from genesis
     pub fn register_operator(&mut self, operator_name: &str, public_key: BLSPublicKey) {
-        self.sp_operators.insert(operator_name.to_string(), public_key);
+        self.sp_operators.insert(operator_name.to_string(), vec![KeyValidityWindow {
+            public_key,
+            valid_from: 0,
+            valid_until: None,
+        }]);
+    }
+
+    /// Schedule an operator's switch to a new signing key
+    /// The current key stays valid for every height below `activation_height`
+    pub fn schedule_key_rotation(
+        &mut self,
+        operator_name: &str,
+        new_key: BLSPublicKey,
+        activation_height: Height,
+    ) -> Result<()> {
+        let windows = self.sp_operators.get_mut(operator_name)
+            .ok_or_else(|| BlockchainError::Crypto(format!("Unknown operator: {}", operator_name)))?;
+
+        // Registration always leaves at least one window
+        let latest = windows.last_mut().expect("operator without signing key");
+        if activation_height <= latest.valid_from {
+            return Err(BlockchainError::Crypto(format!(
+                "Key rotation for {} at height {} does not follow pending rotation at {}",
+                operator_name, activation_height, latest.valid_from
+            )));
+        }
+        if latest.public_key == new_key {
+            return Err(BlockchainError::Crypto(format!("Operator {} is already using this key", operator_name)));
+        }
+
+        latest.valid_until = Some(activation_height);
+        windows.push(KeyValidityWindow {
+            public_key: new_key,
+            valid_from: activation_height,
+            valid_until: None,
+        });
+        Ok(())
+    }
+
+    /// Advance the verifier's height and drop windows that expired before it
+    pub fn advance_to_height(&mut self, height: Height) {
+        self.current_height = height;
+        for windows in self.sp_operators.values_mut() {
+            windows.retain(|window| window.valid_until.map_or(true, |until| until > height));
+        }
+    }
+
+    pub fn current_height(&self) -> Height {
+        self.current_height
+    }
+
+    /// Key an operator signs with at the given height
+    pub fn public_key_at(&self, operator_name: &str, height: Height) -> Result<&BLSPublicKey> {
+        let windows = self.sp_operators.get(operator_name)
+            .ok_or_else(|| BlockchainError::Crypto(format!("Unknown operator: {}", operator_name)))?;
+
+        windows.iter()
+            .find(|window| window.is_valid_at(height))
+            .map(|window| &window.public_key)
+            .ok_or_else(|| BlockchainError::Crypto(format!(
+                "Operator {} has no valid key at height {}", operator_name, height
+            )))
+    }
+
+    /// Key validity windows for an operator
+    pub fn key_windows(&self, operator_name: &str) -> Option<&[KeyValidityWindow]> {
+        self.sp_operators.get(operator_name).map(|windows| windows.as_slice())
     }
 
     /// Verify operator signature for CDR/settlement data
@@ -204,8 +299,18 @@ impl BLSVerifier {
         message: &[u8],
         signature_bytes: &[u8],
     ) -> Result<bool> {
-        let public_key = self.sp_operators.get(operator_name)
-            .ok_or_else(|| BlockchainError::Crypto(format!("Unknown operator: {}", operator_name)))?;
+        self.verify_operator_signature_at(operator_name, self.current_height, message, signature_bytes)
+    }
+
+    /// Verify operator signature against the key valid at `height`
+    pub fn verify_operator_signature_at(
+        &self,
+        operator_name: &str,
+        height: Height,
+        message: &[u8],
+        signature_bytes: &[u8],
+    ) -> Result<bool> {
+        let public_key = self.public_key_at(operator_name, height)?;
 
         let signature = BLSSignature::from_bytes(signature_bytes)?;
         signature.verify(public_key, message)
@@ -217,6 +322,17 @@ impl BLSVerifier {
         operator_names: &[String],
         message: &[u8],
         aggregate_signature_bytes: &[u8],
+    ) -> Result<bool> {
+        self.verify_multi_party_signature_at(operator_names, self.current_height, message, aggregate_signature_bytes)
+    }
+
+    /// Verify multi-party signature against the keys valid at `height`
+    pub fn verify_multi_party_signature_at(
+        &self,
+        operator_names: &[String],
+        height: Height,
+        message: &[u8],
+        aggregate_signature_bytes: &[u8],
     ) -> Result<bool> {
         if operator_names.is_empty() {
             return Err(BlockchainError::Crypto("No operators specified".to_string()));
@@ -225,8 +341,7 @@ impl BLSVerifier {
         // Collect public keys
         let mut public_keys = Vec::new();
         for operator_name in operator_names {
-            let pubkey = self.sp_operators.get(operator_name)
-                .ok_or_else(|| BlockchainError::Crypto(format!("Unknown operator: {}", operator_name)))?;
+            let pubkey = self.public_key_at(operator_name, height)?;
 
             let blst_pubkey = PublicKey::from_bytes(&pubkey.compressed)
                 .map_err(|_| BlockchainError::Crypto("Invalid operator public key".to_string()))?;
@@ -322,6 +437,26 @@ pub fn aggregate_public_keys(public_keys: &[BLSPublicKey]) -> Result<BLSPublicKe
     })
 }
 
+/// Load the validator's BLS key from `path`, generating and saving one on first start
+pub fn load_or_generate_bls_key(path: &std::path::Path) -> Result<BLSPrivateKey> {
+    if path.exists() {
+        let bytes = std::fs::read(path).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+        return BLSPrivateKey::from_bytes(&bytes);
+    }
+
+    // The file keeps the key material `from_bytes` derives the key from
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed)
+        .map_err(|e| BlockchainError::Crypto(format!("RNG failed: {}", e)))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+    }
+    std::fs::write(path, seed).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+
+    tracing::info!("🔑 Generated validator BLS key at {}", path.display());
+    BLSPrivateKey::from_bytes(&seed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -421,4 +556,35 @@ mod tests {
 
         println!("✅ SP Consortium BLS workflow test passed!");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_key_rotation_windows() {
+        let mut verifier = BLSVerifier::new();
+        let old_sk = BLSPrivateKey::generate().unwrap();
+        let new_sk = BLSPrivateKey::generate().unwrap();
+        verifier.register_operator("Orange-FR", old_sk.public_key());
+
+        let activation = crate::primitives::Policy::next_election_block(100);
+        verifier.schedule_key_rotation("Orange-FR", new_sk.public_key(), activation).unwrap();
+
+        let message = b"prevote";
+        let old_sig = old_sk.sign(message).unwrap();
+        let new_sig = new_sk.sign(message).unwrap();
+
+        // Old key signs until the election block, new key from it onwards
+        assert!(verifier.verify_operator_signature_at("Orange-FR", activation - 1, message, old_sig.to_bytes()).unwrap());
+        assert!(!verifier.verify_operator_signature_at("Orange-FR", activation - 1, message, new_sig.to_bytes()).unwrap());
+        assert!(verifier.verify_operator_signature_at("Orange-FR", activation, message, new_sig.to_bytes()).unwrap());
+        assert!(!verifier.verify_operator_signature_at("Orange-FR", activation, message, old_sig.to_bytes()).unwrap());
+
+        // A second rotation cannot activate before the pending one
+        let other_sk = BLSPrivateKey::generate().unwrap();
+        assert!(verifier.schedule_key_rotation("Orange-FR", other_sk.public_key(), activation).is_err());
+
+        // Expired windows are pruned once the chain moves past them
+        verifier.advance_to_height(activation);
+        assert_eq!(verifier.key_windows("Orange-FR").unwrap().len(), 1);
+        assert!(verifier.verify_operator_signature("Orange-FR", message, new_sig.to_bytes()).unwrap());
+        assert!(verifier.public_key_at("Orange-FR", activation - 1).is_err());
+    }
+}
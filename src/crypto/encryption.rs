@@ -0,0 +1,458 @@
+// Encrypted CDR payloads for on-chain CDR transactions
+// Home and visited operators derive a pairwise session key from static X25519
+// keys (HKDF-SHA256), payloads are sealed with XChaCha20-Poly1305, and the
+// session key is escrowed to each consortium auditor ECIES-style. Validator signing
+// keys are escrowed the same way to hot-standby nodes
+
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    Key, KeyInit, XChaCha20Poly1305, XNonce,
+};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+use crate::primitives::{Blake2bHash, Result, BlockchainError};
+
+// HKDF info labels, one per key purpose
+const SESSION_KEY_INFO: &[u8] = b"SP_CDR_PAIRWISE_SESSION_KEY";
+const ESCROW_KEY_INFO: &[u8] = b"SP_CDR_AUDITOR_KEY_ESCROW";
+const VALIDATOR_ESCROW_KEY_INFO: &[u8] = b"SP_CDR_VALIDATOR_KEY_ESCROW";
+
+/// X25519 key pair an operator or auditor uses for CDR encryption
+#[derive(Clone)]
+pub struct EncryptionKeyPair {
+    secret: StaticSecret,
+    public: EncryptionPublicKey,
+}
+
+/// X25519 public encryption key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EncryptionPublicKey(#[serde(with = "hex")] [u8; 32]);
+
+/// Symmetric key shared by exactly one home/visited operator pair
+#[derive(Clone)]
+pub struct SessionKey {
+    key: [u8; 32],
+    key_id: Blake2bHash,
+}
+
+/// Session key wrapped for one auditor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowedKey {
+    pub auditor_id: String,
+    pub ephemeral_public: EncryptionPublicKey,
+    #[serde(with = "hex")]
+    pub nonce: [u8; 24],
+    pub wrapped_key: Vec<u8>,
+}
+
+/// Validator key material sealed to a hot-standby node's encryption key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorKeyEscrow {
+    /// Validator the key signs for, bound to the ciphertext
+    pub validator_id: String,
+    pub standby_key: EncryptionPublicKey,
+    pub ephemeral_public: EncryptionPublicKey,
+    #[serde(with = "hex")]
+    pub nonce: [u8; 24],
+    pub sealed_key: Vec<u8>,
+}
+
+/// CDR payload as stored in `CDRTransaction.encrypted_data`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedCDRPayload {
+    pub home_network: String,
+    pub visited_network: String,
+    /// Hash of the session key, so receivers detect key mismatches before decrypting
+    pub session_key_id: Blake2bHash,
+    #[serde(with = "hex")]
+    pub nonce: [u8; 24],
+    pub ciphertext: Vec<u8>,
+    pub escrow: Vec<EscrowedKey>,
+}
+
+impl EncryptionKeyPair {
+    pub fn generate() -> Self {
+        Self::from_secret(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 32] = bytes.try_into()
+            .map_err(|_| BlockchainError::Crypto("Encryption private key must be 32 bytes".to_string()))?;
+        Ok(Self::from_secret(StaticSecret::from(bytes)))
+    }
+
+    fn from_secret(secret: StaticSecret) -> Self {
+        let public = EncryptionPublicKey(X25519PublicKey::from(&secret).to_bytes());
+        Self { secret, public }
+    }
+
+    pub fn public_key(&self) -> EncryptionPublicKey {
+        self.public
+    }
+
+    /// Export private key bytes (for storage - handle with care!)
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.secret.to_bytes()
+    }
+
+    /// Derive the session key shared with the counterparty of a network pair
+    /// Both sides obtain the same key regardless of which one is home
+    pub fn derive_session_key(
+        &self,
+        peer: &EncryptionPublicKey,
+        home_network: &str,
+        visited_network: &str,
+    ) -> Result<SessionKey> {
+        let shared = self.diffie_hellman(peer)?;
+
+        let (first, second) = if self.public.0 <= peer.0 {
+            (&self.public, peer)
+        } else {
+            (peer, &self.public)
+        };
+
+        let mut info = SESSION_KEY_INFO.to_vec();
+        info.extend_from_slice(&first.0);
+        info.extend_from_slice(&second.0);
+
+        let salt = format!("{}|{}", home_network, visited_network);
+        let key = hkdf_expand(salt.as_bytes(), &shared, &info)?;
+        Ok(SessionKey::from_bytes(key))
+    }
+
+    fn diffie_hellman(&self, peer: &EncryptionPublicKey) -> Result<[u8; 32]> {
+        let shared = self.secret.diffie_hellman(&X25519PublicKey::from(peer.0));
+        // Low-order peer keys would make the shared secret predictable
+        if !shared.was_contributory() {
+            return Err(BlockchainError::Crypto("Non-contributory X25519 key exchange".to_string()));
+        }
+        Ok(shared.to_bytes())
+    }
+}
+
+impl std::fmt::Debug for EncryptionKeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptionKeyPair").field("public", &self.public).finish()
+    }
+}
+
+impl EncryptionPublicKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 32] = bytes.try_into()
+            .map_err(|_| BlockchainError::Crypto("Encryption public key must be 32 bytes".to_string()))?;
+        Ok(Self(bytes))
+    }
+
+    pub fn to_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+}
+
+impl SessionKey {
+    fn from_bytes(key: [u8; 32]) -> Self {
+        let key_id = crate::primitives::primitives::hash_data(&key);
+        Self { key, key_id }
+    }
+
+    pub fn key_id(&self) -> Blake2bHash {
+        self.key_id
+    }
+}
+
+impl EncryptedCDRPayload {
+    /// Encode for `CDRTransaction.encrypted_data`
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self)
+            .map_err(|e| BlockchainError::Serialization(format!("Encrypted CDR encoding failed: {}", e)))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("Encrypted CDR decoding failed: {}", e)))
+    }
+
+    /// Associated data binding the ciphertext to its network pair and key
+    fn associated_data(&self) -> Vec<u8> {
+        associated_data(&self.home_network, &self.visited_network, &self.session_key_id)
+    }
+
+    fn open(&self, session_key: &SessionKey) -> Result<Vec<u8>> {
+        if session_key.key_id != self.session_key_id {
+            return Err(BlockchainError::Crypto("CDR payload was encrypted under a different session key".to_string()));
+        }
+        let aad = self.associated_data();
+        open(&session_key.key, &self.nonce, &self.ciphertext, &aad)
+    }
+
+    /// Decrypt with an auditor's escrowed copy of the session key
+    pub fn decrypt_escrowed(&self, auditor_id: &str, auditor_key: &EncryptionKeyPair) -> Result<Vec<u8>> {
+        let escrowed = self.escrow.iter()
+            .find(|escrowed| escrowed.auditor_id == auditor_id)
+            .ok_or_else(|| BlockchainError::Crypto(format!("No escrowed key for auditor {}", auditor_id)))?;
+
+        let shared = auditor_key.diffie_hellman(&escrowed.ephemeral_public)?;
+        let wrapping_key = escrow_wrapping_key(ESCROW_KEY_INFO, &shared, &escrowed.ephemeral_public, &auditor_key.public)?;
+
+        let key = open(&wrapping_key, &escrowed.nonce, &escrowed.wrapped_key, self.session_key_id.as_bytes())?;
+        let key: [u8; 32] = key.try_into()
+            .map_err(|_| BlockchainError::Crypto("Escrowed session key has wrong length".to_string()))?;
+
+        self.open(&SessionKey::from_bytes(key))
+    }
+}
+
+impl ValidatorKeyEscrow {
+    /// Seal `key_material` for the standby node holding `standby_key`
+    pub fn seal(validator_id: &str, key_material: &[u8], standby_key: &EncryptionPublicKey) -> Result<Self> {
+        let ephemeral = EncryptionKeyPair::generate();
+        let shared = ephemeral.diffie_hellman(standby_key)?;
+        let wrapping_key = escrow_wrapping_key(VALIDATOR_ESCROW_KEY_INFO, &shared, &ephemeral.public, standby_key)?;
+
+        let (nonce, sealed_key) = seal(&wrapping_key, key_material, validator_id.as_bytes())?;
+        Ok(Self {
+            validator_id: validator_id.to_string(),
+            standby_key: *standby_key,
+            ephemeral_public: ephemeral.public,
+            nonce,
+            sealed_key,
+        })
+    }
+
+    /// Recover the key material with the standby node's key
+    pub fn open(&self, standby: &EncryptionKeyPair) -> Result<Vec<u8>> {
+        if standby.public != self.standby_key {
+            return Err(BlockchainError::Crypto("Validator key was escrowed to a different standby key".to_string()));
+        }
+        let shared = standby.diffie_hellman(&self.ephemeral_public)?;
+        let wrapping_key = escrow_wrapping_key(VALIDATOR_ESCROW_KEY_INFO, &shared, &self.ephemeral_public, &standby.public)?;
+        open(&wrapping_key, &self.nonce, &self.sealed_key, self.validator_id.as_bytes())
+    }
+}
+
+/// Load an X25519 key pair from `path`, generating and saving one on first start
+pub fn load_or_generate_encryption_key(path: &std::path::Path) -> Result<EncryptionKeyPair> {
+    if path.exists() {
+        let bytes = std::fs::read(path).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+        return EncryptionKeyPair::from_bytes(&bytes);
+    }
+
+    let key = EncryptionKeyPair::generate();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+    }
+    std::fs::write(path, key.to_bytes()).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+
+    tracing::info!("🔑 Generated encryption key at {}", path.display());
+    Ok(key)
+}
+
+/// CDR encryption for one operator: seals payloads for its roaming partners
+/// and escrows every session key to the registered auditors
+pub struct CDREncryption {
+    local_network: String,
+    local_key: EncryptionKeyPair,
+    /// Encryption keys of partner operators by network name
+    operator_keys: HashMap<String, EncryptionPublicKey>,
+    /// Encryption keys of consortium auditors by auditor id
+    auditor_keys: HashMap<String, EncryptionPublicKey>,
+}
+
+impl CDREncryption {
+    pub fn new(local_network: &str, local_key: EncryptionKeyPair) -> Self {
+        Self {
+            local_network: local_network.to_string(),
+            local_key,
+            operator_keys: HashMap::new(),
+            auditor_keys: HashMap::new(),
+        }
+    }
+
+    pub fn public_key(&self) -> EncryptionPublicKey {
+        self.local_key.public_key()
+    }
+
+    /// Register a partner operator's encryption key
+    pub fn register_operator_key(&mut self, network: &str, public_key: EncryptionPublicKey) {
+        self.operator_keys.insert(network.to_string(), public_key);
+    }
+
+    /// Register an auditor that receives an escrowed copy of every session key
+    pub fn register_auditor(&mut self, auditor_id: &str, public_key: EncryptionPublicKey) {
+        self.auditor_keys.insert(auditor_id.to_string(), public_key);
+    }
+
+    /// Session key for a network pair this operator is part of
+    pub fn session_key(&self, home_network: &str, visited_network: &str) -> Result<SessionKey> {
+        let peer_network = if home_network == self.local_network {
+            visited_network
+        } else if visited_network == self.local_network {
+            home_network
+        } else {
+            return Err(BlockchainError::Crypto(format!(
+                "{} is not a party to {} -> {}", self.local_network, home_network, visited_network
+            )));
+        };
+
+        let peer_key = self.operator_keys.get(peer_network)
+            .ok_or_else(|| BlockchainError::Crypto(format!("No encryption key for operator {}", peer_network)))?;
+
+        self.local_key.derive_session_key(peer_key, home_network, visited_network)
+    }
+
+    /// Encrypt a CDR payload for the two operators of a network pair
+    pub fn encrypt(&self, home_network: &str, visited_network: &str, plaintext: &[u8]) -> Result<EncryptedCDRPayload> {
+        let session_key = self.session_key(home_network, visited_network)?;
+        let aad = associated_data(home_network, visited_network, &session_key.key_id);
+        let (nonce, ciphertext) = seal(&session_key.key, plaintext, &aad)?;
+
+        // Sorted so the payload encoding is deterministic for a given set of auditors
+        let mut auditors: Vec<_> = self.auditor_keys.iter().collect();
+        auditors.sort_by(|a, b| a.0.cmp(b.0));
+
+        let escrow = auditors.into_iter()
+            .map(|(auditor_id, auditor_key)| escrow_session_key(auditor_id, auditor_key, &session_key))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(EncryptedCDRPayload {
+            home_network: home_network.to_string(),
+            visited_network: visited_network.to_string(),
+            session_key_id: session_key.key_id,
+            nonce,
+            ciphertext,
+            escrow,
+        })
+    }
+
+    /// Decrypt a CDR payload addressed to this operator's network pair
+    pub fn decrypt(&self, payload: &EncryptedCDRPayload) -> Result<Vec<u8>> {
+        let session_key = self.session_key(&payload.home_network, &payload.visited_network)?;
+        payload.open(&session_key)
+    }
+}
+
+/// Wrap a session key for an auditor under a fresh ephemeral key
+fn escrow_session_key(auditor_id: &str, auditor_key: &EncryptionPublicKey, session_key: &SessionKey) -> Result<EscrowedKey> {
+    let ephemeral = EncryptionKeyPair::generate();
+    let shared = ephemeral.diffie_hellman(auditor_key)?;
+    let wrapping_key = escrow_wrapping_key(ESCROW_KEY_INFO, &shared, &ephemeral.public, auditor_key)?;
+
+    let (nonce, wrapped_key) = seal(&wrapping_key, &session_key.key, session_key.key_id.as_bytes())?;
+
+    Ok(EscrowedKey {
+        auditor_id: auditor_id.to_string(),
+        ephemeral_public: ephemeral.public,
+        nonce,
+        wrapped_key,
+    })
+}
+
+fn escrow_wrapping_key(label: &[u8], shared: &[u8; 32], ephemeral: &EncryptionPublicKey, recipient: &EncryptionPublicKey) -> Result<[u8; 32]> {
+    let mut info = label.to_vec();
+    info.extend_from_slice(&ephemeral.0);
+    info.extend_from_slice(&recipient.0);
+    hkdf_expand(&[], shared, &info)
+}
+
+fn associated_data(home_network: &str, visited_network: &str, key_id: &Blake2bHash) -> Vec<u8> {
+    let mut aad = Vec::new();
+    aad.extend_from_slice(&(home_network.len() as u64).to_le_bytes());
+    aad.extend_from_slice(home_network.as_bytes());
+    aad.extend_from_slice(&(visited_network.len() as u64).to_le_bytes());
+    aad.extend_from_slice(visited_network.as_bytes());
+    aad.extend_from_slice(key_id.as_bytes());
+    aad
+}
+
+fn hkdf_expand(salt: &[u8], ikm: &[u8], info: &[u8]) -> Result<[u8; 32]> {
+    let mut okm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut okm)
+        .map_err(|_| BlockchainError::Crypto("HKDF expansion failed".to_string()))?;
+    Ok(okm)
+}
+
+/// Seal with a random 192-bit nonce, safe to pick at random under a long-lived key
+fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<([u8; 24], Vec<u8>)> {
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    let ciphertext = cipher.encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| BlockchainError::Crypto("CDR payload encryption failed".to_string()))?;
+    Ok((nonce, ciphertext))
+}
+
+fn open(key: &[u8; 32], nonce: &[u8; 24], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    cipher.decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| BlockchainError::Crypto("CDR payload authentication failed".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairwise_encryption_with_auditor_escrow() {
+        let tmobile_key = EncryptionKeyPair::generate();
+        let vodafone_key = EncryptionKeyPair::generate();
+        let orange_key = EncryptionKeyPair::generate();
+        let auditor_key = EncryptionKeyPair::generate();
+
+        let mut tmobile = CDREncryption::new("T-Mobile-DE", tmobile_key.clone());
+        tmobile.register_operator_key("Vodafone-UK", vodafone_key.public_key());
+        tmobile.register_auditor("BNetzA", auditor_key.public_key());
+
+        let mut vodafone = CDREncryption::new("Vodafone-UK", vodafone_key);
+        vodafone.register_operator_key("T-Mobile-DE", tmobile_key.public_key());
+
+        let mut orange = CDREncryption::new("Orange-FR", orange_key);
+        orange.register_operator_key("T-Mobile-DE", tmobile_key.public_key());
+        orange.register_operator_key("Vodafone-UK", vodafone.public_key());
+
+        let record = b"IMSI 262011234567890, 300s, 12MB, 4500 cents";
+        let payload = tmobile.encrypt("T-Mobile-DE", "Vodafone-UK", record).unwrap();
+        let payload = EncryptedCDRPayload::from_bytes(&payload.to_bytes().unwrap()).unwrap();
+
+        // Both counterparties and the auditor can read the record
+        assert_eq!(vodafone.decrypt(&payload).unwrap(), record);
+        assert_eq!(tmobile.decrypt(&payload).unwrap(), record);
+        assert_eq!(payload.decrypt_escrowed("BNetzA", &auditor_key).unwrap(), record);
+
+        // A third operator is not a party to the pair
+        assert!(orange.decrypt(&payload).is_err());
+        assert!(payload.decrypt_escrowed("BNetzA", &EncryptionKeyPair::generate()).is_err());
+
+        // Swapping home and visited selects a different session key
+        let mut relabelled = payload.clone();
+        std::mem::swap(&mut relabelled.home_network, &mut relabelled.visited_network);
+        assert!(vodafone.decrypt(&relabelled).is_err());
+
+        let mut tampered = payload;
+        tampered.ciphertext[0] ^= 1;
+        assert!(vodafone.decrypt(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_validator_key_escrow() {
+        let standby = EncryptionKeyPair::generate();
+        let key_material = [7u8; 32];
+        let escrow = ValidatorKeyEscrow::seal("12D3KooWPrimary", &key_material, &standby.public_key()).unwrap();
+        assert_eq!(escrow.open(&standby).unwrap(), key_material);
+
+        // Only the standby can open it, and only for the validator it was sealed for
+        assert!(escrow.open(&EncryptionKeyPair::generate()).is_err());
+        let mut relabelled = escrow;
+        relabelled.validator_id = "12D3KooWOther".to_string();
+        assert!(relabelled.open(&standby).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+// PKCS#11 signing backend: the validator's BLS key stays in the HSM, which signs through the
+// vendor's BLS12-381 mechanism. Only the public key is read at startup. The vendor module is
+// loaded at runtime and called through the few Cryptoki functions signing needs
+use std::ffi::c_void;
+use std::os::raw::c_ulong;
+use std::sync::Mutex;
+use libloading::Library;
+use tracing::info;
+
+use super::bls::{BLSPublicKey, BLSSignature};
+use super::keys::Signer;
+use super::{CryptoError, Result};
+
+/// Where the validator key lives in the HSM
+#[derive(Debug, Clone)]
+pub struct Pkcs11Config {
+    /// Vendor PKCS#11 module, e.g. /usr/lib/libCryptoki2_64.so
+    pub module_path: String,
+    /// Slot of the token holding the key
+    pub slot_index: usize,
+    pub pin: String,
+    /// CKA_LABEL of the key pair
+    pub key_label: String,
+    /// Vendor-defined mechanism (offset from CKM_VENDOR_DEFINED) signing BLS12-381 min-pk
+    pub bls_mechanism: u64,
+}
+
+type CkUlong = c_ulong;
+type CkRv = CkUlong;
+
+const CKR_OK: CkRv = 0;
+const CKR_USER_ALREADY_LOGGED_IN: CkRv = 0x100;
+const CKR_CRYPTOKI_ALREADY_INITIALIZED: CkRv = 0x191;
+const CKF_OS_LOCKING_OK: CkUlong = 0x2;
+const CKF_SERIAL_SESSION: CkUlong = 0x4;
+const CKU_USER: CkUlong = 1;
+const CKA_CLASS: CkUlong = 0x0;
+const CKA_LABEL: CkUlong = 0x3;
+const CKA_VALUE: CkUlong = 0x11;
+const CKO_PUBLIC_KEY: CkUlong = 2;
+const CKO_PRIVATE_KEY: CkUlong = 3;
+const CKM_VENDOR_DEFINED: CkUlong = 0x8000_0000;
+
+#[repr(C)]
+struct CkInitializeArgs {
+    create_mutex: *mut c_void,
+    destroy_mutex: *mut c_void,
+    lock_mutex: *mut c_void,
+    unlock_mutex: *mut c_void,
+    flags: CkUlong,
+    reserved: *mut c_void,
+}
+
+#[repr(C)]
+struct CkAttribute {
+    kind: CkUlong,
+    value: *mut c_void,
+    value_len: CkUlong,
+}
+
+#[repr(C)]
+struct CkMechanism {
+    mechanism: CkUlong,
+    parameter: *mut c_void,
+    parameter_len: CkUlong,
+}
+
+type Unused = Option<unsafe extern "C" fn()>;
+
+/// `CK_FUNCTION_LIST` up to `C_Sign`, the entries after it are never read
+#[repr(C)]
+struct CkFunctionList {
+    version: [u8; 2],
+    initialize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+    finalize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+    _get_info: Unused,
+    _get_function_list: Unused,
+    get_slot_list: unsafe extern "C" fn(u8, *mut CkUlong, *mut CkUlong) -> CkRv,
+    _get_slot_info: Unused,
+    _get_token_info: Unused,
+    _get_mechanism_list: Unused,
+    _get_mechanism_info: Unused,
+    _init_token: Unused,
+    _init_pin: Unused,
+    _set_pin: Unused,
+    open_session: unsafe extern "C" fn(CkUlong, CkUlong, *mut c_void, *mut c_void, *mut CkUlong) -> CkRv,
+    close_session: unsafe extern "C" fn(CkUlong) -> CkRv,
+    _close_all_sessions: Unused,
+    _get_session_info: Unused,
+    _get_operation_state: Unused,
+    _set_operation_state: Unused,
+    login: unsafe extern "C" fn(CkUlong, CkUlong, *const u8, CkUlong) -> CkRv,
+    _logout: Unused,
+    _create_object: Unused,
+    _copy_object: Unused,
+    _destroy_object: Unused,
+    _get_object_size: Unused,
+    get_attribute_value: unsafe extern "C" fn(CkUlong, CkUlong, *mut CkAttribute, CkUlong) -> CkRv,
+    _set_attribute_value: Unused,
+    find_objects_init: unsafe extern "C" fn(CkUlong, *const CkAttribute, CkUlong) -> CkRv,
+    find_objects: unsafe extern "C" fn(CkUlong, *mut CkUlong, CkUlong, *mut CkUlong) -> CkRv,
+    find_objects_final: unsafe extern "C" fn(CkUlong) -> CkRv,
+    _encrypt_init: Unused,
+    _encrypt: Unused,
+    _encrypt_update: Unused,
+    _encrypt_final: Unused,
+    _decrypt_init: Unused,
+    _decrypt: Unused,
+    _decrypt_update: Unused,
+    _decrypt_final: Unused,
+    _digest_init: Unused,
+    _digest: Unused,
+    _digest_update: Unused,
+    _digest_key: Unused,
+    _digest_final: Unused,
+    sign_init: unsafe extern "C" fn(CkUlong, *const CkMechanism, CkUlong) -> CkRv,
+    sign: unsafe extern "C" fn(CkUlong, *const u8, CkUlong, *mut u8, *mut CkUlong) -> CkRv,
+}
+
+fn check(function: &str, rv: CkRv) -> Result<()> {
+    match rv {
+        CKR_OK => Ok(()),
+        rv => Err(CryptoError::SigningFailed(format!("PKCS#11: {} returned 0x{:x}", function, rv))),
+    }
+}
+
+/// Signer backed by a PKCS#11 token
+pub struct Pkcs11Signer {
+    functions: *const CkFunctionList,
+    // A session is single-threaded, signing requests take turns
+    session: Mutex<CkUlong>,
+    private_key: CkUlong,
+    public_key: BLSPublicKey,
+    mechanism: CkUlong,
+    // Kept loaded while the function list is in use
+    _module: Library,
+}
+
+// The function list is immutable once returned and the session is only used under its lock
+unsafe impl Send for Pkcs11Signer {}
+unsafe impl Sync for Pkcs11Signer {}
+
+impl Pkcs11Signer {
+    /// Log in to the token and find the key pair labelled `config.key_label`
+    pub fn open(config: &Pkcs11Config) -> Result<Self> {
+        // SAFETY: loading the vendor module runs its initializers, it is trusted like the HSM itself
+        let module = unsafe { Library::new(&config.module_path) }
+            .map_err(|e| CryptoError::SigningFailed(format!("Cannot load PKCS#11 module {}: {}", config.module_path, e)))?;
+        let functions = unsafe {
+            let get_function_list = module.get::<unsafe extern "C" fn(*mut *const CkFunctionList) -> CkRv>(b"C_GetFunctionList\0")
+                .map_err(|e| CryptoError::SigningFailed(format!("Not a PKCS#11 module: {}", e)))?;
+            let mut functions = std::ptr::null();
+            check("C_GetFunctionList", get_function_list(&mut functions))?;
+            if functions.is_null() {
+                return Err(CryptoError::SigningFailed("PKCS#11 module returned no function list".to_string()));
+            }
+            functions
+        };
+        // SAFETY: the list stays valid while the module is loaded
+        let f = unsafe { &*functions };
+
+        unsafe {
+            let mut args = CkInitializeArgs {
+                create_mutex: std::ptr::null_mut(),
+                destroy_mutex: std::ptr::null_mut(),
+                lock_mutex: std::ptr::null_mut(),
+                unlock_mutex: std::ptr::null_mut(),
+                flags: CKF_OS_LOCKING_OK,
+                reserved: std::ptr::null_mut(),
+            };
+            match (f.initialize)(&mut args as *mut CkInitializeArgs as *mut c_void) {
+                CKR_CRYPTOKI_ALREADY_INITIALIZED => {}
+                rv => check("C_Initialize", rv)?,
+            }
+        }
+
+        let slot = unsafe {
+            let mut count: CkUlong = 0;
+            check("C_GetSlotList", (f.get_slot_list)(1, std::ptr::null_mut(), &mut count))?;
+            let mut slots = vec![0 as CkUlong; count as usize];
+            check("C_GetSlotList", (f.get_slot_list)(1, slots.as_mut_ptr(), &mut count))?;
+            slots.truncate(count as usize);
+            *slots.get(config.slot_index)
+                .ok_or_else(|| CryptoError::SigningFailed(format!("No PKCS#11 token in slot {}", config.slot_index)))?
+        };
+
+        let mut session: CkUlong = 0;
+        unsafe {
+            check("C_OpenSession", (f.open_session)(slot, CKF_SERIAL_SESSION, std::ptr::null_mut(), std::ptr::null_mut(), &mut session))?;
+            match (f.login)(session, CKU_USER, config.pin.as_ptr(), config.pin.len() as CkUlong) {
+                CKR_USER_ALREADY_LOGGED_IN => {}
+                rv => check("C_Login", rv)?,
+            }
+        }
+
+        let find = |class: CkUlong| -> Result<CkUlong> {
+            let mut class = class;
+            let mut label = config.key_label.as_bytes().to_vec();
+            let template = [
+                CkAttribute { kind: CKA_CLASS, value: &mut class as *mut CkUlong as *mut c_void, value_len: std::mem::size_of::<CkUlong>() as CkUlong },
+                CkAttribute { kind: CKA_LABEL, value: label.as_mut_ptr() as *mut c_void, value_len: label.len() as CkUlong },
+            ];
+            let (mut handle, mut found): (CkUlong, CkUlong) = (0, 0);
+            unsafe {
+                check("C_FindObjectsInit", (f.find_objects_init)(session, template.as_ptr(), template.len() as CkUlong))?;
+                let rv = (f.find_objects)(session, &mut handle, 1, &mut found);
+                check("C_FindObjectsFinal", (f.find_objects_final)(session))?;
+                check("C_FindObjects", rv)?;
+            }
+            if found == 0 {
+                return Err(CryptoError::SigningFailed(format!("No key {} on the token", config.key_label)));
+            }
+            Ok(handle)
+        };
+        let private_key = find(CKO_PRIVATE_KEY)?;
+        let public_key_handle = find(CKO_PUBLIC_KEY)?;
+
+        let public_key = unsafe {
+            let mut attribute = CkAttribute { kind: CKA_VALUE, value: std::ptr::null_mut(), value_len: 0 };
+            check("C_GetAttributeValue", (f.get_attribute_value)(session, public_key_handle, &mut attribute, 1))?;
+            let mut value = vec![0u8; attribute.value_len as usize];
+            attribute.value = value.as_mut_ptr() as *mut c_void;
+            check("C_GetAttributeValue", (f.get_attribute_value)(session, public_key_handle, &mut attribute, 1))?;
+            value.truncate(attribute.value_len as usize);
+            BLSPublicKey::from_bytes(&value).map_err(|_| CryptoError::InvalidPublicKey)?
+        };
+
+        info!("🔐 Signing with HSM key {} ({})", config.key_label, public_key.to_hex());
+        Ok(Self {
+            functions,
+            session: Mutex::new(session),
+            private_key,
+            public_key,
+            mechanism: CKM_VENDOR_DEFINED + config.bls_mechanism as CkUlong,
+            _module: module,
+        })
+    }
+}
+
+impl Drop for Pkcs11Signer {
+    fn drop(&mut self) {
+        let f = unsafe { &*self.functions };
+        if let Ok(session) = self.session.lock() {
+            unsafe {
+                (f.close_session)(*session);
+                (f.finalize)(std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for Pkcs11Signer {
+    fn public_key(&self) -> BLSPublicKey {
+        self.public_key.clone()
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<BLSSignature> {
+        let session = self.session.lock().map_err(|_| CryptoError::SigningFailed("HSM session poisoned".to_string()))?;
+        let f = unsafe { &*self.functions };
+        let mechanism = CkMechanism { mechanism: self.mechanism, parameter: std::ptr::null_mut(), parameter_len: 0 };
+        // Compressed G2 signature, with room for vendors returning it uncompressed
+        let mut signature = vec![0u8; 192];
+        let mut signature_len = signature.len() as CkUlong;
+        unsafe {
+            check("C_SignInit", (f.sign_init)(*session, &mechanism, self.private_key))?;
+            check("C_Sign", (f.sign)(*session, message.as_ptr(), message.len() as CkUlong, signature.as_mut_ptr(), &mut signature_len))?;
+        }
+        signature.truncate(signature_len as usize);
+        BLSSignature::from_bytes(&signature).map_err(|_| CryptoError::InvalidSignature)
+    }
+}
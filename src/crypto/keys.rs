@@ -8,6 +8,7 @@ use super::{
     PrivateKey, PublicKey, CompressedPublicKey, 
     CryptoError, Result
 };
+use super::bls::{BLSPrivateKey, BLSPublicKey, BLSSignature};
 
 /// Key pair for validators and network operators
 #[derive(Clone, Debug)]
@@ -304,10 +305,44 @@ impl Default for KeyManager {
     }
 }
 
+/// Signs with a validator's BLS key wherever it is held: in memory, in an HSM or by a remote signer
+/// Consensus and settlement messaging sign through this, never with the raw key
+#[async_trait::async_trait]
+pub trait Signer: Send + Sync {
+    /// Public key signatures verify against
+    fn public_key(&self) -> BLSPublicKey;
+
+    /// BLS signature over `message`
+    async fn sign(&self, message: &[u8]) -> Result<BLSSignature>;
+}
+
+/// In-memory key, for development and for operators accepting keys on disk
+#[async_trait::async_trait]
+impl Signer for BLSPrivateKey {
+    fn public_key(&self) -> BLSPublicKey {
+        BLSPrivateKey::public_key(self)
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<BLSSignature> {
+        BLSPrivateKey::sign(self, message).map_err(|e| CryptoError::SigningFailed(e.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_in_memory_signer() {
+        let key = BLSPrivateKey::generate().unwrap();
+        let signer: std::sync::Arc<dyn Signer> = std::sync::Arc::new(key.clone());
+
+        let signature = signer.sign(b"proposal").await.unwrap();
+        assert_eq!(signer.public_key(), key.public_key());
+        assert!(signature.verify(&signer.public_key(), b"proposal").unwrap());
+        assert!(!signature.verify(&signer.public_key(), b"other").unwrap());
+    }
+
     #[test]
     fn test_keypair_generation() {
         let keypair = KeyPair::generate().unwrap();
@@ -4,12 +4,21 @@
 use serde::{Deserialize, Serialize};
 
 pub mod bls;
+pub mod encryption;
 pub mod keys;
 pub mod signatures;
+#[cfg(feature = "hsm")]
+pub mod hsm;
+#[cfg(feature = "grpc")]
+pub mod remote_signer;
 
 pub use bls::{
     BLSPrivateKey, BLSPublicKey, BLSSignature, BLSVerifier,
-    aggregate_signatures, aggregate_public_keys,
+    aggregate_signatures, aggregate_public_keys, load_or_generate_bls_key,
+};
+pub use encryption::{
+    CDREncryption, EncryptedCDRPayload, EncryptionKeyPair, EncryptionPublicKey, ValidatorKeyEscrow,
+    load_or_generate_encryption_key,
 };
 
 // Create wrapper types to handle Result conversion
@@ -226,6 +235,8 @@ pub enum CryptoError {
     AggregationFailed(String),
     #[error("Serialization error: {0}")]
     SerializationError(String),
+    #[error("Signing failed: {0}")]
+    SigningFailed(String),
 }
 
 pub type Result<T> = std::result::Result<T, CryptoError>;
\ No newline at end of file
@@ -0,0 +1,55 @@
+// Remote signing backend: the validator's BLS key is held by the operator's signing service,
+// reached over the `RemoteSigner` gRPC service of proto/sp_cdr.proto
+use tonic::transport::Channel;
+use tracing::info;
+
+use crate::api::grpc::proto::remote_signer_client::RemoteSignerClient;
+use crate::api::grpc::proto::{PublicKeyRequest, SignRequest};
+use super::bls::{BLSPublicKey, BLSSignature};
+use super::keys::Signer;
+use super::{CryptoError, Result};
+
+/// Signer calling a remote signing service
+pub struct RemoteSigner {
+    client: RemoteSignerClient<Channel>,
+    key_id: String,
+    public_key: BLSPublicKey,
+}
+
+impl RemoteSigner {
+    /// Connect to the signing service at `endpoint` and fetch the public key of `key_id`
+    pub async fn connect(endpoint: String, key_id: String) -> Result<Self> {
+        let mut client = RemoteSignerClient::connect(endpoint.clone()).await
+            .map_err(|e| CryptoError::SigningFailed(format!("Remote signer {} unreachable: {}", endpoint, e)))?;
+        let reply = client.get_public_key(PublicKeyRequest { key_id: key_id.clone() }).await
+            .map_err(|e| CryptoError::SigningFailed(format!("Remote signer: {}", e.message())))?;
+        let public_key = BLSPublicKey::from_bytes(&reply.into_inner().public_key)
+            .map_err(|_| CryptoError::InvalidPublicKey)?;
+
+        info!("🔐 Signing with remote key {} at {} ({})", key_id, endpoint, public_key.to_hex());
+        Ok(Self { client, key_id, public_key })
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> BLSPublicKey {
+        self.public_key.clone()
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<BLSSignature> {
+        // Clients share one connection, each call takes its own handle
+        let reply = self.client.clone()
+            .sign(SignRequest { key_id: self.key_id.clone(), message: message.to_vec() })
+            .await
+            .map_err(|e| CryptoError::SigningFailed(format!("Remote signer: {}", e.message())))?;
+        let signature = BLSSignature::from_bytes(&reply.into_inner().signature)
+            .map_err(|_| CryptoError::InvalidSignature)?;
+
+        // A compromised or misconfigured signer must not get a wrong signature out under our name
+        if !signature.verify(&self.public_key, message).unwrap_or(false) {
+            return Err(CryptoError::InvalidSignature);
+        }
+        Ok(signature)
+    }
+}
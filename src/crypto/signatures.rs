@@ -4,10 +4,14 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use crate::primitives::{Blake2bHash, hash_data};
+use blstrs::{G1Projective, G2Projective, Scalar};
+use group::{ff::Field, Group};
+use rand::rngs::OsRng;
 use super::{
     PublicKey, Signature, AggregateSignature, AggregatePublicKey,
     CryptoError, Result
 };
+use super::bls::{BLSPublicKey, BLSSignature, DST};
 
 /// Multi-signature threshold configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +48,186 @@ impl ThresholdConfig {
     }
 }
 
+/// One authorized signer's Shamir share of a t-of-n threshold BLS key
+#[derive(Clone)]
+pub struct ThresholdKeyShare {
+    /// Evaluation point of the share, 1-based
+    pub index: u32,
+    secret: Scalar,
+}
+
+/// Signature by a single key share, combinable into the group signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub signer_index: u32,
+    pub signature: BLSSignature,
+}
+
+/// Public side of a threshold key: the group key plus one verification key per share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdPublicKey {
+    pub config: ThresholdConfig,
+    /// Key the combined signature verifies against
+    pub group_key: BLSPublicKey,
+    /// Verification key of share `i` at position `i - 1`
+    pub verification_keys: Vec<BLSPublicKey>,
+}
+
+/// Deal a fresh t-of-n threshold key to an operator's authorized signers
+/// The dealer sees the group secret, so this runs inside the operator's own HSM boundary
+pub fn generate_threshold_key(config: &ThresholdConfig) -> Result<(ThresholdPublicKey, Vec<ThresholdKeyShare>)> {
+    // Random polynomial of degree t - 1, the group secret is its constant term
+    let coefficients: Vec<Scalar> = (0..config.threshold).map(|_| Scalar::random(OsRng)).collect();
+
+    let shares: Vec<ThresholdKeyShare> = (1..=config.total_signers as u32)
+        .map(|index| {
+            let x = Scalar::from(index as u64);
+            let secret = coefficients.iter().rev().fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient);
+            ThresholdKeyShare { index, secret }
+        })
+        .collect();
+
+    let group_key = g1_public_key(&coefficients[0])?;
+    let verification_keys = shares.iter()
+        .map(|share| share.public_key())
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((
+        ThresholdPublicKey {
+            config: config.clone(),
+            group_key,
+            verification_keys,
+        },
+        shares,
+    ))
+}
+
+fn g1_public_key(secret: &Scalar) -> Result<BLSPublicKey> {
+    let point = G1Projective::generator() * secret;
+    BLSPublicKey::from_bytes(&point.to_compressed())
+        .map_err(|_| CryptoError::InvalidPublicKey)
+}
+
+impl ThresholdKeyShare {
+    /// Verification key for this share
+    pub fn public_key(&self) -> Result<BLSPublicKey> {
+        g1_public_key(&self.secret)
+    }
+
+    /// Sign with this share, verifiable like any BLS signature under `public_key`
+    pub fn sign(&self, message: &[u8]) -> Result<PartialSignature> {
+        let point = G2Projective::hash_to_curve(message, DST, &[]) * self.secret;
+        let signature = BLSSignature::from_bytes(&point.to_compressed())
+            .map_err(|_| CryptoError::InvalidSignature)?;
+
+        Ok(PartialSignature {
+            signer_index: self.index,
+            signature,
+        })
+    }
+
+    /// Export share bytes (for storage - handle with care!)
+    pub fn to_bytes(&self) -> [u8; 36] {
+        let mut bytes = [0u8; 36];
+        bytes[..4].copy_from_slice(&self.index.to_be_bytes());
+        bytes[4..].copy_from_slice(&self.secret.to_bytes_be());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 36 {
+            return Err(CryptoError::InvalidPrivateKey);
+        }
+        let index = u32::from_be_bytes(bytes[..4].try_into().map_err(|_| CryptoError::InvalidPrivateKey)?);
+        let secret: Option<Scalar> = Scalar::from_bytes_be(bytes[4..].try_into().map_err(|_| CryptoError::InvalidPrivateKey)?).into();
+
+        match secret {
+            Some(secret) if index > 0 => Ok(Self { index, secret }),
+            _ => Err(CryptoError::InvalidPrivateKey),
+        }
+    }
+}
+
+impl std::fmt::Debug for ThresholdKeyShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThresholdKeyShare").field("index", &self.index).finish()
+    }
+}
+
+impl ThresholdPublicKey {
+    /// Check a partial signature against its signer's verification key
+    pub fn verify_partial(&self, partial: &PartialSignature, message: &[u8]) -> Result<bool> {
+        let verification_key = match self.verification_key(partial.signer_index) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+
+        partial.signature.verify(verification_key, message)
+            .map_err(|e| CryptoError::VerificationFailed(e.to_string()))
+    }
+
+    pub fn verification_key(&self, signer_index: u32) -> Option<&BLSPublicKey> {
+        (signer_index as usize).checked_sub(1).and_then(|i| self.verification_keys.get(i))
+    }
+
+    /// Combine a quorum of partial signatures into the group signature
+    /// Invalid and duplicate partials are skipped so one bad signer cannot spoil the result; it
+    /// only fails with fewer valid partials than the threshold
+    pub fn combine(&self, partials: &[PartialSignature], message: &[u8]) -> Result<BLSSignature> {
+        let mut quorum: Vec<&PartialSignature> = Vec::with_capacity(self.config.threshold);
+        let mut invalid = 0;
+        for partial in partials {
+            if quorum.len() == self.config.threshold {
+                break;
+            }
+            if quorum.iter().any(|p| p.signer_index == partial.signer_index) {
+                continue;
+            }
+            if !matches!(self.verify_partial(partial, message), Ok(true)) {
+                invalid += 1;
+                continue;
+            }
+            quorum.push(partial);
+        }
+
+        if !self.config.meets_threshold(quorum.len()) {
+            return Err(CryptoError::AggregationFailed(format!(
+                "{} of {} required partial signatures valid, {} invalid", quorum.len(), self.config.threshold, invalid
+            )));
+        }
+
+        let indices: Vec<Scalar> = quorum.iter().map(|p| Scalar::from(p.signer_index as u64)).collect();
+
+        // Lagrange interpolation at zero, in the exponent
+        let mut combined = G2Projective::identity();
+        for (i, partial) in quorum.iter().enumerate() {
+            let mut numerator = Scalar::ONE;
+            let mut denominator = Scalar::ONE;
+            for (j, x_j) in indices.iter().enumerate() {
+                if i != j {
+                    numerator *= x_j;
+                    denominator *= *x_j - indices[i];
+                }
+            }
+            let inverse: Option<Scalar> = denominator.invert().into();
+            let coefficient = numerator * inverse.ok_or_else(|| CryptoError::AggregationFailed("Duplicate signer index".to_string()))?;
+
+            let point: Option<G2Projective> = G2Projective::from_compressed(partial.signature.to_bytes()).into();
+            let point = point.ok_or(CryptoError::InvalidSignature)?;
+            combined += point * coefficient;
+        }
+
+        BLSSignature::from_bytes(&combined.to_compressed())
+            .map_err(|_| CryptoError::InvalidSignature)
+    }
+
+    /// Verify a combined signature against the group key
+    pub fn verify(&self, signature: &BLSSignature, message: &[u8]) -> Result<bool> {
+        signature.verify(&self.group_key, message)
+            .map_err(|e| CryptoError::VerificationFailed(e.to_string()))
+    }
+}
+
 /// Multi-signature data for validator consensus
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MultiSignature {
@@ -552,4 +736,41 @@ mod tests {
         // Should be removed from pending after creation
         assert_eq!(sig_manager.get_signature_count(message), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_threshold_signing() {
+        let config = ThresholdConfig::new(3, 5).unwrap();
+        let (public, shares) = generate_threshold_key(&config).unwrap();
+        let message = b"Approve settlement: Vodafone-UK -> T-Mobile-DE 2.4M EUR";
+
+        let partials: Vec<_> = shares.iter().map(|share| share.sign(message).unwrap()).collect();
+        for partial in &partials {
+            assert!(public.verify_partial(partial, message).unwrap());
+        }
+
+        // Any quorum yields the same group signature
+        let first = public.combine(&partials[0..3], message).unwrap();
+        let last = public.combine(&partials[2..5], message).unwrap();
+        assert_eq!(first, last);
+        assert!(public.verify(&first, message).unwrap());
+        assert!(!public.verify(&first, b"different settlement").unwrap());
+
+        // Below threshold, even with a duplicate signer
+        let duplicated = vec![partials[0].clone(), partials[0].clone(), partials[1].clone()];
+        assert!(public.combine(&duplicated, message).is_err());
+
+        // A partial over a different message is rejected
+        let forged = vec![partials[0].clone(), partials[1].clone(), shares[2].sign(b"other").unwrap()];
+        assert!(public.combine(&forged, message).is_err());
+
+        // With one more partial than the threshold, a corrupted one is skipped
+        let corrupted = PartialSignature { signer_index: 1, signature: partials[3].signature.clone() };
+        let quorum = vec![corrupted, partials[1].clone(), partials[2].clone(), partials[3].clone()];
+        assert_eq!(public.combine(&quorum, message).unwrap(), first);
+        let quorum = vec![shares[0].sign(b"other").unwrap(), partials[1].clone(), partials[2].clone(), partials[4].clone()];
+        assert_eq!(public.combine(&quorum, message).unwrap(), first);
+
+        let restored = ThresholdKeyShare::from_bytes(&shares[3].to_bytes()).unwrap();
+        assert_eq!(restored.public_key().unwrap(), shares[3].public_key().unwrap());
+    }
+}
@@ -11,8 +11,13 @@ pub mod zkp;
 pub mod crypto;
 
 pub mod network;
+pub mod settlement_execution;
 pub mod bce_pipeline;
+pub mod metrics;
 pub mod api;
+pub mod notifications;
+pub mod bridge;
+pub mod simulation;
 
 // Re-export key types for easy access
 pub use primitives::{
@@ -33,13 +38,14 @@ pub use common::{
 };
 
 pub use storage::{
-    ChainStore, SimpleChainStore, MdbxChainStore,
+    ChainStore, SimpleChainStore, MdbxChainStore, StateTrie, StateProof, WriteBatch,
 };
 use smart_contracts::{
     ContractVM, MemoryStorage, MdbxContractStorage, create_mdbx_contract_storage,
     ContractCryptoVerifier, ConsensusContractEngine, ExecutionContext,
 };
 use blockchain::block::{TransactionData, CDRTransaction, SettlementTransaction};
+use blockchain::light_client;
 use std::any::Any;
 
 pub use zkp::{
@@ -63,6 +69,13 @@ pub struct SPCDRBlockchain {
     election_head: std::sync::Arc<tokio::sync::RwLock<Block>>,
     network_id: NetworkId,
     contract_engine: Option<std::sync::Arc<ConsensusContractEngine<MdbxContractStorage>>>,
+    state_trie: std::sync::Arc<std::sync::RwLock<StateTrie>>,
+    /// Election block of each bridged consortium's chain its first bridged settlement is proven from
+    bridge_checkpoints: std::collections::HashMap<NetworkId, Blake2bHash>,
+    /// Contract events of each block as it is committed
+    contract_events: tokio::sync::broadcast::Sender<smart_contracts::EventRecord>,
+    /// Disputes opened on escrows left unconfirmed past their deadline, none are opened if `None`
+    dispute_manager: Option<std::sync::Arc<network::dispute_resolution::DisputeManager>>,
 }
 
 #[async_trait::async_trait]
@@ -110,54 +123,32 @@ impl common::AbstractBlockchain for SPCDRBlockchain {
     }
     
     async fn push_block(&self, block: Block) -> Result<()> {
-        // Execute transactions in the block first
-        self.execute_block_transactions(&block).await?;
-
-        // Store block
-        self.chain_store.put_block(&block).await?;
-
-        let block_hash = block.hash();
-
-        // Update head pointers based on block type
-        match &block {
-            Block::Micro(_) => {
-                *self.head_block.write().await = block;
-                self.chain_store.set_head(&block_hash).await?;
-            }
-            Block::Macro(macro_block) => {
-                *self.head_block.write().await = block.clone();
-                *self.macro_head.write().await = block.clone();
-
-                self.chain_store.set_head(&block_hash).await?;
-                self.chain_store.set_macro_head(&block_hash).await?;
+        self.check_extends_head(&block).await?;
+        if let Block::Macro(macro_block) = &block {
+            self.check_macro_roots(macro_block).await?;
+            self.check_macro_rewards(macro_block).await?;
+            self.check_justification(macro_block).await?;
+        }
 
-                // Check if it's an election block (every 32 macro blocks following Albatross)
-                if macro_block.header.block_number % (primitives::Policy::EPOCH_LENGTH * primitives::Policy::BATCH_LENGTH) == 0 {
-                    *self.election_head.write().await = block.clone();
-                    self.chain_store.set_election_head(&block_hash).await?;
+        // Changes are logged so a block that fails to execute or has a wrong state root leaves the trie untouched
+        self.state_trie.write().unwrap().begin_block();
 
-                    // Update validator set if present
-                    if let Some(ref validators) = macro_block.body.validators {
-                        let mut validator_set = self.validator_set.write().await;
-                        // Convert block::ValidatorInfo to validator_set::ValidatorInfo
-                        let converted_validators: Vec<blockchain::validator_set::ValidatorInfo> = validators
-                            .iter()
-                            .map(|v| blockchain::validator_set::ValidatorInfo {
-                                validator_address: v.address,
-                                signing_key: crate::crypto::PublicKey::from_bytes(&v.signing_key).unwrap_or_else(|_| crate::crypto::PublicKey::from_bytes(&[0u8; 48]).unwrap()),
-                                voting_power: 1, // Default voting power
-                                network_operator: "default".to_string(),
-                                joined_at_height: 0,
-                            })
-                            .collect();
-                        validator_set.update_validators(converted_validators);
-                        validator_set.finalize_epoch();
-                    }
-                }
+        let (state_root, receipts) = match self.execute_block_state(&block).await {
+            Ok(executed) => executed,
+            Err(e) => {
+                self.state_trie.write().unwrap().revert_block();
+                return Err(e);
             }
+        };
+        if state_root != *block.state_root() {
+            self.state_trie.write().unwrap().revert_block();
+            return Err(BlockchainError::BlockValidation(format!(
+                "State root mismatch at block {}: header {}, computed {}",
+                block.block_number(), block.state_root(), state_root
+            )));
         }
 
-        Ok(())
+        self.commit_block(block, receipts).await
     }
     
     fn get_chain_info(&self) -> common::ChainInfo {
@@ -200,6 +191,7 @@ impl SPCDRBlockchain {
         
         // Create genesis blocks
         let genesis_block = Block::Macro(MacroBlock {
+            justification: None,
             header: blockchain::MacroHeader {
                 network: NetworkId::SPConsortium,
                 version: 1,
@@ -235,6 +227,10 @@ impl SPCDRBlockchain {
             network_id: NetworkId::SPConsortium,
             consensus: common::Consensus::placeholder(),
             contract_engine,
+            state_trie: std::sync::Arc::new(std::sync::RwLock::new(StateTrie::new())),
+            bridge_checkpoints: std::collections::HashMap::new(),
+            contract_events: tokio::sync::broadcast::channel(1024).0,
+            dispute_manager: None,
         };
         
         // TODO: Fix circular dependency - consensus needs blockchain reference
@@ -243,6 +239,287 @@ impl SPCDRBlockchain {
         blockchain
     }
     
+    /// Share the state trie the contract storage writes into, see `MdbxContractStorage::with_state_trie`
+    pub fn with_state_trie(mut self, state_trie: std::sync::Arc<std::sync::RwLock<StateTrie>>) -> Self {
+        self.state_trie = state_trie;
+        self
+    }
+
+    /// Consortium chain the produced blocks belong to, for operators bridging settlements between several
+    pub fn with_network_id(mut self, network_id: NetworkId) -> Self {
+        self.network_id = network_id;
+        self
+    }
+
+    /// Trust these election blocks of other consortia's chains for the first settlement bridged from each,
+    /// see `bridge::BridgeLink`
+    pub fn with_bridge_checkpoints(mut self, checkpoints: std::collections::HashMap<NetworkId, Blake2bHash>) -> Self {
+        self.bridge_checkpoints = checkpoints;
+        self
+    }
+
+    /// Open a dispute in `dispute_manager` on every escrow a committed block puts into dispute
+    pub fn with_dispute_manager(mut self, dispute_manager: std::sync::Arc<network::dispute_resolution::DisputeManager>) -> Self {
+        self.dispute_manager = Some(dispute_manager);
+        self
+    }
+
+    /// Dispute manager escrow disputes are opened in, see `with_dispute_manager`
+    pub fn dispute_manager(&self) -> Option<std::sync::Arc<network::dispute_resolution::DisputeManager>> {
+        self.dispute_manager.clone()
+    }
+
+    /// State root after the last pushed block
+    pub fn state_root(&self) -> Blake2bHash {
+        self.state_trie.read().unwrap().root()
+    }
+
+    /// Proof that `key` has its current value under `state_root`, for light clients
+    pub fn state_proof(&self, key: &Blake2bHash) -> StateProof {
+        self.state_trie.read().unwrap().prove(key)
+    }
+
+    /// Whether a settlement on chain covered a BCE batch
+    pub fn is_batch_settled(&self, batch_id: &Blake2bHash) -> bool {
+        self.state_trie.read().unwrap().is_batch_settled(batch_id)
+    }
+
+    /// Check settlements against those on chain, see `StateTrie::check_settlements`
+    pub fn check_settlements(&self, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        self.state_trie.read().unwrap().check_settlements(transactions)
+    }
+
+    /// Check bridged settlements against the bridge links and bridged settlements on chain
+    pub fn check_bridged_settlements(&self, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        self.verify_bridged_settlements(&self.state_trie.read().unwrap(), transactions)
+    }
+
+    /// Election blocks trusted on a bridged consortium's chain, `None` before its first bridged settlement
+    pub fn bridge_link(&self, network: &NetworkId) -> Option<bridge::BridgeLink> {
+        self.state_trie.read().unwrap().bridge_link(network)
+    }
+
+    /// Whether a settlement of another consortium was bridged onto this chain
+    pub fn is_bridged(&self, network: &NetworkId, source_transaction: &Blake2bHash) -> bool {
+        self.state_trie.read().unwrap().is_bridged(network, source_transaction)
+    }
+
+    /// Check every bridged settlement comes from another consortium, proves its finality there from an
+    /// election block trusted on chain, or the configured checkpoint for a first one, and was not
+    /// bridged before. Election blocks an earlier proof in the list went through are trusted too
+    fn verify_bridged_settlements(&self, state_trie: &StateTrie, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        let mut links: std::collections::HashMap<NetworkId, bridge::BridgeLink> = std::collections::HashMap::new();
+        let mut bridged = std::collections::HashSet::new();
+        for transaction in transactions {
+            let TransactionData::BridgedSettlement(bridged_settlement) = &transaction.data else {
+                continue;
+            };
+            let source = &bridged_settlement.source_network;
+            if *source == self.network_id {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "Transaction {} bridges a settlement from this chain's own consortium {}", transaction.hash(), source
+                )));
+            }
+            let link = match links.entry(source.clone()) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    let link = state_trie.bridge_link(source)
+                        .or_else(|| self.bridge_checkpoints.get(source).copied().map(bridge::BridgeLink::from_checkpoint))
+                        .ok_or_else(|| BlockchainError::InvalidTransaction(format!(
+                            "Transaction {} bridges a settlement from {}, which has no trusted checkpoint", transaction.hash(), source
+                        )))?;
+                    entry.insert(link)
+                }
+            };
+            let finalized = bridged_settlement.verify(link)?;
+            if state_trie.is_bridged(source, &finalized.transaction_hash) || !bridged.insert((source.clone(), finalized.transaction_hash)) {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "Transaction {} bridges settlement {} of {} again", transaction.hash(), finalized.transaction_hash, source
+                )));
+            }
+            link.extend(&bridged_settlement.proof);
+        }
+        Ok(())
+    }
+
+    /// Check validator updates against the registrations on chain, see `StateTrie::check_validator_updates`
+    pub fn check_validator_updates(&self, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        self.state_trie.read().unwrap().check_validator_updates(transactions)
+    }
+
+    /// Check CDR commitments, challenges and responses, see `StateTrie::check_commitments`
+    pub fn check_commitments(&self, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        self.state_trie.read().unwrap().check_commitments(transactions)
+    }
+
+    /// Aggregated CDR commitment on chain, `None` if it was never committed
+    pub fn cdr_commitment(&self, commitment_id: &Blake2bHash) -> Option<blockchain::CommitmentRecord> {
+        self.state_trie.read().unwrap().cdr_commitment(commitment_id)
+    }
+
+    /// Challenges on CDR commitments waiting for their committer's response
+    pub fn commitment_challenges(&self) -> Vec<blockchain::OpenChallenge> {
+        self.state_trie.read().unwrap().commitment_challenges()
+    }
+
+    /// Whether a settlement period was closed on chain
+    pub fn is_period_closed(&self, period: &str) -> bool {
+        self.state_trie.read().unwrap().is_period_closed(period)
+    }
+
+    /// Fraud score a BCE batch is quarantined with on chain, `None` if it is not quarantined
+    pub fn quarantine_score(&self, batch_id: &Blake2bHash) -> Option<u32> {
+        self.state_trie.read().unwrap().quarantine_score(batch_id)
+    }
+
+    /// Merkle root a BCE batch was committed with on chain, `None` if it was not committed
+    pub fn batch_commitment(&self, batch_id: &Blake2bHash) -> Option<Blake2bHash> {
+        self.state_trie.read().unwrap().batch_commitment(batch_id)
+    }
+
+    /// Hash of the payment document `coordinator` anchored for a settlement
+    pub fn payment_document_hash(&self, settlement_tx: &Blake2bHash, coordinator: &Blake2bHash) -> Option<Blake2bHash> {
+        self.state_trie.read().unwrap().payment_document(settlement_tx, coordinator)
+    }
+
+    /// Nonce the next transaction of `sender` has to carry
+    pub fn account_nonce(&self, sender: &Blake2bHash) -> u64 {
+        self.state_trie.read().unwrap().account_nonce(sender)
+    }
+
+    /// Parameter set in effect after the last pushed block
+    pub fn chain_parameters(&self) -> blockchain::ChainParameters {
+        self.state_trie.read().unwrap().chain_parameters()
+    }
+
+    /// Governance proposals in their vote or waiting for activation
+    pub fn governance_proposals(&self) -> Vec<blockchain::Proposal> {
+        self.state_trie.read().unwrap().governance_proposals()
+    }
+
+    /// Join applications of new operators in their vote
+    pub fn operator_applications(&self) -> Vec<blockchain::Application> {
+        self.state_trie.read().unwrap().operator_applications()
+    }
+
+    /// Whether a transaction is included in the chain
+    pub async fn contains_transaction(&self, tx_hash: &Blake2bHash) -> Result<bool> {
+        Ok(self.chain_store.get_transaction(tx_hash).await?.is_some())
+    }
+
+    /// Contract receipt of a transaction included in the chain
+    pub async fn get_receipt(&self, tx_hash: &Blake2bHash) -> Result<Option<smart_contracts::ContractReceipt>> {
+        self.chain_store.get_receipt(tx_hash).await
+    }
+
+    /// Receipts of every transaction that executed `contract`, in chain order
+    pub async fn get_receipts_by_contract(&self, contract: &Blake2bHash) -> Result<Vec<smart_contracts::ContractReceipt>> {
+        self.chain_store.get_receipts_by_contract(contract).await
+    }
+
+    /// Events `contract` emitted, only those carrying `topic` if given, in chain order
+    pub async fn get_events(&self, contract: &Blake2bHash, topic: Option<&Blake2bHash>) -> Result<Vec<smart_contracts::EventRecord>> {
+        self.chain_store.get_events(contract, topic).await
+    }
+
+    /// Receiver of the contract events of blocks committed from now on
+    pub fn subscribe_contract_events(&self) -> tokio::sync::broadcast::Receiver<smart_contracts::EventRecord> {
+        self.contract_events.subscribe()
+    }
+
+    /// Rate table `operator` charges `partner` with, `None` if none was published or contracts are not executed
+    pub async fn rate_table(&self, operator: &str, partner: &str) -> Result<Option<blockchain::RateTable>> {
+        match &self.contract_engine {
+            Some(engine) => engine.rate_table(operator, partner).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Escrow of a settlement, `None` if it was never locked or contracts are not executed
+    pub async fn settlement_escrow(&self, settlement_tx: &Blake2bHash) -> Result<Option<smart_contracts::SettlementEscrow>> {
+        match &self.contract_engine {
+            Some(engine) => engine.escrow(settlement_tx).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Settlement transaction `settlement_tx` if it executed and the last macro block before the
+    /// block at `block_number` made it final, which every validator executing that block agrees on
+    async fn settlement_final_before(&self, settlement_tx: &Blake2bHash, block_number: u32) -> Result<Option<SettlementTransaction>> {
+        let Some((transaction, location)) = self.chain_store.get_transaction(settlement_tx).await? else {
+            return Ok(None);
+        };
+        let TransactionData::Settlement(settlement) = transaction.data else {
+            return Ok(None);
+        };
+        let Some(settled_in) = self.chain_store.get_block(&location.block_hash).await? else {
+            return Ok(None);
+        };
+        let last_macro = block_number.saturating_sub(1) / primitives::Policy::EPOCH_LENGTH * primitives::Policy::EPOCH_LENGTH;
+        let canonical = self.chain_store.get_block_at(settled_in.block_number()).await?
+            .is_some_and(|block| block.hash() == location.block_hash);
+        let executed = self.chain_store.get_receipt(settlement_tx).await?
+            .is_some_and(|receipt| receipt.success);
+        Ok((canonical && executed && settled_in.block_number() <= last_macro).then_some(settlement))
+    }
+
+    /// Verify contract proofs with the verifying keys of the trusted setup ceremony, a no-op if contracts are not executed
+    pub async fn load_verifying_keys(&self, ceremony: &zkp::trusted_setup::TrustedSetupCeremony) -> Result<()> {
+        match &self.contract_engine {
+            Some(engine) => engine.load_verifying_keys(ceremony).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Registry record of the operator `plmn` belongs to, `None` if unregistered or contracts are not executed
+    pub async fn operator_by_plmn(&self, plmn: &str) -> Result<Option<blockchain::OperatorRecord>> {
+        match &self.contract_engine {
+            Some(engine) => engine.operator_by_plmn(plmn).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Registry record of `operator`, `None` if unregistered or contracts are not executed
+    pub async fn operator(&self, operator: &str) -> Result<Option<blockchain::OperatorRecord>> {
+        match &self.contract_engine {
+            Some(engine) => engine.operator(operator).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Networks of all registered operators, empty if none registered or contracts are not executed
+    pub async fn registered_operators(&self) -> Result<Vec<NetworkId>> {
+        let engine = match &self.contract_engine {
+            Some(engine) => engine,
+            None => return Ok(Vec::new()),
+        };
+        let mut operators = Vec::new();
+        for name in engine.operators().await? {
+            if let Some(record) = engine.operator(&name).await? {
+                operators.push(record.network_id());
+            }
+        }
+        Ok(operators)
+    }
+
+    /// Account each registered operator sends transactions from, the one of its registered signing
+    /// key, keyed by `name:country`
+    pub async fn operator_accounts(&self) -> Result<std::collections::HashMap<String, Blake2bHash>> {
+        let engine = match &self.contract_engine {
+            Some(engine) => engine,
+            None => return Ok(std::collections::HashMap::new()),
+        };
+        let mut accounts = std::collections::HashMap::new();
+        for name in engine.operators().await? {
+            if let Some(record) = engine.operator(&name).await? {
+                if let Ok(key) = crypto::BLSPublicKey::from_bytes(&record.signing_key) {
+                    accounts.insert(record.network_id().to_string(), blockchain::block::account_address(&key));
+                }
+            }
+        }
+        Ok(accounts)
+    }
+
     /// Async method to get current head
     pub async fn head_async(&self) -> Block {
         self.head_block.read().await.clone()
@@ -258,6 +535,776 @@ impl SPCDRBlockchain {
         self.election_head.read().await.clone()
     }
 
+    /// Open the chain on an MDBX store, restoring heads and state and executing contracts on its storage
+    pub async fn open(
+        chain_store: std::sync::Arc<MdbxChainStore>,
+        initial_validators: Vec<ValidatorInfo>,
+    ) -> Result<Self> {
+        let state_trie = std::sync::Arc::new(std::sync::RwLock::new(chain_store.load_state_trie().await?));
+        let contract_storage = MdbxContractStorage::new(chain_store.clone()).with_state_trie(state_trie.clone());
+        let contract_engine = std::sync::Arc::new(ConsensusContractEngine::new(contract_storage, ContractCryptoVerifier::new()));
+
+        let blockchain = Self::new_with_contract_engine(chain_store.clone(), initial_validators, Some(contract_engine))
+            .with_state_trie(state_trie);
+
+        // A fresh store has no head and keeps the in-memory genesis
+        if let Ok(head_hash) = chain_store.get_head_hash().await {
+            if let Some(head) = chain_store.get_block(&head_hash).await? {
+                *blockchain.head_block.write().await = head;
+            }
+            if let Some(macro_head) = chain_store.get_block(&chain_store.get_macro_head_hash().await?).await? {
+                *blockchain.macro_head.write().await = macro_head;
+            }
+            if let Some(election_head) = chain_store.get_block(&chain_store.get_election_head_hash().await?).await? {
+                *blockchain.election_head.write().await = election_head;
+            }
+        }
+
+        Ok(blockchain)
+    }
+
+    /// Assemble the block extending the head, without executing it
+    /// Macro blocks carry their Tendermint round and, at elections, the next validator set
+    async fn build_block(
+        &self,
+        transactions: Vec<blockchain::block::Transaction>,
+        round: u32,
+        validators: Option<Vec<blockchain::block::ValidatorInfo>>,
+    ) -> Result<Block> {
+        let head = self.head_async().await;
+        let block_number = head.block_number() + 1;
+        let timestamp = (chrono::Utc::now().timestamp() as u64).max(head.timestamp());
+
+        if validators.is_some() && !primitives::Policy::is_election_block(block_number) {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "Block {} is not an election block and cannot change validators", block_number
+            )));
+        }
+
+        let block = if primitives::Policy::is_macro_block(block_number) {
+            let lost_reward_set = self.lost_reward_set().await?;
+            let (body_root, history_root) = self.macro_roots(&head, &validators, &lost_reward_set, &transactions).await?;
+            Block::Macro(MacroBlock {
+                justification: None,
+                header: blockchain::MacroHeader {
+                    network: self.network_id.clone(),
+                    version: 1,
+                    block_number,
+                    round,
+                    timestamp,
+                    parent_hash: head.hash(),
+                    parent_election_hash: self.election_head_async().await.hash(),
+                    seed: Blake2bHash::zero(),
+                    extra_data: vec![],
+                    state_root: Blake2bHash::zero(),
+                    body_root,
+                    history_root,
+                },
+                body: blockchain::MacroBody {
+                    validators,
+                    lost_reward_set,
+                    disabled_set: vec![],
+                    transactions,
+                },
+            })
+        } else {
+            Block::Micro(MicroBlock {
+                header: blockchain::MicroHeader {
+                    network: self.network_id.clone(),
+                    version: 1,
+                    block_number,
+                    timestamp,
+                    parent_hash: head.hash(),
+                    seed: Blake2bHash::zero(),
+                    extra_data: vec![],
+                    state_root: Blake2bHash::zero(),
+                    body_root: primitives::hash_canonical(&transactions),
+                    history_root: Blake2bHash::zero(),
+                },
+                body: blockchain::MicroBody { transactions },
+            })
+        };
+
+        Ok(block)
+    }
+
+    /// Build the next block on the head from `transactions`, executing them to fill in the state root
+    /// Other validators re-execute the block in `push_block` and reject it if their state root differs
+    pub async fn produce_block(&self, transactions: Vec<blockchain::block::Transaction>) -> Result<Block> {
+        let mut block = self.build_block(transactions, 0, None).await?;
+
+        self.state_trie.write().unwrap().begin_block();
+        let (state_root, receipts) = match self.execute_block_state(&block).await {
+            Ok(executed) => executed,
+            Err(e) => {
+                self.state_trie.write().unwrap().revert_block();
+                return Err(e);
+            }
+        };
+        match &mut block {
+            Block::Micro(micro_block) => micro_block.header.state_root = state_root,
+            Block::Macro(macro_block) => macro_block.header.state_root = state_root,
+        }
+
+        self.commit_block(block.clone(), receipts).await?;
+        Ok(block)
+    }
+
+    /// Propose the macro block extending the head in Tendermint round `round`
+    /// Nothing is committed; validators push the block once it is finalized
+    pub async fn propose_macro_block(
+        &self,
+        transactions: Vec<blockchain::block::Transaction>,
+        round: u32,
+        validators: Option<Vec<blockchain::block::ValidatorInfo>>,
+    ) -> Result<Block> {
+        let block_number = self.head_async().await.block_number() + 1;
+        let mut transactions = transactions;
+        if primitives::Policy::is_election_block(block_number) {
+            transactions.insert(0, self.reward_payout(block_number).await);
+        }
+        let lost_reward_set = self.lost_reward_set().await?;
+        let (validators, state_root) = self.proposal_state(block_number, &transactions, &lost_reward_set, |state| {
+            // Only validators registered and active at the election block are elected,
+            // and they vote with the stake bonded then
+            let validators = validators.map(|validators| validators.into_iter()
+                .filter(|validator| state.is_electable(&validator.address))
+                .map(|validator| blockchain::block::ValidatorInfo {
+                    stake: state.validator_stake(&validator.address).bonded,
+                    ..validator
+                })
+                .collect::<Vec<_>>());
+            Ok((validators, state.root()))
+        }).await?;
+        if validators.as_ref().is_some_and(|validators| validators.is_empty()) {
+            return Err(BlockchainError::InvalidState(format!(
+                "No registered active validator to elect in block {}", block_number
+            )));
+        }
+
+        let mut block = self.build_block(transactions, round, validators).await?;
+        let Block::Macro(macro_block) = &mut block else {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "Block {} is a micro block", block.block_number()
+            )));
+        };
+        macro_block.header.state_root = state_root;
+        Ok(block)
+    }
+
+    /// Check a macro block proposal before voting for it, leaving the chain state untouched
+    pub async fn check_proposal(&self, block: &Block) -> Result<()> {
+        self.check_extends_head(block).await?;
+
+        let Block::Macro(macro_block) = block else {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} is proposed for finality but is a micro block", block.block_number()
+            )));
+        };
+        if !primitives::Policy::is_macro_block(block.block_number()) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} is not at a batch boundary", block.block_number()
+            )));
+        }
+        if macro_block.body.validators.is_some() != primitives::Policy::is_election_block(block.block_number()) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} must carry a validator set exactly when it is an election block", block.block_number()
+            )));
+        }
+
+        self.check_macro_roots(macro_block).await?;
+        self.check_macro_rewards(macro_block).await?;
+
+        self.proposal_state(block.block_number(), &macro_block.body.transactions, &macro_block.body.lost_reward_set, |state| {
+            let state_root = state.root();
+            if state_root != macro_block.header.state_root {
+                return Err(BlockchainError::BlockValidation(format!(
+                    "State root mismatch in proposal {}: header {}, computed {}",
+                    block.block_number(), macro_block.header.state_root, state_root
+                )));
+            }
+            let validators = macro_block.body.validators.as_deref().unwrap_or_default();
+            if let Some(validator) = validators.iter().find(|validator| !state.is_electable(&validator.address)) {
+                return Err(BlockchainError::BlockValidation(format!(
+                    "Validator {} is elected in proposal {} but is not a registered active validator",
+                    validator.address, block.block_number()
+                )));
+            }
+            if let Some(validator) = validators.iter().find(|validator| validator.stake != state.validator_stake(&validator.address).bonded) {
+                return Err(BlockchainError::BlockValidation(format!(
+                    "Validator {} is elected with {} stake in proposal {}, {} is bonded",
+                    validator.address, validator.stake, block.block_number(), state.validator_stake(&validator.address).bonded
+                )));
+            }
+            Ok(())
+        }).await
+    }
+
+    /// Inspect the state a macro block proposal leads to with `inspect`, the proposal being applied
+    /// to the trie under an undo log and reverted afterwards
+    /// Proposals carry no contract transactions, so they can be checked without running the VM
+    async fn proposal_state<T>(
+        &self,
+        block_number: u32,
+        transactions: &[blockchain::block::Transaction],
+        lost_reward_set: &[Blake2bHash],
+        inspect: impl FnOnce(&StateTrie) -> Result<T>,
+    ) -> Result<T> {
+        if let Some(transaction) = transactions.iter().find(|transaction| transaction.executes_contract()) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Macro block proposal executes contract transaction {}", transaction.hash()
+            )));
+        }
+
+        let epoch_validators = self.epoch_validators().await;
+        Self::check_governance(block_number, transactions, &epoch_validators)?;
+        Self::check_network_joins(block_number, transactions)?;
+        Self::check_signatures(block_number, transactions)?;
+        self.check_operator_senders(block_number, transactions).await?;
+        let mut state_trie = self.state_trie.write().unwrap();
+        state_trie.check_nonces(transactions)
+            .and_then(|()| state_trie.check_settlements(transactions))
+            .and_then(|()| self.verify_bridged_settlements(&state_trie, transactions))
+            .and_then(|()| state_trie.check_validator_updates(transactions))
+            .and_then(|()| state_trie.check_commitments(transactions))
+            .map_err(|e| BlockchainError::BlockValidation(format!("Macro block proposal {}: {}", block_number, e)))?;
+        state_trie.speculate(|state_trie| {
+            state_trie.apply_transactions(block_number, transactions);
+            state_trie.apply_governance(block_number, &epoch_validators, transactions);
+            state_trie.record_participation(&epoch_validators, lost_reward_set);
+            inspect(state_trie)
+        })
+    }
+
+    /// Validators elected by the latest election block, who vote on the blocks of the current epoch
+    async fn epoch_validators(&self) -> Vec<blockchain::block::ValidatorInfo> {
+        match &*self.election_head.read().await {
+            Block::Macro(election) => election.body.validators.clone().unwrap_or_default(),
+            Block::Micro(_) => vec![],
+        }
+    }
+
+    /// Check every transaction is signed by the account it is sent from
+    fn check_signatures(block_number: u32, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        for transaction in transactions {
+            transaction.verify_signature().map_err(|e| BlockchainError::BlockValidation(format!(
+                "Block {}: {}", block_number, e
+            )))?;
+        }
+        Ok(())
+    }
+
+    /// Check settlements and fraud flags are sent by the account of an operator they name, the one
+    /// its registry entry's signing key derives, once any operator is registered
+    async fn check_operator_senders(&self, block_number: u32, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        let accounts = self.operator_accounts().await?;
+        if accounts.is_empty() {
+            return Ok(());
+        }
+        for transaction in transactions {
+            let parties = match &transaction.data {
+                TransactionData::Settlement(settlement) => [&settlement.creditor_network, &settlement.debtor_network],
+                TransactionData::FraudFlag(flag) => [&flag.home_network, &flag.visited_network],
+                _ => continue,
+            };
+            let sent_by_party = parties.iter()
+                .any(|party| accounts.get(&storage::settlement_report::display_name(party)) == Some(&transaction.sender));
+            if !sent_by_party {
+                return Err(BlockchainError::BlockValidation(format!(
+                    "Block {}: transaction {} is sent by {}, not by {} or {}",
+                    block_number, transaction.hash(), transaction.sender, parties[0], parties[1]
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check join applications carry valid PLMN codes and are signed by the key they register
+    fn check_network_joins(block_number: u32, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        for transaction in transactions {
+            if let TransactionData::NetworkJoin(join) = &transaction.data {
+                join.verify().map_err(|e| BlockchainError::BlockValidation(format!(
+                    "Block {} carries invalid join application {}: {}", block_number, transaction.hash(), e
+                )))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every sent transaction pays at least its minimum fee
+    fn check_fees(block_number: u32, transactions: &[blockchain::block::Transaction]) -> Result<()> {
+        for transaction in transactions {
+            blockchain::fees::check_fee(transaction).map_err(|e| BlockchainError::BlockValidation(format!(
+                "Block {}: {}", block_number, e
+            )))?;
+        }
+        Ok(())
+    }
+
+    /// Check governance transactions are signed by the validators elected for the epoch they are in
+    fn check_governance(
+        block_number: u32,
+        transactions: &[blockchain::block::Transaction],
+        epoch_validators: &[blockchain::block::ValidatorInfo],
+    ) -> Result<()> {
+        for transaction in transactions {
+            if let TransactionData::Governance(governance) = &transaction.data {
+                governance.verify(epoch_validators).map_err(|e| BlockchainError::BlockValidation(format!(
+                    "Block {} carries invalid governance transaction {}: {}", block_number, transaction.hash(), e
+                )))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validators missing from the certificate of the macro head, who lose their reward for it
+    /// An election block was certified by the previous validator set, already paid in that block
+    async fn lost_reward_set(&self) -> Result<Vec<Blake2bHash>> {
+        let macro_head = self.macro_head_async().await.block_number();
+        if primitives::Policy::is_election_block(macro_head) {
+            return Ok(vec![]);
+        }
+        Ok(match self.macro_certificate(macro_head).await? {
+            Some(certificate) => blockchain::rewards::lost_reward_set(&self.epoch_validators().await, &certificate.signers()),
+            None => vec![],
+        })
+    }
+
+    /// Payout of the fees accrued since the last election to the validators of the ending epoch
+    async fn reward_payout(&self, election_block: u32) -> blockchain::block::Transaction {
+        let validators = self.epoch_validators().await;
+        let state_trie = self.state_trie.read().unwrap();
+        let participation: Vec<u64> = validators.iter().map(|validator| state_trie.participation(&validator.address)).collect();
+        blockchain::rewards::payout_transaction(
+            blockchain::rewards::distribute(election_block, state_trie.reward_pool(), &validators, &participation)
+        )
+    }
+
+    /// Check the reward fields of a macro block: its lost reward set names validators of the epoch
+    /// and election blocks open with the payout the accrued fees and participation lead to
+    /// Certificates differ between nodes by which precommits they aggregated, so the lost set is not recomputed
+    async fn check_macro_rewards(&self, macro_block: &MacroBlock) -> Result<()> {
+        let block_number = macro_block.header.block_number;
+        let epoch_validators = self.epoch_validators().await;
+        let lost_reward_set = &macro_block.body.lost_reward_set;
+        let unknown = lost_reward_set.iter().any(|lost| !epoch_validators.iter().any(|validator| validator.address == *lost));
+        let repeated = lost_reward_set.iter().enumerate().any(|(index, lost)| lost_reward_set[..index].contains(lost));
+        if unknown || repeated {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Macro block {} has an invalid lost reward set", block_number
+            )));
+        }
+
+        let is_payout = |transaction: &blockchain::block::Transaction| matches!(transaction.data, TransactionData::RewardPayout(_));
+        let payouts = macro_block.body.transactions.iter().filter(|transaction| is_payout(transaction)).count();
+        if !primitives::Policy::is_election_block(block_number) {
+            if payouts > 0 {
+                return Err(BlockchainError::BlockValidation(format!(
+                    "Macro block {} pays rewards but is not an election block", block_number
+                )));
+            }
+            return Ok(());
+        }
+
+        let expected = self.reward_payout(block_number).await.hash();
+        match macro_block.body.transactions.first() {
+            Some(payout) if payouts == 1 && payout.hash() == expected => Ok(()),
+            _ => Err(BlockchainError::BlockValidation(format!(
+                "Election block {} does not open with the expected reward payout", block_number
+            ))),
+        }
+    }
+
+    /// Body and history root of the macro block following `head`
+    /// The history root is the Merkle root over every transaction of the batch the block closes
+    async fn macro_roots(
+        &self,
+        head: &Block,
+        validators: &Option<Vec<blockchain::block::ValidatorInfo>>,
+        lost_reward_set: &[Blake2bHash],
+        transactions: &[blockchain::block::Transaction],
+    ) -> Result<(Blake2bHash, Blake2bHash)> {
+        let mut batch = self.batch_transaction_hashes(head.clone()).await?;
+        batch.extend(transactions.iter().map(blockchain::block::Transaction::hash));
+
+        let body_root = light_client::macro_body_root(validators, lost_reward_set, &light_client::transactions_root(transactions));
+        Ok((body_root, light_client::merkle_root(&batch)))
+    }
+
+    /// Hashes of the transactions in the micro blocks from `last` back to the previous macro block, in chain order
+    async fn batch_transaction_hashes(&self, last: Block) -> Result<Vec<Blake2bHash>> {
+        let mut micro_blocks = Vec::new();
+        let mut block = Some(last);
+        while let Some(micro_block @ Block::Micro(_)) = block {
+            // Genesis and blocks before a snapshot import are not stored, the walk ends there
+            block = self.chain_store.get_block(micro_block.parent_hash()).await?;
+            micro_blocks.push(micro_block);
+        }
+        Ok(micro_blocks.iter().rev()
+            .flat_map(|block| block.transactions().iter().map(blockchain::block::Transaction::hash))
+            .collect())
+    }
+
+    async fn check_macro_roots(&self, macro_block: &MacroBlock) -> Result<()> {
+        let head = self.head_async().await;
+        let (body_root, history_root) = self.macro_roots(
+            &head, &macro_block.body.validators, &macro_block.body.lost_reward_set, &macro_block.body.transactions,
+        ).await?;
+        if body_root != macro_block.header.body_root || history_root != macro_block.header.history_root {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Body or history root mismatch in macro block {}", macro_block.header.block_number
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the certificate a macro block carries against the validators of its epoch
+    async fn check_justification(&self, macro_block: &MacroBlock) -> Result<()> {
+        if macro_block.header.block_number == 0 {
+            return Ok(());
+        }
+        let Some(certificate) = &macro_block.justification else {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Macro block {} has no precommit certificate", macro_block.header.block_number
+            )));
+        };
+        if certificate.block_hash != primitives::hash_canonical(&macro_block.header) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Justification of macro block {} certifies another block", macro_block.header.block_number
+            )));
+        }
+
+        // Until the first election the initial validators vote
+        let mut validators = self.epoch_validators().await;
+        if validators.is_empty() {
+            validators = self.validator_set.read().await.current_validators().to_vec();
+        }
+        certificate.verify(&validators)
+    }
+
+    /// Keep the finality certificate of a macro block for light clients
+    pub async fn put_macro_certificate(&self, block_number: u32, certificate: &light_client::MacroCertificate) -> Result<()> {
+        let data = bincode::serialize(certificate).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        self.chain_store.put_macro_certificate(block_number, &data).await
+    }
+
+    async fn macro_certificate(&self, block_number: u32) -> Result<Option<light_client::MacroCertificate>> {
+        let Some(data) = self.chain_store.macro_certificate(block_number).await? else {
+            return Ok(None);
+        };
+        bincode::deserialize(&data).map(Some).map_err(|e| BlockchainError::Serialization(e.to_string()))
+    }
+
+    async fn certified_macro_header(&self, block_number: u32) -> Result<Option<light_client::CertifiedMacroHeader>> {
+        let Some(Block::Macro(macro_block)) = self.chain_store.get_block_at(block_number).await? else {
+            return Ok(None);
+        };
+        let Some(certificate) = self.macro_certificate(block_number).await?.or(macro_block.justification) else {
+            return Ok(None);
+        };
+        Ok(Some(light_client::CertifiedMacroHeader {
+            transactions_root: light_client::transactions_root(&macro_block.body.transactions),
+            lost_reward_set: macro_block.body.lost_reward_set,
+            validators: macro_block.body.validators,
+            header: macro_block.header,
+            certificate,
+        }))
+    }
+
+    /// Certified macro headers after `from_block` for light client sync, at most `limit`
+    /// From zero the headers start at the latest certified election block, the client's checkpoint
+    pub async fn certified_macro_headers(&self, from_block: u32, limit: usize) -> Result<Vec<light_client::CertifiedMacroHeader>> {
+        let macro_head = self.macro_head_async().await.block_number();
+        let mut block_number = if from_block == 0 {
+            let mut election = macro_head - macro_head % primitives::Policy::ELECTION_BLOCK_INTERVAL;
+            while election > 0 && self.certified_macro_header(election).await?.is_none() {
+                election -= primitives::Policy::ELECTION_BLOCK_INTERVAL;
+            }
+            if election == 0 {
+                return Ok(vec![]);
+            }
+            election
+        } else {
+            from_block - from_block % primitives::Policy::EPOCH_LENGTH + primitives::Policy::EPOCH_LENGTH
+        };
+
+        let mut headers = Vec::new();
+        while block_number <= macro_head && headers.len() < limit {
+            match self.certified_macro_header(block_number).await? {
+                Some(header) => headers.push(header),
+                // Light clients cannot skip an uncertified block
+                None => break,
+            }
+            block_number += primitives::Policy::EPOCH_LENGTH;
+        }
+        Ok(headers)
+    }
+
+    /// Settlement transactions finalized by the latest macro block, in chain order
+    pub async fn finalized_settlements(&self) -> Result<Vec<blockchain::block::SettlementTransaction>> {
+        let macro_head = self.macro_head_async().await.block_number();
+        let mut settlements = Vec::new();
+        for block_number in 1..=macro_head {
+            // Pruned micro blocks keep their settlement transactions
+            let Some(block) = self.chain_store.get_block_at(block_number).await? else {
+                continue;
+            };
+            settlements.extend(block.transactions().iter().filter_map(|transaction| match &transaction.data {
+                blockchain::block::TransactionData::Settlement(settlement) => Some(settlement.clone()),
+                _ => None,
+            }));
+        }
+        Ok(settlements)
+    }
+
+    /// Proof that a finalized batch included the transaction, `None` while its batch is open
+    pub async fn inclusion_proof(&self, transaction_hash: &Blake2bHash) -> Result<Option<light_client::InclusionProof>> {
+        let Some((transaction, location)) = self.chain_store.get_transaction(transaction_hash).await? else {
+            return Ok(None);
+        };
+        let Some(block) = self.chain_store.get_block(&location.block_hash).await? else {
+            return Ok(None);
+        };
+        let epoch = primitives::Policy::EPOCH_LENGTH;
+        let macro_block_number = block.block_number().div_ceil(epoch) * epoch;
+        let Some(Block::Macro(macro_block)) = self.chain_store.get_block_at(macro_block_number).await? else {
+            return Ok(None);
+        };
+        let Some(parent) = self.chain_store.get_block(&macro_block.header.parent_hash).await? else {
+            return Ok(None);
+        };
+
+        let mut batch = self.batch_transaction_hashes(parent).await?;
+        batch.extend(macro_block.body.transactions.iter().map(blockchain::block::Transaction::hash));
+        let proof = batch.iter().position(|hash| hash == transaction_hash)
+            .and_then(|index| light_client::MerkleProof::new(&batch, index));
+        Ok(proof.map(|proof| light_client::InclusionProof { transaction, macro_block_number, proof }))
+    }
+
+    /// Finality proof of a transaction for auditors, starting from the last certified election
+    /// block before the macro block that finalized it. `None` while that block is not certified
+    pub async fn finality_proof(&self, transaction_hash: &Blake2bHash) -> Result<Option<blockchain::FinalityProof>> {
+        let Some(inclusion) = self.inclusion_proof(transaction_hash).await? else {
+            return Ok(None);
+        };
+        let finalizing = inclusion.macro_block_number;
+        if self.certified_macro_header(finalizing).await?.is_none() {
+            return Ok(None);
+        }
+
+        let interval = primitives::Policy::ELECTION_BLOCK_INTERVAL;
+        let mut checkpoint_number = (finalizing - 1) - (finalizing - 1) % interval;
+        loop {
+            if checkpoint_number == 0 {
+                return Err(BlockchainError::NotFound(format!(
+                    "No certified election block before macro block {}", finalizing
+                )));
+            }
+            if self.certified_macro_header(checkpoint_number).await?.is_some() {
+                break;
+            }
+            checkpoint_number -= interval;
+        }
+        self.finality_proof_from(inclusion, checkpoint_number).await
+    }
+
+    /// Finality proof of an included transaction starting from the election block `checkpoint_number`,
+    /// for verifiers trusting that block rather than the latest one. `None` while the finalizing block
+    /// is not certified
+    pub async fn finality_proof_from(
+        &self,
+        inclusion: light_client::InclusionProof,
+        checkpoint_number: u32,
+    ) -> Result<Option<blockchain::FinalityProof>> {
+        let finalizing = inclusion.macro_block_number;
+        if !primitives::Policy::is_election_block(checkpoint_number) || checkpoint_number >= finalizing {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "Block {} is not an election block before macro block {}", checkpoint_number, finalizing
+            )));
+        }
+        let Some(finalizing_header) = self.certified_macro_header(finalizing).await? else {
+            return Ok(None);
+        };
+        let checkpoint = self.certified_macro_header(checkpoint_number).await?.ok_or_else(|| BlockchainError::NotFound(format!(
+            "Election block {} is not certified", checkpoint_number
+        )))?;
+
+        let interval = primitives::Policy::ELECTION_BLOCK_INTERVAL;
+
+        // Every election in between hands the signing over to the next validator set
+        let mut headers = Vec::new();
+        for election in (checkpoint_number + interval..finalizing).step_by(interval as usize) {
+            let header = self.certified_macro_header(election).await?.ok_or_else(|| BlockchainError::NotFound(format!(
+                "Election block {} is not certified", election
+            )))?;
+            headers.push(header);
+        }
+        headers.push(finalizing_header);
+
+        Ok(Some(blockchain::FinalityProof::new(checkpoint, headers, inclusion)))
+    }
+
+    async fn check_extends_head(&self, block: &Block) -> Result<()> {
+        let head = self.head_async().await;
+        if block.block_number() != head.block_number() + 1 || *block.parent_hash() != head.hash() {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} does not extend head {} at {}",
+                block.block_number(), head.hash(), head.block_number()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Execute a block's transactions and apply them to the state trie, returning the new state root
+    async fn execute_block_state(&self, block: &Block) -> Result<(Blake2bHash, Vec<smart_contracts::ContractReceipt>)> {
+        let mut receipts = self.execute_block_transactions(block).await?;
+
+        let epoch_validators = self.epoch_validators().await;
+        let admitted = {
+            let mut state_trie = self.state_trie.write().unwrap();
+            state_trie.apply_transactions(block.block_number(), block.transactions());
+            state_trie.apply_governance(block.block_number(), &epoch_validators, block.transactions());
+            match block {
+                Block::Macro(macro_block) => {
+                    state_trie.record_participation(&epoch_validators, &macro_block.body.lost_reward_set);
+                    vec![]
+                }
+                // Admissions write the operator registry through the contract VM, which only runs for micro blocks
+                Block::Micro(_) => state_trie.close_applications(block.block_number(), &epoch_validators),
+            }
+        };
+
+        if let Some(contract_engine) = &self.contract_engine {
+            for join in &admitted {
+                let receipt = contract_engine.admit_operator(join, block.block_number()).await?;
+                if receipt.success {
+                    tracing::info!("🏛️ {} admitted to the operator registry by governance", join.record.name);
+                } else {
+                    tracing::warn!("Accepted join application of {} not registered: {}",
+                        join.record.name, receipt.error.as_deref().unwrap_or("unknown"));
+                }
+                receipts.push(receipt);
+            }
+        }
+        Ok((self.state_trie.read().unwrap().root(), receipts))
+    }
+
+    /// Persist an executed block with its state changes and receipts and advance the heads
+    /// Everything is written in one atomic batch; if it fails the block's changes to the trie are reverted
+    /// and the in-memory heads are left alone, so a crash or error never leaves the heads half moved
+    async fn commit_block(&self, block: Block, receipts: Vec<smart_contracts::ContractReceipt>) -> Result<()> {
+        let block_hash = block.hash();
+        let (is_macro, is_election) = match &block {
+            Block::Micro(_) => (false, false),
+            Block::Macro(macro_block) => (true, primitives::Policy::is_election_block(macro_block.header.block_number)),
+        };
+        // Persist the state so it survives restarts and can be exported in snapshots
+        let state_changes = self.state_trie.read().unwrap().block_changes();
+        let events: Vec<_> = receipts.iter().flat_map(smart_contracts::EventRecord::of_receipt).collect();
+        let batch = WriteBatch {
+            block: Some(block.clone()),
+            state_changes,
+            receipts,
+            head: Some(block_hash),
+            macro_head: is_macro.then_some(block_hash),
+            election_head: is_election.then_some(block_hash),
+        };
+        if let Err(e) = self.chain_store.commit(batch).await {
+            self.state_trie.write().unwrap().revert_block();
+            return Err(e);
+        }
+        self.state_trie.write().unwrap().end_block();
+
+        metrics::metrics().chain_height.set(block.block_number() as i64);
+        if let Some(size_bytes) = self.chain_store.size_bytes() {
+            metrics::metrics().mdbx_size_bytes.set(size_bytes as i64);
+        }
+
+        // Update head pointers based on block type
+        match &block {
+            Block::Micro(_) => {
+                *self.head_block.write().await = block;
+            }
+            Block::Macro(macro_block) => {
+                *self.head_block.write().await = block.clone();
+                *self.macro_head.write().await = block.clone();
+
+                // Check if it's an election block (every 32 macro blocks following Albatross)
+                if is_election {
+                    *self.election_head.write().await = block.clone();
+
+                    // Update validator set if present
+                    if let Some(ref validators) = macro_block.body.validators {
+                        // Registrations are read before taking the set's lock, the trie lock is not held across awaits
+                        let joined_at: Vec<u32> = {
+                            let state = self.state_trie.read().unwrap();
+                            validators.iter()
+                                .map(|v| state.validator_record(&v.address).map_or(0, |record| record.registered_at))
+                                .collect()
+                        };
+                        let mut validator_set = self.validator_set.write().await;
+                        // Convert block::ValidatorInfo to validator_set::ValidatorInfo
+                        let converted_validators: Vec<blockchain::validator_set::ValidatorInfo> = validators
+                            .iter()
+                            .zip(blockchain::staking::voting_powers(validators))
+                            .zip(joined_at)
+                            .map(|((v, voting_power), joined_at_height)| blockchain::validator_set::ValidatorInfo {
+                                validator_address: v.address,
+                                signing_key: crate::crypto::PublicKey::from_bytes(&v.signing_key).unwrap_or_else(|_| crate::crypto::PublicKey::from_bytes(&[0u8; 48]).unwrap()),
+                                voting_power,
+                                network_operator: "default".to_string(),
+                                joined_at_height,
+                            })
+                            .collect();
+                        validator_set.update_validators(converted_validators);
+                        validator_set.finalize_epoch();
+                    }
+                }
+            }
+        }
+
+        self.open_escrow_disputes(&events).await;
+
+        // Nobody subscribed is not an error
+        for event in events {
+            let _ = self.contract_events.send(event);
+        }
+
+        Ok(())
+    }
+
+    /// Open a dispute by the creditor against the debtor for each escrow a committed block put into
+    /// dispute. Disputes are only opened once the block is committed, never for a proposal executed
+    /// speculatively; failing to open one leaves the block committed
+    async fn open_escrow_disputes(&self, events: &[smart_contracts::EventRecord]) {
+        let (Some(dispute_manager), Some(engine)) = (&self.dispute_manager, &self.contract_engine) else {
+            return;
+        };
+        let disputed_topic = smart_contracts::event_topic("EscrowDisputed");
+        let disputed = events.iter()
+            .filter(|record| record.event.contract_address == smart_contracts::settlement_contract::settlement_escrow_address())
+            .filter(|record| record.event.topics.first() == Some(&disputed_topic))
+            .filter_map(|record| record.event.topics.get(1));
+        for settlement_tx in disputed {
+            let opened = async {
+                let escrow = engine.escrow(settlement_tx).await?
+                    .ok_or_else(|| BlockchainError::NotFound(format!("No escrow of settlement {}", settlement_tx)))?;
+                let registered = |operator: &str, record: Option<blockchain::operator_registry::OperatorRecord>| record
+                    .map(|record| record.network_id())
+                    .ok_or_else(|| BlockchainError::NotFound(format!("{} is not a registered operator", operator)));
+                let creditor = registered(&escrow.creditor, engine.operator(&escrow.creditor).await?)?;
+                let debtor = registered(&escrow.debtor, engine.operator(&escrow.debtor).await?)?;
+                dispute_manager.open_dispute(
+                    *settlement_tx, network::settlement_messaging::DisputeReason::PaymentOverdue, None, escrow.amount, creditor, Some(debtor),
+                ).await
+            };
+            match opened.await {
+                Ok(dispute_id) => tracing::info!("⚖️ Dispute {} opened on the overdue payment of settlement {}", dispute_id, settlement_tx),
+                Err(e) => tracing::warn!("⚠️ Could not open a dispute on the overdue payment of settlement {}: {}", settlement_tx, e),
+            }
+        }
+    }
+
     /// Convert NetworkId to Blake2bHash for use as caller address
     fn network_id_to_hash(&self, network_id: &NetworkId) -> Blake2bHash {
         match network_id {
@@ -272,94 +1319,178 @@ impl SPCDRBlockchain {
         }
     }
 
-    /// Execute all transactions in a block before applying it
-    async fn execute_block_transactions(&self, block: &Block) -> Result<()> {
+    /// Execute all transactions in a block before applying it, returning the receipts to commit with it
+    /// Every contract transaction gets a receipt; failed ones have their state writes reverted
+    async fn execute_block_transactions(&self, block: &Block) -> Result<Vec<smart_contracts::ContractReceipt>> {
+        // Reject oversized blocks before spending any execution on them
+        let block_gas_limit = block.gas_limit();
+        let max_block_gas = self.chain_parameters().block_gas_limit;
+        if block_gas_limit > max_block_gas {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} reserves {} gas, limit is {}",
+                block.block_number(), block_gas_limit, max_block_gas
+            )));
+        }
+
+        Self::check_governance(block.block_number(), block.transactions(), &self.epoch_validators().await)?;
+        Self::check_network_joins(block.block_number(), block.transactions())?;
+
+        // Every transaction is signed by its sender, settlements and fraud flags by an operator they name,
+        // pays its minimum fee and continues its nonce sequence, replays are rejected, no batch or network
+        // pair period is settled twice, bridged settlements are proven final on their consortium's chain
+        // and validators are only changed by the accounts that created them, and CDR commitments are only
+        // challenged and answered as they allow
+        Self::check_signatures(block.block_number(), block.transactions())?;
+        self.check_operator_senders(block.block_number(), block.transactions()).await?;
+        Self::check_fees(block.block_number(), block.transactions())?;
+        self.check_settlements(block.transactions())
+            .and_then(|()| self.check_bridged_settlements(block.transactions()))
+            .and_then(|()| self.state_trie.read().unwrap().check_nonces(block.transactions()))
+            .and_then(|()| self.check_validator_updates(block.transactions()))
+            .and_then(|()| self.check_commitments(block.transactions()))
+            .map_err(|e| BlockchainError::BlockValidation(format!("Block {}: {}", block.block_number(), e)))?;
+
+        // Settlement periods are closed in macro blocks only
+        let is_macro = matches!(block, Block::Macro(_));
+        if !is_macro && block.transactions().iter().any(|transaction| matches!(transaction.data, TransactionData::PeriodClose(_))) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Micro block {} closes a settlement period", block.block_number()
+            )));
+        }
+
+        // Rewards are paid out in election blocks only
+        let is_election = primitives::Policy::is_election_block(block.block_number());
+        if !is_election && block.transactions().iter().any(|transaction| matches!(transaction.data, TransactionData::RewardPayout(_))) {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Block {} pays rewards but is not an election block", block.block_number()
+            )));
+        }
+
         // Only execute if we have a contract engine
         let contract_engine = match &self.contract_engine {
             Some(engine) => engine,
-            None => return Ok(()), // No contract execution without engine
+            None => return Ok(vec![]), // No contract execution without engine
         };
 
-        // Extract transactions from block
-        let transactions = match block {
-            Block::Micro(micro_block) => &micro_block.body.transactions,
-            Block::Macro(macro_block) => &macro_block.body.transactions,
-        };
+        // Contract state unwritten for the epochs governance keeps it expires before the block's transactions run
+        let expired = contract_engine.begin_block(block.block_number(), self.chain_parameters().state_expiry_epochs).await?;
+        if expired > 0 {
+            tracing::info!("🗄️ {} contract storage slots expired and archived at block {}", expired, block.block_number());
+        }
+        // Escrows left unconfirmed past their deadline go into dispute before the block's confirmations count
+        let disputed = contract_engine.expire_escrows(block.timestamp(), block.height()).await?;
+        for receipt in &disputed {
+            tracing::warn!("⚖️ {}, escrow disputed at block {}", receipt.logs.join("; "), block.block_number());
+        }
 
-        // Execute each transaction through the contract engine
-        for transaction in transactions {
-            // Check if this is a contract transaction (CDR settlement, deployment, etc.)
-            if let TransactionData::CDRRecord(cdr_tx) = &transaction.data {
-                // Create contract transaction from CDR transaction
-                // Generate settlement address from network pair
-                let settlement_address = crate::primitives::primitives::hash_data(
-                    format!("{}-{}", cdr_tx.home_network, cdr_tx.visited_network).as_bytes()
-                );
-
-                let contract_tx = smart_contracts::ContractTransaction {
-                    contract_address: settlement_address,
-                    caller: transaction.sender, // Use transaction sender as caller
+        // CDR records and settlements are executed in runs, in parallel where they call different
+        // contracts; upgrades, tariffs, resurrections and registrations change state later
+        // transactions read, so each of them ends the run before it
+        let mut block_gas_used = 0;
+        let mut receipts = disputed;
+        let mut run = Vec::new();
+        for (index, transaction) in block.transactions().iter().enumerate() {
+            let contract_tx = match &transaction.data {
+                // CDR records execute the settlement contract of their network pair
+                TransactionData::CDRRecord(cdr_tx) => smart_contracts::ContractTransaction {
+                    contract_address: crate::primitives::primitives::hash_data(
+                        format!("{}-{}", cdr_tx.home_network, cdr_tx.visited_network).as_bytes()
+                    ),
+                    caller: transaction.sender,
                     input_data: bincode::serialize(cdr_tx)
                         .map_err(|e| BlockchainError::Serialization(e.to_string()))?,
-                    gas_limit: 1_000_000, // Default gas limit for CDR transactions
+                    gas_limit: transaction.gas_limit(),
                     value: transaction.value,
-                    nonce: 0, // Basic nonce for now
-                };
-
-                // Execute the contract transaction
-                match contract_engine.execute_transaction(contract_tx, block.height(), 0).await {
-                    Ok(receipt) => {
-                        // Store execution result
-                        if let Some(mdbx_store) = self.chain_store.as_any().downcast_ref::<MdbxChainStore>() {
-                            let result_data = bincode::serialize(&receipt)
-                                .map_err(|e| BlockchainError::Serialization(e.to_string()))?;
-                            mdbx_store.put_execution_result(&transaction.hash(), &result_data).await?;
-                        }
-
-                        // Log successful execution
-                        println!("Contract execution successful: tx={}, gas_used={}",
-                            transaction.hash(), receipt.gas_used);
-                    }
-                    Err(e) => {
-                        eprintln!("Contract execution failed: tx={}, error={}",
-                            transaction.hash(), e);
-                        // In a production system, we might want to fail the entire block
-                        // For now, we continue processing other transactions
-                    }
-                }
-            }
-            // Handle other transaction types (SettlementTransaction, etc.)
-            else if let TransactionData::Settlement(settlement_tx) = &transaction.data {
-                // Settlement transactions can also trigger contract execution
-                // Generate settlement contract address from network pair
-                let contract_address = crate::primitives::primitives::hash_data(
-                    format!("{}-{}", settlement_tx.creditor_network, settlement_tx.debtor_network).as_bytes()
-                );
-
-                let contract_tx = smart_contracts::ContractTransaction {
-                    contract_address,
+                    nonce: transaction.nonce,
+                },
+                // Settlements are validated by the contract of their network pair
+                TransactionData::Settlement(settlement_tx) => smart_contracts::ContractTransaction {
+                    contract_address: crate::primitives::primitives::hash_data(
+                        format!("{}-{}", settlement_tx.creditor_network, settlement_tx.debtor_network).as_bytes()
+                    ),
                     caller: Blake2bHash::zero(), // System caller for settlements
-                    input_data: bincode::serialize(&settlement_tx)
+                    input_data: bincode::serialize(settlement_tx)
                         .map_err(|e| BlockchainError::Serialization(e.to_string()))?,
-                    gas_limit: 2_000_000, // Higher gas limit for settlement validation
+                    gas_limit: transaction.gas_limit(),
                     value: settlement_tx.amount,
-                    nonce: 0, // Basic nonce for now
-                };
-
-                match contract_engine.execute_transaction(contract_tx, block.height(), 0).await {
-                    Ok(receipt) => {
-                        println!("Settlement validation successful: tx={}, gas_used={}",
-                            transaction.hash(), receipt.gas_used);
-                    }
-                    Err(e) => {
-                        eprintln!("Settlement validation failed: tx={}, error={}",
-                            transaction.hash(), e);
+                    nonce: transaction.nonce,
+                },
+                _ if transaction.executes_contract() => {
+                    receipts.extend(self.execute_contract_run(contract_engine, std::mem::take(&mut run), block, &mut block_gas_used).await?);
+                    let (mut receipt, kind) = match &transaction.data {
+                        // Upgrades take effect from the next block
+                        TransactionData::ContractUpgrade(upgrade) => {
+                            (contract_engine.apply_upgrade(upgrade, block.height(), index as u32).await?, "Contract upgrade")
+                        }
+                        // Published tariffs are what contracts and CDR validation price usage with
+                        TransactionData::RateTable(signed) => {
+                            (contract_engine.publish_rate_table(signed, block.height(), index as u32).await?, "Rate table")
+                        }
+                        // Expired contract state comes back with a proof against the root of its expiry
+                        TransactionData::StateResurrection(resurrection) => {
+                            (contract_engine.resurrect_state(resurrection, block.height(), index as u32).await?, "State resurrection")
+                        }
+                        // The operator registry maps PLMN codes to network ids for the pipeline
+                        TransactionData::OperatorRegistration(registration) => {
+                            (contract_engine.register_operator(registration, block.height(), index as u32).await?, "Operator registration")
+                        }
+                        // Escrowed obligations are released by the payment confirmations recorded against them
+                        TransactionData::Escrow(escrow) => {
+                            let settlement = match escrow {
+                                smart_contracts::EscrowTransaction::Lock(lock) => self.settlement_final_before(&lock.settlement_tx, block.block_number()).await?,
+                                smart_contracts::EscrowTransaction::Confirm(_) => None,
+                            };
+                            (contract_engine.execute_escrow(escrow, settlement.as_ref(), block.timestamp(), block.height(), index as u32).await?, "Settlement escrow")
+                        }
+                        _ => continue,
+                    };
+                    receipt.transaction_hash = transaction.hash();
+                    if !receipt.success {
+                        tracing::warn!("{} rejected: tx={}, error={}",
+                            kind, transaction.hash(), receipt.error.as_deref().unwrap_or("unknown"));
                     }
+                    receipts.push(receipt);
+                    continue;
                 }
-            }
+                _ => continue,
+            };
+            run.push((index as u32, contract_tx));
         }
+        receipts.extend(self.execute_contract_run(contract_engine, run, block, &mut block_gas_used).await?);
 
-        Ok(())
+        metrics::metrics().block_gas_used.observe(block_gas_used as f64);
+        tracing::debug!("Block {} used {} of {} reserved gas", block.block_number(), block_gas_used, block_gas_limit);
+        // Receipts are kept for failed executions too, committed with the block
+        Ok(receipts)
+    }
+
+    /// Execute a run of CDR and settlement transactions of `block`, see `ConsensusContractEngine::execute_block_transactions`
+    async fn execute_contract_run(
+        &self,
+        contract_engine: &ConsensusContractEngine<MdbxContractStorage>,
+        run: Vec<(u32, smart_contracts::ContractTransaction)>,
+        block: &Block,
+        block_gas_used: &mut u64,
+    ) -> Result<Vec<smart_contracts::ContractReceipt>> {
+        if run.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut receipts = contract_engine.execute_block_transactions(run, block.height(), block.timestamp()).await?;
+        for receipt in &mut receipts {
+            // Receipts are looked up by the hash of the transaction in the block
+            let transaction = &block.transactions()[receipt.transaction_index as usize];
+            receipt.transaction_hash = transaction.hash();
+            *block_gas_used += receipt.gas_used;
+
+            if receipt.success {
+                tracing::debug!("Contract execution successful: tx={}, gas_used={}",
+                    receipt.transaction_hash, receipt.gas_used);
+            } else {
+                tracing::warn!("Contract execution failed, state reverted: tx={}, error={}",
+                    receipt.transaction_hash, receipt.error.as_deref().unwrap_or("unknown"));
+            }
+        }
+        Ok(receipts)
     }
 }
 
@@ -367,10 +1498,425 @@ impl SPCDRBlockchain {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    /// Register `name` with `key` in the operator registry, approved by the operators registered before it
+    async fn register_operator(
+        engine: &ConsensusContractEngine<MdbxContractStorage>,
+        name: &str,
+        plmn: &str,
+        key: &crypto::BLSPrivateKey,
+        approvers: &[(&str, &crypto::BLSPrivateKey)],
+    ) {
+        use blockchain::operator_registry::{OperatorApproval, OperatorRecord, OperatorRegistration};
+        let record = OperatorRecord {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            country: "Europe".to_string(),
+            plmn_codes: vec![plmn.to_string()],
+            signing_key: key.public_key().to_bytes().to_vec(),
+            encryption_key: None,
+            version: 1,
+        };
+        let payload = OperatorRegistration::signing_payload(&record);
+        let registration = OperatorRegistration {
+            signature: key.sign(&payload).unwrap().to_bytes().to_vec(),
+            approvals: approvers.iter()
+                .map(|(operator, key)| OperatorApproval { operator: operator.to_string(), signature: key.sign(&payload).unwrap().to_bytes().to_vec() })
+                .collect(),
+            record,
+        };
+        assert!(engine.register_operator(&registration, 1, 0).await.unwrap().success);
+    }
     
     #[tokio::test]
     async fn test_blockchain_integration() {
         // Test that all components can be instantiated and work together
         // This ensures our API integration is correct
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_produced_block_validates_on_other_validators() {
+        let producer_dir = tempfile::tempdir().unwrap();
+        let validator_dir = tempfile::tempdir().unwrap();
+        let producer = SPCDRBlockchain::open(std::sync::Arc::new(MdbxChainStore::new(producer_dir.path()).unwrap()), vec![]).await.unwrap();
+        let validator = SPCDRBlockchain::open(std::sync::Arc::new(MdbxChainStore::new(validator_dir.path()).unwrap()), vec![]).await.unwrap();
+
+        let key = crate::crypto::BLSPrivateKey::generate().unwrap();
+        let mut settlement = blockchain::block::Transaction {
+            sender: blockchain::block::account_address(&key.public_key()),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 12_500,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Settlement(SettlementTransaction {
+                creditor_network: "T-Mobile-DE".to_string(),
+                debtor_network: "Vodafone-UK".to_string(),
+                amount: 12_500,
+                currency: "EUR".to_string(),
+                period: "2024-01".to_string(),
+                breakdown: Default::default(),
+                batch_ids: vec![],
+            }),
+            signature: vec![],
+            signature_proof: vec![],
+        };
+
+        // Transactions are only included signed by their sender
+        assert!(producer.produce_block(vec![settlement.clone()]).await.is_err());
+        settlement.sign(&key).unwrap();
+        let mut forged = settlement.clone();
+        forged.value += 1;
+        assert!(producer.produce_block(vec![forged]).await.is_err());
+
+        let block = producer.produce_block(vec![settlement]).await.unwrap();
+        assert_eq!(block.block_number(), 1);
+        assert_ne!(*block.state_root(), Blake2bHash::zero());
+
+        // Re-execution on another validator reproduces the state root
+        validator.push_block(block.clone()).await.unwrap();
+        assert_eq!(validator.state_root(), producer.state_root());
+        assert_eq!(validator.head_async().await.hash(), block.hash());
+
+        // A block whose state root does not match execution is rejected
+        let mut forged = producer.produce_block(vec![]).await.unwrap();
+        if let Block::Micro(micro_block) = &mut forged {
+            micro_block.header.state_root = Blake2bHash::from_data(b"forged");
+        }
+        assert!(validator.push_block(forged).await.is_err());
+        assert_eq!(validator.head_async().await.hash(), block.hash());
+
+        // Blocks that do not extend the head are rejected
+        assert!(validator.push_block(block).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_settled_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let blockchain = SPCDRBlockchain::open(std::sync::Arc::new(MdbxChainStore::new(dir.path()).unwrap()), vec![]).await.unwrap();
+
+        let key = crate::crypto::BLSPrivateKey::generate().unwrap();
+        let settlement = |nonce: u64, period: &str| {
+            let mut transaction = blockchain::block::Transaction {
+                sender: blockchain::block::account_address(&key.public_key()),
+                recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+                value: 12_500,
+                fee: 100,
+                nonce,
+                validity_start_height: 0,
+                data: TransactionData::Settlement(SettlementTransaction {
+                    creditor_network: "T-Mobile-DE".to_string(),
+                    debtor_network: "Vodafone-UK".to_string(),
+                    amount: 12_500,
+                    currency: "EUR".to_string(),
+                    period: period.to_string(),
+                    breakdown: Default::default(),
+                    batch_ids: vec![Blake2bHash::from_data(b"batch-1")],
+                }),
+                signature: vec![],
+                signature_proof: vec![],
+            };
+            transaction.sign(&key).unwrap();
+            transaction
+        };
+
+        assert!(blockchain.produce_block(vec![settlement(0, "2024-01"), settlement(1, "2024-02")]).await.is_err());
+        blockchain.produce_block(vec![settlement(0, "2024-01")]).await.unwrap();
+        assert!(blockchain.produce_block(vec![settlement(1, "2024-02")]).await.is_err());
+        assert!(blockchain.check_settlements(&[settlement(1, "2024-01")]).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_gas_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let blockchain = SPCDRBlockchain::open(std::sync::Arc::new(MdbxChainStore::new(dir.path()).unwrap()), vec![]).await.unwrap();
+
+        let key = crate::crypto::BLSPrivateKey::generate().unwrap();
+        let settlement = |amount: u64| {
+            let mut transaction = blockchain::block::Transaction {
+                sender: blockchain::block::account_address(&key.public_key()),
+                recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+                value: amount,
+                fee: 100,
+                nonce: amount,
+                validity_start_height: 0,
+                data: TransactionData::Settlement(SettlementTransaction {
+                    creditor_network: "T-Mobile-DE".to_string(),
+                    debtor_network: "Vodafone-UK".to_string(),
+                    amount,
+                    currency: "EUR".to_string(),
+                    period: "2024-01".to_string(),
+                    breakdown: Default::default(),
+                    batch_ids: vec![],
+                }),
+                signature: vec![],
+                signature_proof: vec![],
+            };
+            transaction.sign(&key).unwrap();
+            transaction
+        };
+
+        let max_settlements = primitives::Policy::BLOCK_GAS_LIMIT / primitives::Policy::SETTLEMENT_TRANSACTION_GAS_LIMIT;
+        let oversized: Vec<_> = (0..=max_settlements).map(settlement).collect();
+        assert!(blockchain.produce_block(oversized).await.is_err());
+        assert_eq!(blockchain.head_async().await.block_number(), 0);
+        assert_eq!(blockchain.state_root(), Blake2bHash::zero());
+
+        // Settlements to a network pair without a deployed contract get failed receipts, the block still applies
+        let block = blockchain.produce_block((0..max_settlements).map(settlement).collect()).await.unwrap();
+        assert_eq!(block.gas_limit(), primitives::Policy::BLOCK_GAS_LIMIT);
+        assert_eq!(blockchain.head_async().await.hash(), block.hash());
+
+        let first = &block.transactions()[0];
+        let receipt = blockchain.get_receipt(&first.hash()).await.unwrap().unwrap();
+        assert!(!receipt.success);
+        assert_eq!(receipt.block_number, block.block_number());
+        assert_eq!(receipt.transaction_index, 0);
+
+        // All settlements of the pair went to the same contract, in block order
+        let receipts = blockchain.get_receipts_by_contract(&receipt.contract_address).await.unwrap();
+        assert_eq!(receipts.len() as u64, max_settlements);
+        assert!(receipts.windows(2).all(|pair| pair[0].transaction_index < pair[1].transaction_index));
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_macro_block_proposal_applies_once_finalized() {
+        let dir = tempfile::tempdir().unwrap();
+        let validator_key = crate::crypto::BLSPrivateKey::generate().unwrap();
+        let validator = ValidatorInfo {
+            address: Blake2bHash::from_data(b"validator"),
+            signing_key: validator_key.public_key().to_bytes().to_vec(),
+            voting_key: vec![],
+            reward_address: Blake2bHash::from_data(b"validator"),
+            signal_data: None,
+            inactive_from: None,
+            jailed_from: None,
+            stake: 0,
+        };
+        let blockchain = SPCDRBlockchain::open(std::sync::Arc::new(MdbxChainStore::new(dir.path()).unwrap()), vec![validator]).await.unwrap();
+
+        for _ in 1..primitives::Policy::EPOCH_LENGTH {
+            blockchain.produce_block(vec![]).await.unwrap();
+        }
+        let key = crate::crypto::BLSPrivateKey::generate().unwrap();
+        let transaction = |data| {
+            let mut transaction = blockchain::block::Transaction {
+                sender: blockchain::block::account_address(&key.public_key()),
+                recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+                value: 0,
+                fee: 100,
+                nonce: 0,
+                validity_start_height: 0,
+                data,
+                signature: vec![],
+                signature_proof: vec![],
+            };
+            transaction.sign(&key).unwrap();
+            transaction
+        };
+
+        // Election validators only belong into election blocks
+        assert!(blockchain.propose_macro_block(vec![], 0, Some(vec![])).await.is_err());
+
+        let proposal = blockchain.propose_macro_block(vec![transaction(TransactionData::Basic)], 1, None).await.unwrap();
+        assert_eq!(proposal.block_number(), primitives::Policy::EPOCH_LENGTH);
+        blockchain.check_proposal(&proposal).await.unwrap();
+        // Proposing commits nothing
+        assert_eq!(blockchain.head_async().await.block_number(), primitives::Policy::EPOCH_LENGTH - 1);
+
+        // Only finalized with the validators' precommit certificate, of the round it was signed in
+        assert!(blockchain.push_block(proposal.clone()).await.is_err());
+        let certified = |signed_round: u32, round: u32| {
+            let message = blockchain::light_client::certificate_message(&proposal.hash(), signed_round);
+            let signatures = vec![(0, validator_key.sign(&message).unwrap())];
+            let mut certified = proposal.clone();
+            if let Block::Macro(macro_block) = &mut certified {
+                macro_block.justification = Some(blockchain::light_client::MacroCertificate::aggregate(proposal.hash(), round, signatures).unwrap());
+            }
+            certified
+        };
+        assert!(blockchain.push_block(certified(0, 1)).await.is_err());
+        blockchain.push_block(certified(1, 1)).await.unwrap();
+        assert_eq!(blockchain.macro_head_async().await.hash(), proposal.hash());
+        assert!(blockchain.check_proposal(&proposal).await.is_err());
+
+        // The history root commits to the batch, light clients check inclusion against it
+        let Block::Macro(macro_block) = &proposal else { unreachable!() };
+        let included = proposal.transactions()[0].hash();
+        let proof = blockchain.inclusion_proof(&included).await.unwrap().unwrap();
+        assert_eq!(proof.macro_block_number, primitives::Policy::EPOCH_LENGTH);
+        assert_eq!(proof.proof.root(&included), Some(macro_block.header.history_root));
+
+        // Fees accrue for the validators until the next election block
+        assert_eq!(blockchain.state_trie.read().unwrap().reward_pool(), 100);
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_signatures_checked() {
+        let key = crypto::BLSPrivateKey::generate().unwrap();
+        let mut transaction = blockchain::block::Transaction {
+            sender: Blake2bHash::zero(),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 0,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Basic,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        transaction.sign(&key).unwrap();
+        SPCDRBlockchain::check_signatures(1, &[transaction.clone()]).unwrap();
+
+        // One tampered or foreign-signed transaction fails the whole block
+        let mut tampered = transaction.clone();
+        tampered.fee += 1;
+        assert!(SPCDRBlockchain::check_signatures(1, &[transaction.clone(), tampered]).is_err());
+        let other = crypto::BLSPrivateKey::generate().unwrap();
+        let mut foreign = transaction.clone();
+        foreign.signature = other.sign(&transaction.signing_payload()).unwrap().to_bytes().to_vec();
+        assert!(SPCDRBlockchain::check_signatures(1, &[foreign]).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_operator_senders_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let blockchain = SPCDRBlockchain::open(std::sync::Arc::new(MdbxChainStore::new(dir.path()).unwrap()), vec![]).await.unwrap();
+        let engine = blockchain.contract_engine.clone().unwrap();
+
+        let creditor_key = crypto::BLSPrivateKey::generate().unwrap();
+        let debtor_key = crypto::BLSPrivateKey::generate().unwrap();
+        let outsider_key = crypto::BLSPrivateKey::generate().unwrap();
+        let (creditor, debtor) = (NetworkId::new("T-Mobile-DE", "Europe"), NetworkId::new("Vodafone-UK", "Europe"));
+        let unsigned = |data| blockchain::block::Transaction {
+            sender: Blake2bHash::zero(),
+            recipient: Blake2bHash::from_data(b"Vodafone-UK"),
+            value: 0,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        let settlement = || unsigned(TransactionData::Settlement(SettlementTransaction {
+            creditor_network: format!("{:?}", creditor),
+            debtor_network: format!("{:?}", debtor),
+            amount: 12_500,
+            currency: "EUR".to_string(),
+            period: "2024-01".to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![],
+        }));
+        let fraud_flag = || unsigned(TransactionData::FraudFlag(blockchain::block::FraudFlagTransaction {
+            batch_id: Blake2bHash::from_data(b"batch"),
+            home_network: format!("{:?}", creditor),
+            visited_network: format!("{:?}", debtor),
+            score: 90,
+            reasons: vec!["velocity".to_string()],
+            quarantine: true,
+        }));
+        let signed = |mut transaction: blockchain::block::Transaction, key: &crypto::BLSPrivateKey| {
+            transaction.sign(key).unwrap();
+            transaction
+        };
+
+        // Anyone settles until operators are registered
+        blockchain.check_operator_senders(1, &[signed(settlement(), &outsider_key)]).await.unwrap();
+
+        register_operator(&engine, "T-Mobile-DE", "26201", &creditor_key, &[]).await;
+        register_operator(&engine, "Vodafone-UK", "23415", &debtor_key, &[("T-Mobile-DE", &creditor_key)]).await;
+
+        // Either named operator sends, nobody else
+        blockchain.check_operator_senders(2, &[signed(settlement(), &creditor_key), signed(settlement(), &debtor_key)]).await.unwrap();
+        blockchain.check_operator_senders(2, &[signed(fraud_flag(), &debtor_key)]).await.unwrap();
+        assert!(blockchain.check_operator_senders(2, &[signed(settlement(), &outsider_key)]).await.is_err());
+        assert!(blockchain.check_operator_senders(2, &[signed(fraud_flag(), &outsider_key)]).await.is_err());
+
+        // A node hosting the debtor sends from the debtor's account with the debtor's signer
+        let hosted: std::sync::Arc<dyn crypto::Signer> = std::sync::Arc::new(debtor_key.clone());
+        let mut transaction = settlement();
+        transaction.sign_with(hosted.as_ref()).await.unwrap();
+        SPCDRBlockchain::check_signatures(2, &[transaction.clone()]).unwrap();
+        blockchain.check_operator_senders(2, &[transaction]).await.unwrap();
+
+        // Transactions naming no operator are not restricted
+        blockchain.check_operator_senders(2, &[signed(unsigned(TransactionData::Basic), &outsider_key)]).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_expired_escrow_opens_dispute() {
+        use network::dispute_resolution::DisputeManager;
+        use network::settlement_messaging::{ConfirmationType, DisputeReason};
+        use smart_contracts::{EscrowLock, EscrowStatus, EscrowTransaction, PaymentConfirmation};
+
+        let dir = tempfile::tempdir().unwrap();
+        let chain_store = std::sync::Arc::new(MdbxChainStore::new(dir.path()).unwrap());
+        let dispute_manager = std::sync::Arc::new(DisputeManager::new(chain_store.clone()));
+        let blockchain = SPCDRBlockchain::open(chain_store, vec![]).await.unwrap()
+            .with_dispute_manager(dispute_manager.clone());
+        let engine = blockchain.contract_engine.clone().unwrap();
+
+        // Both parties are registered, the debtor with the key it locks escrows with
+        let creditor_key = crypto::BLSPrivateKey::generate().unwrap();
+        let debtor_key = crypto::BLSPrivateKey::generate().unwrap();
+        register_operator(&engine, "T-Mobile-DE", "26201", &creditor_key, &[]).await;
+        register_operator(&engine, "Vodafone-UK", "23415", &debtor_key, &[("T-Mobile-DE", &creditor_key)]).await;
+        let (creditor, debtor) = (NetworkId::new("T-Mobile-DE", "Europe"), NetworkId::new("Vodafone-UK", "Europe"));
+
+        let settlement = SettlementTransaction {
+            creditor_network: format!("{:?}", creditor),
+            debtor_network: format!("{:?}", debtor),
+            amount: 12_500,
+            currency: "EUR".to_string(),
+            period: "2024-01".to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![],
+        };
+        let lock = |settlement_tx: Blake2bHash| {
+            let mut lock = EscrowLock {
+                settlement_tx,
+                creditor: "T-Mobile-DE".to_string(),
+                debtor: "Vodafone-UK".to_string(),
+                amount: 12_500,
+                currency: "EUR".to_string(),
+                payment_agent: None,
+                signature: vec![],
+                agent_approval: vec![],
+            };
+            lock.signature = debtor_key.sign(&lock.signing_payload()).unwrap().to_bytes().to_vec();
+            EscrowTransaction::Lock(lock)
+        };
+        let (paid, unpaid) = (Blake2bHash::from_data(b"paid"), Blake2bHash::from_data(b"unpaid"));
+        for settlement_tx in [paid, unpaid] {
+            assert!(engine.execute_escrow(&lock(settlement_tx), Some(&settlement), 1_000, 2, 0).await.unwrap().success);
+        }
+
+        // The creditor's confirmation releases one escrow, the other is disputed at its deadline
+        let mut confirmation = PaymentConfirmation {
+            settlement_tx: paid,
+            confirmer: "T-Mobile-DE".to_string(),
+            confirmation_type: ConfirmationType::PaymentConfirmed,
+            transaction_ref: Some("SEPA-42".to_string()),
+            signature: vec![],
+        };
+        confirmation.signature = creditor_key.sign(&confirmation.signing_payload()).unwrap().to_bytes().to_vec();
+        assert!(engine.execute_escrow(&EscrowTransaction::Confirm(confirmation), None, 2_000, 3, 0).await.unwrap().success);
+
+        let deadline = engine.escrow(&unpaid).await.unwrap().unwrap().deadline;
+        blockchain.state_trie.write().unwrap().begin_block();
+        assert!(engine.expire_escrows(deadline - 1, 4).await.unwrap().is_empty());
+        let disputed = engine.expire_escrows(deadline, 4).await.unwrap();
+        assert_eq!(disputed.len(), 1);
+        assert_eq!(engine.escrow(&unpaid).await.unwrap().unwrap().status, EscrowStatus::Disputed { opened_at: deadline });
+        assert!(matches!(engine.escrow(&paid).await.unwrap().unwrap().status, EscrowStatus::Released { .. }));
+        assert!(dispute_manager.find_by_settlement(&unpaid).await.is_none());
+
+        // Committing the block with the expiry opens the creditor's dispute against the debtor
+        let block = blockchain.build_block(vec![], 0, None).await.unwrap();
+        blockchain.commit_block(block, disputed).await.unwrap();
+        let dispute = dispute_manager.find_by_settlement(&unpaid).await.unwrap();
+        assert_eq!(dispute.reason, DisputeReason::PaymentOverdue);
+        assert_eq!((dispute.initiator, dispute.respondent), (creditor, Some(debtor)));
+        assert_eq!(dispute.original_amount, 12_500);
+        assert!(dispute_manager.find_by_settlement(&paid).await.is_none());
+    }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@
 // Main entry point for running the blockchain node
 
 use clap::{Parser, Subcommand};
-use sp_cdr_reconciliation_bc::{*, bce_pipeline, storage, blockchain, primitives::Blake2bHash};
+use sp_cdr_reconciliation_bc::{*, bce_pipeline, storage, blockchain, smart_contracts, primitives::Blake2bHash};
 use tracing::{info, error};
 use std::sync::Arc;
 
@@ -12,6 +12,28 @@ use std::sync::Arc;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Output format of command results on stdout: text or json; logs go to stderr either way
+    #[arg(long = "output", global = true, default_value = "text")]
+    output_format: String,
+}
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = primitives::BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(primitives::BlockchainError::InvalidOperation(format!("Unknown output format {}, expected text or json", s))),
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -30,11 +52,109 @@ enum Commands {
         /// Bootstrap node - generates trusted setup keys for the network
         #[arg(long)]
         bootstrap: bool,
+        /// Bootstrap nodes to dial on startup (comma-separated multiaddrs)
+        #[arg(long, value_delimiter = ',')]
+        bootnodes: Vec<String>,
+        /// Block history to keep: archive (everything) or validator (recent epochs and macro blocks)
+        #[arg(long, default_value = "archive")]
+        pruning: String,
+        /// Settlement billing cycle: <days>d, semi-monthly or monthly
+        #[arg(long, default_value = "15d")]
+        settlement_cycle: String,
+        /// Port to serve Prometheus metrics on, disabled if not set
+        #[arg(long)]
+        metrics_port: Option<u16>,
+        /// Light client: follow certified macro headers only, without validating or storing blocks
+        #[arg(long)]
+        light: bool,
+        /// Hot standby for the validator with this peer id, signing once its heartbeats stop
+        #[arg(long)]
+        standby_for: Option<String>,
+        /// Validator key escrow a standby takes over with [default: <data-dir>/validator.escrow]
+        #[arg(long)]
+        key_escrow: Option<String>,
+        /// Node ids of the other nodes signing for this validator (comma-separated), enables failover
+        #[arg(long, value_delimiter = ',')]
+        failover_peers: Vec<String>,
+        /// When settlement runs: an interval like 60s, 5m or 1h, or a cron expression in UTC
+        #[arg(long, default_value = "60s")]
+        settlement_schedule: String,
+        /// Pending BCE records at which ingestion is refused, resumed below 80% of it
+        #[arg(long, default_value = "5000000")]
+        max_pending_records: usize,
+        /// Seconds to wait for the bootstrap node's trusted setup keys
+        #[arg(long, default_value = "300")]
+        trusted_setup_timeout: u64,
+        /// Generate local trusted setup keys if none arrive in time; other nodes reject this node's proofs
+        #[arg(long)]
+        allow_local_trusted_setup: bool,
+        /// Operators contributing to a multi-party trusted setup ceremony in this order (comma-separated),
+        /// on every node alike; a single-party ceremony on the bootstrap node if not set
+        #[arg(long, value_delimiter = ',')]
+        ceremony_participants: Vec<String>,
+        /// ZK profile: verifier (verifying keys only, no records ingested), prover or full
+        #[arg(long, default_value = "full")]
+        role: String,
+        /// Open connections allowed to all nodes of one operator
+        #[arg(long, default_value = "4")]
+        max_operator_connections: u32,
+        /// Connections a peer may open per minute before it is banned for ten minutes
+        #[arg(long, default_value = "10")]
+        max_connection_rate: u32,
+        /// Consortium relay nodes to be reached through behind NAT (comma-separated, ending in /p2p/<peer id>)
+        #[arg(long, value_delimiter = ',')]
+        relay_nodes: Vec<String>,
+        /// Relay connections for nodes behind NAT, for publicly reachable nodes
+        #[arg(long)]
+        relay: bool,
+        /// Transport to prefer: tcp, or quic to listen on QUIC as well and dial it before falling back to TCP
+        #[arg(long, default_value = "tcp")]
+        transport: String,
+        /// Sentry deployment: off, validator to connect only to --sentry-peers, or sentry to relay for them
+        #[arg(long, default_value = "off")]
+        sentry_mode: String,
+        /// A validator's sentries, or the validators behind a sentry (comma-separated, ending in /p2p/<peer id>)
+        #[arg(long, value_delimiter = ',')]
+        sentry_peers: Vec<String>,
+        /// JSON file of webhook, email and syslog sinks settlement lifecycle events are notified to
+        #[arg(long)]
+        notifications: Option<String>,
+        /// JSON file naming this node's consortium and the consortia whose settlements it bridges onto it
+        #[arg(long)]
+        bridge: Option<String>,
+        /// Days detailed CDR data is kept, per record type (e.g. voice=180,data=180,sms=90,roaming=365),
+        /// indefinitely for types not listed
+        #[arg(long)]
+        retention: Option<String>,
+        /// JSON file of operator identities this node hosts besides its own, each with its signing key and settlement policy
+        #[arg(long)]
+        tenancy: Option<String>,
+        /// JSON file of the operators' bank accounts, to issue signed pain.001 documents for this node's finalized settlements
+        #[arg(long)]
+        bank_accounts: Option<String>,
+    },
+    /// Print this node's escrow key and node id, to set it up as hot standby
+    StandbyKey {
+        /// Data directory of the standby node
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+    },
+    /// Seal this validator's BLS key for a hot standby
+    EscrowKey {
+        /// Data directory of the validator
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Escrow key printed by standby-key on the standby node
+        #[arg(short, long)]
+        standby_key: String,
+        /// Escrow file to write, copied into the standby's data directory
+        #[arg(short, long = "output-file")]
+        output: String,
     },
     /// Generate validator keys
     GenerateKeys {
         /// Output directory for keys
-        #[arg(short, long, default_value = "./keys")]
+        #[arg(short, long = "output-dir", default_value = "./keys")]
         output: String,
     },
     /// Validate CDR records
@@ -48,7 +168,7 @@ enum Commands {
         /// Data directory to inspect
         #[arg(short, long, default_value = "./data")]
         data_dir: String,
-        /// What to inspect: blocks, transactions, cdrs, settlements
+        /// What to inspect: blocks, transactions, receipts, cdrs, settlements
         #[arg(short, long, default_value = "blocks")]
         target: String,
         /// Optional block number or transaction hash
@@ -57,38 +177,296 @@ enum Commands {
         /// Number of recent items to show
         #[arg(short, long, default_value = "10")]
         limit: usize,
+        /// Only settlements and batches between these operators (comma-separated, one or two)
+        #[arg(long, value_delimiter = ',')]
+        operators: Vec<String>,
+        /// Only settlements and batches of this settlement period
+        #[arg(long)]
+        period: Option<String>,
+        /// Only settlements (finalized, pending) or batches (committed, quarantined, released, settled) in this status
+        #[arg(long)]
+        status: Option<String>,
+    },
+    /// Export the chain state into a snapshot file
+    ExportSnapshot {
+        /// Data directory to export from
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Snapshot file to write
+        #[arg(short, long = "output-file")]
+        output: String,
+    },
+    /// Restore the chain state from a snapshot file into an empty data directory
+    ImportSnapshot {
+        /// Data directory to import into
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Snapshot file to read
+        #[arg(short, long)]
+        file: String,
+    },
+    /// Export the settlement audit log as signed JSONL
+    ExportAudit {
+        /// Data directory to export from
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// JSONL file to write
+        #[arg(short, long = "output-file")]
+        output: String,
+        /// Node key to sign the export with [default: <data-dir>/node.key]
+        #[arg(short, long)]
+        key: Option<String>,
+    },
+    /// Compile a settlement contract source file to VM bytecode
+    CompileContract {
+        /// Contract source file
+        file: String,
+        /// Bytecode file to write
+        #[arg(short, long = "output-file")]
+        output: Option<String>,
+    },
+    /// Import a bulk BCE export from a mediation system into the pipeline
+    ImportBce {
+        /// Export file to import
+        #[arg(short, long)]
+        file: String,
+        /// File format: csv, asn1 or json
+        #[arg(long, default_value = "csv")]
+        format: String,
+        /// Records handed to the pipeline at a time
+        #[arg(long, default_value = "500")]
+        chunk_size: usize,
+        /// Data directory of the node, which must not be running
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Network ID to import as
+        #[arg(short, long, default_value = "consortium")]
+        network: String,
+    },
+    /// Export the proof that a settlement transaction is final, for auditors without a node
+    ExportFinalityProof {
+        /// Data directory to export from
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Hash of the settlement transaction
+        #[arg(short, long)]
+        transaction: String,
+        /// Proof file to write
+        #[arg(short, long = "output-file")]
+        output: String,
+    },
+    /// Verify a finality proof file without chain data
+    VerifyFinality {
+        /// Proof file to verify
+        file: String,
+        /// Published hash of the election block the proof must start from; without it the proof is
+        /// reported UNTRUSTED and the command exits with status 2
+        #[arg(long)]
+        checkpoint: Option<String>,
+    },
+    /// Check the chain store for corruption, optionally rebuilding the derived indexes
+    Fsck {
+        /// Data directory to check
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Rebuild the height, transaction and receipt indexes from the stored blocks; the node must not be running
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Report gross, netted and settled amounts per operator pair for a settlement period
+    Report {
+        /// Data directory to report from
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Period to report, e.g. 2024-03 for every settlement period starting in March 2024
+        #[arg(short, long)]
+        period: String,
+        /// Export format: statement or csv
+        #[arg(long, default_value = "statement")]
+        format: String,
+        /// File to write the export to [default: stdout]
+        #[arg(short, long = "output-file")]
+        output: Option<String>,
+    },
+    /// Project the savings of bilateral, triangular and multilateral netting over the pending balances
+    NettingProjection {
+        /// Data directory of the node
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+    },
+    /// Export finalized settlements of a period for legacy billing systems, one file per counterparty
+    ExportSettlements {
+        /// Data directory to export from
+        #[arg(short, long, default_value = "./data")]
+        data_dir: String,
+        /// Period to export, e.g. 2024-03 for every settlement period starting in March 2024
+        #[arg(short, long)]
+        period: String,
+        /// Operator the files are for: tmobile, vodafone or orange
+        #[arg(short, long)]
+        network: String,
+        /// File format: tap or bce-json
+        #[arg(long, default_value = "bce-json")]
+        format: String,
+        /// Directory to write the files to [default: <data-dir>/exports]
+        #[arg(long)]
+        output_dir: Option<String>,
+    },
+    /// Run a devnet of in-process nodes over mock links and check the settlements of synthetic roaming traffic
+    Simulate {
+        /// Validator nodes, one per operator
+        #[arg(long, default_value = "5")]
+        nodes: usize,
+        /// CDR records generated per second of simulated time
+        #[arg(long, default_value = "100")]
+        tps: u32,
+        /// Blocks to generate traffic for
+        #[arg(long, default_value = "96")]
+        blocks: u32,
+        /// Operator graph: mesh, ring, star or random:<degree>
+        #[arg(long, default_value = "mesh")]
+        graph: String,
+        /// One-way link latency in milliseconds
+        #[arg(long, default_value = "50")]
+        latency_ms: u64,
+        /// Seed of the traffic and the link jitter
+        #[arg(long, default_value = "1")]
+        seed: u64,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
-    
+    // Initialize tracing, on stderr to keep stdout for command results
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
     let cli = Cli::parse();
+    let format: OutputFormat = cli.output_format.parse()?;
 
     match cli.command {
-        Commands::Start { network, data_dir, port, bootstrap } => {
-            start_node(network, data_dir, port, bootstrap).await
+        Commands::Start {
+            network, data_dir, port, bootstrap, bootnodes, pruning, settlement_cycle, metrics_port, light,
+            standby_for, key_escrow, failover_peers, settlement_schedule, max_pending_records,
+            trusted_setup_timeout, allow_local_trusted_setup, ceremony_participants, role,
+            max_operator_connections, max_connection_rate, relay_nodes, relay, transport, sentry_mode, sentry_peers, notifications, bridge, retention, tenancy, bank_accounts,
+        } => {
+            if let Some(metrics_port) = metrics_port {
+                tokio::spawn(metrics::serve(metrics_port));
+            }
+            if light {
+                return start_light_node(network, port, bootnodes).await;
+            }
+            let failover = parse_failover(&data_dir, standby_for, key_escrow, &failover_peers)?;
+            let ingest_limits = bce_pipeline::ingest_queue::IngestLimits {
+                high_watermark: max_pending_records,
+                low_watermark: max_pending_records / 5 * 4,
+                ..Default::default()
+            };
+            let schedule = bce_pipeline::scheduler::PipelineSchedule {
+                settlement: settlement_schedule.parse()?,
+                ..Default::default()
+            };
+            let key_fetch = network::KeyFetchConfig {
+                timeout: std::time::Duration::from_secs(trusted_setup_timeout),
+                allow_local_keys: allow_local_trusted_setup,
+            };
+            let role = role.parse()?;
+            let connection_limits = network::ConnectionLimits {
+                max_connections_per_operator: max_operator_connections,
+                max_connections_per_window: max_connection_rate,
+                ..Default::default()
+            };
+            let network_config = network::NetworkConfig {
+                nat: network::NatConfig {
+                    relay_nodes: relay_nodes.iter()
+                        .map(|addr| addr.parse::<libp2p::Multiaddr>()
+                            .map_err(|e| primitives::BlockchainError::NetworkError(format!("Invalid relay node {}: {}", addr, e))))
+                        .collect::<Result<_>>()?,
+                    act_as_relay: relay,
+                },
+                transport: transport.parse()?,
+                sentry: network::SentryConfig {
+                    mode: sentry_mode.parse()?,
+                    private_peers: sentry_peers.iter()
+                        .map(|addr| addr.parse::<libp2p::Multiaddr>()
+                            .map_err(|e| primitives::BlockchainError::NetworkError(format!("Invalid sentry peer {}: {}", addr, e))))
+                        .collect::<Result<_>>()?,
+                },
+            };
+            let notifications = notifications
+                .map(|path| notifications::NotificationConfig::load(std::path::Path::new(&path)))
+                .transpose()?;
+            let bridge = bridge
+                .map(|path| bridge::BridgeConfig::load(std::path::Path::new(&path)))
+                .transpose()?;
+            let retention = retention.map(|retention| retention.parse()).transpose()?;
+            let tenancy = tenancy
+                .map(|path| network::TenancyConfig::load(std::path::Path::new(&path)))
+                .transpose()?;
+            let bank_accounts = bank_accounts
+                .map(|path| settlement_execution::BankAccountDirectory::load(std::path::Path::new(&path)))
+                .transpose()?;
+            start_node(network, data_dir, port, bootstrap, bootnodes, pruning, settlement_cycle, failover, ingest_limits, schedule, key_fetch, ceremony_participants, role, connection_limits, network_config, notifications, bridge, retention, tenancy, bank_accounts).await
+        }
+        Commands::StandbyKey { data_dir } => {
+            standby_key(data_dir, format).await
+        }
+        Commands::EscrowKey { data_dir, standby_key, output } => {
+            escrow_key(data_dir, standby_key, output, format).await
         }
         Commands::GenerateKeys { output } => {
-            generate_validator_keys(output).await
+            generate_validator_keys(output, format).await
         }
         Commands::ValidateCDR { file } => {
-            validate_cdr_file(file).await
+            validate_cdr_file(file, format).await
+        }
+        Commands::Inspect { data_dir, target, id, limit, operators, period, status } => {
+            let query = storage::ChainQuery { operators, period, status };
+            inspect_blockchain(data_dir, target, id, limit, query, format).await
+        }
+        Commands::ExportSnapshot { data_dir, output } => {
+            export_snapshot(data_dir, output, format).await
+        }
+        Commands::ImportSnapshot { data_dir, file } => {
+            import_snapshot(data_dir, file, format).await
         }
-        Commands::Inspect { data_dir, target, id, limit } => {
-            inspect_blockchain(data_dir, target, id, limit).await
+        Commands::ExportAudit { data_dir, output, key } => {
+            export_audit(data_dir, output, key, format).await
+        }
+        Commands::CompileContract { file, output } => {
+            compile_contract(file, output, format).await
+        }
+        Commands::ImportBce { file, format: file_format, chunk_size, data_dir, network } => {
+            import_bce(file, file_format, chunk_size, data_dir, network, format).await
+        }
+        Commands::Report { data_dir, period, format: export_format, output } => {
+            settlement_report(data_dir, period, export_format, output, format).await
+        }
+        Commands::NettingProjection { data_dir } => {
+            netting_projection(data_dir, format).await
+        }
+        Commands::ExportSettlements { data_dir, period, network, format: export_format, output_dir } => {
+            export_settlements(data_dir, period, network, export_format, output_dir, format).await
+        }
+        Commands::ExportFinalityProof { data_dir, transaction, output } => {
+            export_finality_proof(data_dir, transaction, output, format).await
+        }
+        Commands::VerifyFinality { file, checkpoint } => {
+            verify_finality(file, checkpoint, format)
+        }
+        Commands::Fsck { data_dir, repair } => {
+            fsck(data_dir, repair, format).await
+        }
+        Commands::Simulate { nodes, tps, blocks, graph, latency_ms, seed } => {
+            let config = simulation::SimulationConfig { nodes, tps, blocks, graph: graph.parse()?, latency_ms, seed };
+            simulate(config, format).await
         }
     }
 }
 
-async fn start_node(network: String, data_dir: String, port: u16, bootstrap: bool) -> Result<()> {
-    info!("Starting SP CDR Reconciliation Blockchain Node");
-    info!("Network: {}, Data Directory: {}, Port: {}", network, data_dir, port);
-
-    // Parse network ID - use specific operator networks for demo
-    let network_id = match network.as_str() {
+/// Parse network ID - use specific operator networks for demo
+fn parse_network_id(network: &str) -> NetworkId {
+    match network {
         "tmobile" => NetworkId::new("T-Mobile", "DE"),
         "vodafone" => NetworkId::new("Vodafone", "UK"),
         "orange" => NetworkId::new("Orange", "FR"),
@@ -99,7 +477,150 @@ async fn start_node(network: String, data_dir: String, port: u16, bootstrap: boo
             error!("Unknown network: {}. Use: tmobile, vodafone, orange, consortium, devnet, testnet", network);
             std::process::exit(1);
         }
+    }
+}
+
+/// Parse bootstrap node addresses
+fn parse_bootnodes(bootnodes: &[String]) -> Result<Vec<libp2p::Multiaddr>> {
+    bootnodes.iter()
+        .map(|addr| addr.parse::<libp2p::Multiaddr>()
+            .map_err(|e| primitives::BlockchainError::NetworkError(format!("Invalid bootnode {}: {}", addr, e))))
+        .collect()
+}
+
+/// Parse the hot-standby setup, `None` without failover peers or standby target
+fn parse_failover(
+    data_dir: &str,
+    standby_for: Option<String>,
+    key_escrow: Option<String>,
+    failover_peers: &[String],
+) -> Result<Option<network::FailoverConfig>> {
+    let parse_peer_id = |peer: &str| peer.parse::<libp2p::PeerId>()
+        .map_err(|e| primitives::BlockchainError::NetworkError(format!("Invalid peer id {}: {}", peer, e)));
+    let peers = failover_peers.iter().map(|peer| parse_peer_id(peer)).collect::<Result<Vec<_>>>()?;
+
+    let role = match standby_for {
+        Some(validator_id) => network::FailoverRole::Standby {
+            validator_id: parse_peer_id(&validator_id)?,
+            key_escrow: key_escrow.unwrap_or_else(|| format!("{}/validator.escrow", data_dir)).into(),
+        },
+        None if peers.is_empty() => return Ok(None),
+        None => network::FailoverRole::Primary,
     };
+    info!("Failover: {:?} with {} peers", role, peers.len());
+    Ok(Some(network::FailoverConfig { role, peers }))
+}
+
+/// Run a light client for small operators: sync certified macro headers from full nodes
+/// and keep the validator set current, without the ZK setup, block execution or storage
+async fn start_light_node(network: String, port: u16, bootnodes: Vec<String>) -> Result<()> {
+    info!("Starting SP CDR light client");
+    let network_id = parse_network_id(&network);
+    let bootnodes = parse_bootnodes(&bootnodes)?;
+
+    let listen_addr = format!("/ip4/127.0.0.1/tcp/{}", port).parse()
+        .map_err(|e| primitives::BlockchainError::NetworkError(format!("Invalid address: {}", e)))?;
+    let (mut network_manager, command_sender, mut events) =
+        network::SPNetworkManager::new(network_id.clone(), listen_addr).await?;
+    network_manager.add_bootnodes(bootnodes);
+    tokio::spawn(network_manager.run());
+
+    info!("🪶 Light client for {:?} listening on port {}", network_id, port);
+    info!("Press Ctrl+C to stop...");
+
+    let mut sync = network::LightSync::new();
+    let mut peers = std::collections::HashSet::new();
+    let mut poll = tokio::time::interval(std::time::Duration::from_millis(primitives::Policy::BLOCK_TIME * primitives::Policy::EPOCH_LENGTH as u64));
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                info!("Shutdown signal received...");
+                return Ok(());
+            }
+            _ = poll.tick() => {
+                // Ask every full node, headers already synced from another peer are skipped
+                for peer in &peers {
+                    let _ = command_sender.send(network::NetworkCommand::SendMessage {
+                        peer: *peer,
+                        message: sync.headers_request(),
+                    }).await;
+                }
+            }
+            event = events.recv() => match event {
+                Ok(network::NetworkEvent::PeerConnected(peer)) => {
+                    peers.insert(peer);
+                    let _ = command_sender.send(network::NetworkCommand::SendMessage {
+                        peer,
+                        message: sync.headers_request(),
+                    }).await;
+                }
+                Ok(network::NetworkEvent::PeerDisconnected(peer)) => {
+                    peers.remove(&peer);
+                }
+                Ok(network::NetworkEvent::MessageReceived { peer, message: network::SPNetworkMessage::MacroHeaders { headers } }) => {
+                    if let Err(e) = sync.handle_headers(headers) {
+                        error!("❌ Rejected macro headers from {}: {}", peer, e);
+                    }
+                }
+                Ok(network::NetworkEvent::MessageReceived { message: network::SPNetworkMessage::InclusionProofResponse { transaction_hash, proof }, .. }) => {
+                    match sync.handle_inclusion_proof(&transaction_hash, proof.as_ref()) {
+                        Ok(true) => info!("✅ Transaction {} is in a finalized batch", transaction_hash),
+                        Ok(false) => info!("⏳ Transaction {} is not finalized yet", transaction_hash),
+                        Err(e) => error!("❌ Invalid inclusion proof for {}: {}", transaction_hash, e),
+                    }
+                }
+                Ok(_) => {}
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    error!("Light client missed {} network events", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    error!("Network stopped unexpectedly");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn start_node(
+    network: String,
+    data_dir: String,
+    port: u16,
+    bootstrap: bool,
+    bootnodes: Vec<String>,
+    pruning: String,
+    settlement_cycle: String,
+    failover: Option<network::FailoverConfig>,
+    ingest_limits: bce_pipeline::ingest_queue::IngestLimits,
+    schedule: bce_pipeline::scheduler::PipelineSchedule,
+    key_fetch: network::KeyFetchConfig,
+    ceremony_participants: Vec<String>,
+    role: bce_pipeline::NodeRole,
+    connection_limits: network::ConnectionLimits,
+    network_config: network::NetworkConfig,
+    notifications: Option<notifications::NotificationConfig>,
+    bridge: Option<bridge::BridgeConfig>,
+    retention: Option<bce_pipeline::retention::RetentionPolicy>,
+    tenancy: Option<network::TenancyConfig>,
+    bank_accounts: Option<settlement_execution::BankAccountDirectory>,
+) -> Result<()> {
+    info!("Starting SP CDR Reconciliation Blockchain Node");
+    info!("Network: {}, Data Directory: {}, Port: {}", network, data_dir, port);
+
+    let network_id = parse_network_id(&network);
+    let bootnodes = parse_bootnodes(&bootnodes)?;
+
+    let pruning_mode: storage::PruningMode = pruning.parse()?;
+    info!("Pruning mode: {:?}", pruning_mode);
+
+    let settlement_cycle: bce_pipeline::settlement_period::SettlementCycle = settlement_cycle.parse()?;
+    info!("Settlement cycle: {:?}", settlement_cycle);
+    info!("Node role: {:?}", role);
+    if let Some(retention) = &retention {
+        info!("CDR retention: {:?}", retention);
+    }
 
     // Create data directory
     std::fs::create_dir_all(&data_dir)?;
@@ -112,6 +633,22 @@ async fn start_node(network: String, data_dir: String, port: u16, bootstrap: boo
         auto_accept_threshold_cents: 500, // €5 auto-accept (demo)
         enable_triangular_netting: true,
         is_bootstrap: bootstrap,
+        bootnodes,
+        pruning_mode,
+        settlement_cycle,
+        failover,
+        ingest_limits,
+        schedule,
+        key_fetch,
+        ceremony_participants,
+        role,
+        connection_limits,
+        network: network_config,
+        approval_window: bce_pipeline::approvals::DEFAULT_APPROVAL_WINDOW,
+        bridge,
+        retention,
+        tenancy,
+        bank_accounts,
     };
 
     // Create network listen address
@@ -137,10 +674,17 @@ async fn start_node(network: String, data_dir: String, port: u16, bootstrap: boo
     info!("📊 BCE Pipeline ready - waiting for BCE records from operator billing systems");
     info!("📌 Submit BCE records via: POST http://localhost:9090/api/v1/bce/submit");
 
+    // Notify finance and NOC teams of settlement lifecycle events as the audit log records them
+    if let Some(config) = notifications {
+        let dispatcher = notifications::NotificationDispatcher::from_config(config)?;
+        tokio::spawn(dispatcher.run(pipeline.audit_log().subscribe()));
+    }
+
     info!("🚀 Starting integrated BCE processing pipeline...");
 
     // Start the complete pipeline
-    let pipeline_handle = tokio::spawn(async move {
+    let shutdown = pipeline.shutdown_handle();
+    let mut pipeline_handle = tokio::spawn(async move {
         if let Err(e) = pipeline.run().await {
             error!("CDR pipeline error: {:?}", e);
         }
@@ -154,16 +698,22 @@ async fn start_node(network: String, data_dir: String, port: u16, bootstrap: boo
         _ = tokio::signal::ctrl_c() => {
             info!("Shutdown signal received...");
         }
-        result = pipeline_handle => {
+        result = &mut pipeline_handle => {
             error!("Pipeline stopped unexpectedly: {:?}", result);
+            return Ok(());
         }
     }
 
+    // Let the pipeline flush in-flight batches and announce it is leaving
     info!("🛑 Shutting down CDR pipeline...");
+    shutdown.shutdown();
+    if tokio::time::timeout(std::time::Duration::from_secs(30), pipeline_handle).await.is_err() {
+        error!("Pipeline did not shut down within 30s, exiting anyway");
+    }
     Ok(())
 }
 
-async fn generate_validator_keys(output: String) -> Result<()> {
+async fn generate_validator_keys(output: String, format: OutputFormat) -> Result<()> {
     info!("Generating validator keys");
     
     std::fs::create_dir_all(&output)?;
@@ -187,7 +737,14 @@ async fn generate_validator_keys(output: String) -> Result<()> {
     info!("Validator keys generated successfully");
     info!("Signing key ID: {:?}", signing_keypair.key_id);
     info!("Keys saved to: {}", output);
-    
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "output": output,
+            "signing_key_id": format!("{:?}", signing_keypair.key_id),
+            "validator_address": validator_key.validator_address.to_string(),
+        }));
+    }
     println!("✅ Validator keys generated at: {}", output);
     println!("   Signing Key ID: {:?}", signing_keypair.key_id);
     println!("   Validator Address: {:?}", validator_key.validator_address);
@@ -195,7 +752,60 @@ async fn generate_validator_keys(output: String) -> Result<()> {
     Ok(())
 }
 
-async fn validate_cdr_file(file_path: String) -> Result<()> {
+async fn standby_key(data_dir: String, format: OutputFormat) -> Result<()> {
+    let escrow_key = crypto::load_or_generate_encryption_key(&std::path::Path::new(&data_dir).join("escrow.key"))?;
+    let node_key = network::load_or_generate_node_key(&std::path::Path::new(&data_dir).join("node.key"))?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "node_id": node_key.public().to_peer_id().to_string(),
+            "escrow_key": escrow_key.public_key().to_hex(),
+        }));
+    }
+
+    println!("🛟 Standby node: {}", node_key.public().to_peer_id());
+    println!("   🔑 Escrow key: {}", escrow_key.public_key().to_hex());
+    println!("   Seal the validator key with: sp-cdr-node escrow-key --standby-key {}", escrow_key.public_key().to_hex());
+    println!("   and start the primary with: --failover-peers {}", node_key.public().to_peer_id());
+
+    Ok(())
+}
+
+async fn escrow_key(data_dir: String, standby_key: String, output: String, format: OutputFormat) -> Result<()> {
+    info!("Sealing validator key from: {}", data_dir);
+
+    let bls_path = std::path::Path::new(&data_dir).join("validator.bls");
+    if !bls_path.exists() {
+        error!("No validator key found in: {}", data_dir);
+        std::process::exit(1);
+    }
+    let key_material = std::fs::read(&bls_path)?;
+    let standby_key = hex::decode(&standby_key)
+        .map_err(|e| primitives::BlockchainError::Serialization(format!("Invalid standby key: {}", e)))?;
+    let standby_key = crypto::EncryptionPublicKey::from_bytes(&standby_key)?;
+
+    // The primary's node id identifies the validator the standby takes over
+    let node_key = network::load_or_generate_node_key(&std::path::Path::new(&data_dir).join("node.key"))?;
+    let validator_id = node_key.public().to_peer_id();
+    let escrow = crypto::ValidatorKeyEscrow::seal(&validator_id.to_string(), &key_material, &standby_key)?;
+    let json = serde_json::to_vec_pretty(&escrow)
+        .map_err(|e| primitives::BlockchainError::Serialization(e.to_string()))?;
+    std::fs::write(&output, json)?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "output": output,
+            "validator_id": validator_id.to_string(),
+        }));
+    }
+    println!("✅ Validator key sealed to: {}", output);
+    println!("   🛡️  Validator: {}", validator_id);
+    println!("   Start the standby with: --standby-for {} --key-escrow <copied file>", validator_id);
+
+    Ok(())
+}
+
+async fn validate_cdr_file(file_path: String, format: OutputFormat) -> Result<()> {
     info!("Validating CDR file: {}", file_path);
     
     // Check if file exists
@@ -212,54 +822,549 @@ async fn validate_cdr_file(file_path: String) -> Result<()> {
     // 5. Validate charges
     
     info!("CDR validation completed for: {}", file_path);
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({"file": file_path, "valid": true}));
+    }
     println!("✅ CDR file validation completed: {}", file_path);
-    
+
+    Ok(())
+}
+
+async fn export_snapshot(data_dir: String, output: String, format: OutputFormat) -> Result<()> {
+    info!("Exporting snapshot from: {}", data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+
+    let chain_store = storage::MdbxChainStore::new(&blockchain_path)?;
+    let snapshot = storage::ChainSnapshot::export(&chain_store).await?;
+    let hash = snapshot.write_to(std::path::Path::new(&output))?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "output": output,
+            "head_block": snapshot.head.block_number(),
+            "state_entries": snapshot.state_entries.len(),
+            "snapshot_hash": hash.to_string(),
+        }));
+    }
+    println!("✅ Snapshot exported to: {}", output);
+    println!("   📏 Head block: #{}", snapshot.head.block_number());
+    println!("   🌱 State entries: {}", snapshot.state_entries.len());
+    println!("   🔐 Snapshot hash: {}", hash);
+
+    Ok(())
+}
+
+async fn import_snapshot(data_dir: String, file: String, format: OutputFormat) -> Result<()> {
+    info!("Importing snapshot {} into: {}", file, data_dir);
+
+    // Verifies the content hash and the head block's state root before touching the store
+    let (snapshot, hash) = storage::ChainSnapshot::read_from(std::path::Path::new(&file))?;
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    std::fs::create_dir_all(&blockchain_path)?;
+    let chain_store = storage::MdbxChainStore::new(&blockchain_path)?;
+    snapshot.import(&chain_store).await?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "data_dir": data_dir,
+            "head_block": snapshot.head.block_number(),
+            "snapshot_hash": hash.to_string(),
+        }));
+    }
+    println!("✅ Snapshot imported into: {}", data_dir);
+    println!("   📏 Head block: #{}", snapshot.head.block_number());
+    println!("   🔐 Snapshot hash: {}", hash);
+
+    Ok(())
+}
+
+async fn export_audit(data_dir: String, output: String, key: Option<String>, format: OutputFormat) -> Result<()> {
+    info!("Exporting audit log from: {}", data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+
+    // Opening verifies the hash chain, a tampered log is not exported
+    let chain_store = storage::MdbxChainStore::new(&blockchain_path)?;
+    let audit_log = storage::AuditLog::open(chain_store).await?;
+    let entries = audit_log.entries().await?;
+
+    let key_path = key.unwrap_or_else(|| format!("{}/node.key", data_dir));
+    let node_key = network::load_or_generate_node_key(std::path::Path::new(&key_path))?;
+    let jsonl = storage::audit_log::export_signed_jsonl(&entries, &node_key)?;
+    std::fs::write(&output, jsonl)?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "output": output,
+            "entries": entries.len(),
+            "chain_head": entries.last().map(|last| last.hash.to_string()),
+            "signed_by": node_key.public().to_peer_id().to_string(),
+        }));
+    }
+    println!("✅ Audit log exported to: {}", output);
+    println!("   📜 Entries: {}", entries.len());
+    if let Some(last) = entries.last() {
+        println!("   🔗 Chain head: {}", last.hash);
+    }
+    println!("   ✍️  Signed by: {}", node_key.public().to_peer_id());
+
+    Ok(())
+}
+
+async fn export_finality_proof(data_dir: String, transaction: String, output: String, format: OutputFormat) -> Result<()> {
+    info!("Exporting finality proof of {} from: {}", transaction, data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+    let transaction_hash = match parse_hash_id(&transaction) {
+        Ok(hash) => hash,
+        Err(message) => {
+            error!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let chain_store = Arc::new(storage::MdbxChainStore::new(&blockchain_path)?);
+    let blockchain = SPCDRBlockchain::open(chain_store, vec![]).await?;
+    let Some(proof) = blockchain.finality_proof(&transaction_hash).await? else {
+        return lookup_failed(format!("Transaction {} is not in a certified macro block yet", transaction_hash), format);
+    };
+    // Refuse to hand out a proof auditors would reject
+    let finalized = proof.verify(None)?;
+    proof.write_to(std::path::Path::new(&output))?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "output": output,
+            "finalized": finalized,
+        }));
+    }
+    println!("✅ Finality proof exported to: {}", output);
+    println!("   📝 Transaction: {}", finalized.transaction_hash);
+    println!("   🏁 Finalized in macro block #{} ({})", finalized.macro_block_number, finalized.macro_block_hash);
+    println!("   🗳️  Checkpoint: election block #{} ({})", finalized.checkpoint_block_number, finalized.checkpoint_hash);
+
+    Ok(())
+}
+
+fn verify_finality(file: String, checkpoint: Option<String>, format: OutputFormat) -> Result<()> {
+    let trusted_checkpoint = match checkpoint.as_deref().map(parse_hash_id).transpose() {
+        Ok(checkpoint) => checkpoint,
+        Err(message) => {
+            error!("{}", message);
+            std::process::exit(1);
+        }
+    };
+
+    let proof = blockchain::FinalityProof::read_from(std::path::Path::new(&file))?;
+    let finalized = match proof.verify(trusted_checkpoint.as_ref()) {
+        Ok(finalized) => finalized,
+        Err(e) => {
+            error!("❌ Finality proof {} does not verify: {}", file, e);
+            std::process::exit(1);
+        }
+    };
+
+    if format == OutputFormat::Json {
+        print_json(&finalized)?;
+    } else {
+        match finalized.trusted {
+            true => println!("✅ Transaction {} is final", finalized.transaction_hash),
+            false => println!("⚠️  UNTRUSTED: transaction {} is final on the chain the proof's own checkpoint starts", finalized.transaction_hash),
+        }
+        println!("   🏁 Macro block #{} ({})", finalized.macro_block_number, finalized.macro_block_hash);
+        if let blockchain::block::TransactionData::Settlement(settlement) = &proof.inclusion.transaction.data {
+            println!("   💰 {} {} from {} to {} for {}", settlement.amount, settlement.currency, settlement.debtor_network, settlement.creditor_network, settlement.period);
+        }
+        match finalized.trusted {
+            true => println!("   🗳️  From trusted election block #{} ({})", finalized.checkpoint_block_number, finalized.checkpoint_hash),
+            false => println!("   ⚠️  From election block #{} ({}), compare its hash with a published one and pass it as --checkpoint",
+                              finalized.checkpoint_block_number, finalized.checkpoint_hash),
+        }
+    }
+    // Anyone can build a chain of their own, so a proof from an untrusted checkpoint must not pass
+    // a script checking the exit status
+    if !finalized.trusted {
+        std::process::exit(2);
+    }
+
+    Ok(())
+}
+
+async fn fsck(data_dir: String, repair: bool, format: OutputFormat) -> Result<()> {
+    info!("Checking chain store in: {}", data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+
+    let chain_store = storage::MdbxChainStore::new(&blockchain_path)?;
+    let report = chain_store.fsck(repair).await?;
+    let remaining = report.remaining().count();
+
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else {
+        println!("\n🩺 CHAIN STORE CHECK");
+        println!("═══════════════════════════════════════════");
+        println!("   🧱 Blocks: {}", report.blocks_checked);
+        println!("   📝 Transactions: {}", report.transactions_checked);
+        println!("   🧾 Receipts: {}", report.receipts_checked);
+        match report.head_block {
+            Some(height) => println!("   📏 Head block: #{}", height),
+            None => println!("   📏 Head block: none"),
+        }
+        for issue in &report.issues {
+            let fixed = if report.repaired && issue.repairable { " (repaired)" } else if issue.repairable { " (repairable)" } else { "" };
+            println!("   ❌ {}: {}{}", issue.table, issue.description, fixed);
+        }
+        if report.repaired {
+            println!("   🔧 Index entries repaired: {}", report.entries_repaired);
+        }
+        if remaining == 0 {
+            println!("✅ Chain store is consistent");
+        } else {
+            println!("⚠️  {} issues remain{}", remaining,
+                     if !report.repaired && report.issues.iter().any(|issue| issue.repairable) { ", run with --repair to rebuild the indexes" } else { "" });
+        }
+    }
+
+    if remaining > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn settlement_report(data_dir: String, period: String, export_format: String, output: Option<String>, format: OutputFormat) -> Result<()> {
+    info!("Reporting settlement period {} from: {}", period, data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+
+    // A snapshot keeps the report consistent while a running node commits blocks
+    let chain_store = storage::MdbxChainStore::new(&blockchain_path)?.snapshot().await?;
+    let report = storage::SettlementReport::build(&chain_store, &period).await?;
+    let export = match export_format.as_str() {
+        "statement" => report.to_statement(),
+        "csv" => report.to_csv()?,
+        _ => {
+            error!("Unknown report format: {}. Use: statement, csv", export_format);
+            std::process::exit(1);
+        }
+    };
+
+    match &output {
+        Some(output) => std::fs::write(output, &export)?,
+        None if format == OutputFormat::Text => print!("{}", export),
+        None => {}
+    }
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "report": report,
+            "totals": report.totals(),
+            "output": output,
+        }));
+    }
+    if let Some(output) = &output {
+        let totals = report.totals();
+        println!("✅ Settlement report for {} exported to: {}", period, output);
+        println!("   🤝 Operator pairs: {}", report.pairs.len());
+        println!("   💶 Gross charges: €{:.2}", totals.gross_cents as f64 / 100.0);
+        println!("   💰 Netting savings: €{:.2}", totals.savings_cents as f64 / 100.0);
+    }
+
+    Ok(())
+}
+
+async fn netting_projection(data_dir: String, format: OutputFormat) -> Result<()> {
+    info!("Projecting netting of pending balances from: {}", data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+
+    // Pending batches as the node last wrote them through
+    let pipeline_store = bce_pipeline::recovery::PipelineStore::new(storage::MdbxChainStore::new(&blockchain_path)?);
+    let pending = pipeline_store.load().await?.pending_batches;
+    let projection = bce_pipeline::analytics::NettingProjection::of_batches(&pending)?;
+
+    if format == OutputFormat::Json {
+        return print_json(&projection);
+    }
+    if projection.gross_cents == 0 {
+        println!("📭 No pending balances to net");
+        return Ok(());
+    }
+    println!("\n🔮 NETTING PROJECTION");
+    println!("═══════════════════════════════════════════");
+    println!("   🤝 {} operators, {} bilateral balances from {} batches", projection.operators, projection.obligations, pending.len());
+    println!("   💶 Gross: €{:.2}", projection.gross_cents as f64 / 100.0);
+    for scenario in &projection.scenarios {
+        println!("   {:<13} {:>3} payments, €{:.2} settled, €{:.2} saved ({}%)",
+                 format!("{}:", scenario.scenario), scenario.settlements, scenario.settled_cents as f64 / 100.0,
+                 scenario.eliminated_cents as f64 / 100.0, scenario.savings_percentage);
+    }
+    Ok(())
+}
+
+async fn export_settlements(data_dir: String, period: String, network: String, export_format: String, output_dir: Option<String>, format: OutputFormat) -> Result<()> {
+    info!("Exporting settlement period {} from: {}", period, data_dir);
+
+    let blockchain_path = format!("{}/blockchain", data_dir);
+    if !std::path::Path::new(&blockchain_path).exists() {
+        error!("No blockchain data found in: {}", data_dir);
+        std::process::exit(1);
+    }
+    let export_format: storage::ExportFormat = export_format.parse()?;
+    let operator = parse_network_id(&network).to_string();
+    let output_dir = output_dir.unwrap_or_else(|| format!("{}/exports", data_dir));
+
+    let chain_store = storage::MdbxChainStore::new(&blockchain_path)?.snapshot().await?;
+    let exports = storage::CounterpartyExport::build(&chain_store, &period, &operator).await?;
+    let files = storage::settlement_export::write_exports(&exports, export_format, std::path::Path::new(&output_dir))?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "period": period,
+            "operator": operator,
+            "exports": exports,
+            "files": files,
+        }));
+    }
+    if files.is_empty() {
+        println!("📭 No finalized settlements or charges of {} for {}", operator, period);
+        return Ok(());
+    }
+    println!("✅ Settlement period {} exported for {}:", period, operator);
+    for (export, file) in exports.iter().zip(&files) {
+        println!("   📄 {} ({}, {} settlements, net €{:.2}): {}",
+                 export.counterparty, export.period, export.settlements.len(), export.settled_cents() as f64 / 100.0, file.display());
+    }
+    Ok(())
+}
+
+async fn simulate(config: simulation::SimulationConfig, format: OutputFormat) -> Result<()> {
+    let report = simulation::Simulation::new(config)?.run().await?;
+
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else {
+        println!("\n🧪 SIMULATION");
+        println!("═══════════════════════════════════════════");
+        println!("   🖥️  Nodes: {} over a {} graph (seed {})", report.nodes, report.graph, report.seed);
+        println!("   🧱 Blocks: {} in {}s simulated", report.height, report.simulated_seconds);
+        println!("   📞 CDR records: {}", report.cdr_records);
+        println!("   📨 Messages: {} ({} rejected)", report.messages, report.rejected_messages);
+        println!("   💰 Settlements: {} of {} final on every node", report.settlements_finalized, report.settlements_expected);
+        println!("   🧮 Netting: {} gross, {} settled, {}% saved", report.gross_total, report.net_total, report.netting_savings_percentage);
+        println!("   ⏱️  Consensus latency: {}ms average, {}ms max over {} finalizations",
+                 report.consensus_latency_avg_ms, report.consensus_latency_max_ms, report.finalizations);
+        println!("   🔗 Nodes agree on the chain: {}", if report.nodes_agree { "yes" } else { "no" });
+        for error in &report.settlement_errors {
+            println!("   ❌ {}", error);
+        }
+        if report.is_correct() {
+            println!("✅ Settlements match the simulated traffic");
+        }
+    }
+
+    if !report.is_correct() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn compile_contract(file: String, output: Option<String>, format: OutputFormat) -> Result<()> {
+    info!("Compiling settlement contract: {}", file);
+
+    let source = std::fs::read_to_string(&file)?;
+    let contract = smart_contracts::SettlementContractSource::parse(&source)?;
+    let code = contract.compile();
+
+    // Same encoding contract storage keeps deployed code in
+    let bytecode = bincode::serialize(&code)
+        .map_err(|e| primitives::BlockchainError::Serialization(e.to_string()))?;
+    if let Some(output) = &output {
+        std::fs::write(output, &bytecode)?;
+    }
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "contract": contract.name,
+            "instructions": code.iter().map(|instruction| format!("{:?}", instruction)).collect::<Vec<_>>(),
+            "code_hash": Blake2bHash::from_data(&bytecode).to_string(),
+            "output": output,
+            "bytes": bytecode.len(),
+        }));
+    }
+    println!("📜 Contract: {}", contract.name);
+    for (address, instruction) in code.iter().enumerate() {
+        println!("   {:>4}  {:?}", address, instruction);
+    }
+    println!("   🔐 Code hash: {}", Blake2bHash::from_data(&bytecode));
+    if let Some(output) = output {
+        println!("✅ Bytecode written to: {} ({} bytes)", output, bytecode.len());
+    }
+
+    Ok(())
+}
+
+async fn import_bce(file: String, file_format: String, chunk_size: usize, data_dir: String, network: String, format: OutputFormat) -> Result<()> {
+    info!("Importing BCE file {} into: {}", file, data_dir);
+
+    let file_format: bce_pipeline::import::BceFileFormat = file_format.parse()?;
+    if chunk_size == 0 {
+        return Err(primitives::BlockchainError::InvalidOperation("Chunk size must be at least 1".to_string()));
+    }
+    let started = std::time::Instant::now();
+    let parsed = bce_pipeline::import::parse_bce_file(&std::fs::read(&file)?, file_format)?;
+    let total = parsed.records.len();
+    if format == OutputFormat::Text {
+        println!("📄 Parsed {} records from {} ({} rejected)", total, file, parsed.rejected.len());
+        for (entry, reason) in &parsed.rejected {
+            println!("   ❌ Entry {}: {}", entry, reason);
+        }
+    }
+
+    let pipeline_config = bce_pipeline::PipelineConfig {
+        keys_dir: std::path::PathBuf::from(format!("{}/zkp_keys", data_dir)),
+        batch_size: 1000,
+        settlement_threshold_cents: 100,
+        auto_accept_threshold_cents: 500,
+        enable_triangular_netting: true,
+        is_bootstrap: false,
+        bootnodes: vec![],
+        pruning_mode: storage::PruningMode::Archive,
+        settlement_cycle: bce_pipeline::settlement_period::SettlementCycle::Days(15),
+        failover: None,
+        ingest_limits: bce_pipeline::ingest_queue::IngestLimits::default(),
+        schedule: bce_pipeline::scheduler::PipelineSchedule::default(),
+        key_fetch: network::KeyFetchConfig::default(),
+        ceremony_participants: vec![],
+        role: bce_pipeline::NodeRole::Full,
+        connection_limits: network::ConnectionLimits::default(),
+        network: network::NetworkConfig::default(),
+        approval_window: bce_pipeline::approvals::DEFAULT_APPROVAL_WINDOW,
+        bridge: None,
+        retention: None,
+        tenancy: None,
+        bank_accounts: None,
+    };
+    let listen_addr = "/ip4/127.0.0.1/tcp/0".parse()
+        .map_err(|e| primitives::BlockchainError::NetworkError(format!("Invalid address: {}", e)))?;
+    let mut pipeline = bce_pipeline::BCEPipeline::new(parse_network_id(&network), listen_addr, pipeline_config).await?;
+
+    let (mut imported, mut failed, mut done) = (0, 0, 0);
+    for chunk in parsed.records.chunks(chunk_size) {
+        match pipeline.process_bce_records_batch(chunk.to_vec()).await {
+            Ok(processed) => {
+                imported += processed;
+                failed += chunk.len() - processed;
+            }
+            Err(e) => {
+                error!("Failed to import records {}..{}: {}", done + 1, done + chunk.len(), e);
+                failed += chunk.len();
+            }
+        }
+        done += chunk.len();
+        if format == OutputFormat::Text {
+            println!("📦 {}/{} records ({:.0}%)", done, total, done as f64 * 100.0 / total as f64);
+        }
+    }
+
+    // Persists the imported batches for the node to settle when it starts
+    pipeline.shutdown().await?;
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "file": file,
+            "imported": imported,
+            "rejected_by_pipeline": failed,
+            "invalid": parsed.rejected.iter()
+                .map(|(entry, reason)| serde_json::json!({"entry": entry, "reason": reason}))
+                .collect::<Vec<_>>(),
+            "seconds": started.elapsed().as_secs_f64(),
+        }));
+    }
+
+    println!("✅ BCE import completed: {}", file);
+    println!("   📥 Imported: {}", imported);
+    println!("   ⚠️  Rejected by pipeline: {}", failed);
+    println!("   ❌ Invalid in file: {}", parsed.rejected.len());
+    println!("   ⏱️  Took: {:.1}s", started.elapsed().as_secs_f64());
+
     Ok(())
 }
 
-async fn inspect_blockchain(data_dir: String, target: String, id: Option<String>, limit: usize) -> Result<()> {
+async fn inspect_blockchain(data_dir: String, target: String, id: Option<String>, limit: usize, query: storage::ChainQuery, format: OutputFormat) -> Result<()> {
     info!("Inspecting blockchain data in: {}", data_dir);
-    println!("🔍 SP CDR Blockchain Inspector");
-    println!("📁 Data directory: {}", data_dir);
-    println!("🎯 Target: {}", target);
+    if format == OutputFormat::Text {
+        println!("🔍 SP CDR Blockchain Inspector");
+        println!("📁 Data directory: {}", data_dir);
+        println!("🎯 Target: {}", target);
+    }
 
     // Check if data directory exists
     let data_path = std::path::Path::new(&data_dir);
     if !data_path.exists() {
-        println!("❌ Data directory not found: {}", data_dir);
-        println!("💡 Make sure the validator node has been running to generate blockchain data");
+        error!("❌ Data directory not found: {}", data_dir);
+        error!("💡 Make sure the validator node has been running to generate blockchain data");
         std::process::exit(1);
     }
 
     // Initialize chain store to read blockchain data (try MDBX first, fallback to simple)
     let blockchain_path = format!("{}/blockchain", data_dir);
     let chain_store: Arc<dyn storage::ChainStore> = if std::path::Path::new(&blockchain_path).exists() {
-        println!("🔍 Using persistent MDBX storage");
-        Arc::new(storage::MdbxChainStore::new(&blockchain_path)?)
+        info!("🔍 Using persistent MDBX storage (read snapshot)");
+        Arc::new(storage::MdbxChainStore::new(&blockchain_path)?.snapshot().await?)
     } else {
-        println!("🔍 Using in-memory storage (no persistent data found)");
+        info!("🔍 Using in-memory storage (no persistent data found)");
         Arc::new(storage::SimpleChainStore::new())
     };
 
     match target.as_str() {
         "blocks" => {
-            inspect_blocks(&chain_store, id, limit).await?;
+            inspect_blocks(&chain_store, id, limit, format).await?;
         }
         "transactions" => {
-            inspect_transactions(&chain_store, id, limit).await?;
+            inspect_transactions(&chain_store, id, limit, format).await?;
+        }
+        "receipts" => {
+            inspect_receipts(&chain_store, id, limit, format).await?;
         }
         "cdrs" => {
-            inspect_cdr_data(&data_dir, limit).await?;
+            inspect_cdr_data(&chain_store, &query, limit, format).await?;
         }
         "settlements" => {
-            inspect_settlements(&data_dir, limit).await?;
+            inspect_settlements(&chain_store, &query, limit, format).await?;
         }
         "stats" => {
-            inspect_blockchain_stats(&data_dir).await?;
+            inspect_blockchain_stats(&data_dir, format).await?;
         }
         _ => {
-            println!("❌ Unknown target: {}", target);
-            println!("Valid targets: blocks, transactions, cdrs, settlements, stats");
+            error!("❌ Unknown target: {}", target);
+            error!("Valid targets: blocks, transactions, receipts, cdrs, settlements, stats");
             std::process::exit(1);
         }
     }
@@ -267,179 +1372,337 @@ async fn inspect_blockchain(data_dir: String, target: String, id: Option<String>
     Ok(())
 }
 
-async fn inspect_blocks(chain_store: &Arc<dyn storage::ChainStore>, id: Option<String>, limit: usize) -> Result<()> {
-    println!("\n📦 BLOCKCHAIN BLOCKS");
-    println!("═══════════════════════════════════════════");
+/// Parse a 64 character hex hash given with --id
+fn parse_hash_id(id: &str) -> std::result::Result<Blake2bHash, String> {
+    match hex::decode(id) {
+        Ok(bytes) if bytes.len() == 32 => {
+            let mut arr = [0u8; 32];
+            arr.copy_from_slice(&bytes);
+            Ok(Blake2bHash::from_bytes(arr))
+        }
+        Ok(_) => Err(format!("Invalid hash length: {}. Expected 64 hex characters", id)),
+        Err(_) => Err(format!("Invalid ID: {}. Expected 64 hex characters", id)),
+    }
+}
 
+/// Report a failed lookup: a line of text, or an error exit keeping stdout clean for JSON
+fn lookup_failed(message: String, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Text => {
+            println!("❌ {}", message);
+            Ok(())
+        }
+        OutputFormat::Json => Err(primitives::BlockchainError::NotFound(message)),
+    }
+}
+
+async fn inspect_blocks(chain_store: &Arc<dyn storage::ChainStore>, id: Option<String>, limit: usize, format: OutputFormat) -> Result<()> {
     if let Some(block_id) = id {
-        // Show specific block
-        if let Ok(block_num) = block_id.parse::<u32>() {
-            match chain_store.get_block_at(block_num).await? {
-                Some(block) => {
-                    display_block_details(&block);
-                }
-                None => {
-                    println!("❌ Block #{} not found", block_num);
-                }
+        // Show specific block, by number or hash
+        let block = match block_id.parse::<u32>() {
+            Ok(block_num) => chain_store.get_block_at(block_num).await?
+                .ok_or_else(|| format!("Block #{} not found", block_num)),
+            Err(_) => match parse_hash_id(&block_id) {
+                Ok(hash) => chain_store.get_block(&hash).await?
+                    .ok_or_else(|| format!("Block with hash {} not found", block_id)),
+                Err(_) => Err(format!("Invalid block ID: {}. Use block number or hash", block_id)),
+            },
+        };
+        return match block {
+            Ok(block) if format == OutputFormat::Json => print_json(&block),
+            Ok(block) => {
+                println!("\n📦 BLOCKCHAIN BLOCKS");
+                println!("═══════════════════════════════════════════");
+                display_block_details(&block);
+                Ok(())
             }
-        } else {
-            // Try to parse as hex hash
-            if let Ok(hash_bytes) = hex::decode(&block_id) {
-                if hash_bytes.len() == 32 {
-                    let mut arr = [0u8; 32];
-                    arr.copy_from_slice(&hash_bytes);
-                    let hash = Blake2bHash::from_bytes(arr);
-                    match chain_store.get_block(&hash).await? {
-                        Some(block) => {
-                            display_block_details(&block);
-                        }
-                        None => {
-                            println!("❌ Block with hash {} not found", block_id);
-                        }
-                    }
-                } else {
-                    println!("❌ Invalid hash length: {}. Expected 64 hex characters", block_id);
-                }
-            } else {
-                println!("❌ Invalid block ID: {}. Use block number or hash", block_id);
+            Err(message) => lookup_failed(message, format),
+        };
+    }
+
+    // Walk back from the head
+    let mut blocks = Vec::new();
+    // An empty store has no head
+    let head_block = match chain_store.get_head_hash().await {
+        Ok(head_hash) => chain_store.get_block(&head_hash).await?,
+        Err(_) => None,
+    };
+    if let Some(head_block) = head_block {
+        let head_number = head_block.block_number();
+        blocks.push(head_block);
+        for block_number in (1..head_number).rev().take(limit.saturating_sub(1)) {
+            if let Some(block) = chain_store.get_block_at(block_number).await? {
+                blocks.push(block);
             }
         }
-    } else {
-        // Show recent blocks
-        println!("📊 Recent {} blocks:", limit);
-        println!("(Note: This demo shows simulated blockchain data structure)");
+    }
 
-        // Since we're using in-memory storage for the demo, show structure
-        let head_hash = chain_store.get_head_hash().await?;
-        println!("\n🏷️  Current head: {:?}", head_hash);
+    if format == OutputFormat::Json {
+        return print_json(&blocks.iter().map(|block| serde_json::json!({
+            "block_number": block.block_number(),
+            "hash": block.hash().to_string(),
+            "type": match block { Block::Micro(_) => "micro", Block::Macro(_) => "macro" },
+            "timestamp": block.timestamp(),
+            "transactions": block.transactions().len(),
+        })).collect::<Vec<_>>());
+    }
 
-        if head_hash != Blake2bHash::zero() {
-            if let Some(head_block) = chain_store.get_block(&head_hash).await? {
-                display_block_summary(&head_block, 0);
-            }
-        } else {
-            println!("ℹ️  No blocks found. The blockchain is empty or still initializing.");
-            println!("💡 BCE processing creates blocks with settlement transactions.");
+    println!("\n📦 BLOCKCHAIN BLOCKS");
+    println!("═══════════════════════════════════════════");
+    println!("🏷️  Current head: {:?}", head_hash);
+    if blocks.is_empty() {
+        println!("ℹ️  No blocks found. The blockchain is empty or still initializing.");
+        println!("💡 BCE processing creates blocks with settlement transactions.");
+    } else {
+        println!("📊 Recent {} blocks:", blocks.len());
+        for (index, block) in blocks.iter().enumerate() {
+            display_block_summary(block, index);
         }
     }
 
     Ok(())
 }
 
-async fn inspect_transactions(chain_store: &Arc<dyn storage::ChainStore>, _id: Option<String>, _limit: usize) -> Result<()> {
-    println!("\n💳 BLOCKCHAIN TRANSACTIONS");
-    println!("═══════════════════════════════════════════");
+async fn inspect_transactions(chain_store: &Arc<dyn storage::ChainStore>, id: Option<String>, _limit: usize, format: OutputFormat) -> Result<()> {
+    if let Some(tx_id) = id {
+        // Look up a specific transaction through the transaction index
+        let hash = match parse_hash_id(&tx_id) {
+            Ok(hash) => hash,
+            Err(message) => return lookup_failed(message, format),
+        };
+        let Some((tx, location)) = chain_store.get_transaction(&hash).await? else {
+            return lookup_failed(format!("Transaction with hash {} not found", tx_id), format);
+        };
+        let receipt = chain_store.get_receipt(&hash).await?;
 
-    let head_hash = chain_store.get_head_hash().await?;
-    if head_hash != Blake2bHash::zero() {
-        if let Some(head_block) = chain_store.get_block(&head_hash).await? {
-            println!("📊 Transactions in head block:");
-            let transactions = head_block.transactions();
-            for (i, tx) in transactions.iter().enumerate() {
-                println!("\n🔸 Transaction #{}", i + 1);
-                display_transaction_details(tx);
-            }
+        if format == OutputFormat::Json {
+            return print_json(&serde_json::json!({
+                "hash": hash.to_string(),
+                "block_hash": location.block_hash.to_string(),
+                "index": location.index,
+                "transaction": tx,
+                "receipt": receipt,
+            }));
         }
-    } else {
+        println!("\n💳 BLOCKCHAIN TRANSACTIONS");
+        println!("═══════════════════════════════════════════");
+        println!("📦 Block: {}", location.block_hash);
+        println!("📍 Position: #{}", location.index + 1);
+        display_transaction_details(&tx);
+        if let Some(receipt) = receipt {
+            display_receipt(&receipt);
+        }
+        return Ok(());
+    }
+
+    let head_block = match chain_store.get_head_hash().await {
+        Ok(head_hash) => chain_store.get_block(&head_hash).await?,
+        Err(_) => None,
+    };
+    let transactions = match head_block {
+        Some(head_block) => head_block.transactions().to_vec(),
+        None => vec![],
+    };
+
+    if format == OutputFormat::Json {
+        return print_json(&transactions.iter().map(|tx| serde_json::json!({
+            "hash": tx.hash().to_string(),
+            "transaction": tx,
+        })).collect::<Vec<_>>());
+    }
+
+    println!("\n💳 BLOCKCHAIN TRANSACTIONS");
+    println!("═══════════════════════════════════════════");
+    if transactions.is_empty() {
         println!("ℹ️  No transactions found. Blockchain is empty or initializing.");
+    } else {
+        println!("📊 Transactions in head block:");
+        for (i, tx) in transactions.iter().enumerate() {
+            println!("\n🔸 Transaction #{}", i + 1);
+            display_transaction_details(tx);
+        }
     }
 
     Ok(())
 }
 
-async fn inspect_cdr_data(data_dir: &str, _limit: usize) -> Result<()> {
-    println!("\n📞 CDR RECORDS & PROCESSING");
-    println!("═══════════════════════════════════════════");
-
-    // Check for ceremony transcript
-    let zkp_keys_dir = format!("{}/zkp_keys", data_dir);
-    let transcript_path = format!("{}/ceremony_transcript.json", zkp_keys_dir);
-
-    if std::path::Path::new(&transcript_path).exists() {
-        println!("🔐 Trusted Setup Ceremony Status:");
-        if let Ok(content) = tokio::fs::read_to_string(&transcript_path).await {
-            if let Ok(transcript) = serde_json::from_str::<serde_json::Value>(&content) {
-                println!("   ✅ Ceremony ID: {}", transcript["ceremony_id"].as_str().unwrap_or("unknown"));
-                println!("   👥 Participants: {:?}", transcript["participants"].as_array().unwrap_or(&vec![]));
-                println!("   🔑 Circuits: {}", transcript["contributions"].as_array().map(|a| a.len()).unwrap_or(0));
-
-                // Check for keys
-                let cdr_privacy_pk = format!("{}/cdr_privacy.pk", zkp_keys_dir);
-                let settlement_pk = format!("{}/settlement_calculation.pk", zkp_keys_dir);
-
-                if std::path::Path::new(&cdr_privacy_pk).exists() {
-                    let metadata = std::fs::metadata(&cdr_privacy_pk).unwrap();
-                    println!("   📁 CDR Privacy Keys: {} bytes", metadata.len());
-                }
+async fn inspect_receipts(chain_store: &Arc<dyn storage::ChainStore>, id: Option<String>, limit: usize, format: OutputFormat) -> Result<()> {
+    let hash = match id.as_deref().and_then(Blake2bHash::from_hex) {
+        Some(hash) => hash,
+        None => {
+            return lookup_failed("Pass a transaction hash or contract address with --id (64 hex characters)".to_string(), format);
+        }
+    };
 
-                if std::path::Path::new(&settlement_pk).exists() {
-                    let metadata = std::fs::metadata(&settlement_pk).unwrap();
-                    println!("   📁 Settlement Keys: {} bytes", metadata.len());
-                }
-            }
+    // A transaction hash names one receipt, a contract address all receipts of its calls
+    if let Some(receipt) = chain_store.get_receipt(&hash).await? {
+        if format == OutputFormat::Json {
+            return print_json(&receipt);
         }
-    } else {
-        println!("⚠️  No ZK setup found at: {}", transcript_path);
+        println!("\n🧾 CONTRACT RECEIPTS");
+        println!("═══════════════════════════════════════════");
+        display_receipt(&receipt);
+        return Ok(());
     }
 
-    println!("\n💡 BCE processing creates ZK proofs for privacy-preserving reconciliation");
-    println!("💡 Settlement calculations are verified using these ZK proofs");
+    let receipts = chain_store.get_receipts_by_contract(&hash).await?;
+    if receipts.is_empty() {
+        return lookup_failed(format!("No receipts found for {}", hash), format);
+    }
+
+    let latest: Vec<_> = receipts.iter().rev().take(limit).collect();
+    if format == OutputFormat::Json {
+        return print_json(&latest);
+    }
+    println!("\n🧾 CONTRACT RECEIPTS");
+    println!("═══════════════════════════════════════════");
+    println!("📊 {} receipts for contract {}, showing the last {}:", receipts.len(), hash, latest.len());
+    for receipt in latest {
+        display_receipt(receipt);
+    }
 
     Ok(())
 }
 
-async fn inspect_settlements(data_dir: &str, _limit: usize) -> Result<()> {
-    println!("\n💰 SETTLEMENT PROCESSING");
+fn display_receipt(receipt: &smart_contracts::ContractReceipt) {
+    println!("\n🧾 Receipt for {}", receipt.transaction_hash);
+    println!("   📜 Contract: {}", receipt.contract_address);
+    println!("   📦 Block: #{} position #{}", receipt.block_number, receipt.transaction_index + 1);
+    println!("   {} Status: {}", if receipt.success { "✅" } else { "❌" }, if receipt.success { "success" } else { "failed, state reverted" });
+    println!("   ⛽ Gas used: {}", receipt.gas_used);
+    if let Some(value) = receipt.return_value {
+        println!("   ↩️  Return value: {}", value);
+    }
+    if let Some(error) = &receipt.error {
+        println!("   ⚠️  Error: {}", error);
+    }
+    for log in &receipt.logs {
+        println!("   📝 {}", log);
+    }
+}
+
+async fn inspect_cdr_data(chain_store: &Arc<dyn storage::ChainStore>, query: &storage::ChainQuery, limit: usize, format: OutputFormat) -> Result<()> {
+    let activity = storage::ChainActivity::scan(chain_store.as_ref()).await?;
+    let batches = query.batches(&activity, limit)?;
+    if format == OutputFormat::Json {
+        return print_json(&batches);
+    }
+
+    println!("\n📞 BCE BATCH COMMITMENTS");
     println!("═══════════════════════════════════════════");
+    if batches.is_empty() {
+        println!("ℹ️  No committed batches match. {} batches on chain.", activity.batches.len());
+        return Ok(());
+    }
 
-    println!("📊 Settlement processing happens automatically when:");
-    println!("   • BCE records are processed by validators");
-    println!("   • Settlement amounts exceed threshold (€100)");
-    println!("   • ZK proofs verify CDR calculations");
-    println!("   • Multi-party consensus is reached");
+    println!("{:>8}  {:<64}  {:<16}  {:<16}  {:>7}  {:<23}  {}", "Block", "Batch", "Home", "Visited", "Records", "Period", "Status");
+    for batch in &batches {
+        println!("{:>8}  {:<64}  {:<16}  {:<16}  {:>7}  {:<23}  {:?}",
+                 batch.block_number,
+                 batch.batch_id,
+                 batch.home_network,
+                 batch.visited_network,
+                 batch.record_count,
+                 batch.period.as_deref().unwrap_or("open"),
+                 batch.status);
+    }
+    println!("📊 {} of {} batches on chain", batches.len(), activity.batches.len());
 
-    println!("\n🔄 Current processing status:");
-    println!("   📁 Data directory: {}", data_dir);
+    Ok(())
+}
+
+async fn inspect_settlements(chain_store: &Arc<dyn storage::ChainStore>, query: &storage::ChainQuery, limit: usize, format: OutputFormat) -> Result<()> {
+    let activity = storage::ChainActivity::scan(chain_store.as_ref()).await?;
+    let settlements = query.settlements(&activity, limit)?;
+    if format == OutputFormat::Json {
+        return print_json(&settlements);
+    }
 
-    // In a real implementation, this would read actual settlement data
-    println!("   ⚡ Processing pipeline: Active");
-    println!("   🌐 P2P network: Connected to peers");
+    println!("\n💰 SETTLEMENT TRANSACTIONS");
+    println!("═══════════════════════════════════════════");
+    if settlements.is_empty() {
+        println!("ℹ️  No settlements match. {} settlements on chain.", activity.settlements.len());
+        return Ok(());
+    }
+
+    println!("{:>8}  {:<64}  {:<40}  {:<40}  {:>12}  {:<8}  {:<23}  {}", "Block", "Transaction", "Creditor", "Debtor", "Amount", "Currency", "Period", "Status");
+    for settlement in &settlements {
+        println!("{:>8}  {:<64}  {:<40}  {:<40}  {:>12}  {:<8}  {:<23}  {:?}",
+                 settlement.block_number,
+                 settlement.transaction_hash,
+                 settlement.creditor,
+                 settlement.debtor,
+                 settlement.amount,
+                 settlement.currency,
+                 settlement.period,
+                 settlement.status);
+    }
+    println!("📊 {} of {} settlements on chain", settlements.len(), activity.settlements.len());
 
     Ok(())
 }
 
-async fn inspect_blockchain_stats(data_dir: &str) -> Result<()> {
+/// Print a value as pretty JSON on stdout
+fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| primitives::BlockchainError::Serialization(e.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+async fn inspect_blockchain_stats(data_dir: &str, format: OutputFormat) -> Result<()> {
+    let components = [
+        "ZK Proof System (Groth16 with BN254)",
+        "P2P Networking (libp2p)",
+        "CDR Privacy Circuits",
+        "Settlement Calculation Circuits",
+        "Multi-party Trusted Setup",
+    ];
+
+    // Check data directory contents
+    let mut contents = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(data_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let size = if path.is_dir() { None } else { entry.metadata().ok().map(|metadata| metadata.len()) };
+            contents.push((name, path.is_dir(), size));
+        }
+    }
+
+    if format == OutputFormat::Json {
+        return print_json(&serde_json::json!({
+            "data_dir": data_dir,
+            "contents": contents.iter().map(|(name, is_dir, size)| serde_json::json!({
+                "name": name,
+                "directory": is_dir,
+                "bytes": size,
+            })).collect::<Vec<_>>(),
+            "components": components,
+        }));
+    }
+
     println!("\n📈 BLOCKCHAIN STATISTICS");
     println!("═══════════════════════════════════════════");
 
     println!("🏢 SP CDR Reconciliation Blockchain");
     println!("📁 Data directory: {}", data_dir);
 
-    // Check data directory contents
-    if let Ok(entries) = std::fs::read_dir(data_dir) {
+    if !contents.is_empty() {
         println!("\n📂 Data directory contents:");
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                let name = path.file_name().unwrap().to_string_lossy();
-
-                if path.is_dir() {
-                    println!("   📁 {}/", name);
-                } else {
-                    if let Ok(metadata) = entry.metadata() {
-                        println!("   📄 {} ({} bytes)", name, metadata.len());
-                    }
-                }
+        for (name, is_dir, size) in &contents {
+            match (is_dir, size) {
+                (true, _) => println!("   📁 {}/", name),
+                (false, Some(size)) => println!("   📄 {} ({} bytes)", name, size),
+                (false, None) => {}
             }
         }
     }
 
     println!("\n🔧 System Components:");
-    println!("   ✅ ZK Proof System (Groth16 with BN254)");
-    println!("   ✅ P2P Networking (libp2p)");
-    println!("   ✅ CDR Privacy Circuits");
-    println!("   ✅ Settlement Calculation Circuits");
-    println!("   ✅ Multi-party Trusted Setup");
+    for component in components {
+        println!("   ✅ {}", component);
+    }
 
     Ok(())
 }
@@ -491,10 +1754,9 @@ fn display_block_summary(block: &Block, index: usize) {
 }
 
 fn display_transaction_details(tx: &blockchain::block::Transaction) {
-    // Transaction hash needs to be computed
-    let tx_hash = Blake2bHash::from_data(&format!("{:?}", tx).as_bytes());
-    println!("     🆔 Hash: {}", tx_hash);
+    println!("     🆔 Hash: {}", tx.hash());
     println!("     💰 Fee: {} units", tx.fee);
+    println!("     🔢 Nonce: {}", tx.nonce);
     println!("     🏠 Sender: {}", tx.sender);
     println!("     🎯 Recipient: {}", tx.recipient);
     println!("     💵 Value: {} units", tx.value);
@@ -514,6 +1776,13 @@ fn display_transaction_details(tx: &blockchain::block::Transaction) {
             println!("     👤 Debtor Network: {}", settlement_tx.debtor_network);
             println!("     💵 Amount: {} {}", settlement_tx.amount, settlement_tx.currency);
             println!("     📅 Period: {}", settlement_tx.period);
+            let breakdown = &settlement_tx.breakdown;
+            println!("     📞 Voice: {} min, {} cents", breakdown.voice_minutes, breakdown.voice_cents);
+            println!("     📶 Data: {} MB, {} cents", breakdown.data_mb, breakdown.data_cents);
+            println!("     💬 SMS: {} messages, {} cents", breakdown.sms_count, breakdown.sms_cents);
+            if breakdown.other_cents > 0 {
+                println!("     📦 Other: {} cents", breakdown.other_cents);
+            }
         }
         blockchain::block::TransactionData::ValidatorUpdate(validator_tx) => {
             println!("     👤 Type: Validator Update");
@@ -521,6 +1790,157 @@ fn display_transaction_details(tx: &blockchain::block::Transaction) {
             println!("     🏷️  Validator: {}", validator_tx.validator_address);
             println!("     💰 Stake: {} units", validator_tx.stake);
         }
+        blockchain::block::TransactionData::ProofAggregate(aggregate) => {
+            println!("     🔐 Type: Proof Aggregate");
+            println!("     ⚙️  Circuit: {}", aggregate.circuit_id);
+            println!("     📦 Proofs: {}", aggregate.len());
+        }
+        blockchain::block::TransactionData::ContractUpgrade(upgrade) => {
+            println!("     ⬆️  Type: Contract Upgrade");
+            println!("     📜 Contract: {}", upgrade.contract_address);
+            println!("     🔢 Version: {}", upgrade.version);
+            println!("     🔐 Code Hash: {}", upgrade.code_hash());
+            println!("     ✍️  Signed by: {}", upgrade.signatures.iter().map(|(operator, _)| operator.as_str()).collect::<Vec<_>>().join(", "));
+        }
+        blockchain::block::TransactionData::PeriodClose(close) => {
+            println!("     🗓️  Type: Settlement Period Close");
+            println!("     📅 Period: {}", close.period);
+            println!("     🔒 Frozen Batches: {}", close.frozen_batches.len());
+            for balance in &close.balances {
+                println!("     💶 {} ↔ {}: {} cents", balance.home_network, balance.visited_network, balance.amount_cents);
+            }
+        }
+        blockchain::block::TransactionData::FraudFlag(flag) => {
+            println!("     🚨 Type: Fraud Flag");
+            println!("     📦 Batch: {}", flag.batch_id);
+            println!("     🏠 Home Network: {}", flag.home_network);
+            println!("     🌍 Visited Network: {}", flag.visited_network);
+            println!("     📊 Score: {}", flag.score);
+            println!("     🔒 Action: {}", if flag.quarantine { "quarantine" } else { "release" });
+            for reason in &flag.reasons {
+                println!("     ⚠️  {}", reason);
+            }
+        }
+        blockchain::block::TransactionData::BatchCommitment(commitment) => {
+            println!("     🌳 Type: Batch Commitment");
+            println!("     📦 Batch: {}", commitment.batch_id);
+            println!("     🏠 Home Network: {}", commitment.home_network);
+            println!("     🌍 Visited Network: {}", commitment.visited_network);
+            println!("     📋 Records: {}", commitment.record_count);
+            println!("     🔏 Merkle Root: {}", commitment.merkle_root);
+        }
+        blockchain::block::TransactionData::RateTable(signed) => {
+            println!("     📑 Type: Rate Table");
+            println!("     🏢 Operator: {}", signed.table.operator);
+            println!("     🤝 Partner: {}", signed.table.partner);
+            println!("     📅 Effective From: {}", signed.table.effective_from);
+            println!("     💶 Rates: {} ({})", signed.table.rates.len(), signed.table.currency);
+        }
+        blockchain::block::TransactionData::OperatorRegistration(registration) => {
+            println!("     🏛️  Type: Operator Registration");
+            println!("     🏢 Operator: {} ({})", registration.record.display_name, registration.record.network_id());
+            println!("     📶 PLMN Codes: {}", registration.record.plmn_codes.join(", "));
+            println!("     🔢 Version: {}", registration.record.version);
+            println!("     ✍️  Approvals: {}", registration.approvals.len());
+        }
+        blockchain::block::TransactionData::RewardPayout(payout) => {
+            println!("     🏆 Type: Reward Payout");
+            println!("     🗳️  Election Block: {}", payout.election_block);
+            println!("     💰 Pool: {}", payout.pool);
+            for reward in &payout.payouts {
+                println!("     🏷️  {}: {} for {} macro blocks", reward.validator, reward.amount, reward.participation);
+            }
+        }
+        blockchain::block::TransactionData::Governance(governance) => {
+            println!("     🗳️  Type: Governance");
+            println!("     🏷️  Validator: {}", governance.validator);
+            match &governance.action {
+                blockchain::GovernanceAction::Propose { parameter, value, activation_height } => {
+                    println!("     📜 Proposal: {:?} = {} from block {}", parameter, value, activation_height);
+                }
+                blockchain::GovernanceAction::Vote { proposal, approve } => {
+                    println!("     ✋ Vote: {} on {}", if *approve { "for" } else { "against" }, proposal);
+                }
+            }
+        }
+        blockchain::block::TransactionData::NetworkJoin(join) => {
+            println!("     🙋 Type: Network Join Application");
+            println!("     🏢 Operator: {} ({})", join.record.display_name, join.record.network_id());
+            println!("     📶 PLMN Codes: {}", join.record.plmn_codes.join(", "));
+            println!("     🆔 Application: {}", join.application_id());
+        }
+        blockchain::block::TransactionData::BridgedSettlement(bridged) => {
+            println!("     🌉 Type: Bridged Settlement");
+            println!("     🌐 Source Consortium: {}", bridged.source_network);
+            println!("     🆔 Source Transaction: {}", bridged.source_transaction());
+            println!("     🧱 Finalized In: macro block {}", bridged.proof.inclusion.macro_block_number);
+            if let Ok(settlement) = bridged.settlement() {
+                println!("     👤 Creditor Network: {}", settlement.creditor_network);
+                println!("     👤 Debtor Network: {}", settlement.debtor_network);
+                println!("     💵 Amount: {} {}", settlement.amount, settlement.currency);
+                println!("     📅 Period: {}", settlement.period);
+            }
+        }
+        blockchain::block::TransactionData::PurgedCDRRecord(purged) => {
+            println!("     🗑️  Type: CDR Transaction (payload purged)");
+            println!("     🏠 Home Network: {}", purged.home_network);
+            println!("     🌍 Visited Network: {}", purged.visited_network);
+            println!("     📋 Record Type: {:?}", purged.record_type);
+            println!("     🔐 Purged: {} bytes at {}", purged.purged_bytes, purged.purged_at);
+        }
+        blockchain::block::TransactionData::StateResurrection(resurrection) => {
+            println!("     🗄️  Type: Contract State Resurrection");
+            println!("     📜 Contract: {}", resurrection.slot.contract);
+            println!("     🔑 Slot: {}", resurrection.slot.key);
+            println!("     ⏳ Expired At: block {} (last written at block {})", resurrection.expired_at, resurrection.slot.touched_at);
+        }
+        blockchain::block::TransactionData::CDRCommitment(commitment) => {
+            println!("     🌳 Type: CDR Commitment");
+            println!("     🆔 Commitment: {}", commitment.commitment_id());
+            println!("     📅 Period: {}", commitment.period);
+            println!("     🏠 Home Network: {}", commitment.home_network);
+            println!("     🌍 Visited Network: {}", commitment.visited_network);
+            println!("     📋 Records: {}", commitment.record_count);
+            println!("     💶 Total: {} cents", commitment.total_charges_cents);
+            println!("     🔏 Merkle Root: {}", commitment.merkle_root);
+            println!("     🔐 ZK Proofs: {}", commitment.zk_proofs.len());
+        }
+        blockchain::block::TransactionData::CommitmentChallenge(challenge) => {
+            println!("     ⚔️  Type: CDR Commitment Challenge");
+            println!("     🆔 Commitment: {}", challenge.commitment_id);
+            println!("     📋 Record Index: {}", challenge.record_index);
+        }
+        blockchain::block::TransactionData::CommitmentResponse(response) => {
+            println!("     🛡️  Type: CDR Commitment Response");
+            println!("     🆔 Commitment: {}", response.commitment_id);
+            println!("     📋 Record Index: {}", response.record_index());
+            println!("     🧾 Record: {}", response.record.record_id);
+        }
+        blockchain::block::TransactionData::PaymentDocument(document) => {
+            println!("     🏦 Type: Payment Document");
+            println!("     🔗 Settlement: {}", document.settlement_tx);
+            println!("     📄 Schema: {}", document.schema);
+            println!("     🔏 Document Hash: {}", document.document_hash);
+        }
+        blockchain::block::TransactionData::Escrow(smart_contracts::EscrowTransaction::Lock(lock)) => {
+            println!("     🔐 Type: Escrow Lock");
+            println!("     🔗 Settlement: {}", lock.settlement_tx);
+            println!("     🏦 Creditor: {}", lock.creditor);
+            println!("     💸 Debtor: {}", lock.debtor);
+            println!("     💶 Amount: {} {}", lock.amount, lock.currency);
+            if let Some(agent) = &lock.payment_agent {
+                println!("     🏛️  Payment Agent: {}", agent);
+            }
+        }
+        blockchain::block::TransactionData::Escrow(smart_contracts::EscrowTransaction::Confirm(confirmation)) => {
+            println!("     ✅ Type: Payment Confirmation");
+            println!("     🔗 Settlement: {}", confirmation.settlement_tx);
+            println!("     ✍️  Confirmer: {}", confirmation.confirmer);
+            println!("     📋 Confirmation: {:?}", confirmation.confirmation_type);
+            if let Some(reference) = &confirmation.transaction_ref {
+                println!("     🧾 Reference: {}", reference);
+            }
+        }
         blockchain::block::TransactionData::Basic => {
             println!("     📝 Type: Basic Transaction");
         }
@@ -534,7 +1954,23 @@ mod tests {
     #[tokio::test]
     async fn test_key_generation() {
         let temp_dir = "/tmp/test_keys";
-        let result = generate_validator_keys(temp_dir.to_string()).await;
+        let result = generate_validator_keys(temp_dir.to_string(), OutputFormat::Text).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_output_flag_is_global() {
+        let cli = Cli::try_parse_from(["sp-cdr-node", "inspect", "--target", "settlements", "--output", "json"]).unwrap();
+        assert_eq!(cli.output_format.parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+
+        let cli = Cli::try_parse_from(["sp-cdr-node", "--output", "json", "generate-keys"]).unwrap();
+        assert_eq!(cli.output_format.parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+
+        // File arguments keep -o, their long names make way for the global flag
+        let cli = Cli::try_parse_from(["sp-cdr-node", "export-snapshot", "-o", "chain.snapshot"]).unwrap();
+        assert_eq!(cli.output_format.parse::<OutputFormat>().unwrap(), OutputFormat::Text);
+        let cli = Cli::try_parse_from(["sp-cdr-node", "validate-cdr", "--file", "records.csv"]).unwrap();
+        assert_eq!(cli.output_format.parse::<OutputFormat>().unwrap(), OutputFormat::Text);
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
 }
\ No newline at end of file
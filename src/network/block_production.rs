@@ -0,0 +1,523 @@
+// Block production schedule driven by Policy: micro blocks sealed from the mempool every
+// block time, macro blocks finalized by Tendermint votes at batch boundaries and
+// election macro blocks that hand over to the next validator set
+use libp2p::PeerId;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::blockchain::{Block, block::ValidatorInfo};
+use crate::blockchain::light_client::{certificate_message, MacroCertificate};
+use crate::blockchain::staking;
+use crate::common::{TendermintStep, TendermintVote};
+use crate::crypto::bls::{BLSPrivateKey, BLSPublicKey, BLSSignature};
+use crate::metrics::metrics;
+use crate::primitives::{Blake2bHash, BlockchainError, Policy, Result};
+
+/// Interval micro blocks are sealed at
+pub const MICRO_BLOCK_INTERVAL: Duration = Duration::from_millis(Policy::BLOCK_TIME);
+
+/// Time a macro block round gets to finalize before the next proposer takes over
+pub const MACRO_ROUND_TIMEOUT: Duration = Duration::from_millis(Policy::BLOCK_TIME * 10);
+
+/// Kind of block a height is reserved for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Micro,
+    /// Last block of a batch, final once precommitted
+    Macro,
+    /// Macro block closing an election interval, carries the next validator set
+    Election,
+}
+
+impl BlockKind {
+    pub fn at(block_number: u32) -> Self {
+        if Policy::is_election_block(block_number) {
+            BlockKind::Election
+        } else if Policy::is_macro_block(block_number) {
+            BlockKind::Macro
+        } else {
+            BlockKind::Micro
+        }
+    }
+}
+
+/// What the local node has to do on a production tick
+#[derive(Debug, Clone)]
+pub enum ProductionStep {
+    Idle,
+    /// Seal queued transactions into a micro block
+    Micro,
+    /// Propose the macro block of `round`, election blocks carry `validators`
+    ProposeMacro {
+        round: u32,
+        validators: Option<Vec<ValidatorInfo>>,
+    },
+}
+
+/// Result of counting a vote
+#[derive(Debug, Clone)]
+pub enum VoteOutcome {
+    Pending,
+    /// Prevotes reached the quorum, the local precommit has to be broadcast
+    Precommit(TendermintVote),
+    /// Precommits reached the quorum, the block is final and can be pushed
+    /// The certificate aggregates the precommit signatures light clients verify it with
+    Finalized {
+        block: Block,
+        certificate: Option<MacroCertificate>,
+    },
+}
+
+/// Macro block proposal being voted on
+#[derive(Debug)]
+struct MacroRound {
+    block: Block,
+    hash: Blake2bHash,
+    prevotes: HashSet<PeerId>,
+    /// Precommitting validators and their BLS signatures over the certificate message
+    precommits: HashMap<PeerId, Vec<u8>>,
+    precommitted: bool,
+}
+
+/// Libp2p peer id a validator entry was announced with, kept in its signal data
+pub fn validator_peer_id(validator: &ValidatorInfo) -> Option<PeerId> {
+    validator.signal_data.as_deref().and_then(|bytes| PeerId::from_bytes(bytes).ok())
+}
+
+/// Combined voting power of `voters`
+fn voting_power<'a>(powers: &HashMap<PeerId, u64>, voters: impl IntoIterator<Item = &'a PeerId>) -> u64 {
+    voters.into_iter().filter_map(|voter| powers.get(voter)).sum()
+}
+
+/// Decides which block the local validator produces next and runs Tendermint
+/// finality for macro blocks
+#[derive(Debug)]
+pub struct BlockProductionScheduler {
+    local_peer_id: PeerId,
+    /// Key the local validator signs precommits with
+    signing_key: Option<BLSPrivateKey>,
+    /// BLS public keys validators announced
+    signing_keys: HashMap<PeerId, Vec<u8>>,
+    last_micro_block: Option<Instant>,
+    /// Validators of the current epoch, ordered so everyone agrees on proposers and vote indices
+    validators: Vec<PeerId>,
+    /// Voting power of the current validators, from the stake they were elected with
+    voting_powers: HashMap<PeerId, u64>,
+    /// Validators the next election block hands over to
+    candidates: BTreeSet<PeerId>,
+    /// Macro block height, round and round start being worked on
+    macro_height: u32,
+    round: u32,
+    round_started: Option<Instant>,
+    proposed: bool,
+    pending: Option<MacroRound>,
+}
+
+impl BlockProductionScheduler {
+    /// Scheduler for a validator that starts out as the only member of the set
+    pub fn new(local_peer_id: PeerId) -> Self {
+        Self {
+            local_peer_id,
+            signing_key: None,
+            signing_keys: HashMap::new(),
+            last_micro_block: None,
+            validators: vec![local_peer_id],
+            voting_powers: HashMap::from([(local_peer_id, 1)]),
+            candidates: BTreeSet::from([local_peer_id]),
+            macro_height: 0,
+            round: 0,
+            round_started: None,
+            proposed: false,
+            pending: None,
+        }
+    }
+
+    /// Sign precommits with `key`, so finalized macro blocks get certificates
+    pub fn with_signing_key(mut self, key: BLSPrivateKey) -> Self {
+        self.set_local_signing_key(key);
+        self
+    }
+
+    /// Start signing precommits with `key`, as a standby does when it takes over
+    pub fn set_local_signing_key(&mut self, key: BLSPrivateKey) {
+        self.signing_keys.insert(self.local_peer_id, key.public_key().to_bytes().to_vec());
+        self.signing_key = Some(key);
+    }
+
+    /// Record the BLS key a validator announced
+    pub fn set_signing_key(&mut self, peer_id: PeerId, signing_key: Vec<u8>) {
+        if !signing_key.is_empty() {
+            self.signing_keys.insert(peer_id, signing_key);
+        }
+    }
+
+    pub fn validators(&self) -> &[PeerId] {
+        &self.validators
+    }
+
+    /// Macro block height and round being worked on
+    pub fn position(&self) -> (u32, u32) {
+        (self.macro_height, self.round)
+    }
+
+    /// Voting power a macro block needs to be prevoted or finalized
+    pub fn required_votes(&self) -> u64 {
+        staking::quorum(self.voting_powers.values().sum())
+    }
+
+    /// Proposer of `round` for the macro block at `block_number`
+    pub fn proposer(&self, block_number: u32, round: u32) -> PeerId {
+        let slot = (block_number / Policy::EPOCH_LENGTH) as usize + round as usize;
+        self.validators[slot % self.validators.len()]
+    }
+
+    /// Validator to include in the next election
+    pub fn add_candidate(&mut self, peer_id: PeerId) {
+        if self.candidates.insert(peer_id) {
+            debug!("🗳️  Validator candidate {} joins at the next election", peer_id);
+        }
+    }
+
+    /// Validator to leave out of the next election; the local node always stands
+    pub fn remove_candidate(&mut self, peer_id: &PeerId) {
+        if *peer_id != self.local_peer_id && self.candidates.remove(peer_id) {
+            debug!("🗳️  Validator candidate {} dropped from the next election", peer_id);
+        }
+    }
+
+    /// Validator set an election block proposed now would carry
+    pub fn election_validators(&self) -> Vec<ValidatorInfo> {
+        self.candidates.iter().map(|peer_id| self.validator_info(peer_id)).collect()
+    }
+
+    fn validator_info(&self, peer_id: &PeerId) -> ValidatorInfo {
+        let address = Blake2bHash::from_data(&peer_id.to_bytes());
+        ValidatorInfo {
+            address,
+            signing_key: self.signing_keys.get(peer_id).cloned().unwrap_or_default(),
+            voting_key: vec![],
+            reward_address: address,
+            signal_data: Some(peer_id.to_bytes()),
+            inactive_from: None,
+            jailed_from: None,
+            stake: 0,
+        }
+    }
+
+    /// Sign `message` with the local validator key, `None` without one
+    pub fn sign(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let key = self.signing_key.as_ref()?;
+        key.sign(message).ok().map(|signature| signature.to_bytes().to_vec())
+    }
+
+    /// Step due for the block at `block_number`
+    pub fn next_step(&mut self, block_number: u32, mempool_empty: bool, now: Instant) -> ProductionStep {
+        let kind = BlockKind::at(block_number);
+        if kind == BlockKind::Micro {
+            let due = self.last_micro_block.map_or(true, |last| now.duration_since(last) >= MICRO_BLOCK_INTERVAL);
+            if mempool_empty || !due {
+                return ProductionStep::Idle;
+            }
+            self.last_micro_block = Some(now);
+            return ProductionStep::Micro;
+        }
+
+        if self.macro_height != block_number {
+            self.enter_round(block_number, 0, now);
+        } else if self.round_started.map_or(false, |started| now.duration_since(started) >= MACRO_ROUND_TIMEOUT) {
+            info!("⏱️  Macro block {} round {} timed out, moving to round {}", block_number, self.round, self.round + 1);
+            metrics().view_changes.inc();
+            self.enter_round(block_number, self.round + 1, now);
+        }
+
+        if self.proposed || self.proposer(block_number, self.round) != self.local_peer_id {
+            return ProductionStep::Idle;
+        }
+        self.proposed = true;
+        ProductionStep::ProposeMacro {
+            round: self.round,
+            validators: (kind == BlockKind::Election).then(|| self.election_validators()),
+        }
+    }
+
+    fn enter_round(&mut self, block_number: u32, round: u32, now: Instant) {
+        self.macro_height = block_number;
+        self.round = round;
+        self.round_started = Some(now);
+        self.proposed = false;
+        self.pending = None;
+    }
+
+    /// Accept the macro block `proposer` proposed and return the local prevote for it
+    pub fn start_round(&mut self, block: Block, proposer: &PeerId, now: Instant) -> Result<TendermintVote> {
+        let Block::Macro(macro_block) = &block else {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "Block {} is not a macro block", block.block_number()
+            )));
+        };
+        let (block_number, round) = (macro_block.header.block_number, macro_block.header.round);
+
+        if self.macro_height == block_number && round < self.round {
+            return Err(BlockchainError::InvalidState(format!(
+                "Proposal for round {} of block {} arrived in round {}", round, block_number, self.round
+            )));
+        }
+        if self.proposer(block_number, round) != *proposer {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "{} is not the proposer of block {} round {}", proposer, block_number, round
+            )));
+        }
+
+        if self.macro_height != block_number || self.round != round {
+            self.enter_round(block_number, round, now);
+        }
+        let hash = block.hash();
+        self.pending = Some(MacroRound {
+            block,
+            hash,
+            prevotes: HashSet::new(),
+            precommits: HashMap::new(),
+            precommitted: false,
+        });
+        Ok(self.local_vote(TendermintStep::Prevote, hash))
+    }
+
+    fn local_vote(&self, step: TendermintStep, hash: Blake2bHash) -> TendermintVote {
+        // Precommits are signed for the block's certificate
+        let signature = match (&self.signing_key, step) {
+            (Some(key), TendermintStep::Precommit) => key.sign(&certificate_message(&hash, self.round))
+                .map(|signature| signature.to_bytes().to_vec())
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+        TendermintVote {
+            proposal_hash: Some(hash),
+            round: self.round,
+            step,
+            validator_idx: self.validator_index(&self.local_peer_id).unwrap_or_default(),
+            signature,
+        }
+    }
+
+    /// Aggregate the precommits with valid signatures, if enough validators signed
+    fn certificate(&self, round: &MacroRound) -> Option<MacroCertificate> {
+        let message = certificate_message(&round.hash, self.round);
+        let signatures: Vec<(u16, BLSSignature)> = round.precommits.iter()
+            .filter_map(|(peer_id, signature)| {
+                let index = self.validator_index(peer_id)?;
+                let public_key = BLSPublicKey::from_bytes(self.signing_keys.get(peer_id)?).ok()?;
+                let signature = BLSSignature::from_bytes(signature).ok()?;
+                signature.verify(&public_key, &message).ok()?.then_some((index, signature))
+            })
+            .collect();
+
+        let signers = signatures.iter().map(|(index, _)| &self.validators[*index as usize]);
+        if voting_power(&self.voting_powers, signers) < self.required_votes() {
+            debug!("Macro block {} finalized without certificate, {} signed precommits", round.hash, signatures.len());
+            return None;
+        }
+        MacroCertificate::aggregate(round.hash, self.round, signatures).ok()
+    }
+
+    fn validator_index(&self, peer_id: &PeerId) -> Option<u16> {
+        self.validators.iter().position(|validator| validator == peer_id).map(|index| index as u16)
+    }
+
+    /// Count a vote of `voter`, including the local node's own votes
+    pub fn handle_vote(&mut self, voter: PeerId, vote: &TendermintVote) -> VoteOutcome {
+        if self.validator_index(&voter) != Some(vote.validator_idx) {
+            debug!("Ignoring vote from {}, not validator {}", voter, vote.validator_idx);
+            return VoteOutcome::Pending;
+        }
+        let required = self.required_votes();
+        let round = self.round;
+        let powers = &self.voting_powers;
+        let Some(pending) = self.pending.as_mut() else {
+            return VoteOutcome::Pending;
+        };
+        if vote.round != round || vote.proposal_hash != Some(pending.hash) {
+            return VoteOutcome::Pending;
+        }
+
+        match vote.step {
+            TendermintStep::Propose => VoteOutcome::Pending,
+            TendermintStep::Prevote => {
+                pending.prevotes.insert(voter);
+                if pending.precommitted || voting_power(powers, &pending.prevotes) < required {
+                    return VoteOutcome::Pending;
+                }
+                pending.precommitted = true;
+                let hash = pending.hash;
+                VoteOutcome::Precommit(self.local_vote(TendermintStep::Precommit, hash))
+            }
+            TendermintStep::Precommit => {
+                pending.precommits.insert(voter, vote.signature.clone());
+                if voting_power(powers, pending.precommits.keys()) < required {
+                    return VoteOutcome::Pending;
+                }
+                let finalized = self.pending.take().expect("pending round checked above");
+                self.proposed = false;
+                self.round_started = None;
+                let certificate = self.certificate(&finalized);
+                VoteOutcome::Finalized { block: finalized.block, certificate }
+            }
+        }
+    }
+
+    /// Hand over to the validators of a finalized election block, weighted by their elected stake
+    pub fn rotate(&mut self, validators: &[ValidatorInfo]) {
+        let voting_powers: HashMap<PeerId, u64> = validators.iter()
+            .zip(staking::voting_powers(validators))
+            .filter_map(|(validator, power)| Some((validator_peer_id(validator)?, power)))
+            .collect();
+        if voting_powers.is_empty() {
+            return;
+        }
+        let mut next: Vec<PeerId> = voting_powers.keys().copied().collect();
+        next.sort();
+
+        info!("🔄 Validator set rotated: {} -> {} validators", self.validators.len(), next.len());
+        self.candidates = next.iter().copied().collect();
+        self.candidates.insert(self.local_peer_id);
+        self.validators = next;
+        self.voting_powers = voting_powers;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{MacroBlock, MacroBody, MacroHeader};
+    use crate::primitives::NetworkId;
+
+    fn macro_block(block_number: u32, round: u32, validators: Option<Vec<ValidatorInfo>>) -> Block {
+        Block::Macro(MacroBlock {
+            justification: None,
+            header: MacroHeader {
+                network: NetworkId::new("T-Mobile", "DE"),
+                version: 1,
+                block_number,
+                round,
+                timestamp: 0,
+                parent_hash: Blake2bHash::zero(),
+                parent_election_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MacroBody { validators, lost_reward_set: vec![], disabled_set: vec![], transactions: vec![] },
+        })
+    }
+
+    #[test]
+    fn test_block_kinds_follow_policy() {
+        assert_eq!(BlockKind::at(1), BlockKind::Micro);
+        assert_eq!(BlockKind::at(Policy::EPOCH_LENGTH), BlockKind::Macro);
+        assert_eq!(BlockKind::at(Policy::ELECTION_BLOCK_INTERVAL), BlockKind::Election);
+
+        let mut scheduler = BlockProductionScheduler::new(PeerId::random());
+        let now = Instant::now();
+        assert!(matches!(scheduler.next_step(1, true, now), ProductionStep::Idle));
+        assert!(matches!(scheduler.next_step(1, false, now), ProductionStep::Micro));
+        // The next micro block waits for the block time
+        assert!(matches!(scheduler.next_step(2, false, now), ProductionStep::Idle));
+        assert!(matches!(scheduler.next_step(2, false, now + MICRO_BLOCK_INTERVAL), ProductionStep::Micro));
+
+        // Macro blocks are proposed once per round even with an empty mempool
+        let macro_step = scheduler.next_step(Policy::EPOCH_LENGTH, true, now);
+        assert!(matches!(macro_step, ProductionStep::ProposeMacro { round: 0, validators: None }));
+        assert!(matches!(scheduler.next_step(Policy::EPOCH_LENGTH, true, now), ProductionStep::Idle));
+        let retry = scheduler.next_step(Policy::EPOCH_LENGTH, true, now + MACRO_ROUND_TIMEOUT);
+        assert!(matches!(retry, ProductionStep::ProposeMacro { round: 1, .. }));
+
+        let election = scheduler.next_step(Policy::ELECTION_BLOCK_INTERVAL, true, now);
+        assert!(matches!(election, ProductionStep::ProposeMacro { validators: Some(ref validators), .. } if validators.len() == 1));
+    }
+
+    #[test]
+    fn test_macro_block_finality_and_rotation() {
+        let local = PeerId::random();
+        let others = [PeerId::random(), PeerId::random()];
+        let keys: Vec<BLSPrivateKey> = (0..2).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let mut scheduler = BlockProductionScheduler::new(local).with_signing_key(BLSPrivateKey::generate().unwrap());
+        for (peer, key) in others.iter().zip(&keys) {
+            scheduler.add_candidate(*peer);
+            scheduler.set_signing_key(*peer, key.public_key().to_bytes().to_vec());
+        }
+        scheduler.rotate(&scheduler.election_validators());
+        assert_eq!(scheduler.validators().len(), 3);
+        assert_eq!(scheduler.required_votes(), 3);
+
+        let height = Policy::EPOCH_LENGTH;
+        let now = Instant::now();
+        let block = macro_block(height, 0, None);
+        let proposer = scheduler.proposer(height, 0);
+        let stranger = PeerId::random();
+        assert!(scheduler.start_round(block.clone(), &stranger, now).is_err());
+
+        let prevote = scheduler.start_round(block.clone(), &proposer, now).unwrap();
+        assert!(matches!(scheduler.handle_vote(local, &prevote), VoteOutcome::Pending));
+
+        let vote_of = |peer: &PeerId, step, key: &BLSPrivateKey| TendermintVote {
+            proposal_hash: Some(block.hash()),
+            round: 0,
+            step,
+            validator_idx: scheduler.validators().iter().position(|v| v == peer).unwrap() as u16,
+            signature: key.sign(&certificate_message(&block.hash(), 0)).unwrap().to_bytes().to_vec(),
+        };
+        let remote_prevotes: Vec<_> = others.iter().zip(&keys).map(|(peer, key)| vote_of(peer, TendermintStep::Prevote, key)).collect();
+        let remote_precommits: Vec<_> = others.iter().zip(&keys).map(|(peer, key)| vote_of(peer, TendermintStep::Precommit, key)).collect();
+
+        // A vote under someone else's index is not counted
+        assert!(matches!(scheduler.handle_vote(stranger, &remote_prevotes[0]), VoteOutcome::Pending));
+        assert!(matches!(scheduler.handle_vote(others[0], &remote_prevotes[0]), VoteOutcome::Pending));
+        let VoteOutcome::Precommit(precommit) = scheduler.handle_vote(others[1], &remote_prevotes[1]) else {
+            panic!("two thirds of prevotes lead to a precommit");
+        };
+
+        assert!(matches!(scheduler.handle_vote(local, &precommit), VoteOutcome::Pending));
+        assert!(matches!(scheduler.handle_vote(others[0], &remote_precommits[0]), VoteOutcome::Pending));
+        let VoteOutcome::Finalized { block: finalized, certificate } = scheduler.handle_vote(others[1], &remote_precommits[1]) else {
+            panic!("two thirds of precommits finalize the block");
+        };
+        assert_eq!(finalized.hash(), block.hash());
+
+        // The certificate verifies against the election's validator list
+        certificate.unwrap().verify(&scheduler.election_validators()).unwrap();
+
+        // An election without the dropped candidate shrinks the set
+        scheduler.remove_candidate(&others[0]);
+        let election = macro_block(Policy::ELECTION_BLOCK_INTERVAL, 0, Some(scheduler.election_validators()));
+        let Block::Macro(election) = election else { unreachable!() };
+        scheduler.rotate(election.body.validators.as_deref().unwrap());
+        assert_eq!(scheduler.validators().len(), 2);
+        assert!(!scheduler.validators().contains(&others[0]));
+    }
+
+    #[test]
+    fn test_votes_weighted_by_elected_stake() {
+        let local = PeerId::random();
+        let other = PeerId::random();
+        let mut scheduler = BlockProductionScheduler::new(local);
+        scheduler.add_candidate(other);
+        let mut validators = scheduler.election_validators();
+        for validator in &mut validators {
+            validator.stake = if validator_peer_id(validator) == Some(local) { 900 } else { 100 };
+        }
+        scheduler.rotate(&validators);
+        assert_eq!(scheduler.required_votes(), 667);
+
+        // The local validator holds 90% of the stake and finalizes on its own votes
+        let height = Policy::EPOCH_LENGTH;
+        let block = macro_block(height, 0, None);
+        let proposer = scheduler.proposer(height, 0);
+        let prevote = scheduler.start_round(block, &proposer, Instant::now()).unwrap();
+        let VoteOutcome::Precommit(precommit) = scheduler.handle_vote(local, &prevote) else {
+            panic!("a two-thirds stake majority precommits");
+        };
+        assert!(matches!(scheduler.handle_vote(local, &precommit), VoteOutcome::Finalized { .. }));
+    }
+}
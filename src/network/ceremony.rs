@@ -0,0 +1,422 @@
+// Multi-party trusted setup over the network: the bootstrap node calls for contributions, each
+// participant in turn fetches the state of the one before over the trusted setup protocol, checks it,
+// contributes and gossips the signed contributions so far, and the last one installs the final keys
+// for the others to fetch
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use libp2p::PeerId;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+use crate::crypto::BLSPrivateKey;
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use crate::zkp::trusted_setup::{
+    CeremonyPart, CeremonyTranscript, ParticipantContribution, TrustedSetupCeremony, VerificationStatus,
+    CIRCUIT_IDS, POWERS_OF_TAU,
+};
+use super::setup_sync::QUERY_INTERVAL;
+use super::{NetworkCommand, NetworkEvent, SPNetworkMessage, TrustedSetupRequest, TrustedSetupResponse, TrustedSetupSync};
+
+/// What a node does next in the ceremony
+#[derive(Debug, Clone, PartialEq)]
+pub enum CeremonyAction {
+    Wait,
+    /// Fetch parts of the previous participant's state, then contribute
+    Fetch(PeerId, Vec<CeremonyPart>),
+    Contribute,
+    /// Fetch the final keys and transcript from the peer announcing them
+    Install(PeerId, Blake2bHash),
+}
+
+/// One node's part in a multi-party ceremony, as coordinator, participant or both
+pub struct CeremonyDriver {
+    ceremony: TrustedSetupCeremony,
+    /// This node's operator, as named in the participant list
+    participant: String,
+    signing_key: BLSPrivateKey,
+    local_peer_id: PeerId,
+    /// Contribution order, configured alike on every node
+    participants: Vec<String>,
+    coordinator: bool,
+    transcript: Option<CeremonyTranscript>,
+    /// Peer holding the state of the latest contribution
+    holder: Option<PeerId>,
+    /// Parts of the previous state still to arrive before this node contributes
+    pending: HashSet<CeremonyPart>,
+    /// Last message this node gossiped, repeated for participants that joined late
+    last_message: Option<SPNetworkMessage>,
+    sync: Option<TrustedSetupSync>,
+}
+
+impl CeremonyDriver {
+    pub fn new(
+        keys_dir: PathBuf,
+        participants: Vec<String>,
+        participant: String,
+        signing_key: BLSPrivateKey,
+        local_peer_id: PeerId,
+        coordinator: bool,
+    ) -> Self {
+        Self {
+            ceremony: TrustedSetupCeremony::sp_consortium_ceremony(keys_dir),
+            participant,
+            signing_key,
+            local_peer_id,
+            participants,
+            coordinator,
+            transcript: None,
+            holder: None,
+            pending: HashSet::new(),
+            last_message: None,
+            sync: None,
+        }
+    }
+
+    /// Coordinator: open a ceremony, returns the call for contributions to gossip
+    pub fn start(&mut self, powers_degree: usize) -> SPNetworkMessage {
+        let start_time = chrono::Utc::now().timestamp() as u64;
+        let ceremony_id = format!("sp-consortium-{}", start_time);
+        self.transcript = Some(Self::transcript(&ceremony_id, self.participants.clone(), powers_degree, start_time));
+        let message = SPNetworkMessage::CeremonyStarted {
+            ceremony_id,
+            participants: self.participants.clone(),
+            powers_degree,
+            start_time,
+        };
+        self.last_message = Some(message.clone());
+        message
+    }
+
+    fn transcript(ceremony_id: &str, participants: Vec<String>, powers_degree: usize, start_time: u64) -> CeremonyTranscript {
+        CeremonyTranscript {
+            ceremony_id: ceremony_id.to_string(),
+            start_time,
+            end_time: None,
+            participants,
+            contributions: Vec::new(),
+            final_parameters_hash: None,
+            verification_status: VerificationStatus::Pending,
+            powers_degree,
+            single_party: false,
+        }
+    }
+
+    /// Join the ceremony the coordinator called for, if its participants are the configured ones
+    pub fn handle_started(&mut self, ceremony_id: &str, participants: Vec<String>, powers_degree: usize, start_time: u64, min_degree: usize) -> CeremonyAction {
+        if self.transcript.is_some() {
+            return CeremonyAction::Wait;
+        }
+        if participants != self.participants {
+            warn!("⚠️  Ignoring ceremony {} of participants {:?}, expected {:?}", ceremony_id, participants, self.participants);
+            return CeremonyAction::Wait;
+        }
+        if powers_degree < min_degree {
+            warn!("⚠️  Ignoring ceremony {} of degree {}, the circuits need {}", ceremony_id, powers_degree, min_degree);
+            return CeremonyAction::Wait;
+        }
+        info!("🔐 Joining trusted setup ceremony {} of {:?}", ceremony_id, participants);
+        self.transcript = Some(Self::transcript(ceremony_id, participants, powers_degree, start_time));
+        self.next_action()
+    }
+
+    /// Follow contributions gossiped for `holder` if they extend the followed ones and check out
+    pub fn handle_contributed(
+        &mut self,
+        ceremony_id: &str,
+        contributions: Vec<ParticipantContribution>,
+        holder: PeerId,
+        transcript_hash: Option<Blake2bHash>,
+    ) -> Result<CeremonyAction> {
+        let Some(transcript) = &self.transcript else {
+            return Ok(CeremonyAction::Wait);
+        };
+        if transcript.ceremony_id != ceremony_id || contributions.len() <= transcript.contributions.len() {
+            return Ok(CeremonyAction::Wait);
+        }
+        if !transcript.contributions.iter().zip(&contributions).all(|(followed, received)| followed.signature == received.signature) {
+            return Err(BlockchainError::Crypto(format!("Contributions of {} conflict with those followed", holder)));
+        }
+
+        let mut extended = transcript.clone();
+        extended.contributions = contributions;
+        extended.verify_contributions()?;
+        // Participants contribute to every circuit at once
+        let parameter_steps = extended.contributions_to(CIRCUIT_IDS[0]).count();
+        if CIRCUIT_IDS.iter().any(|circuit_id| extended.contributions_to(circuit_id).count() != parameter_steps) {
+            return Err(BlockchainError::Crypto(format!("Contributions of {} leave out circuits", holder)));
+        }
+
+        let complete = extended.is_complete();
+        info!("📥 Trusted setup ceremony {} at {} contributions", ceremony_id, extended.contributions.len());
+        self.transcript = Some(extended);
+        self.holder = Some(holder);
+        // Superseded by later contributions
+        self.last_message = None;
+
+        Ok(match (complete, transcript_hash) {
+            (true, Some(transcript_hash)) => CeremonyAction::Install(holder, transcript_hash),
+            (true, None) => CeremonyAction::Wait,
+            (false, _) => self.next_action(),
+        })
+    }
+
+    /// Participant whose turn it is, contributing to the powers of tau then to the circuits
+    fn next_contributor(transcript: &CeremonyTranscript) -> Option<&String> {
+        let powers_steps = transcript.contributions_to(POWERS_OF_TAU).count();
+        if powers_steps < transcript.participants.len() {
+            transcript.participants.get(powers_steps)
+        } else {
+            transcript.participants.get(transcript.contributions_to(CIRCUIT_IDS[0]).count())
+        }
+    }
+
+    fn next_action(&mut self) -> CeremonyAction {
+        let Some(transcript) = &self.transcript else {
+            return CeremonyAction::Wait;
+        };
+        if transcript.is_complete() || Self::next_contributor(transcript) != Some(&self.participant) {
+            return CeremonyAction::Wait;
+        }
+        // Nothing to fetch for the first contribution, nor after this node's own
+        let previous = match (transcript.contributions.last(), self.holder) {
+            (Some(previous), Some(holder)) if previous.participant_id != self.participant => holder,
+            _ => return CeremonyAction::Contribute,
+        };
+
+        let mut parts: Vec<CeremonyPart> = CeremonyPart::powers().collect();
+        if transcript.contributions_to(CIRCUIT_IDS[0]).count() > 0 {
+            parts.extend(CIRCUIT_IDS.iter().map(|circuit_id| CeremonyPart::Parameters(circuit_id.to_string())));
+        }
+        self.pending = parts.iter().cloned().collect();
+        CeremonyAction::Fetch(previous, parts)
+    }
+
+    /// Store a part of the previous participant's state, `true` once every part arrived
+    pub async fn handle_state(&mut self, peer: PeerId, part: CeremonyPart, data: Vec<u8>) -> Result<bool> {
+        if self.holder != Some(peer) || !self.pending.contains(&part) {
+            return Ok(false);
+        }
+        self.ceremony.store_ceremony_file(&part, &data).await?;
+        self.pending.remove(&part);
+        Ok(self.pending.is_empty())
+    }
+
+    /// Contribute this node's step, returns the message announcing it
+    /// The last contribution installs the final keys, the message then carries the transcript's hash
+    pub async fn contribute(&mut self) -> Result<SPNetworkMessage> {
+        let transcript = self.transcript.as_mut()
+            .ok_or_else(|| BlockchainError::InvalidState("No ceremony to contribute to".to_string()))?;
+        let mut rng = StdRng::from_entropy();
+
+        if transcript.contributions_to(POWERS_OF_TAU).count() < transcript.participants.len() {
+            let contribution = self.ceremony.contribute_powers(transcript, &self.participant, &self.signing_key, &mut rng).await?;
+            transcript.contributions.push(contribution);
+        } else {
+            let contributions = self.ceremony.contribute_parameters(transcript, &self.participant, &self.signing_key, &mut rng).await?;
+            transcript.contributions.extend(contributions);
+        }
+        info!("🎲 Contributed to trusted setup ceremony {}", transcript.ceremony_id);
+
+        let transcript_hash = match transcript.is_complete() {
+            true => Some(Blake2bHash::from_data(&self.ceremony.finish_ceremony(transcript.clone()).await?)),
+            false => None,
+        };
+        let message = SPNetworkMessage::CeremonyContributed {
+            ceremony_id: transcript.ceremony_id.clone(),
+            contributions: transcript.contributions.clone(),
+            holder: self.local_peer_id,
+            transcript_hash,
+        };
+        self.last_message = Some(message.clone());
+        Ok(message)
+    }
+
+    /// Take part in the ceremony until this node holds the final keys, `false` if it did not complete in time
+    pub async fn run(
+        mut self,
+        commands: &mpsc::Sender<NetworkCommand>,
+        events: &mut broadcast::Receiver<NetworkEvent>,
+    ) -> Result<bool> {
+        let deadline = tokio::time::sleep(Duration::from_secs(self.ceremony.config().ceremony_timeout));
+        tokio::pin!(deadline);
+        // Ticks first right away, which sends the call for contributions
+        let mut tick = tokio::time::interval(QUERY_INTERVAL);
+        let min_degree = self.ceremony.powers_degree()?;
+
+        let mut action = CeremonyAction::Wait;
+        if self.coordinator {
+            self.start(min_degree);
+            info!("📣 Calling for trusted setup contributions from {:?}", self.participants);
+            action = self.next_action();
+        }
+
+        loop {
+            match std::mem::replace(&mut action, CeremonyAction::Wait) {
+                CeremonyAction::Wait => {}
+                CeremonyAction::Fetch(peer, parts) => {
+                    for part in parts {
+                        let request = TrustedSetupRequest::CeremonyState(part);
+                        let _ = commands.send(NetworkCommand::RequestTrustedSetup { peer, request }).await;
+                    }
+                }
+                CeremonyAction::Contribute => {
+                    let message = self.contribute().await?;
+                    broadcast_zkp(commands, message).await;
+                    if self.transcript.as_ref().is_some_and(CeremonyTranscript::is_complete) {
+                        return Ok(true);
+                    }
+                }
+                CeremonyAction::Install(peer, transcript_hash) => {
+                    let ceremony_id = self.transcript.as_ref().map(|t| t.ceremony_id.clone()).unwrap_or_default();
+                    let mut sync = TrustedSetupSync::new(true);
+                    for (peer, request) in sync.handle_announcement(peer, &ceremony_id, transcript_hash) {
+                        let _ = commands.send(NetworkCommand::RequestTrustedSetup { peer, request }).await;
+                    }
+                    self.sync = Some(sync);
+                }
+            }
+
+            action = tokio::select! {
+                _ = &mut deadline => return Ok(false),
+
+                _ = tick.tick() => {
+                    if let Some(message) = &self.last_message {
+                        broadcast_zkp(commands, message.clone()).await;
+                    }
+                    // Once the ceremony completed without this node following the final keys, ask who holds them
+                    if self.sync.is_none() && self.transcript.as_ref().is_some_and(CeremonyTranscript::is_complete) {
+                        broadcast_zkp(commands, SPNetworkMessage::TrustedSetupQuery).await;
+                    }
+                    match (self.holder, self.pending.is_empty()) {
+                        (Some(holder), false) => CeremonyAction::Fetch(holder, self.pending.iter().cloned().collect()),
+                        _ => CeremonyAction::Wait,
+                    }
+                }
+
+                event = events.recv() => match event {
+                    Ok(NetworkEvent::GossipReceived { message, source, .. }) => match message {
+                        SPNetworkMessage::CeremonyStarted { ceremony_id, participants, powers_degree, start_time } => {
+                            self.handle_started(&ceremony_id, participants, powers_degree, start_time, min_degree)
+                        }
+                        SPNetworkMessage::CeremonyContributed { ceremony_id, contributions, holder, transcript_hash } => {
+                            self.handle_contributed(&ceremony_id, contributions, holder, transcript_hash).unwrap_or_else(|e| {
+                                warn!("⚠️  Discarding ceremony contributions from {}: {}", source, e);
+                                CeremonyAction::Wait
+                            })
+                        }
+                        SPNetworkMessage::TrustedSetupAvailable { ceremony_id, transcript_hash }
+                            if self.sync.is_none() && self.transcript.as_ref().is_some_and(|t| t.ceremony_id == ceremony_id) =>
+                        {
+                            CeremonyAction::Install(source, transcript_hash)
+                        }
+                        _ => CeremonyAction::Wait,
+                    },
+                    Ok(NetworkEvent::TrustedSetupResponse { peer, response: TrustedSetupResponse::CeremonyState { part, data } }) => {
+                        match self.handle_state(peer, part, data).await? {
+                            true => CeremonyAction::Contribute,
+                            false => CeremonyAction::Wait,
+                        }
+                    }
+                    Ok(NetworkEvent::TrustedSetupResponse { peer, response }) => {
+                        if let Some(sync) = self.sync.as_mut() {
+                            match sync.handle_response(peer, response) {
+                                Ok(requests) => {
+                                    for (peer, request) in requests {
+                                        let _ = commands.send(NetworkCommand::RequestTrustedSetup { peer, request }).await;
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("⚠️  Discarding final trusted setup keys from {}: {}", peer, e);
+                                    self.sync = None;
+                                }
+                            }
+                        }
+                        if self.sync.as_ref().is_some_and(TrustedSetupSync::is_complete) {
+                            let sync = self.sync.take().expect("sync is complete");
+                            sync.install(&self.ceremony).await?;
+                            self.audit().await?;
+                            return Ok(true);
+                        }
+                        CeremonyAction::Wait
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err(BlockchainError::NetworkError("Network stopped during the trusted setup ceremony".to_string()));
+                    }
+                    _ => CeremonyAction::Wait,
+                },
+            };
+        }
+    }
+
+    /// Participants audit the installed keys against the final powers of tau they checked, nodes that
+    /// only coordinated do not hold those
+    async fn audit(&self) -> Result<()> {
+        if !self.participants.contains(&self.participant) {
+            return Ok(());
+        }
+        info!("🔍 Auditing the final trusted setup keys...");
+        self.ceremony.audit_ceremony(&mut StdRng::from_entropy()).await
+    }
+}
+
+async fn broadcast_zkp(commands: &mpsc::Sender<NetworkCommand>, message: SPNetworkMessage) {
+    let _ = commands.send(NetworkCommand::Broadcast { topic: "zkp".to_string(), message }).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zkp::mpc::PowersOfTau;
+    use ark_std::test_rng;
+
+    #[test]
+    fn test_follow_contributions_in_turn() {
+        let participants: Vec<String> = ["T-Mobile-DE", "Vodafone-UK", "Orange-FR"].iter().map(|name| name.to_string()).collect();
+        let keys: Vec<BLSPrivateKey> = (0..3).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let driver = |index: usize, coordinator: bool| CeremonyDriver::new(
+            dir.path().join(&participants[index]), participants.clone(), participants[index].clone(),
+            keys[index].clone(), PeerId::random(), coordinator,
+        );
+
+        // The coordinator opens the ceremony, its first participant contributes without fetching anything
+        let mut first = driver(0, true);
+        let SPNetworkMessage::CeremonyStarted { ceremony_id, powers_degree, start_time, .. } = first.start(4) else {
+            panic!("Expected the call for contributions");
+        };
+        assert_eq!(first.next_action(), CeremonyAction::Contribute);
+
+        // Nodes configured with other participants, or needing larger powers, stay out
+        let mut third = driver(2, false);
+        assert_eq!(third.handle_started(&ceremony_id, participants[..2].to_vec(), powers_degree, start_time, 4), CeremonyAction::Wait);
+        assert!(third.transcript.is_none());
+        assert_eq!(third.handle_started(&ceremony_id, participants.clone(), powers_degree, start_time, 8), CeremonyAction::Wait);
+        let mut second = driver(1, false);
+        assert_eq!(second.handle_started(&ceremony_id, participants.clone(), powers_degree, start_time, 4), CeremonyAction::Wait);
+        assert_eq!(third.handle_started(&ceremony_id, participants.clone(), powers_degree, start_time, 4), CeremonyAction::Wait);
+
+        let mut powers = PowersOfTau::new(powers_degree);
+        let update = powers.contribute(&mut test_rng());
+        let contribution = ParticipantContribution::signed(
+            &ceremony_id, &participants[0], POWERS_OF_TAU, Blake2bHash::default(), powers.hash().unwrap(), update.to_bytes().unwrap(), &keys[0],
+        ).unwrap();
+        let holder = PeerId::random();
+
+        // A contribution signed by another participant's key is not followed
+        let mut forged = contribution.clone();
+        forged.signature = ParticipantContribution::signed(
+            &ceremony_id, &participants[0], POWERS_OF_TAU, Blake2bHash::default(), powers.hash().unwrap(), update.to_bytes().unwrap(), &keys[1],
+        ).unwrap().signature;
+        assert!(second.handle_contributed(&ceremony_id, vec![forged], holder, None).is_err());
+
+        // The next participant fetches the powers of tau from the holder, the others wait their turn
+        let action = second.handle_contributed(&ceremony_id, vec![contribution.clone()], holder, None).unwrap();
+        assert_eq!(action, CeremonyAction::Fetch(holder, CeremonyPart::powers().collect()));
+        assert_eq!(third.handle_contributed(&ceremony_id, vec![contribution.clone()], holder, None).unwrap(), CeremonyAction::Wait);
+        // Repeated gossip changes nothing
+        assert_eq!(second.handle_contributed(&ceremony_id, vec![contribution], holder, None).unwrap(), CeremonyAction::Wait);
+        assert_eq!(second.pending.len(), 4);
+    }
+}
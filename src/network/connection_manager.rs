@@ -0,0 +1,160 @@
+// Connection management: caps the connections one operator holds across its nodes, limits how
+// often a peer may connect and describes peer bans, which the peer store keeps across restarts
+use libp2p::PeerId;
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::NetworkId;
+use super::{serialize_peer_id, deserialize_peer_id};
+
+/// Connection caps and per-peer connection rate
+#[derive(Debug, Clone)]
+pub struct ConnectionLimits {
+    /// Open connections to all nodes of one verified operator
+    pub max_connections_per_operator: u32,
+    /// Connections a peer may establish within `rate_window_secs`
+    pub max_connections_per_window: u32,
+    pub rate_window_secs: u64,
+    /// How long a peer connecting faster than that is banned
+    pub rate_ban_secs: u64,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_connections_per_operator: 4,
+            max_connections_per_window: 10,
+            rate_window_secs: 60,
+            rate_ban_secs: 600, // 10 minutes
+        }
+    }
+}
+
+/// Ban of a peer, lifted at `until`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerBan {
+    #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+    pub peer_id: PeerId,
+    pub until: u64,
+    pub reason: String,
+}
+
+impl PeerBan {
+    pub fn is_active(&self, now: u64) -> bool {
+        now < self.until
+    }
+}
+
+/// Whether a connection is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionVerdict {
+    Accept,
+    /// The peer connected more often than the rate allows
+    RateLimited,
+    /// The operator the peer is verified as holds more connections than allowed
+    OperatorLimit(NetworkId),
+}
+
+/// Open connections and recent connection times per peer, checked against the limits
+#[derive(Debug, Default)]
+pub struct ConnectionTracker {
+    limits: ConnectionLimits,
+    open: HashMap<PeerId, u32>,
+    recent: HashMap<PeerId, VecDeque<u64>>,
+}
+
+impl ConnectionTracker {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        Self {
+            limits,
+            open: HashMap::new(),
+            recent: HashMap::new(),
+        }
+    }
+
+    pub fn limits(&self) -> &ConnectionLimits {
+        &self.limits
+    }
+
+    /// Record a connection established at `now`, `open` being the peer's connections including it
+    pub fn connection_established(&mut self, peer_id: PeerId, open: u32, now: u64) -> ConnectionVerdict {
+        self.open.insert(peer_id, open);
+
+        let window_start = now.saturating_sub(self.limits.rate_window_secs);
+        let recent = self.recent.entry(peer_id).or_default();
+        recent.push_back(now);
+        while recent.front().is_some_and(|&at| at <= window_start) {
+            recent.pop_front();
+        }
+        if recent.len() > self.limits.max_connections_per_window as usize {
+            return ConnectionVerdict::RateLimited;
+        }
+        ConnectionVerdict::Accept
+    }
+
+    /// Record a connection closed, `remaining` being the peer's connections still open
+    pub fn connection_closed(&mut self, peer_id: PeerId, remaining: u32) {
+        if remaining == 0 {
+            self.open.remove(&peer_id);
+        } else {
+            self.open.insert(peer_id, remaining);
+        }
+    }
+
+    /// Check the connections of `network_id`, whose nodes are the peers `verified` as it
+    pub fn check_operator(&self, network_id: &NetworkId, verified: &HashMap<PeerId, NetworkId>) -> ConnectionVerdict {
+        let connections: u32 = verified.iter()
+            .filter(|(_, operator)| *operator == network_id)
+            .map(|(peer_id, _)| self.open.get(peer_id).copied().unwrap_or(0))
+            .sum();
+        if connections > self.limits.max_connections_per_operator {
+            return ConnectionVerdict::OperatorLimit(network_id.clone());
+        }
+        ConnectionVerdict::Accept
+    }
+
+    /// Forget connection times that have left the rate window
+    pub fn prune(&mut self, now: u64) {
+        let window_start = now.saturating_sub(self.limits.rate_window_secs);
+        self.recent.retain(|_, recent| recent.back().is_some_and(|&at| at > window_start));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_rate_and_operator_limits() {
+        let limits = ConnectionLimits {
+            max_connections_per_operator: 2,
+            max_connections_per_window: 3,
+            rate_window_secs: 60,
+            rate_ban_secs: 600,
+        };
+        let mut tracker = ConnectionTracker::new(limits);
+
+        // A fourth connection within a minute is one too many, after the window it is fine again
+        let peer = PeerId::random();
+        for at in [100, 110, 120] {
+            assert_eq!(tracker.connection_established(peer, 1, at), ConnectionVerdict::Accept);
+            tracker.connection_closed(peer, 0);
+        }
+        assert_eq!(tracker.connection_established(peer, 1, 130), ConnectionVerdict::RateLimited);
+        tracker.connection_closed(peer, 0);
+        assert_eq!(tracker.connection_established(peer, 1, 200), ConnectionVerdict::Accept);
+        tracker.prune(1_000);
+        assert!(tracker.recent.is_empty());
+
+        // Connections count per operator, across all of its nodes
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let second = PeerId::random();
+        let verified = HashMap::from([(peer, vodafone.clone()), (second, vodafone.clone())]);
+        assert_eq!(tracker.check_operator(&vodafone, &verified), ConnectionVerdict::Accept);
+        tracker.connection_established(second, 2, 200);
+        assert_eq!(tracker.check_operator(&vodafone, &verified), ConnectionVerdict::OperatorLimit(vodafone.clone()));
+        tracker.connection_closed(second, 1);
+        assert_eq!(tracker.check_operator(&vodafone, &verified), ConnectionVerdict::Accept);
+        assert_eq!(tracker.check_operator(&NetworkId::new("Orange", "FR"), &verified), ConnectionVerdict::Accept);
+    }
+}
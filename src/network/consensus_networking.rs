@@ -1,6 +1,7 @@
 // Consensus networking for SP CDR blockchain
 use libp2p::PeerId;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, RwLock};
 use tracing::{info, debug, warn, error};
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
@@ -21,30 +22,17 @@ where
     s.parse().map_err(serde::de::Error::custom)
 }
 
-fn serialize_peer_id_vec<S>(peer_ids: &Vec<(PeerId, Vec<u8>)>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let string_vec: Vec<(String, Vec<u8>)> = peer_ids.iter()
-        .map(|(peer, data)| (peer.to_string(), data.clone()))
-        .collect();
-    string_vec.serialize(serializer)
-}
-
-fn deserialize_peer_id_vec<'de, D>(deserializer: D) -> Result<Vec<(PeerId, Vec<u8>)>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let string_vec: Vec<(String, Vec<u8>)> = Vec::deserialize(deserializer)?;
-    string_vec.into_iter()
-        .map(|(s, data)| s.parse().map(|peer| (peer, data)).map_err(serde::de::Error::custom))
-        .collect()
-}
-
-use crate::primitives::{Blake2bHash, NetworkId, BlockchainError, Height};
+use crate::primitives::{Blake2bHash, NetworkId, BlockchainError, Height, Policy};
 use crate::blockchain::{Block, Transaction};
 use crate::network::{SPNetworkMessage, NetworkCommand};
-use crate::crypto::bls::{BLSPrivateKey, BLSPublicKey, BLSSignature, BLSVerifier};
+use crate::network::replay::{MessageRecorder, RecordedEvent};
+use crate::crypto::bls::{BLSPublicKey, BLSSignature, BLSVerifier, key_rotation_message};
+use crate::crypto::keys::Signer;
+use crate::blockchain::block::{TransactionData, ValidatorAction, ValidatorInfo};
+use crate::blockchain::light_client::{certificate_message, MacroCertificate};
+use crate::blockchain::staking;
+use crate::zkp::AlbatrossZKVerifier;
+use crate::storage::StateTrie;
 
 /// Consensus message types for SP blockchain
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,8 +69,8 @@ pub enum ConsensusMessage {
         block_hash: Blake2bHash,
         round: u64,
         height: u64,
-        #[serde(serialize_with = "serialize_peer_id_vec", deserialize_with = "deserialize_peer_id_vec")]
-        signatures: Vec<(PeerId, Vec<u8>)>,
+        /// Aggregated precommits of two thirds of the stake
+        certificate: MacroCertificate,
     },
 
     /// View change/timeout
@@ -92,6 +80,7 @@ pub enum ConsensusMessage {
         #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
         requester_id: PeerId,
         reason: ViewChangeReason,
+        signature: Vec<u8>,
     },
 
     /// Synchronization request
@@ -118,6 +107,51 @@ pub enum ViewChangeReason {
     NetworkPartition,
 }
 
+/// Interval the round timer checks phase timeouts at
+pub const ROUND_TIMER_TICK: Duration = Duration::from_millis(100);
+
+/// How long each phase waits before the validator votes to change the view
+/// Every round a height fails to commit adds `increment`, so slow networks eventually make progress
+#[derive(Debug, Clone)]
+pub struct RoundTimeouts {
+    pub propose: Duration,
+    pub prevote: Duration,
+    pub precommit: Duration,
+    pub increment: Duration,
+}
+
+impl Default for RoundTimeouts {
+    fn default() -> Self {
+        Self {
+            propose: Duration::from_secs(3),
+            prevote: Duration::from_secs(1),
+            precommit: Duration::from_secs(1),
+            increment: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RoundTimeouts {
+    /// Timeout of `phase` after `failed_rounds` rounds at the height, none once committing
+    pub fn for_phase(&self, phase: &ConsensusPhase, failed_rounds: u32) -> Option<Duration> {
+        let base = match phase {
+            ConsensusPhase::Propose => self.propose,
+            ConsensusPhase::PreVote => self.prevote,
+            ConsensusPhase::PreCommit => self.precommit,
+            ConsensusPhase::Commit => return None,
+        };
+        Some(base + self.increment * failed_rounds)
+    }
+}
+
+/// Bytes a validator signs to vote for leaving `round` at `height`
+pub(crate) fn view_change_message(height: u64, round: u64) -> Vec<u8> {
+    let mut message = height.to_le_bytes().to_vec();
+    message.extend_from_slice(&round.to_le_bytes());
+    message.extend_from_slice(b"viewchange");
+    message
+}
+
 /// Consensus state for tracking rounds and votes
 #[derive(Debug, Clone)]
 pub struct ConsensusState {
@@ -127,8 +161,90 @@ pub struct ConsensusState {
     pub proposed_block: Option<Block>,
     pub pre_votes: HashMap<PeerId, Blake2bHash>,
     pub pre_commits: HashMap<PeerId, Blake2bHash>,
+    /// Verified precommit signatures over the certificate message, aggregated on commit
+    pub precommit_signatures: HashMap<PeerId, BLSSignature>,
     pub validators: HashSet<PeerId>,
     pub validator_weights: HashMap<PeerId, u64>,
+    /// When the current phase began, its timeout runs from here
+    pub phase_started_at: Instant,
+    /// Rounds at the current height that ended in a view change
+    pub rounds_at_height: u32,
+    /// Validators that voted to leave the current round
+    pub view_changes: HashSet<PeerId>,
+    /// Block prevoted by a quorum, the only one the validator proposes or prevotes until the height commits
+    pub locked_block: Option<Block>,
+    pub locked_round: Option<u64>,
+    /// Validators seen in later rounds of the height, and whether each voted to leave that round
+    pub later_rounds: BTreeMap<u64, HashMap<PeerId, bool>>,
+}
+
+impl ConsensusState {
+    /// Voting power of a validator, its stake once any validator has stake bonded
+    pub fn voting_power(&self, validator: &PeerId) -> u64 {
+        let total_stake = self.validators.iter().map(|peer| self.stake(peer)).sum();
+        staking::voting_power(self.stake(validator), total_stake)
+    }
+
+    fn stake(&self, validator: &PeerId) -> u64 {
+        self.validator_weights.get(validator).copied().unwrap_or(0)
+    }
+
+    /// Combined voting power of the validators that voted for `block_hash`
+    fn votes_for(&self, votes: &HashMap<PeerId, Blake2bHash>, block_hash: &Blake2bHash) -> u64 {
+        votes.iter()
+            .filter(|(_, hash)| *hash == block_hash)
+            .map(|(voter, _)| self.voting_power(voter))
+            .sum()
+    }
+
+    fn enter_phase(&mut self, phase: ConsensusPhase) {
+        self.phase = phase;
+        self.phase_started_at = Instant::now();
+    }
+
+    fn reset_round(&mut self) {
+        self.enter_phase(ConsensusPhase::Propose);
+        self.proposed_block = None;
+        self.pre_votes.clear();
+        self.pre_commits.clear();
+        self.precommit_signatures.clear();
+        self.view_changes.clear();
+    }
+
+    /// Move to the next height after a commit, locks only hold within a height
+    fn start_new_height(&mut self) {
+        self.current_round += 1;
+        self.current_height += 1;
+        self.rounds_at_height = 0;
+        self.locked_block = None;
+        self.locked_round = None;
+        self.later_rounds.clear();
+        self.reset_round();
+
+        info!("Starting new round {} at height {}", self.current_round, self.current_height);
+    }
+
+    /// Give the height another round under the next proposer, keeping the lock
+    fn advance_round(&mut self) {
+        self.enter_round(self.current_round + 1);
+    }
+
+    /// Move to the later `round` of the height, counting the votes to leave it that arrived early
+    fn enter_round(&mut self, round: u64) {
+        self.rounds_at_height += (round - self.current_round) as u32;
+        self.current_round = round;
+        self.reset_round();
+        let seen = self.later_rounds.remove(&round).unwrap_or_default();
+        self.later_rounds.retain(|later, _| *later > round);
+        self.view_changes = seen.into_iter().filter(|(_, leaving)| *leaving).map(|(peer, _)| peer).collect();
+        crate::metrics::metrics().view_changes.inc();
+
+        match &self.locked_block {
+            Some(locked) => info!("🔄 View change to round {} at height {}, locked on {:?} from round {:?}",
+                                  self.current_round, self.current_height, locked.hash(), self.locked_round),
+            None => info!("🔄 View change to round {} at height {}", self.current_round, self.current_height),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -147,12 +263,26 @@ pub struct ConsensusNetwork {
     local_peer_id: PeerId,
 
     // Consensus parameters
-    timeout_duration: std::time::Duration,
+    timeouts: RoundTimeouts,
     min_validators: usize,
 
-    // BLS cryptography for validator signatures
-    validator_private_key: BLSPrivateKey,
-    bls_verifier: BLSVerifier,
+    // BLS cryptography for validator signatures, the key held wherever the signer keeps it
+    signer: std::sync::Arc<dyn Signer>,
+    bls_verifier: RwLock<BLSVerifier>,
+    /// Validator addresses used in `ValidatorUpdate` transactions, by peer
+    validator_addresses: HashMap<Blake2bHash, PeerId>,
+
+    // ZK verification of aggregated block proofs
+    zk_verifier: Option<std::sync::Arc<AlbatrossZKVerifier>>,
+
+    // Committed state, block headers carry its root
+    state_trie: std::sync::Arc<std::sync::RwLock<StateTrie>>,
+
+    // Validators that announced a shutdown, skipped in proposer rotation
+    leaving_validators: std::sync::RwLock<HashSet<PeerId>>,
+
+    // Replay file inbound messages and fired timeouts are logged to
+    recorder: Option<std::sync::Arc<MessageRecorder>>,
 }
 
 impl ConsensusNetwork {
@@ -162,7 +292,7 @@ impl ConsensusNetwork {
         validators: HashSet<PeerId>,
         validator_weights: HashMap<PeerId, u64>,
         command_sender: broadcast::Sender<NetworkCommand>,
-        validator_private_key: BLSPrivateKey,
+        signer: std::sync::Arc<dyn Signer>,
         validator_public_keys: HashMap<PeerId, BLSPublicKey>,
     ) -> Self {
         let state = ConsensusState {
@@ -172,14 +302,23 @@ impl ConsensusNetwork {
             proposed_block: None,
             pre_votes: HashMap::new(),
             pre_commits: HashMap::new(),
+            precommit_signatures: HashMap::new(),
             validators,
             validator_weights,
+            phase_started_at: Instant::now(),
+            rounds_at_height: 0,
+            view_changes: HashSet::new(),
+            locked_block: None,
+            locked_round: None,
+            later_rounds: BTreeMap::new(),
         };
 
         // Initialize BLS verifier with validator public keys
         let mut bls_verifier = BLSVerifier::new();
+        let mut validator_addresses = HashMap::new();
         for (peer_id, public_key) in validator_public_keys {
             bls_verifier.register_operator(&peer_id.to_string(), public_key);
+            validator_addresses.insert(Self::validator_address(&peer_id), peer_id);
         }
 
         Self {
@@ -187,13 +326,64 @@ impl ConsensusNetwork {
             command_sender,
             network_id,
             local_peer_id,
-            timeout_duration: std::time::Duration::from_secs(30),
+            timeouts: RoundTimeouts::default(),
             min_validators: 3,
-            validator_private_key,
-            bls_verifier,
+            signer,
+            bls_verifier: RwLock::new(bls_verifier),
+            validator_addresses,
+            zk_verifier: None,
+            state_trie: std::sync::Arc::new(std::sync::RwLock::new(StateTrie::new())),
+            leaving_validators: std::sync::RwLock::new(HashSet::new()),
+            recorder: None,
         }
     }
 
+    /// Stop routing proposals to a validator that announced it is shutting down
+    /// Votes still count against the full validator set, so the quorum is unchanged
+    pub fn handle_node_leaving(&self, peer_id: PeerId) {
+        info!("👋 Validator {} is leaving, removed from proposer rotation", peer_id);
+        self.leaving_validators.write().unwrap().insert(peer_id);
+    }
+
+    /// A validator that left is back
+    pub fn handle_node_rejoined(&self, peer_id: &PeerId) {
+        self.leaving_validators.write().unwrap().remove(peer_id);
+    }
+
+    /// Validators taking turns proposing, without those that are leaving
+    fn proposer_rotation<'a>(&self, validators: &'a HashSet<PeerId>) -> Vec<&'a PeerId> {
+        let leaving = self.leaving_validators.read().unwrap();
+        validators.iter().filter(|validator| !leaving.contains(validator)).collect()
+    }
+
+    /// Override the default phase timeouts
+    pub fn set_timeouts(&mut self, timeouts: RoundTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Share the state trie with contract storage so state roots cover contract state
+    pub fn set_state_trie(&mut self, state_trie: std::sync::Arc<std::sync::RwLock<StateTrie>>) {
+        self.state_trie = state_trie;
+    }
+
+    /// State root after applying `transactions` of the block at `height` on top of the committed state
+    fn compute_state_root(&self, height: Height, transactions: &[crate::blockchain::block::Transaction]) -> Blake2bHash {
+        self.state_trie.write().unwrap().speculate(|state_trie| {
+            state_trie.apply_transactions(height, transactions);
+            state_trie.root()
+        })
+    }
+
+    /// Log inbound messages and fired timeouts for replay
+    pub fn set_recorder(&mut self, recorder: std::sync::Arc<MessageRecorder>) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Enable ZK proof verification during block validation
+    pub fn set_zk_verifier(&mut self, verifier: std::sync::Arc<AlbatrossZKVerifier>) {
+        self.zk_verifier = Some(verifier);
+    }
+
     /// Start consensus for a new block
     pub async fn start_consensus(&self, transactions: Vec<Transaction>) -> std::result::Result<(), BlockchainError> {
         let mut state = self.state.write().await;
@@ -211,20 +401,23 @@ impl ConsensusNetwork {
 
         info!("Starting consensus for round {} height {}", state.current_round, state.current_height);
 
-        // Create new block
-        let block = self.create_block(transactions, state.current_height).await?;
+        // A locked proposer re-proposes its locked block instead of a new one
+        let block = match state.locked_block.clone() {
+            Some(locked) => locked,
+            None => self.create_block(transactions, state.current_height).await?,
+        };
         let block_hash = block.hash();
 
         // Store proposed block
         state.proposed_block = Some(block.clone());
-        state.phase = ConsensusPhase::PreVote;
+        state.enter_phase(ConsensusPhase::PreVote);
 
         // Create message to sign (block hash + round)
         let mut message_to_sign = block_hash.as_bytes().to_vec();
         message_to_sign.extend_from_slice(&state.current_round.to_le_bytes());
 
-        // Sign with validator's BLS private key
-        let signature = self.validator_private_key.sign(&message_to_sign)
+        // Sign with validator's BLS key
+        let signature = self.signer.sign(&message_to_sign).await
             .map_err(|e| BlockchainError::Crypto(format!("Failed to sign proposal: {:?}", e)))?;
 
         // Broadcast proposal with real signature
@@ -242,6 +435,10 @@ impl ConsensusNetwork {
 
     /// Handle incoming consensus message
     pub async fn handle_consensus_message(&self, message: ConsensusMessage, from_peer: PeerId) -> std::result::Result<(), BlockchainError> {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Instant::now(), RecordedEvent::Consensus { from_peer, message: message.clone() });
+        }
+
         match message {
             ConsensusMessage::Propose { block, proposer_id, round, signature } => {
                 self.handle_proposal(block, proposer_id, round, signature, from_peer).await
@@ -255,12 +452,12 @@ impl ConsensusNetwork {
                 self.handle_pre_commit(block_hash, round, voter_id, signature).await
             }
 
-            ConsensusMessage::Commit { block_hash, round, height, signatures } => {
-                self.handle_commit(block_hash, round, height, signatures).await
+            ConsensusMessage::Commit { block_hash, round, height, certificate } => {
+                self.handle_commit(block_hash, round, height, certificate).await
             }
 
-            ConsensusMessage::ViewChange { round, height, requester_id, reason } => {
-                self.handle_view_change(round, height, requester_id, reason).await
+            ConsensusMessage::ViewChange { round, height, requester_id, reason, signature } => {
+                self.handle_view_change(round, height, requester_id, reason, signature).await
             }
 
             ConsensusMessage::SyncRequest { from_height, to_height, requester_id } => {
@@ -284,13 +481,8 @@ impl ConsensusNetwork {
     ) -> std::result::Result<(), BlockchainError> {
         let mut state = self.state.write().await;
 
-        if round != state.current_round {
-            debug!("Ignoring proposal for different round: {} vs {}", round, state.current_round);
-            return Ok(());
-        }
-
-        if state.phase != ConsensusPhase::Propose {
-            debug!("Not in propose phase, ignoring proposal");
+        if round < state.current_round {
+            debug!("Ignoring proposal for earlier round: {} vs {}", round, state.current_round);
             return Ok(());
         }
 
@@ -305,8 +497,9 @@ impl ConsensusNetwork {
         let mut message_to_verify = block_hash.as_bytes().to_vec();
         message_to_verify.extend_from_slice(&round.to_le_bytes());
 
-        let signature_valid = self.bls_verifier.verify_operator_signature(
+        let signature_valid = self.bls_verifier.read().await.verify_operator_signature_at(
             &proposer_id.to_string(),
+            state.current_height as Height,
             &message_to_verify,
             &signature,
         ).unwrap_or(false);
@@ -316,13 +509,32 @@ impl ConsensusNetwork {
             return Ok(());
         }
 
+        if round > state.current_round {
+            self.note_later_round(&mut state, round, proposer_id, false);
+            if round != state.current_round {
+                debug!("Proposal for later round {} while in round {}", round, state.current_round);
+                return Ok(());
+            }
+        }
+
+        if state.phase != ConsensusPhase::Propose {
+            debug!("Not in propose phase, ignoring proposal");
+            return Ok(());
+        }
+
         info!("Received valid signed proposal from {} for round {}", proposer_id, round);
 
+        // A locked validator prevotes nil on any block but the one it is locked on
+        let locked_elsewhere = state.locked_block.as_ref().is_some_and(|locked| locked.hash() != block_hash);
+        if locked_elsewhere {
+            debug!("Locked on another block at height {}, rejecting proposal", state.current_height);
+        }
+
         // Validate block
-        if self.validate_block(&block).await? {
+        if !locked_elsewhere && self.validate_block(&block).await? {
             // Accept proposal and move to pre-vote
             state.proposed_block = Some(block.clone());
-            state.phase = ConsensusPhase::PreVote;
+            state.enter_phase(ConsensusPhase::PreVote);
 
             let block_hash = block.hash();
 
@@ -331,7 +543,7 @@ impl ConsensusNetwork {
             prevote_message.extend_from_slice(&round.to_le_bytes());
             prevote_message.extend_from_slice(b"prevote");
 
-            let prevote_signature = self.validator_private_key.sign(&prevote_message)
+            let prevote_signature = self.signer.sign(&prevote_message).await
                 .map_err(|e| BlockchainError::Crypto(format!("Failed to sign pre-vote: {:?}", e)))?;
 
             // Send pre-vote with real BLS signature
@@ -369,7 +581,7 @@ impl ConsensusNetwork {
     ) -> std::result::Result<(), BlockchainError> {
         let mut state = self.state.write().await;
 
-        if round != state.current_round {
+        if round < state.current_round {
             return Ok(());
         }
 
@@ -383,8 +595,9 @@ impl ConsensusNetwork {
         prevote_message.extend_from_slice(&round.to_le_bytes());
         prevote_message.extend_from_slice(b"prevote");
 
-        let signature_valid = self.bls_verifier.verify_operator_signature(
+        let signature_valid = self.bls_verifier.read().await.verify_operator_signature_at(
             &voter_id.to_string(),
+            state.current_height as Height,
             &prevote_message,
             &signature,
         ).unwrap_or(false);
@@ -394,29 +607,46 @@ impl ConsensusNetwork {
             return Ok(());
         }
 
+        if round > state.current_round {
+            self.note_later_round(&mut state, round, voter_id, false);
+            if round != state.current_round {
+                return Ok(());
+            }
+        }
+
         // Record pre-vote
         state.pre_votes.insert(voter_id, block_hash);
 
         debug!("Received pre-vote from {} for block {:?}", voter_id, block_hash);
 
+        // A quorum prevoting another block in a later round moved on without the locked block,
+        // holding on to it would keep this validator from ever voting with them again
+        let locked = state.locked_block.as_ref().map(|locked| locked.hash());
+        if let (Some(locked_hash), Some(locked_round)) = (locked, state.locked_round) {
+            let polka = state.pre_votes.values()
+                .any(|hash| *hash != locked_hash && state.votes_for(&state.pre_votes, hash) >= self.required_votes(&state));
+            if round > locked_round && polka {
+                info!("🔓 Round {} reached a polka for another block, releasing the lock from round {}", round, locked_round);
+                state.locked_block = None;
+                state.locked_round = None;
+            }
+        }
+
         // Check if we have enough pre-votes for the proposed block
-        if let Some(ref proposed_block) = state.proposed_block {
+        if let Some(proposed_block) = state.proposed_block.clone() {
             let proposed_hash = proposed_block.hash();
-            let votes_for_block = state.pre_votes.values()
-                .filter(|&hash| *hash == proposed_hash)
-                .count();
+            let votes_for_block = state.votes_for(&state.pre_votes, &proposed_hash);
 
-            if votes_for_block >= self.required_votes(&state.validators) {
+            if votes_for_block >= self.required_votes(&state) && state.phase == ConsensusPhase::PreVote {
                 info!("Received sufficient pre-votes for block, moving to pre-commit");
 
-                state.phase = ConsensusPhase::PreCommit;
+                state.enter_phase(ConsensusPhase::PreCommit);
+                // Lock on the block, later rounds at this height carry it forward
+                state.locked_block = Some(proposed_block);
+                state.locked_round = Some(round);
 
-                // Create message to sign for pre-commit (block hash + round + "precommit")
-                let mut precommit_message = proposed_hash.as_bytes().to_vec();
-                precommit_message.extend_from_slice(&round.to_le_bytes());
-                precommit_message.extend_from_slice(b"precommit");
-
-                let precommit_signature = self.validator_private_key.sign(&precommit_message)
+                // Pre-commits sign the certificate message so they aggregate into the commit certificate
+                let precommit_signature = self.signer.sign(&certificate_message(&proposed_hash, round as u32)).await
                     .map_err(|e| BlockchainError::Crypto(format!("Failed to sign pre-commit: {:?}", e)))?;
 
                 // Send pre-commit with real BLS signature
@@ -444,7 +674,7 @@ impl ConsensusNetwork {
     ) -> std::result::Result<(), BlockchainError> {
         let mut state = self.state.write().await;
 
-        if round != state.current_round {
+        if round < state.current_round {
             return Ok(());
         }
 
@@ -454,57 +684,72 @@ impl ConsensusNetwork {
         }
 
         // Verify BLS signature on pre-commit
-        let mut precommit_message = block_hash.as_bytes().to_vec();
-        precommit_message.extend_from_slice(&round.to_le_bytes());
-        precommit_message.extend_from_slice(b"precommit");
-
-        let signature_valid = self.bls_verifier.verify_operator_signature(
+        let signature_valid = self.bls_verifier.read().await.verify_operator_signature_at(
             &voter_id.to_string(),
-            &precommit_message,
+            state.current_height as Height,
+            &certificate_message(&block_hash, round as u32),
             &signature,
         ).unwrap_or(false);
 
-        if !signature_valid {
-            warn!("Invalid BLS signature on pre-commit from {}", voter_id);
-            return Ok(());
+        let signature = match BLSSignature::from_bytes(&signature) {
+            Ok(signature) if signature_valid => signature,
+            _ => {
+                warn!("Invalid BLS signature on pre-commit from {}", voter_id);
+                return Ok(());
+            }
+        };
+
+        if round > state.current_round {
+            self.note_later_round(&mut state, round, voter_id, false);
+            if round != state.current_round {
+                return Ok(());
+            }
         }
 
         // Record pre-commit
         state.pre_commits.insert(voter_id, block_hash);
+        state.precommit_signatures.insert(voter_id, signature);
 
         debug!("Received pre-commit from {} for block {:?}", voter_id, block_hash);
 
         // Check if we have enough pre-commits
-        if let Some(ref proposed_block) = state.proposed_block.clone() {
+        if let Some(mut proposed_block) = state.proposed_block.clone() {
             let proposed_hash = proposed_block.hash();
-            let commits_for_block = state.pre_commits.values()
-                .filter(|&hash| *hash == proposed_hash)
-                .count();
+            let commits_for_block = state.votes_for(&state.pre_commits, &proposed_hash);
 
-            if commits_for_block >= self.required_votes(&state.validators) {
+            if commits_for_block >= self.required_votes(&state) {
                 info!("Received sufficient pre-commits, committing block");
 
-                // Collect signatures for commit message
-                let signatures: Vec<(PeerId, Vec<u8>)> = state.pre_commits.iter()
+                // Aggregate the pre-commit signatures by signer index
+                let order = Self::validator_order(&state.validators);
+                let signatures: Vec<(u16, BLSSignature)> = state.pre_commits.iter()
                     .filter(|(_, hash)| **hash == proposed_hash)
-                    .map(|(peer, _)| (*peer, vec![])) // Would include actual signatures
+                    .filter_map(|(peer, _)| Some((
+                        order.iter().position(|validator| validator == peer)? as u16,
+                        state.precommit_signatures.get(peer)?.clone(),
+                    )))
                     .collect();
+                let certificate = MacroCertificate::aggregate(proposed_hash, round as u32, signatures)?;
 
-                state.phase = ConsensusPhase::Commit;
+                state.enter_phase(ConsensusPhase::Commit);
 
                 // Broadcast commit
                 let commit = ConsensusMessage::Commit {
                     block_hash: proposed_hash,
                     round,
                     height: state.current_height,
-                    signatures,
+                    certificate: certificate.clone(),
                 };
 
                 self.broadcast_consensus_message(commit).await?;
 
                 // Apply block and move to next round
-                self.apply_block(proposed_block.clone()).await?;
-                self.start_new_round().await?;
+                if let Block::Macro(macro_block) = &mut proposed_block {
+                    macro_block.justification = Some(certificate);
+                }
+                self.apply_election(&mut state, &proposed_block);
+                self.apply_block(proposed_block).await?;
+                state.start_new_height();
             }
         }
 
@@ -517,7 +762,7 @@ impl ConsensusNetwork {
         block_hash: Blake2bHash,
         round: u64,
         height: u64,
-        _signatures: Vec<(PeerId, Vec<u8>)>,
+        certificate: MacroCertificate,
     ) -> std::result::Result<(), BlockchainError> {
         let mut state = self.state.write().await;
 
@@ -525,39 +770,151 @@ impl ConsensusNetwork {
             return Ok(());
         }
 
+        if certificate.block_hash != block_hash {
+            warn!("Commit of {:?} carries a certificate for {:?}", block_hash, certificate.block_hash);
+            return Ok(());
+        }
+        if let Err(e) = self.check_certificate(&state, &certificate).await {
+            warn!("Rejected commit of {:?}: {}", block_hash, e);
+            return Ok(());
+        }
+
         if let Some(ref proposed_block) = state.proposed_block {
             if proposed_block.hash() == block_hash {
                 info!("Block committed: {:?}", block_hash);
+                crate::metrics::metrics().blocks_committed.inc();
 
                 // Apply block and start new round
-                self.apply_block(proposed_block.clone()).await?;
-                self.start_new_round().await?;
+                let mut proposed_block = proposed_block.clone();
+                if let Block::Macro(macro_block) = &mut proposed_block {
+                    macro_block.justification = Some(certificate);
+                }
+                self.apply_election(&mut state, &proposed_block);
+                self.apply_block(proposed_block).await?;
+                state.start_new_height();
             }
         }
 
         Ok(())
     }
 
-    /// Handle view change
+    /// Count a vote to leave the round, which advances once two thirds of the stake voted
     async fn handle_view_change(
         &self,
         round: u64,
         height: u64,
         requester_id: PeerId,
         reason: ViewChangeReason,
+        signature: Vec<u8>,
     ) -> std::result::Result<(), BlockchainError> {
+        let mut state = self.state.write().await;
+
+        if round < state.current_round || height != state.current_height {
+            debug!("Ignoring view change for round {} height {}", round, height);
+            return Ok(());
+        }
+
+        if !state.validators.contains(&requester_id) {
+            warn!("View change from non-validator: {}", requester_id);
+            return Ok(());
+        }
+
+        let signature_valid = self.bls_verifier.read().await.verify_operator_signature_at(
+            &requester_id.to_string(),
+            state.current_height as Height,
+            &view_change_message(height, round),
+            &signature,
+        ).unwrap_or(false);
+
+        if !signature_valid {
+            warn!("Invalid BLS signature on view change from {}", requester_id);
+            return Ok(());
+        }
+
         info!("View change requested by {} for round {} height {}: {:?}",
               requester_id, round, height, reason);
+        if round > state.current_round {
+            // Catching up to the round counts the vote to leave it
+            self.note_later_round(&mut state, round, requester_id, true);
+            return Ok(());
+        }
+        state.view_changes.insert(requester_id);
+        self.advance_round_on_quorum(&mut state);
+
+        Ok(())
+    }
+
+    /// Record a validator's signed message for a later round of the height, catching up to that round
+    /// once more than a third of the stake is in it, as at least one of them is honest
+    fn note_later_round(&self, state: &mut ConsensusState, round: u64, validator: PeerId, leaving: bool) {
+        *state.later_rounds.entry(round).or_default().entry(validator).or_default() |= leaving;
+        let in_round: u64 = state.later_rounds[&round].keys().map(|peer| state.voting_power(peer)).sum();
+        if in_round >= self.catch_up_votes(state) {
+            info!("⏩ More than a third of the stake is in round {}, catching up from round {}", round, state.current_round);
+            state.enter_round(round);
+            self.advance_round_on_quorum(state);
+        }
+    }
 
-        // In a real implementation, we would:
-        // 1. Validate the view change request
-        // 2. Collect view change messages from other validators
-        // 3. Move to new round with new proposer
+    /// Vote to leave the round once the current phase ran past its timeout
+    pub async fn on_tick(&self, now: Instant) -> std::result::Result<(), BlockchainError> {
+        let mut state = self.state.write().await;
+
+        let Some(timeout) = self.timeouts.for_phase(&state.phase, state.rounds_at_height) else {
+            return Ok(());
+        };
+        if now < state.phase_started_at + timeout || state.view_changes.contains(&self.local_peer_id) {
+            return Ok(());
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.record(now, RecordedEvent::Timeout);
+        }
+
+        warn!("⏰ {:?} timed out in round {} at height {}", state.phase, state.current_round, state.current_height);
+
+        let signature = self.signer.sign(&view_change_message(state.current_height, state.current_round)).await
+            .map_err(|e| BlockchainError::Crypto(format!("Failed to sign view change: {:?}", e)))?;
+
+        let view_change = ConsensusMessage::ViewChange {
+            round: state.current_round,
+            height: state.current_height,
+            requester_id: self.local_peer_id,
+            reason: ViewChangeReason::Timeout,
+            signature: signature.to_bytes().to_vec(),
+        };
+        self.broadcast_consensus_message(view_change).await?;
+
+        state.view_changes.insert(self.local_peer_id);
+        self.advance_round_on_quorum(&mut state);
 
-        self.start_new_round().await?;
         Ok(())
     }
 
+    /// Share the consensus and start its round timer, replays fire timeouts through `on_tick` instead
+    pub fn spawn(self) -> std::sync::Arc<Self> {
+        let consensus = std::sync::Arc::new(self);
+        tokio::spawn(consensus.clone().run_round_timer());
+        consensus
+    }
+
+    /// Fire phase timeouts for as long as the task runs
+    pub async fn run_round_timer(self: std::sync::Arc<Self>) {
+        let mut interval = tokio::time::interval(ROUND_TIMER_TICK);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.on_tick(Instant::now()).await {
+                error!("Round timer failed: {}", e);
+            }
+        }
+    }
+
+    fn advance_round_on_quorum(&self, state: &mut ConsensusState) {
+        // Votes to leave the next round may have arrived early and already be a quorum
+        while state.view_changes.iter().map(|voter| state.voting_power(voter)).sum::<u64>() >= self.required_votes(state) {
+            state.advance_round();
+        }
+    }
+
     /// Handle sync request
     async fn handle_sync_request(
         &self,
@@ -604,8 +961,22 @@ impl ConsensusNetwork {
         info!("Sync response from {} with {} blocks, current height: {}",
               responder_id, blocks.len(), current_height);
 
-        // Process received blocks
+        // Macro blocks are only final with a certificate of the validators that voted on them
+        let mut state = self.state.write().await;
         for block in blocks {
+            if let Block::Macro(macro_block) = &block {
+                let justified = match &macro_block.justification {
+                    Some(certificate) if certificate.block_hash == block.hash() => {
+                        self.check_certificate(&state, certificate).await.is_ok()
+                    }
+                    _ => false,
+                };
+                if !justified {
+                    warn!("Synced macro block {} from {} is not justified, stopping sync", block.height(), responder_id);
+                    break;
+                }
+            }
+            self.apply_election(&mut state, &block);
             self.apply_block(block).await?;
         }
 
@@ -615,7 +986,7 @@ impl ConsensusNetwork {
     /// Check if this node is the proposer for the given round
     async fn is_proposer(&self, round: u64, validators: &HashSet<PeerId>) -> bool {
         // Simple round-robin proposer selection
-        let sorted_validators: Vec<_> = validators.iter().collect();
+        let sorted_validators = self.proposer_rotation(validators);
         if sorted_validators.is_empty() {
             return false;
         }
@@ -631,7 +1002,7 @@ impl ConsensusNetwork {
         }
 
         // Simple round-robin validation
-        let sorted_validators: Vec<_> = validators.iter().collect();
+        let sorted_validators = self.proposer_rotation(validators);
         if sorted_validators.is_empty() {
             return false;
         }
@@ -649,8 +1020,36 @@ impl ConsensusNetwork {
         // 4. ZK proofs for settlements
         // 5. Digital signatures
 
-        // For now, just basic validation
-        Ok(!block.transactions().is_empty())
+        if block.transactions().is_empty() {
+            return Ok(false);
+        }
+
+        let state_root = self.compute_state_root(block.height(), block.transactions());
+        if state_root != *block.state_root() {
+            warn!("❌ Block {} state root mismatch: header {}, computed {}", block.height(), block.state_root(), state_root);
+            return Ok(false);
+        }
+
+        // Each proof aggregate costs one multi-pairing regardless of proof count
+        for transaction in block.transactions() {
+            if let TransactionData::ProofAggregate(aggregate) = &transaction.data {
+                let verifier = match &self.zk_verifier {
+                    Some(verifier) => verifier,
+                    None => {
+                        warn!("No ZK verifier configured - skipping {} aggregated proofs", aggregate.len());
+                        continue;
+                    }
+                };
+
+                if !verifier.verify_aggregate_proof(aggregate)? {
+                    warn!("❌ Block {} has invalid {} proof aggregate", block.height(), aggregate.circuit_id);
+                    return Ok(false);
+                }
+                debug!("✅ Verified {} aggregated {} proofs", aggregate.len(), aggregate.circuit_id);
+            }
+        }
+
+        Ok(true)
     }
 
     /// Create a new block with given transactions
@@ -667,6 +1066,9 @@ impl ConsensusNetwork {
 
         // Return a placeholder block - this needs proper implementation
         // when we have the real block structure finalized
+        let body_transactions = vec![]; // Use empty for now, fix transaction types later
+        let state_root = self.compute_state_root(height as Height, &body_transactions);
+
         Ok(Block::Micro(crate::blockchain::MicroBlock {
             header: crate::blockchain::MicroHeader {
                 network: crate::primitives::NetworkId::new("SP", "Consortium"),
@@ -676,12 +1078,12 @@ impl ConsensusNetwork {
                 parent_hash: Blake2bHash::default(),
                 seed: Blake2bHash::from_bytes([0u8; 32]), // Simplified seed
                 extra_data: vec![],
-                state_root: Blake2bHash::default(),
+                state_root,
                 body_root: Blake2bHash::default(),
                 history_root: Blake2bHash::default(),
             },
             body: crate::blockchain::MicroBody {
-                transactions: vec![], // Use empty for now, fix transaction types later
+                transactions: body_transactions,
             },
         }))
     }
@@ -697,25 +1099,96 @@ impl ConsensusNetwork {
         // 4. Verify and store ZK proofs
         // 5. Update blockchain state
 
+        let height = block.height();
+        for transaction in block.transactions() {
+            if let TransactionData::ValidatorUpdate(update) = &transaction.data {
+                if let ValidatorAction::RotateSigningKey { new_signing_key, proof_of_possession } = &update.action {
+                    if let Err(e) = self.apply_key_rotation(&update.validator_address, new_signing_key, proof_of_possession, height).await {
+                        warn!("Rejected key rotation for validator {}: {}", update.validator_address.to_hex(), e);
+                    }
+                }
+            }
+        }
+
+        self.bls_verifier.write().await.advance_to_height(height);
+        self.state_trie.write().unwrap().apply_transactions(height, block.transactions());
+
         Ok(())
     }
 
-    /// Start a new consensus round
-    async fn start_new_round(&self) -> std::result::Result<(), BlockchainError> {
-        let mut state = self.state.write().await;
+    /// Weigh votes with the stake an election block elected validators with
+    /// Stake bonded or unbonded during an epoch only counts from the next election
+    fn apply_election(&self, state: &mut ConsensusState, block: &Block) {
+        let Block::Macro(macro_block) = block else {
+            return;
+        };
+        let Some(validators) = &macro_block.body.validators else {
+            return;
+        };
+        state.validator_weights = validators.iter()
+            .filter_map(|validator| self.validator_addresses.get(&validator.address).map(|peer| (*peer, validator.stake)))
+            .collect();
+        info!("⚖️  Election block {} sets stake of {} validators", block.height(), state.validator_weights.len());
+    }
 
-        state.current_round += 1;
-        state.current_height += 1;
-        state.phase = ConsensusPhase::Propose;
-        state.proposed_block = None;
-        state.pre_votes.clear();
-        state.pre_commits.clear();
+    /// Schedule a validator's announced signing key for the next election block
+    /// The current key keeps signing until then, so in-flight rounds are unaffected
+    async fn apply_key_rotation(
+        &self,
+        validator_address: &Blake2bHash,
+        new_signing_key: &[u8],
+        proof_of_possession: &[u8],
+        announced_at: Height,
+    ) -> std::result::Result<(), BlockchainError> {
+        let peer_id = self.validator_addresses.get(validator_address)
+            .ok_or_else(|| BlockchainError::Crypto("Key rotation for unknown validator".to_string()))?;
+
+        // Possession proof stops rogue-key attacks on aggregated signatures
+        let new_key = BLSPublicKey::from_bytes(new_signing_key)?;
+        let proof = BLSSignature::from_bytes(proof_of_possession)?;
+        if !proof.verify(&new_key, &key_rotation_message(validator_address, &new_key))? {
+            return Err(BlockchainError::InvalidSignature);
+        }
 
-        info!("Starting new round {} at height {}", state.current_round, state.current_height);
+        let activation_height = Policy::next_election_block(announced_at);
+        self.bls_verifier.write().await.schedule_key_rotation(&peer_id.to_string(), new_key, activation_height)?;
 
+        info!("🔑 Validator {} rotates signing key at election block {}", peer_id, activation_height);
         Ok(())
     }
 
+    /// Validators in certificate signer order, by validator address
+    fn validator_order(validators: &HashSet<PeerId>) -> Vec<PeerId> {
+        let mut order: Vec<PeerId> = validators.iter().copied().collect();
+        order.sort_by_key(|peer| Self::validator_address(peer).0);
+        order
+    }
+
+    /// Check that a commit certificate carries pre-commits of two thirds of the current stake
+    async fn check_certificate(&self, state: &ConsensusState, certificate: &MacroCertificate) -> std::result::Result<(), BlockchainError> {
+        let verifier = self.bls_verifier.read().await;
+        let validators: Vec<ValidatorInfo> = Self::validator_order(&state.validators).iter()
+            .map(|peer| ValidatorInfo {
+                address: Self::validator_address(peer),
+                signing_key: verifier.public_key_at(&peer.to_string(), state.current_height as Height)
+                    .map(|key| key.to_bytes().to_vec())
+                    .unwrap_or_default(),
+                voting_key: vec![],
+                reward_address: Self::validator_address(peer),
+                signal_data: None,
+                inactive_from: None,
+                jailed_from: None,
+                stake: state.stake(peer),
+            })
+            .collect();
+        certificate.verify(&validators)
+    }
+
+    /// Validator address of a peer, as used in `ValidatorUpdate` transactions
+    pub fn validator_address(peer_id: &PeerId) -> Blake2bHash {
+        Blake2bHash::from_data(&peer_id.to_bytes())
+    }
+
     /// Broadcast consensus message to all validators
     async fn broadcast_consensus_message(&self, message: ConsensusMessage) -> std::result::Result<(), BlockchainError> {
         let dummy_block = self.create_block(vec![], 0).await?;
@@ -734,9 +1207,14 @@ impl ConsensusNetwork {
         Ok(())
     }
 
-    /// Calculate required number of votes (2/3 + 1)
-    fn required_votes(&self, validators: &HashSet<PeerId>) -> usize {
-        (validators.len() * 2 / 3) + 1
+    /// Voting power needed to prevote or commit a block, two thirds of the validators' stake plus one
+    fn required_votes(&self, state: &ConsensusState) -> u64 {
+        staking::quorum(state.validators.iter().map(|validator| state.voting_power(validator)).sum())
+    }
+
+    /// Voting power that cannot all be faulty, a third of the validators' stake plus one
+    fn catch_up_votes(&self, state: &ConsensusState) -> u64 {
+        state.validators.iter().map(|validator| state.voting_power(validator)).sum::<u64>() / 3 + 1
     }
 
     /// Get current consensus state
@@ -774,6 +1252,7 @@ impl ConsensusNetwork {
 mod tests {
     use super::*;
     use tokio::sync::broadcast;
+    use crate::crypto::bls::BLSPrivateKey;
 
     #[tokio::test]
     async fn test_consensus_network() {
@@ -805,4 +1284,231 @@ mod tests {
         assert_eq!(state.current_round, 0);
         assert_eq!(state.phase, ConsensusPhase::Propose);
     }
+
+    #[tokio::test]
+    async fn test_leaving_validator_skipped_as_proposer() {
+        let (cmd_sender, _) = broadcast::channel(10);
+        let local = PeerId::random();
+        let leaving = PeerId::random();
+        let validators: HashSet<PeerId> = [local, leaving].into_iter().collect();
+
+        let consensus = ConsensusNetwork::new(
+            NetworkId::new("Test", "Network"),
+            local,
+            validators.clone(),
+            validators.iter().map(|peer| (*peer, 100)).collect(),
+            cmd_sender,
+            std::sync::Arc::new(BLSPrivateKey::generate().unwrap()),
+            HashMap::new(),
+        );
+
+        consensus.handle_node_leaving(leaving);
+        for round in 0..4 {
+            assert!(consensus.is_proposer(round, &validators).await);
+            assert!(!consensus.is_valid_proposer(leaving, round, &validators));
+        }
+
+        consensus.handle_node_rejoined(&leaving);
+        let proposers = futures::future::join_all((0..2).map(|round| consensus.is_proposer(round, &validators))).await;
+        assert_eq!(proposers.iter().filter(|is_local| **is_local).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quorum_weighted_by_stake() {
+        let (cmd_sender, _) = broadcast::channel(10);
+        let peers = [PeerId::random(), PeerId::random(), PeerId::random()];
+
+        let consensus = ConsensusNetwork::new(
+            NetworkId::new("Test", "Network"),
+            peers[0],
+            peers.iter().copied().collect(),
+            peers.iter().copied().zip([700, 200, 100]).collect(),
+            cmd_sender,
+            std::sync::Arc::new(BLSPrivateKey::generate().unwrap()),
+            HashMap::new(),
+        );
+
+        let state = consensus.get_state().await;
+        assert_eq!(consensus.required_votes(&state), 667);
+
+        // The largest validator alone is short of two thirds of the stake
+        let block_hash = Blake2bHash::from_data(b"block");
+        let mut votes = HashMap::from([(peers[0], block_hash)]);
+        assert!(state.votes_for(&votes, &block_hash) < consensus.required_votes(&state));
+        votes.insert(peers[2], block_hash);
+        assert_eq!(state.votes_for(&votes, &block_hash), 800);
+    }
+
+    #[test]
+    fn test_timeouts_grow_with_failed_rounds() {
+        let timeouts = RoundTimeouts::default();
+        assert_eq!(timeouts.for_phase(&ConsensusPhase::Propose, 0), Some(timeouts.propose));
+        assert_eq!(timeouts.for_phase(&ConsensusPhase::PreVote, 2), Some(timeouts.prevote + timeouts.increment * 2));
+        assert_eq!(timeouts.for_phase(&ConsensusPhase::Commit, 5), None);
+    }
+
+    #[tokio::test]
+    async fn test_view_change_needs_quorum_and_keeps_lock() {
+        let (cmd_sender, _receiver) = broadcast::channel(16);
+        let keys: Vec<BLSPrivateKey> = (0..4).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+
+        let consensus = ConsensusNetwork::new(
+            NetworkId::new("Test", "Network"),
+            peers[0],
+            peers.iter().copied().collect(),
+            peers.iter().map(|peer| (*peer, 100)).collect(),
+            cmd_sender,
+            std::sync::Arc::new(keys[0].clone()),
+            peers.iter().copied().zip(keys.iter().map(|key| key.public_key())).collect(),
+        );
+
+        let locked = consensus.create_block(vec![], 0).await.unwrap();
+        {
+            let mut state = consensus.state.write().await;
+            state.locked_block = Some(locked.clone());
+            state.locked_round = Some(0);
+        }
+
+        // Before the timeout nothing happens, after it the local vote is a quarter of the stake
+        consensus.on_tick(Instant::now()).await.unwrap();
+        assert!(consensus.get_state().await.view_changes.is_empty());
+        consensus.on_tick(Instant::now() + Duration::from_secs(60)).await.unwrap();
+        assert_eq!(consensus.get_state().await.view_changes.len(), 1);
+
+        // Unsigned votes don't count
+        let unsigned = ConsensusMessage::ViewChange {
+            round: 0, height: 0, requester_id: peers[1], reason: ViewChangeReason::Timeout, signature: vec![],
+        };
+        consensus.handle_consensus_message(unsigned, peers[1]).await.unwrap();
+        assert_eq!(consensus.get_state().await.view_changes.len(), 1);
+
+        for (peer, key) in peers.iter().zip(&keys).skip(1).take(2) {
+            assert_eq!(consensus.get_state().await.current_round, 0);
+            let view_change = ConsensusMessage::ViewChange {
+                round: 0,
+                height: 0,
+                requester_id: *peer,
+                reason: ViewChangeReason::Timeout,
+                signature: key.sign(&view_change_message(0, 0)).unwrap().to_bytes().to_vec(),
+            };
+            consensus.handle_consensus_message(view_change, *peer).await.unwrap();
+        }
+
+        let state = consensus.get_state().await;
+        assert_eq!((state.current_round, state.current_height, state.rounds_at_height), (1, 0, 1));
+        assert_eq!(state.phase, ConsensusPhase::Propose);
+        assert!(state.view_changes.is_empty());
+        assert_eq!(state.locked_block.map(|block| block.hash()), Some(locked.hash()));
+    }
+
+    #[tokio::test]
+    async fn test_catches_up_to_later_round_and_releases_lock_on_polka() {
+        let (cmd_sender, _receiver) = broadcast::channel(16);
+        let keys: Vec<BLSPrivateKey> = (0..4).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let consensus = ConsensusNetwork::new(
+            NetworkId::new("Test", "Network"),
+            peers[0],
+            peers.iter().copied().collect(),
+            peers.iter().map(|peer| (*peer, 100)).collect(),
+            cmd_sender,
+            std::sync::Arc::new(keys[0].clone()),
+            peers.iter().copied().zip(keys.iter().map(|key| key.public_key())).collect(),
+        );
+
+        let locked = consensus.create_block(vec![], 0).await.unwrap();
+        {
+            let mut state = consensus.state.write().await;
+            state.locked_block = Some(locked.clone());
+            state.locked_round = Some(0);
+        }
+        let other = Blake2bHash::from_data(b"other block");
+        let pre_vote = |signer: usize| {
+            let mut message = other.as_bytes().to_vec();
+            message.extend_from_slice(&2u64.to_le_bytes());
+            message.extend_from_slice(b"prevote");
+            ConsensusMessage::PreVote {
+                block_hash: other,
+                round: 2,
+                voter_id: peers[signer],
+                signature: keys[signer].sign(&message).unwrap().to_bytes().to_vec(),
+            }
+        };
+
+        // One validator in round 2 could be faulty, a second one is enough to follow them
+        let view_change = ConsensusMessage::ViewChange {
+            round: 2,
+            height: 0,
+            requester_id: peers[1],
+            reason: ViewChangeReason::Timeout,
+            signature: keys[1].sign(&view_change_message(0, 2)).unwrap().to_bytes().to_vec(),
+        };
+        consensus.handle_consensus_message(view_change, peers[1]).await.unwrap();
+        assert_eq!(consensus.get_state().await.current_round, 0);
+        consensus.handle_consensus_message(pre_vote(2), peers[2]).await.unwrap();
+        let state = consensus.get_state().await;
+        assert_eq!((state.current_round, state.rounds_at_height), (2, 2));
+        assert_eq!(state.view_changes, HashSet::from([peers[1]]));
+        assert_eq!(state.pre_votes.len(), 1);
+        assert!(state.locked_block.is_some());
+
+        // Two thirds prevoting another block in a later round release the lock
+        consensus.handle_consensus_message(pre_vote(1), peers[1]).await.unwrap();
+        assert!(consensus.get_state().await.locked_block.is_some());
+        consensus.handle_consensus_message(pre_vote(3), peers[3]).await.unwrap();
+        let state = consensus.get_state().await;
+        assert!(state.locked_block.is_none() && state.locked_round.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_precommits_aggregate_into_commit_certificate() {
+        let (cmd_sender, _receiver) = broadcast::channel(16);
+        let keys: Vec<BLSPrivateKey> = (0..4).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let consensus = ConsensusNetwork::new(
+            NetworkId::new("Test", "Network"),
+            peers[0],
+            peers.iter().copied().collect(),
+            peers.iter().map(|peer| (*peer, 100)).collect(),
+            cmd_sender,
+            std::sync::Arc::new(keys[0].clone()),
+            peers.iter().copied().zip(keys.iter().map(|key| key.public_key())).collect(),
+        );
+
+        let block = consensus.create_block(vec![], 0).await.unwrap();
+        let block_hash = block.hash();
+        let state = consensus.get_state().await;
+        let order = ConsensusNetwork::validator_order(&state.validators);
+        let signed_by = |signers: &[usize]| {
+            let signatures = signers.iter()
+                .map(|signer| {
+                    let index = order.iter().position(|peer| *peer == peers[*signer]).unwrap() as u16;
+                    (index, keys[*signer].sign(&certificate_message(&block_hash, 0)).unwrap())
+                })
+                .collect();
+            MacroCertificate::aggregate(block_hash, 0, signatures).unwrap()
+        };
+
+        // Three of four equal validators are a quorum, two are not
+        assert!(consensus.check_certificate(&state, &signed_by(&[0, 1, 3])).await.is_ok());
+        assert!(consensus.check_certificate(&state, &signed_by(&[1, 2])).await.is_err());
+
+        {
+            let mut state = consensus.state.write().await;
+            state.proposed_block = Some(block);
+            state.enter_phase(ConsensusPhase::PreCommit);
+        }
+        for signer in 1..4 {
+            assert_eq!(consensus.get_state().await.current_height, 0);
+            let pre_commit = ConsensusMessage::PreCommit {
+                block_hash,
+                round: 0,
+                voter_id: peers[signer],
+                signature: keys[signer].sign(&certificate_message(&block_hash, 0)).unwrap().to_bytes().to_vec(),
+            };
+            consensus.handle_consensus_message(pre_commit, peers[signer]).await.unwrap();
+        }
+        assert_eq!(consensus.get_state().await.current_height, 1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,157 @@
+// Counter-offer rounds of a bilateral settlement negotiation: the parties take turns revising
+// the amount, each revision citing the evidence it rests on. Offers within tolerance of each
+// other settle on the amount between them; past the last round the negotiation goes to dispute
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{Blake2bHash, NetworkId};
+
+/// How many counter-offers a negotiation allows and when two offers are close enough
+#[derive(Debug, Clone)]
+pub struct CounterOfferPolicy {
+    /// Counter-offers after the original proposal, escalated to dispute when the last is not agreed
+    pub max_rounds: u32,
+    /// Offers differing by at most this share of the larger one, in basis points, split the difference
+    pub tolerance_bps: u32,
+}
+
+impl Default for CounterOfferPolicy {
+    fn default() -> Self {
+        Self {
+            max_rounds: 3,
+            tolerance_bps: 200, // 2%
+        }
+    }
+}
+
+/// One offer, round 0 being the original proposal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfferRound {
+    pub round: u32,
+    pub proposer: NetworkId,
+    pub amount_cents: u64,
+    /// Hash of the evidence document supporting the revised amount
+    pub evidence_hash: Option<Blake2bHash>,
+}
+
+/// What receiving a counter-offer leads to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CounterOfferDecision {
+    /// The offer was received before, nothing changes
+    Stale,
+    /// The offer is close enough to ours, both settle on this amount
+    Converged(u64),
+    /// The offer stands until answered with another one or accepted
+    Countered,
+    /// The last round passed without agreement
+    Escalate,
+}
+
+impl CounterOfferPolicy {
+    /// Whether two offers differ by at most the tolerance
+    pub fn within_tolerance(&self, a: u64, b: u64) -> bool {
+        let difference = a.abs_diff(b) as u128;
+        difference * 10_000 <= a.max(b) as u128 * self.tolerance_bps as u128
+    }
+
+    /// Amount halfway between two offers, rounded down
+    pub fn split_difference(a: u64, b: u64) -> u64 {
+        a.min(b) + a.abs_diff(b) / 2
+    }
+
+    /// Decide on `incoming`, `rounds` being the offers so far starting with the original proposal
+    pub fn decide(&self, rounds: &[OfferRound], incoming: &OfferRound) -> Result<CounterOfferDecision, String> {
+        let last = rounds.last().ok_or("No offer to counter")?;
+        let expected = rounds.len() as u32;
+        if incoming.round < expected {
+            return Ok(CounterOfferDecision::Stale);
+        }
+        if incoming.round > expected {
+            return Err(format!("Counter-offer for round {} while round {} is open", incoming.round, expected));
+        }
+        if incoming.round > self.max_rounds {
+            return Err(format!("Counter-offer past the last round {}", self.max_rounds));
+        }
+        if incoming.proposer == last.proposer {
+            return Err(format!("{} countered its own offer", incoming.proposer));
+        }
+
+        if self.within_tolerance(last.amount_cents, incoming.amount_cents) {
+            return Ok(CounterOfferDecision::Converged(Self::split_difference(last.amount_cents, incoming.amount_cents)));
+        }
+        if incoming.round == self.max_rounds {
+            return Ok(CounterOfferDecision::Escalate);
+        }
+        Ok(CounterOfferDecision::Countered)
+    }
+
+    /// Next offer of `proposer`, answering the last one in `rounds`
+    pub fn next_round(
+        &self,
+        rounds: &[OfferRound],
+        proposer: NetworkId,
+        amount_cents: u64,
+        evidence_hash: Option<Blake2bHash>,
+    ) -> Result<OfferRound, String> {
+        let last = rounds.last().ok_or("No offer to counter")?;
+        if last.proposer == proposer {
+            return Err("The last offer is our own, waiting for an answer".to_string());
+        }
+        let round = rounds.len() as u32;
+        if round > self.max_rounds {
+            return Err(format!("All {} counter-offer rounds are used", self.max_rounds));
+        }
+        Ok(OfferRound { round, proposer, amount_cents, evidence_hash })
+    }
+
+    /// Whether an acceptance at `amount_cents` is consistent with the last offers
+    /// An offer is accepted as is, or converged to an amount between the last two
+    pub fn is_agreed_amount(rounds: &[OfferRound], amount_cents: u64) -> bool {
+        match rounds {
+            [] => false,
+            [.., last] if last.amount_cents == amount_cents => true,
+            [.., previous, last] => {
+                let (low, high) = (previous.amount_cents.min(last.amount_cents), previous.amount_cents.max(last.amount_cents));
+                (low..=high).contains(&amount_cents)
+            }
+            [_] => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(round: u32, proposer: &NetworkId, amount_cents: u64) -> OfferRound {
+        OfferRound { round, proposer: proposer.clone(), amount_cents, evidence_hash: None }
+    }
+
+    #[test]
+    fn test_rounds_converge_or_escalate() {
+        let policy = CounterOfferPolicy::default();
+        let creditor = NetworkId::new("Vodafone", "UK");
+        let debtor = NetworkId::new("Orange", "FR");
+        let mut rounds = vec![offer(0, &creditor, 100_000)];
+
+        // Far apart, the counter-offer stands
+        let counter = offer(1, &debtor, 90_000);
+        assert_eq!(policy.decide(&rounds, &counter).unwrap(), CounterOfferDecision::Countered);
+        rounds.push(counter.clone());
+        assert_eq!(policy.decide(&rounds, &counter).unwrap(), CounterOfferDecision::Stale);
+        assert!(policy.decide(&rounds, &offer(3, &creditor, 95_000)).is_err());
+        assert!(policy.next_round(&rounds, debtor.clone(), 91_000, None).is_err());
+
+        // Within 2% the difference is split
+        let close = policy.next_round(&rounds, creditor.clone(), 91_000, None).unwrap();
+        assert_eq!(close.round, 2);
+        assert_eq!(policy.decide(&rounds, &close).unwrap(), CounterOfferDecision::Converged(90_500));
+        assert!(CounterOfferPolicy::is_agreed_amount(&[rounds.clone(), vec![close]].concat(), 90_500));
+        assert!(!CounterOfferPolicy::is_agreed_amount(&rounds, 80_000));
+
+        // Still apart after the last round
+        rounds.push(offer(2, &creditor, 98_000));
+        assert_eq!(policy.decide(&rounds, &offer(3, &debtor, 85_000)).unwrap(), CounterOfferDecision::Escalate);
+        rounds.push(offer(3, &debtor, 85_000));
+        assert!(policy.next_round(&rounds, creditor, 97_000, None).is_err());
+    }
+}
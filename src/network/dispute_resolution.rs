@@ -0,0 +1,542 @@
+// Dispute resolution workflow for SP settlements
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{Blake2bHash, NetworkId, BlockchainError, to_canonical_bytes};
+use crate::crypto::bls::{BLSPublicKey, BLSSignature};
+use crate::storage::ChainStore;
+use crate::network::settlement_messaging::DisputeReason;
+
+/// Dispute lifecycle: Opened → EvidenceSubmitted → ArbitrationVote → Resolved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DisputeState {
+    Opened,
+    EvidenceSubmitted,
+    ArbitrationVote,
+    Resolved(DisputeOutcome),
+}
+
+/// Final outcome of an arbitrated dispute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DisputeOutcome {
+    /// Dispute upheld, settlement amount replaced by the arbitrated amount
+    Upheld { adjusted_amount: u64 },
+    /// Dispute rejected, original settlement amount stands
+    Rejected,
+}
+
+/// Validator verdict on a dispute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DisputeVerdict {
+    Uphold { adjusted_amount: u64 },
+    Reject,
+}
+
+/// Evidence document reference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceRecord {
+    pub evidence_hash: Blake2bHash,
+    pub submitter: NetworkId,
+    pub submitted_at: u64,
+}
+
+/// Settlement dispute tracked by the arbitration process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dispute {
+    pub dispute_id: Blake2bHash,
+    pub settlement_id: Blake2bHash,
+    pub reason: DisputeReason,
+    pub initiator: NetworkId,
+    /// Other party of the disputed settlement, when known
+    #[serde(default)]
+    pub respondent: Option<NetworkId>,
+    pub original_amount: u64,
+    pub disputed_amount: Option<u64>,
+    pub evidence: Vec<EvidenceRecord>,
+    pub votes: HashMap<NetworkId, DisputeVerdict>,
+    pub state: DisputeState,
+    pub opened_at: u64,
+    pub resolved_at: Option<u64>,
+}
+
+impl Dispute {
+    /// Check if dispute has reached a final outcome
+    pub fn is_resolved(&self) -> bool {
+        matches!(self.state, DisputeState::Resolved(_))
+    }
+
+    /// Get final outcome, if any
+    pub fn outcome(&self) -> Option<&DisputeOutcome> {
+        match &self.state {
+            DisputeState::Resolved(outcome) => Some(outcome),
+            _ => None,
+        }
+    }
+
+    /// Whether `network` is a party to the dispute, who cannot arbitrate it
+    pub fn is_party(&self, network: &NetworkId) -> bool {
+        *network == self.initiator || self.respondent.as_ref() == Some(network)
+    }
+}
+
+/// Dispute manager driving the arbitration state machine
+pub struct DisputeManager {
+    chain_store: Arc<dyn ChainStore>,
+    disputes: RwLock<HashMap<Blake2bHash, Dispute>>,
+    arbitrators: RwLock<HashMap<NetworkId, BLSPublicKey>>,
+    party_keys: RwLock<HashMap<NetworkId, BLSPublicKey>>,
+}
+
+impl DisputeManager {
+    pub fn new(chain_store: Arc<dyn ChainStore>) -> Self {
+        Self {
+            chain_store,
+            disputes: RwLock::new(HashMap::new()),
+            arbitrators: RwLock::new(HashMap::new()),
+            party_keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Register a validator allowed to vote on dispute outcomes, with the key its votes are signed with
+    pub async fn register_arbitrator(&self, validator: NetworkId, key: BLSPublicKey) {
+        self.arbitrators.write().await.insert(validator, key);
+    }
+
+    /// Register the key an operator signs the evidence it submits to its disputes with
+    pub async fn register_party_key(&self, operator: NetworkId, key: BLSPublicKey) {
+        self.party_keys.write().await.insert(operator, key);
+    }
+
+    /// Remove a validator from the arbitration set
+    pub async fn remove_arbitrator(&self, validator: &NetworkId) {
+        self.arbitrators.write().await.remove(validator);
+    }
+
+    /// Open a new dispute for a settlement
+    pub async fn open_dispute(
+        &self,
+        settlement_id: Blake2bHash,
+        reason: DisputeReason,
+        disputed_amount: Option<u64>,
+        original_amount: u64,
+        initiator: NetworkId,
+        respondent: Option<NetworkId>,
+    ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        let dispute_id = Self::dispute_id(&settlement_id, &initiator);
+
+        let mut disputes = self.disputes.write().await;
+        if let Some(existing) = disputes.get(&dispute_id) {
+            if !existing.is_resolved() {
+                return Err(BlockchainError::InvalidState(
+                    format!("Dispute already open for settlement {}", settlement_id)
+                ));
+            }
+        }
+
+        let dispute = Dispute {
+            dispute_id,
+            settlement_id,
+            reason,
+            initiator: initiator.clone(),
+            respondent,
+            original_amount,
+            disputed_amount,
+            evidence: Vec::new(),
+            votes: HashMap::new(),
+            state: DisputeState::Opened,
+            opened_at: chrono::Utc::now().timestamp() as u64,
+            resolved_at: None,
+        };
+
+        info!("⚖️  Dispute {} opened by {} for settlement {}", dispute_id, initiator, settlement_id);
+        disputes.insert(dispute_id, dispute);
+
+        Ok(dispute_id)
+    }
+
+    /// Store an evidence document in the chain store and attach it to a dispute
+    pub async fn submit_evidence(
+        &self,
+        dispute_id: &Blake2bHash,
+        submitter: NetworkId,
+        document: &[u8],
+    ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        let evidence_hash = self.chain_store.put_evidence(document).await?;
+        self.attach_evidence(dispute_id, submitter, evidence_hash).await?;
+        Ok(evidence_hash)
+    }
+
+    /// Attach evidence already stored under its content hash to a dispute
+    pub async fn attach_evidence(
+        &self,
+        dispute_id: &Blake2bHash,
+        submitter: NetworkId,
+        evidence_hash: Blake2bHash,
+    ) -> std::result::Result<(), BlockchainError> {
+        let mut disputes = self.disputes.write().await;
+        let dispute = disputes.get_mut(dispute_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Dispute {} not found", dispute_id)))?;
+
+        match dispute.state {
+            DisputeState::Opened | DisputeState::EvidenceSubmitted => {}
+            _ => {
+                return Err(BlockchainError::InvalidState(
+                    format!("Dispute {} no longer accepts evidence ({:?})", dispute_id, dispute.state)
+                ));
+            }
+        }
+
+        if dispute.evidence.iter().any(|e| e.evidence_hash == evidence_hash) {
+            debug!("Evidence {} already attached to dispute {}", evidence_hash, dispute_id);
+            return Ok(());
+        }
+
+        dispute.evidence.push(EvidenceRecord {
+            evidence_hash,
+            submitter: submitter.clone(),
+            submitted_at: chrono::Utc::now().timestamp() as u64,
+        });
+        dispute.state = DisputeState::EvidenceSubmitted;
+
+        info!("📎 Evidence {} submitted by {} for dispute {}", evidence_hash, submitter, dispute_id);
+        Ok(())
+    }
+
+    /// Attach evidence a peer submitted, signed over `evidence_message` by a party to the dispute
+    pub async fn attach_signed_evidence(
+        &self,
+        dispute_id: &Blake2bHash,
+        submitter: NetworkId,
+        evidence_hash: Blake2bHash,
+        signature: &[u8],
+    ) -> std::result::Result<(), BlockchainError> {
+        {
+            let disputes = self.disputes.read().await;
+            let dispute = disputes.get(dispute_id)
+                .ok_or_else(|| BlockchainError::NotFound(format!("Dispute {} not found", dispute_id)))?;
+            if !dispute.is_party(&submitter) {
+                return Err(BlockchainError::Consensus(
+                    format!("{} is not a party to dispute {} and cannot submit evidence", submitter, dispute_id)
+                ));
+            }
+        }
+
+        {
+            let party_keys = self.party_keys.read().await;
+            let key = party_keys.get(&submitter).ok_or_else(|| BlockchainError::Consensus(
+                format!("{} has no registered key to sign evidence with", submitter)
+            ))?;
+            let signature = BLSSignature::from_bytes(signature)
+                .map_err(|_| BlockchainError::InvalidSignature)?;
+            if !signature.verify(key, &Self::evidence_message(dispute_id, &evidence_hash)?)? {
+                return Err(BlockchainError::InvalidSignature);
+            }
+        }
+
+        self.attach_evidence(dispute_id, submitter, evidence_hash).await
+    }
+
+    /// Payload a party signs evidence it submits to a dispute over
+    pub fn evidence_message(dispute_id: &Blake2bHash, evidence_hash: &Blake2bHash) -> std::result::Result<Vec<u8>, BlockchainError> {
+        to_canonical_bytes(&("dispute-evidence", dispute_id, evidence_hash))
+    }
+
+    /// Load an evidence document from the chain store
+    pub async fn get_evidence(&self, evidence_hash: &Blake2bHash) -> std::result::Result<Option<Vec<u8>>, BlockchainError> {
+        self.chain_store.get_evidence(evidence_hash).await
+    }
+
+    /// Close the evidence phase and open validator voting
+    pub async fn begin_arbitration(&self, dispute_id: &Blake2bHash) -> std::result::Result<(), BlockchainError> {
+        let mut disputes = self.disputes.write().await;
+        let dispute = disputes.get_mut(dispute_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Dispute {} not found", dispute_id)))?;
+
+        if dispute.state != DisputeState::EvidenceSubmitted {
+            return Err(BlockchainError::InvalidState(
+                format!("Dispute {} cannot enter arbitration from {:?}", dispute_id, dispute.state)
+            ));
+        }
+
+        dispute.state = DisputeState::ArbitrationVote;
+        info!("🗳️  Arbitration vote started for dispute {}", dispute_id);
+        Ok(())
+    }
+
+    /// Record a validator vote signed over `vote_message`; returns the outcome once the dispute is resolved
+    /// The parties to the dispute do not arbitrate it
+    pub async fn cast_vote(
+        &self,
+        dispute_id: &Blake2bHash,
+        validator: NetworkId,
+        verdict: DisputeVerdict,
+        signature: &[u8],
+    ) -> std::result::Result<Option<DisputeOutcome>, BlockchainError> {
+        let arbitrators = self.arbitrators.read().await;
+        let mut disputes = self.disputes.write().await;
+        let dispute = disputes.get_mut(dispute_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Dispute {} not found", dispute_id)))?;
+        Self::check_vote(&arbitrators, dispute, &validator, &verdict, signature)?;
+
+        if dispute.state != DisputeState::ArbitrationVote {
+            return Err(BlockchainError::InvalidState(
+                format!("Dispute {} is not open for voting ({:?})", dispute_id, dispute.state)
+            ));
+        }
+
+        if dispute.votes.contains_key(&validator) {
+            return Err(BlockchainError::Consensus(
+                format!("{} already voted on dispute {}", validator, dispute_id)
+            ));
+        }
+
+        debug!("Vote from {} on dispute {}: {:?}", validator, dispute_id, verdict);
+        dispute.votes.insert(validator, verdict);
+
+        let arbitrator_count = arbitrators.keys().filter(|arbitrator| !dispute.is_party(arbitrator)).count();
+        let outcome = Self::tally_votes(dispute, arbitrator_count);
+        if let Some(outcome) = &outcome {
+            dispute.state = DisputeState::Resolved(outcome.clone());
+            dispute.resolved_at = Some(chrono::Utc::now().timestamp() as u64);
+            info!("✅ Dispute {} resolved: {:?}", dispute_id, outcome);
+        }
+
+        Ok(outcome)
+    }
+
+    /// Check a vote is signed by a registered arbitrator not party to the dispute, without recording it
+    pub async fn verify_vote(
+        &self,
+        dispute_id: &Blake2bHash,
+        validator: &NetworkId,
+        verdict: &DisputeVerdict,
+        signature: &[u8],
+    ) -> std::result::Result<(), BlockchainError> {
+        let arbitrators = self.arbitrators.read().await;
+        let disputes = self.disputes.read().await;
+        let dispute = disputes.get(dispute_id)
+            .ok_or_else(|| BlockchainError::NotFound(format!("Dispute {} not found", dispute_id)))?;
+        Self::check_vote(&arbitrators, dispute, validator, verdict, signature)
+    }
+
+    fn check_vote(
+        arbitrators: &HashMap<NetworkId, BLSPublicKey>,
+        dispute: &Dispute,
+        validator: &NetworkId,
+        verdict: &DisputeVerdict,
+        signature: &[u8],
+    ) -> std::result::Result<(), BlockchainError> {
+        let key = arbitrators.get(validator).ok_or_else(|| BlockchainError::Consensus(
+            format!("{} is not an arbitrating validator", validator)
+        ))?;
+
+        if dispute.is_party(validator) {
+            return Err(BlockchainError::Consensus(
+                format!("{} is a party to dispute {} and cannot arbitrate it", validator, dispute.dispute_id)
+            ));
+        }
+
+        let signature = BLSSignature::from_bytes(signature)
+            .map_err(|_| BlockchainError::InvalidSignature)?;
+        if !signature.verify(key, &Self::vote_message(&dispute.dispute_id, verdict)?)? {
+            return Err(BlockchainError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    /// Payload an arbitrator signs its verdict on a dispute over
+    pub fn vote_message(dispute_id: &Blake2bHash, verdict: &DisputeVerdict) -> std::result::Result<Vec<u8>, BlockchainError> {
+        to_canonical_bytes(&("dispute-vote", dispute_id, verdict))
+    }
+
+    /// Get dispute by ID
+    pub async fn get_dispute(&self, dispute_id: &Blake2bHash) -> Option<Dispute> {
+        self.disputes.read().await.get(dispute_id).cloned()
+    }
+
+    /// Get open dispute for a settlement, if any
+    pub async fn find_by_settlement(&self, settlement_id: &Blake2bHash) -> Option<Dispute> {
+        self.disputes.read().await.values()
+            .find(|d| &d.settlement_id == settlement_id && !d.is_resolved())
+            .cloned()
+    }
+
+    /// Get all disputes that have not been resolved yet
+    pub async fn get_open_disputes(&self) -> Vec<Dispute> {
+        self.disputes.read().await.values()
+            .filter(|d| !d.is_resolved())
+            .cloned()
+            .collect()
+    }
+
+    /// Deterministic dispute ID derived from settlement and initiator
+    pub fn dispute_id(settlement_id: &Blake2bHash, initiator: &NetworkId) -> Blake2bHash {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"dispute");
+        data.extend_from_slice(settlement_id.as_bytes());
+        data.extend_from_slice(initiator.to_string().as_bytes());
+        crate::primitives::primitives::hash_data(&data)
+    }
+
+    /// Decide outcome once a 2/3+1 supermajority is reached either way
+    fn tally_votes(dispute: &Dispute, arbitrator_count: usize) -> Option<DisputeOutcome> {
+        let required = arbitrator_count * 2 / 3 + 1;
+
+        let mut upheld_amounts: Vec<u64> = dispute.votes.values()
+            .filter_map(|v| match v {
+                DisputeVerdict::Uphold { adjusted_amount } => Some(*adjusted_amount),
+                DisputeVerdict::Reject => None,
+            })
+            .collect();
+        let reject_votes = dispute.votes.len() - upheld_amounts.len();
+
+        if upheld_amounts.len() >= required {
+            // Median of proposed amounts so a single outlier cannot skew the adjustment
+            upheld_amounts.sort_unstable();
+            let adjusted_amount = upheld_amounts[(upheld_amounts.len() - 1) / 2];
+            return Some(DisputeOutcome::Upheld { adjusted_amount });
+        }
+
+        if reject_votes >= required {
+            return Some(DisputeOutcome::Rejected);
+        }
+
+        // Neither side can reach the threshold any more: original amount stands
+        let remaining = arbitrator_count.saturating_sub(dispute.votes.len());
+        if upheld_amounts.len() + remaining < required && reject_votes + remaining < required {
+            warn!("Dispute {} deadlocked, keeping original settlement amount", dispute.dispute_id);
+            return Some(DisputeOutcome::Rejected);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::BLSPrivateKey;
+    use crate::storage::{MdbxChainStore, SimpleChainStore};
+    use tempfile::TempDir;
+
+    async fn manager_with_arbitrators() -> (DisputeManager, HashMap<NetworkId, BLSPrivateKey>) {
+        let manager = DisputeManager::new(Arc::new(SimpleChainStore::new()));
+        let mut keys = HashMap::new();
+        for (name, country) in [("T-Mobile", "DE"), ("Vodafone", "UK"), ("Orange", "FR"), ("Telefonica", "ES"), ("KPN", "NL")] {
+            let key = BLSPrivateKey::generate().unwrap();
+            manager.register_arbitrator(NetworkId::new(name, country), key.public_key()).await;
+            keys.insert(NetworkId::new(name, country), key);
+        }
+        (manager, keys)
+    }
+
+    fn signed(key: &BLSPrivateKey, dispute_id: &Blake2bHash, verdict: &DisputeVerdict) -> Vec<u8> {
+        key.sign(&DisputeManager::vote_message(dispute_id, verdict).unwrap()).unwrap().to_bytes().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_evidence_blob_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Arc::new(MdbxChainStore::new(temp_dir.path()).unwrap());
+        let manager = DisputeManager::new(store);
+
+        let settlement_id = Blake2bHash::from_data(b"settlement-1");
+        let dispute_id = manager.open_dispute(
+            settlement_id,
+            DisputeReason::AmountDiscrepancy,
+            Some(80000),
+            100000,
+            NetworkId::new("Vodafone", "UK"),
+            Some(NetworkId::new("T-Mobile", "DE")),
+        ).await.unwrap();
+
+        let document = b"CDR reconciliation report: 2000 duplicate records";
+        let evidence_hash = manager.submit_evidence(&dispute_id, NetworkId::new("Vodafone", "UK"), document).await.unwrap();
+
+        assert_eq!(evidence_hash, Blake2bHash::from_data(document));
+        assert_eq!(manager.get_evidence(&evidence_hash).await.unwrap(), Some(document.to_vec()));
+
+        let dispute = manager.get_dispute(&dispute_id).await.unwrap();
+        assert_eq!(dispute.state, DisputeState::EvidenceSubmitted);
+        assert_eq!(dispute.evidence.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dispute_upheld_by_supermajority() {
+        let (manager, keys) = manager_with_arbitrators().await;
+        let settlement_id = Blake2bHash::from_data(b"settlement-2");
+        let initiator = NetworkId::new("Orange", "FR");
+        let respondent = NetworkId::new("T-Mobile", "DE");
+
+        let dispute_id = manager.open_dispute(
+            settlement_id, DisputeReason::InvalidCDR, Some(70000), 100000, initiator.clone(), Some(respondent.clone())
+        ).await.unwrap();
+
+        // Voting is not allowed before evidence is in
+        assert!(manager.begin_arbitration(&dispute_id).await.is_err());
+
+        manager.attach_evidence(&dispute_id, initiator.clone(), Blake2bHash::from_data(b"evidence")).await.unwrap();
+        manager.begin_arbitration(&dispute_id).await.unwrap();
+
+        // The parties do not arbitrate their own dispute
+        for party in [&initiator, &respondent] {
+            let verdict = DisputeVerdict::Uphold { adjusted_amount: 1 };
+            let signature = signed(&keys[party], &dispute_id, &verdict);
+            assert!(manager.cast_vote(&dispute_id, party.clone(), verdict, &signature).await.is_err());
+        }
+
+        // The three remaining arbitrators must all agree
+        let votes = [("Vodafone", "UK", 75000), ("Telefonica", "ES", 70000), ("KPN", "NL", 90000)];
+        let mut outcome = None;
+        for (name, country, adjusted_amount) in votes {
+            assert!(outcome.is_none());
+            let validator = NetworkId::new(name, country);
+            let verdict = DisputeVerdict::Uphold { adjusted_amount };
+            let signature = signed(&keys[&validator], &dispute_id, &verdict);
+            outcome = manager.cast_vote(&dispute_id, validator, verdict, &signature).await.unwrap();
+        }
+        assert_eq!(outcome, Some(DisputeOutcome::Upheld { adjusted_amount: 75000 }));
+
+        assert!(manager.get_dispute(&dispute_id).await.unwrap().is_resolved());
+        assert!(manager.get_open_disputes().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dispute_vote_rules() {
+        let (manager, keys) = manager_with_arbitrators().await;
+        let settlement_id = Blake2bHash::from_data(b"settlement-3");
+        let initiator = NetworkId::new("T-Mobile", "DE");
+
+        let dispute_id = manager.open_dispute(
+            settlement_id, DisputeReason::TechnicalError, None, 50000, initiator.clone(), Some(NetworkId::new("Vodafone", "UK"))
+        ).await.unwrap();
+        manager.attach_evidence(&dispute_id, initiator, Blake2bHash::from_data(b"logs")).await.unwrap();
+        manager.begin_arbitration(&dispute_id).await.unwrap();
+
+        // Non-validators cannot vote
+        let outsider = BLSPrivateKey::generate().unwrap();
+        let signature = signed(&outsider, &dispute_id, &DisputeVerdict::Reject);
+        assert!(manager.cast_vote(&dispute_id, NetworkId::new("Swisscom", "CH"), DisputeVerdict::Reject, &signature).await.is_err());
+
+        // Votes must be signed by the arbitrator's registered key, over the verdict cast
+        let orange = NetworkId::new("Orange", "FR");
+        let uphold = DisputeVerdict::Uphold { adjusted_amount: 1 };
+        assert!(manager.cast_vote(&dispute_id, orange.clone(), uphold.clone(), &signature).await.is_err());
+        let reject_signature = signed(&keys[&orange], &dispute_id, &DisputeVerdict::Reject);
+        assert!(manager.cast_vote(&dispute_id, orange.clone(), uphold.clone(), &reject_signature).await.is_err());
+
+        // Double voting is rejected
+        let signature = signed(&keys[&orange], &dispute_id, &uphold);
+        manager.cast_vote(&dispute_id, orange.clone(), uphold, &signature).await.unwrap();
+        assert!(manager.cast_vote(&dispute_id, orange, DisputeVerdict::Reject, &reject_signature).await.is_err());
+
+        // Split vote makes a supermajority impossible: original amount stands
+        let telefonica = NetworkId::new("Telefonica", "ES");
+        let signature = signed(&keys[&telefonica], &dispute_id, &DisputeVerdict::Reject);
+        let outcome = manager.cast_vote(&dispute_id, telefonica, DisputeVerdict::Reject, &signature).await.unwrap();
+        assert_eq!(outcome, Some(DisputeOutcome::Rejected));
+    }
+}
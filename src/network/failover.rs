@@ -0,0 +1,286 @@
+// Hot-standby validator failover: a standby node follows the chain with the validator's BLS key
+// sealed in an escrow and takes over signing once the signing node's heartbeats stop.
+// Heartbeats are validator announcements carrying a signed claim; the highest claim generation
+// signs, and a node never signs at or below the height and round its predecessor reached
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::primitives::{BlockchainError, Policy, Result, to_canonical_bytes};
+use super::{deserialize_peer_id, serialize_peer_id};
+
+/// How often the signing node announces itself to its standbys
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(Policy::BLOCK_TIME * 5);
+
+/// Heartbeat silence after which a primary starts signing, standbys wait twice as long
+pub const FAILOVER_TIMEOUT: Duration = Duration::from_millis(Policy::BLOCK_TIME * 20);
+
+/// Block height and round a block or vote is signed at, ordered by height first
+pub type SigningPosition = (u32, u32);
+
+/// Part a node plays for its validator
+#[derive(Debug, Clone)]
+pub enum FailoverRole {
+    /// Holds the validator key, the validator is identified by the node's own peer id
+    Primary,
+    /// Backs up `validator_id`, whose key is sealed to this node in `key_escrow`
+    Standby {
+        validator_id: PeerId,
+        key_escrow: PathBuf,
+    },
+}
+
+/// Failover setup of a validator node
+#[derive(Debug, Clone)]
+pub struct FailoverConfig {
+    pub role: FailoverRole,
+    /// Node ids of the other nodes signing for the same validator
+    pub peers: Vec<PeerId>,
+}
+
+/// Claim of a node to sign for a validator, carried by its validator announcements
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerClaim {
+    #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+    pub node_id: PeerId,
+    /// Raised by every takeover, the highest generation signs
+    pub generation: u64,
+    /// Last height and round the node signed at
+    pub signed_up_to: SigningPosition,
+    /// Protobuf-encoded node key the claim is signed with
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignerClaim {
+    /// Bytes covered by the node signature
+    fn signing_payload(validator_id: &PeerId, node_id: &PeerId, generation: u64, signed_up_to: SigningPosition) -> Vec<u8> {
+        let mut payload = b"sp-cdr-signer-claim".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(&(validator_id.to_bytes(), node_id.to_bytes(), generation, signed_up_to))
+            .expect("claim fields have a canonical encoding"));
+        payload
+    }
+
+    pub fn sign(validator_id: &PeerId, node_key: &Keypair, generation: u64, signed_up_to: SigningPosition) -> Result<Self> {
+        let node_id = node_key.public().to_peer_id();
+        let payload = Self::signing_payload(validator_id, &node_id, generation, signed_up_to);
+        let signature = node_key.sign(&payload)
+            .map_err(|e| BlockchainError::Crypto(format!("Signer claim signing failed: {}", e)))?;
+        Ok(Self {
+            node_id,
+            generation,
+            signed_up_to,
+            public_key: node_key.public().encode_protobuf(),
+            signature,
+        })
+    }
+
+    /// Check the claim for `validator_id` was signed by the node it names
+    pub fn verify(&self, validator_id: &PeerId) -> Result<()> {
+        let public_key = PublicKey::try_decode_protobuf(&self.public_key)
+            .map_err(|e| BlockchainError::Crypto(format!("Invalid signer claim key: {}", e)))?;
+        if public_key.to_peer_id() != self.node_id {
+            return Err(BlockchainError::Crypto(format!("Signer claim key does not belong to {}", self.node_id)));
+        }
+        let payload = Self::signing_payload(validator_id, &self.node_id, self.generation, self.signed_up_to);
+        if !public_key.verify(&payload, &self.signature) {
+            return Err(BlockchainError::Crypto(format!("Invalid signature on signer claim of {}", self.node_id)));
+        }
+        Ok(())
+    }
+}
+
+/// Heartbeat-failure protocol between the nodes signing for one validator
+#[derive(Debug)]
+pub struct FailoverMonitor {
+    validator_id: PeerId,
+    node_key: Keypair,
+    node_id: PeerId,
+    is_standby: bool,
+    /// Nodes whose claims are accepted
+    peers: Vec<PeerId>,
+    signing: bool,
+    /// Highest claim generation seen, the local one while signing
+    generation: u64,
+    /// When the signing node was last heard from, or the monitor started
+    last_heard: Instant,
+    /// Positions another node may have signed at
+    fence: SigningPosition,
+    signed_up_to: SigningPosition,
+}
+
+impl FailoverMonitor {
+    /// Monitor that starts out following, so a restarted node never signs before hearing from the others
+    pub fn new(config: FailoverConfig, node_key: Keypair, now: Instant) -> Self {
+        let node_id = node_key.public().to_peer_id();
+        let (validator_id, is_standby) = match config.role {
+            FailoverRole::Primary => (node_id, false),
+            FailoverRole::Standby { validator_id, .. } => (validator_id, true),
+        };
+        let mut peers = config.peers;
+        peers.push(validator_id);
+        peers.retain(|peer| *peer != node_id);
+
+        Self {
+            validator_id,
+            node_key,
+            node_id,
+            is_standby,
+            peers,
+            signing: false,
+            generation: 0,
+            last_heard: now,
+            fence: (0, 0),
+            signed_up_to: (0, 0),
+        }
+    }
+
+    /// Validator the node signs for
+    pub fn validator_id(&self) -> PeerId {
+        self.validator_id
+    }
+
+    pub fn is_signing(&self) -> bool {
+        self.signing
+    }
+
+    fn timeout(&self) -> Duration {
+        if self.is_standby { FAILOVER_TIMEOUT * 2 } else { FAILOVER_TIMEOUT }
+    }
+
+    /// Take over signing once the signing node was silent for the timeout, returns whether it did
+    /// `position` is the height and round in progress, which the silent node may already have signed
+    pub fn tick(&mut self, now: Instant, position: SigningPosition) -> bool {
+        if self.signing || now.duration_since(self.last_heard) < self.timeout() {
+            return false;
+        }
+        self.generation += 1;
+        self.fence = self.fence.max(position);
+        self.signing = true;
+        warn!("🔁 No heartbeat for validator {}, taking over signing in generation {} after block {} round {}",
+              self.validator_id, self.generation, self.fence.0, self.fence.1);
+        true
+    }
+
+    /// Stop signing after a takeover could not be completed, retried after the next timeout
+    pub fn step_down(&mut self, now: Instant) {
+        self.signing = false;
+        self.last_heard = now;
+    }
+
+    /// Follow a claim from another node of the validator, returns whether the local node stopped signing
+    /// Claims of equal generation are settled by the lower node id
+    pub fn handle_claim(&mut self, claim: &SignerClaim, now: Instant) -> Result<bool> {
+        if claim.node_id == self.node_id {
+            return Ok(false);
+        }
+        if !self.peers.contains(&claim.node_id) {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "{} does not sign for validator {}", claim.node_id, self.validator_id
+            )));
+        }
+        claim.verify(&self.validator_id)?;
+
+        let outranks = claim.generation > self.generation
+            || (claim.generation == self.generation && (!self.signing || claim.node_id < self.node_id));
+        if !outranks {
+            return Ok(false);
+        }
+        self.generation = claim.generation;
+        self.last_heard = now;
+        self.fence = self.fence.max(claim.signed_up_to);
+
+        let stepped_down = self.signing;
+        if stepped_down {
+            self.signing = false;
+            info!("🔁 {} signs for validator {} in generation {}, standing by", claim.node_id, self.validator_id, claim.generation);
+        }
+        Ok(stepped_down)
+    }
+
+    /// Whether the local node may sign at `position`, recorded as signed if so
+    pub fn sign(&mut self, position: SigningPosition) -> bool {
+        if !self.signing || position <= self.fence || position < self.signed_up_to {
+            return false;
+        }
+        self.signed_up_to = position;
+        true
+    }
+
+    /// Claim announced as heartbeat while signing
+    pub fn claim(&self) -> Result<SignerClaim> {
+        SignerClaim::sign(&self.validator_id, &self.node_key, self.generation, self.signed_up_to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(now: Instant) -> (FailoverMonitor, FailoverMonitor) {
+        let primary_key = Keypair::generate_ed25519();
+        let standby_key = Keypair::generate_ed25519();
+        let validator_id = primary_key.public().to_peer_id();
+
+        let primary = FailoverMonitor::new(
+            FailoverConfig { role: FailoverRole::Primary, peers: vec![standby_key.public().to_peer_id()] },
+            primary_key,
+            now,
+        );
+        let standby = FailoverMonitor::new(
+            FailoverConfig {
+                role: FailoverRole::Standby { validator_id, key_escrow: PathBuf::from("validator.escrow") },
+                peers: vec![],
+            },
+            standby_key,
+            now,
+        );
+        (primary, standby)
+    }
+
+    #[test]
+    fn test_standby_takes_over_without_double_signing() {
+        let now = Instant::now();
+        let (mut primary, mut standby) = pair(now);
+
+        // Nobody signs before the timeout, the primary starts first
+        assert!(!primary.sign((1, 0)));
+        assert!(primary.tick(now + FAILOVER_TIMEOUT, (1, 0)));
+        assert!(primary.sign((32, 0)));
+        assert!(primary.sign((32, 0)));
+
+        let heartbeat = primary.claim().unwrap();
+        assert!(!standby.handle_claim(&heartbeat, now + FAILOVER_TIMEOUT).unwrap());
+        assert!(!standby.tick(now + FAILOVER_TIMEOUT * 2, (33, 0)));
+
+        // The primary dies after signing round 1, the standby skips the round in progress
+        assert!(primary.sign((32, 1)));
+        assert!(standby.tick(now + FAILOVER_TIMEOUT * 3, (32, 1)));
+        assert!(!standby.sign((32, 0)));
+        assert!(!standby.sign((32, 1)));
+        assert!(standby.sign((32, 2)));
+
+        // The restarted primary sees the higher generation and stands by
+        let takeover = standby.claim().unwrap();
+        assert_eq!(takeover.generation, 2);
+        assert!(primary.handle_claim(&takeover, now + FAILOVER_TIMEOUT * 3).unwrap());
+        assert!(!primary.sign((33, 0)));
+    }
+
+    #[test]
+    fn test_forged_claims_are_rejected() {
+        let now = Instant::now();
+        let (mut primary, standby) = pair(now);
+
+        // A node outside the validator's group cannot demote it
+        let outsider = SignerClaim::sign(&primary.validator_id(), &Keypair::generate_ed25519(), 99, (0, 0)).unwrap();
+        assert!(primary.handle_claim(&outsider, now).is_err());
+
+        let mut forged = standby.claim().unwrap();
+        forged.generation = 99;
+        assert!(primary.handle_claim(&forged, now).is_err());
+    }
+}
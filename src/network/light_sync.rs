@@ -0,0 +1,76 @@
+// Light client sync: follows certified macro headers served by full nodes and
+// checks settlement inclusion proofs against them, without storing blocks
+use tracing::{info, warn};
+
+use crate::blockchain::{CertifiedMacroHeader, InclusionProof, LightClient};
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::SPNetworkMessage;
+
+/// Sync state of a light node
+#[derive(Debug, Default)]
+pub struct LightSync {
+    client: Option<LightClient>,
+}
+
+impl LightSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chain view, once a checkpoint was received
+    pub fn client(&self) -> Option<&LightClient> {
+        self.client.as_ref()
+    }
+
+    /// Request for the headers following the synced head, or for a checkpoint before the first sync
+    pub fn headers_request(&self) -> SPNetworkMessage {
+        SPNetworkMessage::MacroHeadersRequest {
+            from_block: self.client.as_ref().map_or(0, LightClient::head_block_number),
+        }
+    }
+
+    /// Follow the headers of a `MacroHeaders` response, returns how many were applied
+    /// Headers already synced from another peer are skipped
+    pub fn handle_headers(&mut self, headers: Vec<CertifiedMacroHeader>) -> Result<usize> {
+        let mut applied = 0;
+        for certified in headers {
+            let client = match &mut self.client {
+                Some(client) => client,
+                None => {
+                    warn!("⚠️  Trusting election block {} as light client checkpoint", certified.header.block_number);
+                    self.client = Some(LightClient::from_checkpoint(&certified)?);
+                    applied += 1;
+                    continue;
+                }
+            };
+            if certified.header.block_number <= client.head_block_number() {
+                continue;
+            }
+            client.apply(certified)?;
+            applied += 1;
+        }
+
+        if let Some(client) = &self.client {
+            if applied > 0 {
+                info!("🪶 Light client synced to macro block {}", client.head_block_number());
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Check an `InclusionProofResponse`, `false` while the transaction is not in a finalized batch
+    pub fn handle_inclusion_proof(&self, transaction_hash: &Blake2bHash, proof: Option<&InclusionProof>) -> Result<bool> {
+        let Some(proof) = proof else {
+            return Ok(false);
+        };
+        if proof.transaction.hash() != *transaction_hash {
+            return Err(BlockchainError::BlockValidation(format!(
+                "Inclusion proof is for {}, not {}", proof.transaction.hash(), transaction_hash
+            )));
+        }
+        let client = self.client.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidState("Light client has no checkpoint yet".to_string()))?;
+        client.verify_inclusion(proof)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,288 @@
+// Gossip message framing: network messages are zstd compressed and, when still too large for one
+// gossip message, split into chunks that carry their own hash and are reassembled on arrival.
+// Reassembly gives up on messages whose chunks stop arriving
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::SPNetworkMessage;
+
+/// Largest gossip message, chunks stay below it with room for their framing
+pub const GOSSIP_MAX_TRANSMIT_SIZE: usize = 1024 * 1024;
+
+/// Framing bytes a chunk adds to its data
+const CHUNK_OVERHEAD: usize = 1024;
+
+/// zstd level, fast enough to compress every gossiped block
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Compression and chunking limits
+#[derive(Debug, Clone)]
+pub struct ChunkingConfig {
+    /// Compressed messages above this size are chunked, at most `GOSSIP_MAX_TRANSMIT_SIZE` less framing
+    pub chunk_size: usize,
+    /// Largest message accepted once decompressed
+    pub max_message_size: usize,
+    /// How long the chunks of one message may take to arrive
+    pub reassembly_timeout: Duration,
+    /// Messages reassembled at once, the oldest is dropped beyond it
+    pub max_pending_messages: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 256 * 1024,
+            max_message_size: 64 * 1024 * 1024,
+            reassembly_timeout: Duration::from_secs(30),
+            max_pending_messages: 64,
+        }
+    }
+}
+
+/// One gossip message on the wire
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GossipFrame {
+    /// Compressed message fitting in one gossip message
+    Whole(Vec<u8>),
+    /// Part `index` of `total` of a compressed message hashing to `message_hash`
+    Chunk {
+        message_hash: Blake2bHash,
+        index: u32,
+        total: u32,
+        chunk_hash: Blake2bHash,
+        data: Vec<u8>,
+    },
+}
+
+/// Chunks of a message received so far
+struct PartialMessage {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    started: Instant,
+}
+
+/// Frames outgoing messages and reassembles incoming ones
+pub struct MessageChunker {
+    config: ChunkingConfig,
+    pending: HashMap<Blake2bHash, PartialMessage>,
+}
+
+impl MessageChunker {
+    pub fn new(mut config: ChunkingConfig) -> Self {
+        config.chunk_size = config.chunk_size.clamp(1, GOSSIP_MAX_TRANSMIT_SIZE - CHUNK_OVERHEAD);
+        Self {
+            config,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Gossip messages carrying `message`, one unless it had to be chunked
+    pub fn encode(&self, message: &SPNetworkMessage) -> Result<Vec<Vec<u8>>> {
+        let serialized = bincode::serialize(message)
+            .map_err(|e| BlockchainError::NetworkError(format!("Serialization error: {}", e)))?;
+        let compressed = zstd::encode_all(serialized.as_slice(), COMPRESSION_LEVEL)
+            .map_err(|e| BlockchainError::NetworkError(format!("Compression failed: {}", e)))?;
+
+        let frames = if compressed.len() <= self.config.chunk_size {
+            vec![GossipFrame::Whole(compressed)]
+        } else {
+            let message_hash = Blake2bHash::from_data(&compressed);
+            let total = compressed.len().div_ceil(self.config.chunk_size) as u32;
+            debug!("📦 Chunking {} byte message {} into {} chunks", compressed.len(), message_hash, total);
+            compressed.chunks(self.config.chunk_size).enumerate().map(|(index, data)| GossipFrame::Chunk {
+                message_hash,
+                index: index as u32,
+                total,
+                chunk_hash: Blake2bHash::from_data(data),
+                data: data.to_vec(),
+            }).collect()
+        };
+
+        frames.iter()
+            .map(|frame| bincode::serialize(frame)
+                .map_err(|e| BlockchainError::NetworkError(format!("Serialization error: {}", e))))
+            .collect()
+    }
+
+    /// Take in a gossip message, returning the network message once all of its chunks arrived
+    pub fn decode(&mut self, data: &[u8], now: Instant) -> Result<Option<SPNetworkMessage>> {
+        self.expire(now);
+
+        let frame: GossipFrame = bincode::deserialize(data)
+            .map_err(|e| BlockchainError::NetworkError(format!("Failed to deserialize frame: {}", e)))?;
+        let compressed = match frame {
+            GossipFrame::Whole(compressed) => compressed,
+            GossipFrame::Chunk { message_hash, index, total, chunk_hash, data } => {
+                match self.add_chunk(message_hash, index, total, chunk_hash, data, now)? {
+                    Some(compressed) => compressed,
+                    None => return Ok(None),
+                }
+            }
+        };
+        self.decompress(&compressed).map(Some)
+    }
+
+    fn add_chunk(
+        &mut self,
+        message_hash: Blake2bHash,
+        index: u32,
+        total: u32,
+        chunk_hash: Blake2bHash,
+        data: Vec<u8>,
+        now: Instant,
+    ) -> Result<Option<Vec<u8>>> {
+        if Blake2bHash::from_data(&data) != chunk_hash {
+            return Err(BlockchainError::NetworkError(format!(
+                "Chunk {} of message {} does not match its hash", index, message_hash
+            )));
+        }
+        // Chunks of a message too large to accept are not collected
+        let max_chunks = self.config.max_message_size.div_ceil(self.config.chunk_size).max(1);
+        if total == 0 || index >= total || total as usize > max_chunks {
+            return Err(BlockchainError::NetworkError(format!(
+                "Chunk {} of {} of message {} is out of bounds", index, total, message_hash
+            )));
+        }
+
+        if !self.pending.contains_key(&message_hash) && self.pending.len() >= self.config.max_pending_messages {
+            let oldest = self.pending.iter().min_by_key(|(_, partial)| partial.started).map(|(hash, _)| *hash);
+            if let Some(oldest) = oldest {
+                warn!("Dropping incomplete message {}, too many messages being reassembled", oldest);
+                self.pending.remove(&oldest);
+            }
+        }
+        let partial = self.pending.entry(message_hash).or_insert_with(|| PartialMessage {
+            chunks: vec![None; total as usize],
+            received: 0,
+            started: now,
+        });
+        if partial.chunks.len() != total as usize {
+            return Err(BlockchainError::NetworkError(format!(
+                "Message {} announced with {} and {} chunks", message_hash, partial.chunks.len(), total
+            )));
+        }
+        let slot = &mut partial.chunks[index as usize];
+        if slot.is_none() {
+            *slot = Some(data);
+            partial.received += 1;
+        }
+        if partial.received < partial.chunks.len() {
+            return Ok(None);
+        }
+
+        let partial = self.pending.remove(&message_hash).expect("message is being reassembled");
+        let compressed: Vec<u8> = partial.chunks.into_iter().flatten().flatten().collect();
+        if Blake2bHash::from_data(&compressed) != message_hash {
+            return Err(BlockchainError::NetworkError(format!("Reassembled message {} does not match its hash", message_hash)));
+        }
+        debug!("📦 Reassembled message {} from {} chunks", message_hash, total);
+        Ok(Some(compressed))
+    }
+
+    fn decompress(&self, compressed: &[u8]) -> Result<SPNetworkMessage> {
+        // Bounded, a small message must not decompress to gigabytes
+        let mut serialized = Vec::new();
+        let decoder = zstd::Decoder::new(compressed)
+            .map_err(|e| BlockchainError::NetworkError(format!("Decompression failed: {}", e)))?;
+        std::io::Read::read_to_end(&mut std::io::Read::take(decoder, self.config.max_message_size as u64 + 1), &mut serialized)
+            .map_err(|e| BlockchainError::NetworkError(format!("Decompression failed: {}", e)))?;
+        if serialized.len() > self.config.max_message_size {
+            return Err(BlockchainError::NetworkError(format!(
+                "Message exceeds {} bytes once decompressed", self.config.max_message_size
+            )));
+        }
+        bincode::deserialize(&serialized)
+            .map_err(|e| BlockchainError::NetworkError(format!("Failed to deserialize message: {}", e)))
+    }
+
+    /// Give up on messages whose chunks took longer than the reassembly timeout
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.config.reassembly_timeout;
+        self.pending.retain(|message_hash, partial| {
+            let live = now.saturating_duration_since(partial.started) < timeout;
+            if !live {
+                warn!("Incomplete message {} timed out with {}/{} chunks", message_hash, partial.received, partial.chunks.len());
+            }
+            live
+        });
+    }
+
+    /// Messages still being reassembled
+    pub fn pending_messages(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::PeerId;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use crate::primitives::NetworkId;
+
+    fn large_message() -> SPNetworkMessage {
+        // Proof bytes that do not compress, so the message stays large
+        let mut rng = StdRng::seed_from_u64(7);
+        let proof_data: Vec<u8> = (0..200_000).map(|_| rng.gen()).collect();
+        SPNetworkMessage::zkp_generated("settlement".to_string(), proof_data, vec![], NetworkId::new("T-Mobile", "DE"))
+    }
+
+    #[test]
+    fn test_small_messages_compress_into_one_frame() {
+        let chunker = MessageChunker::new(ChunkingConfig::default());
+        let message = SPNetworkMessage::node_leaving(PeerId::random(), NetworkId::SPConsortium);
+        let frames = chunker.encode(&message).unwrap();
+        assert_eq!(frames.len(), 1);
+
+        let mut receiver = MessageChunker::new(ChunkingConfig::default());
+        let decoded = receiver.decode(&frames[0], Instant::now()).unwrap().unwrap();
+        assert!(matches!(decoded, SPNetworkMessage::NodeLeaving { .. }));
+    }
+
+    #[test]
+    fn test_chunks_reassemble_in_any_order() {
+        let config = ChunkingConfig { chunk_size: 32 * 1024, ..Default::default() };
+        let chunker = MessageChunker::new(config.clone());
+        let mut frames = chunker.encode(&large_message()).unwrap();
+        assert!(frames.len() > 3);
+        assert!(frames.iter().all(|frame| frame.len() < GOSSIP_MAX_TRANSMIT_SIZE));
+
+        let now = Instant::now();
+        let mut receiver = MessageChunker::new(config.clone());
+        frames.reverse();
+        let last = frames.pop().unwrap();
+        for frame in &frames {
+            assert!(receiver.decode(frame, now).unwrap().is_none());
+        }
+        // A repeated chunk changes nothing
+        assert!(receiver.decode(&frames[0], now).unwrap().is_none());
+        assert_eq!(receiver.pending_messages(), 1);
+        match receiver.decode(&last, now).unwrap().unwrap() {
+            SPNetworkMessage::ZKProofGenerated { proof_data, .. } => assert_eq!(proof_data.len(), 200_000),
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert_eq!(receiver.pending_messages(), 0);
+
+        // Chunks that stop arriving are given up on
+        let mut receiver = MessageChunker::new(config);
+        receiver.decode(&frames[0], now).unwrap();
+        assert!(receiver.decode(&last, now + Duration::from_secs(31)).unwrap().is_none());
+        assert_eq!(receiver.pending_messages(), 1);
+    }
+
+    #[test]
+    fn test_tampered_chunk_is_rejected() {
+        let config = ChunkingConfig { chunk_size: 32 * 1024, ..Default::default() };
+        let frames = MessageChunker::new(config.clone()).encode(&large_message()).unwrap();
+        let mut frame: GossipFrame = bincode::deserialize(&frames[1]).unwrap();
+        if let GossipFrame::Chunk { data, .. } = &mut frame {
+            data[0] ^= 0xff;
+        }
+
+        let mut receiver = MessageChunker::new(config);
+        assert!(receiver.decode(&bincode::serialize(&frame).unwrap(), Instant::now()).is_err());
+    }
+}
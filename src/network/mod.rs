@@ -1,28 +1,34 @@
 // P2P networking layer for SP CDR reconciliation blockchain
 use libp2p::{
+    allow_block_list::{self, BlockedPeers},
+    autonat,
+    dcutr,
     gossipsub::{self, Behaviour as Gossipsub, Event as GossipsubEvent, IdentTopic, MessageAuthenticity},
     identify::{self, Behaviour as Identify},
+    kad::{self, store::MemoryStore},
     mdns::{self, tokio::Behaviour as Mdns},
-    noise,
-    swarm::{NetworkBehaviour, SwarmEvent, ConnectionDenied, ConnectionId},
-    tcp,
-    yamux,
-    Multiaddr, PeerId, Swarm, Transport,
+    multiaddr::Protocol,
+    relay,
+    request_response::{self, ProtocolSupport},
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour, SwarmEvent, ConnectionDenied, ConnectionId},
+    Multiaddr, PeerId, StreamProtocol, Swarm,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, info, warn, error};
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
 
 // Helper functions for PeerId serialization
-fn serialize_peer_id<S>(peer_id: &PeerId, serializer: S) -> Result<S::Ok, S::Error>
+pub(crate) fn serialize_peer_id<S>(peer_id: &PeerId, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     serializer.serialize_str(&peer_id.to_string())
 }
 
-fn deserialize_peer_id<'de, D>(deserializer: D) -> Result<PeerId, D::Error>
+pub(crate) fn deserialize_peer_id<'de, D>(deserializer: D) -> Result<PeerId, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -36,10 +42,48 @@ use crate::blockchain::{Block, Transaction};
 pub mod peer_discovery;
 pub mod consensus_networking;
 pub mod settlement_messaging;
-
-pub use peer_discovery::PeerDiscovery;
+pub mod dispute_resolution;
+pub mod multilateral_netting;
+pub mod operator_identity;
+pub mod block_production;
+pub mod light_sync;
+pub mod failover;
+pub mod setup_sync;
+pub mod ceremony;
+pub mod connection_manager;
+pub mod message_chunking;
+pub mod nat;
+pub mod transport;
+pub mod topic_auth;
+pub mod rate_limit;
+pub mod counter_offer;
+pub mod settlement_policy;
+pub mod replay;
+pub mod sentry;
+pub mod tenancy;
+
+pub use peer_discovery::{PeerDiscovery, PeerStore, PeerRecord, ReconnectBackoff, operator_provider_key, MIN_DIAL_REPUTATION};
 pub use consensus_networking::ConsensusNetwork;
 pub use settlement_messaging::SettlementMessaging;
+pub use dispute_resolution::DisputeManager;
+pub use multilateral_netting::{MultilateralNettingSolver, NettingConfig};
+pub use operator_identity::{OperatorCertificate, OperatorIdentityVerifier, ConsortiumAuthority, load_or_generate_node_key};
+pub use block_production::{BlockProductionScheduler, BlockKind, ProductionStep, VoteOutcome};
+pub use light_sync::LightSync;
+pub use failover::{FailoverConfig, FailoverMonitor, FailoverRole, SignerClaim};
+pub use setup_sync::{KeyFetchConfig, TrustedSetupRequest, TrustedSetupResponse, TrustedSetupSync};
+pub use ceremony::CeremonyDriver;
+pub use connection_manager::{ConnectionLimits, ConnectionTracker, ConnectionVerdict, PeerBan};
+pub use message_chunking::{ChunkingConfig, MessageChunker};
+pub use nat::NatConfig;
+pub use transport::TransportPreference;
+pub use topic_auth::PublishAuthorization;
+pub use rate_limit::{MessageClass, MessageRateLimiter, RateLimitConfig, RateLimitVerdict};
+pub use counter_offer::{CounterOfferPolicy, OfferRound};
+pub use settlement_policy::{CounterpartyPolicy, SettlementPolicies};
+pub use replay::{MessageRecorder, ReplayEngine, ReplayLog};
+pub use sentry::{SentryConfig, SentryMode};
+pub use tenancy::{OperatorIdentity, TenancyConfig};
 
 /// SP-specific network messages for telecom operators
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +102,12 @@ pub enum SPNetworkMessage {
         approve: bool,
         signature: Vec<u8>,
     },
+    /// Tendermint prevote or precommit on a macro block proposal
+    MacroBlockVote {
+        #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+        voter: PeerId,
+        vote: crate::common::TendermintVote,
+    },
 
     /// Settlement negotiation
     SettlementProposal {
@@ -82,12 +132,23 @@ pub enum SPNetworkMessage {
         network_pair: (NetworkId, NetworkId),
         record_count: u32,
         total_amount: u64,
+        /// Merkle root over the batch's records, committed on chain when the batch is frozen
+        merkle_root: Blake2bHash,
     },
     CDRBatchRequest {
         batch_id: Blake2bHash,
         requester: NetworkId,
     },
 
+    /// Fraud detection: a record put its batch into quarantine pending review
+    FraudAlert {
+        batch_id: Blake2bHash,
+        record_id: String,
+        network_pair: (NetworkId, NetworkId),
+        score: u32,
+        reasons: Vec<String>,
+    },
+
     /// ZK proof sharing
     ZKProofGenerated {
         proof_type: String, // "cdr_privacy" or "settlement"
@@ -103,6 +164,59 @@ pub enum SPNetworkMessage {
         network_ids: Vec<NetworkId>,
         stake_amount: u64,
         endpoint: Multiaddr,
+        /// BLS key the validator signs macro block certificates with
+        #[serde(default)]
+        signing_key: Vec<u8>,
+        /// Heartbeat of the node signing for a validator with hot standbys
+        #[serde(default)]
+        failover: Option<SignerClaim>,
+    },
+    /// Light client sync: certified macro headers after `from_block`, or from the latest election if zero
+    MacroHeadersRequest {
+        from_block: u32,
+    },
+    MacroHeaders {
+        headers: Vec<crate::blockchain::CertifiedMacroHeader>,
+    },
+    /// Light client request for the proof that a finalized batch included a transaction
+    InclusionProofRequest {
+        transaction_hash: Blake2bHash,
+    },
+    InclusionProofResponse {
+        transaction_hash: Blake2bHash,
+        proof: Option<crate::blockchain::InclusionProof>,
+    },
+
+    /// Trusted setup distribution: the bootstrap node's ceremony and the hash of its transcript
+    TrustedSetupAvailable {
+        ceremony_id: String,
+        transcript_hash: Blake2bHash,
+    },
+    /// Asked by nodes waiting for trusted setup keys, answered with `TrustedSetupAvailable`
+    TrustedSetupQuery,
+    /// Multi-party trusted setup: the bootstrap node's call for contributions, in participant order
+    CeremonyStarted {
+        ceremony_id: String,
+        participants: Vec<String>,
+        powers_degree: usize,
+        start_time: u64,
+    },
+    /// Every contribution to a multi-party ceremony so far, gossiped by the participant who contributed
+    /// last and holds the state the next one builds on, with the hash of the transcript it installed
+    /// once the ceremony is complete
+    CeremonyContributed {
+        ceremony_id: String,
+        contributions: Vec<crate::zkp::trusted_setup::ParticipantContribution>,
+        #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+        holder: PeerId,
+        transcript_hash: Option<Blake2bHash>,
+    },
+
+    /// A node is shutting down, peers stop routing consensus work to it
+    NodeLeaving {
+        #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+        peer_id: PeerId,
+        network_id: NetworkId,
     },
 }
 
@@ -120,13 +234,55 @@ pub enum NetworkEvent {
         message: SPNetworkMessage,
         source: PeerId,
     },
+    /// A peer announced itself in the DHT as serving an operator identity
+    OperatorDiscovered {
+        network_id: NetworkId,
+        peer_id: PeerId,
+    },
+    /// A peer failed operator certificate verification and was disconnected
+    PeerRejected {
+        peer_id: PeerId,
+        reason: String,
+    },
+    /// A gossip message was rejected, its author not being allowed to publish on the topic
+    UnauthorizedMessage {
+        topic: String,
+        author: Option<PeerId>,
+        reason: String,
+    },
+    /// Answer to a trusted setup request, `Unavailable` if the request failed
+    TrustedSetupResponse {
+        peer: PeerId,
+        response: TrustedSetupResponse,
+    },
 }
 
+/// Kademlia protocol name, kept separate from the public IPFS DHT
+const KAD_PROTOCOL: StreamProtocol = StreamProtocol::new("/sp-cdr-blockchain/kad/1.0.0");
+
+/// How often the Kademlia routing table is refreshed
+const KAD_BOOTSTRAP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+
+pub type Kademlia = kad::Behaviour<MemoryStore>;
+
 #[derive(NetworkBehaviour)]
 pub struct SPNetworkBehaviour {
     pub gossipsub: Gossipsub,
-    pub mdns: Mdns,
+    /// Local discovery, off on validators behind sentries
+    pub mdns: Toggle<Mdns>,
     pub identify: Identify,
+    pub kademlia: Kademlia,
+    pub trusted_setup: request_response::cbor::Behaviour<TrustedSetupRequest, TrustedSetupResponse>,
+    /// Refuses and closes connections of banned peers
+    pub blocked_peers: allow_block_list::Behaviour<BlockedPeers>,
+    /// Whether peers can dial this node on the addresses it observed
+    pub autonat: autonat::Behaviour,
+    /// Reservations on relay nodes, for nodes peers cannot dial
+    pub relay_client: relay::client::Behaviour,
+    /// Relays connections for other nodes, on designated relay nodes only
+    pub relay: Toggle<relay::Behaviour>,
+    /// Hole punching, upgrading relayed connections to direct ones
+    pub dcutr: dcutr::Behaviour,
 }
 
 
@@ -145,13 +301,72 @@ pub struct SPNetworkManager {
     // Network state
     connected_peers: HashSet<PeerId>,
     network_id: NetworkId,
+
+    // WAN discovery: configured bootnodes and remembered peers
+    bootnodes: Vec<Multiaddr>,
+    peer_store: Arc<PeerStore>,
+
+    // DHT operator discovery: pending provider lookups and the operator table they feed
+    provider_queries: HashMap<kad::QueryId, NetworkId>,
+    peer_discovery: Option<Arc<PeerDiscovery>>,
+
+    // Operator identity: certificate checks and the NetworkIds peers proved
+    identity_verifier: Option<OperatorIdentityVerifier>,
+    verified_operators: HashMap<PeerId, NetworkId>,
+
+    // Peers that announced a shutdown, not redialed until they reconnect
+    leaving_peers: HashSet<PeerId>,
+
+    // Keys directory trusted setup requests are served from
+    trusted_setup_dir: Option<PathBuf>,
+
+    // Connection caps and per-peer connection rate
+    connection_tracker: ConnectionTracker,
+
+    // Compression and chunking of gossiped messages
+    chunker: MessageChunker,
+
+    // Consortium relays listened on while this node is not publicly reachable
+    nat_config: NatConfig,
+    relay_listeners: HashSet<PeerId>,
+
+    // Transport dialed first, QUIC addresses being skipped by TCP-only nodes
+    transport_preference: TransportPreference,
+
+    // Who may publish consensus and settlement messages
+    publish_authorization: PublishAuthorization,
+
+    // Validator kept behind sentries, or sentry keeping validators unadvertised
+    sentry: SentryConfig,
+}
+
+/// How the network manager reaches its peers
+#[derive(Debug, Clone, Default)]
+pub struct NetworkConfig {
+    pub nat: NatConfig,
+    pub transport: TransportPreference,
+    pub sentry: SentryConfig,
 }
 
+/// Reputation change of a peer publishing on a topic it is not authorized for
+const UNAUTHORIZED_MESSAGE_PENALTY: i32 = -5;
+
+/// How often remembered peers are redialed
+const REDIAL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// Commands that can be sent to the network manager
 #[derive(Debug)]
 pub enum NetworkCommand {
     Connect(Multiaddr),
+    /// Close every connection to a peer
     Disconnect(PeerId),
+    /// Disconnect a peer and refuse its connections for `duration_secs`
+    Ban {
+        peer: PeerId,
+        duration_secs: u64,
+        reason: String,
+    },
+    Unban(PeerId),
     SendMessage {
         peer: PeerId,
         message: SPNetworkMessage,
@@ -162,6 +377,19 @@ pub enum NetworkCommand {
     },
     JoinTopic(String),
     LeaveTopic(String),
+    /// Look up the peers serving an operator identity in the DHT
+    FindOperator(NetworkId),
+    /// Announce that this node also serves another operator identity
+    ProvideOperator(NetworkId),
+    /// Ask a peer for a trusted setup file, answered with `NetworkEvent::TrustedSetupResponse`
+    RequestTrustedSetup {
+        peer: PeerId,
+        request: TrustedSetupRequest,
+    },
+    /// Only deliver consensus messages published by this validator set
+    SetValidators(Vec<PeerId>),
+    /// Only deliver settlement messages published by nodes verified as one of these operators
+    SetRegisteredOperators(Vec<NetworkId>),
 }
 
 impl SPNetworkManager {
@@ -172,22 +400,55 @@ impl SPNetworkManager {
     ) -> std::result::Result<(Self, mpsc::Sender<NetworkCommand>, broadcast::Receiver<NetworkEvent>), BlockchainError> {
         // Generate keypair for this node
         let local_key = libp2p::identity::Keypair::generate_ed25519();
+        Self::with_identity(network_id, listen_addr, local_key, None).await
+    }
+
+    /// Create a network manager with a persistent node key and the operator certificate binding it
+    pub async fn with_identity(
+        network_id: NetworkId,
+        listen_addr: Multiaddr,
+        local_key: libp2p::identity::Keypair,
+        certificate: Option<OperatorCertificate>,
+    ) -> std::result::Result<(Self, mpsc::Sender<NetworkCommand>, broadcast::Receiver<NetworkEvent>), BlockchainError> {
+        Self::with_config(network_id, listen_addr, local_key, certificate, NetworkConfig::default()).await
+    }
+
+    /// Create a network manager with its transport preference and NAT traversal configured
+    /// `listen_addr` selects its transport, a TCP address is also listened on over QUIC if preferred
+    pub async fn with_config(
+        network_id: NetworkId,
+        listen_addr: Multiaddr,
+        local_key: libp2p::identity::Keypair,
+        certificate: Option<OperatorCertificate>,
+        config: NetworkConfig,
+    ) -> std::result::Result<(Self, mpsc::Sender<NetworkCommand>, broadcast::Receiver<NetworkEvent>), BlockchainError> {
+        let NetworkConfig { nat: nat_config, transport: transport_preference, sentry } = config;
+        sentry.validate()?;
         let local_peer_id = PeerId::from(local_key.public());
 
+        if let Some(certificate) = &certificate {
+            if certificate.peer_id()? != local_peer_id || certificate.network_id != network_id {
+                return Err(BlockchainError::Crypto(format!(
+                    "Operator certificate does not bind {} to {}", local_peer_id, network_id
+                )));
+            }
+        }
+
         info!("SP Node Peer ID: {}", local_peer_id);
         info!("Network ID: {:?}", network_id);
 
-        // Create transport
-        let transport = tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
-            .upgrade(libp2p::core::upgrade::Version::V1Lazy)
-            .authenticate(noise::Config::new(&local_key)?)
-            .multiplex(yamux::Config::default())
-            .boxed();
+        // Create transport, dialing over QUIC, TCP and relayed circuits
+        let (relay_transport, relay_client) = relay::client::new(local_peer_id);
+        let transport = transport::build_transport(&local_key, relay_transport)?;
 
         // Configure gossipsub
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .heartbeat_interval(std::time::Duration::from_secs(10))
             .validation_mode(gossipsub::ValidationMode::Strict)
+            // Messages are forwarded once their publisher is authorized for the topic
+            .validate_messages()
+            // Larger messages are chunked below this size
+            .max_transmit_size(message_chunking::GOSSIP_MAX_TRANSMIT_SIZE)
             .message_id_fn(|message| {
                 use std::hash::{Hash, Hasher};
                 let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -202,26 +463,60 @@ impl SPNetworkManager {
             gossipsub_config,
         ).map_err(|e| crate::primitives::BlockchainError::NetworkError(e.to_string()))?;
 
-        // Create other behaviors
-        let mdns = Mdns::new(mdns::Config::default(), local_peer_id)
-            .map_err(|e| crate::primitives::BlockchainError::NetworkError(e.to_string()))?;
+        // Create other behaviors, a validator behind sentries not announcing itself on the local network
+        let mdns = if sentry.discovers() {
+            Some(Mdns::new(mdns::Config::default(), local_peer_id)
+                .map_err(|e| crate::primitives::BlockchainError::NetworkError(e.to_string()))?)
+        } else {
+            None
+        };
 
-        let identify = Identify::new(identify::Config::new(
+        // The operator certificate travels in the identify agent version
+        let mut identify_config = identify::Config::new(
             "/sp-cdr-blockchain/1.0.0".to_string(),
             local_key.public(),
-        ));
+        );
+        if let Some(certificate) = &certificate {
+            identify_config = identify_config.with_agent_version(certificate.to_agent_version()?);
+        }
+        let identify = Identify::new(identify_config);
+
+        let mut kad_config = kad::Config::default();
+        kad_config.set_protocol_names(vec![KAD_PROTOCOL]);
+        let mut kademlia = Kademlia::with_config(local_peer_id, MemoryStore::new(local_peer_id), kad_config);
+        // Operator nodes are publicly reachable, serve DHT requests without waiting for external address confirmation
+        // A validator behind sentries only queries the DHT, so it never enters peers' routing tables
+        kademlia.set_mode(Some(if sentry.discovers() { kad::Mode::Server } else { kad::Mode::Client }));
+
+        // Proving keys run to megabytes, more than a gossip message carries
+        let trusted_setup = request_response::cbor::Behaviour::new(
+            [(setup_sync::TRUSTED_SETUP_PROTOCOL, ProtocolSupport::Full)],
+            request_response::Config::default().with_request_timeout(std::time::Duration::from_secs(120)),
+        );
 
         // Combine behaviors
         let behavior = SPNetworkBehaviour {
             gossipsub,
-            mdns,
+            mdns: Toggle::from(mdns),
             identify,
+            kademlia,
+            trusted_setup,
+            blocked_peers: allow_block_list::Behaviour::default(),
+            autonat: autonat::Behaviour::new(local_peer_id, autonat::Config::default()),
+            relay_client,
+            relay: Toggle::from(nat_config.act_as_relay.then(|| relay::Behaviour::new(local_peer_id, relay::Config::default()))),
+            dcutr: dcutr::Behaviour::new(local_peer_id),
         };
 
         // Create swarm
         let mut swarm = Swarm::new(transport, behavior, local_peer_id, libp2p::swarm::Config::with_tokio_executor());
 
-        // Listen on the provided address
+        // Listen on the provided address, and over QUIC too if it is preferred
+        if transport_preference == TransportPreference::Quic {
+            if let Some(quic_addr) = transport::quic_listen_address(&listen_addr) {
+                swarm.listen_on(quic_addr)?;
+            }
+        }
         swarm.listen_on(listen_addr)?;
 
         // Create communication channels
@@ -239,6 +534,8 @@ impl SPNetworkManager {
         swarm.behaviour_mut().gossipsub.subscribe(&settlement_topic)?;
         swarm.behaviour_mut().gossipsub.subscribe(&cdr_topic)?;
         swarm.behaviour_mut().gossipsub.subscribe(&zkp_topic)?;
+        // Direct messages to this node arrive on its own topic
+        swarm.behaviour_mut().gossipsub.subscribe(&IdentTopic::new(format!("direct-{}", local_peer_id)))?;
 
         let manager = SPNetworkManager {
             swarm,
@@ -250,17 +547,431 @@ impl SPNetworkManager {
             zkp_topic,
             connected_peers: HashSet::new(),
             network_id,
+            bootnodes: Vec::new(),
+            peer_store: Arc::new(PeerStore::in_memory()),
+            provider_queries: HashMap::new(),
+            peer_discovery: None,
+            identity_verifier: None,
+            verified_operators: HashMap::new(),
+            leaving_peers: HashSet::new(),
+            trusted_setup_dir: None,
+            connection_tracker: ConnectionTracker::default(),
+            chunker: MessageChunker::new(ChunkingConfig::default()),
+            nat_config,
+            relay_listeners: HashSet::new(),
+            transport_preference,
+            publish_authorization: PublishAuthorization::default(),
+            sentry,
         };
 
         Ok((manager, command_sender, event_receiver))
     }
 
+    /// Serve trusted setup requests from the keys in `keys_dir`
+    pub fn serve_trusted_setup(&mut self, keys_dir: PathBuf) {
+        self.trusted_setup_dir = Some(keys_dir);
+    }
+
+    /// Dial these nodes at startup, needed wherever mDNS cannot reach
+    pub fn add_bootnodes(&mut self, bootnodes: Vec<Multiaddr>) {
+        self.bootnodes.extend(bootnodes);
+    }
+
+    /// Cap the connections per operator and limit how often a peer may connect
+    pub fn set_connection_limits(&mut self, limits: ConnectionLimits) {
+        self.connection_tracker = ConnectionTracker::new(limits);
+    }
+
+    /// Chunk size and reassembly limits of gossiped messages
+    pub fn set_chunking_config(&mut self, config: ChunkingConfig) {
+        self.chunker = MessageChunker::new(config);
+    }
+
+    /// Replace the in-memory peer store with a persistent one
+    pub fn set_peer_store(&mut self, peer_store: Arc<PeerStore>) {
+        self.peer_store = peer_store;
+    }
+
+    pub fn peer_store(&self) -> Arc<PeerStore> {
+        self.peer_store.clone()
+    }
+
+    /// Feed operators found through DHT provider records into this operator table
+    pub fn set_peer_discovery(&mut self, peer_discovery: Arc<PeerDiscovery>) {
+        self.peer_discovery = Some(peer_discovery);
+    }
+
+    /// Require peers to present an operator certificate from a trusted consortium authority
+    pub fn set_identity_verifier(&mut self, verifier: OperatorIdentityVerifier) {
+        self.identity_verifier = Some(verifier);
+    }
+
+    /// NetworkId a peer proved with its operator certificate
+    pub fn verified_operator(&self, peer_id: &PeerId) -> Option<&NetworkId> {
+        self.verified_operators.get(peer_id)
+    }
+
+    /// Check the operator certificate a peer presented in the identify handshake
+    /// Returns false if the peer was rejected and disconnected
+    async fn verify_peer_identity(&mut self, peer_id: PeerId, agent_version: &str) -> std::result::Result<bool, BlockchainError> {
+        let verifier = match &self.identity_verifier {
+            Some(verifier) => verifier,
+            None => return Ok(true),
+        };
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        match verifier.verify_agent_version(agent_version, &peer_id, now) {
+            Ok(certificate) => {
+                let network_id = certificate.network_id;
+                info!("🪪 Peer {} verified as operator {}", peer_id, network_id);
+                self.publish_authorization.set_hosted_identities(network_id.clone(), certificate.hosted);
+                self.verified_operators.insert(peer_id, network_id);
+                Ok(true)
+            }
+            Err(e) => {
+                warn!("🚫 Rejecting peer {}: {}", peer_id, e);
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+                self.peer_store.adjust_reputation(peer_id, MIN_DIAL_REPUTATION).await?;
+
+                let _ = self.event_sender.send(NetworkEvent::PeerRejected {
+                    peer_id,
+                    reason: e.to_string(),
+                });
+                Ok(false)
+            }
+        }
+    }
+
+    /// Announce an operator identity served by this node as a DHT provider record
+    /// A validator behind sentries is not announced, its sentries are what peers find
+    fn provide_operator(&mut self, network_id: &NetworkId) {
+        if !self.sentry.discovers() {
+            debug!("Not announcing operator {} from behind sentries", network_id);
+            return;
+        }
+        let key = kad::RecordKey::new(&operator_provider_key(network_id));
+        match self.swarm.behaviour_mut().kademlia.start_providing(key) {
+            Ok(_) => info!("Announcing operator {} in the DHT", network_id),
+            Err(e) => warn!("Failed to announce operator {}: {:?}", network_id, e),
+        }
+    }
+
+    /// Start a DHT lookup for the peers serving an operator identity
+    fn find_operator(&mut self, network_id: NetworkId) {
+        debug!("Looking up operator {} in the DHT", network_id);
+        let key = kad::RecordKey::new(&operator_provider_key(&network_id));
+        let query_id = self.swarm.behaviour_mut().kademlia.get_providers(key);
+        self.provider_queries.insert(query_id, network_id);
+    }
+
+    /// Handle a peer found as provider of an operator identity
+    async fn handle_operator_provider(&mut self, network_id: NetworkId, peer_id: PeerId) -> std::result::Result<(), BlockchainError> {
+        if peer_id == *self.swarm.local_peer_id() {
+            return Ok(());
+        }
+
+        if let Some(verified) = self.verified_operators.get(&peer_id) {
+            if *verified != network_id {
+                warn!("Peer {} announced {} but is certified for {}", peer_id, network_id, verified);
+                return Ok(());
+            }
+        }
+
+        info!("Found operator {} at peer {}", network_id, peer_id);
+
+        if let Some(peer_discovery) = &self.peer_discovery {
+            let addresses = self.peer_store.get(&peer_id).await
+                .map(|record| record.addresses)
+                .unwrap_or_default();
+            peer_discovery.record_operator_provider(network_id.clone(), peer_id, addresses).await?;
+        }
+
+        // Kademlia supplies the addresses from its routing table when dialing by peer id
+        if !self.connected_peers.contains(&peer_id) {
+            if let Err(e) = self.swarm.dial(peer_id) {
+                debug!("Failed to dial operator peer {}: {}", peer_id, e);
+            }
+        }
+
+        let _ = self.event_sender.send(NetworkEvent::OperatorDiscovered { network_id, peer_id });
+        Ok(())
+    }
+
+    /// Handle Kademlia events
+    async fn handle_kademlia_event(&mut self, event: kad::Event) -> std::result::Result<(), BlockchainError> {
+        match event {
+            kad::Event::OutboundQueryProgressed { id, result, step, .. } => {
+                match result {
+                    kad::QueryResult::GetProviders(Ok(kad::GetProvidersOk::FoundProviders { providers, .. })) => {
+                        if let Some(network_id) = self.provider_queries.get(&id).cloned() {
+                            for provider in providers {
+                                self.handle_operator_provider(network_id.clone(), provider).await?;
+                            }
+                        }
+                    }
+                    kad::QueryResult::GetProviders(Err(e)) => {
+                        debug!("Operator provider lookup failed: {:?}", e);
+                    }
+                    kad::QueryResult::StartProviding(Err(e)) => {
+                        warn!("Failed to publish operator provider record: {:?}", e);
+                    }
+                    kad::QueryResult::Bootstrap(Ok(result)) => {
+                        debug!("DHT bootstrap step, {} buckets remaining", result.num_remaining);
+                    }
+                    _ => {}
+                }
+
+                if step.last {
+                    self.provider_queries.remove(&id);
+                }
+            }
+
+            kad::Event::RoutingUpdated { peer, addresses, .. } => {
+                if self.sentry.hidden_peers().contains(&peer) {
+                    self.swarm.behaviour_mut().kademlia.remove_peer(&peer);
+                    return Ok(());
+                }
+                debug!("DHT routing table updated with {}", peer);
+                self.peer_store.record_addresses(peer, addresses.into_vec()).await?;
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Ban a peer: its connections are closed and refused until the ban expires
+    async fn ban_peer(&mut self, peer_id: PeerId, duration_secs: u64, reason: String) -> std::result::Result<(), BlockchainError> {
+        let until = (chrono::Utc::now().timestamp() as u64).saturating_add(duration_secs);
+        warn!("⛔ Banning peer {} for {}s: {}", peer_id, duration_secs, reason);
+        self.peer_store.ban(peer_id, until, reason).await?;
+        self.swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+        Ok(())
+    }
+
+    /// Lift a peer's ban before it expires
+    async fn unban_peer(&mut self, peer_id: PeerId) -> std::result::Result<(), BlockchainError> {
+        if self.peer_store.unban(&peer_id).await? {
+            info!("Ban of peer {} lifted", peer_id);
+        }
+        self.swarm.behaviour_mut().blocked_peers.unblock_peer(peer_id);
+        Ok(())
+    }
+
+    /// Lift expired bans and forget connection times outside the rate window
+    async fn expire_bans(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.connection_tracker.prune(now);
+        match self.peer_store.expire_bans(now).await {
+            Ok(expired) => {
+                for peer_id in expired {
+                    debug!("Ban of peer {} expired", peer_id);
+                    self.swarm.behaviour_mut().blocked_peers.unblock_peer(peer_id);
+                }
+            }
+            Err(e) => warn!("Failed to update peer store: {}", e),
+        }
+    }
+
+    /// Close connections beyond the connection rate or the cap of the peer's operator
+    /// Returns false if the connection was closed
+    async fn check_connection(&mut self, peer_id: PeerId, connection_id: ConnectionId, open: u32) -> std::result::Result<bool, BlockchainError> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut verdict = self.connection_tracker.connection_established(peer_id, open, now);
+        if verdict == ConnectionVerdict::Accept {
+            if let Some(network_id) = self.verified_operators.get(&peer_id) {
+                verdict = self.connection_tracker.check_operator(network_id, &self.verified_operators);
+            }
+        }
+
+        match verdict {
+            ConnectionVerdict::Accept => Ok(true),
+            ConnectionVerdict::RateLimited => {
+                let ban_secs = self.connection_tracker.limits().rate_ban_secs;
+                self.ban_peer(peer_id, ban_secs, "connection rate exceeded".to_string()).await?;
+                Ok(false)
+            }
+            ConnectionVerdict::OperatorLimit(network_id) => {
+                warn!("Closing connection to {}: operator {} is at its connection limit", peer_id, network_id);
+                self.swarm.close_connection(connection_id);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Listen through the configured relays, so peers reach this node while it cannot be dialed
+    fn listen_on_relays(&mut self) {
+        for relay in self.nat_config.relay_nodes.clone() {
+            let (Some(relay_peer), Some(circuit)) = (nat::relay_peer_id(&relay), nat::relay_circuit_address(&relay)) else {
+                warn!("Relay address {} has no /p2p peer id, skipping it", relay);
+                continue;
+            };
+            if relay_peer == *self.swarm.local_peer_id() || self.relay_listeners.contains(&relay_peer) {
+                continue;
+            }
+
+            info!("📡 Listening through relay {}", relay);
+            match self.swarm.listen_on(circuit) {
+                Ok(_) => { self.relay_listeners.insert(relay_peer); }
+                Err(e) => warn!("Failed to listen through relay {}: {}", relay, e),
+            }
+        }
+    }
+
+    /// Follow what AutoNAT found out about this node's reachability
+    fn handle_autonat_event(&mut self, event: autonat::Event) {
+        if let autonat::Event::StatusChanged { old, new } = event {
+            info!("🧭 NAT status changed from {:?} to {:?}", old, new);
+            // Peers reach a validator behind sentries through them, never directly or over a relay
+            if !self.sentry.discovers() {
+                return;
+            }
+            match new {
+                // Advertised through identify, so peers dial it directly
+                autonat::NatStatus::Public(address) => {
+                    self.swarm.add_external_address(address);
+                }
+                autonat::NatStatus::Private => self.listen_on_relays(),
+                autonat::NatStatus::Unknown => {}
+            }
+        }
+    }
+
+    /// Dial remembered peers whose reconnection backoff has expired
+    async fn redial_known_peers(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        for record in self.peer_store.peers_due_for_dial(now).await {
+            if self.connected_peers.contains(&record.peer_id) || self.leaving_peers.contains(&record.peer_id) {
+                continue;
+            }
+            if !self.sentry.allows_connection(&record.peer_id) {
+                continue;
+            }
+
+            debug!("Redialing known peer {} (failed dials: {})", record.peer_id, record.failed_dials);
+            // One address at a time, so the preferred transport is tried before the fallback
+            let opts = libp2p::swarm::dial_opts::DialOpts::peer_id(record.peer_id)
+                .addresses(transport::dial_order(record.addresses, self.transport_preference))
+                .override_dial_concurrency_factor(std::num::NonZeroU8::MIN)
+                .build();
+
+            if let Err(e) = self.swarm.dial(opts) {
+                debug!("Failed to dial known peer {}: {}", record.peer_id, e);
+                if let Err(e) = self.peer_store.record_dial_failure(record.peer_id).await {
+                    warn!("Failed to update peer store: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Keep connections to the sentries of this validator, or the validators behind this sentry
+    fn dial_private_peers(&mut self) {
+        for address in self.sentry.private_peers.clone() {
+            let Some(peer_id) = sentry::peer_id(&address) else { continue };
+            if self.connected_peers.contains(&peer_id) {
+                continue;
+            }
+            debug!("Dialing private peer {}", address);
+            if let Err(e) = self.swarm.dial(address.clone()) {
+                warn!("Failed to dial private peer {}: {}", address, e);
+            }
+        }
+    }
+
     /// Start the network event loop
     pub async fn run(mut self) {
         info!("Starting SP Network Manager for {:?}", self.network_id);
 
+        // Private peers exchange every gossip message, whatever the mesh looks like
+        for peer_id in self.sentry.private_peer_ids() {
+            self.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+        }
+        match self.sentry.mode {
+            SentryMode::Validator => {
+                info!("🛡️  Validator behind {} sentries, discovery disabled", self.sentry.private_peers.len());
+                // Sentries answer this node's DHT lookups
+                for address in self.sentry.private_peers.clone() {
+                    if let Some(peer_id) = sentry::peer_id(&address) {
+                        self.swarm.behaviour_mut().kademlia.add_address(&peer_id, address);
+                    }
+                }
+                self.bootnodes.clear();
+                self.nat_config.relay_nodes.clear();
+            }
+            SentryMode::Sentry => {
+                info!("🛡️  Sentry for {} validators", self.sentry.private_peers.len());
+                if let Some(peer_discovery) = &self.peer_discovery {
+                    peer_discovery.hide_peers(self.sentry.hidden_peers()).await;
+                }
+            }
+            SentryMode::Off => {}
+        }
+        self.dial_private_peers();
+
+        for bootnode in self.bootnodes.clone() {
+            // Bootnodes with a /p2p suffix seed the DHT routing table
+            if let Some(Protocol::P2p(peer_id)) = bootnode.iter().last() {
+                self.swarm.behaviour_mut().kademlia.add_address(&peer_id, bootnode.clone());
+            }
+
+            info!("Dialing bootnode: {}", bootnode);
+            if let Err(e) = self.swarm.dial(bootnode.clone()) {
+                warn!("Failed to dial bootnode {}: {}", bootnode, e);
+            }
+        }
+
+        for record in self.peer_store.all_peers().await {
+            if !self.sentry.discovers() {
+                break;
+            }
+            for address in record.addresses {
+                self.swarm.behaviour_mut().kademlia.add_address(&record.peer_id, address);
+            }
+        }
+
+        // Relays are dialed up front, they are peers' way to this node until AutoNAT finds it reachable
+        for relay in self.nat_config.relay_nodes.clone() {
+            if let Some(relay_peer) = nat::relay_peer_id(&relay) {
+                self.swarm.behaviour_mut().kademlia.add_address(&relay_peer, relay.clone());
+                self.swarm.behaviour_mut().autonat.add_server(relay_peer, Some(relay.clone()));
+            }
+            if let Err(e) = self.swarm.dial(relay.clone()) {
+                warn!("Failed to dial relay {}: {}", relay, e);
+            }
+        }
+        if self.nat_config.act_as_relay {
+            info!("📡 Relaying connections for nodes behind NAT");
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        for ban in self.peer_store.active_bans(now).await {
+            self.swarm.behaviour_mut().blocked_peers.block_peer(ban.peer_id);
+        }
+
+        let local_network = self.network_id.clone();
+        self.provide_operator(&local_network);
+
+        let mut redial_interval = tokio::time::interval(REDIAL_INTERVAL);
+        let mut kad_bootstrap_interval = tokio::time::interval(KAD_BOOTSTRAP_INTERVAL);
+
         loop {
             tokio::select! {
+                // Reconnect to known peers with exponential backoff
+                _ = redial_interval.tick() => {
+                    self.expire_bans().await;
+                    self.chunker.expire(std::time::Instant::now());
+                    self.dial_private_peers();
+                    self.redial_known_peers().await;
+                }
+
+                // Refresh the DHT routing table
+                _ = kad_bootstrap_interval.tick(), if self.sentry.discovers() => {
+                    if let Err(e) = self.swarm.behaviour_mut().kademlia.bootstrap() {
+                        debug!("DHT bootstrap skipped: {}", e);
+                    }
+                }
+
                 // Handle swarm events
                 event = futures::StreamExt::select_next_some(&mut self.swarm) => {
                     if let Err(e) = self.handle_swarm_event(event).await {
@@ -293,32 +1004,80 @@ impl SPNetworkManager {
                 info!("Listening on: {}", address);
             }
 
-            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+            // Addresses peers observed this node at are probed by AutoNAT before they are advertised
+            SwarmEvent::NewExternalAddrCandidate { address } => {
+                debug!("Observed address candidate: {}", address);
+            }
+
+            SwarmEvent::ExternalAddrConfirmed { address } => {
+                info!("🌍 Advertising reachable address: {}", address);
+            }
+
+            SwarmEvent::ExternalAddrExpired { address } => {
+                debug!("No longer advertising address: {}", address);
+            }
+
+            SwarmEvent::ConnectionEstablished { peer_id, connection_id, endpoint, num_established, .. } => {
+                if !self.sentry.allows_connection(&peer_id) {
+                    debug!("Closing connection to {}: not one of this validator's sentries", peer_id);
+                    self.swarm.close_connection(connection_id);
+                    return Ok(());
+                }
+                if !self.check_connection(peer_id, connection_id, num_established.get()).await? {
+                    return Ok(());
+                }
+
                 info!("Connected to peer: {}", peer_id);
                 self.connected_peers.insert(peer_id);
+                self.leaving_peers.remove(&peer_id);
+                crate::metrics::metrics().peers_connected.set(self.connected_peers.len() as i64);
+
+                // Inbound connections come from ephemeral ports, only dialed addresses are reusable
+                let address = endpoint.is_dialer().then(|| endpoint.get_remote_address().clone());
+                self.peer_store.record_connected(peer_id, address).await?;
 
                 let _ = self.event_sender.send(NetworkEvent::PeerConnected(peer_id));
             }
 
-            SwarmEvent::ConnectionClosed { peer_id, .. } => {
-                info!("Disconnected from peer: {}", peer_id);
-                self.connected_peers.remove(&peer_id);
+            SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), error, .. } => {
+                debug!("Dial to {} failed: {}", peer_id, error);
+                self.peer_store.record_dial_failure(peer_id).await?;
+            }
+
+            SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+                self.connection_tracker.connection_closed(peer_id, num_established);
+                // Only the last connection closing disconnects the peer
+                if num_established > 0 {
+                    return Ok(());
+                }
 
-                let _ = self.event_sender.send(NetworkEvent::PeerDisconnected(peer_id));
+                info!("Disconnected from peer: {}", peer_id);
+                self.verified_operators.remove(&peer_id);
+                if self.connected_peers.remove(&peer_id) {
+                    crate::metrics::metrics().peers_connected.set(self.connected_peers.len() as i64);
+                    let _ = self.event_sender.send(NetworkEvent::PeerDisconnected(peer_id));
+                }
             }
 
             SwarmEvent::Behaviour(SPNetworkBehaviourEvent::Gossipsub(gossipsub::Event::Message {
                 propagation_source: source,
-                message_id: _,
+                message_id,
                 message,
             })) => {
-                self.handle_gossip_message(source, message).await?;
+                crate::metrics::metrics().gossip_messages_received.inc();
+                self.handle_gossip_message(source, message_id, message).await?;
             }
 
             SwarmEvent::Behaviour(SPNetworkBehaviourEvent::Mdns(mdns::Event::Discovered(list))) => {
                 for (peer_id, multiaddr) in list {
                     debug!("Discovered peer via mDNS: {} at {}", peer_id, multiaddr);
 
+                    self.swarm.behaviour_mut().kademlia.add_address(&peer_id, multiaddr.clone());
+
+                    if self.transport_preference == TransportPreference::Tcp && transport::is_quic(&multiaddr) {
+                        continue;
+                    }
+
                     // Auto-connect to discovered SP nodes
                     if let Err(e) = self.swarm.dial(multiaddr) {
                         debug!("Failed to dial discovered peer: {}", e);
@@ -332,9 +1091,58 @@ impl SPNetworkManager {
             })) => {
                 debug!("Identified peer {}: {}", peer_id, info.protocol_version);
 
+                if !self.verify_peer_identity(peer_id, &info.agent_version).await? {
+                    return Ok(());
+                }
+
+                // Connections opened before the peer proved its operator count from now on
+                if let Some(network_id) = self.verified_operators.get(&peer_id) {
+                    if let ConnectionVerdict::OperatorLimit(network_id) = self.connection_tracker.check_operator(network_id, &self.verified_operators) {
+                        warn!("Disconnecting {}: operator {} is at its connection limit", peer_id, network_id);
+                        let _ = self.swarm.disconnect_peer_id(peer_id);
+                        return Ok(());
+                    }
+                }
+
                 // Check if this is an SP node
                 if info.protocol_version.contains("sp-cdr-blockchain") {
                     info!("Connected to SP CDR node: {}", peer_id);
+
+                    // Validators behind this sentry stay out of the DHT
+                    if info.protocols.contains(&KAD_PROTOCOL) && !self.sentry.hidden_peers().contains(&peer_id) {
+                        for address in &info.listen_addrs {
+                            self.swarm.behaviour_mut().kademlia.add_address(&peer_id, address.clone());
+                        }
+                    }
+
+                    self.peer_store.record_addresses(peer_id, info.listen_addrs).await?;
+                }
+            }
+
+            SwarmEvent::Behaviour(SPNetworkBehaviourEvent::Kademlia(event)) => {
+                self.handle_kademlia_event(event).await?;
+            }
+
+            SwarmEvent::Behaviour(SPNetworkBehaviourEvent::TrustedSetup(event)) => {
+                self.handle_trusted_setup_event(event).await;
+            }
+
+            SwarmEvent::Behaviour(SPNetworkBehaviourEvent::Autonat(event)) => {
+                self.handle_autonat_event(event);
+            }
+
+            SwarmEvent::Behaviour(SPNetworkBehaviourEvent::RelayClient(relay::client::Event::ReservationReqAccepted { relay_peer_id, .. })) => {
+                info!("📡 Reachable through relay {}", relay_peer_id);
+            }
+
+            SwarmEvent::Behaviour(SPNetworkBehaviourEvent::Relay(relay::Event::ReservationReqAccepted { src_peer_id, .. })) => {
+                debug!("Relaying connections for {}", src_peer_id);
+            }
+
+            SwarmEvent::Behaviour(SPNetworkBehaviourEvent::Dcutr(event)) => {
+                match event.result {
+                    Ok(_) => info!("🕳️  Direct connection to {} by hole punching", event.remote_peer_id),
+                    Err(e) => debug!("Hole punching to {} failed, staying relayed: {}", event.remote_peer_id, e),
                 }
             }
 
@@ -344,19 +1152,75 @@ impl SPNetworkManager {
         Ok(())
     }
 
-    /// Handle gossipsub messages
+    /// Serve trusted setup requests and hand responses to the application layer
+    async fn handle_trusted_setup_event(&mut self, event: request_response::Event<TrustedSetupRequest, TrustedSetupResponse>) {
+        match event {
+            request_response::Event::Message { peer, message: request_response::Message::Request { request, channel, .. } } => {
+                let response = match &self.trusted_setup_dir {
+                    Some(keys_dir) => setup_sync::serve(keys_dir, request).await,
+                    None => TrustedSetupResponse::Unavailable("No trusted setup served here".to_string()),
+                };
+                debug!("📤 Serving trusted setup to {}", peer);
+                if self.swarm.behaviour_mut().trusted_setup.send_response(channel, response).is_err() {
+                    debug!("Trusted setup request of {} closed before the response", peer);
+                }
+            }
+            request_response::Event::Message { peer, message: request_response::Message::Response { response, .. } } => {
+                let _ = self.event_sender.send(NetworkEvent::TrustedSetupResponse { peer, response });
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                let response = TrustedSetupResponse::Unavailable(error.to_string());
+                let _ = self.event_sender.send(NetworkEvent::TrustedSetupResponse { peer, response });
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle gossipsub messages, forwarding and delivering only those of authorized publishers
     async fn handle_gossip_message(
         &mut self,
         source: PeerId,
+        message_id: gossipsub::MessageId,
         message: gossipsub::Message,
     ) -> std::result::Result<(), BlockchainError> {
-        // Deserialize SP network message
-        let sp_message: SPNetworkMessage = bincode::deserialize(&message.data)
-            .map_err(|e| crate::primitives::BlockchainError::NetworkError(format!("Failed to deserialize message: {}", e)))?;
+        let topic = message.topic.to_string();
+        let operator = message.source.and_then(|author| self.verified_operators.get(&author));
+        if let Err(reason) = self.publish_authorization.authorize_author(&topic, message.source, operator) {
+            return self.reject_gossip_message(source, &message_id, topic, message.source, reason).await;
+        }
+
+        // Decompress the SP network message, chunked ones once their last chunk arrived
+        let sp_message = match self.chunker.decode(&message.data, std::time::Instant::now()) {
+            Ok(Some(sp_message)) => sp_message,
+            Ok(None) => {
+                self.report_validation(&message_id, &source, gossipsub::MessageAcceptance::Accept);
+                return Ok(());
+            }
+            Err(e) => {
+                self.report_validation(&message_id, &source, gossipsub::MessageAcceptance::Reject);
+                return Err(e);
+            }
+        };
+
+        let operator = message.source.and_then(|author| self.verified_operators.get(&author));
+        if let Err(reason) = self.publish_authorization.authorize(&topic, message.source, operator, &sp_message) {
+            return self.reject_gossip_message(source, &message_id, topic, message.source, reason).await;
+        }
+        self.report_validation(&message_id, &source, gossipsub::MessageAcceptance::Accept);
 
         debug!("Received gossip message from {}: {:?}", source, sp_message);
 
-        let topic = message.topic.to_string();
+        if let SPNetworkMessage::NodeLeaving { peer_id, network_id } = &sp_message {
+            info!("👋 {} ({}) is shutting down", network_id, peer_id);
+            self.leaving_peers.insert(*peer_id);
+        }
+
+        // Report the topic under the name used in NetworkCommand::Broadcast
+        if topic == format!("direct-{}", self.swarm.local_peer_id()) {
+            let _ = self.event_sender.send(NetworkEvent::MessageReceived { peer: source, message: sp_message });
+            return Ok(());
+        }
+        let topic = topic.strip_prefix("sp-").map(str::to_string).unwrap_or(topic);
 
         // Send to application layer
         let _ = self.event_sender.send(NetworkEvent::GossipReceived {
@@ -368,6 +1232,33 @@ impl SPNetworkManager {
         Ok(())
     }
 
+    /// Tell gossipsub whether to forward a message to the mesh
+    fn report_validation(&mut self, message_id: &gossipsub::MessageId, source: &PeerId, acceptance: gossipsub::MessageAcceptance) {
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.report_message_validation_result(message_id, source, acceptance) {
+            debug!("Could not report validation of gossip message {}: {}", message_id, e);
+        }
+    }
+
+    /// Drop a message of an unauthorized publisher, lowering its score and that of the peer relaying it
+    async fn reject_gossip_message(
+        &mut self,
+        source: PeerId,
+        message_id: &gossipsub::MessageId,
+        topic: String,
+        author: Option<PeerId>,
+        reason: String,
+    ) -> std::result::Result<(), BlockchainError> {
+        warn!("🚫 Rejected gossip message on {} from {:?}: {}", topic, author, reason);
+        self.report_validation(message_id, &source, gossipsub::MessageAcceptance::Reject);
+        crate::metrics::metrics().gossip_messages_rejected.inc();
+        if let Some(author) = author {
+            self.peer_store.adjust_reputation(author, UNAUTHORIZED_MESSAGE_PENALTY).await?;
+        }
+
+        let _ = self.event_sender.send(NetworkEvent::UnauthorizedMessage { topic, author, reason });
+        Ok(())
+    }
+
     /// Handle network commands
     async fn handle_command(&mut self, command: NetworkCommand) -> std::result::Result<(), BlockchainError> {
         match command {
@@ -378,29 +1269,39 @@ impl SPNetworkManager {
 
             NetworkCommand::Disconnect(peer_id) => {
                 info!("Disconnecting from: {}", peer_id);
-                // libp2p doesn't have a direct disconnect method, we'd need to close the connection
-                // For now, we just remove from our tracking
-                self.connected_peers.remove(&peer_id);
+                // Tracking is updated once the connections report closed
+                if self.swarm.disconnect_peer_id(peer_id).is_err() {
+                    debug!("Not connected to {}", peer_id);
+                }
+            }
+
+            NetworkCommand::Ban { peer, duration_secs, reason } => {
+                self.ban_peer(peer, duration_secs, reason).await?;
+            }
+
+            NetworkCommand::Unban(peer) => {
+                self.unban_peer(peer).await?;
             }
 
             NetworkCommand::SendMessage { peer, message } => {
                 debug!("Sending direct message to {}: {:?}", peer, message);
                 // For direct messaging, we'd need to implement a custom protocol
                 // For now, we'll use gossip with a specific topic
-                let serialized = bincode::serialize(&message)
-                    .map_err(|e| crate::primitives::BlockchainError::NetworkError(format!("Serialization error: {}", e)))?;
+                let frames = self.chunker.encode(&message)?;
 
                 // Use a peer-specific topic for direct messaging
                 let direct_topic = IdentTopic::new(format!("direct-{}", peer));
                 self.swarm.behaviour_mut().gossipsub.subscribe(&direct_topic)?;
-                self.swarm.behaviour_mut().gossipsub.publish(direct_topic, serialized)?;
+                for frame in frames {
+                    self.swarm.behaviour_mut().gossipsub.publish(direct_topic.clone(), frame)?;
+                    crate::metrics::metrics().gossip_messages_published.inc();
+                }
             }
 
             NetworkCommand::Broadcast { topic, message } => {
                 debug!("Broadcasting to topic {}: {:?}", topic, message);
 
-                let serialized = bincode::serialize(&message)
-                    .map_err(|e| crate::primitives::BlockchainError::NetworkError(format!("Serialization error: {}", e)))?;
+                let frames = self.chunker.encode(&message)?;
 
                 let gossip_topic = match topic.as_str() {
                     "consensus" => &self.consensus_topic,
@@ -413,7 +1314,10 @@ impl SPNetworkManager {
                     }
                 };
 
-                self.swarm.behaviour_mut().gossipsub.publish(gossip_topic.clone(), serialized)?;
+                for frame in frames {
+                    self.swarm.behaviour_mut().gossipsub.publish(gossip_topic.clone(), frame)?;
+                    crate::metrics::metrics().gossip_messages_published.inc();
+                }
             }
 
             NetworkCommand::JoinTopic(topic) => {
@@ -427,6 +1331,33 @@ impl SPNetworkManager {
                 let gossip_topic = IdentTopic::new(topic);
                 self.swarm.behaviour_mut().gossipsub.unsubscribe(&gossip_topic)?;
             }
+
+            NetworkCommand::FindOperator(network_id) => {
+                self.find_operator(network_id);
+            }
+
+            NetworkCommand::ProvideOperator(network_id) => {
+                self.provide_operator(&network_id);
+            }
+
+            NetworkCommand::RequestTrustedSetup { peer, request } => {
+                self.swarm.behaviour_mut().trusted_setup.send_request(&peer, request);
+            }
+
+            NetworkCommand::SetValidators(validators) => {
+                info!("🛡️  Accepting consensus messages from {} validators", validators.len());
+                self.publish_authorization.set_validators(validators);
+            }
+
+            NetworkCommand::SetRegisteredOperators(operators) => {
+                // Without operator certificates no publisher can be tied to an operator
+                if self.identity_verifier.is_none() {
+                    debug!("Operator certificates are not checked, settlement publishers stay unrestricted");
+                    return Ok(());
+                }
+                info!("🛡️  Accepting settlement messages from {} registered operators", operators.len());
+                self.publish_authorization.set_registered_operators(operators);
+            }
         }
 
         Ok(())
@@ -463,6 +1394,14 @@ impl SPNetworkMessage {
         Self::BlockProposal { block, proposer, signature }
     }
 
+    /// Operator a settlement message claims to be sent by
+    pub fn settlement_sender(&self) -> Option<&NetworkId> {
+        match self {
+            Self::SettlementProposal { creditor, .. } => Some(creditor),
+            _ => None,
+        }
+    }
+
     pub fn settlement_proposal(
         creditor: NetworkId,
         debtor: NetworkId,
@@ -484,15 +1423,37 @@ impl SPNetworkMessage {
         network_pair: (NetworkId, NetworkId),
         record_count: u32,
         total_amount: u64,
+        merkle_root: Blake2bHash,
     ) -> Self {
         Self::CDRBatchReady {
             batch_id,
             network_pair,
             record_count,
             total_amount,
+            merkle_root,
         }
     }
 
+    pub fn fraud_alert(
+        batch_id: Blake2bHash,
+        record_id: String,
+        network_pair: (NetworkId, NetworkId),
+        score: u32,
+        reasons: Vec<String>,
+    ) -> Self {
+        Self::FraudAlert {
+            batch_id,
+            record_id,
+            network_pair,
+            score,
+            reasons,
+        }
+    }
+
+    pub fn node_leaving(peer_id: PeerId, network_id: NetworkId) -> Self {
+        Self::NodeLeaving { peer_id, network_id }
+    }
+
     pub fn zkp_generated(
         proof_type: String,
         proof_data: Vec<u8>,
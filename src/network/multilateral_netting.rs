@@ -0,0 +1,403 @@
+// Multilateral netting solver for N-party settlement cycles
+use std::collections::BTreeMap;
+use tracing::{info, debug};
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{NetworkId, BlockchainError};
+
+/// Netting solver configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NettingConfig {
+    /// Longest obligation cycle to cancel (2 = bilateral only, 3 = triangular, ...)
+    pub max_cycle_length: usize,
+    /// Upper bound on cycle cancellations, guards against pathological inputs
+    pub max_cancellations: usize,
+}
+
+impl Default for NettingConfig {
+    fn default() -> Self {
+        Self {
+            max_cycle_length: 6,
+            max_cancellations: 10_000,
+        }
+    }
+}
+
+/// A cancelled obligation cycle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NettedCycle {
+    pub networks: Vec<NetworkId>,
+    pub amount: u64,
+}
+
+/// Deterministic result of a netting run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NettingResult {
+    /// Participants in canonical order
+    pub participants: Vec<NetworkId>,
+    /// Obligations left after cycle cancellation, in canonical order
+    pub residual_obligations: Vec<(NetworkId, NetworkId, u64)>,
+    /// Net position per participant (positive = receives), in canonical order
+    pub net_positions: Vec<(NetworkId, i64)>,
+    /// Cycles cancelled, in the order they were applied
+    pub cancelled_cycles: Vec<NettedCycle>,
+    pub gross_total: u64,
+    pub residual_total: u64,
+}
+
+impl NettingResult {
+    /// Percentage of gross obligations eliminated by netting
+    pub fn savings_percentage(&self) -> u32 {
+        if self.gross_total == 0 {
+            return 0;
+        }
+        (((self.gross_total - self.residual_total) as u128 * 100) / self.gross_total as u128) as u32
+    }
+
+    /// Obligation matrix ([i][j] = i owes j) over the canonical participant order
+    pub fn obligation_matrix(&self, obligations: &[(NetworkId, NetworkId, u64)]) -> Vec<Vec<u64>> {
+        let n = self.participants.len();
+        let mut matrix = vec![vec![0u64; n]; n];
+        for (from, to, amount) in obligations {
+            let i = self.participants.iter().position(|p| p == from);
+            let j = self.participants.iter().position(|p| p == to);
+            if let (Some(i), Some(j)) = (i, j) {
+                if i != j {
+                    matrix[i][j] = matrix[i][j].saturating_add(*amount);
+                }
+            }
+        }
+        matrix
+    }
+
+    /// Residual obligation matrix over the canonical participant order
+    pub fn residual_matrix(&self) -> Vec<Vec<u64>> {
+        self.obligation_matrix(&self.residual_obligations)
+    }
+
+    /// Net position values in canonical participant order
+    pub fn net_position_values(&self) -> Vec<i64> {
+        self.net_positions.iter().map(|(_, position)| *position).collect()
+    }
+}
+
+/// Iterative cycle-cancellation solver over arbitrary cycle lengths
+///
+/// Cancelling a cycle subtracts its minimum edge from every edge on it, which
+/// leaves every participant's net position unchanged while reducing gross
+/// obligations. Only existing bilateral obligations are reduced; the solver
+/// never introduces a payment relationship that was not already present.
+///
+/// Output is fully deterministic: participants are ordered canonically, shorter
+/// cycles are cancelled first and cycles are enumerated from their lowest
+/// participant index, so every validator computes the same result.
+#[derive(Debug, Clone, Default)]
+pub struct MultilateralNettingSolver {
+    config: NettingConfig,
+}
+
+impl MultilateralNettingSolver {
+    pub fn new(config: NettingConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &NettingConfig {
+        &self.config
+    }
+
+    /// Run multilateral netting over bilateral obligations (from owes to)
+    pub fn solve(&self, bilateral_amounts: &[(NetworkId, NetworkId, u64)]) -> std::result::Result<NettingResult, BlockchainError> {
+        if self.config.max_cycle_length < 2 {
+            return Err(BlockchainError::InvalidOperation(
+                format!("Netting max cycle length must be at least 2, got {}", self.config.max_cycle_length)
+            ));
+        }
+
+        // Canonical participant order, independent of input order
+        let mut index: BTreeMap<String, NetworkId> = BTreeMap::new();
+        for (from, to, _) in bilateral_amounts {
+            index.insert(from.to_string(), from.clone());
+            index.insert(to.to_string(), to.clone());
+        }
+        let participants: Vec<NetworkId> = index.into_values().collect();
+        let n = participants.len();
+        let position = |network: &NetworkId| participants.iter().position(|p| p == network);
+
+        // obligations[i][j] = amount participant i owes participant j
+        let mut obligations = vec![vec![0u64; n]; n];
+        let mut gross_total = 0u64;
+        for (from, to, amount) in bilateral_amounts {
+            if from == to {
+                continue;
+            }
+            if let (Some(i), Some(j)) = (position(from), position(to)) {
+                obligations[i][j] = obligations[i][j].checked_add(*amount)
+                    .ok_or_else(|| BlockchainError::InvalidOperation("Netting obligation overflow".to_string()))?;
+                gross_total = gross_total.checked_add(*amount)
+                    .ok_or_else(|| BlockchainError::InvalidOperation("Netting gross total overflow".to_string()))?;
+            }
+        }
+
+        // Net positions are signed, so a participant's position must fit an i64
+        if gross_total > i64::MAX as u64 {
+            return Err(BlockchainError::InvalidOperation("Netting gross total overflow".to_string()));
+        }
+
+        let max_length = self.config.max_cycle_length.min(n.max(2));
+        let mut cancelled_cycles = Vec::new();
+
+        'search: for length in 2..=max_length {
+            while let Some(cycle) = Self::find_cycle(&obligations, length) {
+                if cancelled_cycles.len() >= self.config.max_cancellations {
+                    debug!("Netting stopped after {} cancellations", cancelled_cycles.len());
+                    break 'search;
+                }
+
+                let amount = (0..cycle.len())
+                    .map(|k| obligations[cycle[k]][cycle[(k + 1) % cycle.len()]])
+                    .min()
+                    .unwrap_or(0);
+
+                for k in 0..cycle.len() {
+                    obligations[cycle[k]][cycle[(k + 1) % cycle.len()]] -= amount;
+                }
+
+                debug!("   Cancelled {}-cycle for €{:.2}", cycle.len(), amount as f64 / 100.0);
+                cancelled_cycles.push(NettedCycle {
+                    networks: cycle.iter().map(|&i| participants[i].clone()).collect(),
+                    amount,
+                });
+            }
+        }
+
+        let mut residual_obligations = Vec::new();
+        let mut net_positions = vec![0i64; n];
+        let mut residual_total = 0u64;
+        for i in 0..n {
+            for j in 0..n {
+                let amount = obligations[i][j];
+                if amount > 0 {
+                    residual_obligations.push((participants[i].clone(), participants[j].clone(), amount));
+                    residual_total += amount;
+                    net_positions[i] -= amount as i64;
+                    net_positions[j] += amount as i64;
+                }
+            }
+        }
+
+        // Conservation check: netting must neither create nor destroy value
+        let total_net: i64 = net_positions.iter().sum();
+        if total_net != 0 {
+            return Err(BlockchainError::InvalidOperation(
+                format!("Netting calculation error: net positions sum to {} instead of 0", total_net)
+            ));
+        }
+
+        info!("🔄 Multilateral netting: {} participants, {} cycles cancelled, €{:.2} → €{:.2}",
+              n, cancelled_cycles.len(), gross_total as f64 / 100.0, residual_total as f64 / 100.0);
+
+        Ok(NettingResult {
+            net_positions: participants.iter().cloned().zip(net_positions).collect(),
+            participants,
+            residual_obligations,
+            cancelled_cycles,
+            gross_total,
+            residual_total,
+        })
+    }
+
+    /// Find the first cycle of exactly `length` edges in canonical enumeration order
+    fn find_cycle(obligations: &[Vec<u64>], length: usize) -> Option<Vec<usize>> {
+        let n = obligations.len();
+        let mut path = Vec::with_capacity(length);
+
+        for start in 0..n {
+            path.clear();
+            path.push(start);
+            if Self::extend_path(obligations, length, &mut path) {
+                return Some(path);
+            }
+        }
+
+        None
+    }
+
+    /// Depth-first extension; only visits nodes above the start so each cycle has one rotation
+    fn extend_path(obligations: &[Vec<u64>], length: usize, path: &mut Vec<usize>) -> bool {
+        let start = path[0];
+        let last = *path.last().unwrap();
+
+        if path.len() == length {
+            return obligations[last][start] > 0;
+        }
+
+        for next in (start + 1)..obligations.len() {
+            if obligations[last][next] == 0 || path.contains(&next) {
+                continue;
+            }
+            path.push(next);
+            if Self::extend_path(obligations, length, path) {
+                return true;
+            }
+            path.pop();
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn op(name: &str) -> NetworkId {
+        NetworkId::new(name, "EU")
+    }
+
+    #[test]
+    fn test_four_party_cycle_fully_netted() {
+        let bilateral = vec![
+            (op("A"), op("B"), 10000),
+            (op("B"), op("C"), 10000),
+            (op("C"), op("D"), 10000),
+            (op("D"), op("A"), 10000),
+        ];
+
+        // Triangular-only netting leaves a 4-cycle untouched
+        let triangular = MultilateralNettingSolver::new(NettingConfig { max_cycle_length: 3, ..Default::default() });
+        assert_eq!(triangular.solve(&bilateral).unwrap().residual_total, 40000);
+
+        let solver = MultilateralNettingSolver::default();
+        let result = solver.solve(&bilateral).unwrap();
+        assert_eq!(result.residual_total, 0);
+        assert_eq!(result.cancelled_cycles.len(), 1);
+        assert_eq!(result.savings_percentage(), 100);
+        assert!(result.net_positions.iter().all(|(_, p)| *p == 0));
+    }
+
+    #[test]
+    fn test_net_positions_preserved() {
+        let bilateral = vec![
+            (op("A"), op("B"), 50000),
+            (op("B"), op("C"), 75000),
+            (op("C"), op("A"), 25000),
+            (op("B"), op("A"), 10000),
+            (op("C"), op("B"), 15000),
+            (op("A"), op("C"), 7500),
+            (op("C"), op("D"), 30000),
+            (op("D"), op("E"), 20000),
+            (op("E"), op("A"), 12000),
+        ];
+
+        let mut expected: BTreeMap<String, i64> = BTreeMap::new();
+        for (from, to, amount) in &bilateral {
+            *expected.entry(from.to_string()).or_insert(0) -= *amount as i64;
+            *expected.entry(to.to_string()).or_insert(0) += *amount as i64;
+        }
+
+        let result = MultilateralNettingSolver::default().solve(&bilateral).unwrap();
+        for (network, position) in &result.net_positions {
+            assert_eq!(expected[&network.to_string()], *position);
+        }
+        assert!(result.residual_total < result.gross_total);
+    }
+
+    #[test]
+    fn test_deterministic_output() {
+        let mut bilateral = vec![
+            (op("A"), op("B"), 300),
+            (op("B"), op("C"), 200),
+            (op("C"), op("A"), 100),
+            (op("C"), op("D"), 400),
+            (op("D"), op("B"), 250),
+        ];
+
+        let solver = MultilateralNettingSolver::default();
+        let first = solver.solve(&bilateral).unwrap();
+        bilateral.reverse();
+        let second = solver.solve(&bilateral).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_invalid_cycle_length() {
+        let solver = MultilateralNettingSolver::new(NettingConfig { max_cycle_length: 1, ..Default::default() });
+        assert!(solver.solve(&[(op("A"), op("B"), 1)]).is_err());
+    }
+
+    #[test]
+    fn test_positions_beyond_i64_rejected() {
+        let bilateral = vec![(op("A"), op("B"), u64::MAX / 2), (op("C"), op("B"), u64::MAX / 2)];
+        assert!(MultilateralNettingSolver::default().solve(&bilateral).is_err());
+    }
+
+    /// Random obligation graphs over up to 7 operators, self-obligations and parallel edges included
+    fn obligation_graph() -> impl Strategy<Value = Vec<(NetworkId, NetworkId, u64)>> {
+        let names = ["A", "B", "C", "D", "E", "F", "G"];
+        proptest::collection::vec((0..names.len(), 0..names.len(), 1..10_000_000u64), 0..40).prop_map(move |edges| {
+            edges.into_iter().map(|(from, to, amount)| (op(names[from]), op(names[to]), amount)).collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_netting_conserves_positions(bilateral in obligation_graph(), max_cycle_length in 2..8usize) {
+            let solver = MultilateralNettingSolver::new(NettingConfig { max_cycle_length, ..Default::default() });
+            let result = solver.solve(&bilateral).unwrap();
+
+            let mut expected: BTreeMap<String, i64> = BTreeMap::new();
+            for (from, to, amount) in bilateral.iter().filter(|(from, to, _)| from != to) {
+                *expected.entry(from.to_string()).or_insert(0) -= *amount as i64;
+                *expected.entry(to.to_string()).or_insert(0) += *amount as i64;
+            }
+            for (network, position) in &result.net_positions {
+                prop_assert_eq!(expected.get(&network.to_string()).copied().unwrap_or(0), *position);
+            }
+            prop_assert_eq!(result.net_position_values().iter().sum::<i64>(), 0);
+        }
+
+        #[test]
+        fn prop_netting_only_reduces_existing_flows(bilateral in obligation_graph()) {
+            let result = MultilateralNettingSolver::default().solve(&bilateral).unwrap();
+            let gross = result.obligation_matrix(&bilateral);
+            let residual = result.residual_matrix();
+
+            for (i, row) in residual.iter().enumerate() {
+                for (j, amount) in row.iter().enumerate() {
+                    prop_assert!(*amount <= gross[i][j]);
+                }
+            }
+            prop_assert!(result.residual_obligations.iter().all(|(from, to, amount)| from != to && *amount > 0));
+            prop_assert!(result.cancelled_cycles.iter().all(|cycle| cycle.amount > 0));
+            prop_assert_eq!(result.residual_total, residual.iter().flatten().sum::<u64>());
+        }
+
+        #[test]
+        fn prop_netting_terminates(bilateral in obligation_graph()) {
+            // Each cancellation clears at least one edge, so the cancellation bound is never reached
+            let result = MultilateralNettingSolver::default().solve(&bilateral).unwrap();
+            let edges = result.obligation_matrix(&bilateral).iter().flatten().filter(|amount| **amount > 0).count();
+            prop_assert!(result.cancelled_cycles.len() <= edges);
+
+            let capped = MultilateralNettingSolver::new(NettingConfig { max_cancellations: 1, ..Default::default() });
+            prop_assert!(capped.solve(&bilateral).unwrap().cancelled_cycles.len() <= 1);
+        }
+
+        #[test]
+        fn prop_longer_cycles_never_reduce_savings(bilateral in obligation_graph()) {
+            let mut previous: Option<NettingResult> = None;
+            for max_cycle_length in 2..8 {
+                let solver = MultilateralNettingSolver::new(NettingConfig { max_cycle_length, ..Default::default() });
+                let result = solver.solve(&bilateral).unwrap();
+                prop_assert!(result.residual_total <= result.gross_total);
+                if let Some(previous) = previous {
+                    prop_assert!(result.residual_total <= previous.residual_total);
+                    prop_assert!(result.savings_percentage() >= previous.savings_percentage());
+                }
+                previous = Some(result);
+            }
+        }
+    }
+}
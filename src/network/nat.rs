@@ -0,0 +1,57 @@
+// NAT traversal for operator nodes without a public address: AutoNAT tells a node whether peers
+// can dial it, consortium relay nodes forward connections to nodes that cannot be dialed, and
+// DCUtR upgrades relayed connections to direct ones by hole punching
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+
+/// Relays and whether this node serves as one
+#[derive(Debug, Clone, Default)]
+pub struct NatConfig {
+    /// Designated consortium relay nodes, each address ending in `/p2p/<peer id>`
+    pub relay_nodes: Vec<Multiaddr>,
+    /// Relay connections for other nodes, for publicly reachable nodes only
+    pub act_as_relay: bool,
+}
+
+impl NatConfig {
+    /// Peer ids of the relay nodes, from the `/p2p` suffix of their addresses
+    pub fn relay_peers(&self) -> Vec<PeerId> {
+        self.relay_nodes.iter().filter_map(relay_peer_id).collect()
+    }
+}
+
+/// Peer id a relay address ends in
+pub fn relay_peer_id(relay: &Multiaddr) -> Option<PeerId> {
+    match relay.iter().last() {
+        Some(Protocol::P2p(peer_id)) => Some(peer_id),
+        _ => None,
+    }
+}
+
+/// Address to listen on through `relay`, so peers reach this node over a relayed circuit
+pub fn relay_circuit_address(relay: &Multiaddr) -> Option<Multiaddr> {
+    relay_peer_id(relay)?;
+    Some(relay.clone().with(Protocol::P2pCircuit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_circuit_addresses() {
+        let relay_id = PeerId::random();
+        let relay: Multiaddr = format!("/ip4/198.51.100.7/tcp/9000/p2p/{}", relay_id).parse().unwrap();
+        assert_eq!(relay_peer_id(&relay), Some(relay_id));
+        assert_eq!(
+            relay_circuit_address(&relay).unwrap().to_string(),
+            format!("/ip4/198.51.100.7/tcp/9000/p2p/{}/p2p-circuit", relay_id)
+        );
+
+        // Without a peer id the relay cannot be reserved with
+        let anonymous: Multiaddr = "/ip4/198.51.100.7/tcp/9000".parse().unwrap();
+        assert!(relay_circuit_address(&anonymous).is_none());
+
+        let config = NatConfig { relay_nodes: vec![relay, anonymous], act_as_relay: false };
+        assert_eq!(config.relay_peers(), vec![relay_id]);
+    }
+}
@@ -0,0 +1,247 @@
+// Operator identity layer binding libp2p peer keys to consortium-issued NetworkIds
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+
+use crate::primitives::{BlockchainError, NetworkId, to_canonical_bytes};
+
+/// Agent version prefix of SP nodes, the certificate follows it in hex
+const AGENT_VERSION_PREFIX: &str = "sp-cdr-node/1.0.0";
+
+/// Separator between the agent version and the encoded certificate
+const CERTIFICATE_MARKER: &str = " cert=";
+
+/// Consortium-issued certificate binding an operator's libp2p peer key to its NetworkId
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorCertificate {
+    pub network_id: NetworkId,
+    /// Further operator identities the node hosts and may speak for
+    pub hosted: Vec<NetworkId>,
+    /// Peer id bytes of the certified node key
+    pub peer_id: Vec<u8>,
+    pub issued_at: u64,
+    pub expires_at: u64,
+    /// Protobuf-encoded public key of the issuing consortium authority
+    pub issuer: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl OperatorCertificate {
+    /// Bytes covered by the issuer signature
+    fn signing_payload(network_id: &NetworkId, hosted: &[NetworkId], peer_id: &[u8], issued_at: u64, expires_at: u64) -> Vec<u8> {
+        let mut payload = b"sp-cdr-operator-certificate".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(&(network_id, hosted, peer_id, issued_at, expires_at))
+            .expect("certificate fields have a canonical encoding"));
+        payload
+    }
+
+    pub fn peer_id(&self) -> std::result::Result<PeerId, BlockchainError> {
+        PeerId::from_bytes(&self.peer_id)
+            .map_err(|e| BlockchainError::Crypto(format!("Invalid certificate peer id: {}", e)))
+    }
+
+    pub fn to_bytes(&self) -> std::result::Result<Vec<u8>, BlockchainError> {
+        bincode::serialize(self).map_err(|e| BlockchainError::Serialization(e.to_string()))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, BlockchainError> {
+        bincode::deserialize(bytes).map_err(|e| BlockchainError::Serialization(e.to_string()))
+    }
+
+    /// Identify agent version carrying this certificate
+    pub fn to_agent_version(&self) -> std::result::Result<String, BlockchainError> {
+        Ok(format!("{}{}{}", AGENT_VERSION_PREFIX, CERTIFICATE_MARKER, hex::encode(self.to_bytes()?)))
+    }
+
+    /// Extract the certificate from a peer's identify agent version
+    pub fn from_agent_version(agent_version: &str) -> std::result::Result<Self, BlockchainError> {
+        let (_, encoded) = agent_version.split_once(CERTIFICATE_MARKER)
+            .ok_or_else(|| BlockchainError::Crypto("Peer presented no operator certificate".to_string()))?;
+        let bytes = hex::decode(encoded.trim())
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid certificate encoding: {}", e)))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Consortium authority issuing operator certificates
+pub struct ConsortiumAuthority {
+    keypair: Keypair,
+}
+
+impl ConsortiumAuthority {
+    pub fn new(keypair: Keypair) -> Self {
+        Self { keypair }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.keypair.public()
+    }
+
+    /// Certify that `peer_id` speaks for `network_id`, and the `hosted` identities, for `validity_secs`
+    pub fn issue(
+        &self,
+        network_id: NetworkId,
+        hosted: Vec<NetworkId>,
+        peer_id: PeerId,
+        validity_secs: u64,
+    ) -> std::result::Result<OperatorCertificate, BlockchainError> {
+        let issued_at = chrono::Utc::now().timestamp() as u64;
+        let expires_at = issued_at + validity_secs;
+        let peer_id = peer_id.to_bytes();
+
+        let payload = OperatorCertificate::signing_payload(&network_id, &hosted, &peer_id, issued_at, expires_at);
+        let signature = self.keypair.sign(&payload)
+            .map_err(|e| BlockchainError::Crypto(format!("Certificate signing failed: {}", e)))?;
+
+        info!("📜 Issued operator certificate for {} to {}", network_id, PeerId::from_bytes(&peer_id).expect("encoded above"));
+
+        Ok(OperatorCertificate {
+            network_id,
+            hosted,
+            peer_id,
+            issued_at,
+            expires_at,
+            issuer: self.keypair.public().encode_protobuf(),
+            signature,
+        })
+    }
+}
+
+/// Verifies operator certificates presented during the identify handshake
+#[derive(Debug, Clone)]
+pub struct OperatorIdentityVerifier {
+    trusted_issuers: Vec<PublicKey>,
+}
+
+impl OperatorIdentityVerifier {
+    pub fn new(trusted_issuers: Vec<PublicKey>) -> Self {
+        Self { trusted_issuers }
+    }
+
+    /// Verify that the certificate binds `peer_id` and return the certified NetworkId
+    pub fn verify(
+        &self,
+        certificate: &OperatorCertificate,
+        peer_id: &PeerId,
+        now: u64,
+    ) -> std::result::Result<NetworkId, BlockchainError> {
+        if certificate.peer_id()? != *peer_id {
+            return Err(BlockchainError::Crypto(format!(
+                "Certificate for {} was presented by {}", certificate.network_id, peer_id
+            )));
+        }
+
+        if now < certificate.issued_at || now >= certificate.expires_at {
+            return Err(BlockchainError::Crypto(format!(
+                "Certificate for {} is not valid at {}", certificate.network_id, now
+            )));
+        }
+
+        let issuer = PublicKey::try_decode_protobuf(&certificate.issuer)
+            .map_err(|e| BlockchainError::Crypto(format!("Invalid certificate issuer: {}", e)))?;
+        if !self.trusted_issuers.contains(&issuer) {
+            return Err(BlockchainError::Crypto("Certificate issuer is not a trusted consortium authority".to_string()));
+        }
+
+        let payload = OperatorCertificate::signing_payload(
+            &certificate.network_id,
+            &certificate.hosted,
+            &certificate.peer_id,
+            certificate.issued_at,
+            certificate.expires_at,
+        );
+        if !issuer.verify(&payload, &certificate.signature) {
+            return Err(BlockchainError::Crypto(format!(
+                "Invalid issuer signature on certificate for {}", certificate.network_id
+            )));
+        }
+
+        Ok(certificate.network_id.clone())
+    }
+
+    /// Verify the certificate carried in an identify agent version and return it
+    pub fn verify_agent_version(
+        &self,
+        agent_version: &str,
+        peer_id: &PeerId,
+        now: u64,
+    ) -> std::result::Result<OperatorCertificate, BlockchainError> {
+        let certificate = OperatorCertificate::from_agent_version(agent_version)?;
+        self.verify(&certificate, peer_id, now)?;
+        Ok(certificate)
+    }
+}
+
+/// Load the node key from `path`, generating it on first start
+/// Certificates bind the peer id, so the key must survive restarts
+pub fn load_or_generate_node_key(path: &Path) -> std::result::Result<Keypair, BlockchainError> {
+    if path.exists() {
+        let bytes = std::fs::read(path).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+        return Keypair::from_protobuf_encoding(&bytes)
+            .map_err(|e| BlockchainError::Crypto(format!("Invalid node key: {}", e)));
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    let bytes = keypair.to_protobuf_encoding()
+        .map_err(|e| BlockchainError::Crypto(format!("Node key encoding failed: {}", e)))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+    }
+    std::fs::write(path, bytes).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+
+    info!("🔑 Generated node key at {}", path.display());
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_certificate_binding() {
+        let authority = ConsortiumAuthority::new(Keypair::generate_ed25519());
+        let verifier = OperatorIdentityVerifier::new(vec![authority.public_key()]);
+
+        let node_key = Keypair::generate_ed25519();
+        let peer_id = node_key.public().to_peer_id();
+        let vodafone = NetworkId::new("Vodafone", "UK");
+
+        let certificate = authority.issue(vodafone.clone(), vec![NetworkId::new("Vodafone", "DE")], peer_id, 3600).unwrap();
+        let agent_version = certificate.to_agent_version().unwrap();
+        let now = certificate.issued_at;
+        assert_eq!(verifier.verify_agent_version(&agent_version, &peer_id, now).unwrap(), certificate);
+
+        // Another node cannot reuse the certificate
+        let impostor = Keypair::generate_ed25519().public().to_peer_id();
+        assert!(verifier.verify_agent_version(&agent_version, &impostor, now).is_err());
+
+        // Self-asserted NetworkIds are rejected
+        let mut forged = certificate.clone();
+        forged.network_id = NetworkId::new("Orange", "FR");
+        assert!(verifier.verify(&forged, &peer_id, now).is_err());
+        let mut forged = certificate.clone();
+        forged.hosted.push(NetworkId::new("Orange", "FR"));
+        assert!(verifier.verify(&forged, &peer_id, now).is_err());
+
+        // Expired and untrusted certificates are rejected
+        assert!(verifier.verify(&certificate, &peer_id, certificate.expires_at).is_err());
+        let rogue = ConsortiumAuthority::new(Keypair::generate_ed25519());
+        let rogue_certificate = rogue.issue(vodafone, vec![], peer_id, 3600).unwrap();
+        assert!(verifier.verify(&rogue_certificate, &peer_id, now).is_err());
+
+        // Nodes without a certificate are rejected
+        assert!(verifier.verify_agent_version(AGENT_VERSION_PREFIX, &peer_id, now).is_err());
+    }
+
+    #[test]
+    fn test_node_key_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.key");
+
+        let first = load_or_generate_node_key(&path).unwrap();
+        let second = load_or_generate_node_key(&path).unwrap();
+        assert_eq!(first.public(), second.public());
+    }
+}
@@ -1,11 +1,32 @@
 // Peer discovery for SP CDR reconciliation network
 use libp2p::{Multiaddr, PeerId};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{info, debug, error};
+use tracing::{info, debug, warn, error};
 use serde::{Deserialize, Serialize};
 
 use crate::primitives::{NetworkId, Blake2bHash, BlockchainError};
+use crate::storage::MdbxChainStore;
+use super::{serialize_peer_id, deserialize_peer_id};
+use super::connection_manager::PeerBan;
+
+/// Key of the peer id index in the peers table
+const PEER_INDEX_KEY: &[u8] = b"index";
+
+/// Key of the ban list in the peers table
+const BAN_LIST_KEY: &[u8] = b"bans";
+
+/// Peers at or below this reputation are no longer dialed
+pub const MIN_DIAL_REPUTATION: i32 = -20;
+
+/// Reputation bounds so one long-lived peer cannot become unbannable
+const MAX_REPUTATION: i32 = 100;
+
+/// DHT key under which nodes serving an operator identity announce themselves
+pub fn operator_provider_key(network_id: &NetworkId) -> Vec<u8> {
+    Blake2bHash::from_data(format!("sp-operator:{}", network_id).as_bytes()).as_bytes().to_vec()
+}
 
 fn default_peer_id() -> PeerId {
     PeerId::random()
@@ -27,6 +48,281 @@ pub struct SPOperatorInfo {
     pub last_seen: u64, // timestamp
 }
 
+/// Persisted knowledge about a peer, survives node restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerRecord {
+    #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+    pub peer_id: PeerId,
+    pub addresses: Vec<Multiaddr>,
+    pub last_seen: u64,
+    pub reputation: i32,
+    /// Consecutive failed dials, reset on a successful connection
+    pub failed_dials: u32,
+    /// Earliest time the peer may be dialed again
+    pub next_dial_at: u64,
+}
+
+impl PeerRecord {
+    fn new(peer_id: PeerId) -> Self {
+        Self {
+            peer_id,
+            addresses: Vec::new(),
+            last_seen: 0,
+            reputation: 0,
+            failed_dials: 0,
+            next_dial_at: 0,
+        }
+    }
+}
+
+/// Exponential reconnection backoff
+#[derive(Debug, Clone)]
+pub struct ReconnectBackoff {
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl ReconnectBackoff {
+    /// Delay before the next dial after `failed_dials` consecutive failures
+    pub fn delay_secs(&self, failed_dials: u32) -> u64 {
+        if failed_dials == 0 {
+            return 0;
+        }
+        let factor = 1u64.checked_shl(failed_dials - 1).unwrap_or(u64::MAX);
+        self.initial_delay_secs.saturating_mul(factor).min(self.max_delay_secs)
+    }
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self {
+            initial_delay_secs: 5,
+            max_delay_secs: 600, // 10 minutes
+        }
+    }
+}
+
+/// Peer store with addresses, last-seen times, reputation and bans
+/// Kept in memory and written through to MDBX when a database is attached
+pub struct PeerStore {
+    peers: RwLock<HashMap<PeerId, PeerRecord>>,
+    bans: RwLock<HashMap<PeerId, PeerBan>>,
+    db: Option<MdbxChainStore>,
+    backoff: ReconnectBackoff,
+}
+
+impl PeerStore {
+    /// Peer store without persistence
+    pub fn in_memory() -> Self {
+        Self {
+            peers: RwLock::new(HashMap::new()),
+            bans: RwLock::new(HashMap::new()),
+            db: None,
+            backoff: ReconnectBackoff::default(),
+        }
+    }
+
+    /// Open the peer store persisted in the chain database
+    pub async fn open(db: MdbxChainStore) -> std::result::Result<Self, BlockchainError> {
+        let mut peers = HashMap::new();
+
+        if let Some(index) = db.get_peer_record(PEER_INDEX_KEY).await? {
+            let peer_ids: Vec<String> = bincode::deserialize(&index)
+                .map_err(|e| BlockchainError::Storage(format!("Peer index deserialize failed: {}", e)))?;
+
+            for peer_id in peer_ids {
+                let peer_id: PeerId = match peer_id.parse() {
+                    Ok(peer_id) => peer_id,
+                    Err(_) => continue,
+                };
+                if let Some(data) = db.get_peer_record(&peer_id.to_bytes()).await? {
+                    match bincode::deserialize::<PeerRecord>(&data) {
+                        Ok(record) => { peers.insert(peer_id, record); }
+                        Err(e) => warn!("Dropping unreadable peer record for {}: {}", peer_id, e),
+                    }
+                }
+            }
+        }
+
+        let mut bans = HashMap::new();
+        if let Some(data) = db.get_peer_record(BAN_LIST_KEY).await? {
+            let list: Vec<PeerBan> = bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Storage(format!("Ban list deserialize failed: {}", e)))?;
+            bans.extend(list.into_iter().map(|ban| (ban.peer_id, ban)));
+        }
+
+        info!("📇 Loaded {} known peers and {} bans from peer store", peers.len(), bans.len());
+
+        Ok(Self {
+            peers: RwLock::new(peers),
+            bans: RwLock::new(bans),
+            db: Some(db),
+            backoff: ReconnectBackoff::default(),
+        })
+    }
+
+    pub fn with_backoff(mut self, backoff: ReconnectBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Record a successful connection, remembering the address if we dialed it
+    pub async fn record_connected(&self, peer_id: PeerId, address: Option<Multiaddr>) -> std::result::Result<(), BlockchainError> {
+        self.update(peer_id, |record| {
+            if let Some(address) = address {
+                record.addresses.retain(|known| *known != address);
+                record.addresses.insert(0, address);
+            }
+            record.last_seen = chrono::Utc::now().timestamp() as u64;
+            record.reputation = (record.reputation + 1).min(MAX_REPUTATION);
+            record.failed_dials = 0;
+            record.next_dial_at = 0;
+        }).await
+    }
+
+    /// Record addresses a peer announced, e.g. through identify
+    pub async fn record_addresses(&self, peer_id: PeerId, addresses: Vec<Multiaddr>) -> std::result::Result<(), BlockchainError> {
+        self.update(peer_id, |record| {
+            for address in addresses {
+                if !record.addresses.contains(&address) {
+                    record.addresses.push(address);
+                }
+            }
+        }).await
+    }
+
+    /// Record a failed dial and schedule the next attempt with exponential backoff
+    pub async fn record_dial_failure(&self, peer_id: PeerId) -> std::result::Result<(), BlockchainError> {
+        let backoff = self.backoff.clone();
+        self.update(peer_id, |record| {
+            record.failed_dials = record.failed_dials.saturating_add(1);
+            record.reputation = (record.reputation - 1).max(-MAX_REPUTATION);
+            record.next_dial_at = chrono::Utc::now().timestamp() as u64 + backoff.delay_secs(record.failed_dials);
+        }).await
+    }
+
+    /// Reward or penalize a peer, e.g. for invalid messages
+    pub async fn adjust_reputation(&self, peer_id: PeerId, delta: i32) -> std::result::Result<(), BlockchainError> {
+        self.update(peer_id, |record| {
+            record.reputation = (record.reputation + delta).clamp(-MAX_REPUTATION, MAX_REPUTATION);
+        }).await
+    }
+
+    /// Ban a peer until `until`, replacing any earlier ban
+    pub async fn ban(&self, peer_id: PeerId, until: u64, reason: String) -> std::result::Result<(), BlockchainError> {
+        let mut bans = self.bans.write().await;
+        bans.insert(peer_id, PeerBan { peer_id, until, reason });
+        self.persist_bans(&bans).await
+    }
+
+    /// Lift a peer's ban, returns whether it was banned
+    pub async fn unban(&self, peer_id: &PeerId) -> std::result::Result<bool, BlockchainError> {
+        let mut bans = self.bans.write().await;
+        if bans.remove(peer_id).is_none() {
+            return Ok(false);
+        }
+        self.persist_bans(&bans).await?;
+        Ok(true)
+    }
+
+    pub async fn is_banned(&self, peer_id: &PeerId, now: u64) -> bool {
+        self.bans.read().await.get(peer_id).is_some_and(|ban| ban.is_active(now))
+    }
+
+    /// Bans still in force at `now`
+    pub async fn active_bans(&self, now: u64) -> Vec<PeerBan> {
+        self.bans.read().await.values().filter(|ban| ban.is_active(now)).cloned().collect()
+    }
+
+    /// Drop the bans expired at `now`, returning the peers they were lifted from
+    pub async fn expire_bans(&self, now: u64) -> std::result::Result<Vec<PeerId>, BlockchainError> {
+        let mut bans = self.bans.write().await;
+        let expired: Vec<PeerId> = bans.values().filter(|ban| !ban.is_active(now)).map(|ban| ban.peer_id).collect();
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+        for peer_id in &expired {
+            bans.remove(peer_id);
+        }
+        self.persist_bans(&bans).await?;
+        Ok(expired)
+    }
+
+    /// Peers whose backoff has expired, whose reputation still allows dialing and who are not banned
+    pub async fn peers_due_for_dial(&self, now: u64) -> Vec<PeerRecord> {
+        let peers = self.peers.read().await;
+        let bans = self.bans.read().await;
+        let mut due: Vec<PeerRecord> = peers.values()
+            .filter(|record| {
+                !record.addresses.is_empty()
+                    && record.next_dial_at <= now
+                    && record.reputation > MIN_DIAL_REPUTATION
+                    && !bans.get(&record.peer_id).is_some_and(|ban| ban.is_active(now))
+            })
+            .cloned()
+            .collect();
+
+        // Best peers first
+        due.sort_by(|a, b| b.reputation.cmp(&a.reputation).then(b.last_seen.cmp(&a.last_seen)));
+        due
+    }
+
+    pub async fn get(&self, peer_id: &PeerId) -> Option<PeerRecord> {
+        self.peers.read().await.get(peer_id).cloned()
+    }
+
+    pub async fn all_peers(&self) -> Vec<PeerRecord> {
+        self.peers.read().await.values().cloned().collect()
+    }
+
+    /// Write the ban list through to MDBX
+    async fn persist_bans(&self, bans: &HashMap<PeerId, PeerBan>) -> std::result::Result<(), BlockchainError> {
+        let db = match &self.db {
+            Some(db) => db,
+            None => return Ok(()),
+        };
+        let list: Vec<&PeerBan> = bans.values().collect();
+        let data = bincode::serialize(&list)
+            .map_err(|e| BlockchainError::Storage(format!("Ban list serialize failed: {}", e)))?;
+        db.put_peer_record(BAN_LIST_KEY, &data).await
+    }
+
+    /// Apply a change to a peer record and write it through to MDBX
+    async fn update(&self, peer_id: PeerId, update_fn: impl FnOnce(&mut PeerRecord)) -> std::result::Result<(), BlockchainError> {
+        let (record, index) = {
+            let mut peers = self.peers.write().await;
+            let is_new = !peers.contains_key(&peer_id);
+            let record = peers.entry(peer_id).or_insert_with(|| PeerRecord::new(peer_id));
+            update_fn(record);
+
+            let record = record.clone();
+            let index = if is_new {
+                Some(peers.keys().map(|peer_id| peer_id.to_string()).collect::<Vec<_>>())
+            } else {
+                None
+            };
+            (record, index)
+        };
+
+        let db = match &self.db {
+            Some(db) => db,
+            None => return Ok(()),
+        };
+
+        let data = bincode::serialize(&record)
+            .map_err(|e| BlockchainError::Storage(format!("Peer record serialize failed: {}", e)))?;
+        db.put_peer_record(&peer_id.to_bytes(), &data).await?;
+
+        if let Some(index) = index {
+            let data = bincode::serialize(&index)
+                .map_err(|e| BlockchainError::Storage(format!("Peer index serialize failed: {}", e)))?;
+            db.put_peer_record(PEER_INDEX_KEY, &data).await?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Known SP operators in the consortium
 #[derive(Debug)]
 pub struct PeerDiscovery {
@@ -38,6 +334,18 @@ pub struct PeerDiscovery {
 
     /// Bootstrap nodes for initial discovery
     bootstrap_nodes: Vec<Multiaddr>,
+
+    /// Persistent peer store shared with the network manager
+    peer_store: Option<Arc<PeerStore>>,
+
+    /// Validators behind this sentry, never recorded as operator endpoints
+    hidden_peers: RwLock<HashSet<PeerId>>,
+}
+
+impl std::fmt::Debug for PeerStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeerStore").field("persistent", &self.db.is_some()).finish()
+    }
 }
 
 impl PeerDiscovery {
@@ -47,7 +355,34 @@ impl PeerDiscovery {
             operators: RwLock::new(HashMap::new()),
             network_to_peer: RwLock::new(HashMap::new()),
             bootstrap_nodes,
+            peer_store: None,
+            hidden_peers: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// Attach the persistent peer store
+    pub fn set_peer_store(&mut self, peer_store: Arc<PeerStore>) {
+        self.peer_store = Some(peer_store);
+    }
+
+    pub fn peer_store(&self) -> Option<&Arc<PeerStore>> {
+        self.peer_store.as_ref()
+    }
+
+    /// Addresses to dial at startup: bootstrap nodes, then remembered peers
+    pub async fn initial_dial_addresses(&self) -> Vec<Multiaddr> {
+        let mut addresses = self.bootstrap_nodes.clone();
+        if let Some(peer_store) = &self.peer_store {
+            let now = chrono::Utc::now().timestamp() as u64;
+            for record in peer_store.peers_due_for_dial(now).await {
+                for address in record.addresses {
+                    if !addresses.contains(&address) {
+                        addresses.push(address);
+                    }
+                }
+            }
         }
+        addresses
     }
 
     /// Initialize with known SP consortium members
@@ -132,6 +467,61 @@ impl PeerDiscovery {
         Ok(())
     }
 
+    /// Keep these peers out of the operator table, so their addresses are never passed on
+    pub async fn hide_peers(&self, peers: HashSet<PeerId>) {
+        let mut operators = self.operators.write().await;
+        let mut network_to_peer = self.network_to_peer.write().await;
+        operators.retain(|peer_id, _| !peers.contains(peer_id));
+        network_to_peer.retain(|_, peer_id| !peers.contains(peer_id));
+        self.hidden_peers.write().await.extend(peers);
+    }
+
+    /// Record a peer found in the DHT as provider of an operator identity
+    pub async fn record_operator_provider(
+        &self,
+        network_id: NetworkId,
+        peer_id: PeerId,
+        addresses: Vec<Multiaddr>,
+    ) -> std::result::Result<(), BlockchainError> {
+        if self.hidden_peers.read().await.contains(&peer_id) {
+            return Ok(());
+        }
+
+        let mut operators = self.operators.write().await;
+        let mut network_to_peer = self.network_to_peer.write().await;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let operator = operators.entry(peer_id).or_insert_with(|| {
+            let (operator_name, country_code) = match &network_id {
+                NetworkId::Operator { name, country } => (name.clone(), country.clone()),
+                other => (other.to_string(), String::new()),
+            };
+            SPOperatorInfo {
+                peer_id,
+                network_id: network_id.clone(),
+                operator_name,
+                country_code,
+                endpoints: Vec::new(),
+                validator_stake: 0,
+                supported_currencies: Vec::new(),
+                is_validator: false,
+                last_seen: now,
+            }
+        });
+
+        for address in addresses {
+            if !operator.endpoints.contains(&address) {
+                operator.endpoints.push(address);
+            }
+        }
+        operator.last_seen = now;
+
+        debug!("DHT provider {} serves {:?}", peer_id, network_id);
+        network_to_peer.insert(network_id, peer_id);
+
+        Ok(())
+    }
+
     /// Update operator information
     pub async fn update_operator(&self, peer_id: PeerId, update_fn: impl FnOnce(&mut SPOperatorInfo)) -> std::result::Result<(), BlockchainError> {
         let mut operators = self.operators.write().await;
@@ -318,4 +708,95 @@ mod tests {
         assert!(topology.has_sufficient_validators());
         assert_eq!(topology.total_operators, 3);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_peer_store_backoff_and_persistence() {
+        let backoff = ReconnectBackoff::default();
+        assert_eq!(backoff.delay_secs(0), 0);
+        assert_eq!(backoff.delay_secs(1), 5);
+        assert_eq!(backoff.delay_secs(3), 20);
+        assert_eq!(backoff.delay_secs(40), 600);
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = MdbxChainStore::new(dir.path()).unwrap();
+        let peer = PeerId::random();
+        let address: Multiaddr = "/ip4/192.0.2.10/tcp/8000".parse().unwrap();
+
+        {
+            let store = PeerStore::open(db.clone()).await.unwrap();
+            store.record_connected(peer, Some(address.clone())).await.unwrap();
+            store.record_dial_failure(peer).await.unwrap();
+
+            // Backed off right after the failure
+            let now = chrono::Utc::now().timestamp() as u64;
+            assert!(store.peers_due_for_dial(now).await.is_empty());
+            assert_eq!(store.peers_due_for_dial(now + 5).await.len(), 1);
+        }
+
+        // Reopening restores the record
+        let store = PeerStore::open(db).await.unwrap();
+        let record = store.get(&peer).await.unwrap();
+        assert_eq!(record.addresses, vec![address]);
+        assert_eq!(record.failed_dials, 1);
+        assert_eq!(record.reputation, 0);
+
+        // Misbehaving peers are no longer dialed
+        store.adjust_reputation(peer, MIN_DIAL_REPUTATION).await.unwrap();
+        assert!(store.peers_due_for_dial(u64::MAX).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_peer_bans_expire_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = MdbxChainStore::new(dir.path()).unwrap();
+        let peer = PeerId::random();
+        let address: Multiaddr = "/ip4/192.0.2.11/tcp/8000".parse().unwrap();
+
+        {
+            let store = PeerStore::open(db.clone()).await.unwrap();
+            store.record_connected(peer, Some(address)).await.unwrap();
+            store.ban(peer, 1_000, "connection rate exceeded".to_string()).await.unwrap();
+            assert!(store.is_banned(&peer, 999).await);
+            assert!(store.peers_due_for_dial(999).await.is_empty());
+        }
+
+        // Reopening restores the ban, which lifts at its expiry
+        let store = PeerStore::open(db.clone()).await.unwrap();
+        assert_eq!(store.active_bans(999).await.len(), 1);
+        assert_eq!(store.peers_due_for_dial(1_000).await.len(), 1);
+        assert!(store.expire_bans(999).await.unwrap().is_empty());
+        assert_eq!(store.expire_bans(1_000).await.unwrap(), vec![peer]);
+        assert!(PeerStore::open(db.clone()).await.unwrap().active_bans(0).await.is_empty());
+
+        store.ban(peer, u64::MAX, "manual".to_string()).await.unwrap();
+        assert!(store.unban(&peer).await.unwrap());
+        assert!(!store.unban(&peer).await.unwrap());
+        assert!(!store.is_banned(&peer, 0).await);
+    }
+
+    #[tokio::test]
+    async fn test_operator_provider_records() {
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        assert_eq!(operator_provider_key(&vodafone), operator_provider_key(&NetworkId::new("Vodafone", "UK")));
+        assert_ne!(operator_provider_key(&vodafone), operator_provider_key(&NetworkId::new("Orange", "FR")));
+
+        let discovery = PeerDiscovery::new(vec![]);
+        let peer = PeerId::random();
+        let address: Multiaddr = "/ip4/192.0.2.20/tcp/8000".parse().unwrap();
+
+        discovery.record_operator_provider(vodafone.clone(), peer, vec![address.clone()]).await.unwrap();
+        discovery.record_operator_provider(vodafone.clone(), peer, vec![address.clone()]).await.unwrap();
+
+        let operator = discovery.find_by_network(&vodafone).await.unwrap();
+        assert_eq!(operator.peer_id, peer);
+        assert_eq!(operator.country_code, "UK");
+        assert_eq!(operator.endpoints, vec![address.clone()]);
+        assert!(!operator.is_validator);
+
+        // A sentry never records the validators behind it
+        discovery.hide_peers(HashSet::from([peer])).await;
+        assert!(discovery.find_by_network(&vodafone).await.is_none());
+        discovery.record_operator_provider(vodafone.clone(), peer, vec![address]).await.unwrap();
+        assert!(discovery.find_by_peer(&peer).await.is_none());
+    }
+}
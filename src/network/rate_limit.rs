@@ -0,0 +1,189 @@
+// Token bucket rate limits on settlement and CDR messages, per sending peer and per operator
+// across all of its nodes, so one operator flooding proposals cannot exhaust negotiation state
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::primitives::NetworkId;
+
+/// Kind of message a bucket is drawn from, settlement and CDR traffic being limited apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    Settlement,
+    Cdr,
+}
+
+/// Burst and sustained message rate of each bucket
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Messages a peer may send at once, and per second after that
+    pub peer_burst: u32,
+    pub peer_per_sec: f64,
+    /// Messages all nodes of one operator may send at once, and per second after that
+    pub operator_burst: u32,
+    pub operator_per_sec: f64,
+    /// Buckets of senders quiet for this long are forgotten
+    pub idle_timeout: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            peer_burst: 20,
+            peer_per_sec: 2.0,
+            operator_burst: 50,
+            operator_per_sec: 5.0,
+            idle_timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Whether a message is handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitVerdict {
+    Allowed,
+    /// The sending peer is over its rate
+    PeerLimited,
+    /// The operator the message is from is over its rate
+    OperatorLimited(NetworkId),
+}
+
+/// Tokens refilling at a fixed rate up to a burst, one taken per message
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Full bucket holding `capacity` tokens
+    pub fn new(capacity: u32, refill_per_sec: f64, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_sec,
+            tokens: capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Whether a token was left
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Tokens that would be available at `now`, without taking one
+    pub fn available(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+/// Buckets per peer and per operator for each message class
+#[derive(Debug)]
+pub struct MessageRateLimiter {
+    config: RateLimitConfig,
+    peers: HashMap<(PeerId, MessageClass), TokenBucket>,
+    operators: HashMap<(NetworkId, MessageClass), TokenBucket>,
+}
+
+impl MessageRateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            peers: HashMap::new(),
+            operators: HashMap::new(),
+        }
+    }
+
+    /// Take a token for a message of `class` from `peer`, sent on behalf of `operator`
+    /// A message over either limit takes no token from the other
+    pub fn check(&mut self, class: MessageClass, peer: PeerId, operator: Option<&NetworkId>, now: Instant) -> RateLimitVerdict {
+        let config = &self.config;
+        let peer_bucket = self.peers.entry((peer, class))
+            .or_insert_with(|| TokenBucket::new(config.peer_burst, config.peer_per_sec, now));
+        if peer_bucket.available(now) < 1.0 {
+            return RateLimitVerdict::PeerLimited;
+        }
+
+        if let Some(operator) = operator {
+            let operator_bucket = self.operators.entry((operator.clone(), class))
+                .or_insert_with(|| TokenBucket::new(config.operator_burst, config.operator_per_sec, now));
+            if !operator_bucket.try_take(now) {
+                return RateLimitVerdict::OperatorLimited(operator.clone());
+            }
+        }
+        peer_bucket.try_take(now);
+        RateLimitVerdict::Allowed
+    }
+
+    /// Forget the buckets of senders quiet for longer than the idle timeout
+    pub fn prune(&mut self, now: Instant) {
+        let idle_timeout = self.config.idle_timeout;
+        self.peers.retain(|_, bucket| now.saturating_duration_since(bucket.updated) < idle_timeout);
+        self.operators.retain(|_, bucket| now.saturating_duration_since(bucket.updated) < idle_timeout);
+    }
+
+    /// Senders with a bucket, peers and operators
+    pub fn tracked(&self) -> usize {
+        self.peers.len() + self.operators.len()
+    }
+}
+
+impl Default for MessageRateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_and_operator_buckets() {
+        let config = RateLimitConfig {
+            peer_burst: 3,
+            peer_per_sec: 1.0,
+            operator_burst: 3,
+            operator_per_sec: 1.0,
+            idle_timeout: Duration::from_secs(60),
+        };
+        let mut limiter = MessageRateLimiter::new(config);
+        let now = Instant::now();
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let (first, second) = (PeerId::random(), PeerId::random());
+
+        // A burst, then one message per second
+        for _ in 0..3 {
+            assert_eq!(limiter.check(MessageClass::Settlement, first, Some(&vodafone), now), RateLimitVerdict::Allowed);
+        }
+        assert_eq!(limiter.check(MessageClass::Settlement, first, Some(&vodafone), now), RateLimitVerdict::PeerLimited);
+        // CDR messages are limited apart from settlement messages
+        assert_eq!(limiter.check(MessageClass::Cdr, first, Some(&vodafone), now), RateLimitVerdict::Allowed);
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.check(MessageClass::Settlement, first, Some(&vodafone), later), RateLimitVerdict::Allowed);
+
+        // The operator's other nodes share its bucket, which the first node emptied
+        assert_eq!(
+            limiter.check(MessageClass::Settlement, second, Some(&vodafone), later),
+            RateLimitVerdict::OperatorLimited(vodafone.clone())
+        );
+        assert_eq!(limiter.check(MessageClass::Settlement, second, None, later), RateLimitVerdict::Allowed);
+
+        limiter.prune(later + Duration::from_secs(61));
+        assert_eq!(limiter.tracked(), 0);
+    }
+}
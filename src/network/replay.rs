@@ -0,0 +1,336 @@
+// Message recording and deterministic replay for debugging consensus failures. A recorder logs
+// every inbound consensus and settlement message, and every round timeout that fired, with its
+// offset from the start of the recording to a JSON lines file. The replay engine feeds the
+// recording into fresh ConsensusNetwork and SettlementMessaging instances one entry at a time,
+// firing timeouts at their recorded offsets, so a stall seen in the field can be stepped through
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::primitives::{BlockchainError, NetworkId, Result};
+use super::consensus_networking::{ConsensusMessage, ConsensusNetwork};
+use super::settlement_messaging::{SettlementMessage, SettlementMessaging};
+use super::{deserialize_peer_id, serialize_peer_id};
+
+/// Version of the replay file format
+pub const REPLAY_FORMAT_VERSION: u16 = 1;
+
+/// First line of a replay file, naming the node the recording was taken on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u16,
+    pub network_id: NetworkId,
+    #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+    pub local_peer_id: PeerId,
+    /// Unix time in milliseconds the recording started at
+    pub started_at: u64,
+}
+
+/// Input to the consensus or settlement state machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    Consensus {
+        #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+        from_peer: PeerId,
+        message: ConsensusMessage,
+    },
+    Settlement {
+        #[serde(serialize_with = "serialize_peer_id", deserialize_with = "deserialize_peer_id")]
+        from_peer: PeerId,
+        message: SettlementMessage,
+    },
+    /// Round timer tick that found the consensus phase timed out
+    Timeout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// Milliseconds since the recording started
+    pub offset_ms: u64,
+    pub event: RecordedEvent,
+}
+
+/// Appends inbound messages to a replay file, shared by the components it records
+#[derive(Debug)]
+pub struct MessageRecorder {
+    writer: Mutex<BufWriter<std::fs::File>>,
+    started: Instant,
+}
+
+impl MessageRecorder {
+    /// Start a recording at `path`, replacing an earlier one
+    pub fn create(path: &Path, network_id: NetworkId, local_peer_id: PeerId) -> Result<Self> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to create replay file {}: {}", path.display(), e)))?;
+        let recorder = Self { writer: Mutex::new(BufWriter::new(file)), started: Instant::now() };
+        let header = ReplayHeader {
+            version: REPLAY_FORMAT_VERSION,
+            network_id,
+            local_peer_id,
+            started_at: chrono::Utc::now().timestamp_millis() as u64,
+        };
+        recorder.write_line(&header)?;
+        Ok(recorder)
+    }
+
+    /// Record `event` as happening at `at`. A failed write is logged, recording never fails
+    /// the message handling it observes
+    pub fn record(&self, at: Instant, event: RecordedEvent) {
+        let entry = ReplayEntry {
+            offset_ms: at.saturating_duration_since(self.started).as_millis() as u64,
+            event,
+        };
+        if let Err(e) = self.write_line(&entry) {
+            warn!("⚠️  Message recording failed: {}", e);
+        }
+    }
+
+    /// Write one JSON line and flush it, so a node that crashes keeps what led up to the crash
+    fn write_line<T: Serialize>(&self, value: &T) -> Result<()> {
+        let line = serde_json::to_string(value)
+            .map_err(|e| BlockchainError::Serialization(format!("Replay entry serialize failed: {}", e)))?;
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", line)
+            .and_then(|()| writer.flush())
+            .map_err(|e| BlockchainError::Storage(format!("Failed to write replay entry: {}", e)))
+    }
+}
+
+/// Recording read back from a replay file
+#[derive(Debug, Clone)]
+pub struct ReplayLog {
+    pub header: ReplayHeader,
+    pub entries: Vec<ReplayEntry>,
+}
+
+impl ReplayLog {
+    /// Read a replay file. A last line cut off by a crash is dropped, any other bad line is an error
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to open replay file {}: {}", path.display(), e)))?;
+        let lines = BufReader::new(file).lines().collect::<std::io::Result<Vec<String>>>()
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read replay file {}: {}", path.display(), e)))?;
+        let invalid = |line: usize, e: serde_json::Error| BlockchainError::Serialization(format!(
+            "Invalid replay file {} line {}: {}", path.display(), line + 1, e
+        ));
+
+        let header: ReplayHeader = serde_json::from_str(lines.first().map(String::as_str).unwrap_or_default())
+            .map_err(|e| invalid(0, e))?;
+        if header.version != REPLAY_FORMAT_VERSION {
+            return Err(BlockchainError::InvalidOperation(format!("Unsupported replay file version {}", header.version)));
+        }
+
+        let mut entries = Vec::with_capacity(lines.len().saturating_sub(1));
+        for (line, text) in lines.iter().enumerate().skip(1) {
+            match serde_json::from_str(text) {
+                Ok(entry) => entries.push(entry),
+                Err(e) if line == lines.len() - 1 => warn!("Dropping truncated last replay entry: {}", e),
+                Err(e) => return Err(invalid(line, e)),
+            }
+        }
+        Ok(Self { header, entries })
+    }
+}
+
+/// What feeding an entry did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    Handled,
+    /// The handler returned an error, as it presumably did when the entry was recorded
+    Failed(String),
+    /// No instance to feed the entry to was given
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    /// Position of the entry in the recording
+    pub index: usize,
+    pub entry: ReplayEntry,
+    pub outcome: ReplayOutcome,
+}
+
+/// Feeds a recording into fresh consensus and settlement instances in recorded order
+/// Callers keep their own handles to the instances to inspect them between steps
+pub struct ReplayEngine {
+    entries: Vec<ReplayEntry>,
+    position: usize,
+    started: Instant,
+    consensus: Option<Arc<ConsensusNetwork>>,
+    settlement: Option<Arc<SettlementMessaging>>,
+}
+
+impl ReplayEngine {
+    pub fn new(log: ReplayLog) -> Self {
+        Self {
+            entries: log.entries,
+            position: 0,
+            started: Instant::now(),
+            consensus: None,
+            settlement: None,
+        }
+    }
+
+    /// Feed consensus messages and timeouts to `consensus`
+    pub fn with_consensus(mut self, consensus: Arc<ConsensusNetwork>) -> Self {
+        self.consensus = Some(consensus);
+        self
+    }
+
+    /// Feed settlement messages to `settlement`
+    pub fn with_settlement(mut self, settlement: Arc<SettlementMessaging>) -> Self {
+        self.settlement = Some(settlement);
+        self
+    }
+
+    /// Index of the next entry to feed
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Next entry to feed, `None` at the end of the recording
+    pub fn peek(&self) -> Option<&ReplayEntry> {
+        self.entries.get(self.position)
+    }
+
+    /// Feed the next entry, `None` at the end of the recording
+    /// Timeouts fire at their recorded offset from the start of the replay, so the phase that
+    /// timed out in the field times out again however fast the messages before it were fed
+    pub async fn step(&mut self) -> Option<ReplayStep> {
+        let entry = self.entries.get(self.position)?.clone();
+        let index = self.position;
+        self.position += 1;
+
+        let result = match (&entry.event, &self.consensus, &self.settlement) {
+            (RecordedEvent::Consensus { from_peer, message }, Some(consensus), _) => {
+                Some(consensus.handle_consensus_message(message.clone(), *from_peer).await)
+            }
+            (RecordedEvent::Timeout, Some(consensus), _) => {
+                Some(consensus.on_tick(self.started + Duration::from_millis(entry.offset_ms)).await)
+            }
+            (RecordedEvent::Settlement { from_peer, message }, _, Some(settlement)) => {
+                Some(settlement.handle_settlement_message(message.clone(), *from_peer).await)
+            }
+            _ => None,
+        };
+        let outcome = match result {
+            Some(Ok(())) => ReplayOutcome::Handled,
+            Some(Err(e)) => ReplayOutcome::Failed(e.to_string()),
+            None => ReplayOutcome::Skipped,
+        };
+        Some(ReplayStep { index, entry, outcome })
+    }
+
+    /// Feed the entries recorded up to `offset_ms` into the recording
+    pub async fn run_until(&mut self, offset_ms: u64) -> Vec<ReplayStep> {
+        let mut steps = Vec::new();
+        while self.peek().is_some_and(|entry| entry.offset_ms <= offset_ms) {
+            steps.extend(self.step().await);
+        }
+        steps
+    }
+
+    /// Feed the rest of the recording
+    pub async fn run_to_end(&mut self) -> Vec<ReplayStep> {
+        self.run_until(u64::MAX).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::sync::broadcast;
+    use crate::crypto::bls::BLSPrivateKey;
+    use crate::network::consensus_networking::{view_change_message, ViewChangeReason};
+    use crate::primitives::Blake2bHash;
+
+    fn consensus(peers: &[PeerId], keys: &[BLSPrivateKey], cmd_sender: broadcast::Sender<crate::network::NetworkCommand>) -> ConsensusNetwork {
+        ConsensusNetwork::new(
+            NetworkId::new("Test", "Network"),
+            peers[0],
+            peers.iter().copied().collect(),
+            peers.iter().map(|peer| (*peer, 100)).collect(),
+            cmd_sender,
+            Arc::new(keys[0].clone()),
+            peers.iter().copied().zip(keys.iter().map(|key| key.public_key())).collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_recorded_view_change_replays() {
+        let keys: Vec<BLSPrivateKey> = (0..4).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("consensus.replay");
+
+        // In the field: the proposal times out and two more validators vote to change the view
+        let recorder = Arc::new(MessageRecorder::create(&path, NetworkId::new("Test", "Network"), peers[0]).unwrap());
+        let (cmd_sender, _cmd_receiver) = broadcast::channel(16);
+        let mut field = consensus(&peers, &keys, cmd_sender.clone());
+        field.set_recorder(recorder.clone());
+        field.on_tick(Instant::now()).await.unwrap();
+        field.on_tick(Instant::now() + Duration::from_secs(60)).await.unwrap();
+        for (peer, key) in peers.iter().zip(&keys).skip(1).take(2) {
+            let view_change = ConsensusMessage::ViewChange {
+                round: 0,
+                height: 0,
+                requester_id: *peer,
+                reason: ViewChangeReason::Timeout,
+                signature: key.sign(&view_change_message(0, 0)).unwrap().to_bytes().to_vec(),
+            };
+            field.handle_consensus_message(view_change, *peer).await.unwrap();
+        }
+        let (command_sender, _commands) = broadcast::channel(16);
+        let mut settlement = SettlementMessaging::new(NetworkId::new("Orange", "FR"), peers[0], command_sender.clone());
+        settlement.set_recorder(recorder);
+        let proposal = SettlementMessage::InitiateSettlement {
+            creditor_network: NetworkId::new("Vodafone", "UK"),
+            debtor_network: NetworkId::new("Orange", "FR"),
+            amount_cents: 5_000,
+            currency: "EUR".to_string(),
+            period_start: 1_700_000_000,
+            period_end: 1_702_592_000,
+            cdr_batch_hash: Blake2bHash::from_data(b"batch"),
+            nonce: 42,
+        };
+        settlement.handle_settlement_message(proposal.clone(), peers[1]).await.unwrap();
+        assert_eq!(field.get_state().await.current_round, 1);
+
+        // Only the tick that timed out was recorded
+        let log = ReplayLog::read_from(&path).unwrap();
+        assert_eq!(log.header.local_peer_id, peers[0]);
+        assert_eq!(log.entries.len(), 4);
+        assert!(matches!(log.entries[0].event, RecordedEvent::Timeout));
+        assert!(log.entries[0].offset_ms >= 60_000);
+
+        // Stepping through a fresh instance goes through the same states
+        let replayed = Arc::new(consensus(&peers, &keys, cmd_sender));
+        let mut engine = ReplayEngine::new(log.clone()).with_consensus(replayed.clone());
+        assert_eq!(engine.step().await.unwrap().outcome, ReplayOutcome::Handled);
+        assert_eq!(replayed.get_state().await.view_changes.len(), 1);
+        assert_eq!(engine.step().await.unwrap().outcome, ReplayOutcome::Handled);
+        assert_eq!(replayed.get_state().await.view_changes.len(), 2);
+        engine.step().await.unwrap();
+        assert_eq!(replayed.get_state().await.current_round, 1);
+
+        // Without a settlement instance the settlement message is skipped
+        assert_eq!(engine.step().await.unwrap().outcome, ReplayOutcome::Skipped);
+        assert!(engine.step().await.is_none());
+
+        let replayed_settlement = Arc::new(SettlementMessaging::new(NetworkId::new("Orange", "FR"), peers[0], command_sender));
+        let steps = ReplayEngine::new(log).with_settlement(replayed_settlement.clone()).run_to_end().await;
+        assert_eq!(steps.iter().filter(|step| step.outcome == ReplayOutcome::Handled).count(), 1);
+        let entries = replayed_settlement.audit_log().entries().await.unwrap();
+        assert!(entries.iter().any(|entry| entry.subject == proposal.content_id()));
+
+        // A crash mid-write leaves a truncated line, which is dropped
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"offset_ms\":70000,\"ev").unwrap();
+        assert_eq!(ReplayLog::read_from(&path).unwrap().entries.len(), 4);
+    }
+}
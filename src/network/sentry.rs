@@ -0,0 +1,132 @@
+// Sentry node architecture: a validator's consensus node stays off the public network and talks
+// only to its own sentries, over connections restricted to an allowlist. Sentries are the nodes
+// peers see: they relay gossip between the validator and the consortium and never pass its
+// address on, through the DHT or the operator table
+use std::collections::HashSet;
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use crate::primitives::BlockchainError;
+
+/// Part a node plays in a sentry deployment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SentryMode {
+    /// Reachable by and discovering any peer
+    #[default]
+    Off,
+    /// Validator behind sentries, connected to them alone and never advertised
+    Validator,
+    /// Public node relaying gossip for the validators behind it
+    Sentry,
+}
+
+impl std::str::FromStr for SentryMode {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(SentryMode::Off),
+            "validator" => Ok(SentryMode::Validator),
+            "sentry" => Ok(SentryMode::Sentry),
+            other => Err(BlockchainError::InvalidOperation(format!(
+                "Unknown sentry mode '{}', expected off, validator or sentry", other
+            ))),
+        }
+    }
+}
+
+/// Sentry mode and the private peers on the other side of it
+#[derive(Debug, Clone, Default)]
+pub struct SentryConfig {
+    pub mode: SentryMode,
+    /// A validator's sentries, or the validators a sentry protects, each address ending in
+    /// `/p2p/<peer id>`
+    pub private_peers: Vec<Multiaddr>,
+}
+
+impl SentryConfig {
+    /// Check the private peers fit the mode
+    pub fn validate(&self) -> std::result::Result<(), BlockchainError> {
+        match self.mode {
+            SentryMode::Off if !self.private_peers.is_empty() => {
+                Err(BlockchainError::InvalidOperation("Private peers are only used in sentry mode".to_string()))
+            }
+            SentryMode::Validator | SentryMode::Sentry if self.private_peers.is_empty() => {
+                Err(BlockchainError::InvalidOperation(format!("Sentry mode {:?} needs private peers", self.mode)))
+            }
+            _ => match self.private_peers.iter().find(|address| peer_id(address).is_none()) {
+                Some(address) => Err(BlockchainError::InvalidOperation(format!("Private peer {} has no /p2p peer id", address))),
+                None => Ok(()),
+            },
+        }
+    }
+
+    pub fn private_peer_ids(&self) -> HashSet<PeerId> {
+        self.private_peers.iter().filter_map(peer_id).collect()
+    }
+
+    /// Whether a connection with `peer` is kept: a validator behind sentries talks to them alone
+    pub fn allows_connection(&self, peer: &PeerId) -> bool {
+        self.mode != SentryMode::Validator || self.private_peer_ids().contains(peer)
+    }
+
+    /// Whether the node takes part in open discovery: mDNS, bootnodes, serving the DHT and relays
+    pub fn discovers(&self) -> bool {
+        self.mode != SentryMode::Validator
+    }
+
+    /// Peers whose addresses this node must not pass on, the validators behind a sentry
+    pub fn hidden_peers(&self) -> HashSet<PeerId> {
+        match self.mode {
+            SentryMode::Sentry => self.private_peer_ids(),
+            SentryMode::Off | SentryMode::Validator => HashSet::new(),
+        }
+    }
+
+    /// Address a validator announces itself under: a sentry's instead of its own when behind them
+    pub fn public_endpoint(&self, listen_addr: &Multiaddr) -> Multiaddr {
+        match (self.mode, self.private_peers.first()) {
+            (SentryMode::Validator, Some(sentry)) => sentry.clone(),
+            _ => listen_addr.clone(),
+        }
+    }
+}
+
+/// Peer id a private peer's address ends in
+pub fn peer_id(address: &Multiaddr) -> Option<PeerId> {
+    match address.iter().last() {
+        Some(Protocol::P2p(peer_id)) => Some(peer_id),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentry_allowlist_and_advertising() {
+        let (sentry, validator, outsider) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let sentry_addr: Multiaddr = format!("/ip4/198.51.100.20/tcp/9000/p2p/{}", sentry).parse().unwrap();
+        let validator_addr: Multiaddr = format!("/ip4/10.0.0.5/tcp/9000/p2p/{}", validator).parse().unwrap();
+        let listen_addr: Multiaddr = "/ip4/0.0.0.0/tcp/9000".parse().unwrap();
+
+        // The validator only keeps its sentry, stays out of discovery and announces the sentry's address
+        let behind = SentryConfig { mode: SentryMode::Validator, private_peers: vec![sentry_addr.clone()] };
+        behind.validate().unwrap();
+        assert!(behind.allows_connection(&sentry) && !behind.allows_connection(&outsider));
+        assert!(!behind.discovers());
+        assert_eq!(behind.public_endpoint(&listen_addr), sentry_addr);
+
+        // The sentry is open to everyone but never passes the validator on
+        let front = SentryConfig { mode: SentryMode::Sentry, private_peers: vec![validator_addr] };
+        front.validate().unwrap();
+        assert!(front.allows_connection(&outsider) && front.discovers());
+        assert_eq!(front.hidden_peers(), HashSet::from([validator]));
+        assert_eq!(front.public_endpoint(&listen_addr), listen_addr);
+
+        assert!(SentryConfig { mode: SentryMode::Validator, private_peers: vec![] }.validate().is_err());
+        let anonymous = SentryConfig { mode: SentryMode::Sentry, private_peers: vec![listen_addr] };
+        assert!(anonymous.validate().is_err());
+        assert_eq!("sentry".parse::<SentryMode>().unwrap(), SentryMode::Sentry);
+        assert!("public".parse::<SentryMode>().is_err());
+    }
+}
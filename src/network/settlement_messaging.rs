@@ -1,12 +1,26 @@
 // Settlement messaging and negotiation for SP operators
 use libp2p::PeerId;
 use std::collections::HashMap;
-use tokio::sync::{broadcast, RwLock};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex, RwLock};
 use tracing::{info, debug, warn, error};
 use serde::{Deserialize, Serialize};
 
-use crate::primitives::{Blake2bHash, NetworkId, BlockchainError};
+use crate::primitives::{Blake2bHash, NetworkId, BlockchainError, hash_canonical, to_canonical_bytes};
 use crate::network::{SPNetworkMessage, NetworkCommand};
+use crate::network::dispute_resolution::{DisputeManager, DisputeOutcome, DisputeState, DisputeVerdict};
+use crate::network::multilateral_netting::{MultilateralNettingSolver, NettingConfig, NettingResult};
+use crate::network::counter_offer::{CounterOfferDecision, CounterOfferPolicy, OfferRound};
+use crate::network::rate_limit::{MessageClass, MessageRateLimiter, RateLimitConfig, RateLimitVerdict};
+use crate::network::settlement_policy::{CounterpartyPolicy, SettlementPolicies};
+use crate::network::tenancy::OperatorIdentity;
+use crate::network::replay::{MessageRecorder, RecordedEvent};
+use crate::storage::{AuditAction, AuditLog, ChainStore, SimpleChainStore};
+use crate::settlement_execution::SettlementExecutor;
+use crate::zkp::{AlbatrossZKProver, AlbatrossZKVerifier};
+use crate::crypto::bls::BLSSignature;
+use crate::crypto::keys::Signer;
+use crate::crypto::signatures::{PartialSignature, ThresholdKeyShare, ThresholdPublicKey};
 
 /// Settlement negotiation message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,9 +41,22 @@ pub enum SettlementMessage {
     SettlementResponse {
         proposal_hash: Blake2bHash,
         response: SettlementResponseType,
+        /// Revised amount of a counter-offer, or the amount an acceptance agrees to
         counter_amount: Option<u64>,
         reason: Option<String>,
         responder_signature: Vec<u8>,
+        /// Counter-offer round the response makes or answers, 0 being the original proposal
+        round: u32,
+        /// Evidence document a counter-offer's revised amount rests on
+        evidence_hash: Option<Blake2bHash>,
+    },
+
+    /// Partial approval from one of the debtor's authorized signers, for
+    /// settlements above the auto-accept threshold
+    ApprovalShare {
+        proposal_hash: Blake2bHash,
+        approver_network: NetworkId,
+        partial_signature: PartialSignature,
     },
 
     /// Triangular netting proposal
@@ -40,6 +67,8 @@ pub enum SettlementMessage {
         savings_percentage: u32,
         coordinator: NetworkId,
         proposal_id: Blake2bHash,
+        /// Groth16 proof that net settlements derive from the bilateral amounts
+        netting_proof: Option<Vec<u8>>,
     },
 
     /// Netting agreement
@@ -79,6 +108,46 @@ pub enum SettlementMessage {
         evidence_hash: Blake2bHash,
         initiator: NetworkId,
     },
+
+    /// Additional evidence for an open dispute, signed by the party submitting it
+    DisputeEvidence {
+        dispute_id: Blake2bHash,
+        evidence_hash: Blake2bHash,
+        submitter: NetworkId,
+        submitter_signature: Vec<u8>,
+    },
+
+    /// Validator vote on a dispute outcome
+    DisputeVote {
+        dispute_id: Blake2bHash,
+        validator: NetworkId,
+        verdict: DisputeVerdict,
+        validator_signature: Vec<u8>,
+    },
+}
+
+impl SettlementMessage {
+    /// Content address of a message, the hash of its canonical serialization
+    /// An `InitiateSettlement` hashes to the proposal ID both parties track the negotiation under
+    pub fn content_id(&self) -> Blake2bHash {
+        hash_canonical(self)
+    }
+
+    /// Operator a message claims to be sent by, `None` for responses naming no sender
+    pub fn sender(&self) -> Option<&NetworkId> {
+        match self {
+            SettlementMessage::InitiateSettlement { creditor_network, .. } => Some(creditor_network),
+            SettlementMessage::ApprovalShare { approver_network, .. } => Some(approver_network),
+            SettlementMessage::TriangularNettingProposal { coordinator, .. } => Some(coordinator),
+            SettlementMessage::DisputeInitiation { initiator, .. } => Some(initiator),
+            SettlementMessage::DisputeEvidence { submitter, .. } => Some(submitter),
+            SettlementMessage::DisputeVote { validator, .. } => Some(validator),
+            SettlementMessage::SettlementResponse { .. }
+            | SettlementMessage::NettingAgreement { .. }
+            | SettlementMessage::SettlementInstruction { .. }
+            | SettlementMessage::SettlementConfirmation { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,7 +165,7 @@ pub enum NettingAgreementType {
     ConditionalAgree,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SettlementMethod {
     BankTransfer,
     CryptoTransfer,
@@ -112,13 +181,15 @@ pub enum ConfirmationType {
     PaymentFailed,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DisputeReason {
     AmountDiscrepancy,
     InvalidCDR,
     UnauthorizedCharges,
     TechnicalError,
     FraudSuspicion,
+    /// Escrowed payment left unconfirmed past its deadline
+    PaymentOverdue,
 }
 
 /// Settlement negotiation state
@@ -129,6 +200,9 @@ pub struct SettlementNegotiation {
     pub status: NegotiationStatus,
     pub bilateral_amounts: HashMap<(NetworkId, NetworkId), u64>,
     pub responses: HashMap<NetworkId, SettlementResponseType>,
+    /// Offers of a bilateral negotiation, the original proposal followed by counter-offers
+    pub rounds: Vec<OfferRound>,
+    pub currency: String,
     pub created_at: u64,
     pub expires_at: u64,
 }
@@ -141,6 +215,8 @@ pub enum NegotiationStatus {
     Rejected,
     CounterProposed,
     Expired,
+    /// Counter-offers ran out without agreement, the amount is in dispute
+    Escalated,
 }
 
 /// Settlement instruction for final execution
@@ -168,11 +244,53 @@ pub struct SettlementMessaging {
     pending_settlements: RwLock<HashMap<Blake2bHash, PendingSettlement>>,
     completed_settlements: RwLock<Vec<CompletedSettlement>>,
 
+    // Dispute arbitration
+    dispute_manager: Arc<DisputeManager>,
+
+    // Multilateral netting
+    netting_solver: MultilateralNettingSolver,
+
+    // Payment rails for settlement execution
+    settlement_executor: Arc<SettlementExecutor>,
+
+    // Netting correctness proofs
+    zk_prover: Option<Arc<AlbatrossZKProver>>,
+    zk_verifier: Option<Arc<AlbatrossZKVerifier>>,
+
+    // Quorum approval of settlements above the auto-accept threshold
+    approval_keys: RwLock<HashMap<NetworkId, ThresholdPublicKey>>,
+    approval_share: Option<ThresholdKeyShare>,
+    pending_approvals: RwLock<HashMap<Blake2bHash, PendingApproval>>,
+
+    // Audit trail of every settlement state transition
+    audit_log: Arc<AuditLog>,
+
+    // Network key our responses, agreements, confirmations and votes are signed with
+    signer: Option<Arc<dyn Signer>>,
+
+    // Operator identities hosted besides our own, negotiating with their own keys and policies
+    hosted_identities: RwLock<HashMap<NetworkId, OperatorIdentity>>,
+
+    // Content IDs of messages handled and when, so gossip redelivery is ignored
+    seen_messages: RwLock<HashMap<Blake2bHash, u64>>,
+
+    // Per-peer and per-operator message rates, and when stale negotiations were last dropped
+    rate_limiter: Mutex<MessageRateLimiter>,
+    last_garbage_collection: Mutex<std::time::Instant>,
+
     // Configuration
-    auto_accept_threshold: u64, // Auto-accept settlements below this amount
+    auto_accept_threshold: u64, // Settlements above this amount need our signer quorum
     negotiation_timeout: std::time::Duration,
+    counter_offer_policy: CounterOfferPolicy,
+    policies: RwLock<SettlementPolicies>,
+
+    // Replay file inbound messages are logged to
+    recorder: Option<Arc<MessageRecorder>>,
 }
 
+/// How often stale negotiations, approvals and rate limit buckets are dropped
+const GARBAGE_COLLECTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 #[derive(Debug, Clone)]
 pub struct PendingSettlement {
     pub settlement_id: Blake2bHash,
@@ -181,10 +299,22 @@ pub struct PendingSettlement {
     pub amount: u64,
     pub currency: String,
     pub due_date: u64,
+    pub settlement_method: SettlementMethod,
     pub status: SettlementStatus,
     pub created_at: u64,
 }
 
+/// Large settlement collecting partial signatures from our authorized signers
+#[derive(Debug, Clone)]
+pub struct PendingApproval {
+    pub proposal_hash: Blake2bHash,
+    pub creditor: NetworkId,
+    pub amount: u64,
+    pub currency: String,
+    pub partial_signatures: Vec<PartialSignature>,
+    pub created_at: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct CompletedSettlement {
     pub settlement_id: Blake2bHash,
@@ -209,6 +339,16 @@ impl SettlementMessaging {
         network_id: NetworkId,
         local_peer_id: PeerId,
         command_sender: broadcast::Sender<NetworkCommand>,
+    ) -> Self {
+        Self::new_with_chain_store(network_id, local_peer_id, command_sender, Arc::new(SimpleChainStore::new()))
+    }
+
+    /// Create settlement messaging with dispute evidence persisted in the chain store
+    pub fn new_with_chain_store(
+        network_id: NetworkId,
+        local_peer_id: PeerId,
+        command_sender: broadcast::Sender<NetworkCommand>,
+        chain_store: Arc<dyn ChainStore>,
     ) -> Self {
         Self {
             network_id,
@@ -217,8 +357,25 @@ impl SettlementMessaging {
             active_negotiations: RwLock::new(HashMap::new()),
             pending_settlements: RwLock::new(HashMap::new()),
             completed_settlements: RwLock::new(Vec::new()),
+            dispute_manager: Arc::new(DisputeManager::new(chain_store)),
+            netting_solver: MultilateralNettingSolver::default(),
+            settlement_executor: Arc::new(SettlementExecutor::new()),
+            zk_prover: None,
+            zk_verifier: None,
+            approval_keys: RwLock::new(HashMap::new()),
+            approval_share: None,
+            pending_approvals: RwLock::new(HashMap::new()),
+            audit_log: Arc::new(AuditLog::in_memory()),
+            signer: None,
+            hosted_identities: RwLock::new(HashMap::new()),
+            seen_messages: RwLock::new(HashMap::new()),
+            rate_limiter: Mutex::new(MessageRateLimiter::default()),
+            last_garbage_collection: Mutex::new(std::time::Instant::now()),
             auto_accept_threshold: 100000, // €1000 in cents
             negotiation_timeout: std::time::Duration::from_secs(3600), // 1 hour
+            counter_offer_policy: CounterOfferPolicy::default(),
+            policies: RwLock::new(SettlementPolicies::default()),
+            recorder: None,
         }
     }
 
@@ -232,10 +389,29 @@ impl SettlementMessaging {
         period_end: u64,
         cdr_batch_hash: Blake2bHash,
     ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        self.initiate_settlement_as(
+            self.network_id.clone(), debtor_network, amount_cents, currency, period_start, period_end, cdr_batch_hash,
+        ).await
+    }
+
+    /// Initiate a bilateral settlement as `creditor_network`, our own identity or one we host
+    pub async fn initiate_settlement_as(
+        &self,
+        creditor_network: NetworkId,
+        debtor_network: NetworkId,
+        amount_cents: u64,
+        currency: String,
+        period_start: u64,
+        period_end: u64,
+        cdr_batch_hash: Blake2bHash,
+    ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        if !self.hosts(&creditor_network).await {
+            return Err(BlockchainError::InvalidOperation(format!("{} is not hosted by this node", creditor_network)));
+        }
         let nonce = rand::random::<u64>();
 
         let message = SettlementMessage::InitiateSettlement {
-            creditor_network: self.network_id.clone(),
+            creditor_network: creditor_network.clone(),
             debtor_network: debtor_network.clone(),
             amount_cents,
             currency: currency.clone(),
@@ -248,18 +424,26 @@ impl SettlementMessaging {
         let proposal_id = self.calculate_proposal_hash(&message);
 
         info!("Initiating settlement: {} -> {} for {} {}",
-              self.network_id, debtor_network, amount_cents as f64 / 100.0, currency);
+              creditor_network, debtor_network, amount_cents as f64 / 100.0, currency);
 
         // Send settlement message
         self.send_settlement_message(message, "settlement").await?;
 
         // Track negotiation
+        let mut bilateral_amounts = HashMap::new();
+        bilateral_amounts.insert((creditor_network.clone(), debtor_network.clone()), amount_cents);
+
+        self.audit(&creditor_network, AuditAction::Proposed, proposal_id,
+                   format!("{} -> {}: {} {}", creditor_network, debtor_network, amount_cents, currency)).await?;
+
         let negotiation = SettlementNegotiation {
             proposal_id,
-            participants: vec![self.network_id.clone(), debtor_network],
+            participants: vec![creditor_network.clone(), debtor_network],
             status: NegotiationStatus::Proposed,
-            bilateral_amounts: HashMap::new(),
+            bilateral_amounts,
             responses: HashMap::new(),
+            rounds: vec![OfferRound { round: 0, proposer: creditor_network, amount_cents, evidence_hash: None }],
+            currency,
             created_at: chrono::Utc::now().timestamp() as u64,
             expires_at: chrono::Utc::now().timestamp() as u64 + 3600, // 1 hour
         };
@@ -275,8 +459,10 @@ impl SettlementMessaging {
         participants: Vec<NetworkId>,
         bilateral_amounts: Vec<(NetworkId, NetworkId, u64)>,
     ) -> std::result::Result<Blake2bHash, BlockchainError> {
-        // Calculate net positions
-        let net_settlements = self.calculate_net_positions(&bilateral_amounts);
+        // Calculate net positions and prove they derive from the bilateral amounts
+        let netting = self.calculate_multilateral_netting(&bilateral_amounts)?;
+        let netting_proof = self.generate_netting_proof(&bilateral_amounts, &netting).await?;
+        let net_settlements = netting.net_positions;
         let savings = self.calculate_savings_percentage(&bilateral_amounts, &net_settlements);
 
         let proposal_id = Blake2bHash::from_data(format!("netting-{}-{}",
@@ -290,6 +476,7 @@ impl SettlementMessaging {
             savings_percentage: savings,
             coordinator: self.network_id.clone(),
             proposal_id,
+            netting_proof,
         };
 
         info!("Proposing triangular netting among {:?} with {}% savings",
@@ -297,6 +484,8 @@ impl SettlementMessaging {
 
         // Broadcast to all participants
         self.send_settlement_message(message, "settlement").await?;
+        self.audit(&self.network_id, AuditAction::Proposed, proposal_id,
+                   format!("netting among {} participants, {}% savings", participants.len(), savings)).await?;
 
         // Track negotiation
         let mut bilateral_map = HashMap::new();
@@ -310,6 +499,8 @@ impl SettlementMessaging {
             status: NegotiationStatus::Proposed,
             bilateral_amounts: bilateral_map,
             responses: HashMap::new(),
+            rounds: Vec::new(),
+            currency: "EUR".to_string(), // Default to EUR for SP consortium
             created_at: chrono::Utc::now().timestamp() as u64,
             expires_at: chrono::Utc::now().timestamp() as u64 + 1800, // 30 minutes for netting
         };
@@ -319,11 +510,46 @@ impl SettlementMessaging {
         Ok(proposal_id)
     }
 
-    /// Handle incoming settlement message
+    /// Handle incoming settlement message, dropping it if the peer or operator sending it is over its rate
     pub async fn handle_settlement_message(
         &self,
         message: SettlementMessage,
         from_peer: PeerId,
+    ) -> std::result::Result<(), BlockchainError> {
+        // Recorded before deduplication, redelivery is part of what a replay reproduces
+        if let Some(recorder) = &self.recorder {
+            recorder.record(std::time::Instant::now(), RecordedEvent::Settlement { from_peer, message: message.clone() });
+        }
+        self.maybe_collect_garbage().await;
+
+        // Redelivered messages were handled already, checked and claimed under one lock so concurrent
+        // deliveries of a message are handled once
+        let message_id = message.content_id();
+        {
+            let mut seen_messages = self.seen_messages.write().await;
+            if seen_messages.contains_key(&message_id) {
+                debug!("Ignoring redelivered settlement message {}", message_id);
+                return Ok(());
+            }
+            seen_messages.insert(message_id, chrono::Utc::now().timestamp() as u64);
+        }
+        // A message dropped or failing here is handled again when redelivered
+        if !self.admit(MessageClass::Settlement, from_peer, message.sender()).await {
+            crate::metrics::metrics().settlement_messages_dropped.inc();
+            self.seen_messages.write().await.remove(&message_id);
+            return Ok(());
+        }
+        let result = self.dispatch_settlement_message(message, from_peer).await;
+        if result.is_err() {
+            self.seen_messages.write().await.remove(&message_id);
+        }
+        result
+    }
+
+    async fn dispatch_settlement_message(
+        &self,
+        message: SettlementMessage,
+        from_peer: PeerId,
     ) -> std::result::Result<(), BlockchainError> {
         match message {
             SettlementMessage::InitiateSettlement {
@@ -347,24 +573,39 @@ impl SettlementMessaging {
                 response,
                 counter_amount,
                 reason,
-                responder_signature
+                responder_signature,
+                round,
+                evidence_hash
             } => {
                 self.handle_settlement_response(
-                    proposal_hash, response, counter_amount, reason, responder_signature
+                    proposal_hash, response, counter_amount, reason, responder_signature, round, evidence_hash
                 ).await
             }
 
+            SettlementMessage::ApprovalShare {
+                proposal_hash,
+                approver_network,
+                partial_signature
+            } => {
+                // Only our own signers contribute to our approvals
+                if approver_network != self.network_id {
+                    return Ok(());
+                }
+                self.record_approval_share(proposal_hash, partial_signature).await
+            }
+
             SettlementMessage::TriangularNettingProposal {
                 participants,
                 bilateral_amounts,
                 net_settlements,
                 savings_percentage,
                 coordinator,
-                proposal_id
+                proposal_id,
+                netting_proof
             } => {
                 self.handle_netting_proposal(
                     participants, bilateral_amounts, net_settlements,
-                    savings_percentage, coordinator, proposal_id
+                    savings_percentage, coordinator, proposal_id, netting_proof
                 ).await
             }
 
@@ -418,6 +659,24 @@ impl SettlementMessaging {
                     settlement_id, dispute_reason, disputed_amount, evidence_hash, initiator
                 ).await
             }
+
+            SettlementMessage::DisputeEvidence {
+                dispute_id,
+                evidence_hash,
+                submitter,
+                submitter_signature
+            } => {
+                self.dispute_manager.attach_signed_evidence(&dispute_id, submitter, evidence_hash, &submitter_signature).await
+            }
+
+            SettlementMessage::DisputeVote {
+                dispute_id,
+                validator,
+                verdict,
+                validator_signature
+            } => {
+                self.handle_dispute_vote(dispute_id, validator, verdict, validator_signature).await
+            }
         }
     }
 
@@ -428,39 +687,77 @@ impl SettlementMessaging {
         debtor_network: NetworkId,
         amount_cents: u64,
         currency: String,
-        _period_start: u64,
-        _period_end: u64,
-        _cdr_batch_hash: Blake2bHash,
-        _nonce: u64,
+        period_start: u64,
+        period_end: u64,
+        cdr_batch_hash: Blake2bHash,
+        nonce: u64,
         _from_peer: PeerId,
     ) -> std::result::Result<(), BlockchainError> {
-        // Only handle if we are the debtor
-        if debtor_network != self.network_id {
+        // Only handle if we are the debtor, as our own identity or one we host
+        if !self.hosts(&debtor_network).await {
             return Ok(());
         }
 
         info!("Received settlement request: {} -> {} for {} {}",
               creditor_network, debtor_network, amount_cents as f64 / 100.0, currency);
 
-        // Create proposal hash for response
-        let proposal_hash = Blake2bHash::from_data(format!("{:?}-{}-{}",
-                                                            creditor_network, amount_cents, currency).as_bytes());
-
-        let response_type = if amount_cents <= self.auto_accept_threshold {
-            info!("Auto-accepting settlement under threshold");
+        // Same proposal hash the creditor tracks its negotiation under
+        let proposal_hash = self.calculate_proposal_hash(&SettlementMessage::InitiateSettlement {
+            creditor_network: creditor_network.clone(),
+            debtor_network: debtor_network.clone(),
+            amount_cents,
+            currency: currency.clone(),
+            period_start,
+            period_end,
+            cdr_batch_hash,
+            nonce,
+        });
+
+        let auto_accept = self.policy_for(&debtor_network, &creditor_network).await.auto_accepts(amount_cents);
+
+        // Tracked on our side too, so either party can counter the amount
+        let mut bilateral_amounts = HashMap::new();
+        bilateral_amounts.insert((creditor_network.clone(), debtor_network.clone()), amount_cents);
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.active_negotiations.write().await.insert(proposal_hash, SettlementNegotiation {
+            proposal_id: proposal_hash,
+            participants: vec![creditor_network.clone(), debtor_network.clone()],
+            status: if auto_accept { NegotiationStatus::Accepted } else { NegotiationStatus::UnderReview },
+            bilateral_amounts,
+            responses: HashMap::new(),
+            rounds: vec![OfferRound { round: 0, proposer: creditor_network.clone(), amount_cents, evidence_hash: None }],
+            currency: currency.clone(),
+            created_at: now,
+            expires_at: now + self.negotiation_timeout.as_secs(),
+        });
+
+        let response_type = if auto_accept {
+            info!("Auto-accepting settlement within {}'s policy", creditor_network);
+            self.audit(&debtor_network, AuditAction::Accepted, proposal_hash,
+                       format!("auto-accepted {} {} from {}", amount_cents, currency, creditor_network)).await?;
             SettlementResponseType::Accept
+        } else if self.has_signer_quorum(&debtor_network).await {
+            info!("Settlement exceeds {}'s auto-accept limit - collecting signer quorum", creditor_network);
+            self.audit(&debtor_network, AuditAction::ApprovalRequested, proposal_hash,
+                       format!("{} {} from {}", amount_cents, currency, creditor_network)).await?;
+            return self.request_quorum_approval(proposal_hash, creditor_network, amount_cents, currency).await;
         } else {
-            info!("Settlement requires review - amount exceeds auto-accept threshold");
+            info!("Settlement requires review - amount exceeds {}'s auto-accept limit", creditor_network);
+            self.audit(&debtor_network, AuditAction::UnderReview, proposal_hash,
+                       format!("{} {} from {} exceeds auto-accept limit", amount_cents, currency, creditor_network)).await?;
             SettlementResponseType::RequestModification
         };
 
         // Send response
+        let responder_signature = self.sign_statement_as(&debtor_network, &("settlement-response", proposal_hash, &response_type)).await?;
         let response_message = SettlementMessage::SettlementResponse {
             proposal_hash,
             response: response_type,
             counter_amount: None,
             reason: None,
-            responder_signature: vec![], // Would sign with network key
+            responder_signature,
+            round: 0,
+            evidence_hash: None,
         };
 
         self.send_settlement_message(response_message, "settlement").await?;
@@ -475,15 +772,41 @@ impl SettlementMessaging {
         response: SettlementResponseType,
         counter_amount: Option<u64>,
         reason: Option<String>,
-        _responder_signature: Vec<u8>,
+        responder_signature: Vec<u8>,
+        round: u32,
+        evidence_hash: Option<Blake2bHash>,
     ) -> std::result::Result<(), BlockchainError> {
         let mut negotiations = self.active_negotiations.write().await;
 
         if let Some(negotiation) = negotiations.get_mut(&proposal_hash) {
+            // Accepted, rejected and escalated proposals are decided, later responses change nothing
+            if matches!(negotiation.status, NegotiationStatus::Accepted | NegotiationStatus::Rejected | NegotiationStatus::Escalated) {
+                debug!("Ignoring response to decided proposal {:?}", proposal_hash);
+                return Ok(());
+            }
+            let local = self.local_participant(negotiation).await;
+            let responder = Self::counterparty(negotiation, &local);
             match response {
                 SettlementResponseType::Accept => {
+                    if !self.verify_quorum_acceptance(negotiation, &proposal_hash, &responder_signature).await? {
+                        warn!("❌ Ignoring acceptance of {:?} without a valid signer quorum", proposal_hash);
+                        return Ok(());
+                    }
+
+                    // An acceptance without an amount takes the last offer
+                    let agreed = counter_amount.or_else(|| negotiation.rounds.last().map(|offer| offer.amount_cents));
+                    if let Some(agreed) = agreed {
+                        if !CounterOfferPolicy::is_agreed_amount(&negotiation.rounds, agreed) {
+                            warn!("❌ Ignoring acceptance of {:?} at {}, not an amount offered", proposal_hash, agreed);
+                            return Ok(());
+                        }
+                        negotiation.bilateral_amounts.values_mut().for_each(|amount| *amount = agreed);
+                    }
+
                     info!("Settlement accepted for proposal {:?}", proposal_hash);
                     negotiation.status = NegotiationStatus::Accepted;
+                    self.audit(&responder, AuditAction::Accepted, proposal_hash,
+                               agreed.map(|amount| format!("agreed amount {}", amount)).unwrap_or_default()).await?;
                     // Proceed with settlement execution
                     self.execute_settlement(proposal_hash).await?;
                 }
@@ -491,18 +814,59 @@ impl SettlementMessaging {
                 SettlementResponseType::Reject => {
                     info!("Settlement rejected for proposal {:?}: {:?}", proposal_hash, reason);
                     negotiation.status = NegotiationStatus::Rejected;
+                    self.audit(&responder, AuditAction::Rejected, proposal_hash, reason.unwrap_or_default()).await?;
                 }
 
                 SettlementResponseType::CounterOffer => {
-                    info!("Counter-offer received for proposal {:?}: {:?}",
-                          proposal_hash, counter_amount);
+                    let amount_cents = match counter_amount {
+                        Some(amount_cents) => amount_cents,
+                        None => {
+                            warn!("Ignoring counter-offer for {:?} without an amount", proposal_hash);
+                            return Ok(());
+                        }
+                    };
+                    let offer = OfferRound { round, proposer: responder.clone(), amount_cents, evidence_hash };
+                    let decision = match self.counter_offer_policy.decide(&negotiation.rounds, &offer) {
+                        Ok(CounterOfferDecision::Stale) => return Ok(()),
+                        Ok(decision) => decision,
+                        Err(e) => {
+                            warn!("Ignoring counter-offer for {:?}: {}", proposal_hash, e);
+                            return Ok(());
+                        }
+                    };
+
+                    info!("Counter-offer {} received for proposal {:?}: {}", round, proposal_hash, amount_cents);
+                    let previous = negotiation.rounds.last().map_or(0, |offer| offer.amount_cents);
+                    negotiation.rounds.push(offer);
                     negotiation.status = NegotiationStatus::CounterProposed;
-                    // Handle counter-negotiation
+                    self.audit(&responder, AuditAction::CounterProposed, proposal_hash,
+                               format!("round {} counter amount {}", round, amount_cents)).await?;
+
+                    match decision {
+                        CounterOfferDecision::Converged(agreed) => {
+                            let negotiation = negotiation.clone();
+                            drop(negotiations);
+                            return self.accept_converged_offer(&negotiation, agreed).await;
+                        }
+                        CounterOfferDecision::Escalate => {
+                            negotiation.status = NegotiationStatus::Escalated;
+                            let evidence = to_canonical_bytes(&negotiation.rounds)?;
+                            drop(negotiations);
+                            warn!("⚖️  No agreement on {:?} after {} counter-offers, escalating to dispute",
+                                  proposal_hash, self.counter_offer_policy.max_rounds);
+                            self.initiate_dispute_as(
+                                local, proposal_hash, DisputeReason::AmountDiscrepancy, Some(previous.abs_diff(amount_cents)), &evidence,
+                            ).await?;
+                            return Ok(());
+                        }
+                        _ => {} // Stands until we counter or accept it
+                    }
                 }
 
                 SettlementResponseType::RequestModification => {
                     info!("Modification requested for proposal {:?}", proposal_hash);
                     negotiation.status = NegotiationStatus::UnderReview;
+                    self.audit(&responder, AuditAction::UnderReview, proposal_hash, reason.unwrap_or_default()).await?;
                 }
             }
         }
@@ -510,6 +874,172 @@ impl SettlementMessaging {
         Ok(())
     }
 
+    /// Counter the last offer on a bilateral proposal with `amount_cents`, backed by `evidence_hash`
+    pub async fn counter_offer(
+        &self,
+        proposal_hash: Blake2bHash,
+        amount_cents: u64,
+        evidence_hash: Option<Blake2bHash>,
+    ) -> std::result::Result<u32, BlockchainError> {
+        let (round, local) = {
+            let mut negotiations = self.active_negotiations.write().await;
+            let negotiation = negotiations.get_mut(&proposal_hash)
+                .ok_or_else(|| BlockchainError::NotFound(format!("Negotiation {} not found", proposal_hash)))?;
+            if matches!(negotiation.status, NegotiationStatus::Accepted | NegotiationStatus::Rejected | NegotiationStatus::Escalated) {
+                return Err(BlockchainError::InvalidState(format!("Negotiation {} is decided", proposal_hash)));
+            }
+            let local = self.local_participant(negotiation).await;
+            let offer = self.counter_offer_policy
+                .next_round(&negotiation.rounds, local.clone(), amount_cents, evidence_hash)
+                .map_err(BlockchainError::InvalidOperation)?;
+            let round = offer.round;
+            negotiation.rounds.push(offer);
+            negotiation.status = NegotiationStatus::CounterProposed;
+            (round, local)
+        };
+        // The original amount is no longer what we would approve
+        self.pending_approvals.write().await.remove(&proposal_hash);
+
+        info!("Countering proposal {:?} in round {} with {}", proposal_hash, round, amount_cents);
+        self.audit(&local, AuditAction::CounterProposed, proposal_hash,
+                   format!("round {} counter amount {}", round, amount_cents)).await?;
+
+        let response = SettlementResponseType::CounterOffer;
+        let responder_signature = self.sign_statement_as(&local, &("settlement-response", proposal_hash, &response, round, amount_cents)).await?;
+        let message = SettlementMessage::SettlementResponse {
+            proposal_hash,
+            response,
+            counter_amount: Some(amount_cents),
+            reason: None,
+            responder_signature,
+            round,
+            evidence_hash,
+        };
+        self.send_settlement_message(message, "settlement").await?;
+        Ok(round)
+    }
+
+    /// Accept the amount two offers converged on, through our signer quorum if it needs one
+    async fn accept_converged_offer(
+        &self,
+        negotiation: &SettlementNegotiation,
+        agreed: u64,
+    ) -> std::result::Result<(), BlockchainError> {
+        let proposal_hash = negotiation.proposal_id;
+        let local = self.local_participant(negotiation).await;
+        let creditor = negotiation.bilateral_amounts.keys().next().map(|(creditor, _)| creditor.clone())
+            .unwrap_or_else(|| Self::counterparty(negotiation, &local));
+        info!("🤝 Offers on {:?} converged, splitting the difference at {}", proposal_hash, agreed);
+
+        let is_debtor = creditor != local;
+        if is_debtor && agreed > self.auto_accept_threshold && self.has_signer_quorum(&local).await {
+            self.audit(&local, AuditAction::ApprovalRequested, proposal_hash,
+                       format!("converged {} {} from {}", agreed, negotiation.currency, creditor)).await?;
+            return self.request_quorum_approval(proposal_hash, creditor, agreed, negotiation.currency.clone()).await;
+        }
+
+        if let Some(negotiation) = self.active_negotiations.write().await.get_mut(&proposal_hash) {
+            negotiation.bilateral_amounts.values_mut().for_each(|amount| *amount = agreed);
+            negotiation.status = NegotiationStatus::Accepted;
+        }
+        self.audit(&local, AuditAction::Accepted, proposal_hash,
+                   format!("converged on {} {}", agreed, negotiation.currency)).await?;
+
+        let response = SettlementResponseType::Accept;
+        let round = negotiation.rounds.last().map_or(0, |offer| offer.round);
+        let responder_signature = self.sign_statement_as(&local, &("settlement-response", proposal_hash, &response, round, agreed)).await?;
+        let message = SettlementMessage::SettlementResponse {
+            proposal_hash,
+            response,
+            counter_amount: Some(agreed),
+            reason: None,
+            responder_signature,
+            round,
+            evidence_hash: None,
+        };
+        self.send_settlement_message(message, "settlement").await
+    }
+
+    /// Replace the counter-offer round limit and convergence tolerance
+    pub fn set_counter_offer_policy(&mut self, policy: CounterOfferPolicy) {
+        self.counter_offer_policy = policy;
+    }
+
+    /// Replace the per-counterparty acceptance policies
+    pub fn set_settlement_policies(&mut self, policies: SettlementPolicies) {
+        self.policies = RwLock::new(policies);
+    }
+
+    /// Operators of the on-chain registry, unregistered counterparties getting the unknown operator policy
+    pub async fn set_registered_operators(&self, operators: Vec<NetworkId>) {
+        for hosted in self.hosted_identities.write().await.values_mut() {
+            hosted.policies.set_registered_operators(operators.clone());
+        }
+        self.policies.write().await.set_registered_operators(operators);
+    }
+
+    /// Host a further operator identity, negotiating its settlements with its own key and policies
+    pub fn host_identity(&mut self, identity: OperatorIdentity) {
+        self.hosted_identities.get_mut().insert(identity.network_id.clone(), identity);
+    }
+
+    /// Whether `network` is our own identity or one we host
+    pub async fn hosts(&self, network: &NetworkId) -> bool {
+        *network == self.network_id || self.hosted_identities.read().await.contains_key(network)
+    }
+
+    /// Our identities among `networks`, our own first
+    async fn local_identities(&self, networks: &[NetworkId]) -> Vec<NetworkId> {
+        let hosted = self.hosted_identities.read().await;
+        let mut local: Vec<NetworkId> = networks.iter()
+            .filter(|network| **network == self.network_id || hosted.contains_key(*network))
+            .cloned()
+            .collect();
+        local.sort_by_key(|network| *network != self.network_id);
+        local
+    }
+
+    /// Our identity negotiating `negotiation`
+    async fn local_participant(&self, negotiation: &SettlementNegotiation) -> NetworkId {
+        self.local_identities(&negotiation.participants).await.into_iter().next()
+            .unwrap_or_else(|| self.network_id.clone())
+    }
+
+    /// Policy `identity` applies to proposals and nettings of `counterparty`
+    async fn policy_for(&self, identity: &NetworkId, counterparty: &NetworkId) -> CounterpartyPolicy {
+        match self.hosted_identities.read().await.get(identity) {
+            Some(hosted) => hosted.policies.policy_for(counterparty).clone(),
+            None => self.policies.read().await.policy_for(counterparty).clone(),
+        }
+    }
+
+    /// Key `identity` signs with
+    async fn signer_for(&self, identity: &NetworkId) -> Option<Arc<dyn Signer>> {
+        match self.hosted_identities.read().await.get(identity) {
+            Some(hosted) => hosted.signer.clone(),
+            None => self.signer.clone(),
+        }
+    }
+
+    /// Whether large settlements of `identity` are approved by our signer quorum, which only
+    /// approves for our own identity
+    async fn has_signer_quorum(&self, identity: &NetworkId) -> bool {
+        *identity == self.network_id && self.approval_keys.read().await.contains_key(identity)
+    }
+
+    /// Whether a netting's gross total is above the proof limit of any other participant
+    async fn netting_requires_proof(&self, negotiation: &SettlementNegotiation) -> bool {
+        let gross_total: u64 = negotiation.bilateral_amounts.values().sum();
+        let locals = self.local_identities(&negotiation.participants).await;
+        let local = locals.first().unwrap_or(&self.network_id);
+        for network in negotiation.participants.iter().filter(|network| !locals.contains(network)) {
+            if self.policy_for(local, network).await.requires_proof(gross_total) {
+                return true;
+            }
+        }
+        false
+    }
+
     /// Handle netting proposal
     async fn handle_netting_proposal(
         &self,
@@ -519,39 +1049,72 @@ impl SettlementMessaging {
         savings_percentage: u32,
         coordinator: NetworkId,
         proposal_id: Blake2bHash,
+        netting_proof: Option<Vec<u8>>,
     ) -> std::result::Result<(), BlockchainError> {
-        // Only handle if we are a participant
-        if !participants.contains(&self.network_id) {
+        // Only handle if we are a participant, each of our identities taking part answering for itself
+        let locals = self.local_identities(&participants).await;
+        if locals.is_empty() {
             return Ok(());
         }
 
         info!("Received netting proposal from {} with {}% savings among {:?}",
               coordinator, savings_percentage, participants);
 
-        // Validate netting calculations
-        let our_net = net_settlements.iter()
-            .find(|(network, _)| *network == self.network_id)
-            .map(|(_, amount)| *amount)
-            .unwrap_or(0);
-
-        info!("Our net position in netting: {}", our_net);
-
-        // Auto-agree if savings are significant (>30%) and our position is reasonable
-        let agreement_type = if savings_percentage >= 30 && our_net.abs() <= 1_000_000 { // €10k limit
-            NettingAgreementType::Agree
-        } else {
-            NettingAgreementType::ConditionalAgree
-        };
+        // Never sign a netting we cannot verify
+        if !self.verify_netting_proposal(&bilateral_amounts, &net_settlements, netting_proof.as_deref())? {
+            warn!("❌ Rejecting netting proposal {} from {}: netting could not be verified",
+                  proposal_id, coordinator);
+            for local in &locals {
+                self.audit(local, AuditAction::Rejected, proposal_id,
+                           "netting could not be verified".to_string()).await?;
+
+                let agreement_type = NettingAgreementType::Disagree;
+                let participant_signature = self.sign_statement_as(local, &("netting-agreement", proposal_id, &agreement_type)).await?;
+                let rejection = SettlementMessage::NettingAgreement {
+                    proposal_id,
+                    agreement_type,
+                    participant_signature,
+                    zkp_proof: None,
+                };
+                self.send_settlement_message(rejection, "settlement").await?;
+            }
+            return Ok(());
+        }
 
-        // Send agreement
-        let agreement_message = SettlementMessage::NettingAgreement {
-            proposal_id,
-            agreement_type,
-            participant_signature: vec![], // Would sign with network key
-            zkp_proof: None, // Would generate ZK proof of calculations
-        };
+        let gross_total: u64 = bilateral_amounts.iter().map(|(_, _, amount)| amount).sum();
+        for local in &locals {
+            // Validate netting calculations
+            let our_net = net_settlements.iter()
+                .find(|(network, _)| network == local)
+                .map(|(_, amount)| *amount)
+                .unwrap_or(0);
+
+            info!("Net position of {} in netting: {}", local, our_net);
+
+            // Auto-agree within the coordinator's policy, large nettings only with a verified proof we sign for
+            let proven = self.zk_verifier.is_some() && netting_proof.is_some() && self.signer_for(local).await.is_some();
+            let agrees = self.policy_for(local, &coordinator).await
+                .agrees_to_netting(gross_total, savings_percentage, our_net, proven);
+            let agreement_type = if agrees {
+                NettingAgreementType::Agree
+            } else {
+                NettingAgreementType::ConditionalAgree
+            };
+
+            self.audit(local, AuditAction::Accepted, proposal_id,
+                       format!("{:?}, net position {}", agreement_type, our_net)).await?;
+
+            // Send agreement
+            let participant_signature = self.sign_statement_as(local, &("netting-agreement", proposal_id, &agreement_type)).await?;
+            let agreement_message = SettlementMessage::NettingAgreement {
+                proposal_id,
+                agreement_type,
+                participant_signature,
+                zkp_proof: netting_proof.clone(), // Proof this agreement was checked against
+            };
 
-        self.send_settlement_message(agreement_message, "settlement").await?;
+            self.send_settlement_message(agreement_message, "settlement").await?;
+        }
 
         Ok(())
     }
@@ -561,12 +1124,24 @@ impl SettlementMessaging {
         &self,
         proposal_id: Blake2bHash,
         agreement_type: NettingAgreementType,
-        _participant_signature: Vec<u8>,
-        _zkp_proof: Option<Vec<u8>>,
+        participant_signature: Vec<u8>,
+        zkp_proof: Option<Vec<u8>>,
     ) -> std::result::Result<(), BlockchainError> {
         let mut negotiations = self.active_negotiations.write().await;
 
         if let Some(negotiation) = negotiations.get_mut(&proposal_id) {
+            if matches!(negotiation.status, NegotiationStatus::Accepted | NegotiationStatus::Rejected) {
+                debug!("Ignoring agreement to decided netting proposal {:?}", proposal_id);
+                return Ok(());
+            }
+            // Above a participant's proof limit only a signed agreement against the proof counts
+            if matches!(agreement_type, NettingAgreementType::Agree)
+                && (participant_signature.is_empty() || zkp_proof.is_none())
+                && self.netting_requires_proof(negotiation).await
+            {
+                warn!("❌ Ignoring unsigned or unproven agreement to netting proposal {:?}", proposal_id);
+                return Ok(());
+            }
             info!("Received netting agreement: {:?} for proposal {:?}",
                   agreement_type, proposal_id);
 
@@ -577,11 +1152,17 @@ impl SettlementMessaging {
                     if agreement_count >= negotiation.participants.len() {
                         info!("All participants agreed to netting proposal");
                         negotiation.status = NegotiationStatus::Accepted;
+                        let local = self.local_participant(negotiation).await;
+                        self.audit(&local, AuditAction::Accepted, proposal_id,
+                                   "all participants agreed".to_string()).await?;
                         self.execute_netting_settlement(proposal_id).await?;
                     }
                 }
                 NettingAgreementType::Disagree => {
                     negotiation.status = NegotiationStatus::Rejected;
+                    let local = self.local_participant(negotiation).await;
+                    self.audit(&local, AuditAction::Rejected, proposal_id,
+                               "a participant disagreed".to_string()).await?;
                 }
                 NettingAgreementType::ConditionalAgree => {
                     // Handle conditional agreement
@@ -605,8 +1186,19 @@ impl SettlementMessaging {
         settlement_method: SettlementMethod,
         _coordinator_signature: Vec<u8>,
     ) -> std::result::Result<(), BlockchainError> {
+        // An instruction is paid once, whether still pending or already completed
+        let known = self.pending_settlements.read().await.contains_key(&settlement_id)
+            || self.completed_settlements.read().await.iter().any(|completed| completed.settlement_id == settlement_id);
+        if known {
+            debug!("Ignoring repeated instruction for settlement {:?}", settlement_id);
+            return Ok(());
+        }
+
         info!("Received settlement instruction: {} -> {} for {} {} via {:?}",
-              creditor, debtor, final_amount as f64 / 100.0, currency, settlement_method);
+              creditor, debtor, final_amount as f64 / 100.0, currency, &settlement_method);
+
+        self.audit(&creditor, AuditAction::Instructed, settlement_id,
+                   format!("{} -> {}: {} {} via {:?}", debtor, creditor, final_amount, currency, settlement_method)).await?;
 
         let pending_settlement = PendingSettlement {
             settlement_id,
@@ -615,6 +1207,7 @@ impl SettlementMessaging {
             amount: final_amount,
             currency,
             due_date,
+            settlement_method,
             status: SettlementStatus::Pending,
             created_at: chrono::Utc::now().timestamp() as u64,
         };
@@ -622,7 +1215,7 @@ impl SettlementMessaging {
         self.pending_settlements.write().await.insert(settlement_id, pending_settlement);
 
         // If we are the debtor, initiate payment
-        if debtor == self.network_id {
+        if self.hosts(&debtor).await {
             self.initiate_payment(settlement_id).await?;
         }
 
@@ -641,19 +1234,36 @@ impl SettlementMessaging {
         let mut pending = self.pending_settlements.write().await;
 
         if let Some(settlement) = pending.get_mut(&settlement_id) {
+            // Confirmations only move a settlement forward, a repeated one is ignored
+            let status = match confirmation_type {
+                ConfirmationType::PaymentSent | ConfirmationType::PaymentReceived => SettlementStatus::InProgress,
+                ConfirmationType::PaymentConfirmed => SettlementStatus::Completed,
+                ConfirmationType::PaymentFailed => SettlementStatus::Failed,
+            };
+            if settlement.status == status && status != SettlementStatus::InProgress {
+                debug!("Settlement {:?} already {:?}", settlement_id, status);
+                return Ok(());
+            }
             match confirmation_type {
                 ConfirmationType::PaymentSent => {
                     info!("Payment sent for settlement {:?}", settlement_id);
                     settlement.status = SettlementStatus::InProgress;
+                    self.audit(&settlement.debtor, AuditAction::PaymentSent, settlement_id,
+                               transaction_ref.unwrap_or_default()).await?;
                 }
                 ConfirmationType::PaymentReceived => {
                     info!("Payment received for settlement {:?}", settlement_id);
                     settlement.status = SettlementStatus::InProgress;
+                    self.audit(&settlement.creditor, AuditAction::PaymentReceived, settlement_id,
+                               transaction_ref.unwrap_or_default()).await?;
                 }
                 ConfirmationType::PaymentConfirmed => {
                     info!("Payment confirmed for settlement {:?}: {:?}",
                           settlement_id, transaction_ref);
                     settlement.status = SettlementStatus::Completed;
+                    self.audit(&settlement.debtor, AuditAction::Executed, settlement_id,
+                               format!("{} {} ref {}", settlement.amount, settlement.currency,
+                                       transaction_ref.clone().unwrap_or_default())).await?;
 
                     // Move to completed settlements
                     let completed = CompletedSettlement {
@@ -662,7 +1272,7 @@ impl SettlementMessaging {
                         final_amounts: HashMap::new(), // Would populate with actual amounts
                         completion_time: timestamp,
                         savings_achieved: 0,
-                        method_used: SettlementMethod::BankTransfer, // Would use actual method
+                        method_used: settlement.settlement_method.clone(),
                     };
 
                     self.completed_settlements.write().await.push(completed);
@@ -671,6 +1281,8 @@ impl SettlementMessaging {
                 ConfirmationType::PaymentFailed => {
                     warn!("Payment failed for settlement {:?}", settlement_id);
                     settlement.status = SettlementStatus::Failed;
+                    self.audit(&settlement.debtor, AuditAction::PaymentFailed, settlement_id,
+                               transaction_ref.unwrap_or_default()).await?;
                 }
             }
         }
@@ -687,21 +1299,281 @@ impl SettlementMessaging {
         evidence_hash: Blake2bHash,
         initiator: NetworkId,
     ) -> std::result::Result<(), BlockchainError> {
+        // The initiator's dispute is opened once, later initiations only add their evidence
+        let dispute_id = DisputeManager::dispute_id(&settlement_id, &initiator);
+        if self.dispute_manager.get_dispute(&dispute_id).await.is_some_and(|dispute| !dispute.is_resolved()) {
+            debug!("Dispute {} already open", dispute_id);
+            if evidence_hash != Blake2bHash::zero() {
+                self.dispute_manager.attach_evidence(&dispute_id, initiator, evidence_hash).await?;
+            }
+            return Ok(());
+        }
+
         warn!("Dispute initiated for settlement {:?} by {}: {:?}",
               settlement_id, initiator, dispute_reason);
 
-        let mut pending = self.pending_settlements.write().await;
-        if let Some(settlement) = pending.get_mut(&settlement_id) {
-            settlement.status = SettlementStatus::Disputed;
+        let (original_amount, respondent) = {
+            let mut pending = self.pending_settlements.write().await;
+            match pending.get_mut(&settlement_id) {
+                Some(settlement) => {
+                    settlement.status = SettlementStatus::Disputed;
+                    let respondent = if settlement.creditor == initiator { &settlement.debtor } else { &settlement.creditor };
+                    (settlement.amount, Some(respondent.clone()))
+                }
+                None => (disputed_amount.unwrap_or(0), None),
+            }
+        };
+
+        self.audit(&initiator, AuditAction::Disputed, settlement_id,
+                   format!("{:?}, disputed amount {:?} of {}", dispute_reason, disputed_amount, original_amount)).await?;
+
+        let dispute_id = self.dispute_manager.open_dispute(
+            settlement_id, dispute_reason, disputed_amount, original_amount, initiator.clone(), respondent
+        ).await?;
+
+        // The initiation message references the initiator's primary evidence document
+        if evidence_hash != Blake2bHash::zero() {
+            self.dispute_manager.attach_evidence(&dispute_id, initiator, evidence_hash).await?;
         }
 
-        // In a real implementation, this would trigger dispute resolution process
         info!("Dispute details - Amount: {:?}, Evidence: {:?}",
               disputed_amount, evidence_hash);
 
         Ok(())
     }
 
+    /// Handle validator vote on a dispute
+    async fn handle_dispute_vote(
+        &self,
+        dispute_id: Blake2bHash,
+        validator: NetworkId,
+        verdict: DisputeVerdict,
+        validator_signature: Vec<u8>,
+    ) -> std::result::Result<(), BlockchainError> {
+        // Only a vote signed by the arbitrator's registered key counts, checked before it changes the dispute
+        self.dispute_manager.verify_vote(&dispute_id, &validator, &verdict, &validator_signature).await?;
+
+        // The first validator vote closes the evidence phase
+        if let Some(dispute) = self.dispute_manager.get_dispute(&dispute_id).await {
+            if dispute.votes.get(&validator) == Some(&verdict) {
+                debug!("{} already voted on dispute {}", validator, dispute_id);
+                return Ok(());
+            }
+            if dispute.state == DisputeState::EvidenceSubmitted {
+                self.dispute_manager.begin_arbitration(&dispute_id).await?;
+            }
+        }
+
+        if let Some(outcome) = self.dispute_manager.cast_vote(&dispute_id, validator, verdict, &validator_signature).await? {
+            self.apply_dispute_outcome(dispute_id, outcome).await?;
+        }
+        Ok(())
+    }
+
+    /// Open a dispute on a settlement and submit the supporting evidence document
+    pub async fn initiate_dispute(
+        &self,
+        settlement_id: Blake2bHash,
+        dispute_reason: DisputeReason,
+        disputed_amount: Option<u64>,
+        evidence_document: &[u8],
+    ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        self.initiate_dispute_as(self.network_id.clone(), settlement_id, dispute_reason, disputed_amount, evidence_document).await
+    }
+
+    /// Open a dispute as `initiator`, our own identity or one we host
+    pub async fn initiate_dispute_as(
+        &self,
+        initiator: NetworkId,
+        settlement_id: Blake2bHash,
+        dispute_reason: DisputeReason,
+        disputed_amount: Option<u64>,
+        evidence_document: &[u8],
+    ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        self.handle_dispute_initiation(
+            settlement_id, dispute_reason.clone(), disputed_amount, Blake2bHash::zero(), initiator.clone()
+        ).await?;
+
+        let dispute_id = DisputeManager::dispute_id(&settlement_id, &initiator);
+        let evidence_hash = self.dispute_manager
+            .submit_evidence(&dispute_id, initiator.clone(), evidence_document).await?;
+
+        let message = SettlementMessage::DisputeInitiation {
+            settlement_id,
+            dispute_reason,
+            disputed_amount,
+            evidence_hash,
+            initiator,
+        };
+        self.send_settlement_message(message, "settlement").await?;
+
+        Ok(dispute_id)
+    }
+
+    /// Submit additional evidence for an open dispute
+    pub async fn submit_dispute_evidence(
+        &self,
+        dispute_id: Blake2bHash,
+        evidence_document: &[u8],
+    ) -> std::result::Result<Blake2bHash, BlockchainError> {
+        let evidence_hash = self.dispute_manager
+            .submit_evidence(&dispute_id, self.network_id.clone(), evidence_document).await?;
+
+        let submitter_signature = self.sign_statement(&("dispute-evidence", dispute_id, evidence_hash)).await?;
+        let message = SettlementMessage::DisputeEvidence {
+            dispute_id,
+            evidence_hash,
+            submitter: self.network_id.clone(),
+            submitter_signature,
+        };
+        self.send_settlement_message(message, "settlement").await?;
+
+        Ok(evidence_hash)
+    }
+
+    /// Cast this validator's vote on a dispute and broadcast it
+    pub async fn vote_on_dispute(
+        &self,
+        dispute_id: Blake2bHash,
+        verdict: DisputeVerdict,
+    ) -> std::result::Result<(), BlockchainError> {
+        let validator_signature = self.sign_statement(&("dispute-vote", dispute_id, &verdict)).await?;
+        let message = SettlementMessage::DisputeVote {
+            dispute_id,
+            validator: self.network_id.clone(),
+            verdict: verdict.clone(),
+            validator_signature: validator_signature.clone(),
+        };
+
+        self.handle_dispute_vote(dispute_id, self.network_id.clone(), verdict, validator_signature).await?;
+        self.send_settlement_message(message, "settlement").await
+    }
+
+    /// Adjust the disputed settlement once arbitration concludes
+    async fn apply_dispute_outcome(
+        &self,
+        dispute_id: Blake2bHash,
+        outcome: DisputeOutcome,
+    ) -> std::result::Result<(), BlockchainError> {
+        let dispute = self.dispute_manager.get_dispute(&dispute_id).await
+            .ok_or_else(|| BlockchainError::NotFound(format!("Dispute {} not found", dispute_id)))?;
+
+        let mut pending = self.pending_settlements.write().await;
+        let Some(settlement) = pending.get_mut(&dispute.settlement_id) else {
+            debug!("Dispute {} resolved for unknown settlement {}", dispute_id, dispute.settlement_id);
+            return Ok(());
+        };
+
+        let details = match &outcome {
+            DisputeOutcome::Upheld { adjusted_amount } => format!("dispute {} upheld, {} -> {}", dispute_id, settlement.amount, adjusted_amount),
+            DisputeOutcome::Rejected => format!("dispute {} rejected, {} kept", dispute_id, settlement.amount),
+        };
+        self.audit(&self.network_id, AuditAction::DisputeResolved, settlement.settlement_id, details).await?;
+
+        match outcome {
+            DisputeOutcome::Upheld { adjusted_amount } => {
+                info!("⚖️  Dispute upheld: settlement {} adjusted €{:.2} → €{:.2}",
+                      settlement.settlement_id,
+                      settlement.amount as f64 / 100.0,
+                      adjusted_amount as f64 / 100.0);
+                settlement.amount = adjusted_amount;
+            }
+            DisputeOutcome::Rejected => {
+                info!("⚖️  Dispute rejected: settlement {} keeps €{:.2}",
+                      settlement.settlement_id, settlement.amount as f64 / 100.0);
+            }
+        }
+        settlement.status = SettlementStatus::Pending;
+
+        Ok(())
+    }
+
+    /// Whether a CDR message from `from_peer` on behalf of `operator` is within the rate limits
+    /// Messages over them are counted as dropped and should not be processed
+    pub async fn admit_cdr_message(&self, from_peer: PeerId, operator: &NetworkId) -> bool {
+        let admitted = self.admit(MessageClass::Cdr, from_peer, Some(operator)).await;
+        if !admitted {
+            crate::metrics::metrics().cdr_messages_dropped.inc();
+        }
+        admitted
+    }
+
+    /// Take a token for a message from the buckets of its peer and operator
+    async fn admit(&self, class: MessageClass, from_peer: PeerId, operator: Option<&NetworkId>) -> bool {
+        let verdict = self.rate_limiter.lock().await.check(class, from_peer, operator, std::time::Instant::now());
+        match verdict {
+            RateLimitVerdict::Allowed => true,
+            RateLimitVerdict::PeerLimited => {
+                debug!("Dropping {:?} message from {}: peer over its rate limit", class, from_peer);
+                false
+            }
+            RateLimitVerdict::OperatorLimited(operator) => {
+                debug!("Dropping {:?} message from {}: operator {} over its rate limit", class, from_peer, operator);
+                false
+            }
+        }
+    }
+
+    /// Limit the settlement and CDR messages each peer and operator may send
+    pub fn set_rate_limits(&mut self, config: RateLimitConfig) {
+        self.rate_limiter = Mutex::new(MessageRateLimiter::new(config));
+    }
+
+    /// Collect garbage once the collection interval passed since the last time
+    async fn maybe_collect_garbage(&self) {
+        let now = std::time::Instant::now();
+        {
+            let mut last = self.last_garbage_collection.lock().await;
+            if now.saturating_duration_since(*last) < GARBAGE_COLLECTION_INTERVAL {
+                return;
+            }
+            *last = now;
+        }
+        self.collect_garbage(chrono::Utc::now().timestamp() as u64, now).await;
+    }
+
+    /// Drop negotiations past their expiry, approvals older than the negotiation timeout and
+    /// rate limit buckets of quiet senders, returning the negotiations and approvals dropped
+    pub async fn collect_garbage(&self, now: u64, instant: std::time::Instant) -> usize {
+        let mut expired = 0;
+        self.active_negotiations.write().await.retain(|proposal_id, negotiation| {
+            let live = now < negotiation.expires_at;
+            if !live {
+                if !matches!(negotiation.status, NegotiationStatus::Accepted | NegotiationStatus::Rejected) {
+                    info!("⌛ Negotiation {:?} expired as {:?}", proposal_id, negotiation.status);
+                }
+                expired += 1;
+            }
+            live
+        });
+
+        let timeout = self.negotiation_timeout.as_secs();
+        self.pending_approvals.write().await.retain(|proposal_hash, approval| {
+            let live = now < approval.created_at + timeout;
+            if !live {
+                info!("⌛ Approval of {:?} expired with {} partial signatures", proposal_hash, approval.partial_signatures.len());
+                expired += 1;
+            }
+            live
+        });
+
+        // Redeliveries stop long before a negotiation could expire
+        self.seen_messages.write().await.retain(|_, seen_at| now < *seen_at + timeout);
+        self.rate_limiter.lock().await.prune(instant);
+        crate::metrics::metrics().negotiations_expired.inc_by(expired as u64);
+        expired
+    }
+
+    /// Configure the multilateral netting solver
+    pub fn set_netting_config(&mut self, config: NettingConfig) {
+        self.netting_solver = MultilateralNettingSolver::new(config);
+    }
+
+    /// Get dispute manager
+    pub fn dispute_manager(&self) -> Arc<DisputeManager> {
+        self.dispute_manager.clone()
+    }
+
     /// Execute bilateral settlement
     async fn execute_settlement(&self, _proposal_id: Blake2bHash) -> std::result::Result<(), BlockchainError> {
         // In a real implementation, this would:
@@ -732,10 +1604,11 @@ impl SettlementMessaging {
             info!("   {} → {}: €{:.2}", from, to, *amount as f64 / 100.0);
         }
 
-        // Step 2: Calculate net positions using triangular netting algorithm
-        let net_positions = self.calculate_triangular_netting(&bilateral_amounts)?;
+        // Step 2: Calculate net positions using multilateral netting algorithm
+        let netting = self.calculate_multilateral_netting(&bilateral_amounts)?;
+        let net_positions = netting.net_positions.clone();
 
-        info!("🎯 Net positions after triangular netting:");
+        info!("🎯 Net positions after multilateral netting:");
         for (network, net_amount) in &net_positions {
             if *net_amount != 0 {
                 if *net_amount > 0 {
@@ -764,7 +1637,10 @@ impl SettlementMessaging {
 
         // Step 4: Generate ZK proofs of netting correctness
         info!("🔐 Generating ZK proofs of netting correctness...");
-        let netting_proofs = self.generate_netting_proofs(&bilateral_amounts, &net_positions).await?;
+        match self.generate_netting_proof(&bilateral_amounts, &netting).await? {
+            Some(proof) => info!("✅ Netting proof generated ({} bytes)", proof.len()),
+            None => warn!("⚠️  Netting settled without a correctness proof"),
+        }
 
         // Step 5: Create settlement instructions for net amounts only
         let settlement_instructions = self.create_net_settlement_instructions(&net_positions, proposal_id).await?;
@@ -784,15 +1660,105 @@ impl SettlementMessaging {
     }
 
     /// Initiate payment for settlement
-    async fn initiate_payment(&self, _settlement_id: Blake2bHash) -> std::result::Result<(), BlockchainError> {
-        // In a real implementation, this would:
-        // 1. Interface with banking systems
-        // 2. Execute crypto transfers
-        // 3. Use clearing house protocols
-        // 4. Confirm payment completion
+    async fn initiate_payment(&self, settlement_id: Blake2bHash) -> std::result::Result<(), BlockchainError> {
+        let instruction = {
+            let pending = self.pending_settlements.read().await;
+            let settlement = pending.get(&settlement_id)
+                .ok_or_else(|| BlockchainError::NotFound(format!("Settlement {} not pending", settlement_id)))?;
+
+            SettlementInstruction {
+                instruction_id: settlement.settlement_id,
+                creditor: settlement.creditor.clone(),
+                debtor: settlement.debtor.clone(),
+                amount: settlement.amount,
+                currency: settlement.currency.clone(),
+                due_date: settlement.due_date,
+                settlement_method: settlement.settlement_method.clone(),
+            }
+        };
 
-        info!("Initiating payment - implementation pending");
-        Ok(())
+        self.execute_payment(instruction).await
+    }
+
+    /// Execute payment through the configured adapter and report the confirmation
+    async fn execute_payment(&self, instruction: SettlementInstruction) -> std::result::Result<(), BlockchainError> {
+        let receipt = self.settlement_executor.execute_or_fail(&instruction).await;
+        let confirmer_signature = self.sign_statement(&(
+            "settlement-confirmation", receipt.instruction_id, &receipt.confirmation_type, &receipt.transaction_ref,
+        )).await?;
+        let confirmation = receipt.to_confirmation_message(confirmer_signature.clone());
+
+        // Track our own confirmation before telling the counterparty
+        self.handle_settlement_confirmation(
+            receipt.instruction_id,
+            receipt.confirmation_type.clone(),
+            receipt.transaction_ref.clone(),
+            receipt.executed_at,
+            confirmer_signature,
+        ).await?;
+
+        self.send_settlement_message(confirmation, "settlement").await
+    }
+
+    /// Replace the payment adapters used for settlement execution
+    pub fn set_settlement_executor(&mut self, executor: Arc<SettlementExecutor>) {
+        self.settlement_executor = executor;
+    }
+
+    /// Write settlement state transitions to a shared, persistent audit log
+    pub fn set_audit_log(&mut self, audit_log: Arc<AuditLog>) {
+        self.audit_log = audit_log;
+    }
+
+    /// Get audit log
+    pub fn audit_log(&self) -> Arc<AuditLog> {
+        self.audit_log.clone()
+    }
+
+    /// Log inbound messages for replay
+    pub fn set_recorder(&mut self, recorder: Arc<MessageRecorder>) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Sign outgoing settlement decisions with the network key held by `signer`
+    pub fn set_signer(&mut self, signer: Arc<dyn Signer>) {
+        self.signer = Some(signer);
+    }
+
+    /// BLS signature over a settlement decision, empty without a signer
+    async fn sign_statement<T: Serialize>(&self, statement: &T) -> std::result::Result<Vec<u8>, BlockchainError> {
+        self.sign_statement_as(&self.network_id, statement).await
+    }
+
+    /// BLS signature of `identity` over a settlement decision, empty if it has no signer
+    async fn sign_statement_as<T: Serialize>(&self, identity: &NetworkId, statement: &T) -> std::result::Result<Vec<u8>, BlockchainError> {
+        let signer = match self.signer_for(identity).await {
+            Some(signer) => signer,
+            None => return Ok(vec![]),
+        };
+        let message = to_canonical_bytes(statement)?;
+        let signature = signer.sign(&message).await
+            .map_err(|e| BlockchainError::Crypto(format!("Failed to sign settlement message: {}", e)))?;
+        Ok(signature.to_bytes().to_vec())
+    }
+
+    /// Record a settlement state transition taken by `actor`
+    async fn audit(
+        &self,
+        actor: &NetworkId,
+        action: AuditAction,
+        subject: Blake2bHash,
+        details: String,
+    ) -> std::result::Result<(), BlockchainError> {
+        self.audit_log.record(actor, action, subject, details).await.map(|_| ())
+    }
+
+    /// The other side of a bilateral negotiation
+    fn counterparty(negotiation: &SettlementNegotiation, local: &NetworkId) -> NetworkId {
+        negotiation.participants.iter()
+            .find(|network| *network != local)
+            .cloned()
+            .unwrap_or_else(|| local.clone())
     }
 
     /// Send settlement message
@@ -816,22 +1782,7 @@ impl SettlementMessaging {
 
     /// Calculate proposal hash
     fn calculate_proposal_hash(&self, message: &SettlementMessage) -> Blake2bHash {
-        Blake2bHash::from_data(format!("{:?}", message).as_bytes())
-    }
-
-    /// Calculate net positions for triangular netting
-    fn calculate_net_positions(&self, bilateral_amounts: &[(NetworkId, NetworkId, u64)]) -> Vec<(NetworkId, i64)> {
-        let mut net_positions: HashMap<NetworkId, i64> = HashMap::new();
-
-        for (from, to, amount) in bilateral_amounts {
-            let from_balance = net_positions.entry(from.clone()).or_insert(0);
-            *from_balance -= *amount as i64; // Outgoing is negative
-
-            let to_balance = net_positions.entry(to.clone()).or_insert(0);
-            *to_balance += *amount as i64; // Incoming is positive
-        }
-
-        net_positions.into_iter().collect()
+        message.content_id()
     }
 
     /// Calculate savings percentage from netting
@@ -847,147 +1798,274 @@ impl SettlementMessaging {
         savings as u32
     }
 
-    /// CORE TRIANGULAR NETTING ALGORITHM
-    /// Implements the mathematical algorithm used by telecom clearing houses
-    /// to reduce bilateral settlements into optimal net positions
-    fn calculate_triangular_netting(&self, bilateral_amounts: &[(NetworkId, NetworkId, u64)]) -> std::result::Result<Vec<(NetworkId, i64)>, BlockchainError> {
-        info!("🔄 Starting triangular netting calculation...");
+    /// CORE MULTILATERAL NETTING ALGORITHM
+    /// Cancels obligation cycles of any length up to the configured maximum
+    /// (bilateral, triangular and longer N-party cycles) and returns net positions
+    fn calculate_multilateral_netting(&self, bilateral_amounts: &[(NetworkId, NetworkId, u64)]) -> std::result::Result<NettingResult, BlockchainError> {
+        info!("🔄 Starting multilateral netting (max cycle length {})...",
+              self.netting_solver.config().max_cycle_length);
 
-        // Step 1: Build adjacency matrix of all bilateral obligations
-        let mut networks: std::collections::HashSet<NetworkId> = std::collections::HashSet::new();
-        for (from, to, _) in bilateral_amounts {
-            networks.insert(from.clone());
-            networks.insert(to.clone());
+        let result = self.netting_solver.solve(bilateral_amounts)?;
+
+        for cycle in &result.cancelled_cycles {
+            info!("   🔺 Cycle netted: {} (€{:.2})",
+                  cycle.networks.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" → "),
+                  cycle.amount as f64 / 100.0);
         }
 
-        let network_list: Vec<NetworkId> = networks.into_iter().collect();
-        let n = network_list.len();
+        info!("💰 Total eliminated flows: €{:.2}",
+              (result.gross_total - result.residual_total) as f64 / 100.0);
+        info!("✅ Multilateral netting calculation completed successfully");
 
-        info!("📊 Building netting matrix for {} networks", n);
+        Ok(result)
+    }
 
-        // Create obligation matrix: obligations[i][j] = amount network i owes to network j
-        let mut obligations = vec![vec![0u64; n]; n];
+    /// Generate ZK proof that netting calculation is correct
+    /// Proves that net positions derive from the bilateral amounts, that value
+    /// is conserved and that no residual obligation is negative or grows.
+    /// Returns None when no proving key is configured
+    async fn generate_netting_proof(
+        &self,
+        bilateral_amounts: &[(NetworkId, NetworkId, u64)],
+        netting: &NettingResult,
+    ) -> std::result::Result<Option<Vec<u8>>, BlockchainError> {
+        let prover = match &self.zk_prover {
+            Some(prover) => prover.clone(),
+            None => {
+                warn!("⚠️  No ZK prover configured - netting proof not generated");
+                return Ok(None);
+            }
+        };
 
-        for (from, to, amount) in bilateral_amounts {
-            if let (Some(from_idx), Some(to_idx)) = (
-                network_list.iter().position(|n| n == from),
-                network_list.iter().position(|n| n == to)
-            ) {
-                obligations[from_idx][to_idx] += amount;
-                info!("   {}[{}] → {}[{}]: €{:.2}", from, from_idx, to, to_idx, *amount as f64 / 100.0);
+        info!("🔐 Generating ZK proof for netting correctness...");
+
+        let bilateral = netting.obligation_matrix(bilateral_amounts);
+        let residual = netting.residual_matrix();
+        let net_positions = netting.net_position_values();
+
+        // Groth16 proving is CPU bound, keep it off the async runtime
+        let proof = tokio::task::spawn_blocking(move || {
+            prover.generate_netting_proof(&mut rand::thread_rng(), &bilateral, &residual, &net_positions)
+        }).await
+            .map_err(|e| BlockchainError::ZkProof(format!("Task join error: {}", e)))??;
+
+        Ok(Some(proof))
+    }
+
+    /// Verify a netting proposal before agreeing to it
+    /// Recomputes the netting locally and checks the coordinator's proof
+    fn verify_netting_proposal(
+        &self,
+        bilateral_amounts: &[(NetworkId, NetworkId, u64)],
+        net_settlements: &[(NetworkId, i64)],
+        netting_proof: Option<&[u8]>,
+    ) -> std::result::Result<bool, BlockchainError> {
+        let netting = match self.netting_solver.solve(bilateral_amounts) {
+            Ok(netting) => netting,
+            Err(e) => {
+                warn!("Netting proposal does not solve: {}", e);
+                return Ok(false);
             }
+        };
+
+        if netting.net_positions != net_settlements {
+            warn!("Proposed net settlements differ from local netting calculation");
+            return Ok(false);
         }
 
-        // Step 2: Apply triangular netting algorithm
-        // For each triangle of networks, find the minimum flow and subtract it from all three edges
-        let mut total_eliminated = 0u64;
-        let mut iterations = 0;
-
-        loop {
-            iterations += 1;
-            let mut progress_made = false;
-
-            // Find triangular cycles and net them out
-            for i in 0..n {
-                for j in 0..n {
-                    for k in 0..n {
-                        if i != j && j != k && k != i {
-                            // Check for triangle: i → j → k → i
-                            let cycle_min = obligations[i][j]
-                                .min(obligations[j][k])
-                                .min(obligations[k][i]);
-
-                            if cycle_min > 0 {
-                                info!("   🔺 Triangle found: {} → {} → {} → {} (min: €{:.2})",
-                                      network_list[i], network_list[j], network_list[k], network_list[i],
-                                      cycle_min as f64 / 100.0);
-
-                                // Subtract minimum from all three edges
-                                obligations[i][j] -= cycle_min;
-                                obligations[j][k] -= cycle_min;
-                                obligations[k][i] -= cycle_min;
-
-                                total_eliminated += cycle_min * 3; // Each unit eliminates 3 bilateral flows
-                                progress_made = true;
-
-                                info!("     ✂️  Eliminated €{:.2} from triangle", cycle_min as f64 / 100.0);
-                            }
-                        }
-                    }
-                }
+        let verifier = match &self.zk_verifier {
+            Some(verifier) => verifier,
+            None => {
+                warn!("⚠️  No ZK verifier configured - netting proof not checked");
+                return Ok(true);
             }
+        };
 
-            // Also handle bilateral netting (A owes B, B owes A)
-            for i in 0..n {
-                for j in (i+1)..n {
-                    let mutual_min = obligations[i][j].min(obligations[j][i]);
-                    if mutual_min > 0 {
-                        info!("   ↔️  Bilateral netting: {} ↔ {} (€{:.2})",
-                              network_list[i], network_list[j], mutual_min as f64 / 100.0);
-
-                        obligations[i][j] -= mutual_min;
-                        obligations[j][i] -= mutual_min;
-                        total_eliminated += mutual_min * 2; // Each unit eliminates 2 bilateral flows
-                        progress_made = true;
-                    }
-                }
+        let proof = match netting_proof {
+            Some(proof) => proof,
+            None => {
+                warn!("Netting proposal carries no correctness proof");
+                return Ok(false);
             }
+        };
 
-            if !progress_made || iterations > 100 {
-                break;
+        match verifier.verify_netting_proof(
+            proof, &netting.net_position_values(), netting.gross_total, netting.residual_total,
+        ) {
+            Ok(valid) => Ok(valid),
+            Err(e) => {
+                warn!("Netting proof verification failed: {}", e);
+                Ok(false)
             }
         }
+    }
 
-        info!("🔄 Netting completed in {} iterations", iterations);
-        info!("💰 Total eliminated flows: €{:.2}", total_eliminated as f64 / 100.0);
+    /// Register an operator's threshold key for approving large settlements
+    pub async fn register_approval_key(&self, network: NetworkId, key: ThresholdPublicKey) {
+        self.approval_keys.write().await.insert(network, key);
+    }
 
-        // Step 3: Calculate final net positions
-        let mut net_positions = vec![0i64; n];
+    /// Set this node's share of our operator's approval key
+    pub fn set_approval_share(&mut self, share: ThresholdKeyShare) {
+        self.approval_share = Some(share);
+    }
 
-        for i in 0..n {
-            for j in 0..n {
-                if i != j {
-                    net_positions[i] -= obligations[i][j] as i64; // What i owes (outgoing)
-                    net_positions[i] += obligations[j][i] as i64; // What i receives (incoming)
-                }
-            }
+    /// Message every authorized signer approves for a settlement proposal
+    fn approval_message(proposal_hash: &Blake2bHash) -> Vec<u8> {
+        let mut message = b"SP_CDR_SETTLEMENT_APPROVAL".to_vec();
+        message.extend_from_slice(proposal_hash.as_bytes());
+        message
+    }
+
+    /// Start collecting partial signatures for a settlement above the auto-accept threshold
+    async fn request_quorum_approval(
+        &self,
+        proposal_hash: Blake2bHash,
+        creditor: NetworkId,
+        amount: u64,
+        currency: String,
+    ) -> std::result::Result<(), BlockchainError> {
+        // Partial signatures already collected are kept when the proposal arrives again
+        if self.pending_approvals.read().await.contains_key(&proposal_hash) {
+            return Ok(());
         }
+        self.pending_approvals.write().await.insert(proposal_hash, PendingApproval {
+            proposal_hash,
+            creditor,
+            amount,
+            currency,
+            partial_signatures: Vec::new(),
+            created_at: chrono::Utc::now().timestamp() as u64,
+        });
+
+        let share = match &self.approval_share {
+            Some(share) => share,
+            None => return Ok(()), // Not an authorized signer, wait for the others
+        };
+
+        let partial_signature = share.sign(&Self::approval_message(&proposal_hash))?;
+        let message = SettlementMessage::ApprovalShare {
+            proposal_hash,
+            approver_network: self.network_id.clone(),
+            partial_signature: partial_signature.clone(),
+        };
+        self.send_settlement_message(message, "settlement").await?;
+
+        self.record_approval_share(proposal_hash, partial_signature).await
+    }
 
-        // Step 4: Verification - net positions should sum to zero
-        let total_net: i64 = net_positions.iter().sum();
-        if total_net != 0 {
-            return Err(BlockchainError::InvalidOperation(
-                format!("Netting calculation error: net positions sum to {} instead of 0", total_net)
-            ));
+    /// Add a signer's partial signature and accept once the quorum is reached
+    async fn record_approval_share(
+        &self,
+        proposal_hash: Blake2bHash,
+        partial_signature: PartialSignature,
+    ) -> std::result::Result<(), BlockchainError> {
+        let approval_key = match self.approval_keys.read().await.get(&self.network_id) {
+            Some(key) => key.clone(),
+            None => return Ok(()),
+        };
+
+        let message = Self::approval_message(&proposal_hash);
+        if !approval_key.verify_partial(&partial_signature, &message)? {
+            warn!("Invalid approval share from signer {} for {:?}", partial_signature.signer_index, proposal_hash);
+            return Ok(());
         }
 
-        // Convert back to NetworkId mapping
-        let result: Vec<(NetworkId, i64)> = network_list.into_iter()
-            .zip(net_positions.into_iter())
-            .collect();
+        let (group_signature, approved_amount) = {
+            let mut pending = self.pending_approvals.write().await;
+            let approval = match pending.get_mut(&proposal_hash) {
+                Some(approval) => approval,
+                None => {
+                    debug!("Approval share for unknown settlement {:?}", proposal_hash);
+                    return Ok(());
+                }
+            };
 
-        info!("✅ Triangular netting calculation completed successfully");
-        Ok(result)
+            if approval.partial_signatures.iter().any(|p| p.signer_index == partial_signature.signer_index) {
+                return Ok(());
+            }
+            approval.partial_signatures.push(partial_signature);
+
+            info!("Approval {}/{} for settlement of {} {} to {}",
+                  approval.partial_signatures.len(), approval_key.config.threshold,
+                  approval.amount as f64 / 100.0, approval.currency, approval.creditor);
+
+            if !approval_key.config.meets_threshold(approval.partial_signatures.len()) {
+                return Ok(());
+            }
+
+            let signature = approval_key.combine(&approval.partial_signatures, &message)?;
+            let approved_amount = approval.amount;
+            pending.remove(&proposal_hash);
+            (signature, approved_amount)
+        };
+
+        info!("✅ Signer quorum reached - accepting settlement {:?}", proposal_hash);
+        self.audit(&self.network_id, AuditAction::Approved, proposal_hash,
+                   "signer quorum reached".to_string()).await?;
+
+        let round = self.active_negotiations.read().await.get(&proposal_hash)
+            .and_then(|negotiation| negotiation.rounds.last().map(|offer| offer.round))
+            .unwrap_or(0);
+        if let Some(negotiation) = self.active_negotiations.write().await.get_mut(&proposal_hash) {
+            negotiation.bilateral_amounts.values_mut().for_each(|amount| *amount = approved_amount);
+            negotiation.status = NegotiationStatus::Accepted;
+        }
+        let response_message = SettlementMessage::SettlementResponse {
+            proposal_hash,
+            response: SettlementResponseType::Accept,
+            counter_amount: Some(approved_amount),
+            reason: None,
+            responder_signature: group_signature.to_bytes().to_vec(),
+            round,
+            evidence_hash: None,
+        };
+
+        self.send_settlement_message(response_message, "settlement").await
     }
 
-    /// Generate ZK proofs that netting calculation is correct
-    async fn generate_netting_proofs(
+    /// Check that an acceptance above the auto-accept threshold carries the
+    /// debtor's quorum signature, when the debtor has a registered approval key
+    async fn verify_quorum_acceptance(
         &self,
-        _bilateral_amounts: &[(NetworkId, NetworkId, u64)],
-        _net_positions: &[(NetworkId, i64)]
-    ) -> std::result::Result<Vec<Vec<u8>>, BlockchainError> {
-        info!("🔐 Generating ZK proofs for netting correctness...");
+        negotiation: &SettlementNegotiation,
+        proposal_hash: &Blake2bHash,
+        responder_signature: &[u8],
+    ) -> std::result::Result<bool, BlockchainError> {
+        // Only the debtor's acceptance needs its signer quorum
+        let amount: u64 = negotiation.bilateral_amounts.values().sum();
+        let locals = self.local_identities(&negotiation.participants).await;
+        let is_creditor = negotiation.bilateral_amounts.keys().any(|(creditor, _)| locals.contains(creditor));
+        if amount <= self.auto_accept_threshold || !is_creditor {
+            return Ok(true);
+        }
+
+        let approval_keys = self.approval_keys.read().await;
+        let debtor_key = negotiation.participants.iter()
+            .filter(|network| !locals.contains(network))
+            .find_map(|network| approval_keys.get(network));
+
+        let debtor_key = match debtor_key {
+            Some(key) => key,
+            None => return Ok(true), // Debtor approves without a signer quorum
+        };
 
-        // In production, this would generate real ZK proofs that:
-        // 1. Net positions are calculated correctly from bilateral amounts
-        // 2. No value is created or destroyed in the netting process
-        // 3. Triangular cycles are properly eliminated
-        // 4. All calculations follow the standard netting algorithm
+        let signature = match BLSSignature::from_bytes(responder_signature) {
+            Ok(signature) => signature,
+            Err(_) => return Ok(false),
+        };
+
+        Ok(debtor_key.verify(&signature, &Self::approval_message(proposal_hash))?)
+    }
 
-        // For now, return placeholder proofs
-        // TODO: Integrate with actual ZK proof system
-        let mock_proof = vec![0u8; 192]; // Placeholder for real Groth16 proof
-        Ok(vec![mock_proof])
+    /// Configure ZK components used to prove and verify netting correctness
+    pub fn set_zk_components(
+        &mut self,
+        prover: Option<Arc<AlbatrossZKProver>>,
+        verifier: Option<Arc<AlbatrossZKVerifier>>,
+    ) {
+        self.zk_prover = prover;
+        self.zk_verifier = verifier;
     }
 
     /// Create settlement instructions for net amounts only
@@ -1058,21 +2136,41 @@ impl SettlementMessaging {
     ) -> std::result::Result<(), BlockchainError> {
         info!("💳 Executing settlement: {} → {} for €{:.2}",
               instruction.debtor, instruction.creditor, instruction.amount as f64 / 100.0);
-
-        // In production, this would:
-        // 1. Interface with banking systems/SWIFT
-        // 2. Execute cryptocurrency transfers
-        // 3. Use clearing house protocols (e.g., CLS, TARGET2)
-        // 4. Confirm payment completion and finality
-        // 5. Update blockchain state with settlement record
-
-        // For demo, just log the execution
         info!("   Method: {:?}", instruction.settlement_method);
         info!("   Due date: {}", instruction.due_date);
         info!("   Instruction ID: {:?}", instruction.instruction_id);
 
-        // TODO: Integrate with real payment systems
-        Ok(())
+        self.pending_settlements.write().await.insert(instruction.instruction_id, PendingSettlement {
+            settlement_id: instruction.instruction_id,
+            creditor: instruction.creditor.clone(),
+            debtor: instruction.debtor.clone(),
+            amount: instruction.amount,
+            currency: instruction.currency.clone(),
+            due_date: instruction.due_date,
+            settlement_method: instruction.settlement_method.clone(),
+            status: SettlementStatus::Pending,
+            created_at: chrono::Utc::now().timestamp() as u64,
+        });
+
+        // Only the debtor can pay; other debtors receive the instruction over the network
+        if self.hosts(&instruction.debtor).await {
+            return self.execute_payment(instruction).await;
+        }
+
+        let coordinator_signature = self.sign_statement(&(
+            "settlement-instruction", instruction.instruction_id, &instruction.debtor, instruction.amount, &instruction.currency,
+        )).await?;
+        let message = SettlementMessage::SettlementInstruction {
+            settlement_id: instruction.instruction_id,
+            creditor: instruction.creditor,
+            debtor: instruction.debtor,
+            final_amount: instruction.amount,
+            currency: instruction.currency,
+            due_date: instruction.due_date,
+            settlement_method: instruction.settlement_method,
+            coordinator_signature,
+        };
+        self.send_settlement_message(message, "settlement").await
     }
 
     /// Get active negotiations
@@ -1089,4 +2187,242 @@ impl SettlementMessaging {
     pub async fn get_completed_settlements(&self) -> Vec<CompletedSettlement> {
         self.completed_settlements.read().await.clone()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::bls::BLSPrivateKey;
+
+    #[tokio::test]
+    async fn test_redelivered_proposal_is_handled_once() {
+        let debtor = NetworkId::new("Orange", "FR");
+        let (command_sender, mut commands) = broadcast::channel(16);
+        let messaging = SettlementMessaging::new(debtor.clone(), PeerId::random(), command_sender);
+
+        let proposal = SettlementMessage::InitiateSettlement {
+            creditor_network: NetworkId::new("Vodafone", "UK"),
+            debtor_network: debtor,
+            amount_cents: 5_000,
+            currency: "EUR".to_string(),
+            period_start: 1_700_000_000,
+            period_end: 1_702_592_000,
+            cdr_batch_hash: Blake2bHash::from_data(b"batch"),
+            nonce: 42,
+        };
+        let proposal_id = proposal.content_id();
+        assert_eq!(proposal_id, proposal.clone().content_id());
+
+        let creditor_peer = PeerId::random();
+        let (first, second) = tokio::join!(
+            messaging.handle_settlement_message(proposal.clone(), creditor_peer),
+            messaging.handle_settlement_message(proposal, creditor_peer),
+        );
+        first.unwrap();
+        second.unwrap();
+
+        // One acceptance recorded and sent
+        let entries = messaging.audit_log().entries().await.unwrap();
+        assert_eq!(entries.iter().filter(|entry| entry.subject == proposal_id).count(), 1);
+        assert!(commands.try_recv().is_ok());
+        assert!(commands.try_recv().is_err());
+
+        // A message that failed is handled again when redelivered, and succeeds once it can
+        let initiator = NetworkId::new("Vodafone", "UK");
+        let initiator_key = BLSPrivateKey::generate().unwrap();
+        let dispute_id = open_dispute(&messaging, creditor_peer).await;
+        let evidence_hash = Blake2bHash::from_data(b"evidence");
+        let evidence = SettlementMessage::DisputeEvidence {
+            dispute_id,
+            evidence_hash,
+            submitter: initiator.clone(),
+            submitter_signature: initiator_key.sign(&DisputeManager::evidence_message(&dispute_id, &evidence_hash).unwrap()).unwrap().to_bytes().to_vec(),
+        };
+        // Nobody registered the initiator's key yet
+        assert!(messaging.handle_settlement_message(evidence.clone(), creditor_peer).await.is_err());
+        assert!(!messaging.seen_messages.read().await.contains_key(&evidence.content_id()));
+
+        messaging.dispute_manager().register_party_key(initiator.clone(), initiator_key.public_key()).await;
+        messaging.handle_settlement_message(evidence.clone(), creditor_peer).await.unwrap();
+        assert!(messaging.seen_messages.read().await.contains_key(&evidence.content_id()));
+        let dispute = messaging.dispute_manager().get_dispute(&dispute_id).await.unwrap();
+        assert!(dispute.evidence.iter().any(|record| record.evidence_hash == evidence_hash && record.submitter == initiator));
+
+        // Handled, a redelivery is ignored even concurrently with another
+        let (first, second) = tokio::join!(
+            messaging.handle_settlement_message(evidence.clone(), creditor_peer),
+            messaging.handle_settlement_message(evidence, creditor_peer),
+        );
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(messaging.dispute_manager().get_dispute(&dispute_id).await.unwrap().evidence.len(), dispute.evidence.len());
+    }
+
+    #[tokio::test]
+    async fn test_counter_offers_converge_on_the_difference() {
+        let creditor = NetworkId::new("Vodafone", "UK");
+        let debtor = NetworkId::new("Orange", "FR");
+        let (command_sender, _commands) = broadcast::channel(16);
+        let messaging = SettlementMessaging::new(debtor.clone(), PeerId::random(), command_sender);
+
+        // Above the auto-accept threshold the proposal waits for review
+        let proposal = SettlementMessage::InitiateSettlement {
+            creditor_network: creditor,
+            debtor_network: debtor,
+            amount_cents: 200_000,
+            currency: "EUR".to_string(),
+            period_start: 1_700_000_000,
+            period_end: 1_702_592_000,
+            cdr_batch_hash: Blake2bHash::from_data(b"batch"),
+            nonce: 7,
+        };
+        let proposal_id = proposal.content_id();
+        let creditor_peer = PeerId::random();
+        messaging.handle_settlement_message(proposal, creditor_peer).await.unwrap();
+
+        assert_eq!(messaging.counter_offer(proposal_id, 180_000, Some(Blake2bHash::from_data(b"usage report"))).await.unwrap(), 1);
+        // Our own offer is not countered again before the creditor answers
+        assert!(messaging.counter_offer(proposal_id, 185_000, None).await.is_err());
+
+        let counter = |amount_cents, round| SettlementMessage::SettlementResponse {
+            proposal_hash: proposal_id,
+            response: SettlementResponseType::CounterOffer,
+            counter_amount: Some(amount_cents),
+            reason: None,
+            responder_signature: vec![],
+            round,
+            evidence_hash: None,
+        };
+        // Within 2% of our offer, both settle halfway
+        messaging.handle_settlement_message(counter(183_000, 2), creditor_peer).await.unwrap();
+        let negotiation = messaging.get_active_negotiations().await.into_iter()
+            .find(|negotiation| negotiation.proposal_id == proposal_id).unwrap();
+        assert_eq!(negotiation.rounds.len(), 3);
+        assert_eq!(negotiation.status, NegotiationStatus::Accepted);
+        assert_eq!(negotiation.bilateral_amounts.values().copied().collect::<Vec<_>>(), vec![181_500]);
+    }
+
+    #[tokio::test]
+    async fn test_hosted_identities_answer_under_their_own_policies() {
+        let (uk, de, it) = (NetworkId::new("Vodafone", "UK"), NetworkId::new("Vodafone", "DE"), NetworkId::new("Vodafone", "IT"));
+        let (command_sender, mut commands) = broadcast::channel(16);
+        let mut messaging = SettlementMessaging::new(uk.clone(), PeerId::random(), command_sender);
+        let mut policies = SettlementPolicies::default();
+        policies.default.auto_accept_up_to_cents = Some(500_000);
+        let key = crate::crypto::bls::BLSPrivateKey::generate().unwrap();
+        messaging.host_identity(OperatorIdentity::new(de.clone(), Some(Arc::new(key)), policies));
+        assert!(messaging.hosts(&uk).await && messaging.hosts(&de).await && !messaging.hosts(&it).await);
+
+        let proposal = |debtor: &NetworkId, nonce| SettlementMessage::InitiateSettlement {
+            creditor_network: NetworkId::new("Orange", "FR"),
+            debtor_network: debtor.clone(),
+            amount_cents: 200_000,
+            currency: "EUR".to_string(),
+            period_start: 1_700_000_000,
+            period_end: 1_702_592_000,
+            cdr_batch_hash: Blake2bHash::from_data(b"batch"),
+            nonce,
+        };
+        let creditor_peer = PeerId::random();
+        for message in [proposal(&uk, 1), proposal(&de, 2), proposal(&it, 3)] {
+            messaging.handle_settlement_message(message, creditor_peer).await.unwrap();
+        }
+
+        // The same amount is reviewed by our own identity, accepted by the hosted one and not ours to answer for IT
+        let entries = messaging.audit_log().entries().await.unwrap();
+        let decisions: Vec<_> = entries.iter().map(|entry| (entry.actor.clone(), entry.action)).collect();
+        assert_eq!(decisions, vec![(uk.to_string(), AuditAction::UnderReview), (de.to_string(), AuditAction::Accepted)]);
+        assert!(commands.try_recv().is_ok() && commands.try_recv().is_ok());
+        assert!(commands.try_recv().is_err());
+
+        // A hosted identity proposes as itself
+        let proposal_id = messaging.initiate_settlement_as(
+            de.clone(), NetworkId::new("Orange", "FR"), 10_000, "EUR".to_string(), 0, 1, Blake2bHash::from_data(b"batch"),
+        ).await.unwrap();
+        let negotiation = messaging.get_active_negotiations().await.into_iter()
+            .find(|negotiation| negotiation.proposal_id == proposal_id).unwrap();
+        assert_eq!(negotiation.rounds[0].proposer, de);
+        assert!(messaging.initiate_settlement_as(it, uk, 10_000, "EUR".to_string(), 0, 1, Blake2bHash::zero()).await.is_err());
+    }
+
+    /// Dispute opened by Vodafone UK with its evidence attached
+    async fn open_dispute(messaging: &SettlementMessaging, peer: PeerId) -> Blake2bHash {
+        let settlement_id = Blake2bHash::from_data(b"disputed settlement");
+        let initiator = NetworkId::new("Vodafone", "UK");
+        messaging.handle_settlement_message(SettlementMessage::DisputeInitiation {
+            settlement_id,
+            dispute_reason: DisputeReason::AmountDiscrepancy,
+            disputed_amount: Some(80_000),
+            evidence_hash: Blake2bHash::from_data(b"usage report"),
+            initiator: initiator.clone(),
+        }, peer).await.unwrap();
+        DisputeManager::dispute_id(&settlement_id, &initiator)
+    }
+
+    #[tokio::test]
+    async fn test_dispute_vote_is_verified_before_arbitration_begins() {
+        let (command_sender, _commands) = broadcast::channel(16);
+        let messaging = SettlementMessaging::new(NetworkId::new("Orange", "FR"), PeerId::random(), command_sender);
+        let arbitrator = NetworkId::new("KPN", "NL");
+        let key = BLSPrivateKey::generate().unwrap();
+        messaging.dispute_manager().register_arbitrator(arbitrator.clone(), key.public_key()).await;
+        let peer = PeerId::random();
+        let dispute_id = open_dispute(&messaging, peer).await;
+
+        let verdict = DisputeVerdict::Reject;
+        let vote = |validator: &NetworkId, signer: &BLSPrivateKey| SettlementMessage::DisputeVote {
+            dispute_id,
+            validator: validator.clone(),
+            verdict: verdict.clone(),
+            validator_signature: signer.sign(&DisputeManager::vote_message(&dispute_id, &verdict).unwrap()).unwrap().to_bytes().to_vec(),
+        };
+        let state = || async { messaging.dispute_manager().get_dispute(&dispute_id).await.unwrap().state };
+
+        // Forged and unregistered votes leave the evidence phase open
+        let forger = BLSPrivateKey::generate().unwrap();
+        assert!(messaging.handle_settlement_message(vote(&arbitrator, &forger), peer).await.is_err());
+        assert!(messaging.handle_settlement_message(vote(&NetworkId::new("Telia", "SE"), &forger), peer).await.is_err());
+        assert_eq!(state().await, DisputeState::EvidenceSubmitted);
+
+        // The arbitrator's own vote closes it and, as the only arbitrator, decides the dispute
+        messaging.handle_settlement_message(vote(&arbitrator, &key), peer).await.unwrap();
+        assert_eq!(state().await, DisputeState::Resolved(DisputeOutcome::Rejected));
+    }
+
+    #[tokio::test]
+    async fn test_dispute_evidence_is_signed_by_a_party() {
+        let (command_sender, _commands) = broadcast::channel(16);
+        let messaging = SettlementMessaging::new(NetworkId::new("Orange", "FR"), PeerId::random(), command_sender);
+        let (initiator, outsider) = (NetworkId::new("Vodafone", "UK"), NetworkId::new("Telia", "SE"));
+        let (initiator_key, outsider_key) = (BLSPrivateKey::generate().unwrap(), BLSPrivateKey::generate().unwrap());
+        messaging.dispute_manager().register_party_key(initiator.clone(), initiator_key.public_key()).await;
+        messaging.dispute_manager().register_party_key(outsider.clone(), outsider_key.public_key()).await;
+        let peer = PeerId::random();
+        let dispute_id = open_dispute(&messaging, peer).await;
+
+        let evidence_hash = Blake2bHash::from_data(b"roaming logs");
+        let evidence = |submitter: &NetworkId, signer: &BLSPrivateKey| SettlementMessage::DisputeEvidence {
+            dispute_id,
+            evidence_hash,
+            submitter: submitter.clone(),
+            submitter_signature: signer.sign(&DisputeManager::evidence_message(&dispute_id, &evidence_hash).unwrap()).unwrap().to_bytes().to_vec(),
+        };
+        let unsigned = SettlementMessage::DisputeEvidence {
+            dispute_id,
+            evidence_hash,
+            submitter: initiator.clone(),
+            submitter_signature: vec![],
+        };
+
+        // Unsigned, signed by someone else and sent by an operator outside the dispute
+        assert!(messaging.handle_settlement_message(unsigned, peer).await.is_err());
+        assert!(messaging.handle_settlement_message(evidence(&initiator, &outsider_key), peer).await.is_err());
+        assert!(messaging.handle_settlement_message(evidence(&outsider, &outsider_key), peer).await.is_err());
+        assert_eq!(messaging.dispute_manager().get_dispute(&dispute_id).await.unwrap().evidence.len(), 1);
+
+        messaging.handle_settlement_message(evidence(&initiator, &initiator_key), peer).await.unwrap();
+        let dispute = messaging.dispute_manager().get_dispute(&dispute_id).await.unwrap();
+        assert_eq!(dispute.evidence.len(), 2);
+        assert_eq!(dispute.evidence[1].submitter, initiator);
+    }
+}
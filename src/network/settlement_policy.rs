@@ -0,0 +1,185 @@
+// Per-counterparty settlement policy: how much each operator's proposals are accepted up to
+// without review and what a netting must carry before it is agreed to. Policies are loaded from
+// a JSON file keyed by `name:country`; operators missing from the on-chain registry get the
+// manual review policy. Counterparties with a poor on-chain reputation get a tightened policy
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::primitives::{BlockchainError, NetworkId, Result};
+
+/// Reputation score from which a counterparty's policy applies unchanged
+pub const FULL_TRUST_REPUTATION: u32 = 80;
+
+/// Reputation score below which no proposal of a counterparty is accepted without review
+pub const MIN_AUTO_ACCEPT_REPUTATION: u32 = 40;
+
+/// Acceptance rules for the proposals and nettings of one counterparty
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CounterpartyPolicy {
+    /// Proposals up to this amount are accepted without review, `None` reviewing every proposal
+    pub auto_accept_up_to_cents: Option<u64>,
+    /// Nettings with a gross total above this need a verified ZK proof and a signed agreement
+    pub require_proof_above_cents: u64,
+    /// Smallest netting saving agreed to without review, in percent
+    pub min_netting_savings_pct: u32,
+    /// Largest net position agreed to without review
+    pub max_net_position_cents: u64,
+}
+
+impl Default for CounterpartyPolicy {
+    fn default() -> Self {
+        Self {
+            auto_accept_up_to_cents: Some(100_000), // €1000
+            require_proof_above_cents: 1_000_000, // €10k
+            min_netting_savings_pct: 30,
+            max_net_position_cents: 1_000_000, // €10k
+        }
+    }
+}
+
+impl CounterpartyPolicy {
+    /// Nothing accepted or agreed to without review
+    pub fn manual_review() -> Self {
+        Self {
+            auto_accept_up_to_cents: None,
+            require_proof_above_cents: 0,
+            min_netting_savings_pct: 100,
+            max_net_position_cents: 0,
+        }
+    }
+
+    /// Policy applied to a counterparty of reputation `score` out of 100: below full trust the
+    /// auto-accept limit and the proof threshold shrink in proportion to the score
+    pub fn for_reputation(&self, score: u32) -> Self {
+        if score >= FULL_TRUST_REPUTATION {
+            return self.clone();
+        }
+        Self {
+            auto_accept_up_to_cents: reputation_limit(self.auto_accept_up_to_cents, score),
+            require_proof_above_cents: self.require_proof_above_cents.saturating_mul(score as u64) / FULL_TRUST_REPUTATION as u64,
+            ..self.clone()
+        }
+    }
+
+    pub fn auto_accepts(&self, amount_cents: u64) -> bool {
+        self.auto_accept_up_to_cents.is_some_and(|limit| amount_cents <= limit)
+    }
+
+    pub fn requires_proof(&self, gross_total_cents: u64) -> bool {
+        gross_total_cents > self.require_proof_above_cents
+    }
+
+    /// Whether a netting is agreed to without review, `proven` if its proof was verified and we sign our agreement
+    pub fn agrees_to_netting(&self, gross_total_cents: u64, savings_pct: u32, our_net_cents: i64, proven: bool) -> bool {
+        (proven || !self.requires_proof(gross_total_cents))
+            && savings_pct >= self.min_netting_savings_pct
+            && our_net_cents.unsigned_abs() <= self.max_net_position_cents
+    }
+}
+
+/// Auto-accept `limit` lowered for a counterparty of reputation `score` out of 100
+pub fn reputation_limit(limit: Option<u64>, score: u32) -> Option<u64> {
+    match limit {
+        _ if score < MIN_AUTO_ACCEPT_REPUTATION => None,
+        Some(limit) if score < FULL_TRUST_REPUTATION => Some(limit.saturating_mul(score as u64) / FULL_TRUST_REPUTATION as u64),
+        limit => limit,
+    }
+}
+
+/// Settlement policies per counterparty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementPolicies {
+    /// Policy of registered operators without one of their own
+    #[serde(default)]
+    pub default: CounterpartyPolicy,
+    /// Policy of operators missing from the registry
+    #[serde(default = "CounterpartyPolicy::manual_review")]
+    pub unknown: CounterpartyPolicy,
+    /// Policies of single operators, keyed by `name:country`
+    #[serde(default)]
+    pub operators: HashMap<String, CounterpartyPolicy>,
+    /// Registered operators, every operator counting as registered until the registry is known
+    #[serde(skip)]
+    registered: Option<HashSet<NetworkId>>,
+}
+
+impl Default for SettlementPolicies {
+    fn default() -> Self {
+        Self {
+            default: CounterpartyPolicy::default(),
+            unknown: CounterpartyPolicy::manual_review(),
+            operators: HashMap::new(),
+            registered: None,
+        }
+    }
+}
+
+impl SettlementPolicies {
+    /// Load policies from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read settlement policy {}: {}", path.display(), e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid settlement policy {}: {}", path.display(), e)))
+    }
+
+    /// Operators of the on-chain registry, others getting the unknown operator policy
+    pub fn set_registered_operators(&mut self, operators: impl IntoIterator<Item = NetworkId>) {
+        self.registered = Some(operators.into_iter().collect());
+    }
+
+    /// Policy applied to proposals and nettings of `counterparty`
+    pub fn policy_for(&self, counterparty: &NetworkId) -> &CounterpartyPolicy {
+        if let Some(policy) = self.operators.get(&counterparty.to_string()) {
+            return policy;
+        }
+        match &self.registered {
+            Some(registered) if !registered.contains(counterparty) => &self.unknown,
+            _ => &self.default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies_per_counterparty() {
+        let json = r#"{
+            "default": { "auto_accept_up_to_cents": 50000 },
+            "operators": {
+                "Orange:FR": { "auto_accept_up_to_cents": 500000, "require_proof_above_cents": 1000000 }
+            }
+        }"#;
+        let mut policies: SettlementPolicies = serde_json::from_str(json).unwrap();
+        let orange = NetworkId::new("Orange", "FR");
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let stranger = NetworkId::new("Unknown", "XX");
+
+        assert!(policies.policy_for(&orange).auto_accepts(400_000));
+        assert!(!policies.policy_for(&vodafone).auto_accepts(60_000));
+        // Fields left out keep their defaults
+        assert_eq!(policies.policy_for(&vodafone).max_net_position_cents, 1_000_000);
+
+        // Nettings above the proof threshold are only agreed to when proven
+        assert!(policies.policy_for(&orange).agrees_to_netting(900_000, 40, 10_000, false));
+        assert!(!policies.policy_for(&orange).agrees_to_netting(2_000_000, 40, 10_000, false));
+        assert!(policies.policy_for(&orange).agrees_to_netting(2_000_000, 40, 10_000, true));
+
+        // Once the registry is known, unregistered operators go to manual review
+        assert!(policies.policy_for(&stranger).auto_accepts(100));
+        policies.set_registered_operators([orange.clone(), vodafone.clone()]);
+        assert!(!policies.policy_for(&stranger).auto_accepts(100));
+        assert!(policies.policy_for(&vodafone).auto_accepts(100));
+
+        // A poor reputation halves the limit at half of full trust, and below the minimum ends auto-accepting
+        let orange_policy = policies.policy_for(&orange);
+        assert_eq!(orange_policy.for_reputation(100), *orange_policy);
+        assert_eq!(orange_policy.for_reputation(FULL_TRUST_REPUTATION / 2).auto_accept_up_to_cents, Some(250_000));
+        assert!(orange_policy.for_reputation(FULL_TRUST_REPUTATION / 2).requires_proof(600_000));
+        assert!(!orange_policy.for_reputation(MIN_AUTO_ACCEPT_REPUTATION - 1).auto_accepts(100));
+    }
+}
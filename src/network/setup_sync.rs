@@ -0,0 +1,273 @@
+// Trusted setup key distribution: the bootstrap node announces its ceremony on the zkp topic,
+// nodes without keys fetch the transcript and keys from it over request-response and check
+// them against the announced transcript hash before installing them
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use crate::zkp::trusted_setup::{CeremonyPart, CeremonyTranscript, TrustedSetupCeremony, CIRCUIT_IDS};
+use super::{NetworkCommand, NetworkEvent, SPNetworkMessage};
+
+/// Request-response protocol trusted setup files are served over
+pub const TRUSTED_SETUP_PROTOCOL: StreamProtocol = StreamProtocol::new("/sp-cdr-blockchain/trusted-setup/1.0.0");
+
+/// How often a node waiting for keys asks on the zkp topic who holds them
+pub(super) const QUERY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// File of a ceremony asked for over the trusted setup protocol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TrustedSetupRequest {
+    Transcript,
+    Key { circuit_id: String, proving: bool },
+    /// State of a multi-party ceremony in progress, from the participant who contributed last
+    CeremonyState(CeremonyPart),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TrustedSetupResponse {
+    Transcript(Vec<u8>),
+    Key { circuit_id: String, proving: bool, data: Vec<u8> },
+    CeremonyState { part: CeremonyPart, data: Vec<u8> },
+    Unavailable(String),
+}
+
+/// How a node without trusted setup keys obtains them
+#[derive(Debug, Clone)]
+pub struct KeyFetchConfig {
+    /// How long to wait for the consortium's keys
+    pub timeout: Duration,
+    /// Operator opt-in to generating local keys once the wait times out, whose proofs no other node accepts
+    pub allow_local_keys: bool,
+}
+
+impl Default for KeyFetchConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(300),
+            allow_local_keys: false,
+        }
+    }
+}
+
+/// Answer a trusted setup request from the keys in `keys_dir`
+pub async fn serve(keys_dir: &Path, request: TrustedSetupRequest) -> TrustedSetupResponse {
+    let ceremony = TrustedSetupCeremony::sp_consortium_ceremony(keys_dir.to_path_buf());
+    let response = match request {
+        TrustedSetupRequest::Transcript => ceremony.transcript_file().await.map(TrustedSetupResponse::Transcript),
+        TrustedSetupRequest::Key { circuit_id, proving } => ceremony.key_file(&circuit_id, proving).await
+            .map(|data| TrustedSetupResponse::Key { circuit_id, proving, data }),
+        TrustedSetupRequest::CeremonyState(part) => ceremony.ceremony_file(&part).await
+            .map(|data| TrustedSetupResponse::CeremonyState { part, data }),
+    };
+    response.unwrap_or_else(|e| TrustedSetupResponse::Unavailable(e.to_string()))
+}
+
+/// Fetch state of a node waiting for the consortium's trusted setup keys
+#[derive(Debug)]
+pub struct TrustedSetupSync {
+    include_proving_keys: bool,
+    /// Peer the followed announcement came from and the transcript hash it announced
+    source: Option<(PeerId, Blake2bHash)>,
+    transcript: Option<(Vec<u8>, CeremonyTranscript)>,
+    keys: HashMap<(String, bool), Vec<u8>>,
+}
+
+impl TrustedSetupSync {
+    /// Fetch verifying keys, and proving keys too if this node generates proofs
+    pub fn new(include_proving_keys: bool) -> Self {
+        Self { include_proving_keys, source: None, transcript: None, keys: HashMap::new() }
+    }
+
+    /// Follow the first announcement, returns the request for its transcript
+    pub fn handle_announcement(&mut self, peer: PeerId, ceremony_id: &str, transcript_hash: Blake2bHash) -> Option<(PeerId, TrustedSetupRequest)> {
+        if self.source.is_some() {
+            return None;
+        }
+        info!("📣 Trusted setup ceremony {} announced by {}, fetching its keys", ceremony_id, peer);
+        self.source = Some((peer, transcript_hash));
+        Some((peer, TrustedSetupRequest::Transcript))
+    }
+
+    /// Check a response of the announcing peer, returns the requests it leads to
+    /// A response failing its check drops the announcement, to follow the next one
+    pub fn handle_response(&mut self, peer: PeerId, response: TrustedSetupResponse) -> Result<Vec<(PeerId, TrustedSetupRequest)>> {
+        let Some((source, transcript_hash)) = self.source else {
+            return Ok(vec![]);
+        };
+        if peer != source {
+            return Ok(vec![]);
+        }
+        let requests = self.apply(source, transcript_hash, response);
+        if requests.is_err() {
+            self.source = None;
+            self.transcript = None;
+            self.keys.clear();
+        }
+        requests
+    }
+
+    fn apply(&mut self, source: PeerId, transcript_hash: Blake2bHash, response: TrustedSetupResponse) -> Result<Vec<(PeerId, TrustedSetupRequest)>> {
+        match response {
+            TrustedSetupResponse::Transcript(data) => {
+                if Blake2bHash::from_data(&data) != transcript_hash {
+                    return Err(BlockchainError::Crypto(format!(
+                        "Trusted setup transcript does not hash to the announced {}", transcript_hash
+                    )));
+                }
+                let transcript = serde_json::from_slice(&data)
+                    .map_err(|e| BlockchainError::Serialization(format!("Transcript deserialization error: {}", e)))?;
+                self.transcript = Some((data, transcript));
+                Ok(self.wanted()
+                    .map(|(circuit_id, proving)| (source, TrustedSetupRequest::Key { circuit_id, proving }))
+                    .collect())
+            }
+            TrustedSetupResponse::Key { circuit_id, proving, data } => {
+                let (_, transcript) = self.transcript.as_ref()
+                    .ok_or_else(|| BlockchainError::InvalidState("Trusted setup key received before the transcript".to_string()))?;
+                transcript.verify_key(&circuit_id, proving, &data)?;
+                self.keys.insert((circuit_id, proving), data);
+                Ok(vec![])
+            }
+            TrustedSetupResponse::CeremonyState { .. } => Ok(vec![]),
+            TrustedSetupResponse::Unavailable(reason) => {
+                Err(BlockchainError::NotFound(format!("Trusted setup not served: {}", reason)))
+            }
+        }
+    }
+
+    /// Keys to fetch, by circuit and whether it is the proving key
+    fn wanted(&self) -> impl Iterator<Item = (String, bool)> + '_ {
+        CIRCUIT_IDS.into_iter()
+            .flat_map(|circuit_id| [(circuit_id.to_string(), false), (circuit_id.to_string(), true)])
+            .filter(|(_, proving)| !proving || self.include_proving_keys)
+    }
+
+    /// Whether the transcript and every wanted key arrived and checked out
+    pub fn is_complete(&self) -> bool {
+        self.transcript.is_some() && self.wanted().all(|key| self.keys.contains_key(&key))
+    }
+
+    /// Write the fetched transcript and keys to the ceremony's keys directory
+    pub async fn install(self, ceremony: &TrustedSetupCeremony) -> Result<()> {
+        let (transcript, _) = self.transcript
+            .ok_or_else(|| BlockchainError::InvalidState("No trusted setup transcript fetched".to_string()))?;
+        let keys: Vec<(String, bool, Vec<u8>)> = self.keys.into_iter()
+            .map(|((circuit_id, proving), data)| (circuit_id, proving, data))
+            .collect();
+        ceremony.install_distributed_keys(&transcript, &keys).await
+    }
+
+    /// Wait for an announced ceremony and install its keys into `ceremony`, `false` if none arrived in time
+    pub async fn fetch(
+        mut self,
+        ceremony: &TrustedSetupCeremony,
+        commands: &mpsc::Sender<NetworkCommand>,
+        events: &mut broadcast::Receiver<NetworkEvent>,
+        timeout: Duration,
+    ) -> Result<bool> {
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+        let mut query = tokio::time::interval(QUERY_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = &mut deadline => return Ok(false),
+
+                // Ask again while no announcement is followed, peers may have missed the last one
+                _ = query.tick(), if self.source.is_none() => {
+                    let _ = commands.send(NetworkCommand::Broadcast {
+                        topic: "zkp".to_string(),
+                        message: SPNetworkMessage::TrustedSetupQuery,
+                    }).await;
+                }
+
+                event = events.recv() => {
+                    let requests = match event {
+                        Ok(NetworkEvent::GossipReceived {
+                            message: SPNetworkMessage::TrustedSetupAvailable { ceremony_id, transcript_hash }, source, ..
+                        }) => self.handle_announcement(source, &ceremony_id, transcript_hash).into_iter().collect(),
+                        Ok(NetworkEvent::TrustedSetupResponse { peer, response }) => {
+                            self.handle_response(peer, response).unwrap_or_else(|e| {
+                                warn!("⚠️  Discarding trusted setup from {}: {}", peer, e);
+                                vec![]
+                            })
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            return Err(BlockchainError::NetworkError("Network stopped while fetching trusted setup keys".to_string()));
+                        }
+                        _ => vec![],
+                    };
+                    for (peer, request) in requests {
+                        let _ = commands.send(NetworkCommand::RequestTrustedSetup { peer, request }).await;
+                    }
+
+                    if self.is_complete() {
+                        self.install(ceremony).await?;
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::test_rng;
+
+    #[tokio::test]
+    async fn test_fetch_keys_from_announcing_peer() {
+        let bootstrap_dir = tempfile::tempdir().unwrap();
+        let mut bootstrap = TrustedSetupCeremony::sp_consortium_ceremony(bootstrap_dir.path().to_path_buf());
+        let transcript = bootstrap.run_ceremony(&mut test_rng()).await.unwrap();
+        let transcript_hash = Blake2bHash::from_data(&bootstrap.transcript_file().await.unwrap());
+        let holder = PeerId::random();
+
+        // A peer announcing another transcript is followed until its transcript fails the check
+        let mut sync = TrustedSetupSync::new(true);
+        let impostor = PeerId::random();
+        let (peer, request) = sync.handle_announcement(impostor, "forged", Blake2bHash::from_data(b"forged")).unwrap();
+        let response = serve(bootstrap_dir.path(), request).await;
+        assert!(sync.handle_response(peer, response).is_err());
+
+        // Transcript first, then every key it lists
+        let (peer, request) = sync.handle_announcement(holder, &transcript.ceremony_id, transcript_hash).unwrap();
+        assert!(sync.handle_announcement(impostor, "later", transcript_hash).is_none());
+        let requests = sync.handle_response(peer, serve(bootstrap_dir.path(), request).await).unwrap();
+        assert_eq!(requests.len(), 2 * CIRCUIT_IDS.len());
+
+        // A key swapped for another circuit's does not match the transcript
+        let swapped = match serve(bootstrap_dir.path(), requests[0].1.clone()).await {
+            TrustedSetupResponse::Key { circuit_id, proving, data } => {
+                let other = CIRCUIT_IDS.into_iter().find(|other| *other != circuit_id).unwrap();
+                TrustedSetupResponse::Key { circuit_id: other.to_string(), proving, data }
+            }
+            response => panic!("Unexpected response {:?}", response),
+        };
+        let mut forged = TrustedSetupSync::new(true);
+        forged.handle_announcement(holder, &transcript.ceremony_id, transcript_hash);
+        forged.handle_response(holder, serve(bootstrap_dir.path(), TrustedSetupRequest::Transcript).await).unwrap();
+        assert!(forged.handle_response(holder, swapped).is_err());
+
+        for (peer, request) in requests {
+            assert!(!sync.is_complete());
+            sync.handle_response(peer, serve(bootstrap_dir.path(), request).await).unwrap();
+        }
+        assert!(sync.is_complete());
+
+        let node_dir = tempfile::tempdir().unwrap();
+        let node = TrustedSetupCeremony::sp_consortium_ceremony(node_dir.path().to_path_buf());
+        sync.install(&node).await.unwrap();
+        assert!(node.verify_ceremony().await.unwrap());
+
+        // Paths outside the keys directory are not served
+        let request = TrustedSetupRequest::Key { circuit_id: "../node".to_string(), proving: true };
+        assert!(matches!(serve(bootstrap_dir.path(), request).await, TrustedSetupResponse::Unavailable(_)));
+    }
+}
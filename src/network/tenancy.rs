@@ -0,0 +1,121 @@
+// Multi-tenant nodes: a carrier group runs one node for several of its operating companies. Besides
+// the node's own operator identity it hosts further ones, each signing its settlement decisions
+// with its own key and settling under its own counterparty policies. Messages are handled for the
+// hosted identity they name and ignored when they name none
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::bls::load_or_generate_bls_key;
+use crate::crypto::keys::Signer;
+use crate::network::settlement_policy::SettlementPolicies;
+use crate::primitives::{BlockchainError, NetworkId, Result};
+
+/// Operator identity hosted besides the node's own
+#[derive(Clone)]
+pub struct OperatorIdentity {
+    pub network_id: NetworkId,
+    /// Key its settlement decisions are signed with, left unsigned without one
+    pub signer: Option<Arc<dyn Signer>>,
+    pub policies: SettlementPolicies,
+}
+
+impl OperatorIdentity {
+    pub fn new(network_id: NetworkId, signer: Option<Arc<dyn Signer>>, policies: SettlementPolicies) -> Self {
+        Self { network_id, signer, policies }
+    }
+}
+
+/// Hosted identities of a node, loaded from JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenancyConfig {
+    pub identities: Vec<HostedIdentityConfig>,
+}
+
+/// One hosted identity as configured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedIdentityConfig {
+    pub network: NetworkId,
+    /// BLS key file, generated on first start
+    pub signing_key: PathBuf,
+    /// Settlement policy file, the default policies if `None`
+    #[serde(default)]
+    pub settlement_policy: Option<PathBuf>,
+}
+
+impl TenancyConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read tenancy config {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid tenancy config {}: {}", path.display(), e)))?;
+        let mut networks = HashSet::new();
+        if let Some(duplicate) = config.identities.iter().find(|identity| !networks.insert(&identity.network)) {
+            return Err(BlockchainError::InvalidOperation(format!("{} is hosted twice", duplicate.network)));
+        }
+        Ok(config)
+    }
+
+    /// Keys and policies of the hosted identities, none of which may be the node's own `primary`
+    pub fn open(&self, primary: &NetworkId) -> Result<HashMap<NetworkId, OperatorIdentity>> {
+        let mut identities = HashMap::new();
+        for identity in &self.identities {
+            if identity.network == *primary {
+                return Err(BlockchainError::InvalidOperation(format!("{} is this node's own identity", primary)));
+            }
+            let signer: Arc<dyn Signer> = Arc::new(load_or_generate_bls_key(&identity.signing_key)?);
+            let policies = match &identity.settlement_policy {
+                Some(path) => SettlementPolicies::load(path)?,
+                None => SettlementPolicies::default(),
+            };
+            identities.insert(identity.network.clone(), OperatorIdentity::new(identity.network.clone(), Some(signer), policies));
+        }
+        Ok(identities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosted_identities_keep_their_own_keys_and_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("de.policy.json"), r#"{ "default": { "auto_accept_up_to_cents": 500000 } }"#).unwrap();
+        let config = serde_json::json!({
+            "identities": [
+                {
+                    "network": { "Operator": { "name": "Vodafone", "country": "DE" } },
+                    "signing_key": dir.path().join("de.bls"),
+                    "settlement_policy": dir.path().join("de.policy.json"),
+                },
+                {
+                    "network": { "Operator": { "name": "Vodafone", "country": "IT" } },
+                    "signing_key": dir.path().join("it.bls"),
+                },
+            ]
+        });
+        let path = dir.path().join("tenancy.json");
+        std::fs::write(&path, config.to_string()).unwrap();
+
+        let uk = NetworkId::new("Vodafone", "UK");
+        let (de, it) = (NetworkId::new("Vodafone", "DE"), NetworkId::new("Vodafone", "IT"));
+        let identities = TenancyConfig::load(&path).unwrap().open(&uk).unwrap();
+        assert_eq!(identities.len(), 2);
+        let key = |network: &NetworkId| identities[network].signer.as_ref().unwrap().public_key();
+        assert_ne!(key(&de), key(&it));
+        let orange = NetworkId::new("Orange", "FR");
+        assert!(identities[&de].policies.policy_for(&orange).auto_accepts(400_000));
+        assert!(!identities[&it].policies.policy_for(&orange).auto_accepts(400_000));
+
+        // Keys are kept across restarts, and an identity is hosted once and never as the node's own
+        let reopened = TenancyConfig::load(&path).unwrap().open(&uk).unwrap();
+        assert_eq!(reopened[&de].signer.as_ref().unwrap().public_key(), key(&de));
+        assert!(TenancyConfig::load(&path).unwrap().open(&de).is_err());
+        let mut duplicated = config.clone();
+        duplicated["identities"][1]["network"] = duplicated["identities"][0]["network"].clone();
+        std::fs::write(&path, duplicated.to_string()).unwrap();
+        assert!(TenancyConfig::load(&path).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+// Topic-scoped publish authorization: consensus messages are only delivered when their gossip
+// signature is from the active validator set, settlement messages when it is from a node that
+// proved to be a registered operator, on behalf of itself or an identity its certificate lets it
+// host. Rules are enforced once the sets they check are known
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet};
+
+use crate::primitives::NetworkId;
+use super::SPNetworkMessage;
+
+/// Gossip topic of consensus messages
+pub const CONSENSUS_TOPIC: &str = "sp-consensus";
+/// Gossip topic of settlement negotiation
+pub const SETTLEMENT_TOPIC: &str = "sp-settlement";
+
+/// Who may publish on the consensus and settlement topics, `None` while a rule is not enforced
+#[derive(Debug, Clone, Default)]
+pub struct PublishAuthorization {
+    validators: Option<HashSet<PeerId>>,
+    operators: Option<HashSet<NetworkId>>,
+    /// Identities each verified operator hosts besides its own
+    hosted: HashMap<NetworkId, HashSet<NetworkId>>,
+}
+
+impl PublishAuthorization {
+    /// Only accept consensus messages published by `validators`
+    pub fn set_validators(&mut self, validators: impl IntoIterator<Item = PeerId>) {
+        self.validators = Some(validators.into_iter().collect());
+    }
+
+    /// Only accept settlement messages published by nodes verified as one of `operators`
+    pub fn set_registered_operators(&mut self, operators: impl IntoIterator<Item = NetworkId>) {
+        self.operators = Some(operators.into_iter().collect());
+    }
+
+    /// Let `operator` publish settlement messages on behalf of the identities it hosts
+    pub fn set_hosted_identities(&mut self, operator: NetworkId, identities: impl IntoIterator<Item = NetworkId>) {
+        self.hosted.insert(operator, identities.into_iter().collect());
+    }
+
+    pub fn is_validator(&self, peer_id: &PeerId) -> bool {
+        self.validators.as_ref().map_or(true, |validators| validators.contains(peer_id))
+    }
+
+    /// Check the author of a message on `topic`, `operator` being the operator it verified as
+    pub fn authorize(
+        &self,
+        topic: &str,
+        author: Option<PeerId>,
+        operator: Option<&NetworkId>,
+        message: &SPNetworkMessage,
+    ) -> Result<(), String> {
+        match topic {
+            CONSENSUS_TOPIC => {
+                let author = author.ok_or("Unsigned consensus message")?;
+                // Nodes announce themselves and leave whether or not they are validators, but only for themselves
+                let claimed = match message {
+                    SPNetworkMessage::ValidatorAnnouncement { validator_id, .. } => return Self::same_peer(author, *validator_id),
+                    SPNetworkMessage::NodeLeaving { peer_id, .. } => return Self::same_peer(author, *peer_id),
+                    SPNetworkMessage::BlockProposal { proposer, .. } => Some(*proposer),
+                    SPNetworkMessage::BlockVote { voter, .. } | SPNetworkMessage::MacroBlockVote { voter, .. } => Some(*voter),
+                    _ => None,
+                };
+                if let Some(claimed) = claimed {
+                    Self::same_peer(author, claimed)?;
+                }
+                self.authorize_author(topic, Some(author), operator)
+            }
+            SETTLEMENT_TOPIC => {
+                self.authorize_author(topic, author, operator)?;
+                // Operators negotiate for themselves or the identities they host
+                match (operator, message.settlement_sender()) {
+                    (Some(operator), Some(claimed)) if self.operators.is_some() => self.same_operator(operator, claimed),
+                    _ => Ok(()),
+                }
+            }
+            _ => self.authorize_author(topic, author, operator),
+        }
+    }
+
+    /// Check the author of a chunk on `topic`, before the message it belongs to is known
+    pub fn authorize_author(&self, topic: &str, author: Option<PeerId>, operator: Option<&NetworkId>) -> Result<(), String> {
+        match topic {
+            CONSENSUS_TOPIC => {
+                let author = author.ok_or("Unsigned consensus message")?;
+                if !self.is_validator(&author) {
+                    return Err(format!("{} is not in the active validator set", author));
+                }
+                Ok(())
+            }
+            SETTLEMENT_TOPIC => match (&self.operators, operator) {
+                (None, _) => Ok(()),
+                (Some(operators), Some(operator)) if operators.contains(operator) => Ok(()),
+                (Some(_), Some(operator)) => Err(format!("{} is not a registered operator", operator)),
+                (Some(_), None) => Err(match author {
+                    Some(author) => format!("{} is not verified as an operator", author),
+                    None => "Unsigned settlement message".to_string(),
+                }),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn same_operator(&self, operator: &NetworkId, claimed: &NetworkId) -> Result<(), String> {
+        let hosts = self.hosted.get(operator).is_some_and(|hosted| hosted.contains(claimed));
+        if operator != claimed && !hosts {
+            return Err(format!("{} published a settlement message on behalf of {}", operator, claimed));
+        }
+        Ok(())
+    }
+
+    fn same_peer(author: PeerId, claimed: PeerId) -> Result<(), String> {
+        if author != claimed {
+            return Err(format!("{} published a message on behalf of {}", author, claimed));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consensus_and_settlement_publishers() {
+        let validator = PeerId::random();
+        let outsider = PeerId::random();
+        let vote = |voter| SPNetworkMessage::BlockVote {
+            block_hash: crate::primitives::Blake2bHash::default(),
+            voter,
+            approve: true,
+            signature: vec![],
+        };
+
+        // Until the validator set is known any peer votes for itself
+        let mut authorization = PublishAuthorization::default();
+        assert!(authorization.authorize(CONSENSUS_TOPIC, Some(outsider), None, &vote(outsider)).is_ok());
+        assert!(authorization.authorize(CONSENSUS_TOPIC, Some(outsider), None, &vote(validator)).is_err());
+
+        authorization.set_validators([validator]);
+        assert!(authorization.authorize(CONSENSUS_TOPIC, Some(validator), None, &vote(validator)).is_ok());
+        assert!(authorization.authorize(CONSENSUS_TOPIC, Some(outsider), None, &vote(outsider)).is_err());
+        assert!(authorization.authorize_author(CONSENSUS_TOPIC, Some(outsider), None).is_err());
+        assert!(authorization.authorize(CONSENSUS_TOPIC, None, None, &vote(validator)).is_err());
+        // Leaving is announced by anyone, for itself
+        let leaving = SPNetworkMessage::node_leaving(outsider, NetworkId::SPConsortium);
+        assert!(authorization.authorize(CONSENSUS_TOPIC, Some(outsider), None, &leaving).is_ok());
+        assert!(authorization.authorize(CONSENSUS_TOPIC, Some(validator), None, &leaving).is_err());
+
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let orange = NetworkId::new("Orange", "FR");
+        let proposal = SPNetworkMessage::settlement_proposal(vodafone.clone(), orange.clone(), 100, Default::default(), 1);
+        assert!(authorization.authorize(SETTLEMENT_TOPIC, Some(outsider), None, &proposal).is_ok());
+        authorization.set_registered_operators([vodafone.clone(), orange.clone()]);
+        assert!(authorization.authorize(SETTLEMENT_TOPIC, Some(outsider), Some(&vodafone), &proposal).is_ok());
+        assert!(authorization.authorize(SETTLEMENT_TOPIC, Some(outsider), None, &proposal).is_err());
+        assert!(authorization.authorize_author(SETTLEMENT_TOPIC, Some(outsider), Some(&NetworkId::new("Telefonica", "ES"))).is_err());
+        // A registered operator only speaks for itself and the identities it hosts
+        assert!(authorization.authorize(SETTLEMENT_TOPIC, Some(outsider), Some(&orange), &proposal).is_err());
+        authorization.set_hosted_identities(orange.clone(), [vodafone.clone()]);
+        assert!(authorization.authorize(SETTLEMENT_TOPIC, Some(outsider), Some(&orange), &proposal).is_ok());
+
+        // Other topics are open
+        assert!(authorization.authorize_author("sp-cdr", Some(outsider), None).is_ok());
+    }
+}
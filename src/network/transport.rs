@@ -0,0 +1,113 @@
+// Transports between operator nodes: TCP with noise and yamux, relayed circuits over it, and QUIC,
+// which sets connections up in fewer round trips and passes NATs more easily. Nodes preferring
+// QUIC listen on both and dial QUIC addresses first, falling back to TCP when they fail
+use libp2p::{
+    core::{muxing::StreamMuxerBox, transport::{Boxed, OrTransport}, upgrade::Version},
+    futures::future::Either,
+    multiaddr::Protocol,
+    noise, quic, relay, tcp, yamux,
+    Multiaddr, PeerId, Transport,
+};
+
+use crate::primitives::BlockchainError;
+
+/// Which transport a node dials and listens on first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransportPreference {
+    /// TCP only, QUIC addresses are neither listened on nor dialed
+    #[default]
+    Tcp,
+    /// QUIC first, TCP as fallback
+    Quic,
+}
+
+impl std::str::FromStr for TransportPreference {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(TransportPreference::Tcp),
+            "quic" => Ok(TransportPreference::Quic),
+            _ => Err(BlockchainError::InvalidOperation(format!("Unknown transport {}, expected tcp or quic", s))),
+        }
+    }
+}
+
+/// Whether an address is dialed over QUIC
+pub fn is_quic(address: &Multiaddr) -> bool {
+    address.iter().any(|protocol| matches!(protocol, Protocol::QuicV1))
+}
+
+/// QUIC address on the UDP port of the same number as a TCP listen address, `None` for other addresses
+pub fn quic_listen_address(tcp_address: &Multiaddr) -> Option<Multiaddr> {
+    let mut quic = Multiaddr::empty();
+    let mut has_tcp = false;
+    for protocol in tcp_address.iter() {
+        match protocol {
+            Protocol::Tcp(port) if !has_tcp => {
+                has_tcp = true;
+                quic.push(Protocol::Udp(port));
+                quic.push(Protocol::QuicV1);
+            }
+            Protocol::P2pCircuit => return None,
+            protocol => quic.push(protocol),
+        }
+    }
+    has_tcp.then_some(quic)
+}
+
+/// Addresses of a peer in the order they are dialed, each tried once the one before failed
+pub fn dial_order(addresses: Vec<Multiaddr>, preference: TransportPreference) -> Vec<Multiaddr> {
+    match preference {
+        TransportPreference::Tcp => addresses.into_iter().filter(|address| !is_quic(address)).collect(),
+        TransportPreference::Quic => {
+            let (mut ordered, tcp): (Vec<_>, Vec<_>) = addresses.into_iter().partition(is_quic);
+            ordered.extend(tcp);
+            ordered
+        }
+    }
+}
+
+/// QUIC, TCP and relayed transports of a node with key `local_key`
+pub fn build_transport(
+    local_key: &libp2p::identity::Keypair,
+    relay_transport: relay::client::Transport,
+) -> std::result::Result<Boxed<(PeerId, StreamMuxerBox)>, BlockchainError> {
+    let tcp_transport = relay_transport
+        .or_transport(tcp::tokio::Transport::new(tcp::Config::default().nodelay(true)))
+        .upgrade(Version::V1Lazy)
+        .authenticate(noise::Config::new(local_key)?)
+        .multiplex(yamux::Config::default());
+
+    // QUIC brings its own encryption and stream multiplexing
+    let quic_transport = quic::tokio::Transport::new(quic::Config::new(local_key));
+
+    Ok(OrTransport::new(quic_transport, tcp_transport)
+        .map(|output, _| match output {
+            Either::Left((peer_id, connection)) => (peer_id, StreamMuxerBox::new(connection)),
+            Either::Right((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
+        })
+        .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quic_addresses_and_dial_order() {
+        let tcp: Multiaddr = "/ip4/0.0.0.0/tcp/8000".parse().unwrap();
+        assert_eq!(quic_listen_address(&tcp).unwrap().to_string(), "/ip4/0.0.0.0/udp/8000/quic-v1");
+        assert!(is_quic(&quic_listen_address(&tcp).unwrap()));
+        assert!(quic_listen_address(&"/ip4/0.0.0.0/udp/8000/quic-v1".parse().unwrap()).is_none());
+
+        let quic: Multiaddr = "/ip4/192.0.2.1/udp/8000/quic-v1".parse().unwrap();
+        let remote_tcp: Multiaddr = "/ip4/192.0.2.1/tcp/8000".parse().unwrap();
+        let addresses = vec![remote_tcp.clone(), quic.clone()];
+        assert_eq!(dial_order(addresses.clone(), TransportPreference::Quic), vec![quic, remote_tcp.clone()]);
+        assert_eq!(dial_order(addresses, TransportPreference::Tcp), vec![remote_tcp]);
+
+        assert_eq!("quic".parse::<TransportPreference>().unwrap(), TransportPreference::Quic);
+        assert!("udp".parse::<TransportPreference>().is_err());
+    }
+}
@@ -0,0 +1,356 @@
+// Settlement lifecycle notifications for finance and NOC teams
+// Audit log entries are routed by action to webhook, email and syslog sinks, retried with
+// exponential backoff and written to a dead-letter file once every attempt failed
+
+pub mod sinks;
+
+pub use sinks::{EmailSink, SyslogSink, WebhookSink};
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::metrics::metrics;
+use crate::primitives::{BlockchainError, Result};
+use crate::storage::{AuditAction, AuditEntry};
+
+/// Settlement lifecycle event as sent to a sink
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: AuditAction,
+    /// Audit log sequence number, unique per node
+    pub sequence: u64,
+    pub timestamp: u64,
+    /// Operator that took the decision
+    pub actor: String,
+    /// Settlement, proposal, batch or period the event concerns, hex
+    pub subject: String,
+    pub details: String,
+}
+
+impl From<&AuditEntry> for Notification {
+    fn from(entry: &AuditEntry) -> Self {
+        Self {
+            event: entry.action,
+            sequence: entry.sequence,
+            timestamp: entry.timestamp,
+            actor: entry.actor.clone(),
+            subject: entry.subject.to_hex(),
+            details: entry.details.clone(),
+        }
+    }
+}
+
+impl Notification {
+    /// One line summary, as email subject and syslog message
+    pub fn summary(&self) -> String {
+        format!("Settlement {} {} by {}", self.event, self.subject, self.actor)
+    }
+}
+
+/// Destination of notifications
+#[async_trait::async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Name the sink is routed to and reported under
+    fn name(&self) -> &str;
+
+    /// Deliver one notification, an error to retry it later
+    async fn deliver(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Configured sink, by type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    /// JSON POST of each notification
+    Webhook {
+        url: String,
+        /// Extra request headers, e.g. an authorization token
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Plain text email through an SMTP relay with STARTTLS
+    Email {
+        smtp_host: String,
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    /// RFC 5424 message over UDP
+    Syslog {
+        #[serde(default = "default_syslog_address")]
+        address: String,
+    },
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_syslog_address() -> String {
+    "127.0.0.1:514".to_string()
+}
+
+/// How often and how patiently a failed delivery is retried
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Deliveries tried in total before the notification is dead-lettered
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 60_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after failed attempt `attempt`, counting from 1, doubling up to the maximum
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.initial_backoff_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(32));
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+}
+
+/// Sinks, which events go to which of them, and what happens to failed deliveries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    pub sinks: HashMap<String, SinkConfig>,
+    /// Sink names each event type is sent to, events without a route are not notified
+    pub routes: HashMap<AuditAction, Vec<String>>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// JSONL file notifications are appended to once every delivery attempt failed
+    pub dead_letter: Option<PathBuf>,
+}
+
+impl NotificationConfig {
+    /// Load the notification configuration from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read notification config {}: {}", path.display(), e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid notification config {}: {}", path.display(), e)))
+    }
+}
+
+/// Notification given up on, as written to the dead-letter file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub sink: String,
+    pub notification: Notification,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: u64,
+}
+
+/// Routes audit log entries to their sinks
+pub struct NotificationDispatcher {
+    routes: HashMap<AuditAction, Vec<Arc<dyn NotificationSink>>>,
+    retry: RetryPolicy,
+    dead_letter: Option<PathBuf>,
+}
+
+impl NotificationDispatcher {
+    pub fn new(retry: RetryPolicy, dead_letter: Option<PathBuf>) -> Self {
+        Self {
+            routes: HashMap::new(),
+            retry,
+            dead_letter,
+        }
+    }
+
+    /// Dispatcher with the configured sinks and routes, refusing routes to unknown sinks
+    pub fn from_config(config: NotificationConfig) -> Result<Self> {
+        let mut sinks: HashMap<String, Arc<dyn NotificationSink>> = HashMap::new();
+        for (name, sink) in config.sinks {
+            let sink: Arc<dyn NotificationSink> = match sink {
+                SinkConfig::Webhook { url, headers } => Arc::new(WebhookSink::new(name.clone(), url, headers)?),
+                SinkConfig::Email { smtp_host, smtp_port, username, password, from, to } => {
+                    let credentials = username.zip(password);
+                    Arc::new(EmailSink::new(name.clone(), &smtp_host, smtp_port, credentials, &from, &to)?)
+                }
+                SinkConfig::Syslog { address } => Arc::new(SyslogSink::new(name.clone(), address)),
+            };
+            sinks.insert(name, sink);
+        }
+
+        let mut dispatcher = Self::new(config.retry, config.dead_letter);
+        for (action, names) in config.routes {
+            for name in names {
+                let sink = sinks.get(&name).ok_or_else(|| BlockchainError::InvalidOperation(
+                    format!("Notification route for {} names unknown sink {}", action, name)
+                ))?;
+                dispatcher.add_route(action, sink.clone());
+            }
+        }
+        Ok(dispatcher)
+    }
+
+    /// Send events of `action` to `sink` as well
+    pub fn add_route(&mut self, action: AuditAction, sink: Arc<dyn NotificationSink>) {
+        self.routes.entry(action).or_default().push(sink);
+    }
+
+    /// Notify every entry recorded in the audit log until it is dropped
+    pub async fn run(self, mut events: broadcast::Receiver<AuditEntry>) {
+        info!("🔔 Settlement notifications routed for {} event types", self.routes.len());
+        let retry = Arc::new(self.retry);
+        loop {
+            let entry = match events.recv().await {
+                Ok(entry) => entry,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("🔕 {} settlement events were not notified, notifications fell behind", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let sinks = match self.routes.get(&entry.action) {
+                Some(sinks) => sinks,
+                None => continue,
+            };
+
+            // A slow or failing sink holds up neither the others nor the next event
+            let notification = Notification::from(&entry);
+            for sink in sinks {
+                tokio::spawn(deliver(sink.clone(), notification.clone(), retry.clone(), self.dead_letter.clone()));
+            }
+        }
+    }
+}
+
+/// Deliver a notification with retries, dead-lettering it once every attempt failed
+pub async fn deliver(
+    sink: Arc<dyn NotificationSink>,
+    notification: Notification,
+    retry: Arc<RetryPolicy>,
+    dead_letter: Option<PathBuf>,
+) {
+    let mut attempt = 1;
+    loop {
+        let error = match sink.deliver(&notification).await {
+            Ok(()) => {
+                debug!("🔔 {} #{} notified to {}", notification.event, notification.sequence, sink.name());
+                metrics().notifications_sent.inc();
+                return;
+            }
+            Err(e) => e,
+        };
+        if attempt >= retry.max_attempts {
+            warn!("❌ Giving up notifying {} #{} to {} after {} attempts: {}",
+                  notification.event, notification.sequence, sink.name(), attempt, error);
+            metrics().notifications_dead_lettered.inc();
+            let letter = DeadLetter {
+                sink: sink.name().to_string(),
+                notification,
+                attempts: attempt,
+                error: error.to_string(),
+                failed_at: chrono::Utc::now().timestamp() as u64,
+            };
+            if let Some(path) = &dead_letter {
+                if let Err(e) = append_dead_letter(path, &letter) {
+                    warn!("Failed to write dead letter to {}: {}", path.display(), e);
+                }
+            }
+            return;
+        }
+        debug!("Notifying {} failed (attempt {}): {}", sink.name(), attempt, error);
+        tokio::time::sleep(retry.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Append one dead letter as a JSON line
+fn append_dead_letter(path: &Path, letter: &DeadLetter) -> Result<()> {
+    let mut line = serde_json::to_string(letter).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+    line.push('\n');
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| BlockchainError::Storage(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{Blake2bHash, NetworkId};
+    use crate::storage::AuditLog;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Sink failing its first `failures` deliveries
+    struct FlakySink {
+        failures: u32,
+        attempts: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl NotificationSink for FlakySink {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn deliver(&self, _notification: &Notification) -> Result<()> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(BlockchainError::NetworkError("unreachable".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_then_dead_letter() {
+        let retry = Arc::new(RetryPolicy { max_attempts: 3, initial_backoff_ms: 1, max_backoff_ms: 2 });
+        assert_eq!(RetryPolicy::default().backoff(3), Duration::from_secs(4));
+        assert_eq!(RetryPolicy::default().backoff(10), Duration::from_secs(60));
+
+        let log = AuditLog::in_memory();
+        let mut events = log.subscribe();
+        log.record(&NetworkId::new("Orange", "FR"), AuditAction::Disputed, Blake2bHash::from_data(b"settlement"), "amount").await.unwrap();
+        let notification = Notification::from(&events.recv().await.unwrap());
+        assert_eq!(notification.event, AuditAction::Disputed);
+
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letter = dir.path().join("dead_letter.jsonl");
+
+        // Delivered on the last attempt
+        let recovering = Arc::new(FlakySink { failures: 2, attempts: AtomicU32::new(0) });
+        deliver(recovering.clone(), notification.clone(), retry.clone(), Some(dead_letter.clone())).await;
+        assert_eq!(recovering.attempts.load(Ordering::SeqCst), 3);
+        assert!(!dead_letter.exists());
+
+        // Never delivered
+        let down = Arc::new(FlakySink { failures: u32::MAX, attempts: AtomicU32::new(0) });
+        deliver(down.clone(), notification.clone(), retry, Some(dead_letter.clone())).await;
+        assert_eq!(down.attempts.load(Ordering::SeqCst), 3);
+        let letter: DeadLetter = serde_json::from_str(std::fs::read_to_string(&dead_letter).unwrap().trim()).unwrap();
+        assert_eq!((letter.sink.as_str(), letter.attempts), ("flaky", 3));
+        assert_eq!(letter.notification, notification);
+    }
+
+    #[test]
+    fn test_routes_to_unknown_sinks_are_refused() {
+        let config: NotificationConfig = serde_json::from_str(r#"{
+            "sinks": { "noc": { "type": "syslog" } },
+            "routes": { "Disputed": ["noc"], "Finalized": ["finance"] }
+        }"#).unwrap();
+        assert_eq!(config.retry, RetryPolicy::default());
+        assert!(NotificationDispatcher::from_config(config).is_err());
+    }
+}
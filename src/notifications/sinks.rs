@@ -0,0 +1,166 @@
+// Notification sinks: webhook POST, SMTP email and syslog over UDP
+use std::collections::HashMap;
+use std::time::Duration;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use tokio::net::UdpSocket;
+
+use crate::primitives::{BlockchainError, Result};
+use crate::storage::AuditAction;
+use super::{Notification, NotificationSink};
+
+/// Time a webhook endpoint gets to answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts each notification as JSON, any status outside 2xx failing the delivery
+pub struct WebhookSink {
+    name: String,
+    url: String,
+    headers: HashMap<String, String>,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(name: String, url: String, headers: HashMap<String, String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| BlockchainError::NetworkError(format!("Webhook client for {}: {}", name, e)))?;
+        Ok(Self { name, url, headers, client })
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Result<()> {
+        let mut request = self.client.post(&self.url).json(notification);
+        for (header, value) in &self.headers {
+            request = request.header(header, value);
+        }
+        let response = request.send().await
+            .map_err(|e| BlockchainError::NetworkError(format!("Webhook {} unreachable: {}", self.url, e)))?;
+        if !response.status().is_success() {
+            return Err(BlockchainError::NetworkError(format!("Webhook {} answered {}", self.url, response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Mails each notification as plain text through an SMTP relay
+pub struct EmailSink {
+    name: String,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl EmailSink {
+    pub fn new(
+        name: String,
+        smtp_host: &str,
+        smtp_port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self> {
+        let mailbox = |address: &str| address.parse::<Mailbox>()
+            .map_err(|e| BlockchainError::InvalidOperation(format!("Invalid email address {} of {}: {}", address, name, e)));
+        let from = mailbox(from)?;
+        let to = to.iter().map(|address| mailbox(address)).collect::<Result<Vec<_>>>()?;
+        if to.is_empty() {
+            return Err(BlockchainError::InvalidOperation(format!("Email sink {} has no recipients", name)));
+        }
+
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)
+            .map_err(|e| BlockchainError::NetworkError(format!("SMTP relay {}: {}", smtp_host, e)))?
+            .port(smtp_port);
+        if let Some((username, password)) = credentials {
+            transport = transport.credentials(Credentials::new(username, password));
+        }
+        Ok(Self { name, from, to, transport: transport.build() })
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationSink for EmailSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Result<()> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[SP CDR] {}", notification.summary()));
+        for recipient in &self.to {
+            message = message.to(recipient.clone());
+        }
+        let body = format!(
+            "Event: {}\nSubject: {}\nOperator: {}\nTime: {}\nAudit entry: #{}\n\n{}\n",
+            notification.event,
+            notification.subject,
+            notification.actor,
+            chrono::DateTime::from_timestamp(notification.timestamp as i64, 0).unwrap_or_default().to_rfc3339(),
+            notification.sequence,
+            notification.details,
+        );
+        let email = message.body(body)
+            .map_err(|e| BlockchainError::InvalidOperation(format!("Notification email: {}", e)))?;
+
+        self.transport.send(email).await
+            .map_err(|e| BlockchainError::NetworkError(format!("SMTP delivery by {}: {}", self.name, e)))?;
+        Ok(())
+    }
+}
+
+/// Sends each notification as an RFC 5424 message to a syslog collector over UDP
+pub struct SyslogSink {
+    name: String,
+    address: String,
+}
+
+/// Facility local0, where the NOC's collector expects application events
+const SYSLOG_FACILITY: u8 = 16;
+
+impl SyslogSink {
+    pub fn new(name: String, address: String) -> Self {
+        Self { name, address }
+    }
+
+    /// RFC 5424 line of a notification, failures and disputes at warning severity and the rest at notice
+    pub fn format(notification: &Notification) -> String {
+        let severity = match notification.event {
+            AuditAction::Disputed | AuditAction::PaymentFailed | AuditAction::Quarantined => 4,
+            _ => 5,
+        };
+        let timestamp = chrono::DateTime::from_timestamp(notification.timestamp as i64, 0).unwrap_or_default();
+        format!(
+            "<{}>1 {} - sp-cdr-node - {} - {}: {}",
+            SYSLOG_FACILITY * 8 + severity,
+            timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+            notification.event,
+            notification.summary(),
+            notification.details,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationSink for SyslogSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Result<()> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await
+            .map_err(|e| BlockchainError::NetworkError(format!("Syslog socket: {}", e)))?;
+        socket.send_to(Self::format(notification).as_bytes(), &self.address).await
+            .map_err(|e| BlockchainError::NetworkError(format!("Syslog {} unreachable: {}", self.address, e)))?;
+        Ok(())
+    }
+}
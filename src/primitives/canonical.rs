@@ -0,0 +1,423 @@
+// Canonical encoding of consensus-critical types: every value has exactly one encoding, the same
+// across versions and platforms, so hashes and signatures computed over it agree on every node.
+//
+// Struct fields are encoded in declaration order without their names, integers fixed-width
+// big-endian, strings, byte strings and sequences behind a u32 length, enum variants by their u32
+// index, options behind a 0/1 tag and maps with their entries sorted by encoded key. Floats have
+// no canonical encoding and are refused. Reordering fields or variants changes the encoding,
+// new enum variants go last
+use serde::ser::{self, Serialize};
+
+use super::error::{BlockchainError, Result};
+use super::primitives::{hash_data, Blake2bHash};
+
+/// Why a value has no canonical encoding
+#[derive(Debug)]
+pub struct CanonicalError(String);
+
+impl std::fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CanonicalError {}
+
+impl ser::Error for CanonicalError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        CanonicalError(msg.to_string())
+    }
+}
+
+type Encoded<T = ()> = std::result::Result<T, CanonicalError>;
+
+/// Canonical bytes of a value
+pub fn to_canonical_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    encode(value).map_err(|e| BlockchainError::Serialization(format!("Canonical encoding failed: {}", e)))
+}
+
+/// Hash of a value's canonical bytes, how blocks, transactions and proposals are identified
+pub fn hash_canonical<T: Serialize + ?Sized>(value: &T) -> Blake2bHash {
+    hash_data(&to_canonical_bytes(value).expect("consensus types have a canonical encoding"))
+}
+
+fn encode<T: Serialize + ?Sized>(value: &T) -> Encoded<Vec<u8>> {
+    let mut encoder = CanonicalEncoder { output: Vec::new() };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.output)
+}
+
+struct CanonicalEncoder {
+    output: Vec<u8>,
+}
+
+impl CanonicalEncoder {
+    fn length(&mut self, len: usize) -> Encoded {
+        let len = u32::try_from(len).map_err(|_| CanonicalError(format!("Length {} does not fit in u32", len)))?;
+        self.output.extend_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+
+    fn variant(&mut self, index: u32) {
+        self.output.extend_from_slice(&index.to_be_bytes());
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = MapEncoder<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Encoded {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Encoded {
+        self.output.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Encoded {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Encoded {
+        Err(CanonicalError("Floats have no canonical encoding".to_string()))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Encoded {
+        Err(CanonicalError("Floats have no canonical encoding".to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Encoded {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Encoded {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Encoded {
+        self.length(v.len())?;
+        self.output.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Encoded {
+        self.output.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Encoded {
+        self.output.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Encoded {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Encoded {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Encoded {
+        self.variant(variant_index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Encoded {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Encoded {
+        self.variant(variant_index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Encoded<Self> {
+        let len = len.ok_or_else(|| CanonicalError("Sequences need a known length".to_string()))?;
+        self.length(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Encoded<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Encoded<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Encoded<Self> {
+        self.variant(variant_index);
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Encoded<MapEncoder<'a>> {
+        Ok(MapEncoder { encoder: self, entries: Vec::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Encoded<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Encoded<Self> {
+        self.variant(variant_index);
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Encoded {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Encoded {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Encoded {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Encoded {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Encoded {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Encoded {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Encoded {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Encoded {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Encoded {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Encoded {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut CanonicalEncoder {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Encoded {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Encoded {
+        Ok(())
+    }
+}
+
+/// Map entries are collected and written sorted by their encoded keys, whatever the map's order
+struct MapEncoder<'a> {
+    encoder: &'a mut CanonicalEncoder,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    key: Option<Vec<u8>>,
+}
+
+impl ser::SerializeMap for MapEncoder<'_> {
+    type Ok = ();
+    type Error = CanonicalError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Encoded {
+        self.key = Some(encode(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Encoded {
+        let key = self.key.take().ok_or_else(|| CanonicalError("Map value without a key".to_string()))?;
+        self.entries.push((key, encode(value)?));
+        Ok(())
+    }
+
+    fn end(mut self) -> Encoded {
+        self.entries.sort();
+        self.encoder.length(self.entries.len())?;
+        for (key, value) in self.entries {
+            self.encoder.output.extend_from_slice(&key);
+            self.encoder.output.extend_from_slice(&value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::blockchain::block::{Transaction, TransactionData};
+
+    #[derive(Serialize)]
+    enum Kind {
+        Unit,
+        Pair(u16, bool),
+        Named { id: char },
+    }
+
+    #[derive(Serialize)]
+    struct Sample {
+        small: u8,
+        signed: i64,
+        name: String,
+        bytes: Vec<u8>,
+        missing: Option<u32>,
+        present: Option<u32>,
+        kinds: Vec<Kind>,
+        map: HashMap<String, u32>,
+    }
+
+    // Golden vectors: these bytes and hashes must never change, other nodes and versions rely on them
+    #[test]
+    fn test_golden_encoding() {
+        let sample = Sample {
+            small: 7,
+            signed: -2,
+            name: "ab".to_string(),
+            bytes: vec![1, 2, 3],
+            missing: None,
+            present: Some(5),
+            kinds: vec![Kind::Unit, Kind::Pair(0x0102, true), Kind::Named { id: 'A' }],
+            map: HashMap::from([("b".to_string(), 2), ("a".to_string(), 1)]),
+        };
+        assert_eq!(
+            hex::encode(to_canonical_bytes(&sample).unwrap()),
+            "07fffffffffffffffe00000002616200000003010203000100000005\
+             000000030000000000000001010201000000020000004100000002\
+             000000016100000001000000016200000002"
+        );
+    }
+
+    #[test]
+    fn test_golden_transaction_hash() {
+        let transaction = Transaction {
+            sender: Blake2bHash([1; 32]),
+            recipient: Blake2bHash([2; 32]),
+            value: 1000,
+            fee: 10,
+            nonce: 7,
+            validity_start_height: 42,
+            data: TransactionData::Basic,
+            signature: vec![0xaa, 0xbb],
+            signature_proof: vec![],
+        };
+        assert_eq!(to_canonical_bytes(&transaction).unwrap().len(), 106);
+        assert_eq!(transaction.hash().to_hex(), "548cf723d7c13f8ad7df22a498c6e24fad5283ffc864c33b2603733bc4cb6dca");
+    }
+
+    #[test]
+    fn test_map_order_and_floats() {
+        let forward: HashMap<u32, u32> = (0..64).map(|i| (i, i * 2)).collect();
+        let backward: HashMap<u32, u32> = (0..64).rev().map(|i| (i, i * 2)).collect();
+        assert_eq!(to_canonical_bytes(&forward).unwrap(), to_canonical_bytes(&backward).unwrap());
+
+        assert!(to_canonical_bytes(&1.5f64).is_err());
+    }
+}
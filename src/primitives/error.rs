@@ -38,6 +38,10 @@ pub enum BlockchainError {
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
 
+    /// The node is at capacity, the request can be retried later
+    #[error("Saturated: {0}")]
+    Saturated(String),
+
     #[error("Invalid proof")]
     InvalidProof,
 
@@ -4,9 +4,11 @@ pub mod error;
 pub mod crypto;
 pub mod cdr;
 pub mod blockchain_integration;
+pub mod canonical;
 
 pub use primitives::*;
 pub use error::*;
 pub use crypto::*;
 pub use cdr::*;
-pub use blockchain_integration::*;
\ No newline at end of file
+pub use blockchain_integration::*;
+pub use canonical::{hash_canonical, to_canonical_bytes};
\ No newline at end of file
@@ -38,6 +38,12 @@ impl Blake2bHash {
     pub fn to_hex(&self) -> String {
         hex::encode(self.0)
     }
+
+    /// Parse 64 hex characters, `None` for anything else
+    pub fn from_hex(hex_str: &str) -> Option<Self> {
+        let bytes: [u8; 32] = hex::decode(hex_str).ok()?.try_into().ok()?;
+        Some(Blake2bHash(bytes))
+    }
 }
 
 impl std::fmt::Display for Blake2bHash {
@@ -92,6 +98,48 @@ impl Policy {
     
     /// Block time in milliseconds
     pub const BLOCK_TIME: u64 = 1000; // 1 second for SP reconciliation
+
+    /// Maximum gas the transactions of one block may reserve
+    pub const BLOCK_GAS_LIMIT: u64 = 100_000_000;
+
+    /// Gas a CDR record transaction may spend in its settlement contract
+    pub const CDR_TRANSACTION_GAS_LIMIT: u64 = 1_000_000;
+
+    /// Gas a settlement transaction may spend validating its settlement
+    pub const SETTLEMENT_TRANSACTION_GAS_LIMIT: u64 = 2_000_000;
+
+    /// Minimum fee per 1000 bytes of a sent transaction, rounded up
+    pub const MIN_FEE_PER_KILOBYTE: u64 = 10;
+
+    /// Minimum fee per million gas a sent transaction reserves, rounded up
+    pub const MIN_FEE_PER_MEGAGAS: u64 = 10;
+
+    /// Number of blocks between election blocks
+    pub const ELECTION_BLOCK_INTERVAL: u32 = Self::EPOCH_LENGTH * Self::BATCH_LENGTH;
+
+    /// Blocks unbonded stake stays locked, long enough to cover two full elections
+    pub const UNBONDING_PERIOD: u32 = Self::ELECTION_BLOCK_INTERVAL * 2;
+
+    /// Blocks validators vote on a governance proposal, one election period
+    pub const GOVERNANCE_VOTING_PERIOD: u32 = Self::ELECTION_BLOCK_INTERVAL;
+
+    /// Blocks a committer has to reveal a challenged CDR record, one election period
+    pub const COMMITMENT_RESPONSE_WINDOW: u32 = Self::ELECTION_BLOCK_INTERVAL;
+
+    /// Whether the block at this height closes an epoch as a macro block
+    pub fn is_macro_block(block_number: Height) -> bool {
+        block_number % Self::EPOCH_LENGTH == 0
+    }
+
+    /// Whether the block at this height elects a new validator set
+    pub fn is_election_block(block_number: Height) -> bool {
+        block_number % Self::ELECTION_BLOCK_INTERVAL == 0
+    }
+
+    /// First election block strictly after the given height
+    pub fn next_election_block(block_number: Height) -> Height {
+        (block_number / Self::ELECTION_BLOCK_INTERVAL + 1) * Self::ELECTION_BLOCK_INTERVAL
+    }
 }
 
 pub fn hash_data(data: &[u8]) -> Blake2bHash {
@@ -103,4 +151,22 @@ pub fn hash_data(data: &[u8]) -> Blake2bHash {
 pub fn hash_json<T: serde::Serialize>(data: &T) -> Blake2bHash {
     let json = serde_json::to_string(data).unwrap();
     hash_data(json.as_bytes())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_hex_round_trip() {
+        let hash = hash_data(b"receipt");
+        assert_eq!(Blake2bHash::from_hex(&hash.to_hex()), Some(hash));
+        assert_eq!(Blake2bHash::from_hex(&hash.to_hex().to_uppercase()), Some(hash));
+
+        // Wrong length or non-hex characters
+        assert_eq!(Blake2bHash::from_hex(&hash.to_hex()[..62]), None);
+        assert_eq!(Blake2bHash::from_hex(&format!("{}00", hash.to_hex())), None);
+        assert_eq!(Blake2bHash::from_hex(&"zz".repeat(32)), None);
+        assert_eq!(Blake2bHash::from_hex(""), None);
+    }
+}
@@ -0,0 +1,126 @@
+// SEPA credit transfer adapter producing ISO 20022 pain.001 files
+use std::collections::HashMap;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::primitives::{NetworkId, Result, BlockchainError};
+use crate::network::settlement_messaging::{SettlementInstruction, SettlementMethod, ConfirmationType};
+use super::{PaymentAdapter, PaymentReceipt};
+use super::payment_document::pain001_xml;
+
+/// Bank account used in SEPA transfers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankAccount {
+    pub holder_name: String,
+    pub iban: String,
+    pub bic: String,
+}
+
+/// SEPA bank transfer adapter
+/// Writes one pain.001.001.03 file per instruction into the outbox directory,
+/// which the operator's banking gateway picks up for submission
+pub struct SepaBankTransferAdapter {
+    outbox_dir: PathBuf,
+    debtor_account: BankAccount,
+    creditor_accounts: HashMap<NetworkId, BankAccount>,
+}
+
+impl SepaBankTransferAdapter {
+    pub fn new(outbox_dir: PathBuf, debtor_account: BankAccount) -> Self {
+        Self {
+            outbox_dir,
+            debtor_account,
+            creditor_accounts: HashMap::new(),
+        }
+    }
+
+    /// Register the receiving account of a creditor network
+    pub fn register_creditor_account(&mut self, network: NetworkId, account: BankAccount) {
+        self.creditor_accounts.insert(network, account);
+    }
+
+    /// Generate ISO 20022 pain.001.001.03 customer credit transfer initiation
+    pub fn generate_pain001(&self, instruction: &SettlementInstruction) -> Result<String> {
+        let creditor_account = self.creditor_accounts.get(&instruction.creditor)
+            .ok_or_else(|| BlockchainError::NotFound(
+                format!("No bank account registered for {}", instruction.creditor)
+            ))?;
+
+        pain001_xml(instruction, &self.debtor_account, creditor_account, chrono::Utc::now().timestamp())
+    }
+}
+
+#[async_trait::async_trait]
+impl PaymentAdapter for SepaBankTransferAdapter {
+    fn method(&self) -> SettlementMethod {
+        SettlementMethod::BankTransfer
+    }
+
+    async fn execute(&self, instruction: &SettlementInstruction) -> Result<PaymentReceipt> {
+        let document = self.generate_pain001(instruction)?;
+
+        tokio::fs::create_dir_all(&self.outbox_dir).await
+            .map_err(|e| BlockchainError::Storage(format!("Failed to create payment outbox: {}", e)))?;
+
+        let file_path = self.outbox_dir.join(format!("pain001-{}.xml", &instruction.instruction_id.to_hex()[..32]));
+        tokio::fs::write(&file_path, document).await
+            .map_err(|e| BlockchainError::Storage(format!("Failed to write pain.001 file: {}", e)))?;
+
+        info!("🏦 SEPA pain.001 written to {}", file_path.display());
+
+        Ok(PaymentReceipt {
+            instruction_id: instruction.instruction_id,
+            settlement_method: SettlementMethod::BankTransfer,
+            confirmation_type: ConfirmationType::PaymentSent,
+            transaction_ref: Some(file_path.display().to_string()),
+            executed_at: chrono::Utc::now().timestamp() as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Blake2bHash;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_pain001_generation() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut adapter = SepaBankTransferAdapter::new(temp_dir.path().to_path_buf(), BankAccount {
+            holder_name: "Orange S.A.".to_string(),
+            iban: "FR7630006000011234567890189".to_string(),
+            bic: "AGRIFRPP".to_string(),
+        });
+        adapter.register_creditor_account(NetworkId::new("T-Mobile", "DE"), BankAccount {
+            holder_name: "Telekom Deutschland & Co".to_string(),
+            iban: "DE89370400440532013000".to_string(),
+            bic: "COBADEFFXXX".to_string(),
+        });
+
+        let instruction = SettlementInstruction {
+            instruction_id: Blake2bHash::from_data(b"sepa"),
+            creditor: NetworkId::new("T-Mobile", "DE"),
+            debtor: NetworkId::new("Orange", "FR"),
+            amount: 4250075,
+            currency: "EUR".to_string(),
+            due_date: 1704067200,
+            settlement_method: SettlementMethod::BankTransfer,
+        };
+
+        let xml = adapter.generate_pain001(&instruction).unwrap();
+        assert!(xml.contains("pain.001.001.03"));
+        assert!(xml.contains("<InstdAmt Ccy=\"EUR\">42500.75</InstdAmt>"));
+        assert!(xml.contains("<ReqdExctnDt>2024-01-01</ReqdExctnDt>"));
+        assert!(xml.contains("Telekom Deutschland &amp; Co"));
+
+        let receipt = adapter.execute(&instruction).await.unwrap();
+        assert!(std::path::Path::new(receipt.transaction_ref.as_ref().unwrap()).exists());
+
+        // SEPA is EUR only
+        let mut gbp = instruction.clone();
+        gbp.currency = "GBP".to_string();
+        assert!(adapter.generate_pain001(&gbp).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+// Clearing house API client (stub)
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::primitives::{Result, BlockchainError};
+use crate::network::settlement_messaging::{SettlementInstruction, SettlementMethod, ConfirmationType};
+use super::{PaymentAdapter, PaymentReceipt};
+
+/// Payment submission sent to the clearing house API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearingHouseSubmission {
+    pub submission_id: String,
+    pub participant_id: String,
+    pub debtor: String,
+    pub creditor: String,
+    pub amount_cents: u64,
+    pub currency: String,
+    pub value_date: u64,
+}
+
+/// Clearing house client
+/// Builds the JSON submission for `POST {endpoint}/settlements`; transport is
+/// left to the operator's clearing house integration
+pub struct ClearingHouseClient {
+    endpoint: String,
+    participant_id: String,
+}
+
+impl ClearingHouseClient {
+    pub fn new(endpoint: String, participant_id: String) -> Self {
+        Self { endpoint, participant_id }
+    }
+
+    /// Build clearing house submission for an instruction
+    pub fn build_submission(&self, instruction: &SettlementInstruction) -> ClearingHouseSubmission {
+        ClearingHouseSubmission {
+            submission_id: format!("CH-{}", &instruction.instruction_id.to_hex()[..24]),
+            participant_id: self.participant_id.clone(),
+            debtor: instruction.debtor.to_string(),
+            creditor: instruction.creditor.to_string(),
+            amount_cents: instruction.amount,
+            currency: instruction.currency.clone(),
+            value_date: instruction.due_date,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PaymentAdapter for ClearingHouseClient {
+    fn method(&self) -> SettlementMethod {
+        SettlementMethod::ClearingHouse
+    }
+
+    async fn execute(&self, instruction: &SettlementInstruction) -> Result<PaymentReceipt> {
+        let submission = self.build_submission(instruction);
+        let body = serde_json::to_string(&submission)
+            .map_err(|e| BlockchainError::Serialization(format!("Clearing house submission: {}", e)))?;
+
+        info!("🏛️  Clearing house submission to {}/settlements: {}", self.endpoint, body);
+
+        Ok(PaymentReceipt {
+            instruction_id: instruction.instruction_id,
+            settlement_method: SettlementMethod::ClearingHouse,
+            confirmation_type: ConfirmationType::PaymentSent,
+            transaction_ref: Some(submission.submission_id),
+            executed_at: chrono::Utc::now().timestamp() as u64,
+        })
+    }
+}
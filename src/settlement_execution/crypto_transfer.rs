@@ -0,0 +1,67 @@
+// On-chain crypto transfer adapter (stub)
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::primitives::{NetworkId, Result};
+use crate::network::settlement_messaging::{SettlementInstruction, SettlementMethod, ConfirmationType};
+use super::{PaymentAdapter, PaymentReceipt};
+
+/// Crypto transfer adapter
+/// Builds the transfer and derives a deterministic transaction reference; a
+/// wallet/RPC integration would broadcast the transfer and wait for finality
+pub struct CryptoTransferAdapter {
+    source_wallet: String,
+    creditor_wallets: HashMap<NetworkId, String>,
+}
+
+impl CryptoTransferAdapter {
+    pub fn new(source_wallet: String) -> Self {
+        Self {
+            source_wallet,
+            creditor_wallets: HashMap::new(),
+        }
+    }
+
+    /// Register the receiving wallet of a creditor network
+    pub fn register_creditor_wallet(&mut self, network: NetworkId, wallet: String) {
+        self.creditor_wallets.insert(network, wallet);
+    }
+
+    /// Receiving wallet, derived from the network ID when none is registered
+    fn creditor_wallet(&self, network: &NetworkId) -> String {
+        self.creditor_wallets.get(network).cloned().unwrap_or_else(|| {
+            let hash = crate::primitives::primitives::hash_data(network.to_string().as_bytes());
+            format!("0x{}", &hash.to_hex()[..40])
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PaymentAdapter for CryptoTransferAdapter {
+    fn method(&self) -> SettlementMethod {
+        SettlementMethod::CryptoTransfer
+    }
+
+    async fn execute(&self, instruction: &SettlementInstruction) -> Result<PaymentReceipt> {
+        let destination = self.creditor_wallet(&instruction.creditor);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(instruction.instruction_id.as_bytes());
+        data.extend_from_slice(self.source_wallet.as_bytes());
+        data.extend_from_slice(destination.as_bytes());
+        data.extend_from_slice(&instruction.amount.to_le_bytes());
+        let tx_hash = crate::primitives::primitives::hash_data(&data);
+
+        info!("🪙 Crypto transfer {} → {} for {} {} (tx 0x{})",
+              self.source_wallet, destination, instruction.amount as f64 / 100.0,
+              instruction.currency, tx_hash.to_hex());
+
+        Ok(PaymentReceipt {
+            instruction_id: instruction.instruction_id,
+            settlement_method: SettlementMethod::CryptoTransfer,
+            confirmation_type: ConfirmationType::PaymentSent,
+            transaction_ref: Some(format!("0x{}", tx_hash.to_hex())),
+            executed_at: chrono::Utc::now().timestamp() as u64,
+        })
+    }
+}
@@ -0,0 +1,171 @@
+// Settlement execution subsystem
+// Executes net settlement instructions through payment rails selected by SettlementMethod
+
+pub mod bank_transfer;
+pub mod crypto_transfer;
+pub mod clearing_house;
+pub mod payment_document;
+
+pub use bank_transfer::{SepaBankTransferAdapter, BankAccount};
+pub use crypto_transfer::CryptoTransferAdapter;
+pub use clearing_house::ClearingHouseClient;
+pub use payment_document::{BankAccountDirectory, PaymentDocument};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::primitives::{Blake2bHash, Result, BlockchainError};
+use crate::network::settlement_messaging::{
+    SettlementInstruction, SettlementMethod, SettlementMessage, ConfirmationType,
+};
+
+/// Outcome of handing an instruction to a payment rail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentReceipt {
+    pub instruction_id: Blake2bHash,
+    pub settlement_method: SettlementMethod,
+    pub confirmation_type: ConfirmationType,
+    pub transaction_ref: Option<String>,
+    pub executed_at: u64,
+}
+
+impl PaymentReceipt {
+    /// Convert receipt into a settlement confirmation for the settlement topic
+    pub fn to_confirmation_message(&self, confirmer_signature: Vec<u8>) -> SettlementMessage {
+        SettlementMessage::SettlementConfirmation {
+            settlement_id: self.instruction_id,
+            confirmation_type: self.confirmation_type.clone(),
+            transaction_ref: self.transaction_ref.clone(),
+            timestamp: self.executed_at,
+            confirmer_signature,
+        }
+    }
+}
+
+/// Payment rail adapter
+#[async_trait::async_trait]
+pub trait PaymentAdapter: Send + Sync {
+    /// Settlement method served by this adapter
+    fn method(&self) -> SettlementMethod;
+
+    /// Submit a settlement instruction to the payment rail
+    async fn execute(&self, instruction: &SettlementInstruction) -> Result<PaymentReceipt>;
+}
+
+/// Routes settlement instructions to the adapter for their settlement method
+#[derive(Default)]
+pub struct SettlementExecutor {
+    adapters: HashMap<SettlementMethod, Arc<dyn PaymentAdapter>>,
+}
+
+impl SettlementExecutor {
+    pub fn new() -> Self {
+        Self {
+            adapters: HashMap::new(),
+        }
+    }
+
+    /// Register adapter, replacing any adapter for the same method
+    pub fn register_adapter(&mut self, adapter: Arc<dyn PaymentAdapter>) {
+        info!("💳 Registered payment adapter for {:?}", adapter.method());
+        self.adapters.insert(adapter.method(), adapter);
+    }
+
+    /// Check if a settlement method can be executed
+    pub fn supports(&self, method: &SettlementMethod) -> bool {
+        self.adapters.contains_key(method)
+    }
+
+    /// Execute instruction through its settlement method's adapter
+    pub async fn execute(&self, instruction: &SettlementInstruction) -> Result<PaymentReceipt> {
+        let adapter = self.adapters.get(&instruction.settlement_method)
+            .ok_or_else(|| BlockchainError::InvalidOperation(
+                format!("No payment adapter for {:?}", instruction.settlement_method)
+            ))?;
+
+        if instruction.amount == 0 {
+            return Err(BlockchainError::InvalidOperation(
+                format!("Settlement instruction {} has zero amount", instruction.instruction_id)
+            ));
+        }
+
+        match adapter.execute(instruction).await {
+            Ok(receipt) => {
+                info!("💸 {:?} executed for {}: {:?}",
+                      instruction.settlement_method, instruction.instruction_id, receipt.transaction_ref);
+                Ok(receipt)
+            }
+            Err(e) => {
+                warn!("Payment via {:?} failed for {}: {}",
+                      instruction.settlement_method, instruction.instruction_id, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Execute instruction, turning adapter errors into a failed receipt
+    pub async fn execute_or_fail(&self, instruction: &SettlementInstruction) -> PaymentReceipt {
+        match self.execute(instruction).await {
+            Ok(receipt) => receipt,
+            Err(_) => PaymentReceipt {
+                instruction_id: instruction.instruction_id,
+                settlement_method: instruction.settlement_method.clone(),
+                confirmation_type: ConfirmationType::PaymentFailed,
+                transaction_ref: None,
+                executed_at: chrono::Utc::now().timestamp() as u64,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::NetworkId;
+
+    fn instruction(method: SettlementMethod) -> SettlementInstruction {
+        SettlementInstruction {
+            instruction_id: Blake2bHash::from_data(b"instruction"),
+            creditor: NetworkId::new("T-Mobile", "DE"),
+            debtor: NetworkId::new("Orange", "FR"),
+            amount: 42500,
+            currency: "EUR".to_string(),
+            due_date: 1704067200,
+            settlement_method: method,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_executor_routes_by_method() {
+        let mut executor = SettlementExecutor::new();
+        executor.register_adapter(Arc::new(CryptoTransferAdapter::new("0xorange".to_string())));
+
+        let receipt = executor.execute(&instruction(SettlementMethod::CryptoTransfer)).await.unwrap();
+        assert!(matches!(receipt.settlement_method, SettlementMethod::CryptoTransfer));
+        assert!(receipt.transaction_ref.is_some());
+
+        // No adapter for in-kind services
+        assert!(executor.execute(&instruction(SettlementMethod::InKindServices)).await.is_err());
+        let failed = executor.execute_or_fail(&instruction(SettlementMethod::InKindServices)).await;
+        assert!(matches!(failed.confirmation_type, ConfirmationType::PaymentFailed));
+    }
+
+    #[tokio::test]
+    async fn test_receipt_to_confirmation() {
+        let mut executor = SettlementExecutor::new();
+        executor.register_adapter(Arc::new(ClearingHouseClient::new(
+            "https://clearing.example/api".to_string(), "ORANGE-FR".to_string()
+        )));
+
+        let receipt = executor.execute(&instruction(SettlementMethod::ClearingHouse)).await.unwrap();
+        match receipt.to_confirmation_message(vec![]) {
+            SettlementMessage::SettlementConfirmation { settlement_id, confirmation_type, .. } => {
+                assert_eq!(settlement_id, Blake2bHash::from_data(b"instruction"));
+                assert!(matches!(confirmation_type, ConfirmationType::PaymentSent));
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+}
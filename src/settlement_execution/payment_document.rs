@@ -0,0 +1,229 @@
+// Signed ISO 20022 payment documents: once a settlement is final, the coordinator issues the
+// pain.001 credit transfer initiation paying it, signs the document hash with its account key and
+// anchors the hash on chain, so the banking gateway it is handed to can check it was not altered
+use std::collections::HashSet;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::{account_address, PaymentDocumentTransaction};
+use crate::crypto::{BLSPrivateKey, BLSPublicKey, BLSSignature};
+use crate::network::settlement_messaging::SettlementInstruction;
+use crate::primitives::{Blake2bHash, BlockchainError, NetworkId, Result};
+use super::BankAccount;
+
+/// Schema of the documents issued for settlements
+pub const PAIN001_SCHEMA: &str = "pain.001.001.03";
+
+/// Bank accounts of the operators settlements are paid between, loaded from JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BankAccountDirectory {
+    pub accounts: Vec<OperatorBankAccount>,
+}
+
+/// Settlement account of one operator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorBankAccount {
+    pub network: NetworkId,
+    pub account: BankAccount,
+}
+
+impl BankAccountDirectory {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to read bank accounts {}: {}", path.display(), e)))?;
+        let directory: Self = serde_json::from_str(&json)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid bank accounts {}: {}", path.display(), e)))?;
+        let mut networks = HashSet::new();
+        if let Some(duplicate) = directory.accounts.iter().find(|entry| !networks.insert(&entry.network)) {
+            return Err(BlockchainError::InvalidOperation(format!("{} has two bank accounts", duplicate.network)));
+        }
+        Ok(directory)
+    }
+
+    pub fn account(&self, network: &NetworkId) -> Option<&BankAccount> {
+        self.accounts.iter().find(|entry| entry.network == *network).map(|entry| &entry.account)
+    }
+}
+
+/// Payment document of a settlement as issued by its coordinator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentDocument {
+    /// Settlement transaction the document pays
+    pub settlement_tx: Blake2bHash,
+    pub schema: String,
+    pub xml: String,
+    /// BLS public key of the coordinator
+    pub signer: Vec<u8>,
+    /// Coordinator signature over the settlement and document hash
+    pub signature: Vec<u8>,
+}
+
+impl PaymentDocument {
+    /// Issue the pain.001 document paying `instruction`, whose id is the settlement transaction
+    pub fn issue(
+        instruction: &SettlementInstruction,
+        debtor: &BankAccount,
+        creditor: &BankAccount,
+        created_at: i64,
+        key: &BLSPrivateKey,
+    ) -> Result<Self> {
+        let xml = pain001_xml(instruction, debtor, creditor, created_at)?;
+        let document_hash = Blake2bHash::from_data(xml.as_bytes());
+        let signature = key.sign(&signing_payload(&instruction.instruction_id, &document_hash))?;
+        Ok(Self {
+            settlement_tx: instruction.instruction_id,
+            schema: PAIN001_SCHEMA.to_string(),
+            xml,
+            signer: key.public_key().to_bytes().to_vec(),
+            signature: signature.to_bytes().to_vec(),
+        })
+    }
+
+    pub fn document_hash(&self) -> Blake2bHash {
+        Blake2bHash::from_data(self.xml.as_bytes())
+    }
+
+    /// Account of the coordinator, the one anchoring the document hash
+    pub fn coordinator(&self) -> Result<Blake2bHash> {
+        Ok(account_address(&BLSPublicKey::from_bytes(&self.signer)?))
+    }
+
+    /// Check the document is the one its coordinator signed
+    pub fn verify(&self) -> Result<()> {
+        let public_key = BLSPublicKey::from_bytes(&self.signer)?;
+        let payload = signing_payload(&self.settlement_tx, &self.document_hash());
+        if !BLSSignature::from_bytes(&self.signature)?.verify(&public_key, &payload)? {
+            return Err(BlockchainError::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    /// Transaction anchoring the document hash on chain
+    pub fn anchor(&self) -> PaymentDocumentTransaction {
+        PaymentDocumentTransaction {
+            settlement_tx: self.settlement_tx,
+            schema: self.schema.clone(),
+            document_hash: self.document_hash(),
+        }
+    }
+}
+
+fn signing_payload(settlement_tx: &Blake2bHash, document_hash: &Blake2bHash) -> Vec<u8> {
+    let mut payload = b"sp-cdr-payment-document".to_vec();
+    payload.extend_from_slice(settlement_tx.as_bytes());
+    payload.extend_from_slice(document_hash.as_bytes());
+    payload
+}
+
+/// ISO 20022 pain.001.001.03 customer credit transfer initiation of one instruction
+pub fn pain001_xml(
+    instruction: &SettlementInstruction,
+    debtor: &BankAccount,
+    creditor: &BankAccount,
+    created_at: i64,
+) -> Result<String> {
+    if instruction.currency != "EUR" {
+        return Err(BlockchainError::InvalidOperation(
+            format!("SEPA transfers must be in EUR, got {}", instruction.currency)
+        ));
+    }
+
+    // ISO 20022 identifiers are limited to 35 characters
+    let message_id = &instruction.instruction_id.to_hex()[..32];
+    let amount = format!("{}.{:02}", instruction.amount / 100, instruction.amount % 100);
+    let created_at = chrono::DateTime::from_timestamp(created_at, 0)
+        .unwrap_or_else(chrono::Utc::now)
+        .format("%Y-%m-%dT%H:%M:%S");
+    let execution_date = chrono::DateTime::from_timestamp(instruction.due_date as i64, 0)
+        .unwrap_or_else(chrono::Utc::now)
+        .format("%Y-%m-%d");
+
+    Ok(format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:pain.001.001.03">
+  <CstmrCdtTrfInitn>
+    <GrpHdr>
+      <MsgId>{msg_id}</MsgId>
+      <CreDtTm>{created_at}</CreDtTm>
+      <NbOfTxs>1</NbOfTxs>
+      <CtrlSum>{amount}</CtrlSum>
+      <InitgPty><Nm>{debtor_name}</Nm></InitgPty>
+    </GrpHdr>
+    <PmtInf>
+      <PmtInfId>{msg_id}</PmtInfId>
+      <PmtMtd>TRF</PmtMtd>
+      <NbOfTxs>1</NbOfTxs>
+      <CtrlSum>{amount}</CtrlSum>
+      <PmtTpInf><SvcLvl><Cd>SEPA</Cd></SvcLvl></PmtTpInf>
+      <ReqdExctnDt>{execution_date}</ReqdExctnDt>
+      <Dbtr><Nm>{debtor_name}</Nm></Dbtr>
+      <DbtrAcct><Id><IBAN>{debtor_iban}</IBAN></Id></DbtrAcct>
+      <DbtrAgt><FinInstnId><BIC>{debtor_bic}</BIC></FinInstnId></DbtrAgt>
+      <ChrgBr>SLEV</ChrgBr>
+      <CdtTrfTxInf>
+        <PmtId><EndToEndId>{msg_id}</EndToEndId></PmtId>
+        <Amt><InstdAmt Ccy="EUR">{amount}</InstdAmt></Amt>
+        <CdtrAgt><FinInstnId><BIC>{creditor_bic}</BIC></FinInstnId></CdtrAgt>
+        <Cdtr><Nm>{creditor_name}</Nm></Cdtr>
+        <CdtrAcct><Id><IBAN>{creditor_iban}</IBAN></Id></CdtrAcct>
+        <RmtInf><Ustrd>{remittance}</Ustrd></RmtInf>
+      </CdtTrfTxInf>
+    </PmtInf>
+  </CstmrCdtTrfInitn>
+</Document>
+"#,
+        msg_id = message_id,
+        created_at = created_at,
+        amount = amount,
+        execution_date = execution_date,
+        debtor_name = xml_escape(&debtor.holder_name),
+        debtor_iban = xml_escape(&debtor.iban),
+        debtor_bic = xml_escape(&debtor.bic),
+        creditor_name = xml_escape(&creditor.holder_name),
+        creditor_iban = xml_escape(&creditor.iban),
+        creditor_bic = xml_escape(&creditor.bic),
+        remittance = xml_escape(&format!("SP roaming settlement {} to {}", instruction.debtor, instruction.creditor)),
+    ))
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::settlement_messaging::SettlementMethod;
+
+    #[test]
+    fn test_issued_document_verifies_against_its_anchor() {
+        let instruction = SettlementInstruction {
+            instruction_id: Blake2bHash::from_data(b"settlement-tx"),
+            creditor: NetworkId::new("T-Mobile", "DE"),
+            debtor: NetworkId::new("Orange", "FR"),
+            amount: 125_000,
+            currency: "EUR".to_string(),
+            due_date: 1704067200,
+            settlement_method: SettlementMethod::BankTransfer,
+        };
+        let debtor = BankAccount { holder_name: "Orange S.A.".to_string(), iban: "FR7630006000011234567890189".to_string(), bic: "AGRIFRPP".to_string() };
+        let creditor = BankAccount { holder_name: "Telekom Deutschland".to_string(), iban: "DE89370400440532013000".to_string(), bic: "COBADEFFXXX".to_string() };
+        let key = BLSPrivateKey::generate().unwrap();
+
+        let document = PaymentDocument::issue(&instruction, &debtor, &creditor, 1704067200, &key).unwrap();
+        assert!(document.verify().is_ok());
+        assert_eq!(document.coordinator().unwrap(), account_address(&key.public_key()));
+        assert!(document.xml.contains(&format!("<MsgId>{}</MsgId>", &instruction.instruction_id.to_hex()[..32])));
+        let anchor = document.anchor();
+        assert_eq!((anchor.settlement_tx, anchor.document_hash), (instruction.instruction_id, Blake2bHash::from_data(document.xml.as_bytes())));
+
+        // Issuing again at the same time gives the same document, and any change breaks the signature
+        assert_eq!(PaymentDocument::issue(&instruction, &debtor, &creditor, 1704067200, &key).unwrap().document_hash(), document.document_hash());
+        let mut altered = document.clone();
+        altered.xml = altered.xml.replace("DE89370400440532013000", "DE02120300000000202051");
+        assert!(altered.verify().is_err());
+    }
+}
@@ -0,0 +1,871 @@
+// Devnet simulation: several validator nodes run in one process, each with its own chain store,
+// block production scheduler and mempool, connected by a mock transport that delays messages on a
+// simulated clock. Synthetic roaming traffic flows over an operator graph, every closed period is
+// netted and settled on chain, and the run reports whether the finalized settlements match the
+// traffic, what netting saved and how long macro blocks took to finalize
+use libp2p::{identity::Keypair, PeerId};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::blockchain::block::{account_address, CDRTransaction, CDRType, SettlementTransaction, Transaction, TransactionData, ValidatorInfo};
+use crate::blockchain::fees;
+use crate::blockchain::light_client::MacroCertificate;
+use crate::common::{AbstractBlockchain, TendermintVote};
+use crate::crypto::bls::BLSPrivateKey;
+use crate::network::block_production::{validator_peer_id, BlockProductionScheduler, ProductionStep, VoteOutcome};
+use crate::network::multilateral_netting::MultilateralNettingSolver;
+use crate::primitives::{Blake2bHash, BlockchainError, NetworkId, Policy, Result};
+use crate::storage::SimpleChainStore;
+use crate::{Block, SPCDRBlockchain};
+
+/// Most blocks a run generates traffic for. Simulated validators are not staked, so the chain has
+/// to stay short of the first election block, with an epoch left for the records still queued
+/// and one to settle the last period
+pub const MAX_SIMULATED_BLOCKS: u32 = Policy::ELECTION_BLOCK_INTERVAL - 3 * Policy::EPOCH_LENGTH;
+
+/// Longest one-way link latency, a macro block round has to finish well within its timeout
+pub const MAX_LINK_LATENCY_MS: u64 = 1_000;
+
+/// Operators the simulated nodes run, numbered once the list is used up
+const OPERATORS: [(&str, &str); 8] = [
+    ("T-Mobile", "DE"), ("Vodafone", "UK"), ("Orange", "FR"), ("Telefonica", "ES"),
+    ("TIM", "IT"), ("KPN", "NL"), ("Swisscom", "CH"), ("Telia", "SE"),
+];
+
+const SETTLEMENT_CURRENCY: &str = "EUR";
+
+/// Operator graph roaming traffic flows over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorGraph {
+    /// Every operator roams with every other
+    Mesh,
+    /// Each operator roams with its two neighbours
+    Ring,
+    /// The first operator is a hub every other one roams with
+    Star,
+    /// Each operator roams with `degree` partners picked from the seed
+    Random { degree: usize },
+}
+
+impl std::str::FromStr for OperatorGraph {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mesh" => Ok(OperatorGraph::Mesh),
+            "ring" => Ok(OperatorGraph::Ring),
+            "star" => Ok(OperatorGraph::Star),
+            _ => s.strip_prefix("random:")
+                .and_then(|degree| degree.parse().ok())
+                .filter(|degree| *degree > 0)
+                .map(|degree| OperatorGraph::Random { degree })
+                .ok_or_else(|| BlockchainError::InvalidOperation(format!(
+                    "Invalid operator graph {}. Use: mesh, ring, star or random:<degree>", s
+                ))),
+        }
+    }
+}
+
+impl std::fmt::Display for OperatorGraph {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OperatorGraph::Mesh => write!(f, "mesh"),
+            OperatorGraph::Ring => write!(f, "ring"),
+            OperatorGraph::Star => write!(f, "star"),
+            OperatorGraph::Random { degree } => write!(f, "random:{}", degree),
+        }
+    }
+}
+
+impl OperatorGraph {
+    /// Operator pairs with a roaming agreement, each pair once with the lower index first
+    pub fn edges(&self, operators: usize, rng: &mut StdRng) -> Vec<(usize, usize)> {
+        let pair = |a: usize, b: usize| (a.min(b), a.max(b));
+        let mut edges: Vec<(usize, usize)> = match *self {
+            OperatorGraph::Mesh => (0..operators).flat_map(|a| (a + 1..operators).map(move |b| (a, b))).collect(),
+            OperatorGraph::Ring => (0..operators)
+                .map(|a| pair(a, (a + 1) % operators))
+                .filter(|(a, b)| a != b)
+                .collect(),
+            OperatorGraph::Star => (1..operators).map(|b| (0, b)).collect(),
+            OperatorGraph::Random { degree } => {
+                let mut edges = Vec::new();
+                for a in 0..operators {
+                    let mut partners: Vec<usize> = (0..operators).filter(|b| *b != a).collect();
+                    partners.shuffle(rng);
+                    edges.extend(partners.into_iter().take(degree).map(|b| pair(a, b)));
+                }
+                edges
+            }
+        };
+        edges.sort();
+        edges.dedup();
+        edges
+    }
+}
+
+/// Simulation run parameters
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// Validator nodes, one per operator
+    pub nodes: usize,
+    /// CDR records generated per second of simulated time
+    pub tps: u32,
+    /// Blocks to generate traffic for, the run goes on until their settlements are final
+    pub blocks: u32,
+    pub graph: OperatorGraph,
+    /// One-way link latency, each message is delayed by up to half of it more
+    pub latency_ms: u64,
+    /// Seed of the traffic and the link jitter
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 4,
+            tps: 20,
+            blocks: 96,
+            graph: OperatorGraph::Mesh,
+            latency_ms: 50,
+            seed: 1,
+        }
+    }
+}
+
+impl SimulationConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.nodes < 2 {
+            return Err(BlockchainError::InvalidOperation("A simulation needs at least 2 nodes to roam between".to_string()));
+        }
+        if self.blocks == 0 || self.blocks > MAX_SIMULATED_BLOCKS {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "A simulation runs 1 to {} blocks, {} requested", MAX_SIMULATED_BLOCKS, self.blocks
+            )));
+        }
+        let records_per_block = self.tps as u64 * Policy::BLOCK_TIME / 1_000;
+        if records_per_block * Policy::CDR_TRANSACTION_GAS_LIMIT > Policy::BLOCK_GAS_LIMIT {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "{} CDR records per second do not fit in the blocks, at most {} do",
+                self.tps, Policy::BLOCK_GAS_LIMIT / Policy::CDR_TRANSACTION_GAS_LIMIT * 1_000 / Policy::BLOCK_TIME
+            )));
+        }
+        if self.latency_ms > MAX_LINK_LATENCY_MS {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "Link latency {}ms is above the {}ms limit", self.latency_ms, MAX_LINK_LATENCY_MS
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Message between simulated nodes
+#[derive(Debug, Clone)]
+enum SimMessage {
+    Transaction(Transaction),
+    /// Micro block sealed by the slot leader
+    MicroBlock(Block),
+    /// Macro block proposal to vote on
+    Proposal { block: Block, proposer: PeerId },
+    Vote { voter: PeerId, vote: TendermintVote },
+}
+
+/// In-process transport delivering messages after a simulated link delay, earliest first
+/// Messages due at the same time are delivered in the order they were sent
+struct MockTransport {
+    queue: BinaryHeap<Reverse<(u64, u64)>>,
+    messages: HashMap<u64, (usize, SimMessage)>,
+    latency_ms: u64,
+    rng: StdRng,
+    sent: u64,
+}
+
+impl MockTransport {
+    fn new(latency_ms: u64, seed: u64) -> Self {
+        Self {
+            queue: BinaryHeap::new(),
+            messages: HashMap::new(),
+            latency_ms,
+            rng: StdRng::seed_from_u64(seed),
+            sent: 0,
+        }
+    }
+
+    fn send(&mut self, now_ms: u64, to: usize, message: SimMessage) {
+        let delay = self.latency_ms + self.rng.gen_range(0..=self.latency_ms / 2);
+        let id = self.sent;
+        self.sent += 1;
+        self.queue.push(Reverse((now_ms + delay, id)));
+        self.messages.insert(id, (to, message));
+    }
+
+    fn broadcast(&mut self, now_ms: u64, from: usize, nodes: usize, message: SimMessage) {
+        for to in (0..nodes).filter(|to| *to != from) {
+            self.send(now_ms, to, message.clone());
+        }
+    }
+
+    /// Next message due before `until_ms`, with its delivery time and recipient
+    fn next_before(&mut self, until_ms: u64) -> Option<(u64, usize, SimMessage)> {
+        let Reverse((at, id)) = *self.queue.peek()?;
+        if at >= until_ms {
+            return None;
+        }
+        self.queue.pop();
+        let (to, message) = self.messages.remove(&id)?;
+        Some((at, to, message))
+    }
+}
+
+/// Validator node of one operator
+struct SimNode {
+    operator: NetworkId,
+    peer_id: PeerId,
+    /// Key the operator signs its transactions with
+    account_key: BLSPrivateKey,
+    account: Blake2bHash,
+    next_nonce: u64,
+    blockchain: SPCDRBlockchain,
+    scheduler: BlockProductionScheduler,
+    mempool: Vec<Transaction>,
+    /// Blocks and proposals that arrived ahead of the head, by block number
+    ahead: BTreeMap<u32, Vec<SimMessage>>,
+    /// Proposal the node prevoted, and votes that arrived before it did
+    voting_on: Option<Blake2bHash>,
+    early_votes: Vec<(PeerId, TendermintVote)>,
+}
+
+impl SimNode {
+    /// Transaction of the operator account with the next nonce, paying the minimum fee
+    fn transaction(&mut self, recipient: Blake2bHash, value: u64, data: TransactionData) -> Result<Transaction> {
+        let mut transaction = Transaction {
+            sender: self.account,
+            recipient,
+            value,
+            fee: 0,
+            nonce: self.next_nonce,
+            validity_start_height: 0,
+            data,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        transaction.fee = fees::min_fee(&transaction);
+        transaction.sign(&self.account_key)?;
+        self.next_nonce += 1;
+        Ok(transaction)
+    }
+
+    /// Mempool transactions the next block can include: those of each sender in nonce order
+    /// from its account nonce, up to the block gas limit
+    fn select_transactions(&self) -> Vec<Transaction> {
+        let gas_limit = self.blockchain.chain_parameters().block_gas_limit;
+        let mut pending = self.mempool.clone();
+        pending.sort_by_key(|transaction| transaction.nonce);
+
+        let mut next_nonces: HashMap<Blake2bHash, u64> = HashMap::new();
+        let mut gas = 0;
+        let mut selected = Vec::new();
+        for transaction in pending {
+            let next_nonce = next_nonces.entry(transaction.sender)
+                .or_insert_with(|| self.blockchain.account_nonce(&transaction.sender));
+            if transaction.nonce != *next_nonce || gas + transaction.gas_limit() > gas_limit {
+                continue;
+            }
+            *next_nonce += 1;
+            gas += transaction.gas_limit();
+            selected.push(transaction);
+        }
+        selected
+    }
+
+    /// Drop the transactions the chain included
+    fn prune_mempool(&mut self) {
+        let blockchain = &self.blockchain;
+        self.mempool.retain(|transaction| transaction.nonce >= blockchain.account_nonce(&transaction.sender));
+    }
+
+    async fn head_number(&self) -> u32 {
+        self.blockchain.head_async().await.block_number()
+    }
+}
+
+/// Outcome of a simulation run
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationReport {
+    pub nodes: usize,
+    pub graph: String,
+    pub seed: u64,
+    /// Highest block any node reached
+    pub height: u32,
+    pub simulated_seconds: u64,
+    pub cdr_records: usize,
+    pub messages: u64,
+    /// Blocks, proposals and votes a node refused
+    pub rejected_messages: usize,
+    pub settlements_expected: usize,
+    /// Expected settlements final on every node
+    pub settlements_finalized: usize,
+    pub settlement_errors: Vec<String>,
+    /// Whether every node ended on the same head and state root
+    pub nodes_agree: bool,
+    pub gross_total: u64,
+    pub net_total: u64,
+    pub netting_savings_percentage: u32,
+    /// Macro block finalizations over all nodes
+    pub finalizations: usize,
+    /// Time from a macro block proposal to its finalization on a node
+    pub consensus_latency_avg_ms: u64,
+    pub consensus_latency_max_ms: u64,
+}
+
+impl SimulationReport {
+    /// Whether every expected settlement is final on every node, the settlements net out as the
+    /// traffic did and the nodes agree on the chain
+    pub fn is_correct(&self) -> bool {
+        self.nodes_agree && self.settlement_errors.is_empty() && self.settlements_finalized == self.settlements_expected
+    }
+}
+
+/// Name of the settlement period closed by the macro block ending epoch `period`
+fn period_name(period: u32) -> String {
+    format!("sim-{:03}", period)
+}
+
+/// Net position per operator of `obligations` owed from debtor to creditor, zero positions left out
+fn net_positions(obligations: impl IntoIterator<Item = (String, String, u64)>) -> BTreeMap<String, i64> {
+    let mut positions: BTreeMap<String, i64> = BTreeMap::new();
+    for (debtor, creditor, amount) in obligations {
+        *positions.entry(debtor).or_default() -= amount as i64;
+        *positions.entry(creditor).or_default() += amount as i64;
+    }
+    positions.retain(|_, position| *position != 0);
+    positions
+}
+
+fn same_settlement(a: &SettlementTransaction, b: &SettlementTransaction) -> bool {
+    a.creditor_network == b.creditor_network && a.debtor_network == b.debtor_network
+        && a.amount == b.amount && a.currency == b.currency && a.period == b.period
+}
+
+/// In-process devnet of validator nodes driven by a simulated clock
+pub struct Simulation {
+    config: SimulationConfig,
+    nodes: Vec<SimNode>,
+    edges: Vec<(usize, usize)>,
+    transport: MockTransport,
+    rng: StdRng,
+    started: Instant,
+    now_ms: u64,
+    /// Wholesale charge of each generated CDR record: home operator, visited operator and cents
+    charges: HashMap<Blake2bHash, (usize, usize, u64)>,
+    /// Charges of the records each period included, the home operator owing the visited one
+    period_obligations: BTreeMap<u32, BTreeMap<(usize, usize), u64>>,
+    netted_periods: BTreeSet<u32>,
+    /// Settlements the netted periods call for
+    expected: Vec<SettlementTransaction>,
+    gross_total: u64,
+    net_total: u64,
+    proposed_at: HashMap<(u32, u32), u64>,
+    latencies: Vec<u64>,
+    cdr_records: usize,
+    rejected: usize,
+}
+
+impl Simulation {
+    /// Set up the nodes, all validators of the initial set and each running one operator
+    pub fn new(config: SimulationConfig) -> Result<Self> {
+        config.validate()?;
+
+        // The scheduler orders validators by peer id, the chain has to number certificate signers the same way
+        let mut keys = (0..config.nodes)
+            .map(|_| -> Result<(PeerId, BLSPrivateKey)> {
+                Ok((Keypair::generate_ed25519().public().to_peer_id(), BLSPrivateKey::generate()?))
+            })
+            .collect::<Result<Vec<(PeerId, BLSPrivateKey)>>>()?;
+        keys.sort_by_key(|(peer_id, _)| *peer_id);
+        let validators: Vec<ValidatorInfo> = keys.iter().map(|(peer_id, signing_key)| {
+            let address = Blake2bHash::from_data(&peer_id.to_bytes());
+            ValidatorInfo {
+                address,
+                signing_key: signing_key.public_key().to_bytes().to_vec(),
+                voting_key: vec![],
+                reward_address: address,
+                signal_data: Some(peer_id.to_bytes()),
+                inactive_from: None,
+                jailed_from: None,
+                stake: 0,
+            }
+        }).collect();
+
+        let mut nodes = Vec::with_capacity(config.nodes);
+        for (index, (peer_id, signing_key)) in keys.into_iter().enumerate() {
+            let (name, country) = OPERATORS[index % OPERATORS.len()];
+            let operator = match index / OPERATORS.len() {
+                0 => NetworkId::new(name, country),
+                round => NetworkId::new(&format!("{}-{}", name, round + 1), country),
+            };
+            let mut scheduler = BlockProductionScheduler::new(peer_id).with_signing_key(signing_key);
+            scheduler.rotate(&validators);
+            for validator in &validators {
+                if let Some(validator_peer_id) = validator_peer_id(validator) {
+                    scheduler.set_signing_key(validator_peer_id, validator.signing_key.clone());
+                }
+            }
+            let account_key = BLSPrivateKey::generate()?;
+            nodes.push(SimNode {
+                operator,
+                peer_id,
+                account: account_address(&account_key.public_key()),
+                account_key,
+                next_nonce: 0,
+                blockchain: SPCDRBlockchain::new(Arc::new(SimpleChainStore::new()), validators.clone()),
+                scheduler,
+                mempool: Vec::new(),
+                ahead: BTreeMap::new(),
+                voting_on: None,
+                early_votes: Vec::new(),
+            });
+        }
+
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let edges = config.graph.edges(config.nodes, &mut rng);
+        Ok(Self {
+            transport: MockTransport::new(config.latency_ms, config.seed.wrapping_add(1)),
+            config,
+            nodes,
+            edges,
+            rng,
+            started: Instant::now(),
+            now_ms: 0,
+            charges: HashMap::new(),
+            period_obligations: BTreeMap::new(),
+            netted_periods: BTreeSet::new(),
+            expected: Vec::new(),
+            gross_total: 0,
+            net_total: 0,
+            proposed_at: HashMap::new(),
+            latencies: Vec::new(),
+            cdr_records: 0,
+            rejected: 0,
+        })
+    }
+
+    /// Run until the settlements of every period with traffic are final on all nodes
+    pub async fn run(mut self) -> Result<SimulationReport> {
+        info!("🧪 Simulating {} nodes, {} CDRs/s over a {} graph of {} roaming pairs for {} blocks",
+              self.config.nodes, self.config.tps, self.config.graph, self.edges.len(), self.config.blocks);
+
+        loop {
+            let height = self.height().await;
+            let traffic_included = height >= self.config.blocks && self.nodes.iter().all(|node| node.mempool.is_empty());
+            let periods_netted = self.period_obligations.keys().all(|period| self.netted_periods.contains(period));
+            if traffic_included && periods_netted && self.settled().await? {
+                break;
+            }
+            if Policy::is_election_block(height + 1) {
+                warn!("Simulation stopped at block {} before the first election, settlements are not all final", height);
+                break;
+            }
+
+            if height < self.config.blocks {
+                self.generate_traffic()?;
+            }
+            for index in 0..self.nodes.len() {
+                if let Err(e) = self.produce(index).await {
+                    warn!("Node {} failed to produce block {}: {}", self.nodes[index].operator, height + 1, e);
+                }
+            }
+            let tick_end = self.now_ms + Policy::BLOCK_TIME;
+            self.deliver_until(tick_end).await;
+            self.now_ms = tick_end;
+        }
+
+        // Let votes and blocks still in flight arrive, so every node ends on the same head
+        self.deliver_until(u64::MAX).await;
+        self.report().await
+    }
+
+    fn instant(&self) -> Instant {
+        self.started + Duration::from_millis(self.now_ms)
+    }
+
+    async fn height(&self) -> u32 {
+        let mut height = 0;
+        for node in &self.nodes {
+            height = height.max(node.head_number().await);
+        }
+        height
+    }
+
+    /// Whether every node finalized all the settlements the netted periods call for
+    async fn settled(&self) -> Result<bool> {
+        for node in &self.nodes {
+            if node.blockchain.finalized_settlements().await?.len() < self.expected.len() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn submit(&mut self, index: usize, transaction: Transaction) {
+        self.nodes[index].mempool.push(transaction.clone());
+        self.transport.broadcast(self.now_ms, index, self.nodes.len(), SimMessage::Transaction(transaction));
+    }
+
+    /// CDR records of one block time, each recorded by the visited operator of a roaming pair
+    /// and billed to the home operator
+    fn generate_traffic(&mut self) -> Result<()> {
+        let records = self.config.tps as u64 * Policy::BLOCK_TIME / 1_000;
+        for _ in 0..records {
+            let Some(&(a, b)) = self.edges.choose(&mut self.rng) else {
+                return Ok(());
+            };
+            let (home, visited) = if self.rng.gen::<bool>() { (a, b) } else { (b, a) };
+            let record_type = [CDRType::VoiceCall, CDRType::DataSession, CDRType::SMS, CDRType::Roaming]
+                .choose(&mut self.rng)
+                .cloned()
+                .unwrap_or(CDRType::Roaming);
+            let mut encrypted_data = vec![0u8; 128];
+            self.rng.fill(&mut encrypted_data[..]);
+            let charge = self.rng.gen_range(1..=500u64);
+
+            let data = TransactionData::CDRRecord(CDRTransaction {
+                record_type,
+                home_network: self.nodes[home].operator.to_string(),
+                visited_network: self.nodes[visited].operator.to_string(),
+                encrypted_data,
+                zk_proof: vec![],
+            });
+            let recipient = self.nodes[home].account;
+            let transaction = self.nodes[visited].transaction(recipient, 0, data)?;
+            self.charges.insert(transaction.hash(), (home, visited, charge));
+            self.submit(visited, transaction);
+        }
+        Ok(())
+    }
+
+    /// Production tick of a node. Simulated nodes seal a block every block time, empty or not,
+    /// so periods close on schedule once the traffic stops
+    async fn produce(&mut self, index: usize) -> Result<()> {
+        let now = self.instant();
+        let node = &mut self.nodes[index];
+        let block_number = node.head_number().await + 1;
+        match node.scheduler.next_step(block_number, false, now) {
+            ProductionStep::Idle => Ok(()),
+            // Micro blocks of an epoch are sealed by the proposer of its first round
+            ProductionStep::Micro if node.scheduler.proposer(block_number, 0) != node.peer_id => Ok(()),
+            ProductionStep::Micro => {
+                let transactions = node.select_transactions();
+                let block = node.blockchain.produce_block(transactions).await?;
+                node.prune_mempool();
+                self.record_included(&block);
+                self.transport.broadcast(self.now_ms, index, self.nodes.len(), SimMessage::MicroBlock(block));
+                self.drain_ahead(index).await;
+                Ok(())
+            }
+            ProductionStep::ProposeMacro { round, validators } => {
+                let block = node.blockchain.propose_macro_block(vec![], round, validators).await?;
+                let proposer = node.peer_id;
+                debug!("Node {} proposes macro block {} round {}", node.operator, block_number, round);
+                self.proposed_at.insert((block_number, round), self.now_ms);
+                self.transport.broadcast(self.now_ms, index, self.nodes.len(), SimMessage::Proposal { block: block.clone(), proposer });
+                self.vote_for(index, block, proposer).await
+            }
+        }
+    }
+
+    /// Account the charges of the CDR records a sealed micro block included to its period
+    fn record_included(&mut self, block: &Block) {
+        let period = (block.block_number() - 1) / Policy::EPOCH_LENGTH;
+        for transaction in block.transactions() {
+            if let Some(&(home, visited, charge)) = self.charges.get(&transaction.hash()) {
+                *self.period_obligations.entry(period).or_default().entry((home, visited)).or_default() += charge;
+                self.cdr_records += 1;
+            }
+        }
+    }
+
+    async fn deliver_until(&mut self, until_ms: u64) {
+        while let Some((at, to, message)) = self.transport.next_before(until_ms) {
+            self.now_ms = at;
+            if let Err(e) = self.handle(to, message).await {
+                warn!("Node {} rejected a message: {}", self.nodes[to].operator, e);
+                self.rejected += 1;
+            }
+            self.drain_ahead(to).await;
+        }
+    }
+
+    /// Handle the messages that waited for the block the head reached
+    async fn drain_ahead(&mut self, index: usize) {
+        loop {
+            let next = self.nodes[index].head_number().await + 1;
+            let Some(ready) = self.nodes[index].ahead.remove(&next) else {
+                return;
+            };
+            for message in ready {
+                if let Err(e) = self.handle(index, message).await {
+                    warn!("Node {} rejected a message: {}", self.nodes[index].operator, e);
+                    self.rejected += 1;
+                }
+            }
+        }
+    }
+
+    async fn handle(&mut self, index: usize, message: SimMessage) -> Result<()> {
+        let node = &mut self.nodes[index];
+        let head = node.head_number().await;
+        if let SimMessage::MicroBlock(block) | SimMessage::Proposal { block, .. } = &message {
+            let block_number = block.block_number();
+            if block_number <= head {
+                return Ok(());
+            }
+            if block_number > head + 1 {
+                node.ahead.entry(block_number).or_default().push(message);
+                return Ok(());
+            }
+        }
+
+        match message {
+            SimMessage::Transaction(transaction) => {
+                if transaction.nonce >= node.blockchain.account_nonce(&transaction.sender) {
+                    node.mempool.push(transaction);
+                }
+                Ok(())
+            }
+            SimMessage::MicroBlock(block) => {
+                node.blockchain.push_block(block).await?;
+                node.prune_mempool();
+                Ok(())
+            }
+            SimMessage::Proposal { block, proposer } => {
+                node.blockchain.check_proposal(&block).await?;
+                self.vote_for(index, block, proposer).await
+            }
+            SimMessage::Vote { voter, vote } => self.count_vote(index, voter, vote).await,
+        }
+    }
+
+    /// Prevote a checked proposal, then count the votes that arrived before it
+    async fn vote_for(&mut self, index: usize, block: Block, proposer: PeerId) -> Result<()> {
+        let now = self.instant();
+        let node = &mut self.nodes[index];
+        let hash = block.hash();
+        let prevote = node.scheduler.start_round(block, &proposer, now)?;
+        node.voting_on = Some(hash);
+        let early_votes = std::mem::take(&mut node.early_votes);
+
+        self.cast_vote(index, prevote).await?;
+        for (voter, vote) in early_votes.into_iter().filter(|(_, vote)| vote.proposal_hash == Some(hash)) {
+            self.count_vote(index, voter, vote).await?;
+        }
+        Ok(())
+    }
+
+    /// Broadcast a local vote and count it, precommitting once the prevotes reach the quorum
+    async fn cast_vote(&mut self, index: usize, vote: TendermintVote) -> Result<()> {
+        let peer_id = self.nodes[index].peer_id;
+        let mut vote = vote;
+        loop {
+            self.transport.broadcast(self.now_ms, index, self.nodes.len(), SimMessage::Vote { voter: peer_id, vote: vote.clone() });
+            match self.nodes[index].scheduler.handle_vote(peer_id, &vote) {
+                VoteOutcome::Pending => return Ok(()),
+                VoteOutcome::Precommit(precommit) => vote = precommit,
+                VoteOutcome::Finalized { block, certificate } => return self.finalize(index, block, certificate).await,
+            }
+        }
+    }
+
+    async fn count_vote(&mut self, index: usize, voter: PeerId, vote: TendermintVote) -> Result<()> {
+        let node = &mut self.nodes[index];
+        if node.voting_on.is_none() || vote.proposal_hash != node.voting_on {
+            node.early_votes.push((voter, vote));
+            return Ok(());
+        }
+        match node.scheduler.handle_vote(voter, &vote) {
+            VoteOutcome::Pending => Ok(()),
+            VoteOutcome::Precommit(precommit) => self.cast_vote(index, precommit).await,
+            VoteOutcome::Finalized { block, certificate } => self.finalize(index, block, certificate).await,
+        }
+    }
+
+    /// Push a finalized macro block with its certificate and settle the period it closes
+    async fn finalize(&mut self, index: usize, block: Block, certificate: Option<MacroCertificate>) -> Result<()> {
+        let Block::Macro(mut macro_block) = block else {
+            return Err(BlockchainError::InvalidState("Finalized block is not a macro block".to_string()));
+        };
+        let (block_number, round) = (macro_block.header.block_number, macro_block.header.round);
+        macro_block.justification = certificate.clone();
+
+        let node = &mut self.nodes[index];
+        node.voting_on = None;
+        node.blockchain.push_block(Block::Macro(macro_block)).await?;
+        if let Some(certificate) = &certificate {
+            node.blockchain.put_macro_certificate(block_number, certificate).await?;
+        }
+        node.prune_mempool();
+        if let Some(proposed_at) = self.proposed_at.get(&(block_number, round)) {
+            self.latencies.push(self.now_ms - proposed_at);
+        }
+        debug!("Node {} finalized macro block {} round {}", node.operator, block_number, round);
+
+        self.settle_period(block_number / Policy::EPOCH_LENGTH - 1)
+    }
+
+    /// Net the obligations of a closed period and have each remaining debtor submit its settlement
+    fn settle_period(&mut self, period: u32) -> Result<()> {
+        if !self.netted_periods.insert(period) {
+            return Ok(());
+        }
+        let Some(obligations) = self.period_obligations.get(&period) else {
+            return Ok(());
+        };
+        let obligations: Vec<(NetworkId, NetworkId, u64)> = obligations.iter()
+            .map(|((home, visited), amount)| (self.nodes[*home].operator.clone(), self.nodes[*visited].operator.clone(), *amount))
+            .collect();
+        let netting = MultilateralNettingSolver::default().solve(&obligations)?;
+        self.gross_total += netting.gross_total;
+        self.net_total += netting.residual_total;
+        info!("🧮 Period {} netted: {} gross, {} to settle ({}% saved)",
+              period_name(period), netting.gross_total, netting.residual_total, netting.savings_percentage());
+
+        for (debtor, creditor, amount) in netting.residual_obligations {
+            let operator_index = |operator: &NetworkId| self.nodes.iter().position(|node| node.operator == *operator)
+                .ok_or_else(|| BlockchainError::NotFound(format!("Operator {} is not simulated", operator)));
+            let (debtor_index, creditor_index) = (operator_index(&debtor)?, operator_index(&creditor)?);
+            let settlement = SettlementTransaction {
+                creditor_network: creditor.to_string(),
+                debtor_network: debtor.to_string(),
+                amount,
+                currency: SETTLEMENT_CURRENCY.to_string(),
+                period: period_name(period),
+                breakdown: Default::default(),
+                batch_ids: vec![],
+            };
+            self.expected.push(settlement.clone());
+            let recipient = self.nodes[creditor_index].account;
+            let transaction = self.nodes[debtor_index].transaction(recipient, amount, TransactionData::Settlement(settlement))?;
+            self.submit(debtor_index, transaction);
+        }
+        Ok(())
+    }
+
+    async fn report(&self) -> Result<SimulationReport> {
+        let mut settlement_errors = Vec::new();
+        let mut settlements_finalized = self.expected.len();
+        for node in &self.nodes {
+            let settlements = node.blockchain.finalized_settlements().await?;
+            let mut found = 0;
+            for expected in &self.expected {
+                match settlements.iter().filter(|settlement| same_settlement(settlement, expected)).count() {
+                    0 => settlement_errors.push(format!(
+                        "{}: {} owing {} {} {} for {} is not final",
+                        node.operator, expected.debtor_network, expected.creditor_network, expected.amount, expected.currency, expected.period
+                    )),
+                    1 => found += 1,
+                    count => settlement_errors.push(format!(
+                        "{}: {} owing {} for {} is settled {} times",
+                        node.operator, expected.debtor_network, expected.creditor_network, expected.period, count
+                    )),
+                }
+            }
+            settlements_finalized = settlements_finalized.min(found);
+            for settlement in settlements.iter().filter(|settlement| !self.expected.iter().any(|expected| same_settlement(settlement, expected))) {
+                settlement_errors.push(format!(
+                    "{}: unexpected settlement of {} owing {} {} for {}",
+                    node.operator, settlement.debtor_network, settlement.creditor_network, settlement.amount, settlement.period
+                ));
+            }
+
+            // Netting may reroute what is owed but every operator has to end up where the traffic put it
+            for (period, obligations) in self.period_obligations.iter().filter(|(period, _)| self.netted_periods.contains(period)) {
+                let owed = net_positions(obligations.iter().map(|((home, visited), amount)| {
+                    (self.nodes[*home].operator.to_string(), self.nodes[*visited].operator.to_string(), *amount)
+                }));
+                let settled = net_positions(settlements.iter()
+                    .filter(|settlement| settlement.period == period_name(*period))
+                    .map(|settlement| (settlement.debtor_network.clone(), settlement.creditor_network.clone(), settlement.amount)));
+                if owed != settled {
+                    settlement_errors.push(format!(
+                        "{}: settlements of {} do not net out as the traffic does", node.operator, period_name(*period)
+                    ));
+                }
+            }
+        }
+
+        let reference = &self.nodes[0].blockchain;
+        let (head_hash, state_root) = (reference.head_async().await.hash(), reference.state_root());
+        let mut nodes_agree = true;
+        for node in &self.nodes[1..] {
+            nodes_agree &= node.blockchain.head_async().await.hash() == head_hash && node.blockchain.state_root() == state_root;
+        }
+
+        Ok(SimulationReport {
+            nodes: self.nodes.len(),
+            graph: self.config.graph.to_string(),
+            seed: self.config.seed,
+            height: self.height().await,
+            simulated_seconds: self.now_ms / 1_000,
+            cdr_records: self.cdr_records,
+            messages: self.transport.sent,
+            rejected_messages: self.rejected,
+            settlements_expected: self.expected.len(),
+            settlements_finalized,
+            settlement_errors,
+            nodes_agree,
+            gross_total: self.gross_total,
+            net_total: self.net_total,
+            netting_savings_percentage: match self.gross_total {
+                0 => 0,
+                gross => ((gross - self.net_total) as u128 * 100 / gross as u128) as u32,
+            },
+            finalizations: self.latencies.len(),
+            consensus_latency_avg_ms: match self.latencies.len() {
+                0 => 0,
+                count => self.latencies.iter().sum::<u64>() / count as u64,
+            },
+            consensus_latency_max_ms: self.latencies.iter().copied().max().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_graphs() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!("random:2".parse::<OperatorGraph>().unwrap(), OperatorGraph::Random { degree: 2 });
+        assert!("random:0".parse::<OperatorGraph>().is_err());
+        assert!("tree".parse::<OperatorGraph>().is_err());
+
+        assert_eq!(OperatorGraph::Mesh.edges(4, &mut rng).len(), 6);
+        assert_eq!(OperatorGraph::Ring.edges(4, &mut rng), vec![(0, 1), (0, 3), (1, 2), (2, 3)]);
+        assert_eq!(OperatorGraph::Ring.edges(2, &mut rng), vec![(0, 1)]);
+        assert_eq!(OperatorGraph::Star.edges(3, &mut rng), vec![(0, 1), (0, 2)]);
+        let random = OperatorGraph::Random { degree: 1 }.edges(5, &mut rng);
+        assert!(!random.is_empty() && random.iter().all(|(a, b)| a < b && *b < 5));
+    }
+
+    #[tokio::test]
+    async fn test_simulation_settles_traffic() {
+        let config = SimulationConfig { nodes: 3, tps: 4, blocks: 40, latency_ms: 80, seed: 11, ..Default::default() };
+        let report = Simulation::new(config).unwrap().run().await.unwrap();
+
+        assert!(report.is_correct(), "{:?}", report.settlement_errors);
+        assert_eq!(report.rejected_messages, 0);
+        assert!(report.cdr_records > 0 && report.settlements_expected > 0);
+        assert!(report.net_total <= report.gross_total);
+        // Two periods closed, each finalized by all three nodes
+        assert!(report.finalizations >= 6);
+        assert!(report.consensus_latency_max_ms >= 80);
+
+        assert!(Simulation::new(SimulationConfig { blocks: MAX_SIMULATED_BLOCKS + 1, ..Default::default() }).is_err());
+    }
+}
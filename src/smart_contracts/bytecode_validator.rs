@@ -0,0 +1,308 @@
+// Bytecode validation: contract code is checked once, when it is deployed or upgraded to, so code
+// that jumps out of bounds, overflows the stack, can never halt or uses instructions its contract
+// class may not is rejected before any validator executes it
+use serde::{Deserialize, Serialize};
+use crate::primitives::{BlockchainError, Result};
+use super::events::MAX_EVENT_TOPICS;
+use super::vm::{Instruction, FIELD_ELEMENT_SIZE, GROTH16_PROOF_SIZE, MEMORY_LIMIT, STACK_LIMIT};
+
+/// Instructions contract code holds at most
+pub const MAX_CODE_LENGTH: usize = 24_576;
+
+/// Issues a rejection lists at most
+const MAX_REPORTED_ISSUES: usize = 8;
+
+/// What a contract is deployed for, bounding the instructions its code may use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractClass {
+    /// Any instruction the VM supports
+    #[default]
+    General,
+    /// Settlement logic, which must be fully analysable: proofs and signatures are verified from
+    /// memory, not from a variable number of stack operands
+    Settlement,
+    /// Reads state without changing it: no stores, events or calls
+    View,
+}
+
+impl ContractClass {
+    /// Whether code of this class may use `instruction`
+    pub fn allows(&self, instruction: &Instruction) -> bool {
+        match self {
+            Self::General => true,
+            Self::Settlement => !matches!(instruction, Instruction::VerifyProof | Instruction::CheckSignature),
+            Self::View => !matches!(instruction, Instruction::Store(_) | Instruction::Emit { .. } | Instruction::Call(_)),
+        }
+    }
+}
+
+impl std::fmt::Display for ContractClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::General => write!(f, "general"),
+            Self::Settlement => write!(f, "settlement"),
+            Self::View => write!(f, "view"),
+        }
+    }
+}
+
+/// Reject `code` for a contract of `class` unless it passes every check, listing what failed
+pub fn validate_bytecode(code: &[Instruction], class: ContractClass) -> Result<()> {
+    let issues = bytecode_issues(code, class);
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = issues.iter().take(MAX_REPORTED_ISSUES).cloned().collect::<Vec<_>>().join("; ");
+    if issues.len() > MAX_REPORTED_ISSUES {
+        message.push_str(&format!("; and {} more", issues.len() - MAX_REPORTED_ISSUES));
+    }
+    Err(BlockchainError::InvalidTransaction(format!("Bytecode rejected: {}", message)))
+}
+
+/// Everything wrong with `code` for a contract of `class`
+pub fn bytecode_issues(code: &[Instruction], class: ContractClass) -> Vec<String> {
+    if code.is_empty() {
+        return vec!["no instructions".to_string()];
+    }
+    if code.len() > MAX_CODE_LENGTH {
+        return vec![format!("{} instructions, at most {} allowed", code.len(), MAX_CODE_LENGTH)];
+    }
+
+    let mut issues: Vec<String> = code.iter().enumerate()
+        .filter_map(|(index, instruction)| instruction_issue(instruction, code.len(), class)
+            .map(|issue| format!("instruction {} ({}): {}", index, name(instruction), issue)))
+        .collect();
+
+    // Control flow is only followed once every jump lands inside the code
+    if issues.is_empty() {
+        issues.extend(stack_issues(code));
+        issues.extend(halt_issues(code));
+    }
+    issues
+}
+
+fn instruction_issue(instruction: &Instruction, code_len: usize, class: ContractClass) -> Option<String> {
+    match instruction {
+        Instruction::ValidateNetwork | Instruction::GetBalance | Instruction::Transfer(..) => {
+            return Some("not supported by the VM".to_string());
+        }
+        // Jumping to the end stops execution like falling off it
+        Instruction::Jump(target) | Instruction::JumpIf(target) if *target > code_len => {
+            return Some(format!("jumps to {} past the end of {} instructions", target, code_len));
+        }
+        Instruction::Emit { topics } if topics.len() > MAX_EVENT_TOPICS => {
+            return Some(format!("{} topics, at most {} allowed", topics.len(), MAX_EVENT_TOPICS));
+        }
+        Instruction::VerifyProofAt { offset, len, .. } => {
+            if *len < GROTH16_PROOF_SIZE || (len - GROTH16_PROOF_SIZE) % FIELD_ELEMENT_SIZE != 0 {
+                return Some(format!(
+                    "{} bytes is not a {} byte proof followed by {} byte inputs", len, GROTH16_PROOF_SIZE, FIELD_ELEMENT_SIZE
+                ));
+            }
+            if offset.checked_add(*len).map_or(true, |end| end > MEMORY_LIMIT) {
+                return Some(format!("proof past the {} byte memory limit", MEMORY_LIMIT));
+            }
+        }
+        _ => {}
+    }
+
+    if !class.allows(instruction) {
+        return Some(format!("not allowed in {} contracts", class));
+    }
+    None
+}
+
+/// Stack height relative to the entry, where the call's input bytes are already on the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Height {
+    Known(i64),
+    /// Past an instruction popping a number of values given by an operand
+    Unknown,
+}
+
+/// Follow every path from the entry, checking the stack stays within bounds and has the same
+/// height whichever way an instruction is reached, so no loop can grow or drain it
+fn stack_issues(code: &[Instruction]) -> Vec<String> {
+    let limit = STACK_LIMIT as i64;
+    let mut issues = Vec::new();
+    let mut heights: Vec<Option<Height>> = vec![None; code.len() + 1];
+    heights[0] = Some(Height::Known(0));
+    let mut pending = vec![0];
+
+    while let Some(index) = pending.pop() {
+        let Some(instruction) = code.get(index) else { continue };
+        let after = match (heights[index].expect("queued with a height"), stack_effect(instruction)) {
+            (Height::Known(height), Some((pops, pushes))) => {
+                // Input fills the stack at most, so popping further always underflows
+                if height - pops < -limit {
+                    issues.push(format!("instruction {} ({}): pops more values than any input supplies", index, name(instruction)));
+                    continue;
+                }
+                let after = height - pops + pushes;
+                if after > limit {
+                    issues.push(format!("instruction {} ({}): pushes past the stack limit of {}", index, name(instruction), STACK_LIMIT));
+                    continue;
+                }
+                Height::Known(after)
+            }
+            _ => Height::Unknown,
+        };
+
+        for next in successors(instruction, index) {
+            match heights[next] {
+                None => {
+                    heights[next] = Some(after);
+                    pending.push(next);
+                }
+                Some(Height::Known(height)) if next < code.len() => {
+                    if let Height::Known(other) = after {
+                        if other != height {
+                            issues.push(format!(
+                                "instruction {} is reached with {} and with {} values on the stack",
+                                next, height, other
+                            ));
+                        }
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    issues
+}
+
+/// Check a halt can be reached from every reachable instruction, so the only loops are ones
+/// execution may leave
+fn halt_issues(code: &[Instruction]) -> Vec<String> {
+    let mut predecessors = vec![Vec::new(); code.len() + 1];
+    for (index, instruction) in code.iter().enumerate() {
+        for next in successors(instruction, index) {
+            predecessors[next].push(index);
+        }
+    }
+
+    let mut reachable = vec![false; code.len() + 1];
+    let mut pending = vec![0];
+    while let Some(index) = pending.pop() {
+        if !std::mem::replace(&mut reachable[index], true) && index < code.len() {
+            pending.extend(successors(&code[index], index));
+        }
+    }
+
+    // Walk back from every way of stopping: halting, returning and running off the end
+    let mut halts = vec![false; code.len() + 1];
+    let mut pending: Vec<usize> = code.iter().enumerate()
+        .filter(|(_, instruction)| matches!(instruction, Instruction::Halt | Instruction::Return))
+        .map(|(index, _)| index)
+        .chain([code.len()])
+        .collect();
+    while let Some(index) = pending.pop() {
+        if !std::mem::replace(&mut halts[index], true) {
+            pending.extend(&predecessors[index]);
+        }
+    }
+
+    // Only the first trapped instruction is reported, the rest lead to or lie in the same loops
+    (0..code.len())
+        .find(|index| reachable[*index] && !halts[*index])
+        .map(|index| vec![format!("no path from instruction {} halts, it can only run out of gas", index)])
+        .unwrap_or_default()
+}
+
+/// Values an instruction pops and pushes, `None` if that depends on operands
+fn stack_effect(instruction: &Instruction) -> Option<(i64, i64)> {
+    use Instruction::*;
+    Some(match instruction {
+        Push(_) | Load(_) | LookupRate { .. } | CallDataSize | ReturnDataSize
+            | GetTimestamp | GetCaller | VerifyProofAt { .. } => (0, 1),
+        Pop | Store(_) | JumpIf(_) => (1, 0),
+        Dup => (1, 2),
+        Swap => (2, 2),
+        Add | Sub | Mul | Div | Mod | Eq | Lt | Gt | CalculateSettlement => (2, 1),
+        CallDataLoad | MLoad => (1, 1),
+        MStore | Emit { .. } | Return => (2, 0),
+        CallDataCopy | ReturnDataCopy => (3, 0),
+        Call(_) => (3, 2),
+        Jump(_) | Log(_) | Halt => (0, 0),
+        VerifyProof | CheckSignature | ValidateNetwork | GetBalance | Transfer(..) => return None,
+    })
+}
+
+/// Instructions execution may continue at, `code.len()` meaning it stops
+fn successors(instruction: &Instruction, index: usize) -> Vec<usize> {
+    match instruction {
+        Instruction::Jump(target) => vec![*target],
+        Instruction::JumpIf(target) => vec![index + 1, *target],
+        Instruction::Halt | Instruction::Return => vec![],
+        _ => vec![index + 1],
+    }
+}
+
+/// Instruction name without its operands
+fn name(instruction: &Instruction) -> String {
+    let debug = format!("{:?}", instruction);
+    debug.split(['(', ' ']).next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Blake2bHash;
+    use crate::smart_contracts::contract_language::SettlementContractSource;
+
+    #[test]
+    fn test_bytecode_validation() {
+        let source = SettlementContractSource::parse(r#"
+            contract "T-Mobile-DE/Vodafone-UK 2024"
+            rate voice 12 per minute
+            threshold 10000
+            netting bilateral
+            dispute deviation 5%
+        "#).unwrap();
+        validate_bytecode(&source.compile(), ContractClass::Settlement).unwrap();
+        assert_eq!(bytecode_issues(&[], ContractClass::General), vec!["no instructions".to_string()]);
+
+        // Jumps past the end, unsupported instructions and classes' whitelists
+        let issues = bytecode_issues(&[
+            Instruction::Push(1),
+            Instruction::JumpIf(9),
+            Instruction::GetBalance,
+            Instruction::VerifyProof,
+            Instruction::Halt,
+        ], ContractClass::Settlement);
+        assert_eq!(issues, vec![
+            "instruction 1 (JumpIf): jumps to 9 past the end of 5 instructions".to_string(),
+            "instruction 2 (GetBalance): not supported by the VM".to_string(),
+            "instruction 3 (VerifyProof): not allowed in settlement contracts".to_string(),
+        ]);
+        let store = [Instruction::Push(1), Instruction::Store(Blake2bHash::zero()), Instruction::Halt];
+        assert!(validate_bytecode(&store, ContractClass::General).is_ok());
+        assert!(validate_bytecode(&store, ContractClass::View).is_err());
+
+        // A loop pushing each round grows the stack and never halts
+        let error = validate_bytecode(&[Instruction::Push(1), Instruction::Jump(0)], ContractClass::General).unwrap_err();
+        assert_eq!(error.to_string(), BlockchainError::InvalidTransaction(
+            "Bytecode rejected: instruction 0 is reached with 0 and with 1 values on the stack; \
+             no path from instruction 0 halts, it can only run out of gas".to_string()
+        ).to_string());
+
+        // A counting loop keeps its height and may exit
+        let counter = Blake2bHash::from_bytes([1; 32]);
+        assert!(validate_bytecode(&[
+            Instruction::Load(counter),
+            Instruction::Push(1),
+            Instruction::Add,
+            Instruction::Dup,
+            Instruction::Store(counter),
+            Instruction::Push(10),
+            Instruction::Lt,
+            Instruction::JumpIf(0),
+        ], ContractClass::General).is_ok());
+
+        let mut overflow = vec![Instruction::Push(0); STACK_LIMIT + 1];
+        overflow.push(Instruction::Halt);
+        assert_eq!(bytecode_issues(&overflow, ContractClass::General).len(), 1);
+    }
+}
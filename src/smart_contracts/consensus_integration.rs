@@ -1,11 +1,26 @@
 // Smart contract integration with blockchain consensus
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedRwLockReadGuard, RwLock};
 use crate::primitives::{Result, BlockchainError, Blake2bHash};
 use crate::blockchain::{Transaction, Block};
 use crate::common::AbstractBlockchain;
 use super::vm::{ContractVM, ExecutionContext, ExecutionResult, ContractStorage, Instruction};
 use super::crypto_verifier::ContractCryptoVerifier;
+use super::events::ContractEvent;
+use super::bytecode_validator::{ContractClass, validate_bytecode};
+use super::state_expiry::{expiry_cutoff, StateResurrection};
+use super::parallel::{conflicting_groups, group_by_contract, GroupRun, OverlayStorage};
+use crate::crypto::BLSPublicKey;
+use crate::zkp::trusted_setup::TrustedSetupCeremony;
+use crate::blockchain::tariff::{RateTable, SignedRateTable, rate_table_key, tariff_registry_address};
+use crate::blockchain::NetworkJoinTransaction;
+use crate::blockchain::block::SettlementTransaction;
+use super::settlement_contract::{
+    EscrowLock, EscrowTransaction, PaymentConfirmation, SettlementEscrow, escrow_key, locked_escrows_key, settlement_escrow_address,
+};
+use crate::blockchain::operator_registry::{
+    OperatorRecord, OperatorRegistration, operator_key, operator_members_key, operator_registry_address, plmn_key,
+};
 
 /// Contract transaction execution within blockchain consensus
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -27,6 +42,76 @@ pub struct ContractDeployment {
     pub gas_limit: u64,
     pub value: u64,
     pub nonce: u64,
+    /// Operators who must both sign upgrades, empty for contracts that cannot be upgraded
+    #[serde(default)]
+    pub counterparts: Vec<String>,
+    /// Bounds the instructions the code and its upgrades may use
+    #[serde(default)]
+    pub class: ContractClass,
+}
+
+/// One code version of a deployed contract
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ContractVersion {
+    pub version: u32,
+    pub code_hash: Blake2bHash,
+    /// First block executed with this version
+    pub activation_block: u32,
+}
+
+/// Upgrade authority and code history of a contract, kept in contract storage
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ContractRegistryEntry {
+    pub counterparts: Vec<String>,
+    pub versions: Vec<ContractVersion>,
+    #[serde(default)]
+    pub class: ContractClass,
+}
+
+/// Replaces the code of a deployed contract, signed by all of its counterpart operators
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ContractUpgrade {
+    pub contract_address: Blake2bHash,
+    /// Must follow the contract's current version
+    pub version: u32,
+    pub bytecode: Vec<Instruction>,
+    /// BLS signatures over `signing_payload` by operator name
+    pub signatures: Vec<(String, Vec<u8>)>,
+}
+
+impl ContractUpgrade {
+    pub fn code_hash(&self) -> Blake2bHash {
+        code_hash(&self.bytecode)
+    }
+
+    /// Bytes each counterpart operator signs
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut payload = b"sp-cdr-contract-upgrade".to_vec();
+        payload.extend_from_slice(self.contract_address.as_bytes());
+        payload.extend_from_slice(&self.version.to_be_bytes());
+        payload.extend_from_slice(self.code_hash().as_bytes());
+        payload
+    }
+}
+
+/// Hash of contract code over its canonical encoding
+pub fn code_hash(bytecode: &[Instruction]) -> Blake2bHash {
+    crate::primitives::hash_canonical(bytecode)
+}
+
+/// Address the code of one contract version is retained under
+pub fn versioned_code_address(contract: &Blake2bHash, version: u32) -> Blake2bHash {
+    let mut data = b"contract-code-version".to_vec();
+    data.extend_from_slice(contract.as_bytes());
+    data.extend_from_slice(&version.to_be_bytes());
+    crate::primitives::primitives::hash_data(&data)
+}
+
+/// Storage address of a contract's registry entry, no code runs under it
+fn registry_address(contract: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"contract-registry".to_vec();
+    data.extend_from_slice(contract.as_bytes());
+    crate::primitives::primitives::hash_data(&data)
 }
 
 /// Contract execution receipt
@@ -38,6 +123,9 @@ pub struct ContractReceipt {
     pub gas_used: u64,
     pub return_value: Option<u64>,
     pub logs: Vec<String>,
+    /// Events of the execution, empty if it failed
+    #[serde(default)]
+    pub events: Vec<ContractEvent>,
     pub error: Option<String>,
     pub block_number: u32,
     pub transaction_index: u32,
@@ -54,7 +142,7 @@ pub struct ConsensusContractEngine<S: ContractStorage + Send + Sync + 'static> {
 impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
     pub fn new(storage: S, crypto_verifier: ContractCryptoVerifier) -> Self {
         Self {
-            vm: Arc::new(RwLock::new(ContractVM::new(storage))),
+            vm: Arc::new(RwLock::new(ContractVM::new_with_crypto(storage, crypto_verifier.clone()))),
             crypto_verifier: Arc::new(RwLock::new(crypto_verifier)),
             pending_transactions: Arc::new(RwLock::new(Vec::new())),
             receipts: Arc::new(RwLock::new(Vec::new())),
@@ -67,6 +155,8 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
         deployment: ContractDeployment,
         block_number: u32,
     ) -> Result<(Blake2bHash, ContractReceipt)> {
+        validate_bytecode(&deployment.bytecode, deployment.class)?;
+
         // Generate contract address from deployer + nonce
         let contract_address = self.generate_contract_address(&deployment.deployer, deployment.nonce);
 
@@ -80,10 +170,20 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
             value: deployment.value,
         };
 
-        // Deploy contract to VM
+        // Deploy contract to VM, retaining the code as version 1
         {
             let mut vm = self.vm.write().await;
             vm.deploy_contract(contract_address, deployment.bytecode.clone())?;
+            vm.deploy_contract(versioned_code_address(&contract_address, 1), deployment.bytecode.clone())?;
+            Self::put_registry_entry(&mut vm, &contract_address, &ContractRegistryEntry {
+                counterparts: deployment.counterparts.clone(),
+                versions: vec![ContractVersion {
+                    version: 1,
+                    code_hash: code_hash(&deployment.bytecode),
+                    activation_block: block_number,
+                }],
+                class: deployment.class,
+            })?;
         }
 
         // Execute constructor if provided
@@ -96,7 +196,9 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
                 success: true,
                 return_value: None,
                 gas_used: 100, // Base deployment cost
+                return_data: vec![],
                 logs: vec!["Contract deployed".to_string()],
+                events: vec![],
                 error: None,
             }
         };
@@ -109,6 +211,7 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
             gas_used: execution_result.gas_used,
             return_value: execution_result.return_value,
             logs: execution_result.logs,
+            events: execution_result.events,
             error: execution_result.error,
             block_number,
             transaction_index: 0, // Would be set by block producer
@@ -130,36 +233,435 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
         block_number: u32,
         transaction_index: u32,
     ) -> Result<ContractReceipt> {
-        let context = ExecutionContext {
+        let timestamp = self.get_current_timestamp().await?;
+        self.execute_block_transaction(transaction, block_number, timestamp, transaction_index).await
+    }
+
+    /// Execute a contract transaction included in a block
+    /// Contracts see the block timestamp, so every validator executing the block gets the same result
+    /// A failing transaction reverts its storage writes and yields a failed receipt instead of an error
+    pub async fn execute_block_transaction(
+        &self,
+        transaction: ContractTransaction,
+        block_number: u32,
+        block_timestamp: u64,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let receipt = {
+            let mut vm = self.vm.write().await;
+            self.execute_on(&mut vm, &transaction, block_number, block_timestamp, transaction_index)?
+        };
+
+        // Store receipt
+        {
+            let mut receipts = self.receipts.write().await;
+            receipts.push(receipt.clone());
+        }
+
+        Ok(receipt)
+    }
+
+    /// Execute a run of contract transactions from one block, groups calling different contracts in
+    /// parallel worker tasks, see `parallel`. Groups that touched state another group wrote execute
+    /// again serially in block order. Receipts come back in block order either way
+    pub async fn execute_block_transactions(
+        &self,
+        transactions: Vec<(u32, ContractTransaction)>,
+        block_number: u32,
+        block_timestamp: u64,
+    ) -> Result<Vec<ContractReceipt>> {
+        let groups = group_by_contract(transactions);
+        let receipts = match groups.len() {
+            0 | 1 => self.execute_serially(groups.into_iter().flatten().collect(), block_number, block_timestamp).await?,
+            _ => self.execute_in_parallel(groups, block_number, block_timestamp).await?,
+        };
+
+        {
+            let mut stored = self.receipts.write().await;
+            stored.extend(receipts.iter().cloned());
+        }
+
+        Ok(receipts)
+    }
+
+    async fn execute_serially(
+        &self,
+        transactions: Vec<(u32, ContractTransaction)>,
+        block_number: u32,
+        block_timestamp: u64,
+    ) -> Result<Vec<ContractReceipt>> {
+        let mut vm = self.vm.write().await;
+        transactions.iter()
+            .map(|(index, transaction)| self.execute_on(&mut vm, transaction, block_number, block_timestamp, *index))
+            .collect()
+    }
+
+    async fn execute_in_parallel(
+        &self,
+        groups: Vec<Vec<(u32, ContractTransaction)>>,
+        block_number: u32,
+        block_timestamp: u64,
+    ) -> Result<Vec<ContractReceipt>> {
+        // Workers share the storage as it was before the run, each with a VM of its own
+        let base = Arc::new(self.vm.clone().read_owned().await);
+        let mut workers = Vec::with_capacity(groups.len());
+        for group in groups {
+            workers.push(Self::spawn_group(&base, group, block_number, block_timestamp)?);
+        }
+        let mut runs = Vec::with_capacity(workers.len());
+        for worker in workers {
+            runs.push(Self::join_group(worker).await?);
+        }
+
+        // Grouping is by contract but conflicts are per slot, so only the groups that conflict run
+        // again, merged into one group in block order against the same storage before the run
+        let conflicts = conflicting_groups(&runs.iter().map(|(_, _, access)| access).collect::<Vec<_>>());
+        if !conflicts.is_empty() {
+            tracing::debug!("{} contract groups of block {} touch shared state, executing them serially", conflicts.len(), block_number);
+            let (conflicting, kept): (Vec<_>, Vec<_>) = runs.into_iter().enumerate()
+                .partition(|(i, _)| conflicts.contains(i));
+            runs = kept.into_iter().map(|(_, run)| run).collect();
+            let mut transactions: Vec<(u32, ContractTransaction)> = conflicting.into_iter()
+                .flat_map(|(_, (group, _, _))| group)
+                .collect();
+            transactions.sort_by_key(|(index, _)| *index);
+            runs.push(Self::join_group(Self::spawn_group(&base, transactions, block_number, block_timestamp)?).await?);
+
+            // Run serially the conflicting transactions may take other branches and reach state a kept
+            // group wrote, then only executing the whole run serially gives the serial result
+            if !conflicting_groups(&runs.iter().map(|(_, _, access)| access).collect::<Vec<_>>()).is_empty() {
+                drop(base);
+                let mut transactions: Vec<(u32, ContractTransaction)> = runs.into_iter().flat_map(|(group, _, _)| group).collect();
+                transactions.sort_by_key(|(index, _)| *index);
+                return self.execute_serially(transactions, block_number, block_timestamp).await;
+            }
+        }
+        drop(base);
+
+        // No group sees another's writes, so merging them in block order leaves the serial state
+        let mut vm = self.vm.write().await;
+        let mut receipts = Vec::new();
+        for (group, results, (_, writes)) in runs {
+            for ((contract, key), value) in writes {
+                vm.storage_mut().set(&contract, &key, value)?;
+            }
+            receipts.extend(group.iter().zip(results)
+                .map(|((index, transaction), result)| self.receipt(transaction, result, block_number, *index)));
+        }
+        receipts.sort_by_key(|receipt| receipt.transaction_index);
+        Ok(receipts)
+    }
+
+    /// Execute a group in a worker task on an overlay of `base`, with the engine VM's crypto verifier
+    fn spawn_group(
+        base: &Arc<OwnedRwLockReadGuard<ContractVM<S>>>,
+        group: Vec<(u32, ContractTransaction)>,
+        block_number: u32,
+        block_timestamp: u64,
+    ) -> Result<tokio::task::JoinHandle<GroupRun>> {
+        let code = group.iter()
+            .map(|(_, transaction)| Self::code_at(base, &transaction.contract_address, block_number))
+            .collect::<Result<Vec<_>>>()?;
+        let crypto_verifier = base.crypto_verifier().clone();
+        let base = base.clone();
+        Ok(tokio::task::spawn_blocking(move || {
+            let mut vm = ContractVM::new_with_crypto(OverlayStorage::new(base), crypto_verifier);
+            let results: Vec<ExecutionResult> = group.iter().zip(code)
+                .map(|((_, transaction), code)| run_transaction(&mut vm, transaction, code, block_timestamp))
+                .collect();
+            (group, results, vm.into_storage().into_accesses())
+        }))
+    }
+
+    async fn join_group(worker: tokio::task::JoinHandle<GroupRun>) -> Result<GroupRun> {
+        worker.await
+            .map_err(|e| BlockchainError::InvalidOperation(format!("Contract execution worker failed: {}", e)))
+    }
+
+    /// Execute a transaction on the engine's VM with the code version active at `block_number`
+    fn execute_on(
+        &self,
+        vm: &mut ContractVM<S>,
+        transaction: &ContractTransaction,
+        block_number: u32,
+        block_timestamp: u64,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let code = Self::code_at(vm, &transaction.contract_address, block_number)?;
+        let result = run_transaction(vm, transaction, code, block_timestamp);
+        Ok(self.receipt(transaction, result, block_number, transaction_index))
+    }
+
+    fn receipt(&self, transaction: &ContractTransaction, result: ExecutionResult, block_number: u32, transaction_index: u32) -> ContractReceipt {
+        ContractReceipt {
+            transaction_hash: self.compute_transaction_hash(transaction),
             contract_address: transaction.contract_address,
-            caller: transaction.caller,
-            timestamp: self.get_current_timestamp().await?,
-            gas_limit: transaction.gas_limit,
+            success: result.success,
+            gas_used: result.gas_used,
+            return_value: result.return_value,
+            logs: result.logs,
+            events: result.events,
+            error: result.error,
+            block_number,
+            transaction_index,
+        }
+    }
+
+    /// Register the BLS key an operator signs contract upgrades with
+    /// Contracts verify with the VM's copy of the keys, so both are updated
+    pub async fn register_operator_key(&self, operator: &str, public_key: BLSPublicKey) {
+        self.vm.write().await.crypto_verifier_mut().bls_verifier.register_operator(operator.to_string(), public_key.clone());
+        let mut crypto_verifier = self.crypto_verifier.write().await;
+        crypto_verifier.bls_verifier.register_operator(operator.to_string(), public_key);
+    }
+
+    /// Load the trusted setup verifying keys contracts check settlement and CDR privacy proofs with
+    pub async fn load_verifying_keys(&self, ceremony: &TrustedSetupCeremony) -> Result<()> {
+        let zk_verifier = {
+            let mut crypto_verifier = self.crypto_verifier.write().await;
+            crypto_verifier.load_keys_from_ceremony(ceremony).await?;
+            crypto_verifier.zk_verifier.clone()
+        };
+        self.vm.write().await.crypto_verifier_mut().zk_verifier = zk_verifier;
+        Ok(())
+    }
+
+    /// Check a signature by a registered operator key, unknown operators never verify
+    /// Operators without a locally registered key are checked against their on-chain registry record
+    pub async fn verify_operator_signature(&self, operator: &str, message: &[u8], signature: &[u8]) -> bool {
+        {
+            let crypto_verifier = self.crypto_verifier.read().await;
+            if let Ok(true) = crypto_verifier.bls_verifier.verify_operator_signature(operator, message, signature) {
+                return true;
+            }
+        }
+        match self.operator(operator).await {
+            Ok(Some(record)) => verify_bls(&record.signing_key, message, signature),
+            _ => false,
+        }
+    }
+
+    /// Rate table `operator` currently charges `partner` with
+    pub async fn rate_table(&self, operator: &str, partner: &str) -> Result<Option<RateTable>> {
+        let vm = self.vm.read().await;
+        vm.storage().get(&tariff_registry_address(), &rate_table_key(operator, partner))?
+            .map(|bytes| bincode::deserialize(&bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid rate table: {}", e))))
+            .transpose()
+    }
+
+    /// Publish a rate table included in a block, replacing the operator's earlier table for the partner
+    /// Tables not signed by their operator, or older than the current one, yield a failed receipt
+    pub async fn publish_rate_table(
+        &self,
+        signed: &SignedRateTable,
+        block_number: u32,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let table = &signed.table;
+        let payload = SignedRateTable::signing_payload(table);
+        let current = self.rate_table(&table.operator, &table.partner).await?;
+
+        let result = if !self.verify_operator_signature(&table.operator, &payload, &signed.signature).await {
+            Err(BlockchainError::InvalidTransaction(format!("Rate table is not signed by {}", table.operator)))
+        } else if current.is_some_and(|current| current.effective_from > table.effective_from) {
+            Err(BlockchainError::InvalidTransaction(format!(
+                "Rate table of {} for {} is older than the published one", table.operator, table.partner
+            )))
+        } else {
+            let bytes = bincode::serialize(table)
+                .map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+            let mut vm = self.vm.write().await;
+            vm.storage_mut().set(&tariff_registry_address(), &table.table_key(), bytes)
+        };
+
+        let receipt = ContractReceipt {
+            transaction_hash: crate::primitives::hash_canonical(signed),
+            contract_address: tariff_registry_address(),
+            success: result.is_ok(),
             gas_used: 0,
-            value: transaction.value,
+            return_value: None,
+            logs: match &result {
+                Ok(()) => vec![format!("{} published {} rates for {}", table.operator, table.rates.len(), table.partner)],
+                Err(_) => vec![],
+            },
+            events: vec![],
+            error: result.err().map(|e| e.to_string()),
+            block_number,
+            transaction_index,
         };
 
-        // Execute transaction in VM
-        let execution_result = {
-            let vm = self.vm.clone();
-            let mut vm_guard = vm.write().await;
-            vm_guard.execute(context, &transaction.input_data)?
+        {
+            let mut receipts = self.receipts.write().await;
+            receipts.push(receipt.clone());
+        }
+
+        Ok(receipt)
+    }
+
+    /// Registry record of `operator`
+    pub async fn operator(&self, operator: &str) -> Result<Option<OperatorRecord>> {
+        let vm = self.vm.read().await;
+        Self::registry_get(&vm, &operator_key(operator))
+    }
+
+    /// Registry record of the operator `plmn` belongs to
+    pub async fn operator_by_plmn(&self, plmn: &str) -> Result<Option<OperatorRecord>> {
+        let vm = self.vm.read().await;
+        match Self::registry_get::<Option<String>>(&vm, &plmn_key(plmn))?.flatten() {
+            Some(operator) => Self::registry_get(&vm, &operator_key(&operator)),
+            None => Ok(None),
+        }
+    }
+
+    /// Names of all registered operators, in registration order
+    pub async fn operators(&self) -> Result<Vec<String>> {
+        let vm = self.vm.read().await;
+        Ok(Self::registry_get(&vm, &operator_members_key())?.unwrap_or_default())
+    }
+
+    fn registry_get<T: serde::de::DeserializeOwned>(vm: &ContractVM<S>, key: &Blake2bHash) -> Result<Option<T>> {
+        vm.storage().get(&operator_registry_address(), key)?
+            .map(|bytes| bincode::deserialize(&bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid operator registry entry: {}", e))))
+            .transpose()
+    }
+
+    fn registry_set<T: serde::Serialize>(vm: &mut ContractVM<S>, key: &Blake2bHash, value: &T) -> Result<()> {
+        let bytes = bincode::serialize(value).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        vm.storage_mut().set(&operator_registry_address(), key, bytes)
+    }
+
+    /// Check a registration against the registry: the record's key signed it, updates are approved by
+    /// the operator's current key and new operators by a two-thirds quorum of the registered ones
+    /// The first operator founds the registry
+    async fn validate_registration(&self, registration: &OperatorRegistration) -> Result<()> {
+        let record = &registration.record;
+        record.validate()?;
+        let payload = OperatorRegistration::signing_payload(record);
+        if !verify_bls(&record.signing_key, &payload, &registration.signature) {
+            return Err(BlockchainError::InvalidTransaction(format!("Registration is not signed by the key of {}", record.name)));
+        }
+
+        let approved_by = |operator: &str, key: &[u8]| registration.approvals.iter()
+            .any(|approval| approval.operator == operator && verify_bls(key, &payload, &approval.signature));
+
+        match self.operator(&record.name).await? {
+            Some(current) => {
+                if record.version <= current.version {
+                    return Err(BlockchainError::InvalidTransaction(format!(
+                        "Record version {} of {} is not newer than {}", record.version, record.name, current.version
+                    )));
+                }
+                if !approved_by(&current.name, &current.signing_key) {
+                    return Err(BlockchainError::InvalidTransaction(format!(
+                        "Update of {} is not approved by its current key", record.name
+                    )));
+                }
+            }
+            None => {
+                let members = self.operators().await?;
+                let mut approvals = 0;
+                for member in &members {
+                    if let Some(member) = self.operator(member).await? {
+                        approvals += approved_by(&member.name, &member.signing_key) as usize;
+                    }
+                }
+                if !members.is_empty() && approvals < members.len() * 2 / 3 + 1 {
+                    return Err(BlockchainError::InvalidTransaction(format!(
+                        "{} is approved by {} of {} operators", record.name, approvals, members.len()
+                    )));
+                }
+            }
+        }
+
+        self.check_plmn_codes(record).await
+    }
+
+    /// Check no other operator holds one of the record's PLMN codes
+    async fn check_plmn_codes(&self, record: &OperatorRecord) -> Result<()> {
+        for plmn in &record.plmn_codes {
+            if let Some(owner) = self.operator_by_plmn(plmn).await? {
+                if owner.name != record.name {
+                    return Err(BlockchainError::InvalidTransaction(format!("PLMN {} belongs to {}", plmn, owner.name)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Register the operator of a join application governance accepted
+    /// Applicants registered in the meantime, or claiming PLMN codes another operator took, yield a failed receipt
+    pub async fn admit_operator(&self, join: &NetworkJoinTransaction, block_number: u32) -> Result<ContractReceipt> {
+        let record = OperatorRecord { version: 1, ..join.record.clone() };
+        let result = match self.operator(&record.name).await? {
+            Some(_) => Err(BlockchainError::InvalidTransaction(format!("{} is already registered", record.name))),
+            None => match self.check_plmn_codes(&record).await {
+                Ok(()) => {
+                    let mut vm = self.vm.write().await;
+                    Self::store_operator(&mut vm, &record)
+                }
+                Err(e) => Err(e),
+            },
         };
 
-        // Create receipt
         let receipt = ContractReceipt {
-            transaction_hash: self.compute_transaction_hash(&transaction),
-            contract_address: transaction.contract_address,
-            success: execution_result.success,
-            gas_used: execution_result.gas_used,
-            return_value: execution_result.return_value,
-            logs: execution_result.logs,
-            error: execution_result.error,
+            transaction_hash: join.application_id(),
+            contract_address: operator_registry_address(),
+            success: result.is_ok(),
+            gas_used: 0,
+            return_value: None,
+            logs: match &result {
+                Ok(()) => vec![format!("{} admitted with PLMN codes {}", record.name, record.plmn_codes.join(", "))],
+                Err(_) => vec![],
+            },
+            events: vec![],
+            error: result.err().map(|e| e.to_string()),
+            block_number,
+            transaction_index: 0,
+        };
+
+        {
+            let mut receipts = self.receipts.write().await;
+            receipts.push(receipt.clone());
+        }
+
+        Ok(receipt)
+    }
+
+    /// Register an operator or update its record from a block, indexing its PLMN codes
+    /// Registrations the registry does not accept yield a failed receipt
+    pub async fn register_operator(
+        &self,
+        registration: &OperatorRegistration,
+        block_number: u32,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let record = &registration.record;
+        let result = match self.validate_registration(registration).await {
+            Ok(()) => {
+                let mut vm = self.vm.write().await;
+                Self::store_operator(&mut vm, record)
+            }
+            Err(e) => Err(e),
+        };
+
+        let receipt = ContractReceipt {
+            transaction_hash: crate::primitives::hash_canonical(registration),
+            contract_address: operator_registry_address(),
+            success: result.is_ok(),
+            gas_used: 0,
+            return_value: None,
+            logs: match &result {
+                Ok(()) => vec![format!("{} registered with PLMN codes {}", record.name, record.plmn_codes.join(", "))],
+                Err(_) => vec![],
+            },
+            events: vec![],
+            error: result.err().map(|e| e.to_string()),
             block_number,
             transaction_index,
         };
 
-        // Store receipt
         {
             let mut receipts = self.receipts.write().await;
             receipts.push(receipt.clone());
@@ -168,6 +670,363 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
         Ok(receipt)
     }
 
+    fn store_operator(vm: &mut ContractVM<S>, record: &OperatorRecord) -> Result<()> {
+        let current: Option<OperatorRecord> = Self::registry_get(vm, &operator_key(&record.name))?;
+        match &current {
+            // PLMN codes dropped by an update become free for other operators
+            Some(current) => {
+                for plmn in current.plmn_codes.iter().filter(|plmn| !record.plmn_codes.contains(plmn)) {
+                    Self::registry_set(vm, &plmn_key(plmn), &None::<String>)?;
+                }
+            }
+            None => {
+                let mut members: Vec<String> = Self::registry_get(vm, &operator_members_key())?.unwrap_or_default();
+                members.push(record.name.clone());
+                Self::registry_set(vm, &operator_members_key(), &members)?;
+            }
+        }
+        for plmn in &record.plmn_codes {
+            Self::registry_set(vm, &plmn_key(plmn), &Some(record.name.clone()))?;
+        }
+        Self::registry_set(vm, &operator_key(&record.name), record)
+    }
+
+    /// Escrow of a settlement, `None` if it was never locked
+    pub async fn escrow(&self, settlement_tx: &Blake2bHash) -> Result<Option<SettlementEscrow>> {
+        let vm = self.vm.read().await;
+        Self::escrow_get(&vm, &escrow_key(settlement_tx))
+    }
+
+    fn escrow_get<T: serde::de::DeserializeOwned>(vm: &ContractVM<S>, key: &Blake2bHash) -> Result<Option<T>> {
+        vm.storage().get(&settlement_escrow_address(), key)?
+            .map(|bytes| bincode::deserialize(&bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid escrow entry: {}", e))))
+            .transpose()
+    }
+
+    fn escrow_set<T: serde::Serialize>(vm: &mut ContractVM<S>, key: &Blake2bHash, value: &T) -> Result<()> {
+        let bytes = bincode::serialize(value).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        vm.storage_mut().set(&settlement_escrow_address(), key, bytes)
+    }
+
+    /// Lock a settlement obligation or record its payment confirmation, from a block of `timestamp`
+    /// `settlement` is the final settlement transaction a lock names, if there is one. Locks not
+    /// signed by the debtor or not holding its terms, confirmations not signed by their confirmer
+    /// and those the escrow does not accept yield a failed receipt
+    pub async fn execute_escrow(
+        &self,
+        escrow: &EscrowTransaction,
+        settlement: Option<&SettlementTransaction>,
+        timestamp: u64,
+        block_number: u32,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let result = match escrow {
+            EscrowTransaction::Lock(lock) => self.lock_escrow(lock, settlement, timestamp).await,
+            EscrowTransaction::Confirm(confirmation) => self.confirm_payment(confirmation, timestamp).await,
+        };
+
+        let receipt = ContractReceipt {
+            transaction_hash: crate::primitives::hash_canonical(escrow),
+            contract_address: settlement_escrow_address(),
+            success: result.is_ok(),
+            gas_used: 0,
+            return_value: None,
+            logs: match &result {
+                Ok((log, _)) => vec![log.clone()],
+                Err(_) => vec![],
+            },
+            events: match &result {
+                Ok((_, event)) => vec![event.clone()],
+                Err(_) => vec![],
+            },
+            error: result.err().map(|e| e.to_string()),
+            block_number,
+            transaction_index,
+        };
+
+        {
+            let mut receipts = self.receipts.write().await;
+            receipts.push(receipt.clone());
+        }
+
+        Ok(receipt)
+    }
+
+    async fn lock_escrow(&self, lock: &EscrowLock, settlement: Option<&SettlementTransaction>, timestamp: u64) -> Result<(String, ContractEvent)> {
+        let settlement = settlement.ok_or_else(|| BlockchainError::InvalidTransaction(format!(
+            "Settlement {} is not final on chain", lock.settlement_tx
+        )))?;
+        let registered = |operator: &str, record: Option<OperatorRecord>| record.map(|record| record.network_id())
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("{} is not a registered operator", operator)));
+        let creditor = registered(&lock.creditor, self.operator(&lock.creditor).await?)?;
+        let debtor = registered(&lock.debtor, self.operator(&lock.debtor).await?)?;
+        lock.check_terms(settlement, &creditor, &debtor)?;
+        if !self.verify_operator_signature(&lock.debtor, &lock.signing_payload(), &lock.signature).await {
+            return Err(BlockchainError::InvalidTransaction(format!("Escrow lock is not signed by {}", lock.debtor)));
+        }
+        // A payment agent releases the escrow in the creditor's stead, so only with its agreement
+        if let Some(payload) = lock.agent_approval_payload() {
+            if !self.verify_operator_signature(&lock.creditor, &payload, &lock.agent_approval).await {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "{} did not agree to its payment agent", lock.creditor
+                )));
+            }
+        }
+        let mut vm = self.vm.write().await;
+        if Self::escrow_get::<SettlementEscrow>(&vm, &escrow_key(&lock.settlement_tx))?.is_some() {
+            return Err(BlockchainError::InvalidTransaction(format!("Settlement {} is already in escrow", lock.settlement_tx)));
+        }
+        let escrow = SettlementEscrow::lock(lock, timestamp);
+        let mut locked: Vec<(u64, Blake2bHash)> = Self::escrow_get(&vm, &locked_escrows_key())?.unwrap_or_default();
+        locked.push((escrow.deadline, escrow.settlement_tx));
+        Self::escrow_set(&mut vm, &locked_escrows_key(), &locked)?;
+        Self::escrow_set(&mut vm, &escrow_key(&lock.settlement_tx), &escrow)?;
+
+        Ok((
+            format!("{} locked {} {} owed to {}", lock.debtor, lock.amount, lock.currency, lock.creditor),
+            escrow_event("EscrowLocked", &lock.settlement_tx),
+        ))
+    }
+
+    async fn confirm_payment(&self, confirmation: &PaymentConfirmation, timestamp: u64) -> Result<(String, ContractEvent)> {
+        if !self.verify_operator_signature(&confirmation.confirmer, &confirmation.signing_payload(), &confirmation.signature).await {
+            return Err(BlockchainError::InvalidTransaction(format!("Payment confirmation is not signed by {}", confirmation.confirmer)));
+        }
+        let mut vm = self.vm.write().await;
+        let mut escrow: SettlementEscrow = Self::escrow_get(&vm, &escrow_key(&confirmation.settlement_tx))?
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("Settlement {} is not in escrow", confirmation.settlement_tx)))?;
+        escrow.release(confirmation, timestamp)?;
+        let mut locked: Vec<(u64, Blake2bHash)> = Self::escrow_get(&vm, &locked_escrows_key())?.unwrap_or_default();
+        locked.retain(|(_, settlement_tx)| *settlement_tx != escrow.settlement_tx);
+        Self::escrow_set(&mut vm, &locked_escrows_key(), &locked)?;
+        Self::escrow_set(&mut vm, &escrow_key(&escrow.settlement_tx), &escrow)?;
+
+        Ok((
+            format!("{} confirmed the payment of {} {} by {}", confirmation.confirmer, escrow.amount, escrow.currency, escrow.debtor),
+            escrow_event("EscrowReleased", &escrow.settlement_tx),
+        ))
+    }
+
+    /// Put every escrow still locked at its deadline into dispute, before the transactions of a block
+    /// of `timestamp` run. Each disputed escrow gets a receipt with an `EscrowDisputed` event, from
+    /// which the chain opens the dispute once the block is committed
+    pub async fn expire_escrows(&self, timestamp: u64, block_number: u32) -> Result<Vec<ContractReceipt>> {
+        let mut vm = self.vm.write().await;
+        let locked: Vec<(u64, Blake2bHash)> = Self::escrow_get(&vm, &locked_escrows_key())?.unwrap_or_default();
+        let (expired, pending): (Vec<_>, Vec<_>) = locked.into_iter().partition(|(deadline, _)| *deadline <= timestamp);
+        if expired.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut disputed = Vec::with_capacity(expired.len());
+        for (_, settlement_tx) in expired {
+            let Some(mut escrow) = Self::escrow_get::<SettlementEscrow>(&vm, &escrow_key(&settlement_tx))? else {
+                continue;
+            };
+            if escrow.expire(timestamp) {
+                Self::escrow_set(&mut vm, &escrow_key(&settlement_tx), &escrow)?;
+                disputed.push(ContractReceipt {
+                    transaction_hash: crate::primitives::hash_canonical(&("escrow-expiry", &settlement_tx)),
+                    contract_address: settlement_escrow_address(),
+                    success: true,
+                    gas_used: 0,
+                    return_value: None,
+                    logs: vec![format!(
+                        "Payment of {} {} owed by {} to {} unconfirmed at its deadline",
+                        escrow.amount, escrow.currency, escrow.debtor, escrow.creditor
+                    )],
+                    events: vec![escrow_event("EscrowDisputed", &settlement_tx)],
+                    error: None,
+                    block_number,
+                    transaction_index: 0,
+                });
+            }
+        }
+        Self::escrow_set(&mut vm, &locked_escrows_key(), &pending)?;
+        drop(vm);
+
+        self.receipts.write().await.extend(disputed.iter().cloned());
+        Ok(disputed)
+    }
+
+    /// Code versions of a contract, oldest first
+    pub async fn contract_versions(&self, contract: &Blake2bHash) -> Result<Vec<ContractVersion>> {
+        let vm = self.vm.read().await;
+        Ok(Self::registry_entry(&vm, contract)?.map(|entry| entry.versions).unwrap_or_default())
+    }
+
+    /// Code a contract executed at `block_number`, for re-validating historical blocks
+    pub async fn contract_code_at(&self, contract: &Blake2bHash, block_number: u32) -> Result<Option<Vec<Instruction>>> {
+        let vm = self.vm.read().await;
+        Self::code_at(&vm, contract, block_number)
+    }
+
+    /// Check an upgrade follows the current version, its code is valid for the contract's class and
+    /// it carries a valid signature from every counterpart
+    pub async fn validate_upgrade(&self, upgrade: &ContractUpgrade) -> Result<()> {
+        let entry = {
+            let vm = self.vm.read().await;
+            Self::registry_entry(&vm, &upgrade.contract_address)?
+                .ok_or(BlockchainError::ContractNotFound)?
+        };
+
+        if entry.counterparts.is_empty() {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Contract {} has no counterparts and cannot be upgraded", upgrade.contract_address
+            )));
+        }
+
+        let current = entry.versions.last().map(|version| version.version).unwrap_or(0);
+        if upgrade.version != current + 1 {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Upgrade to version {} of contract {} does not follow version {}",
+                upgrade.version, upgrade.contract_address, current
+            )));
+        }
+        validate_bytecode(&upgrade.bytecode, entry.class)?;
+
+        let payload = upgrade.signing_payload();
+        for operator in &entry.counterparts {
+            let mut signed = false;
+            for (_, signature) in upgrade.signatures.iter().filter(|(signer, _)| signer == operator) {
+                signed |= self.verify_operator_signature(operator, &payload, signature).await;
+            }
+            if !signed {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "Upgrade of contract {} lacks a valid signature from {}", upgrade.contract_address, operator
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply a contract upgrade included in a block
+    /// The new code takes effect from the next block, earlier code stays available for re-validation
+    /// An invalid upgrade yields a failed receipt and leaves the contract unchanged
+    pub async fn apply_upgrade(
+        &self,
+        upgrade: &ContractUpgrade,
+        block_number: u32,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let result = match self.validate_upgrade(upgrade).await {
+            Ok(()) => {
+                let mut vm = self.vm.write().await;
+                let mut entry = Self::registry_entry(&vm, &upgrade.contract_address)?.unwrap_or_default();
+                entry.versions.push(ContractVersion {
+                    version: upgrade.version,
+                    code_hash: upgrade.code_hash(),
+                    activation_block: block_number + 1,
+                });
+
+                vm.deploy_contract(versioned_code_address(&upgrade.contract_address, upgrade.version), upgrade.bytecode.clone())?;
+                vm.deploy_contract(upgrade.contract_address, upgrade.bytecode.clone())?;
+                Self::put_registry_entry(&mut vm, &upgrade.contract_address, &entry)?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+
+        let receipt = ContractReceipt {
+            transaction_hash: self.compute_upgrade_hash(upgrade),
+            contract_address: upgrade.contract_address,
+            success: result.is_ok(),
+            gas_used: 0,
+            return_value: None,
+            logs: match &result {
+                Ok(()) => vec![format!("Contract upgraded to version {} from block {}", upgrade.version, block_number + 1)],
+                Err(_) => vec![],
+            },
+            events: vec![],
+            error: result.err().map(|e| e.to_string()),
+            block_number,
+            transaction_index,
+        };
+
+        {
+            let mut receipts = self.receipts.write().await;
+            receipts.push(receipt.clone());
+        }
+
+        Ok(receipt)
+    }
+
+    /// Start executing a block: later writes are recorded at `block_number`, and in the first micro
+    /// block of an epoch slots unwritten for `expiry_epochs` epochs expire. Returns how many did
+    pub async fn begin_block(&self, block_number: u32, expiry_epochs: u64) -> Result<usize> {
+        let mut vm = self.vm.write().await;
+        vm.storage_mut().begin_block(block_number);
+        match expiry_cutoff(block_number, expiry_epochs) {
+            Some(cutoff) => vm.storage_mut().expire_slots(block_number, cutoff),
+            None => Ok(0),
+        }
+    }
+
+    /// Resurrect an expired storage slot included in a block
+    /// A resurrection whose proof does not hold, or of a slot not expired, yields a failed receipt
+    pub async fn resurrect_state(
+        &self,
+        resurrection: &StateResurrection,
+        block_number: u32,
+        transaction_index: u32,
+    ) -> Result<ContractReceipt> {
+        let result = {
+            let mut vm = self.vm.write().await;
+            vm.storage_mut().resurrect_slot(resurrection)
+        };
+
+        let receipt = ContractReceipt {
+            transaction_hash: crate::primitives::hash_canonical(resurrection),
+            contract_address: resurrection.slot.contract,
+            success: result.is_ok(),
+            gas_used: 0,
+            return_value: None,
+            logs: match &result {
+                Ok(()) => vec![format!("Slot {} resurrected, expired at block {}", resurrection.slot.key, resurrection.expired_at)],
+                Err(_) => vec![],
+            },
+            events: vec![],
+            error: result.err().map(|e| e.to_string()),
+            block_number,
+            transaction_index,
+        };
+
+        {
+            let mut receipts = self.receipts.write().await;
+            receipts.push(receipt.clone());
+        }
+
+        Ok(receipt)
+    }
+
+    fn registry_entry(vm: &ContractVM<S>, contract: &Blake2bHash) -> Result<Option<ContractRegistryEntry>> {
+        vm.storage().get(&registry_address(contract), &Blake2bHash::zero())?
+            .map(|bytes| bincode::deserialize(&bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid contract registry entry: {}", e))))
+            .transpose()
+    }
+
+    fn put_registry_entry(vm: &mut ContractVM<S>, contract: &Blake2bHash, entry: &ContractRegistryEntry) -> Result<()> {
+        let bytes = bincode::serialize(entry)
+            .map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        vm.storage_mut().set(&registry_address(contract), &Blake2bHash::zero(), bytes)
+    }
+
+    /// Code of the version active at `block_number`, contracts without a registry entry run their current code
+    fn code_at(vm: &ContractVM<S>, contract: &Blake2bHash, block_number: u32) -> Result<Option<Vec<Instruction>>> {
+        let entry = match Self::registry_entry(vm, contract)? {
+            Some(entry) => entry,
+            None => return vm.storage().get_code(contract),
+        };
+
+        match entry.versions.iter().rev().find(|version| version.activation_block <= block_number) {
+            Some(version) => vm.storage().get_code(&versioned_code_address(contract, version.version)),
+            None => Ok(None),
+        }
+    }
+
     /// Process all contract transactions in a block
     pub async fn process_block_transactions(
         &self,
@@ -189,7 +1048,7 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
                     receipts.push(receipt);
                 },
                 Transaction::NetworkJoin(_) => {
-                    // Network join might update operator registry contracts
+                    // Join applications go to a governance vote, `admit_operator` registers accepted ones
                     continue;
                 }
             }
@@ -256,6 +1115,11 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
         crate::primitives::primitives::hash_data(&data)
     }
 
+    fn compute_upgrade_hash(&self, upgrade: &ContractUpgrade) -> Blake2bHash {
+        let data = serde_json::to_vec(upgrade).unwrap();
+        crate::primitives::primitives::hash_data(&data)
+    }
+
     async fn get_current_timestamp(&self) -> Result<u64> {
         Ok(std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -282,6 +1146,43 @@ impl<S: ContractStorage + Send + Sync + 'static> ConsensusContractEngine<S> {
     }
 }
 
+/// Run a transaction with `code` at the block timestamp
+/// Errors become failed results charging the full gas limit, the VM has discarded their writes
+fn run_transaction<T: ContractStorage>(
+    vm: &mut ContractVM<T>,
+    transaction: &ContractTransaction,
+    code: Option<Vec<Instruction>>,
+    block_timestamp: u64,
+) -> ExecutionResult {
+    let context = ExecutionContext {
+        contract_address: transaction.contract_address,
+        caller: transaction.caller,
+        timestamp: block_timestamp,
+        gas_limit: transaction.gas_limit,
+        gas_used: 0,
+        value: transaction.value,
+    };
+    code.ok_or(BlockchainError::ContractNotFound)
+        .and_then(|code| vm.execute_code(context, &code, &transaction.input_data))
+        .unwrap_or_else(|e| ExecutionResult {
+            success: false,
+            return_value: None,
+            gas_used: transaction.gas_limit,
+            return_data: vec![],
+            logs: vec![],
+            events: vec![],
+            error: Some(e.to_string()),
+        })
+}
+
+/// Check a BLS signature against raw public key bytes, malformed keys and signatures never verify
+fn verify_bls(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    match (BLSPublicKey::from_bytes(public_key), crate::crypto::BLSSignature::from_bytes(signature)) {
+        (Ok(public_key), Ok(signature)) => signature.verify(&public_key, message).unwrap_or(false),
+        _ => false,
+    }
+}
+
 /// Blockchain integration for smart contracts
 pub trait ContractBlockchain: AbstractBlockchain {
     async fn execute_contracts(&self, block: &Block) -> Result<Vec<ContractReceipt>>;
@@ -292,6 +1193,15 @@ pub trait ContractBlockchain: AbstractBlockchain {
 // Note: This would be implemented by SPCDRBlockchain in a real integration
 // impl ContractBlockchain for SPCDRBlockchain { ... }
 
+/// Event of the escrow contract about a settlement
+fn escrow_event(name: &str, settlement_tx: &Blake2bHash) -> ContractEvent {
+    ContractEvent {
+        contract_address: settlement_escrow_address(),
+        topics: vec![super::events::event_topic(name), *settlement_tx],
+        data: vec![],
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -313,12 +1223,18 @@ mod tests {
             gas_limit: 100000,
             value: 0,
             nonce: 1,
+            counterparts: vec![],
+            class: ContractClass::General,
         };
 
-        let (contract_addr, receipt) = engine.deploy_contract(deployment, 1).await.unwrap();
+        let (contract_addr, receipt) = engine.deploy_contract(deployment.clone(), 1).await.unwrap();
 
         assert!(receipt.success);
         assert_ne!(contract_addr, Blake2bHash::zero());
+
+        // Code jumping past its end is never deployed
+        let invalid = ContractDeployment { bytecode: vec![Instruction::Jump(7)], nonce: 2, ..deployment };
+        assert!(matches!(engine.deploy_contract(invalid, 2).await, Err(BlockchainError::InvalidTransaction(_))));
     }
 
     #[tokio::test]
@@ -340,6 +1256,8 @@ mod tests {
             gas_limit: 100000,
             value: 0,
             nonce: 1,
+            counterparts: vec![],
+            class: ContractClass::General,
         };
 
         let (contract_addr, _) = engine.deploy_contract(deployment, 1).await.unwrap();
@@ -359,4 +1277,256 @@ mod tests {
         assert!(receipt.success);
         assert_eq!(receipt.return_value, Some(8));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_contract_upgrade() {
+        let engine = ConsensusContractEngine::new(MemoryStorage::new(), ContractCryptoVerifier::new());
+        let tmobile = crate::crypto::BLSPrivateKey::generate().unwrap();
+        let vodafone = crate::crypto::BLSPrivateKey::generate().unwrap();
+        engine.register_operator_key("T-Mobile-DE", tmobile.public_key()).await;
+        engine.register_operator_key("Vodafone-UK", vodafone.public_key()).await;
+
+        let deployment = ContractDeployment {
+            deployer: crate::primitives::primitives::hash_data(b"deployer"),
+            bytecode: vec![Instruction::Push(8), Instruction::Halt],
+            constructor_data: vec![],
+            gas_limit: 100000,
+            value: 0,
+            nonce: 1,
+            counterparts: vec!["T-Mobile-DE".to_string(), "Vodafone-UK".to_string()],
+            class: ContractClass::General,
+        };
+        let (contract_addr, _) = engine.deploy_contract(deployment, 1).await.unwrap();
+
+        let mut upgrade = ContractUpgrade {
+            contract_address: contract_addr,
+            version: 2,
+            bytecode: vec![Instruction::Push(9), Instruction::Halt],
+            signatures: vec![],
+        };
+        let payload = upgrade.signing_payload();
+        upgrade.signatures.push(("T-Mobile-DE".to_string(), tmobile.sign(&payload).unwrap().to_bytes().to_vec()));
+
+        // One counterpart alone cannot upgrade
+        let receipt = engine.apply_upgrade(&upgrade, 5, 0).await.unwrap();
+        assert!(!receipt.success);
+        assert_eq!(engine.contract_versions(&contract_addr).await.unwrap().len(), 1);
+
+        upgrade.signatures.push(("Vodafone-UK".to_string(), vodafone.sign(&payload).unwrap().to_bytes().to_vec()));
+        let receipt = engine.apply_upgrade(&upgrade, 5, 0).await.unwrap();
+        assert!(receipt.success);
+
+        // The same version cannot be applied twice
+        assert!(!engine.apply_upgrade(&upgrade, 6, 0).await.unwrap().success);
+
+        // Blocks up to the upgrade block still execute the old code
+        let call = |block_number| {
+            let engine = &engine;
+            async move {
+                let transaction = ContractTransaction {
+                    contract_address: contract_addr,
+                    caller: crate::primitives::primitives::hash_data(b"caller"),
+                    input_data: vec![],
+                    gas_limit: 50000,
+                    value: 0,
+                    nonce: block_number as u64,
+                };
+                engine.execute_block_transaction(transaction, block_number, 0, 0).await.unwrap().return_value
+            }
+        };
+        assert_eq!(call(5).await, Some(8));
+        assert_eq!(call(6).await, Some(9));
+
+        let versions = engine.contract_versions(&contract_addr).await.unwrap();
+        assert_eq!(versions.iter().map(|v| (v.version, v.activation_block)).collect::<Vec<_>>(), vec![(1, 1), (2, 6)]);
+        assert_eq!(engine.contract_code_at(&contract_addr, 1).await.unwrap(), Some(vec![Instruction::Push(8), Instruction::Halt]));
+    }
+
+    #[tokio::test]
+    async fn test_rate_table_publication() {
+        use crate::blockchain::tariff::{TariffRate, TariffService};
+
+        let engine = ConsensusContractEngine::new(MemoryStorage::new(), ContractCryptoVerifier::new());
+        let vodafone = crate::crypto::BLSPrivateKey::generate().unwrap();
+        engine.register_operator_key("Vodafone-UK", vodafone.public_key()).await;
+
+        let table = RateTable {
+            operator: "Vodafone-UK".to_string(),
+            partner: "T-Mobile-DE".to_string(),
+            currency: "EUR".to_string(),
+            effective_from: 1_700_000_000,
+            rates: vec![TariffRate { service: TariffService::Voice, destination: None, time_band: None, cents_per_unit: 12 }],
+        };
+        let sign = |key: &crate::crypto::BLSPrivateKey, table: &RateTable| SignedRateTable {
+            table: table.clone(),
+            signature: key.sign(&SignedRateTable::signing_payload(table)).unwrap().to_bytes().to_vec(),
+        };
+
+        // Only the publishing operator can sign its tariff
+        let impostor = crate::crypto::BLSPrivateKey::generate().unwrap();
+        assert!(!engine.publish_rate_table(&sign(&impostor, &table), 1, 0).await.unwrap().success);
+        assert!(engine.publish_rate_table(&sign(&vodafone, &table), 1, 0).await.unwrap().success);
+        assert_eq!(engine.rate_table("Vodafone-UK", "T-Mobile-DE").await.unwrap(), Some(table.clone()));
+
+        // An older table cannot replace the published one
+        let mut stale = table.clone();
+        stale.effective_from -= 1;
+        assert!(!engine.publish_rate_table(&sign(&vodafone, &stale), 2, 0).await.unwrap().success);
+
+        // Contracts price usage with the published rate: 100 minutes at 12 cents
+        let deployment = ContractDeployment {
+            deployer: crate::primitives::primitives::hash_data(b"deployer"),
+            bytecode: vec![
+                Instruction::LookupRate { table: table.table_key(), service: TariffService::Voice, destination: None },
+                Instruction::Push(100),
+                Instruction::Mul,
+                Instruction::Halt,
+            ],
+            constructor_data: vec![],
+            gas_limit: 100000,
+            value: 0,
+            nonce: 1,
+            counterparts: vec![],
+            class: ContractClass::General,
+        };
+        let (contract_addr, _) = engine.deploy_contract(deployment, 2).await.unwrap();
+        let transaction = ContractTransaction {
+            contract_address: contract_addr,
+            caller: crate::primitives::primitives::hash_data(b"caller"),
+            input_data: vec![],
+            gas_limit: 50000,
+            value: 0,
+            nonce: 1,
+        };
+        let receipt = engine.execute_block_transaction(transaction, 3, 1_700_000_000, 0).await.unwrap();
+        assert_eq!(receipt.return_value, Some(1_200));
+    }
+    #[tokio::test]
+    async fn test_operator_registration() {
+        use crate::blockchain::operator_registry::{OperatorApproval, OperatorRecord, OperatorRegistration};
+        use crate::crypto::BLSPrivateKey;
+
+        let engine = ConsensusContractEngine::new(MemoryStorage::new(), ContractCryptoVerifier::new());
+        let record = |name: &str, plmn: &str, key: &BLSPrivateKey, version: u64| OperatorRecord {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            country: "Germany".to_string(),
+            plmn_codes: vec![plmn.to_string()],
+            signing_key: key.public_key().to_bytes().to_vec(),
+            encryption_key: None,
+            version,
+        };
+        let register = |record: OperatorRecord, key: &BLSPrivateKey, approvers: &[(&str, &BLSPrivateKey)]| {
+            let payload = OperatorRegistration::signing_payload(&record);
+            OperatorRegistration {
+                signature: key.sign(&payload).unwrap().to_bytes().to_vec(),
+                approvals: approvers.iter()
+                    .map(|(operator, key)| OperatorApproval {
+                        operator: operator.to_string(),
+                        signature: key.sign(&payload).unwrap().to_bytes().to_vec(),
+                    })
+                    .collect(),
+                record,
+            }
+        };
+
+        // The first operator founds the registry
+        let tmobile = BLSPrivateKey::generate().unwrap();
+        assert!(engine.register_operator(&register(record("T-Mobile-DE", "26201", &tmobile, 1), &tmobile, &[]), 1, 0).await.unwrap().success);
+        assert_eq!(engine.operator_by_plmn("26201").await.unwrap().unwrap().network_id(), crate::primitives::NetworkId::new("T-Mobile-DE", "Germany"));
+
+        // Later operators need the approval of the registered ones and a free PLMN code
+        let vodafone = BLSPrivateKey::generate().unwrap();
+        assert!(!engine.register_operator(&register(record("Vodafone-DE", "26202", &vodafone, 1), &vodafone, &[]), 2, 0).await.unwrap().success);
+        let approvers = [("T-Mobile-DE", &tmobile)];
+        assert!(!engine.register_operator(&register(record("Vodafone-DE", "26201", &vodafone, 1), &vodafone, &approvers), 2, 0).await.unwrap().success);
+        assert!(engine.register_operator(&register(record("Vodafone-DE", "26202", &vodafone, 1), &vodafone, &approvers), 2, 0).await.unwrap().success);
+        assert_eq!(engine.operators().await.unwrap(), vec!["T-Mobile-DE".to_string(), "Vodafone-DE".to_string()]);
+        assert!(engine.verify_operator_signature("Vodafone-DE", b"usage", vodafone.sign(b"usage").unwrap().to_bytes()).await);
+
+        // Updates are approved by the operator's current key and release dropped PLMN codes
+        let rotated = BLSPrivateKey::generate().unwrap();
+        let update = record("Vodafone-DE", "26209", &rotated, 2);
+        assert!(!engine.register_operator(&register(update.clone(), &rotated, &[]), 3, 0).await.unwrap().success);
+        assert!(engine.register_operator(&register(update, &rotated, &[("Vodafone-DE", &vodafone)]), 3, 0).await.unwrap().success);
+        assert!(engine.operator_by_plmn("26202").await.unwrap().is_none());
+        assert_eq!(engine.operator_by_plmn("26209").await.unwrap().unwrap().version, 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_parallel_execution_matches_serial() {
+        // Each contract counts its calls and returns the new count
+        let key = Blake2bHash::from_data(b"calls");
+        let counter = vec![Instruction::Load(key), Instruction::Push(1), Instruction::Add, Instruction::Store(key), Instruction::Load(key), Instruction::Halt];
+        let deploy = |nonce| ContractDeployment {
+            deployer: crate::primitives::primitives::hash_data(b"deployer"),
+            bytecode: counter.clone(),
+            constructor_data: vec![],
+            gas_limit: 100_000,
+            value: 0,
+            nonce,
+            counterparts: vec![],
+            class: ContractClass::General,
+        };
+        let parallel = ConsensusContractEngine::new(MemoryStorage::new(), ContractCryptoVerifier::new());
+        let serial = ConsensusContractEngine::new(MemoryStorage::new(), ContractCryptoVerifier::new());
+        let mut contracts = vec![];
+        for nonce in 1..=2 {
+            let (contract, _) = parallel.deploy_contract(deploy(nonce), 1).await.unwrap();
+            serial.deploy_contract(deploy(nonce), 1).await.unwrap();
+            contracts.push(contract);
+        }
+
+        let call = |contract: Blake2bHash, nonce| ContractTransaction {
+            contract_address: contract,
+            caller: Blake2bHash::zero(),
+            input_data: vec![],
+            gas_limit: 10_000,
+            value: 0,
+            nonce,
+        };
+        let run: Vec<(u32, ContractTransaction)> = [0, 1, 0, 0, 1].iter().enumerate()
+            .map(|(index, contract)| (index as u32, call(contracts[*contract], index as u64)))
+            .collect();
+        let receipts = parallel.execute_block_transactions(run.clone(), 2, 1_700_000_000).await.unwrap();
+        let mut expected = vec![];
+        for (index, transaction) in run {
+            expected.push(serial.execute_block_transaction(transaction, 2, 1_700_000_000, index).await.unwrap());
+        }
+
+        // Receipts come back in block order with the results serial execution gives
+        assert_eq!(receipts.iter().map(|receipt| receipt.transaction_index).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(receipts.iter().map(|receipt| receipt.return_value).collect::<Vec<_>>(), vec![Some(1), Some(1), Some(2), Some(3), Some(2)]);
+        for (receipt, expected) in receipts.iter().zip(&expected) {
+            assert_eq!((receipt.success, receipt.gas_used, receipt.return_value), (expected.success, expected.gas_used, expected.return_value));
+        }
+        for contract in &contracts {
+            assert_eq!(
+                parallel.vm.read().await.storage().get(contract, &key).unwrap(),
+                serial.vm.read().await.storage().get(contract, &key).unwrap(),
+            );
+        }
+
+        // A contract calling the first counter conflicts with its group, only those two run again
+        let forward = ContractDeployment {
+            bytecode: vec![Instruction::Push(0), Instruction::Push(0), Instruction::Push(0), Instruction::Call(contracts[0]), Instruction::Halt],
+            ..deploy(3)
+        };
+        let (forwarder, _) = parallel.deploy_contract(forward.clone(), 3).await.unwrap();
+        serial.deploy_contract(forward, 3).await.unwrap();
+        let run: Vec<(u32, ContractTransaction)> = [contracts[0], forwarder, contracts[1], contracts[0]].into_iter().enumerate()
+            .map(|(index, contract)| (index as u32, call(contract, 10 + index as u64)))
+            .collect();
+        let receipts = parallel.execute_block_transactions(run.clone(), 4, 1_700_000_000).await.unwrap();
+        for (receipt, (index, transaction)) in receipts.iter().zip(run) {
+            let expected = serial.execute_block_transaction(transaction, 4, 1_700_000_000, index).await.unwrap();
+            assert_eq!((receipt.success, receipt.gas_used, receipt.return_value), (expected.success, expected.gas_used, expected.return_value));
+        }
+        for contract in &contracts {
+            assert_eq!(
+                parallel.vm.read().await.storage().get(contract, &key).unwrap(),
+                serial.vm.read().await.storage().get(contract, &key).unwrap(),
+            );
+        }
+    }
+}
@@ -0,0 +1,398 @@
+// Settlement contract language: roaming agreements written as rates, thresholds,
+// netting and dispute clauses, compiled to VM bytecode
+//
+//     contract "T-Mobile-DE/Vodafone-UK 2024"
+//     rate voice 12 per minute
+//     rate data 3 per mb
+//     threshold 10000
+//     netting bilateral
+//     dispute deviation 5%
+//
+// Compiled contracts read usage from the `forward.<service>` and `reverse.<service>`
+// slots, an optional `declared` amount, and write `net_amount`, `direction`,
+// `settle` and `disputed`. They return the amount due, or zero.
+use std::fmt;
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::vm::Instruction;
+
+/// Storage slot of a named contract value
+pub fn contract_slot(name: &str) -> Blake2bHash {
+    crate::primitives::primitives::hash_data(format!("settlement-slot:{}", name).as_bytes())
+}
+
+/// Wholesale rate for one service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateClause {
+    pub service: String,
+    pub cents_per_unit: u64,
+    pub unit: String,
+}
+
+/// How usage in both directions is settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NettingRule {
+    /// Only the forward direction is charged
+    Gross,
+    /// Both directions are charged and only the difference is settled
+    Bilateral,
+}
+
+/// Raise a dispute when the declared amount deviates too far from the computed one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisputeClause {
+    pub max_deviation_percent: u64,
+}
+
+/// Parsed settlement contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettlementContractSource {
+    pub name: String,
+    pub rates: Vec<RateClause>,
+    pub threshold_cents: u64,
+    pub netting: NettingRule,
+    pub dispute: Option<DisputeClause>,
+}
+
+fn parse_error(line: usize, message: impl fmt::Display) -> BlockchainError {
+    BlockchainError::InvalidOperation(format!("Contract line {}: {}", line, message))
+}
+
+fn parse_amount(line: usize, token: &str) -> Result<u64> {
+    token.parse().map_err(|_| parse_error(line, format!("expected a whole number, found '{}'", token)))
+}
+
+fn is_identifier(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+impl SettlementContractSource {
+    /// Parse contract source, one clause per line, `#` starts a comment
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut name = None;
+        let mut rates: Vec<RateClause> = Vec::new();
+        let mut threshold_cents = None;
+        let mut netting = None;
+        let mut dispute = None;
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix("contract ") {
+                let quoted = rest.trim();
+                let contract_name = quoted.strip_prefix('"').and_then(|s| s.strip_suffix('"'))
+                    .filter(|s| !s.is_empty() && !s.contains('"'))
+                    .ok_or_else(|| parse_error(line_number, "contract name must be a non-empty quoted string"))?;
+                if name.replace(contract_name.to_string()).is_some() {
+                    return Err(parse_error(line_number, "contract is declared twice"));
+                }
+                continue;
+            }
+
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["rate", service, cents, "per", unit] => {
+                    if !is_identifier(service) || !is_identifier(unit) {
+                        return Err(parse_error(line_number, "service and unit must be lowercase identifiers"));
+                    }
+                    if rates.iter().any(|rate| rate.service == *service) {
+                        return Err(parse_error(line_number, format!("rate for '{}' is declared twice", service)));
+                    }
+                    rates.push(RateClause {
+                        service: service.to_string(),
+                        cents_per_unit: parse_amount(line_number, cents)?,
+                        unit: unit.to_string(),
+                    });
+                }
+                ["threshold", cents] => {
+                    if threshold_cents.replace(parse_amount(line_number, cents)?).is_some() {
+                        return Err(parse_error(line_number, "threshold is declared twice"));
+                    }
+                }
+                ["netting", rule] => {
+                    let rule = match *rule {
+                        "gross" => NettingRule::Gross,
+                        "bilateral" => NettingRule::Bilateral,
+                        other => return Err(parse_error(line_number, format!("unknown netting rule '{}'", other))),
+                    };
+                    if netting.replace(rule).is_some() {
+                        return Err(parse_error(line_number, "netting is declared twice"));
+                    }
+                }
+                ["dispute", "deviation", percent] => {
+                    let percent = percent.strip_suffix('%')
+                        .ok_or_else(|| parse_error(line_number, "dispute deviation must be a percentage"))?;
+                    let clause = DisputeClause { max_deviation_percent: parse_amount(line_number, percent)? };
+                    if dispute.replace(clause).is_some() {
+                        return Err(parse_error(line_number, "dispute clause is declared twice"));
+                    }
+                }
+                _ => return Err(parse_error(line_number, format!("unrecognised clause '{}'", line))),
+            }
+        }
+
+        let name = name.ok_or_else(|| BlockchainError::InvalidOperation("Contract has no 'contract' declaration".to_string()))?;
+        if rates.is_empty() {
+            return Err(BlockchainError::InvalidOperation("Contract declares no rates".to_string()));
+        }
+
+        Ok(Self {
+            name,
+            rates,
+            threshold_cents: threshold_cents.unwrap_or(0),
+            netting: netting.unwrap_or(NettingRule::Gross),
+            dispute,
+        })
+    }
+
+    /// Compile to VM bytecode
+    pub fn compile(&self) -> Vec<Instruction> {
+        let mut code = Vec::new();
+        code.push(Instruction::Log(format!("Settlement contract {}", self.name)));
+
+        // Charges per direction: sum of usage times rate
+        let directions: &[&str] = match self.netting {
+            NettingRule::Gross => &["forward"],
+            NettingRule::Bilateral => &["forward", "reverse"],
+        };
+        for direction in directions {
+            code.push(Instruction::Push(0));
+            for rate in &self.rates {
+                code.push(Instruction::Load(contract_slot(&format!("{}.{}", direction, rate.service))));
+                code.push(Instruction::Push(rate.cents_per_unit));
+                code.push(Instruction::Mul);
+                code.push(Instruction::Add);
+            }
+            code.push(Instruction::Store(contract_slot(&format!("{}_charges", direction))));
+        }
+
+        // Net amount and which side owes it
+        let forward = contract_slot("forward_charges");
+        let reverse = contract_slot("reverse_charges");
+        let net = contract_slot("net_amount");
+        let direction = contract_slot("direction");
+        match self.netting {
+            NettingRule::Gross => {
+                code.push(Instruction::Load(forward));
+                code.push(Instruction::Push(0));
+                code.push(Instruction::Store(direction));
+            }
+            NettingRule::Bilateral => {
+                code.push(Instruction::Load(forward));
+                code.push(Instruction::Load(reverse));
+                code.push(Instruction::Lt);
+                let to_reverse = code.len();
+                code.push(Instruction::JumpIf(0));
+
+                code.push(Instruction::Load(forward));
+                code.push(Instruction::Load(reverse));
+                code.push(Instruction::Sub);
+                code.push(Instruction::Push(0));
+                code.push(Instruction::Store(direction));
+                let to_net = code.len();
+                code.push(Instruction::Jump(0));
+
+                code[to_reverse] = Instruction::JumpIf(code.len());
+                code.push(Instruction::Load(reverse));
+                code.push(Instruction::Load(forward));
+                code.push(Instruction::Sub);
+                code.push(Instruction::Push(1));
+                code.push(Instruction::Store(direction));
+
+                code[to_net] = Instruction::Jump(code.len());
+            }
+        }
+        code.push(Instruction::Store(net));
+
+        if let Some(dispute) = &self.dispute {
+            Self::compile_dispute(&mut code, dispute, net);
+        }
+
+        // Settle only amounts above the threshold
+        code.push(Instruction::Load(net));
+        code.push(Instruction::Push(self.threshold_cents));
+        code.push(Instruction::Lt);
+        let to_below = code.len();
+        code.push(Instruction::JumpIf(0));
+
+        code.push(Instruction::Push(1));
+        code.push(Instruction::Store(contract_slot("settle")));
+        code.push(Instruction::Log("Settlement due".to_string()));
+        code.push(Instruction::Load(net));
+        code.push(Instruction::Halt);
+
+        code[to_below] = Instruction::JumpIf(code.len());
+        code.push(Instruction::Push(0));
+        code.push(Instruction::Store(contract_slot("settle")));
+        code.push(Instruction::Log("Below settlement threshold".to_string()));
+        code.push(Instruction::Push(0));
+        code.push(Instruction::Halt);
+
+        code
+    }
+
+    /// Halt with zero and mark the contract disputed if `|declared - net| * 100 > net * percent`
+    fn compile_dispute(code: &mut Vec<Instruction>, dispute: &DisputeClause, net: Blake2bHash) {
+        let declared = contract_slot("declared");
+
+        // Nothing declared, nothing to dispute
+        code.push(Instruction::Load(declared));
+        code.push(Instruction::Push(0));
+        code.push(Instruction::Eq);
+        let to_undisputed = code.len();
+        code.push(Instruction::JumpIf(0));
+
+        code.push(Instruction::Load(declared));
+        code.push(Instruction::Load(net));
+        code.push(Instruction::Lt);
+        let to_declared_low = code.len();
+        code.push(Instruction::JumpIf(0));
+
+        code.push(Instruction::Load(declared));
+        code.push(Instruction::Load(net));
+        code.push(Instruction::Sub);
+        let to_deviation = code.len();
+        code.push(Instruction::Jump(0));
+
+        code[to_declared_low] = Instruction::JumpIf(code.len());
+        code.push(Instruction::Load(net));
+        code.push(Instruction::Load(declared));
+        code.push(Instruction::Sub);
+
+        code[to_deviation] = Instruction::Jump(code.len());
+        code.push(Instruction::Push(100));
+        code.push(Instruction::Mul);
+        code.push(Instruction::Load(net));
+        code.push(Instruction::Push(dispute.max_deviation_percent));
+        code.push(Instruction::Mul);
+        code.push(Instruction::Gt);
+        let to_disputed = code.len();
+        code.push(Instruction::JumpIf(0));
+        let to_undisputed_end = code.len();
+        code.push(Instruction::Jump(0));
+
+        code[to_disputed] = Instruction::JumpIf(code.len());
+        code.push(Instruction::Log(format!("Dispute: declared amount deviates more than {}%", dispute.max_deviation_percent)));
+        code.push(Instruction::Push(1));
+        code.push(Instruction::Store(contract_slot("disputed")));
+        code.push(Instruction::Push(0));
+        code.push(Instruction::Halt);
+
+        code[to_undisputed] = Instruction::JumpIf(code.len());
+        code[to_undisputed_end] = Instruction::Jump(code.len());
+    }
+}
+
+impl fmt::Display for SettlementContractSource {
+    /// Canonical source, parses back to the same contract
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "contract \"{}\"", self.name)?;
+        for rate in &self.rates {
+            writeln!(f, "rate {} {} per {}", rate.service, rate.cents_per_unit, rate.unit)?;
+        }
+        writeln!(f, "threshold {}", self.threshold_cents)?;
+        match self.netting {
+            NettingRule::Gross => writeln!(f, "netting gross")?,
+            NettingRule::Bilateral => writeln!(f, "netting bilateral")?,
+        }
+        if let Some(dispute) = &self.dispute {
+            writeln!(f, "dispute deviation {}%", dispute.max_deviation_percent)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_contracts::vm::{ContractStorage, ContractVM, ExecutionContext, MemoryStorage};
+
+    const ROAMING_AGREEMENT: &str = r#"
+        # T-Mobile Germany and Vodafone UK wholesale roaming
+        contract "T-Mobile-DE/Vodafone-UK 2024"
+        rate voice 12 per minute
+        rate data 3 per mb      # per megabyte
+        threshold 10000
+        netting bilateral
+        dispute deviation 5%
+    "#;
+
+    fn execute(contract: &SettlementContractSource, slots: &[(&str, u64)]) -> (Option<u64>, MemoryStorage) {
+        let mut storage = MemoryStorage::new();
+        let address = Blake2bHash::from_data(b"roaming-agreement");
+        storage.set_code(&address, contract.compile()).unwrap();
+        for (name, value) in slots {
+            storage.set(&address, &contract_slot(name), value.to_le_bytes().to_vec()).unwrap();
+        }
+
+        let mut vm = ContractVM::new(storage);
+        let result = vm.execute(ExecutionContext {
+            contract_address: address,
+            caller: Blake2bHash::zero(),
+            timestamp: 1_700_000_000,
+            gas_limit: 100_000,
+            gas_used: 0,
+            value: 0,
+        }, &[]).unwrap();
+        assert!(result.success, "{:?}", result.error);
+
+        (result.return_value, vm.into_storage())
+    }
+
+    fn slot(storage: &MemoryStorage, name: &str) -> u64 {
+        let value = storage.get(&Blake2bHash::from_data(b"roaming-agreement"), &contract_slot(name)).unwrap().unwrap();
+        u64::from_le_bytes(value.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_source_round_trip() {
+        let contract = SettlementContractSource::parse(ROAMING_AGREEMENT).unwrap();
+        assert_eq!(contract.rates.len(), 2);
+        assert_eq!(contract.netting, NettingRule::Bilateral);
+        assert_eq!(contract.dispute, Some(DisputeClause { max_deviation_percent: 5 }));
+
+        let printed = contract.to_string();
+        assert_eq!(SettlementContractSource::parse(&printed).unwrap(), contract);
+
+        // Bytecode survives the encoding contract storage uses
+        let bytecode = bincode::serialize(&contract.compile()).unwrap();
+        let decoded: Vec<Instruction> = bincode::deserialize(&bytecode).unwrap();
+        assert_eq!(decoded, contract.compile());
+
+        assert!(SettlementContractSource::parse("rate voice 12 per minute").is_err());
+        assert!(SettlementContractSource::parse("contract \"x\"\nrate voice twelve per minute").is_err());
+        assert!(SettlementContractSource::parse("contract \"x\"\nrate voice 1 per minute\nnetting triangular").is_err());
+    }
+
+    #[test]
+    fn test_compiled_contract_execution() {
+        let contract = SettlementContractSource::parse(ROAMING_AGREEMENT).unwrap();
+
+        // 1000 minutes and 2000 MB forward, 200 minutes back: 18000 - 2400 = 15600 owed forward
+        let usage = [("forward.voice", 1000), ("forward.data", 2000), ("reverse.voice", 200)];
+        let (due, storage) = execute(&contract, &usage);
+        assert_eq!(due, Some(15_600));
+        assert_eq!(slot(&storage, "direction"), 0);
+        assert_eq!(slot(&storage, "settle"), 1);
+
+        // Netting flips the direction when the reverse side used more
+        let (due, storage) = execute(&contract, &[("forward.voice", 100), ("reverse.data", 5000)]);
+        assert_eq!(due, Some(13_800));
+        assert_eq!(slot(&storage, "direction"), 1);
+
+        // Amounts below the threshold are carried over
+        let (due, storage) = execute(&contract, &[("forward.voice", 10)]);
+        assert_eq!(due, Some(0));
+        assert_eq!(slot(&storage, "settle"), 0);
+
+        // A declaration within 5% settles, one further off is disputed
+        let (due, _) = execute(&contract, &[usage[0], usage[1], usage[2], ("declared", 16_000)]);
+        assert_eq!(due, Some(15_600));
+        let (due, storage) = execute(&contract, &[usage[0], usage[1], usage[2], ("declared", 20_000)]);
+        assert_eq!(due, Some(0));
+        assert_eq!(slot(&storage, "disputed"), 1);
+    }
+}
@@ -5,14 +5,23 @@ use ark_snark::SNARK;
 use ark_serialize::CanonicalDeserialize;
 use crate::primitives::{Result, BlockchainError, Blake2bHash};
 use crate::crypto::{BLSPublicKey, BLSSignature, BLSVerifier as RealBLSVerifier, PublicKey};
+use crate::zkp::trusted_setup::TrustedSetupCeremony;
 use std::collections::HashMap;
 
 /// Real ZK proof verifier for settlement contracts
+#[derive(Clone)]
 pub struct ZKProofVerifier {
     settlement_vk: Option<VerifyingKey<Bn254>>,
     cdr_privacy_vk: Option<VerifyingKey<Bn254>>,
 }
 
+/// Circuit a contract verifies a proof of, selecting the verifying key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ProofCircuit {
+    Settlement,
+    CDRPrivacy,
+}
+
 /// Settlement proof public inputs
 #[derive(Debug, Clone)]
 pub struct SettlementProofInputs {
@@ -99,6 +108,33 @@ impl ZKProofVerifier {
         Ok(is_valid)
     }
 
+    /// Verify a proof of `circuit` against public inputs as the circuit defines them
+    pub fn verify_with_inputs(
+        &self,
+        circuit: ProofCircuit,
+        proof_bytes: &[u8],
+        public_inputs: &[ark_bn254::Fr],
+    ) -> Result<bool> {
+        let vk = match circuit {
+            ProofCircuit::Settlement => self.settlement_vk.as_ref(),
+            ProofCircuit::CDRPrivacy => self.cdr_privacy_vk.as_ref(),
+        }.ok_or_else(|| BlockchainError::InvalidProof)?;
+
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        // A wrong number of inputs is an error rather than a failed check
+        if public_inputs.len() + 1 != vk.gamma_abc_g1.len() {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "{:?} proofs take {} public inputs, got {}", circuit, vk.gamma_abc_g1.len() - 1, public_inputs.len()
+            )));
+        }
+
+        let prepared_vk = ark_groth16::prepare_verifying_key(vk);
+        Groth16::<Bn254>::verify_proof(&prepared_vk, &proof, public_inputs)
+            .map_err(|_| BlockchainError::InvalidProof)
+    }
+
     fn prepare_settlement_inputs(&self, inputs: &SettlementProofInputs) -> Result<Vec<ark_bn254::Fr>> {
         use ark_ff::PrimeField;
 
@@ -143,6 +179,7 @@ impl ZKProofVerifier {
 }
 
 /// Real BLS signature verifier for multi-party validation
+#[derive(Clone)]
 pub struct BLSVerifier {
     verifier: RealBLSVerifier,
 }
@@ -203,6 +240,7 @@ impl BLSVerifier {
 }
 
 /// Combined cryptographic verifier for smart contracts
+#[derive(Clone)]
 pub struct ContractCryptoVerifier {
     pub zk_verifier: ZKProofVerifier,
     pub bls_verifier: BLSVerifier,
@@ -232,6 +270,17 @@ impl ContractCryptoVerifier {
         Ok(())
     }
 
+    /// Load the settlement and CDR privacy verifying keys of a completed trusted setup ceremony
+    pub async fn load_keys_from_ceremony(&mut self, ceremony: &TrustedSetupCeremony) -> Result<()> {
+        if ceremony.verifying_key_exists("settlement_calculation").await {
+            self.zk_verifier.settlement_vk = Some(ceremony.load_verifying_key("settlement_calculation").await?);
+        }
+        if ceremony.verifying_key_exists("cdr_privacy").await {
+            self.zk_verifier.cdr_privacy_vk = Some(ceremony.load_verifying_key("cdr_privacy").await?);
+        }
+        Ok(())
+    }
+
     /// Verify complete settlement transaction
     pub fn verify_settlement_transaction(
         &self,
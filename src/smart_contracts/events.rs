@@ -0,0 +1,100 @@
+// Contract events: structured logs contracts emit as their settlement state changes. Topics say
+// what happened and are indexed, data carries the details. Events of successful executions are
+// kept with their receipts, indexed by contract and topic in the chain store, and handed to
+// subscribers as their block is committed
+use serde::{Deserialize, Serialize};
+use crate::primitives::Blake2bHash;
+use super::consensus_integration::ContractReceipt;
+
+/// Topics an event carries at most
+pub const MAX_EVENT_TOPICS: usize = 4;
+
+/// Event emitted by a contract execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractEvent {
+    pub contract_address: Blake2bHash,
+    /// The first topic names the event by convention, see `event_topic`
+    pub topics: Vec<Blake2bHash>,
+    pub data: Vec<u8>,
+}
+
+/// Topic naming an event, the hash of its name
+pub fn event_topic(name: &str) -> Blake2bHash {
+    crate::primitives::primitives::hash_data(name.as_bytes())
+}
+
+/// Event with the position it was emitted at in the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub event: ContractEvent,
+    pub transaction_hash: Blake2bHash,
+    pub block_number: u32,
+    pub transaction_index: u32,
+    /// Position of the event among those of its transaction
+    pub log_index: u32,
+}
+
+impl EventRecord {
+    /// Events of a receipt, in emission order
+    pub fn of_receipt(receipt: &ContractReceipt) -> impl Iterator<Item = EventRecord> + '_ {
+        receipt.events.iter().enumerate().map(|(log_index, event)| EventRecord {
+            event: event.clone(),
+            transaction_hash: receipt.transaction_hash,
+            block_number: receipt.block_number,
+            transaction_index: receipt.transaction_index,
+            log_index: log_index as u32,
+        })
+    }
+}
+
+/// Selects events by emitting contract, topic and block, unset criteria matching any event
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventFilter {
+    pub contract: Option<Blake2bHash>,
+    pub topic: Option<Blake2bHash>,
+    #[serde(default)]
+    pub from_block: u32,
+}
+
+impl EventFilter {
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        self.contract.map_or(true, |contract| record.event.contract_address == contract)
+            && self.topic.map_or(true, |topic| record.event.topics.contains(&topic))
+            && record.block_number >= self.from_block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_filter() {
+        let contract = event_topic("contract");
+        let settled = event_topic("SettlementExecuted");
+        let receipt = ContractReceipt {
+            transaction_hash: event_topic("tx"),
+            contract_address: contract,
+            success: true,
+            gas_used: 0,
+            return_value: None,
+            logs: vec![],
+            events: vec![
+                ContractEvent { contract_address: contract, topics: vec![event_topic("SettlementProposed")], data: vec![] },
+                ContractEvent { contract_address: contract, topics: vec![settled], data: 850u64.to_le_bytes().to_vec() },
+            ],
+            error: None,
+            block_number: 7,
+            transaction_index: 2,
+        };
+
+        let records: Vec<_> = EventRecord::of_receipt(&receipt).collect();
+        assert_eq!(records.iter().map(|record| record.log_index).collect::<Vec<_>>(), vec![0, 1]);
+        assert!(records.iter().all(|record| (record.block_number, record.transaction_index) == (7, 2)));
+
+        let filter = EventFilter { contract: Some(contract), topic: Some(settled), from_block: 0 };
+        assert_eq!(records.iter().filter(|record| filter.matches(record)).count(), 1);
+        assert!(!EventFilter { from_block: 8, ..Default::default() }.matches(&records[0]));
+        assert!(!EventFilter { contract: Some(settled), ..Default::default() }.matches(&records[0]));
+    }
+}
@@ -1,38 +1,60 @@
 // MDBX-based smart contract storage (non-breaking addition)
 use std::sync::Arc;
-use crate::primitives::{Blake2bHash, Result};
+use crate::primitives::{Blake2bHash, BlockchainError, Height, Result};
 use crate::storage::MdbxChainStore;
-use crate::smart_contracts::vm::{ContractStorage, Instruction};
+use crate::storage::state_trie::{StateTrie, contract_storage_key, contract_code_key, expired_slots_key};
+use crate::smart_contracts::vm::{ContractStorage, Instruction, wasm_code_key};
+use crate::smart_contracts::state_expiry::{expiry_root, ArchivedSlot, SlotStatus, StateResurrection};
 
 /// MDBX-backed contract storage implementation
 /// This is an ADDITION to MemoryStorage, not a replacement
 pub struct MdbxContractStorage {
     mdbx_store: Arc<MdbxChainStore>,
+    /// Authenticated view of the writes, committed to by block state roots
+    state_trie: Option<Arc<std::sync::RwLock<StateTrie>>>,
+    /// Block being executed, recorded with each write for state expiry
+    block_number: Height,
 }
 
 impl MdbxContractStorage {
     pub fn new(mdbx_store: Arc<MdbxChainStore>) -> Self {
-        Self { mdbx_store }
+        Self { mdbx_store, state_trie: None, block_number: 0 }
     }
+
+    /// Mirror contract writes into the chain's state trie
+    pub fn with_state_trie(mut self, state_trie: Arc<std::sync::RwLock<StateTrie>>) -> Self {
+        self.state_trie = Some(state_trie);
+        self
+    }
+}
+
+/// Run a store future from the synchronous VM, which executes inside async block processing
+/// Needs the multi-threaded runtime
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
 }
 
 impl ContractStorage for MdbxContractStorage {
     fn get(&self, contract: &Blake2bHash, key: &Blake2bHash) -> Result<Option<Vec<u8>>> {
-        // Use tokio runtime to handle async in sync context
-        let rt = tokio::runtime::Handle::current();
-        rt.block_on(self.mdbx_store.get_contract_state(contract, key))
+        // Values of expired slots stay until compaction but are gone as far as contracts can tell
+        if let Some(SlotStatus::Expired { .. }) = block_on(self.mdbx_store.contract_slot_status(contract, key))? {
+            return Ok(None);
+        }
+        block_on(self.mdbx_store.get_contract_state(contract, key))
     }
 
     fn set(&mut self, contract: &Blake2bHash, key: &Blake2bHash, value: Vec<u8>) -> Result<()> {
-        // Use tokio runtime to handle async in sync context
-        let rt = tokio::runtime::Handle::current();
-        rt.block_on(self.mdbx_store.put_contract_state(contract, key, &value))
+        block_on(self.mdbx_store.put_contract_slot(contract, key, &value, self.block_number))?;
+
+        if let Some(state_trie) = &self.state_trie {
+            state_trie.write().unwrap().insert(contract_storage_key(contract, key), value);
+        }
+        Ok(())
     }
 
     fn get_code(&self, contract: &Blake2bHash) -> Result<Option<Vec<Instruction>>> {
         // Get bytecode from MDBX
-        let rt = tokio::runtime::Handle::current();
-        let bytecode_opt = rt.block_on(self.mdbx_store.get_contract_code(contract))?;
+        let bytecode_opt = block_on(self.mdbx_store.get_contract_code(contract))?;
 
         match bytecode_opt {
             Some(bytecode) => {
@@ -55,8 +77,74 @@ impl ContractStorage for MdbxContractStorage {
             ))?;
 
         // Store in MDBX
-        let rt = tokio::runtime::Handle::current();
-        rt.block_on(self.mdbx_store.put_contract_code(contract, &bytecode))
+        block_on(self.mdbx_store.put_contract_code(contract, &bytecode))?;
+
+        if let Some(state_trie) = &self.state_trie {
+            state_trie.write().unwrap().insert(contract_code_key(contract), bytecode);
+        }
+        Ok(())
+    }
+
+    fn begin_block(&mut self, block_number: Height) {
+        self.block_number = block_number;
+    }
+
+    fn expire_slots(&mut self, block_number: Height, cutoff: Height) -> Result<usize> {
+        let state_trie = self.state_trie.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidState("State expiry needs the state trie".to_string()))?;
+
+        // Only state of deployed contracts expires: registries kept under addresses without code
+        // are chain state, and WASM modules are code
+        let mut deployed = std::collections::HashMap::new();
+        let mut slots = Vec::new();
+        for (contract, key, status) in block_on(self.mdbx_store.contract_slot_statuses())? {
+            let SlotStatus::Live { touched_at } = status else { continue };
+            if touched_at >= cutoff || key == wasm_code_key() {
+                continue;
+            }
+            let is_deployed = match deployed.get(&contract) {
+                Some(is_deployed) => *is_deployed,
+                None => {
+                    let is_deployed = self.get_code(&contract)?.is_some() || self.get_wasm_code(&contract)?.is_some();
+                    deployed.insert(contract, is_deployed);
+                    is_deployed
+                }
+            };
+            if !is_deployed {
+                continue;
+            }
+            let value = block_on(self.mdbx_store.get_contract_state(&contract, &key))?.unwrap_or_default();
+            slots.push(ArchivedSlot { contract, key, value, touched_at });
+        }
+        if slots.is_empty() {
+            return Ok(0);
+        }
+
+        {
+            let mut state_trie = state_trie.write().unwrap();
+            for slot in &slots {
+                state_trie.remove(&contract_storage_key(&slot.contract, &slot.key));
+            }
+            state_trie.insert(expired_slots_key(block_number), expiry_root(&slots).as_bytes().to_vec());
+        }
+        block_on(self.mdbx_store.expire_contract_slots(block_number, &slots))?;
+        Ok(slots.len())
+    }
+
+    fn resurrect_slot(&mut self, resurrection: &StateResurrection) -> Result<()> {
+        let slot = &resurrection.slot;
+        let root = self.state_trie.as_ref()
+            .and_then(|state_trie| state_trie.read().unwrap().expired_slots_root(resurrection.expired_at))
+            .ok_or_else(|| BlockchainError::InvalidTransaction(format!("No contract state expired at block {}", resurrection.expired_at)))?;
+        if !resurrection.verify(&root) {
+            return Err(BlockchainError::InvalidTransaction("Archival proof does not match the expired state".to_string()));
+        }
+        // A slot comes back once per expiry, and not over a value written since
+        let status = block_on(self.mdbx_store.contract_slot_status(&slot.contract, &slot.key))?;
+        if status != Some(SlotStatus::Expired { expired_at: resurrection.expired_at }) {
+            return Err(BlockchainError::InvalidTransaction(format!("Slot {} of contract {} is not expired", slot.key, slot.contract)));
+        }
+        self.set(&slot.contract, &slot.key, slot.value.clone())
     }
 }
 
@@ -106,4 +194,41 @@ mod tests {
 
         println!("✅ MDBX contract storage tests passed");
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_state_expiry_and_resurrection() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Arc::new(MdbxChainStore::new(temp_dir.path()).unwrap());
+        let state_trie = Arc::new(std::sync::RwLock::new(StateTrie::new()));
+        let mut contract_storage = MdbxContractStorage::new(store.clone()).with_state_trie(state_trie.clone());
+
+        let contract = Blake2bHash::from_bytes([1; 32]);
+        let (stale, fresh) = (Blake2bHash::from_bytes([2; 32]), Blake2bHash::from_bytes([3; 32]));
+        contract_storage.begin_block(3);
+        contract_storage.set_code(&contract, vec![Instruction::Halt]).unwrap();
+        contract_storage.set(&contract, &stale, b"march-rates".to_vec()).unwrap();
+        contract_storage.begin_block(40);
+        contract_storage.set(&contract, &fresh, b"april-rates".to_vec()).unwrap();
+
+        // Only the slot unwritten since before the cutoff expires
+        contract_storage.begin_block(65);
+        assert_eq!(contract_storage.expire_slots(65, 33).unwrap(), 1);
+        assert_eq!(contract_storage.get(&contract, &stale).unwrap(), None);
+        assert_eq!(contract_storage.get(&contract, &fresh).unwrap(), Some(b"april-rates".to_vec()));
+        assert!(state_trie.read().unwrap().get(&contract_storage_key(&contract, &stale)).is_none());
+
+        // Compaction deletes the value once the expiry is final, the archive keeps it
+        assert_eq!(store.compact_contract_state(64).await.unwrap().values_deleted, 0);
+        assert_eq!(store.compact_contract_state(96).await.unwrap().values_deleted, 1);
+        let archived = store.archived_slots(65).await.unwrap();
+        assert_eq!(archived[0].value, b"march-rates".to_vec());
+
+        let resurrection = StateResurrection::new(&archived, 0, 65).unwrap();
+        let mut forged = resurrection.clone();
+        forged.slot.value = b"forged-rates".to_vec();
+        assert!(contract_storage.resurrect_slot(&forged).is_err());
+        contract_storage.resurrect_slot(&resurrection).unwrap();
+        assert_eq!(contract_storage.get(&contract, &stale).unwrap(), Some(b"march-rates".to_vec()));
+        assert!(contract_storage.resurrect_slot(&resurrection).is_err());
+    }
 }
\ No newline at end of file
@@ -1,9 +1,16 @@
 // Smart contracts module for SP CDR reconciliation blockchain
 pub mod settlement;
 pub mod vm;
+pub mod bytecode_validator;
 pub mod crypto_verifier;
 pub mod consensus_integration;
+pub mod events;
+pub mod state_expiry;
+pub mod parallel;
 pub mod settlement_contract;
+pub mod contract_language;
+#[cfg(feature = "wasm")]
+pub mod wasm_backend;
 pub mod mdbx_storage;  // Non-breaking addition
 
 // Legacy settlement data structures (keeping for compatibility)
@@ -18,9 +25,18 @@ pub use settlement::{
 
 // Real smart contract components
 pub use vm::{ContractVM, ExecutionContext, ExecutionResult, Instruction, ContractStorage, MemoryStorage};
-pub use crypto_verifier::{ZKProofVerifier, BLSVerifier, ContractCryptoVerifier, SettlementProofInputs, CDRPrivacyInputs};
-pub use consensus_integration::{ConsensusContractEngine, ContractTransaction, ContractDeployment, ContractReceipt};
-pub use settlement_contract::{ExecutableSettlementContract, SettlementContractCompiler, SettlementContractFactory};
+pub use crypto_verifier::{ZKProofVerifier, BLSVerifier, ContractCryptoVerifier, ProofCircuit, SettlementProofInputs, CDRPrivacyInputs};
+pub use bytecode_validator::{ContractClass, validate_bytecode};
+pub use consensus_integration::{ConsensusContractEngine, ContractTransaction, ContractDeployment, ContractReceipt, ContractUpgrade, ContractVersion};
+pub use events::{ContractEvent, EventFilter, EventRecord, event_topic};
+pub use state_expiry::{ArchivedSlot, SlotStatus, StateResurrection};
+pub use settlement_contract::{
+    ExecutableSettlementContract, SettlementContractCompiler, SettlementContractFactory,
+    EscrowLock, EscrowStatus, EscrowTransaction, PaymentConfirmation, SettlementEscrow,
+};
+pub use contract_language::{SettlementContractSource, RateClause, NettingRule, DisputeClause};
+#[cfg(feature = "wasm")]
+pub use wasm_backend::WasmContractVM;
 pub use mdbx_storage::{MdbxContractStorage, create_mdbx_contract_storage};  // Non-breaking addition
 
 use serde::{Deserialize, Serialize};
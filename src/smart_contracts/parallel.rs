@@ -0,0 +1,128 @@
+// Parallel execution of a block's contract transactions: transactions are grouped by the contract
+// they call, one group per settlement pair, and each group runs in a worker task against a shared
+// read-only view of contract storage that records the slots it reads and buffers those it writes.
+// Groups that touched no slot another group wrote are merged in block order. The conflicting groups
+// run again as one group in block order, and if that run reaches a slot a kept group wrote the whole
+// run executes serially, so every validator ends up with the state serial execution would have left
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedRwLockReadGuard;
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::consensus_integration::ContractTransaction;
+use super::vm::{ContractStorage, ContractVM, ExecutionResult, Instruction};
+
+/// Storage slot, contract and key
+pub type Slot = (Blake2bHash, Blake2bHash);
+
+/// Slots a worker read from the storage before the block, and the values it wrote
+pub type Accesses = (HashSet<Slot>, BTreeMap<Slot, Vec<u8>>);
+
+/// Transactions a worker executed, their results, and its accesses
+pub type GroupRun = (Vec<(u32, ContractTransaction)>, Vec<ExecutionResult>, Accesses);
+
+/// Storage of one worker: reads fall through to the storage before the block and are recorded,
+/// writes stay buffered until the group is merged
+pub struct OverlayStorage<S: ContractStorage + 'static> {
+    base: Arc<OwnedRwLockReadGuard<ContractVM<S>>>,
+    reads: Mutex<HashSet<Slot>>,
+    writes: BTreeMap<Slot, Vec<u8>>,
+}
+
+impl<S: ContractStorage + 'static> OverlayStorage<S> {
+    pub fn new(base: Arc<OwnedRwLockReadGuard<ContractVM<S>>>) -> Self {
+        Self { base, reads: Mutex::new(HashSet::new()), writes: BTreeMap::new() }
+    }
+
+    /// Slots read from the storage before the block, and the values written, in slot order
+    pub fn into_accesses(self) -> Accesses {
+        (self.reads.into_inner().unwrap(), self.writes)
+    }
+}
+
+impl<S: ContractStorage + 'static> ContractStorage for OverlayStorage<S> {
+    fn get(&self, contract: &Blake2bHash, key: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        if let Some(value) = self.writes.get(&(*contract, *key)) {
+            return Ok(Some(value.clone()));
+        }
+        self.reads.lock().unwrap().insert((*contract, *key));
+        self.base.storage().get(contract, key)
+    }
+
+    fn set(&mut self, contract: &Blake2bHash, key: &Blake2bHash, value: Vec<u8>) -> Result<()> {
+        self.writes.insert((*contract, *key), value);
+        Ok(())
+    }
+
+    fn get_code(&self, contract: &Blake2bHash) -> Result<Option<Vec<Instruction>>> {
+        self.base.storage().get_code(contract)
+    }
+
+    fn set_code(&mut self, contract: &Blake2bHash, _code: Vec<Instruction>) -> Result<()> {
+        Err(BlockchainError::InvalidOperation(format!(
+            "Code of {} cannot change during parallel execution", contract
+        )))
+    }
+}
+
+/// Transactions of a run grouped by the contract they call, in block order within each group and
+/// groups ordered by their first transaction
+pub fn group_by_contract(transactions: Vec<(u32, ContractTransaction)>) -> Vec<Vec<(u32, ContractTransaction)>> {
+    let mut groups: Vec<Vec<(u32, ContractTransaction)>> = Vec::new();
+    for (index, transaction) in transactions {
+        match groups.iter_mut().find(|group| group[0].1.contract_address == transaction.contract_address) {
+            Some(group) => group.push((index, transaction)),
+            None => groups.push(vec![(index, transaction)]),
+        }
+    }
+    groups
+}
+
+/// Groups that read or wrote a slot another group wrote, whose parallel results cannot be kept
+pub fn conflicting_groups(accesses: &[&Accesses]) -> HashSet<usize> {
+    let mut conflicts = HashSet::new();
+    for (i, (reads, writes)) in accesses.iter().enumerate() {
+        for (j, (_, other_writes)) in accesses.iter().enumerate().filter(|(j, _)| *j != i) {
+            if other_writes.keys().any(|slot| reads.contains(slot) || writes.contains_key(slot)) {
+                conflicts.insert(i);
+                conflicts.insert(j);
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(contract: &[u8], key: &[u8]) -> Slot {
+        (Blake2bHash::from_data(contract), Blake2bHash::from_data(key))
+    }
+
+    #[test]
+    fn test_groups_and_conflicts() {
+        let transaction = |contract: &[u8]| ContractTransaction {
+            contract_address: Blake2bHash::from_data(contract),
+            caller: Blake2bHash::zero(),
+            input_data: vec![],
+            gas_limit: 1_000,
+            value: 0,
+            nonce: 0,
+        };
+        let groups = group_by_contract(vec![(0, transaction(b"de-fr")), (1, transaction(b"uk-es")), (2, transaction(b"de-fr"))]);
+        let indices: Vec<Vec<u32>> = groups.iter().map(|group| group.iter().map(|(index, _)| *index).collect()).collect();
+        assert_eq!(indices, vec![vec![0, 2], vec![1]]);
+
+        // Groups keeping to their own contract's slots do not conflict, whoever reads a shared slot does
+        let access = |reads: Vec<Slot>, writes: Vec<Slot>| {
+            (reads.into_iter().collect::<HashSet<_>>(), writes.into_iter().map(|slot| (slot, vec![1])).collect::<BTreeMap<_, _>>())
+        };
+        let own = access(vec![slot(b"de-fr", b"total")], vec![slot(b"de-fr", b"total")]);
+        let other = access(vec![slot(b"uk-es", b"total")], vec![slot(b"uk-es", b"total")]);
+        let reader = access(vec![slot(b"de-fr", b"total")], vec![]);
+        assert!(conflicting_groups(&[&own, &other]).is_empty());
+        assert_eq!(conflicting_groups(&[&own, &other, &reader]), HashSet::from([0, 2]));
+        assert_eq!(conflicting_groups(&[&own, &own]), HashSet::from([0, 1]));
+    }
+}
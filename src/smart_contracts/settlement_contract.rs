@@ -1,13 +1,24 @@
-// Executable settlement smart contracts with real business logic
-use crate::primitives::{Result, BlockchainError, Blake2bHash};
+// Executable settlement smart contracts with real business logic, and the built-in escrow
+// settlement obligations are locked in until their payment is confirmed on chain
+use crate::primitives::{Result, BlockchainError, Blake2bHash, NetworkId, to_canonical_bytes};
+use crate::blockchain::block::SettlementTransaction;
 use super::vm::Instruction;
+use super::contract_language::{contract_slot, SettlementContractSource};
+use crate::blockchain::tariff::{ServiceBreakdown, TariffService};
+use crate::network::settlement_messaging::ConfirmationType;
 use super::crypto_verifier::{SettlementProofInputs, CDRPrivacyInputs};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Compilable settlement smart contract
 pub struct SettlementContractCompiler;
 
 impl SettlementContractCompiler {
+    /// Compile a contract written in the settlement contract language
+    pub fn compile_source(source: &str) -> Result<Vec<Instruction>> {
+        Ok(SettlementContractSource::parse(source)?.compile())
+    }
+
     /// Compile CDR batch validation contract
     pub fn compile_cdr_batch_validator() -> Vec<Instruction> {
         vec![
@@ -320,6 +331,31 @@ impl ExecutableSettlementContract {
         }
     }
 
+    /// Create a contract from settlement contract source, seeded with the per-service usage
+    /// of both directions and the amount declared for the period
+    pub fn new_service_settlement(
+        contract_id: Blake2bHash,
+        source: &SettlementContractSource,
+        forward: &ServiceBreakdown,
+        reverse: &ServiceBreakdown,
+        declared_cents: u64,
+    ) -> Self {
+        let mut state = HashMap::new();
+        for (direction, breakdown) in [("forward", forward), ("reverse", reverse)] {
+            for service in [TariffService::Voice, TariffService::Data, TariffService::Sms] {
+                let (units, _) = breakdown.service(service);
+                state.insert(contract_slot(&format!("{}.{}", direction, service.contract_name())), units);
+            }
+        }
+        state.insert(contract_slot("declared"), declared_cents);
+
+        Self {
+            contract_address: contract_id,
+            bytecode: source.compile(),
+            state,
+        }
+    }
+
     /// Get contract deployment data
     pub fn get_deployment_data(&self) -> (Blake2bHash, Vec<Instruction>) {
         (self.contract_address, self.bytecode.clone())
@@ -396,6 +432,194 @@ impl SettlementContractFactory {
     }
 }
 
+/// Time a debtor has to get an escrowed settlement's payment confirmed before a dispute opens
+pub const ESCROW_PAYMENT_TERM_SECS: u64 = 7 * 24 * 3600;
+
+/// Contract storage address the settlement escrow is kept under, no code runs there
+pub fn settlement_escrow_address() -> Blake2bHash {
+    crate::primitives::primitives::hash_data(b"settlement-escrow")
+}
+
+/// Key of the escrow of a settlement
+pub fn escrow_key(settlement_tx: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"settlement-escrow".to_vec();
+    data.extend_from_slice(settlement_tx.as_bytes());
+    crate::primitives::primitives::hash_data(&data)
+}
+
+/// Key of the locked escrows, as deadline and settlement
+pub fn locked_escrows_key() -> Blake2bHash {
+    crate::primitives::primitives::hash_data(b"settlement-escrow-locked")
+}
+
+/// Escrow transaction, executed by the built-in escrow contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EscrowTransaction {
+    Lock(EscrowLock),
+    Confirm(PaymentConfirmation),
+}
+
+/// Settlement obligation its debtor locks in escrow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowLock {
+    pub settlement_tx: Blake2bHash,
+    pub creditor: String,
+    pub debtor: String,
+    pub amount: u64,
+    pub currency: String,
+    /// Payment rail, such as a clearing house, whose confirmation releases the escrow besides the creditor's
+    pub payment_agent: Option<String>,
+    /// Signature of the debtor's registered key
+    pub signature: Vec<u8>,
+    /// Signature of the creditor's registered key agreeing to the payment agent, empty without one
+    #[serde(default)]
+    pub agent_approval: Vec<u8>,
+}
+
+impl EscrowLock {
+    /// Bytes the debtor signs, every field but the signature
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let fields = (&self.settlement_tx, &self.creditor, &self.debtor, self.amount, &self.currency, &self.payment_agent);
+        let mut payload = b"sp-cdr-escrow-lock".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(&fields).expect("escrow locks have a canonical encoding"));
+        payload
+    }
+
+    /// Bytes the creditor signs to let the payment agent confirm the payment, `None` without an agent
+    pub fn agent_approval_payload(&self) -> Option<Vec<u8>> {
+        self.payment_agent.as_ref().map(|agent| {
+            let mut payload = b"sp-cdr-escrow-agent".to_vec();
+            payload.extend_from_slice(&to_canonical_bytes(&(&self.settlement_tx, &self.creditor, agent))
+                .expect("escrow agents have a canonical encoding"));
+            payload
+        })
+    }
+
+    /// Check the lock holds the terms of the settlement it names, `creditor` and `debtor` being the
+    /// networks the registry has for the lock's operators
+    pub fn check_terms(&self, settlement: &SettlementTransaction, creditor: &NetworkId, debtor: &NetworkId) -> Result<()> {
+        // Settlements name networks in either their `name:country` or their `Debug` form
+        let names = |network: &str, id: &NetworkId| network == id.to_string() || network == format!("{:?}", id);
+        if !names(&settlement.creditor_network, creditor) || !names(&settlement.debtor_network, debtor) {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Settlement {} is owed by {} to {}, not by {} to {}",
+                self.settlement_tx, settlement.debtor_network, settlement.creditor_network, self.debtor, self.creditor
+            )));
+        }
+        if (settlement.amount, settlement.currency.as_str()) != (self.amount, self.currency.as_str()) {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "Settlement {} is for {} {}, not {} {}",
+                self.settlement_tx, settlement.amount, settlement.currency, self.amount, self.currency
+            )));
+        }
+        if self.payment_agent.as_ref().is_some_and(|agent| *agent == self.debtor) {
+            return Err(BlockchainError::InvalidTransaction(format!("{} cannot confirm its own payment", self.debtor)));
+        }
+        Ok(())
+    }
+}
+
+/// `PaymentConfirmed` message recorded on chain by the creditor, or by the escrow's payment agent
+/// as proof of the transfer it executed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentConfirmation {
+    pub settlement_tx: Blake2bHash,
+    pub confirmer: String,
+    pub confirmation_type: ConfirmationType,
+    /// Reference of the transfer at the payment rail
+    pub transaction_ref: Option<String>,
+    /// Signature of the confirmer's registered key
+    pub signature: Vec<u8>,
+}
+
+impl PaymentConfirmation {
+    /// Bytes the confirmer signs, every field but the signature
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let fields = (&self.settlement_tx, &self.confirmer, &self.confirmation_type, &self.transaction_ref);
+        let mut payload = b"sp-cdr-payment-confirmation".to_vec();
+        payload.extend_from_slice(&to_canonical_bytes(&fields).expect("payment confirmations have a canonical encoding"));
+        payload
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscrowStatus {
+    /// Waiting for the payment to be confirmed
+    Locked,
+    /// Payment confirmed, the obligation is discharged
+    Released {
+        confirmed_by: String,
+        transaction_ref: Option<String>,
+        released_at: u64,
+    },
+    /// Deadline passed unconfirmed, the settlement is disputed
+    Disputed { opened_at: u64 },
+}
+
+/// Settlement obligation held by the escrow contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementEscrow {
+    pub settlement_tx: Blake2bHash,
+    pub creditor: String,
+    pub debtor: String,
+    pub amount: u64,
+    pub currency: String,
+    pub payment_agent: Option<String>,
+    pub locked_at: u64,
+    pub deadline: u64,
+    pub status: EscrowStatus,
+}
+
+impl SettlementEscrow {
+    /// Escrow of a lock whose debtor signature was checked, due `ESCROW_PAYMENT_TERM_SECS` after `locked_at`
+    pub fn lock(lock: &EscrowLock, locked_at: u64) -> Self {
+        Self {
+            settlement_tx: lock.settlement_tx,
+            creditor: lock.creditor.clone(),
+            debtor: lock.debtor.clone(),
+            amount: lock.amount,
+            currency: lock.currency.clone(),
+            payment_agent: lock.payment_agent.clone(),
+            locked_at,
+            deadline: locked_at + ESCROW_PAYMENT_TERM_SECS,
+            status: EscrowStatus::Locked,
+        }
+    }
+
+    /// Release the obligation on a confirmation whose signature was checked
+    /// Only the creditor and the payment agent confirm, and only payments confirmed as received
+    pub fn release(&mut self, confirmation: &PaymentConfirmation, now: u64) -> Result<()> {
+        if self.status != EscrowStatus::Locked {
+            return Err(BlockchainError::InvalidTransaction(format!("Escrow of settlement {} is not locked", self.settlement_tx)));
+        }
+        if !matches!(confirmation.confirmation_type, ConfirmationType::PaymentConfirmed) {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "{:?} does not release the escrow of settlement {}", confirmation.confirmation_type, self.settlement_tx
+            )));
+        }
+        if confirmation.confirmer != self.creditor && self.payment_agent.as_ref() != Some(&confirmation.confirmer) {
+            return Err(BlockchainError::InvalidTransaction(format!(
+                "{} cannot confirm the payment of settlement {}", confirmation.confirmer, self.settlement_tx
+            )));
+        }
+        self.status = EscrowStatus::Released {
+            confirmed_by: confirmation.confirmer.clone(),
+            transaction_ref: confirmation.transaction_ref.clone(),
+            released_at: now,
+        };
+        Ok(())
+    }
+
+    /// Open a dispute if the deadline passed with the escrow still locked, returning whether it did
+    pub fn expire(&mut self, now: u64) -> bool {
+        if self.status != EscrowStatus::Locked || now < self.deadline {
+            return false;
+        }
+        self.status = EscrowStatus::Disputed { opened_at: now };
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -440,6 +664,25 @@ mod tests {
         assert_eq!(contracts.len(), 3); // validator + calculator + executor
     }
 
+    #[test]
+    fn test_service_settlement_creation() {
+        let source = SettlementContractSource::parse(
+            "contract \"T-Mobile-DE/Vodafone-UK\"\nrate voice 12 per minute\nrate data 3 per mb\nnetting bilateral"
+        ).unwrap();
+        let forward = ServiceBreakdown { voice_minutes: 1000, voice_cents: 12_000, data_mb: 2000, data_cents: 6_000, ..Default::default() };
+        let reverse = ServiceBreakdown { voice_minutes: 200, voice_cents: 2_400, ..Default::default() };
+
+        let contract_id = crate::primitives::primitives::hash_data(b"test_service_settlement");
+        let contract = ExecutableSettlementContract::new_service_settlement(contract_id, &source, &forward, &reverse, 15_600);
+
+        assert_eq!(contract.bytecode, source.compile());
+        assert_eq!(contract.state[&contract_slot("forward.voice")], 1000);
+        assert_eq!(contract.state[&contract_slot("forward.data")], 2000);
+        assert_eq!(contract.state[&contract_slot("reverse.voice")], 200);
+        assert_eq!(contract.state[&contract_slot("reverse.sms")], 0);
+        assert_eq!(contract.state[&contract_slot("declared")], 15_600);
+    }
+
     #[test]
     fn test_netting_contract_creation() {
         let operators = vec!["T-Mobile-DE".to_string(), "Vodafone-UK".to_string(), "Orange-FR".to_string()];
@@ -454,4 +697,58 @@ mod tests {
         assert!(!contract.bytecode.is_empty());
         assert_eq!(contract.state.len(), 3);
     }
+
+    #[test]
+    fn test_escrow_release_and_timeout() {
+        let lock = EscrowLock {
+            settlement_tx: Blake2bHash::from_data(b"settlement"),
+            creditor: "T-Mobile-DE".to_string(),
+            debtor: "Vodafone-UK".to_string(),
+            amount: 125_000,
+            currency: "EUR".to_string(),
+            payment_agent: Some("EU-Clearing".to_string()),
+            signature: vec![],
+            agent_approval: vec![],
+        };
+        let confirmation = |confirmer: &str, confirmation_type| PaymentConfirmation {
+            settlement_tx: lock.settlement_tx,
+            confirmer: confirmer.to_string(),
+            confirmation_type,
+            transaction_ref: Some("SEPA-42".to_string()),
+            signature: vec![],
+        };
+
+        // Only the creditor or the payment agent release it, and only with a confirmed payment
+        let mut escrow = SettlementEscrow::lock(&lock, 1_000);
+        assert_eq!(escrow.deadline, 1_000 + ESCROW_PAYMENT_TERM_SECS);
+        assert!(escrow.release(&confirmation("Vodafone-UK", ConfirmationType::PaymentConfirmed), 2_000).is_err());
+        assert!(escrow.release(&confirmation("T-Mobile-DE", ConfirmationType::PaymentSent), 2_000).is_err());
+        escrow.release(&confirmation("EU-Clearing", ConfirmationType::PaymentConfirmed), 2_000).unwrap();
+        assert!(matches!(escrow.status, EscrowStatus::Released { ref confirmed_by, released_at: 2_000, .. } if confirmed_by == "EU-Clearing"));
+        assert!(!escrow.expire(escrow.deadline));
+
+        // Unconfirmed past the deadline, a dispute opens and the escrow can no longer be released
+        let mut escrow = SettlementEscrow::lock(&lock, 1_000);
+        assert!(!escrow.expire(escrow.deadline - 1));
+        assert!(escrow.expire(escrow.deadline));
+        assert_eq!(escrow.status, EscrowStatus::Disputed { opened_at: escrow.deadline });
+        assert!(escrow.release(&confirmation("T-Mobile-DE", ConfirmationType::PaymentConfirmed), escrow.deadline).is_err());
+
+        // Locks must carry the terms of the settlement they name, and a debtor is never its own agent
+        let (creditor, debtor) = (NetworkId::new("T-Mobile-DE", "DE"), NetworkId::new("Vodafone-UK", "GB"));
+        let settlement = SettlementTransaction {
+            creditor_network: format!("{:?}", creditor),
+            debtor_network: format!("{:?}", debtor),
+            amount: 125_000,
+            currency: "EUR".to_string(),
+            period: "2024-03".to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![],
+        };
+        lock.check_terms(&settlement, &creditor, &debtor).unwrap();
+        assert!(lock.check_terms(&settlement, &debtor, &creditor).is_err());
+        assert!(lock.check_terms(&SettlementTransaction { amount: 1, ..settlement.clone() }, &creditor, &debtor).is_err());
+        let own_agent = EscrowLock { payment_agent: Some("Vodafone-UK".to_string()), ..lock.clone() };
+        assert!(own_agent.check_terms(&settlement, &creditor, &debtor).is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,119 @@
+// Contract state expiry: storage slots of deployed contracts that go unwritten for the number of
+// epochs governance sets expire in the first micro block of an epoch. They leave the state trie,
+// which keeps a Merkle root over the slots of each expiry instead, and are archived by the node;
+// an archival proof against that root resurrects a slot. Compaction deletes expired values once
+// their expiry is final, and nodes pruning history drop old archives
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::blockchain::MerkleProof;
+use crate::blockchain::light_client::merkle_root;
+use crate::primitives::{hash_canonical, Blake2bHash, Height, Policy};
+
+/// How often expired contract state is compacted
+pub const COMPACTION_INTERVAL: Duration = Duration::from_secs(3_600);
+
+/// When a storage slot was last written, or when it expired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlotStatus {
+    Live { touched_at: Height },
+    Expired { expired_at: Height },
+}
+
+/// Expired storage slot as archived, a leaf of the Merkle root of its expiry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedSlot {
+    pub contract: Blake2bHash,
+    pub key: Blake2bHash,
+    pub value: Vec<u8>,
+    pub touched_at: Height,
+}
+
+impl ArchivedSlot {
+    pub fn leaf(&self) -> Blake2bHash {
+        hash_canonical(self)
+    }
+}
+
+/// Root over the slots expiring together, in the order they are archived in
+pub fn expiry_root(slots: &[ArchivedSlot]) -> Blake2bHash {
+    merkle_root(&slots.iter().map(ArchivedSlot::leaf).collect::<Vec<_>>())
+}
+
+/// Restores an expired slot, proven to be among the slots expiring at `expired_at`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateResurrection {
+    pub slot: ArchivedSlot,
+    pub expired_at: Height,
+    pub proof: MerkleProof,
+}
+
+impl StateResurrection {
+    /// Resurrection of the slot at `index` among `slots`, all expired at `expired_at`
+    pub fn new(slots: &[ArchivedSlot], index: usize, expired_at: Height) -> Option<Self> {
+        let leaves: Vec<_> = slots.iter().map(ArchivedSlot::leaf).collect();
+        Some(Self {
+            slot: slots.get(index)?.clone(),
+            expired_at,
+            proof: MerkleProof::new(&leaves, index)?,
+        })
+    }
+
+    /// Whether the proof leads from the slot to `root`
+    pub fn verify(&self, root: &Blake2bHash) -> bool {
+        self.proof.root(&self.slot.leaf()).as_ref() == Some(root)
+    }
+}
+
+/// Slots last written before the returned height expire at `block_number`, `None` if nothing
+/// expires there: state is kept indefinitely with `expiry_epochs` 0, and expiry only runs in the
+/// first micro block of an epoch
+pub fn expiry_cutoff(block_number: Height, expiry_epochs: u64) -> Option<Height> {
+    if expiry_epochs == 0 || block_number % Policy::EPOCH_LENGTH != 1 {
+        return None;
+    }
+    let kept = expiry_epochs.saturating_mul(Policy::EPOCH_LENGTH as u64);
+    (block_number as u64).checked_sub(kept).map(|cutoff| cutoff as Height)
+}
+
+/// What a compaction pass deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// Values of slots whose expiry is final
+    pub values_deleted: usize,
+    /// Archived slots past the pruning window
+    pub archived_dropped: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(name: &str, touched_at: Height) -> ArchivedSlot {
+        ArchivedSlot {
+            contract: Blake2bHash::from_data(b"roaming-agreement"),
+            key: Blake2bHash::from_data(name.as_bytes()),
+            value: 850u64.to_le_bytes().to_vec(),
+            touched_at,
+        }
+    }
+
+    #[test]
+    fn test_expiry_and_resurrection_proof() {
+        let epoch = Policy::EPOCH_LENGTH;
+        assert_eq!(expiry_cutoff(4 * epoch + 1, 2), Some(2 * epoch + 1));
+        assert_eq!(expiry_cutoff(4 * epoch + 2, 2), None);
+        assert_eq!(expiry_cutoff(4 * epoch + 1, 0), None);
+        assert_eq!(expiry_cutoff(epoch + 1, 2), None);
+
+        let slots = vec![slot("forward.voice", 3), slot("reverse.voice", 5), slot("declared", 9)];
+        let root = expiry_root(&slots);
+        let resurrection = StateResurrection::new(&slots, 1, 4 * epoch + 1).unwrap();
+        assert!(resurrection.verify(&root));
+
+        // A proof only holds for the value that expired
+        let mut forged = resurrection.clone();
+        forged.slot.value = 1u64.to_le_bytes().to_vec();
+        assert!(!forged.verify(&root));
+        assert!(StateResurrection::new(&slots, 3, 4 * epoch + 1).is_none());
+    }
+}
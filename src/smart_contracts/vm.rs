@@ -1,11 +1,14 @@
 // Real smart contract virtual machine for CDR settlement
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::primitives::{Blake2bHash, Result, BlockchainError};
-use super::crypto_verifier::{ContractCryptoVerifier, SettlementProofInputs, CDRPrivacyInputs};
+use crate::primitives::{Blake2bHash, Height, Result, BlockchainError};
+use super::crypto_verifier::{ContractCryptoVerifier, ProofCircuit, SettlementProofInputs, CDRPrivacyInputs};
+use super::events::{ContractEvent, MAX_EVENT_TOPICS};
+use super::state_expiry::StateResurrection;
+use crate::blockchain::tariff::{RateTable, TariffService, tariff_registry_address};
 
 /// Smart contract bytecode instruction set
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Instruction {
     // Stack operations
     Push(u64),
@@ -28,15 +31,42 @@ pub enum Instruction {
     // Control flow
     Jump(usize),
     JumpIf(usize),
+    /// Pop a gas amount, an input length and a memory offset, and call the contract with that much
+    /// memory as input, forwarding at most the gas asked for, 0 asking for all it may. Pushes the
+    /// callee's return value and 1, or 0 and 0 if the call failed and was reverted
     Call(Blake2bHash),
+    /// Pop a length and a memory offset and stop, returning that much memory as return data
     Return,
+    /// Push the length of the data the last call returned
+    ReturnDataSize,
+    /// Pop a length, a return data offset and a memory offset, and copy that much of the data the last call returned into memory
+    ReturnDataCopy,
 
     // State operations
     Load(Blake2bHash),    // Load from contract state
     Store(Blake2bHash),   // Store to contract state
 
+    // Call input and memory
+    /// Push the length of the call's input
+    CallDataSize,
+    /// Pop an input offset, push the 8 input bytes at it as a little-endian value, zero past the end
+    CallDataLoad,
+    /// Pop a length, an input offset and a memory offset, and copy that much input into memory
+    CallDataCopy,
+    /// Pop a memory offset, push the 8 bytes at it as a little-endian value
+    MLoad,
+    /// Pop a value and a memory offset, write the value at it as 8 little-endian bytes
+    MStore,
+
     // CDR-specific operations
     VerifyProof,          // Verify ZK proof
+    /// Verify the Groth16 proof of `circuit` held in memory: a compressed proof followed by its
+    /// public inputs as 32-byte little-endian field elements, `len` bytes from `offset` in all
+    VerifyProofAt {
+        circuit: ProofCircuit,
+        offset: usize,
+        len: usize,
+    },
     CheckSignature,       // Verify BLS signature
     ValidateNetwork,      // Check network authorization
     CalculateSettlement,  // Compute settlement amount
@@ -49,7 +79,19 @@ pub enum Instruction {
 
     // Debugging
     Log(String),
+    /// Pop a length and a memory offset, and emit an event with these topics carrying that much memory as data
+    Emit {
+        topics: Vec<Blake2bHash>,
+    },
     Halt,
+
+    // Tariffs
+    /// Push the published rate of a service at the block time, see `crate::blockchain::tariff`
+    LookupRate {
+        table: Blake2bHash,
+        service: TariffService,
+        destination: Option<String>,
+    },
 }
 
 /// Contract execution context
@@ -85,11 +127,22 @@ impl GasCosts {
     pub const LT: u64 = 3;
     pub const GT: u64 = 3;
 
+    // Call input and memory, plus a word cost per 32 bytes copied or added to memory
+    pub const CALLDATA_SIZE: u64 = 2;
+    pub const CALLDATA_LOAD: u64 = 3;
+    pub const CALLDATA_COPY: u64 = 3;
+    pub const MLOAD: u64 = 3;
+    pub const MSTORE: u64 = 3;
+    pub const COPY_WORD: u64 = 3;
+    pub const MEMORY_WORD: u64 = 3;
+
     // Control flow
     pub const JUMP: u64 = 8;
     pub const JUMP_IF: u64 = 10;
     pub const CALL: u64 = 700;
     pub const RETURN: u64 = 1;
+    pub const RETURNDATA_SIZE: u64 = 2;
+    pub const RETURNDATA_COPY: u64 = 3;
 
     // State operations (expensive)
     pub const LOAD: u64 = 200;
@@ -97,6 +150,7 @@ impl GasCosts {
 
     // CDR-specific operations (very expensive)
     pub const VERIFY_PROOF: u64 = 50000;    // ZK proof verification is expensive
+    pub const VERIFY_PROOF_INPUT: u64 = 500; // Per public input of a proof verified from memory
     pub const CHECK_SIGNATURE: u64 = 3000;  // BLS signature verification
     pub const VALIDATE_NETWORK: u64 = 100;
     pub const CALCULATE_SETTLEMENT: u64 = 1000;
@@ -109,9 +163,29 @@ impl GasCosts {
 
     // Debugging
     pub const LOG: u64 = 375;
+
+    // Events, plus a cost per topic and per byte of data
+    pub const EMIT: u64 = 375;
+    pub const EMIT_TOPIC: u64 = 375;
+    pub const EMIT_BYTE: u64 = 8;
     pub const HALT: u64 = 1;
 }
 
+/// Values the stack holds at most
+pub const STACK_LIMIT: usize = 1024;
+
+/// Bytes of memory an execution may use
+pub const MEMORY_LIMIT: usize = 1024 * 1024;
+
+/// Calls a contract execution may nest
+pub const MAX_CALL_DEPTH: usize = 16;
+
+/// Size of a compressed Groth16 proof over BN254
+pub const GROTH16_PROOF_SIZE: usize = 128;
+
+/// Size of a serialized public input field element
+pub const FIELD_ELEMENT_SIZE: usize = 32;
+
 /// Gas execution error types
 #[derive(Debug, Clone)]
 pub enum GasError {
@@ -126,6 +200,34 @@ pub trait ContractStorage: Send + Sync {
     fn set(&mut self, contract: &Blake2bHash, key: &Blake2bHash, value: Vec<u8>) -> Result<()>;
     fn get_code(&self, contract: &Blake2bHash) -> Result<Option<Vec<Instruction>>>;
     fn set_code(&mut self, contract: &Blake2bHash, code: Vec<Instruction>) -> Result<()>;
+
+    /// WASM module of a contract, kept in its state under a reserved key
+    fn get_wasm_code(&self, contract: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        self.get(contract, &wasm_code_key())
+    }
+
+    fn set_wasm_code(&mut self, contract: &Blake2bHash, module: Vec<u8>) -> Result<()> {
+        self.set(contract, &wasm_code_key(), module)
+    }
+
+    /// Block the following writes belong to, for storages tracking when slots were last written
+    fn begin_block(&mut self, _block_number: Height) {}
+
+    /// Expire the slots last written before `cutoff` at `block_number`, returning how many expired
+    /// Storages without expiry keep every slot
+    fn expire_slots(&mut self, _block_number: Height, _cutoff: Height) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Restore an expired slot from its archival proof
+    fn resurrect_slot(&mut self, _resurrection: &StateResurrection) -> Result<()> {
+        Err(BlockchainError::InvalidOperation("Storage does not expire contract state".to_string()))
+    }
+}
+
+/// State key holding a contract's WASM module
+pub fn wasm_code_key() -> Blake2bHash {
+    crate::primitives::primitives::hash_data(b"contract-wasm-code")
 }
 
 /// Simple in-memory storage implementation
@@ -163,13 +265,32 @@ impl ContractStorage for MemoryStorage {
     }
 }
 
+/// Frame of a calling contract, set aside while its callee runs
+struct CallFrame {
+    contract: Blake2bHash,
+    stack: Vec<u64>,
+    program_counter: usize,
+    calldata: Vec<u8>,
+    memory: Vec<u8>,
+}
+
 /// Smart contract virtual machine
 pub struct ContractVM<S: ContractStorage> {
     storage: S,
     stack: Vec<u64>,
-    call_stack: Vec<usize>,
+    call_stack: Vec<CallFrame>,
     program_counter: usize,
     crypto_verifier: ContractCryptoVerifier,
+    /// Storage writes of the running execution, applied only if it succeeds
+    pending_writes: Vec<(Blake2bHash, Blake2bHash, Vec<u8>)>,
+    /// Input of the running execution
+    calldata: Vec<u8>,
+    /// Scratch memory of the running execution, grown in 32-byte words
+    memory: Vec<u8>,
+    /// Data returned by the last call the running frame made
+    return_data: Vec<u8>,
+    /// Data the running frame returns, set by `Return`
+    output: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -177,7 +298,11 @@ pub struct ExecutionResult {
     pub success: bool,
     pub return_value: Option<u64>,
     pub gas_used: u64,
+    /// Data the contract returned with `Return`
+    pub return_data: Vec<u8>,
     pub logs: Vec<String>,
+    /// Events emitted, only kept if the execution succeeds
+    pub events: Vec<ContractEvent>,
     pub error: Option<String>,
 }
 
@@ -189,6 +314,11 @@ impl<S: ContractStorage> ContractVM<S> {
             call_stack: Vec::new(),
             program_counter: 0,
             crypto_verifier: ContractCryptoVerifier::new(),
+            pending_writes: Vec::new(),
+            calldata: Vec::new(),
+            memory: Vec::new(),
+            return_data: Vec::new(),
+            output: Vec::new(),
         }
     }
 
@@ -199,6 +329,11 @@ impl<S: ContractStorage> ContractVM<S> {
             call_stack: Vec::new(),
             program_counter: 0,
             crypto_verifier,
+            pending_writes: Vec::new(),
+            calldata: Vec::new(),
+            memory: Vec::new(),
+            return_data: Vec::new(),
+            output: Vec::new(),
         }
     }
 
@@ -234,11 +369,23 @@ impl<S: ContractStorage> ContractVM<S> {
             Instruction::JumpIf(_) => GasCosts::JUMP_IF,
             Instruction::Call(_) => GasCosts::CALL,
             Instruction::Return => GasCosts::RETURN,
+            Instruction::ReturnDataSize => GasCosts::RETURNDATA_SIZE,
+            Instruction::ReturnDataCopy => GasCosts::RETURNDATA_COPY,
 
             Instruction::Load(_) => GasCosts::LOAD,
             Instruction::Store(_) => GasCosts::STORE,
 
+            Instruction::CallDataSize => GasCosts::CALLDATA_SIZE,
+            Instruction::CallDataLoad => GasCosts::CALLDATA_LOAD,
+            Instruction::CallDataCopy => GasCosts::CALLDATA_COPY,
+            Instruction::MLoad => GasCosts::MLOAD,
+            Instruction::MStore => GasCosts::MSTORE,
+
             Instruction::VerifyProof => GasCosts::VERIFY_PROOF,
+            Instruction::VerifyProofAt { len, .. } => {
+                let inputs = len.saturating_sub(GROTH16_PROOF_SIZE) / FIELD_ELEMENT_SIZE;
+                GasCosts::VERIFY_PROOF.saturating_add(GasCosts::VERIFY_PROOF_INPUT.saturating_mul(inputs as u64))
+            }
             Instruction::CheckSignature => GasCosts::CHECK_SIGNATURE,
             Instruction::ValidateNetwork => GasCosts::VALIDATE_NETWORK,
             Instruction::CalculateSettlement => GasCosts::CALCULATE_SETTLEMENT,
@@ -249,7 +396,10 @@ impl<S: ContractStorage> ContractVM<S> {
             Instruction::Transfer(_, _) => GasCosts::TRANSFER,
 
             Instruction::Log(_) => GasCosts::LOG,
+            Instruction::Emit { topics } => GasCosts::EMIT + GasCosts::EMIT_TOPIC * topics.len() as u64,
             Instruction::Halt => GasCosts::HALT,
+
+            Instruction::LookupRate { .. } => GasCosts::LOAD,
         }
     }
 
@@ -262,75 +412,214 @@ impl<S: ContractStorage> ContractVM<S> {
         Ok(self.storage.get_code(address)?.is_some())
     }
 
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    pub fn crypto_verifier(&self) -> &ContractCryptoVerifier {
+        &self.crypto_verifier
+    }
+
+    pub fn crypto_verifier_mut(&mut self) -> &mut ContractCryptoVerifier {
+        &mut self.crypto_verifier
+    }
+
+    pub fn into_parts(self) -> (S, ContractCryptoVerifier) {
+        (self.storage, self.crypto_verifier)
+    }
+
+    /// Read a storage slot, seeing the running execution's own writes
+    fn load(&self, contract: &Blake2bHash, key: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        match self.pending_writes.iter().rev().find(|(c, k, _)| c == contract && k == key) {
+            Some((_, _, value)) => Ok(Some(value.clone())),
+            None => self.storage.get(contract, key),
+        }
+    }
+
     pub fn execute(
         &mut self,
         context: ExecutionContext,
         input: &[u8],
     ) -> Result<ExecutionResult> {
-        // Reset VM state
-        self.stack.clear();
+        // Load contract code
+        let code = self.storage.get_code(&context.contract_address)?
+            .ok_or_else(|| BlockchainError::ContractNotFound)?;
+
+        self.execute_code(context, &code, input)
+    }
+
+    /// Execute `code` against the storage of `context.contract_address`
+    /// Used to run a retained earlier version of an upgraded contract
+    pub fn execute_code(
+        &mut self,
+        context: ExecutionContext,
+        code: &[Instruction],
+        input: &[u8],
+    ) -> Result<ExecutionResult> {
         self.call_stack.clear();
-        self.program_counter = 0;
+        self.pending_writes.clear();
 
         let mut ctx = context;
         let mut logs = Vec::new();
+        let mut events = Vec::new();
 
-        // Load contract code
-        let code = self.storage.get_code(&ctx.contract_address)?
-            .ok_or_else(|| BlockchainError::ContractNotFound)?;
-
-        // Push input data onto stack
-        for &byte in input {
-            self.push(byte as u64, &mut ctx)?;
-        }
+        match self.run_frame(&mut ctx, code, input, &mut logs, &mut events) {
+            Ok((return_value, return_data)) => {
+                for (contract, key, value) in std::mem::take(&mut self.pending_writes) {
+                    self.storage.set(&contract, &key, value)?;
+                }
 
-        // Execute instructions
-        while self.program_counter < code.len() {
-            if ctx.gas_used >= ctx.gas_limit {
-                return Ok(ExecutionResult {
+                Ok(ExecutionResult {
+                    success: true,
+                    return_value,
+                    gas_used: ctx.gas_used,
+                    return_data,
+                    logs,
+                    events,
+                    error: None,
+                })
+            }
+            Err(e) => {
+                // Failed executions leave storage untouched
+                self.pending_writes.clear();
+                Ok(ExecutionResult {
                     success: false,
                     return_value: None,
                     gas_used: ctx.gas_used,
+                    return_data: Vec::new(),
                     logs,
-                    error: Some("Out of gas".to_string()),
-                });
+                    events: Vec::new(),
+                    error: Some(e.to_string()),
+                })
             }
+        }
+    }
 
-            let instruction = &code[self.program_counter];
+    /// Run `code` in a fresh frame until it stops, returning the value left on top of its stack and its return data
+    fn run_frame(
+        &mut self,
+        ctx: &mut ExecutionContext,
+        code: &[Instruction],
+        input: &[u8],
+        logs: &mut Vec<String>,
+        events: &mut Vec<ContractEvent>,
+    ) -> Result<(Option<u64>, Vec<u8>)> {
+        self.stack.clear();
+        self.program_counter = 0;
+        self.calldata = input.to_vec();
+        self.memory.clear();
+        self.return_data.clear();
+        self.output.clear();
+
+        // Push input data onto stack, input too long for it is only read as call data
+        if input.len() <= STACK_LIMIT {
+            for &byte in input {
+                self.push(byte as u64, ctx)?;
+            }
+        }
 
-            match self.execute_instruction(instruction, &mut ctx, &mut logs) {
-                Ok(should_continue) => {
-                    if !should_continue {
-                        break;
-                    }
-                },
-                Err(e) => {
-                    return Ok(ExecutionResult {
-                        success: false,
-                        return_value: None,
-                        gas_used: ctx.gas_used,
-                        logs,
-                        error: Some(e.to_string()),
-                    });
-                }
+        // Execute instructions
+        while self.program_counter < code.len() {
+            if ctx.gas_used >= ctx.gas_limit {
+                return Err(BlockchainError::OutOfGas);
             }
 
+            // Advance before executing, so jumps land exactly on their target
+            let instruction = &code[self.program_counter];
             self.program_counter += 1;
+
+            if !self.execute_instruction(instruction, ctx, logs, events)? {
+                break;
+            }
         }
 
-        let return_value = if !self.stack.is_empty() {
-            Some(self.stack.pop().unwrap())
+        Ok((self.stack.pop(), std::mem::take(&mut self.output)))
+    }
+
+    /// Call `callee` in a frame of its own, the caller's frame set aside until it returns. Calls
+    /// that fail, go deeper than `MAX_CALL_DEPTH` or reenter a contract already executing are
+    /// reverted: their writes and events are dropped and the caller is told with a 0 flag
+    fn call(
+        &mut self,
+        callee: &Blake2bHash,
+        input: Vec<u8>,
+        requested_gas: u64,
+        ctx: &mut ExecutionContext,
+        logs: &mut Vec<String>,
+        events: &mut Vec<ContractEvent>,
+    ) -> Result<(u64, bool)> {
+        let refused = if self.call_stack.len() >= MAX_CALL_DEPTH {
+            Some(format!("call depth limit of {} reached", MAX_CALL_DEPTH))
+        } else if *callee == ctx.contract_address || self.call_stack.iter().any(|frame| frame.contract == *callee) {
+            Some(format!("reentrant call into {}", callee))
         } else {
             None
         };
+        let code = match refused {
+            Some(reason) => Err(reason),
+            None => self.storage.get_code(callee)?.ok_or_else(|| format!("no contract at {}", callee)),
+        };
+        let code = match code {
+            Ok(code) => code,
+            Err(reason) => {
+                logs.push(format!("{}: call to {} refused: {}", ctx.contract_address, callee, reason));
+                self.return_data.clear();
+                return Ok((0, false));
+            }
+        };
 
-        Ok(ExecutionResult {
-            success: true,
-            return_value,
-            gas_used: ctx.gas_used,
-            logs,
-            error: None,
-        })
+        // Every caller keeps a 64th of its remaining gas, enough to handle the callee failing
+        let available = ctx.gas_limit.saturating_sub(ctx.gas_used);
+        let forwardable = available - available / 64;
+        let gas_limit = if requested_gas == 0 { forwardable } else { requested_gas.min(forwardable) };
+        let mut callee_ctx = ExecutionContext {
+            contract_address: *callee,
+            caller: ctx.contract_address,
+            timestamp: ctx.timestamp,
+            gas_limit,
+            gas_used: 0,
+            value: 0,
+        };
+
+        let (writes, emitted) = (self.pending_writes.len(), events.len());
+        self.call_stack.push(CallFrame {
+            contract: ctx.contract_address,
+            stack: std::mem::take(&mut self.stack),
+            program_counter: self.program_counter,
+            calldata: std::mem::take(&mut self.calldata),
+            memory: std::mem::take(&mut self.memory),
+        });
+        let outcome = self.run_frame(&mut callee_ctx, &code, &input, logs, events);
+        let frame = self.call_stack.pop().expect("frame pushed for this call");
+        self.stack = frame.stack;
+        self.program_counter = frame.program_counter;
+        self.calldata = frame.calldata;
+        self.memory = frame.memory;
+
+        match outcome {
+            Ok((return_value, return_data)) => {
+                ctx.gas_used += callee_ctx.gas_used;
+                self.return_data = return_data;
+                Ok((return_value.unwrap_or(0), true))
+            }
+            Err(e) => {
+                // Failed callees use up the gas forwarded to them
+                ctx.gas_used += gas_limit;
+                self.pending_writes.truncate(writes);
+                events.truncate(emitted);
+                self.return_data.clear();
+                logs.push(format!("{}: call to {} failed: {}", ctx.contract_address, callee, e));
+                Ok((0, false))
+            }
+        }
     }
 
     fn execute_instruction(
@@ -338,6 +627,7 @@ impl<S: ContractStorage> ContractVM<S> {
         instruction: &Instruction,
         ctx: &mut ExecutionContext,
         logs: &mut Vec<String>,
+        events: &mut Vec<ContractEvent>,
     ) -> Result<bool> {
         // Consume gas for this instruction
         let gas_cost = self.get_instruction_gas_cost(instruction);
@@ -352,45 +642,156 @@ impl<S: ContractStorage> ContractVM<S> {
                 self.pop(ctx)?;
             },
 
+            Instruction::Dup => {
+                let value = self.pop(ctx)?;
+                self.push(value, ctx)?;
+                self.push(value, ctx)?;
+            },
+
+            Instruction::Swap => {
+                let b = self.pop(ctx)?;
+                let a = self.pop(ctx)?;
+                self.push(b, ctx)?;
+                self.push(a, ctx)?;
+            },
+
             Instruction::Add => {
                 let b = self.pop(ctx)?;
                 let a = self.pop(ctx)?;
                 self.push(a.wrapping_add(b), ctx)?;
             },
 
+            Instruction::Sub => {
+                let b = self.pop(ctx)?;
+                let a = self.pop(ctx)?;
+                self.push(a.wrapping_sub(b), ctx)?;
+            },
+
             Instruction::Mul => {
                 let b = self.pop(ctx)?;
                 let a = self.pop(ctx)?;
                 self.push(a.wrapping_mul(b), ctx)?;
             },
 
+            Instruction::Div | Instruction::Mod => {
+                let b = self.pop(ctx)?;
+                let a = self.pop(ctx)?;
+                if b == 0 {
+                    return Err(BlockchainError::InvalidOperation("Division by zero".to_string()));
+                }
+                self.push(if matches!(instruction, Instruction::Div) { a / b } else { a % b }, ctx)?;
+            },
+
             Instruction::Eq => {
                 let b = self.pop(ctx)?;
                 let a = self.pop(ctx)?;
                 self.push(if a == b { 1 } else { 0 }, ctx)?;
             },
 
+            Instruction::Lt => {
+                let b = self.pop(ctx)?;
+                let a = self.pop(ctx)?;
+                self.push(if a < b { 1 } else { 0 }, ctx)?;
+            },
+
+            Instruction::Gt => {
+                let b = self.pop(ctx)?;
+                let a = self.pop(ctx)?;
+                self.push(if a > b { 1 } else { 0 }, ctx)?;
+            },
+
+            Instruction::Jump(addr) => {
+                self.program_counter = *addr;
+            },
+
             Instruction::JumpIf(addr) => {
                 let condition = self.pop(ctx)?;
                 if condition != 0 {
                     self.program_counter = *addr;
-                    return Ok(true); // Don't increment PC
                 }
             },
 
             Instruction::Store(key) => {
                 let value = self.pop(ctx)?;
                 let value_bytes = value.to_le_bytes().to_vec();
-                self.storage.set(&ctx.contract_address, key, value_bytes)?;
+                self.pending_writes.push((ctx.contract_address, *key, value_bytes));
+            },
+
+            Instruction::LookupRate { table, service, destination } => {
+                let bytes = self.storage.get(&tariff_registry_address(), table)?
+                    .ok_or_else(|| BlockchainError::NotFound(format!("Rate table {}", table)))?;
+                let rate_table: RateTable = bincode::deserialize(&bytes)
+                    .map_err(|e| BlockchainError::Serialization(format!("Invalid rate table: {}", e)))?;
+                let rate = rate_table.lookup(*service, ctx.timestamp, destination.as_deref())
+                    .ok_or_else(|| BlockchainError::NotFound(format!(
+                        "{} publishes no {:?} rate for {}", rate_table.operator, service, rate_table.partner
+                    )))?;
+                self.push(rate.cents_per_unit, ctx)?;
             },
 
             Instruction::Load(key) => {
-                let value_bytes = self.storage.get(&ctx.contract_address, key)?
+                let value_bytes = self.load(&ctx.contract_address, key)?
                     .unwrap_or_else(|| vec![0; 8]);
                 let value = u64::from_le_bytes(value_bytes.try_into().unwrap_or([0; 8]));
                 self.push(value, ctx)?;
             },
 
+            Instruction::CallDataSize => {
+                self.push(self.calldata.len() as u64, ctx)?;
+            },
+
+            Instruction::CallDataLoad => {
+                let offset = self.pop(ctx)?;
+                let mut bytes = [0u8; 8];
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = usize::try_from(offset).ok()
+                        .and_then(|offset| self.calldata.get(offset.checked_add(i)?))
+                        .copied()
+                        .unwrap_or(0);
+                }
+                self.push(u64::from_le_bytes(bytes), ctx)?;
+            },
+
+            Instruction::CallDataCopy => {
+                let len = self.pop(ctx)? as usize;
+                let data_offset = self.pop(ctx)? as usize;
+                let memory_offset = self.pop(ctx)? as usize;
+                self.consume_gas(ctx, GasCosts::COPY_WORD.saturating_mul(len.div_ceil(32) as u64))?;
+                let end = self.expand_memory(memory_offset, len, ctx)?;
+
+                // Input past the end is copied as zeroes
+                let available = self.calldata.len().saturating_sub(data_offset).min(len);
+                let memory = &mut self.memory[memory_offset..end];
+                if available > 0 {
+                    memory[..available].copy_from_slice(&self.calldata[data_offset..data_offset + available]);
+                }
+                memory[available..].fill(0);
+            },
+
+            Instruction::MLoad => {
+                let offset = self.pop(ctx)? as usize;
+                let end = self.expand_memory(offset, 8, ctx)?;
+                let value = u64::from_le_bytes(self.memory[offset..end].try_into().unwrap());
+                self.push(value, ctx)?;
+            },
+
+            Instruction::MStore => {
+                let value = self.pop(ctx)?;
+                let offset = self.pop(ctx)? as usize;
+                let end = self.expand_memory(offset, 8, ctx)?;
+                self.memory[offset..end].copy_from_slice(&value.to_le_bytes());
+            },
+
+            Instruction::VerifyProofAt { circuit, offset, len } => {
+                let region = offset.checked_add(*len)
+                    .and_then(|end| self.memory.get(*offset..end))
+                    .ok_or_else(|| BlockchainError::InvalidOperation(format!(
+                        "Proof at {}..{} lies outside the {} bytes of memory", offset, offset.saturating_add(*len), self.memory.len()
+                    )))?;
+                let is_valid = self.verify_proof_region(*circuit, region)?;
+                self.push(if is_valid { 1 } else { 0 }, ctx)?;
+            },
+
             Instruction::VerifyProof => {
                 // Pop proof data from stack
                 let proof_len = self.pop(ctx)? as usize;
@@ -461,6 +862,60 @@ impl<S: ContractStorage> ContractVM<S> {
                 logs.push(format!("{}: {}", ctx.contract_address, message));
             },
 
+            Instruction::Call(callee) => {
+                let requested_gas = self.pop(ctx)?;
+                let len = self.pop(ctx)? as usize;
+                let offset = self.pop(ctx)? as usize;
+                let end = self.expand_memory(offset, len, ctx)?;
+                let input = self.memory[offset..end].to_vec();
+                let (return_value, success) = self.call(callee, input, requested_gas, ctx, logs, events)?;
+                self.push(return_value, ctx)?;
+                self.push(success as u64, ctx)?;
+            },
+
+            Instruction::Return => {
+                let len = self.pop(ctx)? as usize;
+                let offset = self.pop(ctx)? as usize;
+                let end = self.expand_memory(offset, len, ctx)?;
+                self.output = self.memory[offset..end].to_vec();
+                return Ok(false);
+            },
+
+            Instruction::ReturnDataSize => {
+                self.push(self.return_data.len() as u64, ctx)?;
+            },
+
+            Instruction::ReturnDataCopy => {
+                let len = self.pop(ctx)? as usize;
+                let data_offset = self.pop(ctx)? as usize;
+                let memory_offset = self.pop(ctx)? as usize;
+                let data_end = data_offset.checked_add(len)
+                    .filter(|end| *end <= self.return_data.len())
+                    .ok_or_else(|| BlockchainError::InvalidOperation(format!(
+                        "Copy past the {} bytes of return data", self.return_data.len()
+                    )))?;
+                self.consume_gas(ctx, GasCosts::COPY_WORD.saturating_mul(len.div_ceil(32) as u64))?;
+                let end = self.expand_memory(memory_offset, len, ctx)?;
+                self.memory[memory_offset..end].copy_from_slice(&self.return_data[data_offset..data_end]);
+            },
+
+            Instruction::Emit { topics } => {
+                if topics.len() > MAX_EVENT_TOPICS {
+                    return Err(BlockchainError::InvalidOperation(format!(
+                        "Event with {} topics, at most {} allowed", topics.len(), MAX_EVENT_TOPICS
+                    )));
+                }
+                let len = self.pop(ctx)? as usize;
+                let offset = self.pop(ctx)? as usize;
+                self.consume_gas(ctx, GasCosts::EMIT_BYTE.saturating_mul(len as u64))?;
+                let end = self.expand_memory(offset, len, ctx)?;
+                events.push(ContractEvent {
+                    contract_address: ctx.contract_address,
+                    topics: topics.clone(),
+                    data: self.memory[offset..end].to_vec(),
+                });
+            },
+
             Instruction::Halt => {
                 return Ok(false);
             },
@@ -475,8 +930,43 @@ impl<S: ContractStorage> ContractVM<S> {
         Ok(true)
     }
 
+    /// Grow memory to hold `len` bytes at `offset`, charging for the words added; returns the end
+    fn expand_memory(&mut self, offset: usize, len: usize, ctx: &mut ExecutionContext) -> Result<usize> {
+        let end = offset.checked_add(len)
+            .filter(|end| *end <= MEMORY_LIMIT)
+            .ok_or_else(|| BlockchainError::InvalidOperation(format!("Memory access past the {} byte limit", MEMORY_LIMIT)))?;
+        let words = end.div_ceil(32);
+        let current_words = self.memory.len() / 32;
+        if words > current_words {
+            self.consume_gas(ctx, GasCosts::MEMORY_WORD * (words - current_words) as u64)?;
+            self.memory.resize(words * 32, 0);
+        }
+        Ok(end)
+    }
+
+    /// Verify a proof laid out as `VerifyProofAt` takes it
+    fn verify_proof_region(&self, circuit: ProofCircuit, region: &[u8]) -> Result<bool> {
+        use ark_serialize::CanonicalDeserialize;
+
+        if region.len() < GROTH16_PROOF_SIZE || (region.len() - GROTH16_PROOF_SIZE) % FIELD_ELEMENT_SIZE != 0 {
+            return Err(BlockchainError::InvalidOperation(format!(
+                "Proof region of {} bytes is not a {} byte proof followed by {} byte inputs",
+                region.len(), GROTH16_PROOF_SIZE, FIELD_ELEMENT_SIZE
+            )));
+        }
+        let (proof, inputs) = region.split_at(GROTH16_PROOF_SIZE);
+
+        // Inputs must be canonical field elements, so each has one encoding
+        let public_inputs = inputs.chunks(FIELD_ELEMENT_SIZE)
+            .map(|chunk| ark_bn254::Fr::deserialize_compressed(chunk)
+                .map_err(|_| BlockchainError::InvalidOperation("Public input is not a field element".to_string())))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.crypto_verifier.zk_verifier().verify_with_inputs(circuit, proof, &public_inputs)
+    }
+
     fn push(&mut self, value: u64, _ctx: &mut ExecutionContext) -> Result<()> {
-        if self.stack.len() >= 1024 {
+        if self.stack.len() >= STACK_LIMIT {
             return Err(BlockchainError::StackOverflow);
         }
         self.stack.push(value);
@@ -687,4 +1177,257 @@ mod tests {
         assert!(result.error.is_some());
         assert!(result.error.unwrap().contains("Out of gas"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_failed_execution_reverts_storage() {
+        let storage = MemoryStorage::new();
+        let mut vm = ContractVM::new(storage);
+
+        let contract_addr = crate::primitives::primitives::hash_data(b"revert_contract");
+        let key = crate::primitives::primitives::hash_data(b"total_amount");
+
+        // Store succeeds, then the proof check runs out of gas
+        let program = vec![
+            Instruction::Push(42),
+            Instruction::Store(key),
+            Instruction::VerifyProof,
+            Instruction::Halt,
+        ];
+
+        vm.deploy_contract(contract_addr, program).unwrap();
+
+        let context = ExecutionContext {
+            contract_address: contract_addr,
+            caller: Blake2bHash::zero(),
+            timestamp: 1640995200,
+            gas_limit: 1000,
+            gas_used: 0,
+            value: 0,
+        };
+
+        let result = vm.execute(context, &[]).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.gas_used, 501);
+        assert_eq!(vm.storage.get(&contract_addr, &key).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verify_proof_from_calldata() {
+        use ark_bn254::{Bn254, Fr};
+        use ark_groth16::Groth16;
+        use ark_serialize::CanonicalSerialize;
+        use ark_snark::SNARK;
+        use crate::zkp::circuits::{CDRCharges, CDRPrivacyCircuit, ServiceCharge};
+
+        let mut rng = ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(CDRPrivacyCircuit::<Fr>::empty(), &mut rng).unwrap();
+        let charges = CDRCharges {
+            voice: ServiceCharge::of(10, 150),
+            data: ServiceCharge::of(100, 500),
+            ..Default::default()
+        };
+        let proof = Groth16::<Bn254>::prove(&pk, CDRPrivacyCircuit::<Fr>::new(&charges, 7, 2024, 42, 99), &mut rng).unwrap();
+
+        // Call data: the compressed proof followed by its public inputs
+        let mut input = Vec::new();
+        proof.serialize_compressed(&mut input).unwrap();
+        for public_input in CDRPrivacyCircuit::<Fr>::public_inputs(charges.net_cents(), 2024, 42) {
+            public_input.serialize_compressed(&mut input).unwrap();
+        }
+
+        let mut vk_bytes = Vec::new();
+        vk.serialize_compressed(&mut vk_bytes).unwrap();
+        let mut crypto_verifier = ContractCryptoVerifier::new();
+        crypto_verifier.zk_verifier.load_cdr_privacy_key(&vk_bytes).unwrap();
+        let mut vm = ContractVM::new_with_crypto(MemoryStorage::new(), crypto_verifier);
+
+        let contract_addr = crate::primitives::primitives::hash_data(b"proof_contract");
+        let program = vec![
+            Instruction::Push(0),
+            Instruction::Push(0),
+            Instruction::CallDataSize,
+            Instruction::CallDataCopy,
+            Instruction::VerifyProofAt { circuit: ProofCircuit::CDRPrivacy, offset: 0, len: input.len() },
+            Instruction::Halt,
+        ];
+        vm.deploy_contract(contract_addr, program).unwrap();
+
+        let context = ExecutionContext {
+            contract_address: contract_addr,
+            caller: Blake2bHash::zero(),
+            timestamp: 1640995200,
+            gas_limit: 100_000,
+            gas_used: 0,
+            value: 0,
+        };
+
+        let result = vm.execute(context.clone(), &input).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.return_value, Some(1));
+
+        // A different net charge than the one proven fails verification
+        let mut tampered = input.clone();
+        tampered[GROTH16_PROOF_SIZE] ^= 1;
+        let result = vm.execute(context.clone(), &tampered).unwrap();
+        assert!(result.success);
+        assert_eq!(result.return_value, Some(0));
+
+        // Without call data the proof region lies outside memory
+        let result = vm.execute(context, &[]).unwrap();
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn test_emit_event() {
+        let mut vm = ContractVM::new(MemoryStorage::new());
+        let contract_addr = crate::primitives::primitives::hash_data(b"event_contract");
+        let settled = crate::smart_contracts::event_topic("SettlementExecuted");
+
+        // Amount written to memory, then emitted as the event's data
+        let program = vec![
+            Instruction::Push(0),
+            Instruction::Push(85000),
+            Instruction::MStore,
+            Instruction::Push(0),
+            Instruction::Push(8),
+            Instruction::Emit { topics: vec![settled, contract_addr] },
+            Instruction::Halt,
+        ];
+        vm.deploy_contract(contract_addr, program).unwrap();
+
+        let context = ExecutionContext {
+            contract_address: contract_addr,
+            caller: Blake2bHash::zero(),
+            timestamp: 1640995200,
+            gas_limit: 10_000,
+            gas_used: 0,
+            value: 0,
+        };
+        let result = vm.execute(context.clone(), &[]).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.events, vec![ContractEvent {
+            contract_address: contract_addr,
+            topics: vec![settled, contract_addr],
+            data: 85000u64.to_le_bytes().to_vec(),
+        }]);
+
+        // Events of failed executions are dropped
+        let result = vm.execute(ExecutionContext { gas_limit: 1_000, ..context }, &[]).unwrap();
+        assert!(!result.success);
+        assert!(result.events.is_empty());
+    }
+
+    #[test]
+    fn test_settlement_calls_tariff_contract() {
+        use crate::blockchain::tariff::{TariffRate, TariffService};
+        use crate::primitives::primitives::hash_data;
+
+        let mut vm = ContractVM::new(MemoryStorage::new());
+        let table = RateTable {
+            operator: "Vodafone-UK".to_string(),
+            partner: "T-Mobile-DE".to_string(),
+            currency: "EUR".to_string(),
+            effective_from: 1_600_000_000,
+            rates: vec![TariffRate { service: TariffService::Voice, destination: None, time_band: None, cents_per_unit: 12 }],
+        };
+        vm.storage_mut().set(&tariff_registry_address(), &table.table_key(), bincode::serialize(&table).unwrap()).unwrap();
+
+        // Tariff contract: prices the minutes in its input at the voice rate, returning the charge as data too
+        let tariff = hash_data(b"tariff_contract");
+        vm.deploy_contract(tariff, vec![
+            Instruction::Push(0),
+            Instruction::Push(0),
+            Instruction::CallDataLoad,
+            Instruction::LookupRate { table: table.table_key(), service: TariffService::Voice, destination: None },
+            Instruction::Mul,
+            Instruction::MStore,
+            Instruction::Push(0),
+            Instruction::MLoad,
+            Instruction::Push(0),
+            Instruction::Push(8),
+            Instruction::Return,
+        ]).unwrap();
+
+        // Settlement contract: passes its input on to the tariff contract, stores the charge and returns it
+        let settlement = hash_data(b"settlement_contract");
+        let charge_key = hash_data(b"charge");
+        vm.deploy_contract(settlement, vec![
+            Instruction::Push(0),
+            Instruction::Push(0),
+            Instruction::Push(8),
+            Instruction::CallDataCopy,
+            Instruction::Push(0),
+            Instruction::Push(8),
+            Instruction::Push(0),
+            Instruction::Call(tariff),
+            Instruction::JumpIf(11),
+            Instruction::Log("Tariff lookup failed".to_string()),
+            Instruction::Halt,
+            Instruction::Store(charge_key),
+            Instruction::Push(8),
+            Instruction::Push(0),
+            Instruction::ReturnDataSize,
+            Instruction::ReturnDataCopy,
+            Instruction::Push(8),
+            Instruction::MLoad,
+            Instruction::Halt,
+        ]).unwrap();
+
+        let context = ExecutionContext {
+            contract_address: settlement,
+            caller: Blake2bHash::zero(),
+            timestamp: 1_700_000_000,
+            gas_limit: 10_000,
+            gas_used: 0,
+            value: 0,
+        };
+        let result = vm.execute(context.clone(), &100u64.to_le_bytes()).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.return_value, Some(1_200));
+        assert!(result.gas_used > GasCosts::CALL + GasCosts::LOAD);
+        assert_eq!(vm.storage().get(&settlement, &charge_key).unwrap(), Some(1_200u64.to_le_bytes().to_vec()));
+
+        // Without a published rate the lookup fails, its frame is reverted and the caller carries on
+        vm.storage_mut().set(&tariff_registry_address(), &table.table_key(), vec![]).unwrap();
+        let result = vm.execute(context, &100u64.to_le_bytes()).unwrap();
+        assert!(result.success);
+        assert_eq!(result.return_value, Some(0));
+        assert!(result.logs.iter().any(|log| log.contains("call to") && log.contains("failed")));
+    }
+
+    #[test]
+    fn test_reentrant_call_refused() {
+        use crate::primitives::primitives::hash_data;
+
+        let mut vm = ContractVM::new(MemoryStorage::new());
+        let (first, second) = (hash_data(b"first"), hash_data(b"second"));
+        let key = hash_data(b"calls");
+        let calling = |callee| vec![
+            Instruction::Push(1),
+            Instruction::Store(key),
+            Instruction::Push(0),
+            Instruction::Push(0),
+            Instruction::Push(0),
+            Instruction::Call(callee),
+            Instruction::Halt,
+        ];
+        vm.deploy_contract(first, calling(second)).unwrap();
+        vm.deploy_contract(second, calling(first)).unwrap();
+
+        let context = ExecutionContext {
+            contract_address: first,
+            caller: Blake2bHash::zero(),
+            timestamp: 1_700_000_000,
+            gas_limit: 100_000,
+            gas_used: 0,
+            value: 0,
+        };
+        let result = vm.execute(context, &[]).unwrap();
+
+        // The call back into the first contract fails, the second contract's call succeeds
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.return_value, Some(1));
+        assert!(result.logs.iter().any(|log| log.contains("reentrant call")));
+        assert!(vm.storage().get(&second, &key).unwrap().is_some());
+    }
+}
@@ -0,0 +1,396 @@
+// WASM execution backend for smart contracts, metered with wasmtime fuel
+//
+// Contracts export `memory` and `execute() -> i64` and may import from "env":
+//   storage_load(key_ptr) -> i64             32-byte key, value 0 if unset
+//   storage_store(key_ptr, value: i64)
+//   input_len() -> i32, input_read(ptr)
+//   timestamp() -> i64, value() -> i64, caller(ptr)
+//   log(ptr, len)
+//   emit(topics_ptr, topic_count, data_ptr, data_len)   32-byte topics
+//   verify_settlement_proof(proof_ptr, proof_len, total_charges: i64, exchange_rate: i32, settlement_amount: i64) -> i32
+//   verify_operator_signature(name_ptr, name_len, msg_ptr, msg_len, sig_ptr, sig_len) -> i32
+//
+// State values are u64 little endian, the layout the instruction VM uses, so a contract
+// migrated from instructions to WASM keeps its state. Contracts without a WASM module
+// still run on the instruction VM.
+use std::collections::HashMap;
+use wasmtime::{Caller, Config, Engine, Linker, Memory, Module, Store, Trap};
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::vm::{ContractStorage, ContractVM, ExecutionContext, ExecutionResult, GasCosts};
+use super::crypto_verifier::{ContractCryptoVerifier, SettlementProofInputs};
+use super::events::{ContractEvent, MAX_EVENT_TOPICS};
+
+/// State of one execution, owned by the wasmtime store
+struct HostState<S: ContractStorage> {
+    storage: S,
+    crypto_verifier: ContractCryptoVerifier,
+    context: ExecutionContext,
+    input: Vec<u8>,
+    /// Storage writes, applied only if the execution succeeds
+    pending_writes: Vec<(Blake2bHash, Vec<u8>)>,
+    logs: Vec<String>,
+    events: Vec<ContractEvent>,
+}
+
+impl<S: ContractStorage> HostState<S> {
+    fn load(&self, key: &Blake2bHash) -> Result<u64> {
+        let value = match self.pending_writes.iter().rev().find(|(k, _)| k == key) {
+            Some((_, value)) => Some(value.clone()),
+            None => self.storage.get(&self.context.contract_address, key)?,
+        };
+        Ok(value
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(0))
+    }
+}
+
+/// Contract VM running WASM modules, falling back to the instruction VM for unmigrated contracts
+pub struct WasmContractVM<S: ContractStorage + 'static> {
+    engine: Engine,
+    modules: HashMap<Blake2bHash, Module>,
+    /// Storage and verifier, moved into the wasmtime store while a contract executes
+    parts: Option<(S, ContractCryptoVerifier)>,
+}
+
+fn wasm_error(e: impl std::fmt::Display) -> BlockchainError {
+    BlockchainError::InvalidOperation(format!("WASM: {}", e))
+}
+
+/// Take `cost` gas from the remaining fuel, trapping when it runs out
+fn charge<S: ContractStorage>(caller: &mut Caller<'_, HostState<S>>, cost: u64) -> anyhow::Result<()> {
+    let fuel = caller.get_fuel()?;
+    if fuel < cost {
+        caller.set_fuel(0)?;
+        return Err(Trap::OutOfFuel.into());
+    }
+    caller.set_fuel(fuel - cost)?;
+    Ok(())
+}
+
+fn memory<S: ContractStorage>(caller: &mut Caller<'_, HostState<S>>) -> anyhow::Result<Memory> {
+    caller.get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("contract exports no memory"))
+}
+
+fn read_bytes<S: ContractStorage>(caller: &mut Caller<'_, HostState<S>>, ptr: i32, len: i32) -> anyhow::Result<Vec<u8>> {
+    let memory = memory(caller)?;
+    let mut bytes = vec![0u8; usize::try_from(len)?];
+    memory.read(&*caller, usize::try_from(ptr)?, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_key<S: ContractStorage>(caller: &mut Caller<'_, HostState<S>>, ptr: i32) -> anyhow::Result<Blake2bHash> {
+    let bytes = read_bytes(caller, ptr, 32)?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    Ok(Blake2bHash::from_bytes(key))
+}
+
+fn write_bytes<S: ContractStorage>(caller: &mut Caller<'_, HostState<S>>, ptr: i32, bytes: &[u8]) -> anyhow::Result<()> {
+    let memory = memory(caller)?;
+    memory.write(&mut *caller, usize::try_from(ptr)?, bytes)?;
+    Ok(())
+}
+
+impl<S: ContractStorage + 'static> WasmContractVM<S> {
+    pub fn new(storage: S, crypto_verifier: ContractCryptoVerifier) -> Result<Self> {
+        // Fuel meters gas, and everything that could differ between validators is switched off
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.wasm_threads(false);
+        config.wasm_relaxed_simd(false);
+        config.cranelift_nan_canonicalization(true);
+
+        Ok(Self {
+            engine: Engine::new(&config).map_err(wasm_error)?,
+            modules: HashMap::new(),
+            parts: Some((storage, crypto_verifier)),
+        })
+    }
+
+    fn storage(&self) -> &S {
+        &self.parts.as_ref().expect("storage is only moved out during execution").0
+    }
+
+    fn storage_mut(&mut self) -> &mut S {
+        &mut self.parts.as_mut().expect("storage is only moved out during execution").0
+    }
+
+    pub fn into_storage(self) -> S {
+        self.parts.expect("storage is only moved out during execution").0
+    }
+
+    /// Deploy a WASM module, rejecting modules that do not compile
+    pub fn deploy_contract(&mut self, address: Blake2bHash, module: Vec<u8>) -> Result<()> {
+        Module::new(&self.engine, &module).map_err(wasm_error)?;
+        self.storage_mut().set_wasm_code(&address, module)
+    }
+
+    /// Replace an instruction contract with a WASM module, keeping its state
+    pub fn migrate_contract(&mut self, address: Blake2bHash, module: Vec<u8>) -> Result<()> {
+        if self.storage().get_code(&address)?.is_none() {
+            return Err(BlockchainError::ContractNotFound);
+        }
+        self.deploy_contract(address, module)
+    }
+
+    pub fn has_contract(&self, address: &Blake2bHash) -> Result<bool> {
+        Ok(self.storage().get_wasm_code(address)?.is_some() || self.storage().get_code(address)?.is_some())
+    }
+
+    fn module(&mut self, address: &Blake2bHash, code: &[u8]) -> Result<Module> {
+        let code_hash = Blake2bHash::from_data(code);
+        if let Some(module) = self.modules.get(&code_hash) {
+            return Ok(module.clone());
+        }
+
+        let module = Module::new(&self.engine, code)
+            .map_err(|e| wasm_error(format!("contract {} does not compile: {}", address, e)))?;
+        self.modules.insert(code_hash, module.clone());
+        Ok(module)
+    }
+
+    pub fn execute(&mut self, context: ExecutionContext, input: &[u8]) -> Result<ExecutionResult> {
+        let code = match self.storage().get_wasm_code(&context.contract_address)? {
+            Some(code) => code,
+            None => return self.execute_instructions(context, input),
+        };
+        let module = self.module(&context.contract_address, &code)?;
+
+        let (storage, crypto_verifier) = self.parts.take().expect("storage is only moved out during execution");
+        let gas_limit = context.gas_limit.saturating_sub(context.gas_used);
+        let mut store = Store::new(&self.engine, HostState {
+            storage,
+            crypto_verifier,
+            context,
+            input: input.to_vec(),
+            pending_writes: Vec::new(),
+            logs: Vec::new(),
+            events: Vec::new(),
+        });
+
+        let outcome = store.set_fuel(gas_limit)
+            .and_then(|_| Self::linker(&self.engine))
+            .and_then(|linker| linker.instantiate(&mut store, &module))
+            .and_then(|instance| instance.get_typed_func::<(), i64>(&mut store, "execute"))
+            .and_then(|execute| execute.call(&mut store, ()));
+        let gas_used = store.get_fuel()
+            .map(|remaining| gas_limit - remaining)
+            .unwrap_or(gas_limit);
+
+        // Put the storage back before anything can fail
+        let state = store.into_data();
+        let contract = state.context.contract_address;
+        self.parts = Some((state.storage, state.crypto_verifier));
+
+        match outcome {
+            Ok(return_value) => {
+                for (key, value) in state.pending_writes {
+                    self.storage_mut().set(&contract, &key, value)?;
+                }
+                Ok(ExecutionResult {
+                    success: true,
+                    return_value: Some(return_value as u64),
+                    gas_used: state.context.gas_used + gas_used,
+                    return_data: Vec::new(),
+                    logs: state.logs,
+                    events: state.events,
+                    error: None,
+                })
+            }
+            Err(e) => {
+                let out_of_fuel = matches!(e.downcast_ref::<Trap>(), Some(Trap::OutOfFuel));
+                Ok(ExecutionResult {
+                    success: false,
+                    return_value: None,
+                    gas_used: state.context.gas_used + if out_of_fuel { gas_limit } else { gas_used },
+                    return_data: Vec::new(),
+                    logs: state.logs,
+                    events: Vec::new(),
+                    error: Some(if out_of_fuel { "Out of gas".to_string() } else { e.to_string() }),
+                })
+            }
+        }
+    }
+
+    /// Run a contract that has not been migrated on the instruction VM
+    fn execute_instructions(&mut self, context: ExecutionContext, input: &[u8]) -> Result<ExecutionResult> {
+        let (storage, crypto_verifier) = self.parts.take().expect("storage is only moved out during execution");
+        let mut vm = ContractVM::new_with_crypto(storage, crypto_verifier);
+        let result = vm.execute(context, input);
+        self.parts = Some(vm.into_parts());
+        result
+    }
+
+    /// Host functions, each charged at the instruction VM's gas cost
+    fn linker(engine: &Engine) -> anyhow::Result<Linker<HostState<S>>> {
+        let mut linker = Linker::new(engine);
+
+        linker.func_wrap("env", "storage_load", |mut caller: Caller<'_, HostState<S>>, key_ptr: i32| -> anyhow::Result<i64> {
+            charge(&mut caller, GasCosts::LOAD)?;
+            let key = read_key(&mut caller, key_ptr)?;
+            Ok(caller.data().load(&key)? as i64)
+        })?;
+
+        linker.func_wrap("env", "storage_store", |mut caller: Caller<'_, HostState<S>>, key_ptr: i32, value: i64| -> anyhow::Result<()> {
+            charge(&mut caller, GasCosts::STORE)?;
+            let key = read_key(&mut caller, key_ptr)?;
+            caller.data_mut().pending_writes.push((key, (value as u64).to_le_bytes().to_vec()));
+            Ok(())
+        })?;
+
+        linker.func_wrap("env", "input_len", |caller: Caller<'_, HostState<S>>| -> i32 {
+            caller.data().input.len() as i32
+        })?;
+
+        linker.func_wrap("env", "input_read", |mut caller: Caller<'_, HostState<S>>, ptr: i32| -> anyhow::Result<()> {
+            let input = caller.data().input.clone();
+            write_bytes(&mut caller, ptr, &input)
+        })?;
+
+        linker.func_wrap("env", "timestamp", |mut caller: Caller<'_, HostState<S>>| -> anyhow::Result<i64> {
+            charge(&mut caller, GasCosts::GET_TIMESTAMP)?;
+            Ok(caller.data().context.timestamp as i64)
+        })?;
+
+        linker.func_wrap("env", "value", |caller: Caller<'_, HostState<S>>| -> i64 {
+            caller.data().context.value as i64
+        })?;
+
+        linker.func_wrap("env", "caller", |mut caller: Caller<'_, HostState<S>>, ptr: i32| -> anyhow::Result<()> {
+            charge(&mut caller, GasCosts::GET_CALLER)?;
+            let address = caller.data().context.caller;
+            write_bytes(&mut caller, ptr, address.as_bytes())
+        })?;
+
+        linker.func_wrap("env", "log", |mut caller: Caller<'_, HostState<S>>, ptr: i32, len: i32| -> anyhow::Result<()> {
+            charge(&mut caller, GasCosts::LOG)?;
+            let message = String::from_utf8_lossy(&read_bytes(&mut caller, ptr, len)?).into_owned();
+            caller.data_mut().logs.push(message);
+            Ok(())
+        })?;
+
+        linker.func_wrap("env", "emit", |mut caller: Caller<'_, HostState<S>>,
+            topics_ptr: i32, topic_count: i32, data_ptr: i32, data_len: i32| -> anyhow::Result<()> {
+            let topic_count = usize::try_from(topic_count)?;
+            if topic_count > MAX_EVENT_TOPICS {
+                anyhow::bail!("event with {} topics, at most {} allowed", topic_count, MAX_EVENT_TOPICS);
+            }
+            let data_cost = GasCosts::EMIT_BYTE.saturating_mul(u64::try_from(data_len)?);
+            charge(&mut caller, (GasCosts::EMIT + GasCosts::EMIT_TOPIC * topic_count as u64).saturating_add(data_cost))?;
+            let topics = (0..topic_count)
+                .map(|index| read_key(&mut caller, topics_ptr + 32 * index as i32))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let data = read_bytes(&mut caller, data_ptr, data_len)?;
+            let contract_address = caller.data().context.contract_address;
+            caller.data_mut().events.push(ContractEvent { contract_address, topics, data });
+            Ok(())
+        })?;
+
+        // Proof inputs are derived the way the instruction VM derives them
+        linker.func_wrap("env", "verify_settlement_proof", |mut caller: Caller<'_, HostState<S>>,
+            proof_ptr: i32, proof_len: i32, total_charges: i64, exchange_rate: i32, settlement_amount: i64| -> anyhow::Result<i32> {
+            charge(&mut caller, GasCosts::VERIFY_PROOF)?;
+            let proof = read_bytes(&mut caller, proof_ptr, proof_len)?;
+            let state = caller.data();
+            let period = state.context.timestamp / (30 * 24 * 60 * 60);
+            let inputs = SettlementProofInputs {
+                total_charges: total_charges as u64,
+                exchange_rate: exchange_rate as u32,
+                settlement_amount: settlement_amount as u64,
+                period_hash: crate::primitives::primitives::hash_data(&period.to_le_bytes()),
+                network_pair_hash: state.context.contract_address,
+            };
+            Ok(state.crypto_verifier.zk_verifier().verify_settlement_proof(&proof, &inputs).unwrap_or(false) as i32)
+        })?;
+
+        linker.func_wrap("env", "verify_operator_signature", |mut caller: Caller<'_, HostState<S>>,
+            name_ptr: i32, name_len: i32, msg_ptr: i32, msg_len: i32, sig_ptr: i32, sig_len: i32| -> anyhow::Result<i32> {
+            charge(&mut caller, GasCosts::CHECK_SIGNATURE)?;
+            let name = String::from_utf8_lossy(&read_bytes(&mut caller, name_ptr, name_len)?).into_owned();
+            let message = read_bytes(&mut caller, msg_ptr, msg_len)?;
+            let signature = read_bytes(&mut caller, sig_ptr, sig_len)?;
+            Ok(caller.data().crypto_verifier.bls_verifier()
+                .verify_operator_signature(&name, &message, &signature)
+                .unwrap_or(false) as i32)
+        })?;
+
+        Ok(linker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_contracts::vm::{Instruction, MemoryStorage};
+
+    fn context(contract_address: Blake2bHash, gas_limit: u64) -> ExecutionContext {
+        ExecutionContext {
+            contract_address,
+            caller: Blake2bHash::zero(),
+            timestamp: 1_700_000_000,
+            gas_limit,
+            gas_used: 0,
+            value: 0,
+        }
+    }
+
+    /// Adds the input length to the value under `key` and returns the new value
+    fn counter_module(key: &Blake2bHash) -> Vec<u8> {
+        let key: String = key.as_bytes().iter().map(|byte| format!("\\{:02x}", byte)).collect();
+        format!(r#"
+            (module
+              (import "env" "storage_load" (func $load (param i32) (result i64)))
+              (import "env" "storage_store" (func $store (param i32 i64)))
+              (import "env" "input_len" (func $input_len (result i32)))
+              (memory (export "memory") 1)
+              (data (i32.const 0) "{}")
+              (func (export "execute") (result i64)
+                (call $store (i32.const 0)
+                  (i64.add (call $load (i32.const 0)) (i64.extend_i32_u (call $input_len))))
+                (call $load (i32.const 0))))
+        "#, key).into_bytes()
+    }
+
+    #[test]
+    fn test_migrated_contract_keeps_state() {
+        let address = Blake2bHash::from_data(b"settlement-contract");
+        let key = Blake2bHash::from_data(b"total");
+
+        let mut storage = MemoryStorage::new();
+        storage.set_code(&address, vec![Instruction::Push(42), Instruction::Store(key), Instruction::Halt]).unwrap();
+        let mut vm = WasmContractVM::new(storage, ContractCryptoVerifier::new()).unwrap();
+
+        // Before migration the instruction code runs
+        assert!(vm.execute(context(address, 10_000), &[]).unwrap().success);
+
+        vm.migrate_contract(address, counter_module(&key)).unwrap();
+        let result = vm.execute(context(address, 10_000), &[1, 2, 3]).unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.return_value, Some(45));
+        assert!(result.gas_used >= GasCosts::LOAD * 2 + GasCosts::STORE);
+
+        // Only existing contracts can be migrated
+        let missing = Blake2bHash::from_data(b"missing");
+        assert!(vm.migrate_contract(missing, counter_module(&key)).is_err());
+    }
+
+    #[test]
+    fn test_fuel_exhaustion_reverts() {
+        let address = Blake2bHash::from_data(b"looping-contract");
+        let key = Blake2bHash::from_data(b"total");
+        let mut vm = WasmContractVM::new(MemoryStorage::new(), ContractCryptoVerifier::new()).unwrap();
+
+        // Storing and then looping forever runs out of gas and discards the write
+        let store_and_loop = String::from_utf8(counter_module(&key)).unwrap()
+            .replace("(call $load (i32.const 0))))", "(loop $spin (br $spin)) (i64.const 0)))");
+        vm.deploy_contract(address, store_and_loop.into_bytes()).unwrap();
+
+        let result = vm.execute(context(address, 5_000), &[7]).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.gas_used, 5_000);
+        assert_eq!(vm.into_storage().get(&address, &key).unwrap(), None);
+    }
+}
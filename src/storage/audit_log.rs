@@ -0,0 +1,314 @@
+// Tamper-evident audit trail of settlement decisions
+// Entries are hash-chained, persisted in MDBX and exported as signed JSONL for regulators
+use libp2p::identity::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, info};
+
+use crate::primitives::{Blake2bHash, BlockchainError, NetworkId, Result};
+use super::MdbxChainStore;
+
+/// Settlement state transition recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AuditAction {
+    Proposed,
+    Accepted,
+    Rejected,
+    CounterProposed,
+    UnderReview,
+    /// Settlement above the auto-accept threshold waits for a signer quorum
+    ApprovalRequested,
+    /// Signer quorum reached
+    Approved,
+    /// Payment instruction issued for an agreed settlement
+    Instructed,
+    PaymentSent,
+    PaymentReceived,
+    Executed,
+    PaymentFailed,
+    Disputed,
+    DisputeResolved,
+    /// Settlement transaction queued for the chain
+    Finalized,
+    Quarantined,
+    Released,
+    PeriodClosed,
+    /// Detailed CDR data deleted at the end of its retention period
+    Purged,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// One link of the audit hash chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub timestamp: u64,
+    /// Operator that took the decision
+    pub actor: String,
+    pub action: AuditAction,
+    /// Settlement, proposal, batch or period the decision concerns
+    pub subject: Blake2bHash,
+    pub details: String,
+    pub prev_hash: Blake2bHash,
+    pub hash: Blake2bHash,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> Blake2bHash {
+        let fields = (self.sequence, self.timestamp, &self.actor, self.action, self.subject, &self.details, self.prev_hash);
+        let mut data = b"sp-cdr-audit-entry".to_vec();
+        data.extend_from_slice(&bincode::serialize(&fields).expect("audit fields are serializable"));
+        Blake2bHash::from_data(&data)
+    }
+
+    /// Whether the entry still matches its hash
+    pub fn is_intact(&self) -> bool {
+        self.hash == self.compute_hash()
+    }
+}
+
+/// Check that entries form an unbroken hash chain starting at the first entry of the log
+pub fn verify_chain(entries: &[AuditEntry]) -> Result<()> {
+    let mut prev_hash = Blake2bHash::zero();
+    for (sequence, entry) in entries.iter().enumerate() {
+        if entry.sequence != sequence as u64 {
+            return Err(BlockchainError::InvalidState(format!(
+                "Audit entry {} found at position {}", entry.sequence, sequence
+            )));
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(BlockchainError::InvalidState(format!(
+                "Audit entry {} does not link to its predecessor", entry.sequence
+            )));
+        }
+        if !entry.is_intact() {
+            return Err(BlockchainError::InvalidState(format!("Audit entry {} was altered", entry.sequence)));
+        }
+        prev_hash = entry.hash;
+    }
+    Ok(())
+}
+
+/// One line of a signed JSONL export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedAuditEntry {
+    pub entry: AuditEntry,
+    /// Protobuf-encoded public key of the exporting node, hex
+    pub signer: String,
+    /// Signer's signature over the entry hash, hex
+    pub signature: String,
+}
+
+fn signing_payload(hash: &Blake2bHash) -> Vec<u8> {
+    let mut payload = b"sp-cdr-audit-export".to_vec();
+    payload.extend_from_slice(hash.as_bytes());
+    payload
+}
+
+/// Sign every entry with `key` and render one JSON object per line
+pub fn export_signed_jsonl(entries: &[AuditEntry], key: &Keypair) -> Result<String> {
+    let signer = hex::encode(key.public().encode_protobuf());
+    let mut jsonl = String::new();
+    for entry in entries {
+        let signature = key.sign(&signing_payload(&entry.hash))
+            .map_err(|e| BlockchainError::Crypto(format!("Audit entry signing failed: {}", e)))?;
+        let line = SignedAuditEntry {
+            entry: entry.clone(),
+            signer: signer.clone(),
+            signature: hex::encode(signature),
+        };
+        jsonl.push_str(&serde_json::to_string(&line).map_err(|e| BlockchainError::Serialization(e.to_string()))?);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+/// Check the signature on every line of an export and the hash chain they form
+pub fn verify_signed_jsonl(jsonl: &str) -> Result<Vec<AuditEntry>> {
+    let mut entries = Vec::new();
+    for line in jsonl.lines().filter(|line| !line.trim().is_empty()) {
+        let signed: SignedAuditEntry = serde_json::from_str(line)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid audit line: {}", e)))?;
+
+        let signer = hex::decode(&signed.signer).ok()
+            .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+            .ok_or_else(|| BlockchainError::Crypto("Invalid audit signer key".to_string()))?;
+        let signature = hex::decode(&signed.signature)
+            .map_err(|e| BlockchainError::Crypto(format!("Invalid audit signature encoding: {}", e)))?;
+        if !signer.verify(&signing_payload(&signed.entry.hash), &signature) {
+            return Err(BlockchainError::Crypto(format!("Invalid signature on audit entry {}", signed.entry.sequence)));
+        }
+
+        entries.push(signed.entry);
+    }
+    verify_chain(&entries)?;
+    Ok(entries)
+}
+
+struct AuditHead {
+    next_sequence: u64,
+    last_hash: Blake2bHash,
+    /// Entries of a log without a database
+    entries: Vec<AuditEntry>,
+}
+
+/// Entries buffered for a slow subscriber before it starts missing them
+const EVENT_BUFFER: usize = 256;
+
+/// Append-only settlement audit log
+/// Kept in memory, or written through to MDBX when a database is attached
+/// Recorded entries are published to subscribers as settlement lifecycle events
+pub struct AuditLog {
+    head: Mutex<AuditHead>,
+    db: Option<MdbxChainStore>,
+    events: broadcast::Sender<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Audit log without persistence
+    pub fn in_memory() -> Self {
+        Self {
+            head: Mutex::new(AuditHead { next_sequence: 0, last_hash: Blake2bHash::zero(), entries: Vec::new() }),
+            db: None,
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Open the audit log persisted in the chain database, refusing a broken chain
+    pub async fn open(db: MdbxChainStore) -> Result<Self> {
+        let entries = Self::load(&db).await?;
+        verify_chain(&entries)?;
+
+        info!("📜 Audit log opened with {} entries", entries.len());
+
+        let head = AuditHead {
+            next_sequence: entries.len() as u64,
+            last_hash: entries.last().map_or(Blake2bHash::zero(), |entry| entry.hash),
+            entries: Vec::new(),
+        };
+        Ok(Self { head: Mutex::new(head), db: Some(db), events: broadcast::channel(EVENT_BUFFER).0 })
+    }
+
+    async fn load(db: &MdbxChainStore) -> Result<Vec<AuditEntry>> {
+        db.audit_entries().await?
+            .iter()
+            .map(|data| bincode::deserialize(data)
+                .map_err(|e| BlockchainError::Storage(format!("Audit entry deserialize failed: {}", e))))
+            .collect()
+    }
+
+    /// Append a decision taken by `actor` on `subject`
+    pub async fn record(
+        &self,
+        actor: &NetworkId,
+        action: AuditAction,
+        subject: Blake2bHash,
+        details: impl Into<String>,
+    ) -> Result<AuditEntry> {
+        let mut head = self.head.lock().await;
+
+        let mut entry = AuditEntry {
+            sequence: head.next_sequence,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            actor: actor.to_string(),
+            action,
+            subject,
+            details: details.into(),
+            prev_hash: head.last_hash,
+            hash: Blake2bHash::zero(),
+        };
+        entry.hash = entry.compute_hash();
+
+        match &self.db {
+            Some(db) => {
+                let data = bincode::serialize(&entry).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+                db.put_audit_entry(entry.sequence, &data).await?;
+            }
+            None => head.entries.push(entry.clone()),
+        }
+        head.next_sequence += 1;
+        head.last_hash = entry.hash;
+
+        debug!("📝 Audit #{}: {} {} by {}", entry.sequence, entry.action, entry.subject, entry.actor);
+        let _ = self.events.send(entry.clone());
+        Ok(entry)
+    }
+
+    /// Receiver of the entries recorded from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<AuditEntry> {
+        self.events.subscribe()
+    }
+
+    /// All entries in sequence order
+    pub async fn entries(&self) -> Result<Vec<AuditEntry>> {
+        match &self.db {
+            Some(db) => Self::load(db).await,
+            None => Ok(self.head.lock().await.entries.clone()),
+        }
+    }
+
+    /// Verify the chain and export it signed with `key`
+    pub async fn export_jsonl(&self, key: &Keypair) -> Result<String> {
+        let entries = self.entries().await?;
+        verify_chain(&entries)?;
+        export_signed_jsonl(&entries, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audit_chain_and_signed_export() {
+        let log = AuditLog::in_memory();
+        let creditor = NetworkId::new("T-Mobile", "DE");
+        let debtor = NetworkId::new("Vodafone", "UK");
+        let settlement = Blake2bHash::from_data(b"settlement");
+
+        log.record(&creditor, AuditAction::Proposed, settlement, "€120.00").await.unwrap();
+        log.record(&debtor, AuditAction::Accepted, settlement, "").await.unwrap();
+        log.record(&debtor, AuditAction::Executed, settlement, "ref SEPA-1").await.unwrap();
+
+        let entries = log.entries().await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+        verify_chain(&entries).unwrap();
+
+        let key = Keypair::generate_ed25519();
+        let jsonl = log.export_jsonl(&key).await.unwrap();
+        assert_eq!(jsonl.lines().count(), 3);
+        assert_eq!(verify_signed_jsonl(&jsonl).unwrap(), entries);
+
+        // Rewriting who accepted breaks the entry hash
+        let forged = jsonl.replacen("Vodafone", "Orange", 1);
+        assert!(verify_signed_jsonl(&forged).is_err());
+
+        // Dropping an entry breaks the chain
+        let truncated: String = jsonl.lines().skip(1).map(|line| format!("{}\n", line)).collect();
+        assert!(verify_signed_jsonl(&truncated).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+        let operator = NetworkId::new("Orange", "FR");
+
+        let log = AuditLog::open(store.clone()).await.unwrap();
+        log.record(&operator, AuditAction::Quarantined, Blake2bHash::from_data(b"batch"), "score 80").await.unwrap();
+        drop(log);
+
+        // A reopened log continues the chain where it stopped
+        let log = AuditLog::open(store).await.unwrap();
+        let entry = log.record(&operator, AuditAction::Released, Blake2bHash::from_data(b"batch"), "").await.unwrap();
+        assert_eq!(entry.sequence, 1);
+        verify_chain(&log.entries().await.unwrap()).unwrap();
+    }
+}
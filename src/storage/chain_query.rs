@@ -0,0 +1,329 @@
+// Settlement and BCE batch queries over a chain store, for the inspector: one scan of the
+// stored blocks collects settlement transactions and batch commitments with their status
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::TransactionData;
+use crate::bce_pipeline::positions::is_operator;
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use super::ChainStore;
+
+/// Whether a settlement is behind the latest macro block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettlementState {
+    Finalized,
+    Pending,
+}
+
+/// Where a committed BCE batch stands, from its latest transaction on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchState {
+    Committed,
+    Quarantined,
+    Released,
+    Settled,
+}
+
+impl std::str::FromStr for SettlementState {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "finalized" => Ok(SettlementState::Finalized),
+            "pending" => Ok(SettlementState::Pending),
+            _ => Err(BlockchainError::InvalidOperation(format!(
+                "Unknown settlement status {}, expected finalized or pending", s
+            ))),
+        }
+    }
+}
+
+impl std::str::FromStr for BatchState {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "committed" => Ok(BatchState::Committed),
+            "quarantined" => Ok(BatchState::Quarantined),
+            "released" => Ok(BatchState::Released),
+            "settled" => Ok(BatchState::Settled),
+            _ => Err(BlockchainError::InvalidOperation(format!(
+                "Unknown batch status {}, expected committed, quarantined, released or settled", s
+            ))),
+        }
+    }
+}
+
+/// Settlement transaction as stored on chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementRecord {
+    pub block_number: u32,
+    pub transaction_hash: Blake2bHash,
+    pub creditor: String,
+    pub debtor: String,
+    pub amount: u64,
+    pub currency: String,
+    pub period: String,
+    pub batch_count: usize,
+    pub status: SettlementState,
+}
+
+/// BCE batch committed on chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub block_number: u32,
+    pub batch_id: Blake2bHash,
+    pub home_network: String,
+    pub visited_network: String,
+    pub record_count: u32,
+    pub merkle_root: Blake2bHash,
+    /// Period whose close froze the batch, `None` while it is open
+    pub period: Option<String>,
+    pub status: BatchState,
+}
+
+/// Inspector filters, each one only applied if set
+#[derive(Debug, Clone, Default)]
+pub struct ChainQuery {
+    /// Operators of a pair, in either role; one operator matches all of its pairs
+    pub operators: Vec<String>,
+    pub period: Option<String>,
+    pub status: Option<String>,
+}
+
+impl ChainQuery {
+    fn matches_pair(&self, first: &str, second: &str) -> bool {
+        self.operators.iter().all(|operator| is_operator(first, operator) || is_operator(second, operator))
+    }
+
+    fn matches_period(&self, period: Option<&str>) -> bool {
+        self.period.as_deref().map_or(true, |wanted| period == Some(wanted))
+    }
+
+    /// Settlements matching the filters, latest first and at most `limit`
+    pub fn settlements<'a>(&self, activity: &'a ChainActivity, limit: usize) -> Result<Vec<&'a SettlementRecord>> {
+        let status = self.status.as_deref().map(str::parse::<SettlementState>).transpose()?;
+        Ok(activity.settlements.iter().rev()
+            .filter(|settlement| self.matches_pair(&settlement.creditor, &settlement.debtor))
+            .filter(|settlement| self.matches_period(Some(&settlement.period)))
+            .filter(|settlement| status.map_or(true, |status| settlement.status == status))
+            .take(limit)
+            .collect())
+    }
+
+    /// Batch commitments matching the filters, latest first and at most `limit`
+    pub fn batches<'a>(&self, activity: &'a ChainActivity, limit: usize) -> Result<Vec<&'a BatchRecord>> {
+        let status = self.status.as_deref().map(str::parse::<BatchState>).transpose()?;
+        Ok(activity.batches.iter().rev()
+            .filter(|batch| self.matches_pair(&batch.home_network, &batch.visited_network))
+            .filter(|batch| self.matches_period(batch.period.as_deref()))
+            .filter(|batch| status.map_or(true, |status| batch.status == status))
+            .take(limit)
+            .collect())
+    }
+}
+
+/// Settlements and batch commitments of a chain, in chain order
+#[derive(Debug, Clone, Default)]
+pub struct ChainActivity {
+    pub settlements: Vec<SettlementRecord>,
+    pub batches: Vec<BatchRecord>,
+}
+
+impl ChainActivity {
+    /// Scan the blocks of `store` up to its head
+    /// Pruned micro blocks keep their settlements, but lose batch commitments and fraud flags
+    pub async fn scan(store: &dyn ChainStore) -> Result<Self> {
+        let head_hash = store.get_head_hash().await?;
+        let Some(head) = store.get_block(&head_hash).await? else {
+            return Ok(Self::default());
+        };
+        let macro_head = match store.get_block(&store.get_macro_head_hash().await?).await? {
+            Some(block) => block.block_number(),
+            None => 0,
+        };
+
+        let mut activity = Self::default();
+        let mut batch_index = HashMap::new();
+        for block_number in 1..=head.block_number() {
+            let Some(block) = store.get_block_at(block_number).await? else {
+                continue;
+            };
+            for transaction in block.transactions() {
+                match &transaction.data {
+                    TransactionData::Settlement(settlement) => {
+                        for batch_id in &settlement.batch_ids {
+                            if let Some(&index) = batch_index.get(batch_id) {
+                                activity.batches[index].status = BatchState::Settled;
+                            }
+                        }
+                        activity.settlements.push(SettlementRecord {
+                            block_number,
+                            transaction_hash: transaction.hash(),
+                            creditor: settlement.creditor_network.clone(),
+                            debtor: settlement.debtor_network.clone(),
+                            amount: settlement.amount,
+                            currency: settlement.currency.clone(),
+                            period: settlement.period.clone(),
+                            batch_count: settlement.batch_ids.len(),
+                            status: if block_number <= macro_head { SettlementState::Finalized } else { SettlementState::Pending },
+                        });
+                    }
+                    TransactionData::BatchCommitment(commitment) => {
+                        batch_index.insert(commitment.batch_id, activity.batches.len());
+                        activity.batches.push(BatchRecord {
+                            block_number,
+                            batch_id: commitment.batch_id,
+                            home_network: commitment.home_network.clone(),
+                            visited_network: commitment.visited_network.clone(),
+                            record_count: commitment.record_count,
+                            merkle_root: commitment.merkle_root,
+                            period: None,
+                            status: BatchState::Committed,
+                        });
+                    }
+                    TransactionData::FraudFlag(flag) => {
+                        if let Some(&index) = batch_index.get(&flag.batch_id) {
+                            activity.batches[index].status = if flag.quarantine { BatchState::Quarantined } else { BatchState::Released };
+                        }
+                    }
+                    TransactionData::PeriodClose(close) => {
+                        for batch_id in &close.frozen_batches {
+                            if let Some(&index) = batch_index.get(batch_id) {
+                                activity.batches[index].period = Some(close.period.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(activity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Block, MicroBlock, MicroHeader, MicroBody};
+    use crate::blockchain::block::{BatchCommitmentTransaction, FraudFlagTransaction, SettlementTransaction, Transaction};
+    use crate::primitives::NetworkId;
+    use crate::storage::MdbxChainStore;
+
+    fn transaction(data: TransactionData) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: 0,
+            fee: 1,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: u32, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    fn commitment(batch_id: Blake2bHash, home: &str, visited: &str) -> Transaction {
+        transaction(TransactionData::BatchCommitment(BatchCommitmentTransaction {
+            batch_id,
+            home_network: home.to_string(),
+            visited_network: visited.to_string(),
+            record_count: 3,
+            merkle_root: Blake2bHash::zero(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_query_settlements_and_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+
+        let settled_batch = Blake2bHash::from_data(b"settled");
+        let flagged_batch = Blake2bHash::from_data(b"flagged");
+        let tmobile = format!("{:?}", NetworkId::new("T-Mobile", "DE"));
+        let vodafone = format!("{:?}", NetworkId::new("Vodafone", "UK"));
+        let settlement = |period: &str, batch_ids: Vec<Blake2bHash>| transaction(TransactionData::Settlement(SettlementTransaction {
+            creditor_network: tmobile.clone(),
+            debtor_network: vodafone.clone(),
+            amount: 10_000,
+            currency: "EUR".to_string(),
+            period: period.to_string(),
+            breakdown: Default::default(),
+            batch_ids,
+        }));
+
+        let blocks = [
+            micro_block(1, vec![
+                commitment(settled_batch, "T-Mobile:DE", "Vodafone:UK"),
+                commitment(flagged_batch, "Orange:FR", "Vodafone:UK"),
+            ]),
+            micro_block(2, vec![
+                settlement("2024-01", vec![settled_batch]),
+                transaction(TransactionData::FraudFlag(FraudFlagTransaction {
+                    batch_id: flagged_batch,
+                    home_network: "Orange:FR".to_string(),
+                    visited_network: "Vodafone:UK".to_string(),
+                    score: 90,
+                    reasons: vec![],
+                    quarantine: true,
+                })),
+            ]),
+            micro_block(3, vec![settlement("2024-02", vec![])]),
+        ];
+        for block in &blocks {
+            store.put_block(block).await.unwrap();
+        }
+        store.set_head(&blocks[2].hash()).await.unwrap();
+        store.set_macro_head(&blocks[1].hash()).await.unwrap();
+
+        let activity = ChainActivity::scan(&store).await.unwrap();
+        assert_eq!(activity.settlements.len(), 2);
+        assert_eq!(activity.batches.len(), 2);
+
+        // Only the settlement behind the macro head is finalized
+        let query = ChainQuery { status: Some("finalized".to_string()), ..Default::default() };
+        let finalized = query.settlements(&activity, 10).unwrap();
+        assert_eq!(finalized.len(), 1);
+        assert_eq!(finalized[0].period, "2024-01");
+
+        // Operator pairs match either side and the display or debug form of a network
+        let query = ChainQuery { operators: vec!["Vodafone".to_string(), "T-Mobile".to_string()], ..Default::default() };
+        assert_eq!(query.settlements(&activity, 10).unwrap().len(), 2);
+        let batches = query.batches(&activity, 10).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].status, BatchState::Settled);
+
+        let query = ChainQuery { status: Some("quarantined".to_string()), ..Default::default() };
+        let quarantined = query.batches(&activity, 10).unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].batch_id, flagged_batch);
+
+        let query = ChainQuery { period: Some("2024-02".to_string()), ..Default::default() };
+        assert_eq!(query.settlements(&activity, 10).unwrap().len(), 1);
+        assert!(query.batches(&activity, 10).unwrap().is_empty());
+
+        let query = ChainQuery { status: Some("disputed".to_string()), ..Default::default() };
+        assert!(query.settlements(&activity, 10).is_err());
+    }
+}
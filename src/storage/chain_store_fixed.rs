@@ -1,6 +1,25 @@
 // Fixed chain store implementation
-use crate::primitives::{Result, Blake2bHash};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use crate::primitives::{Result, BlockchainError, Blake2bHash, Height, Policy};
 use crate::blockchain::Block;
+use crate::blockchain::block::{Transaction, ValidatorInfo};
+use crate::smart_contracts::{ContractReceipt, EventRecord};
+use super::history_store::TransactionLocation;
+use super::state_trie::StateTrie;
+use super::MdbxChainStore;
+
+/// Writes of one block, applied by `ChainStore::commit` together or not at all
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    pub block: Option<Block>,
+    /// State trie leaves to write, `None` deleting the leaf
+    pub state_changes: Vec<(Blake2bHash, Option<Vec<u8>>)>,
+    pub receipts: Vec<ContractReceipt>,
+    pub head: Option<Blake2bHash>,
+    pub macro_head: Option<Blake2bHash>,
+    pub election_head: Option<Blake2bHash>,
+}
 
 /// Main chain store interface following Albatross patterns
 #[async_trait::async_trait]
@@ -33,18 +52,113 @@ pub trait ChainStore: Send + Sync {
 
     /// Set election head
     async fn set_election_head(&self, hash: &Blake2bHash) -> Result<()>;
+
+    /// Store a content-addressed evidence blob, returning its hash
+    async fn put_evidence(&self, data: &[u8]) -> Result<Blake2bHash>;
+
+    /// Get evidence blob by its content hash
+    async fn get_evidence(&self, hash: &Blake2bHash) -> Result<Option<Vec<u8>>>;
+
+    /// Get a transaction by hash, with the block it was included in
+    async fn get_transaction(&self, hash: &Blake2bHash) -> Result<Option<(Transaction, TransactionLocation)>>;
+
+    /// Store contract receipts, keyed by their transaction hash and indexed by contract
+    async fn put_receipts(&self, receipts: &[ContractReceipt]) -> Result<()>;
+
+    /// Get the contract receipt of a transaction
+    async fn get_receipt(&self, tx_hash: &Blake2bHash) -> Result<Option<ContractReceipt>>;
+
+    /// Get the receipts of transactions that executed a contract, in chain order
+    async fn get_receipts_by_contract(&self, contract: &Blake2bHash) -> Result<Vec<ContractReceipt>>;
+
+    /// Get the events a contract emitted, only those carrying `topic` if given, in chain order
+    async fn get_events(&self, contract: &Blake2bHash, topic: Option<&Blake2bHash>) -> Result<Vec<EventRecord>>;
+
+    /// Apply a write batch atomically, a failure or crash leaving none of its writes behind
+    async fn commit(&self, batch: WriteBatch) -> Result<()>;
+
+    /// Store the serialized result of executing a transaction
+    async fn put_execution_result(&self, tx_hash: &Blake2bHash, result: &[u8]) -> Result<()>;
+
+    /// Get the serialized result of executing a transaction
+    async fn get_execution_result(&self, tx_hash: &Blake2bHash) -> Result<Option<Vec<u8>>>;
+
+    /// Validators elected for `epoch` by the election block at `epoch * ELECTION_BLOCK_INTERVAL`,
+    /// `None` until that block is stored
+    async fn validator_set(&self, epoch: u32) -> Result<Option<Vec<ValidatorInfo>>> {
+        let Some(block_number) = epoch.checked_mul(Policy::ELECTION_BLOCK_INTERVAL) else {
+            return Ok(None);
+        };
+        Ok(match self.get_block_at(block_number).await? {
+            Some(Block::Macro(election)) => election.body.validators,
+            _ => None,
+        })
+    }
+
+    /// Store the serialized finality certificate of a macro block
+    async fn put_macro_certificate(&self, block_number: u32, certificate: &[u8]) -> Result<()>;
+
+    /// Get the serialized finality certificate of a macro block
+    async fn macro_certificate(&self, block_number: u32) -> Result<Option<Vec<u8>>>;
+
+    /// Rebuild the state trie from its persisted leaves
+    async fn load_state_trie(&self) -> Result<StateTrie>;
+
+    /// Bytes the store takes on disk, `None` for stores held in memory
+    fn size_bytes(&self) -> Option<u64>;
 }
 
-/// Simple chain store that actually compiles
+/// Tables of the in-memory store, mirroring those of the MDBX store
+#[derive(Default)]
+struct MemoryTables {
+    blocks: HashMap<Blake2bHash, Block>,
+    block_numbers: BTreeMap<Height, Blake2bHash>,
+    tx_index: HashMap<Blake2bHash, TransactionLocation>,
+    head: Option<Blake2bHash>,
+    macro_head: Option<Blake2bHash>,
+    election_head: Option<Blake2bHash>,
+    evidence: HashMap<Blake2bHash, Vec<u8>>,
+    receipts: HashMap<Blake2bHash, ContractReceipt>,
+    /// Contract, block number and transaction index -> transaction hash
+    contract_receipts: BTreeMap<Vec<u8>, Blake2bHash>,
+    /// Contract, topic, block number, transaction index and log index -> transaction hash
+    contract_events: BTreeMap<Vec<u8>, Blake2bHash>,
+    execution_results: HashMap<Blake2bHash, Vec<u8>>,
+    macro_certificates: BTreeMap<u32, Vec<u8>>,
+    state: HashMap<Blake2bHash, Vec<u8>>,
+}
+
+impl MemoryTables {
+    fn put_block(&mut self, block: &Block) {
+        let hash = block.hash();
+        for (index, transaction) in block.transactions().iter().enumerate() {
+            self.tx_index.insert(transaction.hash(), TransactionLocation { block_hash: hash, index: index as u32 });
+        }
+        self.block_numbers.insert(block.block_number(), hash);
+        self.blocks.insert(hash, block.clone());
+    }
+
+    fn put_receipts(&mut self, receipts: &[ContractReceipt]) {
+        for receipt in receipts {
+            self.contract_receipts.insert(MdbxChainStore::contract_receipt_key(receipt), receipt.transaction_hash);
+            for key in MdbxChainStore::contract_event_keys(receipt) {
+                self.contract_events.insert(key, receipt.transaction_hash);
+            }
+            self.receipts.insert(receipt.transaction_hash, receipt.clone());
+        }
+    }
+}
+
+/// In-memory chain store with the behaviour of the MDBX store, for tests and nodes without a
+/// data directory. Nothing is pruned and nothing survives a restart
+#[derive(Default)]
 pub struct SimpleChainStore {
-    _placeholder: std::marker::PhantomData<()>,
+    tables: RwLock<MemoryTables>,
 }
 
 impl SimpleChainStore {
     pub fn new() -> Self {
-        Self {
-            _placeholder: std::marker::PhantomData,
-        }
+        Self::default()
     }
 }
 
@@ -53,39 +167,301 @@ impl ChainStore for SimpleChainStore {
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
-    async fn get_block(&self, _hash: &Blake2bHash) -> Result<Option<Block>> {
-        Ok(None)
+
+    async fn get_block(&self, hash: &Blake2bHash) -> Result<Option<Block>> {
+        Ok(self.tables.read().unwrap().blocks.get(hash).cloned())
     }
 
-    async fn get_block_at(&self, _block_number: u32) -> Result<Option<Block>> {
-        Ok(None)
+    async fn get_block_at(&self, block_number: u32) -> Result<Option<Block>> {
+        let tables = self.tables.read().unwrap();
+        Ok(tables.block_numbers.get(&block_number).and_then(|hash| tables.blocks.get(hash)).cloned())
     }
 
-    async fn put_block(&self, _block: &Block) -> Result<()> {
+    async fn put_block(&self, block: &Block) -> Result<()> {
+        self.tables.write().unwrap().put_block(block);
         Ok(())
     }
 
     async fn get_head_hash(&self) -> Result<Blake2bHash> {
-        Ok(Blake2bHash::zero())
+        self.tables.read().unwrap().head
+            .ok_or_else(|| BlockchainError::Storage("No head hash found".to_string()))
     }
 
-    async fn set_head(&self, _hash: &Blake2bHash) -> Result<()> {
+    async fn set_head(&self, hash: &Blake2bHash) -> Result<()> {
+        self.tables.write().unwrap().head = Some(*hash);
         Ok(())
     }
 
     async fn get_macro_head_hash(&self) -> Result<Blake2bHash> {
-        Ok(Blake2bHash::zero())
+        self.tables.read().unwrap().macro_head
+            .ok_or_else(|| BlockchainError::Storage("No macro head hash found".to_string()))
     }
 
-    async fn set_macro_head(&self, _hash: &Blake2bHash) -> Result<()> {
+    async fn set_macro_head(&self, hash: &Blake2bHash) -> Result<()> {
+        self.tables.write().unwrap().macro_head = Some(*hash);
         Ok(())
     }
 
     async fn get_election_head_hash(&self) -> Result<Blake2bHash> {
-        Ok(Blake2bHash::zero())
+        self.tables.read().unwrap().election_head
+            .ok_or_else(|| BlockchainError::Storage("No election head hash found".to_string()))
     }
 
-    async fn set_election_head(&self, _hash: &Blake2bHash) -> Result<()> {
+    async fn set_election_head(&self, hash: &Blake2bHash) -> Result<()> {
+        self.tables.write().unwrap().election_head = Some(*hash);
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn put_evidence(&self, data: &[u8]) -> Result<Blake2bHash> {
+        let hash = crate::primitives::primitives::hash_data(data);
+        self.tables.write().unwrap().evidence.insert(hash, data.to_vec());
+        Ok(hash)
+    }
+
+    async fn get_evidence(&self, hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        Ok(self.tables.read().unwrap().evidence.get(hash).cloned())
+    }
+
+    async fn get_transaction(&self, hash: &Blake2bHash) -> Result<Option<(Transaction, TransactionLocation)>> {
+        let tables = self.tables.read().unwrap();
+        let Some(location) = tables.tx_index.get(hash).cloned() else {
+            return Ok(None);
+        };
+        let block = tables.blocks.get(&location.block_hash)
+            .ok_or_else(|| BlockchainError::Storage(format!("Indexed block {} missing", location.block_hash)))?;
+        let transaction = block.transactions().get(location.index as usize).cloned()
+            .ok_or_else(|| BlockchainError::Storage(format!("Indexed transaction {} missing from block", hash)))?;
+        Ok(Some((transaction, location)))
+    }
+
+    async fn put_receipts(&self, receipts: &[ContractReceipt]) -> Result<()> {
+        self.tables.write().unwrap().put_receipts(receipts);
+        Ok(())
+    }
+
+    async fn get_receipt(&self, tx_hash: &Blake2bHash) -> Result<Option<ContractReceipt>> {
+        Ok(self.tables.read().unwrap().receipts.get(tx_hash).cloned())
+    }
+
+    async fn get_receipts_by_contract(&self, contract: &Blake2bHash) -> Result<Vec<ContractReceipt>> {
+        let tables = self.tables.read().unwrap();
+        let prefix = contract.as_bytes().to_vec();
+        Ok(tables.contract_receipts.range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(_, tx_hash)| tables.receipts.get(tx_hash).cloned())
+            .collect())
+    }
+
+    async fn get_events(&self, contract: &Blake2bHash, topic: Option<&Blake2bHash>) -> Result<Vec<EventRecord>> {
+        let tables = self.tables.read().unwrap();
+        let Some(topic) = topic else {
+            let prefix = contract.as_bytes().to_vec();
+            return Ok(tables.contract_receipts.range(prefix.clone()..)
+                .take_while(|(key, _)| key.starts_with(&prefix))
+                .filter_map(|(_, tx_hash)| tables.receipts.get(tx_hash))
+                .flat_map(EventRecord::of_receipt)
+                .collect());
+        };
+
+        let prefix = MdbxChainStore::contract_event_prefix(contract, topic);
+        Ok(tables.contract_events.range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(key, tx_hash)| MdbxChainStore::indexed_event(tables.receipts.get(tx_hash)?, key))
+            .collect())
+    }
+
+    async fn commit(&self, batch: WriteBatch) -> Result<()> {
+        // One write lock, so readers see all of the batch or none of it
+        let mut tables = self.tables.write().unwrap();
+        if let Some(block) = &batch.block {
+            tables.put_block(block);
+        }
+        for (key, value) in batch.state_changes {
+            match value {
+                Some(value) => tables.state.insert(key, value),
+                None => tables.state.remove(&key),
+            };
+        }
+        tables.put_receipts(&batch.receipts);
+        if batch.head.is_some() {
+            tables.head = batch.head;
+        }
+        if batch.macro_head.is_some() {
+            tables.macro_head = batch.macro_head;
+        }
+        if batch.election_head.is_some() {
+            tables.election_head = batch.election_head;
+        }
+        Ok(())
+    }
+
+    async fn put_execution_result(&self, tx_hash: &Blake2bHash, result: &[u8]) -> Result<()> {
+        self.tables.write().unwrap().execution_results.insert(*tx_hash, result.to_vec());
+        Ok(())
+    }
+
+    async fn get_execution_result(&self, tx_hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        Ok(self.tables.read().unwrap().execution_results.get(tx_hash).cloned())
+    }
+
+    async fn put_macro_certificate(&self, block_number: u32, certificate: &[u8]) -> Result<()> {
+        self.tables.write().unwrap().macro_certificates.insert(block_number, certificate.to_vec());
+        Ok(())
+    }
+
+    async fn macro_certificate(&self, block_number: u32) -> Result<Option<Vec<u8>>> {
+        Ok(self.tables.read().unwrap().macro_certificates.get(&block_number).cloned())
+    }
+
+    async fn load_state_trie(&self) -> Result<StateTrie> {
+        let mut state_trie = StateTrie::new();
+        for (key, value) in &self.tables.read().unwrap().state {
+            state_trie.insert(*key, value.clone());
+        }
+        Ok(state_trie)
+    }
+
+    fn size_bytes(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{MicroBlock, MicroHeader, MicroBody};
+    use crate::blockchain::block::TransactionData;
+    use crate::primitives::NetworkId;
+    use crate::smart_contracts::{ContractEvent, event_topic};
+
+    fn transaction(nonce: u64) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: nonce,
+            fee: 1,
+            nonce,
+            validity_start_height: 0,
+            data: TransactionData::Basic,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: Height, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    fn receipt(transaction: &Transaction, contract: Blake2bHash, block_number: u32, transaction_index: u32) -> ContractReceipt {
+        ContractReceipt {
+            transaction_hash: transaction.hash(),
+            contract_address: contract,
+            success: true,
+            gas_used: 21_000,
+            return_value: None,
+            logs: vec![],
+            events: vec![],
+            error: None,
+            block_number,
+            transaction_index,
+        }
+    }
+
+    /// Behaviour every chain store must share, so tests on the in-memory store hold for MDBX
+    async fn conformance(store: &dyn ChainStore) {
+        assert!(store.get_head_hash().await.is_err());
+        assert!(store.get_macro_head_hash().await.is_err());
+        assert!(store.get_block_at(1).await.unwrap().is_none());
+
+        let first = micro_block(1, vec![transaction(1), transaction(2)]);
+        let second = micro_block(2, vec![transaction(3)]);
+        let contract = Blake2bHash::from_data(b"contract");
+        let state_key = Blake2bHash::from_data(b"state-key");
+        store.commit(WriteBatch {
+            block: Some(first.clone()),
+            state_changes: vec![(state_key, Some(b"value".to_vec()))],
+            receipts: vec![receipt(&first.transactions()[1], contract, 1, 1)],
+            head: Some(first.hash()),
+            macro_head: Some(first.hash()),
+            ..WriteBatch::default()
+        }).await.unwrap();
+        let settled = event_topic("SettlementExecuted");
+        let mut executed = receipt(&second.transactions()[0], contract, 2, 0);
+        executed.events = vec![
+            ContractEvent { contract_address: contract, topics: vec![event_topic("SettlementProposed")], data: vec![1] },
+            ContractEvent { contract_address: contract, topics: vec![settled], data: vec![2] },
+        ];
+        store.commit(WriteBatch {
+            block: Some(second.clone()),
+            receipts: vec![executed],
+            head: Some(second.hash()),
+            ..WriteBatch::default()
+        }).await.unwrap();
+
+        // Heads, the height index and the transaction index
+        assert_eq!(store.get_head_hash().await.unwrap(), second.hash());
+        assert_eq!(store.get_macro_head_hash().await.unwrap(), first.hash());
+        assert!(store.get_election_head_hash().await.is_err());
+        assert_eq!(store.get_block(&first.hash()).await.unwrap().unwrap().hash(), first.hash());
+        assert_eq!(store.get_block_at(2).await.unwrap().unwrap().hash(), second.hash());
+        let (transaction, location) = store.get_transaction(&first.transactions()[1].hash()).await.unwrap().unwrap();
+        assert_eq!(transaction.hash(), first.transactions()[1].hash());
+        assert_eq!(location, TransactionLocation { block_hash: first.hash(), index: 1 });
+
+        // Receipts in chain order per contract
+        let receipts = store.get_receipts_by_contract(&contract).await.unwrap();
+        assert_eq!(receipts.iter().map(|receipt| receipt.block_number).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(store.get_receipt(&second.transactions()[0].hash()).await.unwrap().is_some());
+        assert!(store.get_receipts_by_contract(&state_key).await.unwrap().is_empty());
+
+        // Events per contract, and per contract and topic
+        assert_eq!(store.get_events(&contract, None).await.unwrap().len(), 2);
+        let settlements = store.get_events(&contract, Some(&settled)).await.unwrap();
+        assert_eq!(settlements.iter().map(|record| (record.block_number, record.log_index)).collect::<Vec<_>>(), vec![(2, 1)]);
+        assert_eq!(settlements[0].event.data, vec![2]);
+        assert!(store.get_events(&state_key, Some(&settled)).await.unwrap().is_empty());
+
+        // State leaves, execution results, certificates and evidence
+        assert_eq!(store.load_state_trie().await.unwrap().get(&state_key), Some(&b"value".to_vec()));
+        store.commit(WriteBatch { state_changes: vec![(state_key, None)], ..WriteBatch::default() }).await.unwrap();
+        assert!(store.load_state_trie().await.unwrap().get(&state_key).is_none());
+        assert_eq!(store.get_head_hash().await.unwrap(), second.hash());
+
+        let tx_hash = first.transactions()[0].hash();
+        assert!(store.get_execution_result(&tx_hash).await.unwrap().is_none());
+        store.put_execution_result(&tx_hash, b"result").await.unwrap();
+        assert_eq!(store.get_execution_result(&tx_hash).await.unwrap(), Some(b"result".to_vec()));
+
+        store.put_macro_certificate(32, b"certificate").await.unwrap();
+        assert_eq!(store.macro_certificate(32).await.unwrap(), Some(b"certificate".to_vec()));
+        assert!(store.macro_certificate(64).await.unwrap().is_none());
+
+        let evidence = store.put_evidence(b"evidence").await.unwrap();
+        assert_eq!(store.get_evidence(&evidence).await.unwrap(), Some(b"evidence".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_conformance() {
+        conformance(&SimpleChainStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_mdbx_store_conformance() {
+        let dir = tempfile::tempdir().unwrap();
+        conformance(&MdbxChainStore::new(dir.path()).unwrap()).await;
+    }
+}
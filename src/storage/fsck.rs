@@ -0,0 +1,289 @@
+// Storage integrity check: walks the MDBX store verifying that stored blocks link up by hash,
+// that the height, transaction, contract receipt and event indexes match the blocks and receipts they
+// are derived from, and that the persisted state hashes to the head block's state root. Derived
+// indexes can be rebuilt from the raw blocks and receipts, anything else is only reported
+use std::collections::{BTreeMap, HashMap};
+use serde::Serialize;
+
+use crate::primitives::{Result, BlockchainError, Blake2bHash, Height};
+use crate::blockchain::Block;
+use crate::smart_contracts::ContractReceipt;
+use super::MdbxChainStore;
+
+/// Problem found in the store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FsckIssue {
+    /// Table the problem was found in
+    pub table: &'static str,
+    pub description: String,
+    /// Whether rebuilding the derived indexes fixes it
+    pub repairable: bool,
+}
+
+/// Outcome of a storage integrity check
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FsckReport {
+    pub blocks_checked: usize,
+    pub transactions_checked: usize,
+    pub receipts_checked: usize,
+    pub head_block: Option<Height>,
+    pub issues: Vec<FsckIssue>,
+    /// Whether the derived indexes were rebuilt
+    pub repaired: bool,
+    /// Index entries written or deleted by the repair
+    pub entries_repaired: usize,
+}
+
+impl FsckReport {
+    /// Issues still in the store, those fixed by the repair left out
+    pub fn remaining(&self) -> impl Iterator<Item = &FsckIssue> {
+        self.issues.iter().filter(move |issue| !(self.repaired && issue.repairable))
+    }
+
+    fn issue(&mut self, table: &'static str, description: String, repairable: bool) {
+        self.issues.push(FsckIssue { table, description, repairable });
+    }
+}
+
+type IndexWrites = Vec<(&'static str, Vec<u8>, Vec<u8>)>;
+type IndexDeletes = Vec<(&'static str, Vec<u8>)>;
+
+impl MdbxChainStore {
+    /// Check the store, rebuilding the derived indexes from blocks and receipts if `repair` is set.
+    /// Repairs write to the store, so the node must not be running
+    pub async fn fsck(&self, repair: bool) -> Result<FsckReport> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.fsck_blocking(repair))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    fn fsck_blocking(&self, repair: bool) -> Result<FsckReport> {
+        let mut report = FsckReport::default();
+
+        // Every block is stored under its own hash
+        let mut by_height: BTreeMap<Height, Vec<Block>> = BTreeMap::new();
+        for (key, data) in self.mdbx_scan("blocks")? {
+            report.blocks_checked += 1;
+            let block: Block = match bincode::deserialize(&data) {
+                Ok(block) => block,
+                Err(e) => {
+                    report.issue("blocks", format!("Block {} does not decode: {}", hex::encode(&key), e), false);
+                    continue;
+                }
+            };
+            if block.hash().as_bytes() != key.as_slice() {
+                report.issue("blocks", format!("Block #{} stored under {} hashes to {}", block.block_number(), hex::encode(&key), block.hash()), false);
+                continue;
+            }
+            by_height.entry(block.block_number()).or_default().push(block);
+        }
+
+        // One block per height, where several are stored the indexed one is taken
+        let block_numbers: HashMap<Vec<u8>, Vec<u8>> = self.mdbx_scan("block_numbers")?.into_iter().collect();
+        let mut chain: BTreeMap<Height, Block> = BTreeMap::new();
+        for (height, mut blocks) in by_height {
+            if blocks.len() > 1 {
+                let indexed = block_numbers.get(height.to_be_bytes().as_slice());
+                let position = blocks.iter().position(|block| indexed.is_some_and(|hash| hash.as_slice() == block.hash().as_bytes()));
+                report.issue("blocks", format!("{} blocks stored at height {}", blocks.len(), height), false);
+                if let Some(position) = position {
+                    chain.insert(height, blocks.swap_remove(position));
+                }
+                continue;
+            }
+            if let Some(block) = blocks.pop() {
+                chain.insert(height, block);
+            }
+        }
+        let hashes: HashMap<Blake2bHash, Height> = chain.iter().map(|(height, block)| (block.hash(), *height)).collect();
+
+        // Consecutive blocks link by hash, gaps left by a snapshot import are expected
+        for (height, block) in &chain {
+            let parent = height.checked_sub(1).and_then(|parent| chain.get(&parent));
+            if let Some(parent) = parent {
+                if parent.hash() != *block.parent_hash() {
+                    report.issue("blocks", format!("Block #{} has parent {}, block #{} is {}", height, block.parent_hash(), height - 1, parent.hash()), false);
+                }
+            }
+        }
+
+        // The derived indexes as the blocks and receipts say they should be
+        let mut expected_numbers = BTreeMap::new();
+        let mut expected_transactions = BTreeMap::new();
+        for (height, block) in &chain {
+            let hash = block.hash();
+            expected_numbers.insert(height.to_be_bytes().to_vec(), hash.as_bytes().to_vec());
+            for (_, key, location) in Self::transaction_index_writes(&hash, block.transactions())? {
+                expected_transactions.insert(key, location);
+            }
+            report.transactions_checked += block.transactions().len();
+        }
+
+        let mut expected_receipts = BTreeMap::new();
+        let mut expected_events = BTreeMap::new();
+        for (key, data) in self.mdbx_scan("receipts")? {
+            report.receipts_checked += 1;
+            match bincode::deserialize::<ContractReceipt>(&data) {
+                Ok(receipt) if receipt.transaction_hash.as_bytes() == key.as_slice() => {
+                    for event_key in Self::contract_event_keys(&receipt) {
+                        expected_events.insert(event_key, key.clone());
+                    }
+                    expected_receipts.insert(Self::contract_receipt_key(&receipt), key);
+                }
+                Ok(receipt) => report.issue("receipts", format!("Receipt of {} stored under {}", receipt.transaction_hash, hex::encode(&key)), false),
+                Err(e) => report.issue("receipts", format!("Receipt {} does not decode: {}", hex::encode(&key), e), false),
+            }
+        }
+
+        let mut writes = Vec::new();
+        let mut deletes = Vec::new();
+        diff_index("block_numbers", block_numbers, &expected_numbers, &mut report, &mut writes, &mut deletes);
+        let transactions = self.mdbx_scan("tx_index")?.into_iter().collect();
+        diff_index("tx_index", transactions, &expected_transactions, &mut report, &mut writes, &mut deletes);
+        let receipts = self.mdbx_scan("contract_receipts")?.into_iter().collect();
+        diff_index("contract_receipts", receipts, &expected_receipts, &mut report, &mut writes, &mut deletes);
+        let events = self.mdbx_scan("contract_events")?.into_iter().collect();
+        diff_index("contract_events", events, &expected_events, &mut report, &mut writes, &mut deletes);
+
+        // Heads point at stored blocks, the head's state root matches the persisted state
+        for name in ["head", "macro_head", "election_head"] {
+            let hash = match self.mdbx_get("metadata", name.as_bytes())? {
+                Some(data) => match bincode::deserialize::<Blake2bHash>(&data) {
+                    Ok(hash) => hash,
+                    Err(e) => {
+                        report.issue("metadata", format!("{} does not decode: {}", name, e), false);
+                        continue;
+                    }
+                },
+                None => continue,
+            };
+            match hashes.get(&hash) {
+                Some(height) if name == "head" => report.head_block = Some(*height),
+                Some(_) => {}
+                None => report.issue("metadata", format!("{} {} is not a stored block", name, hash), false),
+            }
+        }
+
+        if let Some(head) = report.head_block.and_then(|height| chain.get(&height)) {
+            let state_root = self.state_trie_blocking()?.root();
+            if state_root != *head.state_root() {
+                report.issue("state", format!("State root {} does not match head block #{} state root {}", state_root, head.block_number(), head.state_root()), false);
+            }
+        }
+
+        if repair && !(writes.is_empty() && deletes.is_empty()) {
+            self.mdbx_write_batch(&writes, &deletes)?;
+            report.entries_repaired = writes.len() + deletes.len();
+        }
+        report.repaired = repair;
+
+        Ok(report)
+    }
+}
+
+/// Compare a derived index with what it should hold, recording the differences as issues and
+/// the writes and deletes fixing them
+fn diff_index(
+    table: &'static str,
+    actual: HashMap<Vec<u8>, Vec<u8>>,
+    expected: &BTreeMap<Vec<u8>, Vec<u8>>,
+    report: &mut FsckReport,
+    writes: &mut IndexWrites,
+    deletes: &mut IndexDeletes,
+) {
+    for (key, value) in expected {
+        match actual.get(key) {
+            Some(current) if current == value => continue,
+            Some(_) => report.issue(table, format!("Entry {} is wrong", hex::encode(key)), true),
+            None => report.issue(table, format!("Entry {} is missing", hex::encode(key)), true),
+        }
+        writes.push((table, key.clone(), value.clone()));
+    }
+
+    let mut stale: Vec<_> = actual.into_keys().filter(|key| !expected.contains_key(key)).collect();
+    stale.sort();
+    for key in stale {
+        report.issue(table, format!("Entry {} is stale", hex::encode(&key)), true);
+        deletes.push((table, key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{MicroBlock, MicroHeader, MicroBody};
+    use crate::blockchain::block::{Transaction, TransactionData};
+    use crate::primitives::NetworkId;
+    use crate::storage::{ChainStore, WriteBatch};
+
+    fn transaction(nonce: u64) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: nonce,
+            fee: 1,
+            nonce,
+            validity_start_height: 0,
+            data: TransactionData::Basic,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: Height, parent_hash: Blake2bHash, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash,
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fsck_rebuilds_derived_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+        let first = micro_block(1, Blake2bHash::zero(), vec![transaction(1)]);
+        let second = micro_block(2, first.hash(), vec![transaction(2), transaction(3)]);
+        for block in [&first, &second] {
+            store.commit(WriteBatch { block: Some(block.clone()), head: Some(block.hash()), ..WriteBatch::default() }).await.unwrap();
+        }
+
+        let report = store.fsck(false).await.unwrap();
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert_eq!((report.blocks_checked, report.transactions_checked, report.head_block), (2, 3, Some(2)));
+
+        // Lose a transaction index entry and point height 1 at the wrong block
+        let lost = second.transactions()[1].hash();
+        store.mdbx_write_batch(
+            &[("block_numbers", 1u32.to_be_bytes().to_vec(), second.hash().as_bytes().to_vec())],
+            &[("tx_index", lost.as_bytes().to_vec())],
+        ).unwrap();
+        assert!(store.get_transaction(&lost).await.unwrap().is_none());
+
+        let report = store.fsck(true).await.unwrap();
+        assert_eq!(report.issues.len(), 2);
+        assert!(report.issues.iter().all(|issue| issue.repairable));
+        assert_eq!(report.remaining().count(), 0);
+        assert_eq!(report.entries_repaired, 2);
+        assert_eq!(store.get_block_at(1).await.unwrap().unwrap().hash(), first.hash());
+        assert_eq!(store.get_transaction(&lost).await.unwrap().unwrap().1.index, 1);
+        assert!(store.fsck(false).await.unwrap().issues.is_empty());
+
+        // A broken parent link is reported but not repaired
+        let orphan = micro_block(3, Blake2bHash::from_data(b"elsewhere"), vec![]);
+        store.put_block(&orphan).await.unwrap();
+        let report = store.fsck(true).await.unwrap();
+        assert_eq!(report.remaining().map(|issue| issue.table).collect::<Vec<_>>(), vec!["blocks"]);
+    }
+}
@@ -1,6 +1,14 @@
 // History store for blockchain state history
+use serde::{Deserialize, Serialize};
 use crate::primitives::{Blake2bHash, Result};
 
+/// Where a transaction was included, as kept in the transaction index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionLocation {
+    pub block_hash: Blake2bHash,
+    pub index: u32,
+}
+
 pub struct HistoryStore {}
 
 impl HistoryStore {
@@ -12,4 +20,4 @@ impl HistoryStore {
         // Placeholder implementation
         Ok(())
     }
-}
\ No newline at end of file
+}
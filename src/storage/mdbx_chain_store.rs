@@ -19,7 +19,7 @@ impl MdbxChainStore {
 
         // Use Albatross database configuration
         let config = libmdbx::DatabaseOptions {
-            max_tables: Some(20),
+            max_tables: Some(32),
             max_readers: None,
             no_rdahead: true,
             mode: Mode::ReadWrite(libmdbx::ReadWriteOptions {
@@ -0,0 +1,274 @@
+// Read-only snapshots of the MDBX store: one read transaction held open on a reader thread of
+// its own, so reports and inspect queries see a single consistent view of the chain while blocks
+// keep being committed. MDBX read transactions are bound to the thread that opened them, hence
+// the thread; requests reach it over a channel and it exits once the snapshot is dropped
+use std::sync::{mpsc, Arc};
+use libmdbx::NoWriteMap;
+use tokio::sync::oneshot;
+use crate::primitives::{Result, BlockchainError, Blake2bHash};
+use crate::blockchain::Block;
+use crate::blockchain::block::Transaction;
+use crate::smart_contracts::{ContractReceipt, EventRecord};
+use super::{ChainStore, MdbxChainStore, WriteBatch};
+use super::history_store::TransactionLocation;
+use super::state_trie::StateTrie;
+use super::mdbx_store::{txn_get, txn_scan_prefix};
+
+/// Read served by the snapshot's reader thread
+enum SnapshotRead {
+    Get {
+        table: &'static str,
+        key: Vec<u8>,
+        reply: oneshot::Sender<Result<Option<Vec<u8>>>>,
+    },
+    ScanPrefix {
+        table: &'static str,
+        prefix: Vec<u8>,
+        reply: oneshot::Sender<Result<Vec<(Vec<u8>, Vec<u8>)>>>,
+    },
+}
+
+/// Read handle on the MDBX store as it was when the snapshot was taken. Writers are never
+/// blocked by it, but the pages it sees cannot be reused until it is dropped, so take one per
+/// query rather than keeping it around
+pub struct MdbxSnapshot {
+    requests: mpsc::Sender<SnapshotRead>,
+}
+
+impl MdbxSnapshot {
+    pub(super) async fn open(db: Arc<libmdbx::Database<NoWriteMap>>) -> Result<Self> {
+        let (requests, incoming) = mpsc::channel::<SnapshotRead>();
+        let (opened, ready) = oneshot::channel::<Result<()>>();
+
+        std::thread::Builder::new()
+            .name("mdbx-snapshot".to_string())
+            .spawn(move || {
+                let txn = match db.begin_ro_txn() {
+                    Ok(txn) => txn,
+                    Err(e) => {
+                        let _ = opened.send(Err(BlockchainError::Storage(format!("Read transaction failed: {}", e))));
+                        return;
+                    }
+                };
+                let _ = opened.send(Ok(()));
+
+                // Ends when the snapshot, and with it the only sender, is dropped
+                while let Ok(read) = incoming.recv() {
+                    match read {
+                        SnapshotRead::Get { table, key, reply } => {
+                            let _ = reply.send(txn_get(&txn, table, &key));
+                        }
+                        SnapshotRead::ScanPrefix { table, prefix, reply } => {
+                            let _ = reply.send(txn_scan_prefix(&txn, table, &prefix));
+                        }
+                    }
+                }
+            })
+            .map_err(|e| BlockchainError::Storage(format!("Snapshot reader thread failed: {}", e)))?;
+
+        ready.await
+            .map_err(|_| BlockchainError::Storage("Snapshot reader thread exited".to_string()))??;
+        Ok(Self { requests })
+    }
+
+    async fn get(&self, table: &'static str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let (reply, response) = oneshot::channel();
+        self.requests.send(SnapshotRead::Get { table, key: key.to_vec(), reply })
+            .map_err(|_| BlockchainError::Storage("Snapshot reader thread exited".to_string()))?;
+        response.await
+            .map_err(|_| BlockchainError::Storage("Snapshot reader thread exited".to_string()))?
+    }
+
+    async fn scan_prefix(&self, table: &'static str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let (reply, response) = oneshot::channel();
+        self.requests.send(SnapshotRead::ScanPrefix { table, prefix: prefix.to_vec(), reply })
+            .map_err(|_| BlockchainError::Storage("Snapshot reader thread exited".to_string()))?;
+        response.await
+            .map_err(|_| BlockchainError::Storage("Snapshot reader thread exited".to_string()))?
+    }
+
+    async fn get_hash(&self, table: &'static str, key: &[u8]) -> Result<Option<Blake2bHash>> {
+        match self.get(table, key).await? {
+            Some(data) => {
+                let bytes: [u8; 32] = data.as_slice().try_into()
+                    .map_err(|_| BlockchainError::Storage(format!("Invalid {} index entry", table)))?;
+                Ok(Some(Blake2bHash::from_bytes(bytes)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_head(&self, name: &'static [u8], label: &str) -> Result<Blake2bHash> {
+        match self.get("metadata", name).await? {
+            Some(data) => bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Storage(format!("{} deserialize failed: {}", label, e))),
+            None => Err(BlockchainError::Storage(format!("No {} found", label.to_lowercase()))),
+        }
+    }
+
+    fn read_only<T>() -> Result<T> {
+        Err(BlockchainError::InvalidOperation("MDBX snapshots are read-only".to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ChainStore for MdbxSnapshot {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    async fn get_block(&self, hash: &Blake2bHash) -> Result<Option<Block>> {
+        match self.get("blocks", hash.as_bytes()).await? {
+            Some(data) => {
+                let block: Block = bincode::deserialize(&data)
+                    .map_err(|e| BlockchainError::Storage(format!("Block deserialize failed: {}", e)))?;
+                Ok(Some(block))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_block_at(&self, block_number: u32) -> Result<Option<Block>> {
+        match self.get_hash("block_numbers", &block_number.to_be_bytes()).await? {
+            Some(hash) => self.get_block(&hash).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn put_block(&self, _block: &Block) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn get_head_hash(&self) -> Result<Blake2bHash> {
+        self.get_head(b"head", "Head hash").await
+    }
+
+    async fn set_head(&self, _hash: &Blake2bHash) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn get_macro_head_hash(&self) -> Result<Blake2bHash> {
+        self.get_head(b"macro_head", "Macro head hash").await
+    }
+
+    async fn set_macro_head(&self, _hash: &Blake2bHash) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn get_election_head_hash(&self) -> Result<Blake2bHash> {
+        self.get_head(b"election_head", "Election head hash").await
+    }
+
+    async fn set_election_head(&self, _hash: &Blake2bHash) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn put_evidence(&self, _data: &[u8]) -> Result<Blake2bHash> {
+        Self::read_only()
+    }
+
+    async fn get_evidence(&self, hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        match self.get("evidence", hash.as_bytes()).await? {
+            Some(data) => {
+                if crate::primitives::primitives::hash_data(&data) != *hash {
+                    return Err(BlockchainError::Storage("Evidence blob hash mismatch".to_string()));
+                }
+                Ok(Some(data))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_transaction(&self, hash: &Blake2bHash) -> Result<Option<(Transaction, TransactionLocation)>> {
+        let location: TransactionLocation = match self.get("tx_index", hash.as_bytes()).await? {
+            Some(data) => bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Storage(format!("Transaction location deserialize failed: {}", e)))?,
+            None => return Ok(None),
+        };
+
+        let block = self.get_block(&location.block_hash).await?
+            .ok_or_else(|| BlockchainError::Storage(format!("Indexed block {} missing", location.block_hash)))?;
+        let transaction = block.transactions().get(location.index as usize).cloned()
+            .ok_or_else(|| BlockchainError::Storage(format!("Indexed transaction {} missing from block", hash)))?;
+
+        Ok(Some((transaction, location)))
+    }
+
+    async fn put_receipts(&self, _receipts: &[ContractReceipt]) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn get_receipt(&self, tx_hash: &Blake2bHash) -> Result<Option<ContractReceipt>> {
+        match self.get("receipts", tx_hash.as_bytes()).await? {
+            Some(data) => {
+                let receipt: ContractReceipt = bincode::deserialize(&data)
+                    .map_err(|e| BlockchainError::Storage(format!("Receipt deserialize failed: {}", e)))?;
+                Ok(Some(receipt))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_receipts_by_contract(&self, contract: &Blake2bHash) -> Result<Vec<ContractReceipt>> {
+        let mut receipts = Vec::new();
+        for (_, tx_hash) in self.scan_prefix("contract_receipts", contract.as_bytes()).await? {
+            let bytes: [u8; 32] = tx_hash.as_slice().try_into()
+                .map_err(|_| BlockchainError::Storage("Invalid contract receipt index entry".to_string()))?;
+            if let Some(receipt) = self.get_receipt(&Blake2bHash::from_bytes(bytes)).await? {
+                receipts.push(receipt);
+            }
+        }
+        Ok(receipts)
+    }
+
+    async fn get_events(&self, contract: &Blake2bHash, topic: Option<&Blake2bHash>) -> Result<Vec<EventRecord>> {
+        let Some(topic) = topic else {
+            let receipts = self.get_receipts_by_contract(contract).await?;
+            return Ok(receipts.iter().flat_map(EventRecord::of_receipt).collect());
+        };
+
+        let mut events = Vec::new();
+        for (key, tx_hash) in self.scan_prefix("contract_events", &MdbxChainStore::contract_event_prefix(contract, topic)).await? {
+            let bytes: [u8; 32] = tx_hash.as_slice().try_into()
+                .map_err(|_| BlockchainError::Storage("Invalid contract event index entry".to_string()))?;
+            if let Some(receipt) = self.get_receipt(&Blake2bHash::from_bytes(bytes)).await? {
+                events.extend(MdbxChainStore::indexed_event(&receipt, &key));
+            }
+        }
+        Ok(events)
+    }
+
+    async fn commit(&self, _batch: WriteBatch) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn put_execution_result(&self, _tx_hash: &Blake2bHash, _result: &[u8]) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn get_execution_result(&self, tx_hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        self.get("execution_results", tx_hash.as_bytes()).await
+    }
+
+    async fn put_macro_certificate(&self, _block_number: u32, _certificate: &[u8]) -> Result<()> {
+        Self::read_only()
+    }
+
+    async fn macro_certificate(&self, block_number: u32) -> Result<Option<Vec<u8>>> {
+        self.get("macro_certificates", &block_number.to_be_bytes()).await
+    }
+
+    async fn load_state_trie(&self) -> Result<StateTrie> {
+        let mut state_trie = StateTrie::new();
+        for (key, value) in self.scan_prefix("state", &[]).await? {
+            let key: [u8; 32] = key.as_slice().try_into()
+                .map_err(|_| BlockchainError::Storage("Invalid state trie key".to_string()))?;
+            state_trie.insert(Blake2bHash::from_bytes(key), value);
+        }
+        Ok(state_trie)
+    }
+
+    fn size_bytes(&self) -> Option<u64> {
+        None
+    }
+}
@@ -1,9 +1,14 @@
 // Real MDBX storage implementation using Albatross patterns
-use std::{ops::Range, path::Path, sync::Arc};
+use std::{collections::HashMap, ops::Range, path::{Path, PathBuf}, sync::Arc};
 use libmdbx::{NoWriteMap, TableFlags, WriteFlags};
-use crate::primitives::{Result, BlockchainError, Blake2bHash};
+use crate::primitives::{Result, BlockchainError, Blake2bHash, Height, Policy, Timestamp};
 use crate::blockchain::Block;
-use super::ChainStore;
+use crate::blockchain::block::{CDRType, Transaction, TransactionData, ValidatorInfo};
+use crate::smart_contracts::{ArchivedSlot, ContractReceipt, EventRecord, SlotStatus, StateResurrection};
+use crate::smart_contracts::state_expiry::CompactionStats;
+use super::{ChainReadCache, ChainStore, MdbxSnapshot, WriteBatch};
+use super::history_store::TransactionLocation;
+use super::state_trie::StateTrie;
 
 const GIGABYTE: usize = 1024 * 1024 * 1024;
 const TERABYTE: usize = GIGABYTE * 1024;
@@ -20,7 +25,7 @@ pub struct DatabaseConfig {
 impl Default for DatabaseConfig {
     fn default() -> Self {
         DatabaseConfig {
-            max_tables: Some(20),
+            max_tables: Some(32),
             max_readers: None,
             no_rdahead: true,
             // Default max database size: 2TB
@@ -49,10 +54,104 @@ impl From<DatabaseConfig> for libmdbx::DatabaseOptions {
     }
 }
 
+/// What the store keeps of old blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruningMode {
+    /// Keep every block in full, for auditors and explorers
+    Archive,
+    /// Keep full blocks for the last `keep_epochs` epochs and all macro blocks,
+    /// older micro blocks keep their headers and settlement transactions only
+    Validator { keep_epochs: u32 },
+}
+
+impl PruningMode {
+    /// Epochs a validator keeps in full by default
+    pub const DEFAULT_KEEP_EPOCHS: u32 = 4;
+}
+
+impl Default for PruningMode {
+    fn default() -> Self {
+        PruningMode::Archive
+    }
+}
+
+impl std::str::FromStr for PruningMode {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "archive" => Ok(PruningMode::Archive),
+            "validator" => Ok(PruningMode::Validator { keep_epochs: Self::DEFAULT_KEEP_EPOCHS }),
+            _ => Err(BlockchainError::InvalidOperation(format!("Unknown pruning mode: {}. Use: archive, validator", s))),
+        }
+    }
+}
+
+/// Tables the BCE pipeline keeps its state in as it changes, so it survives a crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineTable {
+    /// Batch id -> batch waiting for its settlement period to close
+    PendingBatches,
+    /// Proposal id -> settlement proposal
+    SettlementProposals,
+    /// Batch id -> batch held back by fraud detection
+    QuarantinedBatches,
+    /// Pipeline statistics and other single values
+    PipelineMeta,
+    /// Proposal id -> settlement waiting for a finance operator's decision
+    PendingApprovals,
+    /// Settlement transaction -> signed payment document issued for it
+    PaymentDocuments,
+}
+
+impl PipelineTable {
+    pub const ALL: [PipelineTable; 6] = [
+        PipelineTable::PendingBatches,
+        PipelineTable::SettlementProposals,
+        PipelineTable::QuarantinedBatches,
+        PipelineTable::PipelineMeta,
+        PipelineTable::PendingApprovals,
+        PipelineTable::PaymentDocuments,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PipelineTable::PendingBatches => "pending_batches",
+            PipelineTable::SettlementProposals => "settlement_proposals",
+            PipelineTable::QuarantinedBatches => "quarantined_batches",
+            PipelineTable::PipelineMeta => "pipeline_meta",
+            PipelineTable::PendingApprovals => "pending_approvals",
+            PipelineTable::PaymentDocuments => "payment_documents",
+        }
+    }
+}
+
+/// Result of a pruning pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    pub blocks_pruned: u32,
+    pub transactions_removed: u32,
+}
+
+/// CDR record whose encrypted payload was purged from a stored block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PurgedPayload {
+    pub block_number: Height,
+    pub block_hash: Blake2bHash,
+    /// Hash of the record, which its stub keeps
+    pub transaction_hash: Blake2bHash,
+    pub record_type: CDRType,
+    pub purged_bytes: u64,
+}
+
 /// Real MDBX Database following Albatross patterns exactly
 #[derive(Clone)]
 pub struct MdbxChainStore {
     db: Arc<libmdbx::Database<NoWriteMap>>,
+    pruning_mode: PruningMode,
+    path: PathBuf,
+    /// Shared by every clone, blocking tasks included
+    cache: Arc<ChainReadCache>,
 }
 
 impl MdbxChainStore {
@@ -61,11 +160,14 @@ impl MdbxChainStore {
             .map_err(|e| BlockchainError::Storage(format!("Failed to create directory: {}", e)))?;
 
         let config = DatabaseConfig::default();
-        let db = libmdbx::Database::open_with_options(path, libmdbx::DatabaseOptions::from(config))
+        let db = libmdbx::Database::open_with_options(path.as_ref(), libmdbx::DatabaseOptions::from(config))
             .map_err(|e| BlockchainError::Storage(format!("MDBX open failed: {}", e)))?;
 
         let store = Self {
             db: Arc::new(db),
+            pruning_mode: PruningMode::default(),
+            path: path.as_ref().to_path_buf(),
+            cache: Arc::new(ChainReadCache::default()),
         };
 
         // Create required tables
@@ -109,6 +211,22 @@ impl MdbxChainStore {
             }
         }
 
+        // Create contract slot status table (contract and key -> last write or expiry height)
+        if let Err(e) = txn.create_table(Some("contract_slot_status"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create contract_slot_status table failed: {}", e)));
+            }
+        }
+
+        // Create contract state archive table (expiry height and index -> expired slot)
+        if let Err(e) = txn.create_table(Some("contract_state_archive"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create contract_state_archive table failed: {}", e)));
+            }
+        }
+
         if let Err(e) = txn.create_table(Some("execution_results"), TableFlags::empty()) {
             // Ignore error if table already exists
             if !e.to_string().contains("already exists") {
@@ -116,6 +234,112 @@ impl MdbxChainStore {
             }
         }
 
+        // Create dispute evidence table (content-addressed blobs)
+        if let Err(e) = txn.create_table(Some("evidence"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create evidence table failed: {}", e)));
+            }
+        }
+
+        // Create block number index table (height -> block hash)
+        if let Err(e) = txn.create_table(Some("block_numbers"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create block_numbers table failed: {}", e)));
+            }
+        }
+
+        // Create transaction index table (tx hash -> block hash and position)
+        if let Err(e) = txn.create_table(Some("tx_index"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create tx_index table failed: {}", e)));
+            }
+        }
+
+        // Create state trie table (leaves of the authenticated state)
+        if let Err(e) = txn.create_table(Some("state"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create state table failed: {}", e)));
+            }
+        }
+
+        // Create receipts table (tx hash -> contract receipt)
+        if let Err(e) = txn.create_table(Some("receipts"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create receipts table failed: {}", e)));
+            }
+        }
+
+        // Create contract receipt index (contract, block number, tx index -> tx hash)
+        if let Err(e) = txn.create_table(Some("contract_receipts"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create contract_receipts table failed: {}", e)));
+            }
+        }
+
+        // Create contract event index (contract, topic, block number, tx index, log index -> tx hash)
+        if let Err(e) = txn.create_table(Some("contract_events"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create contract_events table failed: {}", e)));
+            }
+        }
+
+        // Create peer store table (known peers survive restarts)
+        if let Err(e) = txn.create_table(Some("peers"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create peers table failed: {}", e)));
+            }
+        }
+
+        // Create audit log table (sequence -> hash-chained audit entry)
+        if let Err(e) = txn.create_table(Some("audit_log"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create audit_log table failed: {}", e)));
+            }
+        }
+
+        // Create settlement store table (pipeline work in flight across restarts)
+        if let Err(e) = txn.create_table(Some("settlement_store"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create settlement_store table failed: {}", e)));
+            }
+        }
+
+        // Create macro certificate table (block number -> finality certificate for light clients)
+        if let Err(e) = txn.create_table(Some("macro_certificates"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create macro_certificates table failed: {}", e)));
+            }
+        }
+
+        // Create proof cache table (circuit and witness commitment -> proof, reused on retries)
+        if let Err(e) = txn.create_table(Some("proof_cache"), TableFlags::empty()) {
+            // Ignore error if table already exists
+            if !e.to_string().contains("already exists") {
+                return Err(BlockchainError::Storage(format!("Create proof_cache table failed: {}", e)));
+            }
+        }
+
+        // Create pipeline state tables (pending batches, proposals, quarantine and stats)
+        for table in PipelineTable::ALL {
+            if let Err(e) = txn.create_table(Some(table.name()), TableFlags::empty()) {
+                // Ignore error if table already exists
+                if !e.to_string().contains("already exists") {
+                    return Err(BlockchainError::Storage(format!("Create {} table failed: {}", table.name(), e)));
+                }
+            }
+        }
+
         txn.commit()
             .map_err(|e| BlockchainError::Storage(format!("Transaction commit failed: {}", e)))?;
 
@@ -139,21 +363,97 @@ impl MdbxChainStore {
         Ok(())
     }
 
-    // Direct MDBX get operation
-    fn mdbx_get(&self, table_name: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    // Several MDBX puts and deletes in one transaction, so they land together or not at all
+    pub(super) fn mdbx_write_batch(&self, writes: &[(&str, Vec<u8>, Vec<u8>)], deletes: &[(&str, Vec<u8>)]) -> Result<()> {
+        let txn = self.db.begin_rw_txn()
+            .map_err(|e| BlockchainError::Storage(format!("Write transaction failed: {}", e)))?;
+
+        for (table_name, key, value) in writes {
+            let table = txn.open_table(Some(*table_name))
+                .map_err(|e| BlockchainError::Storage(format!("Open table failed: {}", e)))?;
+
+            txn.put(&table, key, value, WriteFlags::empty())
+                .map_err(|e| BlockchainError::Storage(format!("MDBX put failed: {}", e)))?;
+        }
+
+        for (table_name, key) in deletes {
+            let table = txn.open_table(Some(*table_name))
+                .map_err(|e| BlockchainError::Storage(format!("Open table failed: {}", e)))?;
+
+            // Missing keys are fine, pruning may revisit a block
+            txn.del(&table, key, None)
+                .map_err(|e| BlockchainError::Storage(format!("MDBX delete failed: {}", e)))?;
+        }
+
+        txn.commit()
+            .map_err(|e| BlockchainError::Storage(format!("Transaction commit failed: {}", e)))?;
+
+        Ok(())
+    }
+
+    // Read a whole table in key order
+    pub(super) fn mdbx_scan(&self, table_name: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
         let txn = self.db.begin_ro_txn()
             .map_err(|e| BlockchainError::Storage(format!("Read transaction failed: {}", e)))?;
 
         let table = txn.open_table(Some(table_name))
             .map_err(|e| BlockchainError::Storage(format!("Open table failed: {}", e)))?;
 
-        // Use explicit type annotation to avoid inference issues
-        match txn.get::<Vec<u8>>(&table, key) {
-            Ok(Some(data)) => Ok(Some(data)),
-            Ok(None) => Ok(None),
-            Err(e) => Err(BlockchainError::Storage(format!("MDBX get failed: {}", e))),
+        let mut cursor = txn.cursor(&table)
+            .map_err(|e| BlockchainError::Storage(format!("Open cursor failed: {}", e)))?;
+
+        cursor.iter_start::<Vec<u8>, Vec<u8>>()
+            .map(|entry| entry.map_err(|e| BlockchainError::Storage(format!("MDBX scan failed: {}", e))))
+            .collect()
+    }
+
+    /// Entries whose key starts with `prefix`, in key order
+    fn mdbx_scan_prefix(&self, table_name: &str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let txn = self.db.begin_ro_txn()
+            .map_err(|e| BlockchainError::Storage(format!("Read transaction failed: {}", e)))?;
+        txn_scan_prefix(&txn, table_name, prefix)
+    }
+
+    // Direct MDBX get operation
+    pub(super) fn mdbx_get(&self, table_name: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let txn = self.db.begin_ro_txn()
+            .map_err(|e| BlockchainError::Storage(format!("Read transaction failed: {}", e)))?;
+        txn_get(&txn, table_name, key)
+    }
+
+    /// Consistent read-only view of the store as of now, see [`MdbxSnapshot`]
+    pub async fn snapshot(&self) -> Result<MdbxSnapshot> {
+        MdbxSnapshot::open(self.db.clone()).await
+    }
+}
+
+/// Value of `key` as seen by a read transaction
+pub(super) fn txn_get(txn: &libmdbx::Transaction<'_, libmdbx::RO, NoWriteMap>, table_name: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let table = txn.open_table(Some(table_name))
+        .map_err(|e| BlockchainError::Storage(format!("Open table failed: {}", e)))?;
+
+    // Use explicit type annotation to avoid inference issues
+    txn.get::<Vec<u8>>(&table, key)
+        .map_err(|e| BlockchainError::Storage(format!("MDBX get failed: {}", e)))
+}
+
+/// Entries whose key starts with `prefix` as seen by a read transaction, in key order
+pub(super) fn txn_scan_prefix(txn: &libmdbx::Transaction<'_, libmdbx::RO, NoWriteMap>, table_name: &str, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let table = txn.open_table(Some(table_name))
+        .map_err(|e| BlockchainError::Storage(format!("Open table failed: {}", e)))?;
+
+    let mut cursor = txn.cursor(&table)
+        .map_err(|e| BlockchainError::Storage(format!("Open cursor failed: {}", e)))?;
+
+    let mut entries = Vec::new();
+    for entry in cursor.iter_from::<Vec<u8>, Vec<u8>>(prefix) {
+        let (key, value) = entry.map_err(|e| BlockchainError::Storage(format!("MDBX scan failed: {}", e)))?;
+        if !key.starts_with(prefix) {
+            break;
         }
+        entries.push((key, value));
     }
+    Ok(entries)
 }
 
 #[async_trait::async_trait]
@@ -162,10 +462,14 @@ impl ChainStore for MdbxChainStore {
         self
     }
     async fn get_block(&self, hash: &Blake2bHash) -> Result<Option<Block>> {
+        if let Some(block) = self.cache.block(hash) {
+            return Ok(Some(block));
+        }
+
         let store = self.clone();
         let hash = *hash;
 
-        tokio::task::spawn_blocking(move || {
+        let block = tokio::task::spawn_blocking(move || {
             match store.mdbx_get("blocks", hash.as_bytes())? {
                 Some(data) => {
                     let block: Block = bincode::deserialize(&data)
@@ -176,25 +480,47 @@ impl ChainStore for MdbxChainStore {
             }
         })
         .await
-        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        if let Some(block) = &block {
+            self.cache.insert_block(block);
+        }
+        Ok(block)
     }
 
-    async fn get_block_at(&self, _block_number: u32) -> Result<Option<Block>> {
-        // Would need block number index - not implemented
-        Ok(None)
+    async fn get_block_at(&self, block_number: u32) -> Result<Option<Block>> {
+        let hash = match self.cache.block_hash_at(block_number) {
+            Some(hash) => Some(hash),
+            None => {
+                let store = self.clone();
+                let hash = tokio::task::spawn_blocking(move || store.block_hash_at(block_number))
+                    .await
+                    .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+                if let Some(hash) = hash {
+                    self.cache.insert_height(block_number, hash);
+                }
+                hash
+            }
+        };
+
+        match hash {
+            Some(hash) => self.get_block(&hash).await,
+            None => Ok(None),
+        }
     }
 
     async fn put_block(&self, block: &Block) -> Result<()> {
-        let hash = block.hash();
-        let serialized = bincode::serialize(block)
-            .map_err(|e| BlockchainError::Storage(format!("Block serialize failed: {}", e)))?;
+        let writes = Self::block_writes(block)?;
 
         let store = self.clone();
         tokio::task::spawn_blocking(move || {
-            store.mdbx_put("blocks", hash.as_bytes(), &serialized)
+            store.mdbx_write_batch(&writes, &[])
         })
         .await
-        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+        self.cache.stored(block);
+
+        self.prune_after(block).await
     }
 
     async fn get_head_hash(&self) -> Result<Blake2bHash> {
@@ -280,71 +606,177 @@ impl ChainStore for MdbxChainStore {
         .await
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
-}
 
-// Smart contract storage methods (separate impl block, non-breaking)
-impl MdbxChainStore {
+    async fn put_evidence(&self, data: &[u8]) -> Result<Blake2bHash> {
+        let hash = crate::primitives::primitives::hash_data(data);
+        let data = data.to_vec();
 
-    /// Store contract bytecode
-    pub async fn put_contract_code(&self, contract_address: &Blake2bHash, bytecode: &[u8]) -> Result<()> {
         let store = self.clone();
-        let contract_address = *contract_address;
-        let bytecode = bytecode.to_vec();
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("evidence", hash.as_bytes(), &data)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        Ok(hash)
+    }
+
+    async fn get_evidence(&self, hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let hash = *hash;
 
         tokio::task::spawn_blocking(move || {
-            store.mdbx_put("contracts", contract_address.as_bytes(), &bytecode)
+            match store.mdbx_get("evidence", hash.as_bytes())? {
+                Some(data) => {
+                    // Content addressing doubles as an integrity check
+                    if crate::primitives::primitives::hash_data(&data) != hash {
+                        return Err(BlockchainError::Storage("Evidence blob hash mismatch".to_string()));
+                    }
+                    Ok(Some(data))
+                }
+                None => Ok(None),
+            }
         })
         .await
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
 
-    /// Get contract bytecode
-    pub async fn get_contract_code(&self, contract_address: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+    async fn get_transaction(&self, hash: &Blake2bHash) -> Result<Option<(Transaction, TransactionLocation)>> {
         let store = self.clone();
-        let contract_address = *contract_address;
+        let key = *hash;
+
+        let location = tokio::task::spawn_blocking(move || {
+            match store.mdbx_get("tx_index", key.as_bytes())? {
+                Some(data) => {
+                    let location: TransactionLocation = bincode::deserialize(&data)
+                        .map_err(|e| BlockchainError::Storage(format!("Transaction location deserialize failed: {}", e)))?;
+                    Ok(Some(location))
+                }
+                None => Ok(None),
+            }
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        let location = match location {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+
+        let block = self.get_block(&location.block_hash).await?
+            .ok_or_else(|| BlockchainError::Storage(format!("Indexed block {} missing", location.block_hash)))?;
+        let transaction = block.transactions().get(location.index as usize).cloned()
+            .ok_or_else(|| BlockchainError::Storage(format!("Indexed transaction {} missing from block", hash)))?;
+
+        Ok(Some((transaction, location)))
+    }
+
+    async fn put_receipts(&self, receipts: &[ContractReceipt]) -> Result<()> {
+        let writes = Self::receipt_writes(receipts)?;
 
+        let store = self.clone();
         tokio::task::spawn_blocking(move || {
-            store.mdbx_get("contracts", contract_address.as_bytes())
+            store.mdbx_write_batch(&writes, &[])
         })
         .await
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
 
-    /// Store contract state value
-    pub async fn put_contract_state(&self, contract_address: &Blake2bHash, key: &Blake2bHash, value: &[u8]) -> Result<()> {
+    async fn get_receipt(&self, tx_hash: &Blake2bHash) -> Result<Option<ContractReceipt>> {
         let store = self.clone();
-        let state_key = Self::encode_contract_state_key(contract_address, key);
-        let value = value.to_vec();
+        let key = *tx_hash;
+
+        tokio::task::spawn_blocking(move || store.receipt_blocking(&key))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    async fn get_receipts_by_contract(&self, contract: &Blake2bHash) -> Result<Vec<ContractReceipt>> {
+        let store = self.clone();
+        let contract = *contract;
 
         tokio::task::spawn_blocking(move || {
-            store.mdbx_put("contract_state", &state_key, &value)
+            let mut receipts = Vec::new();
+            for (_, tx_hash) in store.mdbx_scan_prefix("contract_receipts", contract.as_bytes())? {
+                let bytes: [u8; 32] = tx_hash.as_slice().try_into()
+                    .map_err(|_| BlockchainError::Storage("Invalid contract receipt index entry".to_string()))?;
+                if let Some(receipt) = store.receipt_blocking(&Blake2bHash::from_bytes(bytes))? {
+                    receipts.push(receipt);
+                }
+            }
+            Ok(receipts)
         })
         .await
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
 
-    /// Get contract state value
-    pub async fn get_contract_state(&self, contract_address: &Blake2bHash, key: &Blake2bHash) -> Result<Option<Vec<u8>>> {
-        let store = self.clone();
-        let state_key = Self::encode_contract_state_key(contract_address, key);
+    async fn get_events(&self, contract: &Blake2bHash, topic: Option<&Blake2bHash>) -> Result<Vec<EventRecord>> {
+        let Some(topic) = topic else {
+            let receipts = self.get_receipts_by_contract(contract).await?;
+            return Ok(receipts.iter().flat_map(EventRecord::of_receipt).collect());
+        };
 
+        let store = self.clone();
+        let (contract, topic) = (*contract, *topic);
         tokio::task::spawn_blocking(move || {
-            store.mdbx_get("contract_state", &state_key)
+            let mut events = Vec::new();
+            for (key, tx_hash) in store.mdbx_scan_prefix("contract_events", &Self::contract_event_prefix(&contract, &topic))? {
+                let bytes: [u8; 32] = tx_hash.as_slice().try_into()
+                    .map_err(|_| BlockchainError::Storage("Invalid contract event index entry".to_string()))?;
+                if let Some(receipt) = store.receipt_blocking(&Blake2bHash::from_bytes(bytes))? {
+                    events.extend(Self::indexed_event(&receipt, &key));
+                }
+            }
+            Ok(events)
         })
         .await
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
 
-    /// Encode contract state key (contract_address + state_key)
-    fn encode_contract_state_key(contract_address: &Blake2bHash, state_key: &Blake2bHash) -> Vec<u8> {
-        let mut key = Vec::with_capacity(64);
-        key.extend_from_slice(contract_address.as_bytes());
-        key.extend_from_slice(state_key.as_bytes());
-        key
+    async fn commit(&self, batch: WriteBatch) -> Result<()> {
+        let mut writes = Vec::new();
+        let mut deletes = Vec::new();
+        if let Some(block) = &batch.block {
+            writes.extend(Self::block_writes(block)?);
+        }
+        for (key, value) in batch.state_changes {
+            match value {
+                Some(value) => writes.push(("state", key.as_bytes().to_vec(), value)),
+                None => deletes.push(("state", key.as_bytes().to_vec())),
+            }
+        }
+        writes.extend(Self::receipt_writes(&batch.receipts)?);
+        let heads = [
+            (b"head".as_slice(), batch.head),
+            (b"macro_head".as_slice(), batch.macro_head),
+            (b"election_head".as_slice(), batch.election_head),
+        ];
+        for (name, hash) in heads {
+            if let Some(hash) = hash {
+                let serialized = bincode::serialize(&hash)
+                    .map_err(|e| BlockchainError::Storage(format!("Head hash serialize failed: {}", e)))?;
+                writes.push(("metadata", name.to_vec(), serialized));
+            }
+        }
+
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_write_batch(&writes, &deletes)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        // Pruning only drops data of committed blocks, so it runs after the batch
+        match &batch.block {
+            Some(block) => {
+                self.cache.stored(block);
+                self.prune_after(block).await
+            }
+            None => Ok(()),
+        }
     }
 
-    /// Store execution result
-    pub async fn put_execution_result(&self, tx_hash: &Blake2bHash, result: &[u8]) -> Result<()> {
+    async fn put_execution_result(&self, tx_hash: &Blake2bHash, result: &[u8]) -> Result<()> {
         let store = self.clone();
         let tx_hash = *tx_hash;
         let result = result.to_vec();
@@ -356,8 +788,7 @@ impl MdbxChainStore {
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
 
-    /// Get execution result
-    pub async fn get_execution_result(&self, tx_hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+    async fn get_execution_result(&self, tx_hash: &Blake2bHash) -> Result<Option<Vec<u8>>> {
         let store = self.clone();
         let tx_hash = *tx_hash;
 
@@ -367,4 +798,1123 @@ impl MdbxChainStore {
         .await
         .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
     }
-}
\ No newline at end of file
+
+    async fn put_macro_certificate(&self, block_number: u32, certificate: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let certificate = certificate.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("macro_certificates", &block_number.to_be_bytes(), &certificate)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    async fn macro_certificate(&self, block_number: u32) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_get("macro_certificates", &block_number.to_be_bytes())
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    async fn validator_set(&self, epoch: u32) -> Result<Option<Vec<ValidatorInfo>>> {
+        if let Some(validators) = self.cache.validator_set(epoch) {
+            return Ok(Some(validators));
+        }
+        let Some(block_number) = epoch.checked_mul(Policy::ELECTION_BLOCK_INTERVAL) else {
+            return Ok(None);
+        };
+        let validators = match self.get_block_at(block_number).await? {
+            Some(Block::Macro(election)) => election.body.validators,
+            _ => None,
+        };
+        if let Some(validators) = &validators {
+            self.cache.insert_validator_set(epoch, validators.clone());
+        }
+        Ok(validators)
+    }
+
+    async fn load_state_trie(&self) -> Result<StateTrie> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.state_trie_blocking())
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    fn size_bytes(&self) -> Option<u64> {
+        let size = std::fs::read_dir(&self.path)
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum())
+            .unwrap_or(0);
+        Some(size)
+    }
+}
+
+// Receipt methods
+impl MdbxChainStore {
+    fn receipt_writes(receipts: &[ContractReceipt]) -> Result<Vec<(&'static str, Vec<u8>, Vec<u8>)>> {
+        let mut writes = Vec::new();
+        for receipt in receipts {
+            let serialized = bincode::serialize(receipt)
+                .map_err(|e| BlockchainError::Storage(format!("Receipt serialize failed: {}", e)))?;
+            writes.push(("receipts", receipt.transaction_hash.as_bytes().to_vec(), serialized));
+            writes.push(("contract_receipts", Self::contract_receipt_key(receipt), receipt.transaction_hash.as_bytes().to_vec()));
+            for key in Self::contract_event_keys(receipt) {
+                writes.push(("contract_events", key, receipt.transaction_hash.as_bytes().to_vec()));
+            }
+        }
+        Ok(writes)
+    }
+
+    /// Index keys of a receipt's events, one per topic, ordering a contract's events of a topic by chain position
+    pub(super) fn contract_event_keys(receipt: &ContractReceipt) -> Vec<Vec<u8>> {
+        let mut keys = Vec::new();
+        for record in EventRecord::of_receipt(receipt) {
+            for topic in &record.event.topics {
+                let mut key = Self::contract_event_prefix(&record.event.contract_address, topic);
+                key.extend_from_slice(&record.block_number.to_be_bytes());
+                key.extend_from_slice(&record.transaction_index.to_be_bytes());
+                key.extend_from_slice(&record.log_index.to_be_bytes());
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    pub(super) fn contract_event_prefix(contract: &Blake2bHash, topic: &Blake2bHash) -> Vec<u8> {
+        let mut prefix = contract.as_bytes().to_vec();
+        prefix.extend_from_slice(topic.as_bytes());
+        prefix
+    }
+
+    /// Event an index key points at in its receipt, the log index closing the key
+    pub(super) fn indexed_event(receipt: &ContractReceipt, key: &[u8]) -> Option<EventRecord> {
+        let log_index = u32::from_be_bytes(key.get(key.len().checked_sub(4)?..)?.try_into().ok()?);
+        EventRecord::of_receipt(receipt).nth(log_index as usize)
+    }
+
+    /// Index key ordering a contract's receipts by chain position
+    pub(super) fn contract_receipt_key(receipt: &ContractReceipt) -> Vec<u8> {
+        let mut key = receipt.contract_address.as_bytes().to_vec();
+        key.extend_from_slice(&receipt.block_number.to_be_bytes());
+        key.extend_from_slice(&receipt.transaction_index.to_be_bytes());
+        key
+    }
+
+    fn receipt_blocking(&self, tx_hash: &Blake2bHash) -> Result<Option<ContractReceipt>> {
+        match self.mdbx_get("receipts", tx_hash.as_bytes())? {
+            Some(data) => {
+                let receipt: ContractReceipt = bincode::deserialize(&data)
+                    .map_err(|e| BlockchainError::Storage(format!("Receipt deserialize failed: {}", e)))?;
+                Ok(Some(receipt))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+// Pruning methods
+impl MdbxChainStore {
+    /// Use the given pruning mode for blocks stored from now on
+    pub fn with_pruning_mode(mut self, pruning_mode: PruningMode) -> Self {
+        self.pruning_mode = pruning_mode;
+        self
+    }
+
+    pub fn pruning_mode(&self) -> PruningMode {
+        self.pruning_mode
+    }
+
+    fn block_hash_at(&self, block_number: Height) -> Result<Option<Blake2bHash>> {
+        match self.mdbx_get("block_numbers", &block_number.to_be_bytes())? {
+            Some(data) => {
+                let bytes: [u8; 32] = data.as_slice().try_into()
+                    .map_err(|_| BlockchainError::Storage("Invalid block number index entry".to_string()))?;
+                Ok(Some(Blake2bHash::from_bytes(bytes)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Writes storing a block under its hash and number, indexing every transaction so it can be
+    /// found without scanning blocks
+    fn block_writes(block: &Block) -> Result<Vec<(&'static str, Vec<u8>, Vec<u8>)>> {
+        let hash = block.hash();
+        let serialized = bincode::serialize(block)
+            .map_err(|e| BlockchainError::Storage(format!("Block serialize failed: {}", e)))?;
+        let mut writes = vec![
+            ("blocks", hash.as_bytes().to_vec(), serialized),
+            ("block_numbers", block.block_number().to_be_bytes().to_vec(), hash.as_bytes().to_vec()),
+        ];
+        writes.extend(Self::transaction_index_writes(&hash, block.transactions())?);
+        Ok(writes)
+    }
+
+    /// Validators prune as each macro block closes an epoch
+    async fn prune_after(&self, block: &Block) -> Result<()> {
+        if let (Block::Macro(_), PruningMode::Validator { keep_epochs }) = (block, self.pruning_mode) {
+            let keep_blocks = keep_epochs.saturating_mul(Policy::EPOCH_LENGTH);
+            if block.block_number() > keep_blocks {
+                self.prune_before(block.block_number() - keep_blocks).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn transaction_index_writes(block_hash: &Blake2bHash, transactions: &[Transaction]) -> Result<Vec<(&'static str, Vec<u8>, Vec<u8>)>> {
+        transactions.iter().enumerate().map(|(index, transaction)| {
+            let location = TransactionLocation { block_hash: *block_hash, index: index as u32 };
+            let location = bincode::serialize(&location)
+                .map_err(|e| BlockchainError::Storage(format!("Transaction location serialize failed: {}", e)))?;
+            Ok(("tx_index", transaction.hash().as_bytes().to_vec(), location))
+        }).collect()
+    }
+
+    /// Height below which micro block bodies have been pruned
+    pub async fn pruned_height(&self) -> Result<Height> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.pruned_height_blocking())
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    fn pruned_height_blocking(&self) -> Result<Height> {
+        match self.mdbx_get("metadata", b"pruned_before")? {
+            Some(data) => {
+                let bytes: [u8; 4] = data.as_slice().try_into()
+                    .map_err(|_| BlockchainError::Storage("Invalid pruned height".to_string()))?;
+                Ok(Height::from_be_bytes(bytes))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Drop the non-settlement transactions of micro blocks below `height`
+    /// Headers, macro blocks and settlement transactions are kept for audits
+    pub async fn prune_before(&self, height: Height) -> Result<PruneStats> {
+        if self.pruning_mode == PruningMode::Archive {
+            return Err(BlockchainError::InvalidOperation("Archive nodes keep every block".to_string()));
+        }
+
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.prune_before_blocking(height))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    fn prune_before_blocking(&self, height: Height) -> Result<PruneStats> {
+        let mut stats = PruneStats::default();
+        let start = self.pruned_height_blocking()?;
+
+        for block_number in start..height {
+            let hash = match self.block_hash_at(block_number)? {
+                Some(hash) => hash,
+                None => continue,
+            };
+            let data = match self.mdbx_get("blocks", hash.as_bytes())? {
+                Some(data) => data,
+                None => continue,
+            };
+            let mut block: Block = bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Storage(format!("Block deserialize failed: {}", e)))?;
+
+            let micro = match &mut block {
+                Block::Micro(micro) => micro,
+                Block::Macro(_) => continue,
+            };
+
+            let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut micro.body.transactions)
+                .into_iter()
+                .partition(|transaction| matches!(transaction.data, TransactionData::Settlement(_)));
+            if removed.is_empty() {
+                micro.body.transactions = kept;
+                continue;
+            }
+
+            // The header hash is unchanged, only the body shrinks
+            let mut deletes = Vec::new();
+            for transaction in &removed {
+                let tx_hash = transaction.hash();
+                deletes.push(("tx_index", tx_hash.as_bytes().to_vec()));
+                deletes.push(("execution_results", tx_hash.as_bytes().to_vec()));
+                if let Some(receipt) = self.receipt_blocking(&tx_hash)? {
+                    deletes.push(("receipts", tx_hash.as_bytes().to_vec()));
+                    deletes.push(("contract_receipts", Self::contract_receipt_key(&receipt)));
+                    deletes.extend(Self::contract_event_keys(&receipt).into_iter().map(|key| ("contract_events", key)));
+                }
+            }
+
+            // Kept transactions move up in the body, so their index entries are rewritten
+            let mut writes = Self::transaction_index_writes(&hash, &kept)?;
+            micro.body.transactions = kept;
+            let serialized = bincode::serialize(&block)
+                .map_err(|e| BlockchainError::Storage(format!("Block serialize failed: {}", e)))?;
+            writes.push(("blocks", hash.as_bytes().to_vec(), serialized));
+
+            self.mdbx_write_batch(&writes, &deletes)?;
+            self.cache.forget_block(&hash);
+
+            stats.blocks_pruned += 1;
+            stats.transactions_removed += removed.len() as u32;
+        }
+
+        if height > start {
+            self.mdbx_put("metadata", b"pruned_before", &height.to_be_bytes())?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Replace the CDR records of micro blocks up to `up_to` that are older than the cutoff of
+    /// their record type with stubs, which keep the record's hash but not its encrypted payload
+    /// and proof. Record types without a cutoff are kept
+    pub async fn purge_cdr_payloads(&self, up_to: Height, cutoffs: Vec<(CDRType, Timestamp)>, now: Timestamp) -> Result<Vec<PurgedPayload>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.purge_cdr_payloads_blocking(up_to, &cutoffs, now))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    fn purge_cdr_payloads_blocking(&self, up_to: Height, cutoffs: &[(CDRType, Timestamp)], now: Timestamp) -> Result<Vec<PurgedPayload>> {
+        // Height below which each record type is purged already, so blocks are not read again
+        let mut purged_before: HashMap<CDRType, Height> = match self.mdbx_get("metadata", b"payloads_purged_before")? {
+            Some(data) => bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Storage(format!("Payload purge heights deserialize failed: {}", e)))?,
+            None => HashMap::new(),
+        };
+        let Some(start) = cutoffs.iter().map(|(record_type, _)| purged_before.get(record_type).copied().unwrap_or(0)).min() else {
+            return Ok(vec![]);
+        };
+
+        // Blocks are in timestamp order, a type is done at its first block not older than its cutoff
+        let mut done_at: HashMap<CDRType, Height> = HashMap::new();
+        let mut purged = Vec::new();
+        for block_number in start..=up_to {
+            if done_at.len() == cutoffs.len() {
+                break;
+            }
+            let hash = match self.block_hash_at(block_number)? {
+                Some(hash) => hash,
+                None => continue,
+            };
+            let data = match self.mdbx_get("blocks", hash.as_bytes())? {
+                Some(data) => data,
+                None => continue,
+            };
+            let mut block: Block = bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Storage(format!("Block deserialize failed: {}", e)))?;
+
+            let timestamp = block.timestamp();
+            for (record_type, cutoff) in cutoffs {
+                if timestamp >= *cutoff {
+                    done_at.entry(record_type.clone()).or_insert(block_number);
+                }
+            }
+            let micro = match &mut block {
+                Block::Micro(micro) => micro,
+                Block::Macro(_) => continue,
+            };
+
+            let mut changed = false;
+            for transaction in micro.body.transactions.iter_mut() {
+                let TransactionData::CDRRecord(cdr) = &transaction.data else {
+                    continue;
+                };
+                if !cutoffs.iter().any(|(record_type, cutoff)| *record_type == cdr.record_type && timestamp < *cutoff) {
+                    continue;
+                }
+                let stub = transaction.purge_payload(now).expect("transaction is a CDR record");
+                if let TransactionData::PurgedCDRRecord(stub_data) = &stub.data {
+                    purged.push(PurgedPayload {
+                        block_number,
+                        block_hash: hash,
+                        transaction_hash: stub_data.transaction_hash,
+                        record_type: stub_data.record_type.clone(),
+                        purged_bytes: stub_data.purged_bytes,
+                    });
+                }
+                *transaction = stub;
+                changed = true;
+            }
+
+            // Transaction hashes are unchanged, so the index stays as it is
+            if changed {
+                let serialized = bincode::serialize(&block)
+                    .map_err(|e| BlockchainError::Storage(format!("Block serialize failed: {}", e)))?;
+                self.mdbx_put("blocks", hash.as_bytes(), &serialized)?;
+                self.cache.forget_block(&hash);
+            }
+        }
+
+        for (record_type, _) in cutoffs {
+            let height = done_at.get(record_type).copied().unwrap_or(up_to + 1);
+            let previous = purged_before.get(record_type).copied().unwrap_or(0);
+            purged_before.insert(record_type.clone(), height.max(previous));
+        }
+        let data = bincode::serialize(&purged_before)
+            .map_err(|e| BlockchainError::Storage(format!("Payload purge heights serialize failed: {}", e)))?;
+        self.mdbx_put("metadata", b"payloads_purged_before", &data)?;
+
+        Ok(purged)
+    }
+}
+
+// State trie persistence methods
+impl MdbxChainStore {
+    /// Persist state trie changes, see `StateTrie::block_changes`
+    pub async fn put_state_changes(&self, changes: Vec<(Blake2bHash, Option<Vec<u8>>)>) -> Result<()> {
+        let mut writes = Vec::new();
+        let mut deletes = Vec::new();
+        for (key, value) in changes {
+            match value {
+                Some(value) => writes.push(("state", key.as_bytes().to_vec(), value)),
+                None => deletes.push(("state", key.as_bytes().to_vec())),
+            }
+        }
+
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_write_batch(&writes, &deletes)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Rebuild the state trie from its persisted leaves
+    pub(super) fn state_trie_blocking(&self) -> Result<StateTrie> {
+        let mut state_trie = StateTrie::new();
+        for (key, value) in self.mdbx_scan("state")? {
+            let key: [u8; 32] = key.as_slice().try_into()
+                .map_err(|_| BlockchainError::Storage("Invalid state trie key".to_string()))?;
+            state_trie.insert(Blake2bHash::from_bytes(key), value);
+        }
+        Ok(state_trie)
+    }
+}
+
+// Smart contract storage methods (separate impl block, non-breaking)
+impl MdbxChainStore {
+
+    /// Store contract bytecode
+    pub async fn put_contract_code(&self, contract_address: &Blake2bHash, bytecode: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let contract_address = *contract_address;
+        let bytecode = bytecode.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("contracts", contract_address.as_bytes(), &bytecode)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Get contract bytecode
+    pub async fn get_contract_code(&self, contract_address: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let contract_address = *contract_address;
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_get("contracts", contract_address.as_bytes())
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Store contract state value
+    pub async fn put_contract_state(&self, contract_address: &Blake2bHash, key: &Blake2bHash, value: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let state_key = Self::encode_contract_state_key(contract_address, key);
+        let value = value.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("contract_state", &state_key, &value)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Get contract state value
+    pub async fn get_contract_state(&self, contract_address: &Blake2bHash, key: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let state_key = Self::encode_contract_state_key(contract_address, key);
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_get("contract_state", &state_key)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// All deployed contracts with their bytecode
+    pub async fn contract_code_entries(&self) -> Result<Vec<(Blake2bHash, Vec<u8>)>> {
+        let store = self.clone();
+        let entries = tokio::task::spawn_blocking(move || store.mdbx_scan("contracts"))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        entries.into_iter().map(|(key, code)| {
+            let address: [u8; 32] = key.as_slice().try_into()
+                .map_err(|_| BlockchainError::Storage("Invalid contract address".to_string()))?;
+            Ok((Blake2bHash::from_bytes(address), code))
+        }).collect()
+    }
+
+    /// All contract storage slots as (contract, key, value)
+    pub async fn contract_state_entries(&self) -> Result<Vec<(Blake2bHash, Blake2bHash, Vec<u8>)>> {
+        let store = self.clone();
+        let entries = tokio::task::spawn_blocking(move || store.mdbx_scan("contract_state"))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        entries.into_iter().map(|(key, value)| {
+            let (contract, slot) = Self::decode_contract_state_key(&key)?;
+            Ok((contract, slot, value))
+        }).collect()
+    }
+
+    /// Write a contract storage slot and mark it live as of `touched_at`
+    pub async fn put_contract_slot(&self, contract_address: &Blake2bHash, key: &Blake2bHash, value: &[u8], touched_at: Height) -> Result<()> {
+        let store = self.clone();
+        let state_key = Self::encode_contract_state_key(contract_address, key);
+        let status = encode_slot_status(&SlotStatus::Live { touched_at })?;
+        let value = value.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_write_batch(&[
+                ("contract_state", state_key.clone(), value),
+                ("contract_slot_status", state_key, status),
+            ], &[])
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Last write or expiry of a contract storage slot, `None` for slots written before expiry tracking
+    pub async fn contract_slot_status(&self, contract_address: &Blake2bHash, key: &Blake2bHash) -> Result<Option<SlotStatus>> {
+        let store = self.clone();
+        let state_key = Self::encode_contract_state_key(contract_address, key);
+
+        let status = tokio::task::spawn_blocking(move || store.mdbx_get("contract_slot_status", &state_key))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+        status.map(|data| decode_slot_status(&data)).transpose()
+    }
+
+    /// Status of every tracked contract storage slot as (contract, key, status), in key order
+    pub async fn contract_slot_statuses(&self) -> Result<Vec<(Blake2bHash, Blake2bHash, SlotStatus)>> {
+        let store = self.clone();
+        let entries = tokio::task::spawn_blocking(move || store.mdbx_scan("contract_slot_status"))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        entries.into_iter().map(|(key, data)| {
+            let (contract, slot) = Self::decode_contract_state_key(&key)?;
+            Ok((contract, slot, decode_slot_status(&data)?))
+        }).collect()
+    }
+
+    /// Restore slot statuses, as exported by `contract_slot_statuses`
+    pub async fn put_contract_slot_statuses(&self, statuses: &[(Blake2bHash, Blake2bHash, SlotStatus)]) -> Result<()> {
+        let store = self.clone();
+        let writes = statuses.iter()
+            .map(|(contract, key, status)| Ok(("contract_slot_status", Self::encode_contract_state_key(contract, key), encode_slot_status(status)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        tokio::task::spawn_blocking(move || store.mdbx_write_batch(&writes, &[]))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Archive the slots expiring at `expired_at`, in the order their Merkle root is taken over,
+    /// and mark them expired. Their values stay until compaction, see `compact_contract_state`
+    pub async fn expire_contract_slots(&self, expired_at: Height, slots: &[ArchivedSlot]) -> Result<()> {
+        let store = self.clone();
+        let status = encode_slot_status(&SlotStatus::Expired { expired_at })?;
+        let mut writes = Vec::with_capacity(slots.len() * 2);
+        for (index, slot) in slots.iter().enumerate() {
+            let archived = bincode::serialize(slot)
+                .map_err(|e| BlockchainError::Serialization(format!("Failed to serialize archived slot: {}", e)))?;
+            writes.push(("contract_state_archive", encode_archive_key(expired_at, index as u32), archived));
+            writes.push(("contract_slot_status", Self::encode_contract_state_key(&slot.contract, &slot.key), status.clone()));
+        }
+
+        tokio::task::spawn_blocking(move || store.mdbx_write_batch(&writes, &[]))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Slots archived when they expired at `expired_at`, in the order of their Merkle root
+    /// Empty if none expired there or the archive was pruned
+    pub async fn archived_slots(&self, expired_at: Height) -> Result<Vec<ArchivedSlot>> {
+        let store = self.clone();
+        let entries = tokio::task::spawn_blocking(move || store.mdbx_scan_prefix("contract_state_archive", &expired_at.to_be_bytes()))
+            .await
+            .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))??;
+
+        entries.into_iter().map(|(_, data)| {
+            bincode::deserialize(&data)
+                .map_err(|e| BlockchainError::Serialization(format!("Failed to deserialize archived slot: {}", e)))
+        }).collect()
+    }
+
+    /// Proof resurrecting an expired slot from the archive, `None` if the slot is not expired or
+    /// its archive was pruned here
+    pub async fn archival_proof(&self, contract_address: &Blake2bHash, key: &Blake2bHash) -> Result<Option<StateResurrection>> {
+        let Some(SlotStatus::Expired { expired_at }) = self.contract_slot_status(contract_address, key).await? else {
+            return Ok(None);
+        };
+        let slots = self.archived_slots(expired_at).await?;
+        Ok(slots.iter()
+            .position(|slot| slot.contract == *contract_address && slot.key == *key)
+            .and_then(|index| StateResurrection::new(&slots, index, expired_at)))
+    }
+
+    /// Delete the values of slots that expired at or before the finalized height `finalized`;
+    /// expiry tombstones stay so the slots read as expired and can be resurrected. Nodes pruning
+    /// history also drop archived slots older than their pruning window, archive nodes keep them
+    /// to serve resurrection proofs
+    pub async fn compact_contract_state(&self, finalized: Height) -> Result<CompactionStats> {
+        let store = self.clone();
+        let archive_horizon = match self.pruning_mode {
+            PruningMode::Archive => None,
+            PruningMode::Validator { keep_epochs } => finalized.checked_sub(keep_epochs.saturating_mul(Policy::EPOCH_LENGTH)),
+        };
+
+        tokio::task::spawn_blocking(move || -> Result<CompactionStats> {
+            let mut stats = CompactionStats::default();
+            let mut deletes = Vec::new();
+            for (key, data) in store.mdbx_scan("contract_slot_status")? {
+                if let SlotStatus::Expired { expired_at } = decode_slot_status(&data)? {
+                    if expired_at <= finalized && store.mdbx_get("contract_state", &key)?.is_some() {
+                        deletes.push(("contract_state", key));
+                        stats.values_deleted += 1;
+                    }
+                }
+            }
+            if let Some(horizon) = archive_horizon {
+                for (key, _) in store.mdbx_scan("contract_state_archive")? {
+                    if key.len() == 8 && u32::from_be_bytes(key[..4].try_into().unwrap()) < horizon {
+                        deletes.push(("contract_state_archive", key));
+                        stats.archived_dropped += 1;
+                    }
+                }
+            }
+            if !deletes.is_empty() {
+                store.mdbx_write_batch(&[], &deletes)?;
+            }
+            Ok(stats)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Encode contract state key (contract_address + state_key)
+    fn encode_contract_state_key(contract_address: &Blake2bHash, state_key: &Blake2bHash) -> Vec<u8> {
+        let mut key = Vec::with_capacity(64);
+        key.extend_from_slice(contract_address.as_bytes());
+        key.extend_from_slice(state_key.as_bytes());
+        key
+    }
+
+    fn decode_contract_state_key(key: &[u8]) -> Result<(Blake2bHash, Blake2bHash)> {
+        if key.len() != 64 {
+            return Err(BlockchainError::Storage("Invalid contract state key".to_string()));
+        }
+        let mut contract = [0u8; 32];
+        let mut slot = [0u8; 32];
+        contract.copy_from_slice(&key[..32]);
+        slot.copy_from_slice(&key[32..]);
+        Ok((Blake2bHash::from_bytes(contract), Blake2bHash::from_bytes(slot)))
+    }
+}
+
+fn encode_slot_status(status: &SlotStatus) -> Result<Vec<u8>> {
+    bincode::serialize(status)
+        .map_err(|e| BlockchainError::Serialization(format!("Failed to serialize slot status: {}", e)))
+}
+
+fn decode_slot_status(data: &[u8]) -> Result<SlotStatus> {
+    bincode::deserialize(data)
+        .map_err(|e| BlockchainError::Serialization(format!("Failed to deserialize slot status: {}", e)))
+}
+
+/// Archive key, big-endian so slots of one expiry are adjacent and in order
+fn encode_archive_key(expired_at: Height, index: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(8);
+    key.extend_from_slice(&expired_at.to_be_bytes());
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+// Peer store methods
+impl MdbxChainStore {
+    /// Store a serialized peer record
+    pub async fn put_peer_record(&self, peer_key: &[u8], record: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let peer_key = peer_key.to_vec();
+        let record = record.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("peers", &peer_key, &record)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Get a serialized peer record
+    pub async fn get_peer_record(&self, peer_key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let peer_key = peer_key.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_get("peers", &peer_key)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+}
+
+// Audit log methods
+impl MdbxChainStore {
+    /// Append a serialized audit entry under its sequence number
+    pub async fn put_audit_entry(&self, sequence: u64, entry: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let entry = entry.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("audit_log", &sequence.to_be_bytes(), &entry)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// All serialized audit entries in sequence order
+    pub async fn audit_entries(&self) -> Result<Vec<Vec<u8>>> {
+        let store = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            Ok(store.mdbx_scan("audit_log")?.into_iter().map(|(_, entry)| entry).collect())
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+}
+
+// Settlement store methods
+impl MdbxChainStore {
+    /// Store serialized pipeline work that was in flight at shutdown
+    pub async fn put_settlement_state(&self, key: &[u8], state: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let key = key.to_vec();
+        let state = state.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("settlement_store", &key, &state)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Remove and return stored pipeline work, so it is restored only once
+    pub async fn take_settlement_state(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let key = key.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            let state = store.mdbx_get("settlement_store", &key)?;
+            if state.is_some() {
+                store.mdbx_write_batch(&[], &[("settlement_store", key)])?;
+            }
+            Ok(state)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Store one serialized entry of pipeline state
+    pub async fn put_pipeline_state(&self, table: PipelineTable, key: &[u8], value: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let key = key.to_vec();
+        let value = value.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put(table.name(), &key, &value)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    pub async fn get_pipeline_state(&self, table: PipelineTable, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let key = key.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_get(table.name(), &key)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    pub async fn delete_pipeline_state(&self, table: PipelineTable, key: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let key = key.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_write_batch(&[], &[(table.name(), key)])
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// All entries of a pipeline state table in key order
+    pub async fn pipeline_state(&self, table: PipelineTable) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let store = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_scan(table.name())
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Flush committed data to disk before the process exits
+    pub async fn sync(&self) -> Result<()> {
+        let store = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            store.db.sync(true)
+                .map(|_| ())
+                .map_err(|e| BlockchainError::Storage(format!("MDBX sync failed: {}", e)))
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+}
+
+// Proof cache methods
+impl MdbxChainStore {
+    pub async fn put_cached_proof(&self, key: &Blake2bHash, entry: &[u8]) -> Result<()> {
+        let store = self.clone();
+        let key = *key;
+        let entry = entry.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_put("proof_cache", key.as_bytes(), &entry)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    pub async fn cached_proof(&self, key: &Blake2bHash) -> Result<Option<Vec<u8>>> {
+        let store = self.clone();
+        let key = *key;
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_get("proof_cache", key.as_bytes())
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// All serialized proof cache entries in key order
+    pub async fn cached_proofs(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let store = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            store.mdbx_scan("proof_cache")
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+
+    /// Remove proof cache entries in one transaction
+    pub async fn delete_cached_proofs(&self, keys: Vec<Vec<u8>>) -> Result<()> {
+        let store = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let deletes: Vec<(&str, Vec<u8>)> = keys.into_iter().map(|key| ("proof_cache", key)).collect();
+            store.mdbx_write_batch(&[], &deletes)
+        })
+        .await
+        .map_err(|e| BlockchainError::Storage(format!("Task join error: {}", e)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{MicroBlock, MicroHeader, MicroBody};
+    use crate::blockchain::block::{CDRTransaction, SettlementTransaction};
+    use crate::primitives::NetworkId;
+
+    fn transaction(data: TransactionData, nonce: u64) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: nonce,
+            fee: 1,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: Height, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_headers_and_settlements() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap()
+            .with_pruning_mode(PruningMode::Validator { keep_epochs: 1 });
+
+        let settlement = transaction(TransactionData::Settlement(SettlementTransaction {
+            creditor_network: "T-Mobile-DE".to_string(),
+            debtor_network: "Vodafone-UK".to_string(),
+            amount: 10_000,
+            currency: "EUR".to_string(),
+            period: "2024-01".to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![],
+        }), 1);
+        let cdr = transaction(TransactionData::Basic, 2);
+
+        let old_block = micro_block(1, vec![cdr.clone(), settlement.clone()]);
+        let recent_block = micro_block(5, vec![transaction(TransactionData::Basic, 3)]);
+        store.put_block(&old_block).await.unwrap();
+        store.put_block(&recent_block).await.unwrap();
+
+        let (_, location) = store.get_transaction(&settlement.hash()).await.unwrap().unwrap();
+        assert_eq!(location.index, 1);
+
+        let stats = store.prune_before(5).await.unwrap();
+        assert_eq!(stats, PruneStats { blocks_pruned: 1, transactions_removed: 1 });
+        assert_eq!(store.pruned_height().await.unwrap(), 5);
+
+        // Header survives, only the settlement remains in the body and its index moved up
+        let pruned = store.get_block_at(1).await.unwrap().unwrap();
+        assert_eq!(pruned.hash(), old_block.hash());
+        assert_eq!(pruned.transactions().len(), 1);
+        let (_, location) = store.get_transaction(&settlement.hash()).await.unwrap().unwrap();
+        assert_eq!(location, TransactionLocation { block_hash: old_block.hash(), index: 0 });
+
+        // Blocks at or above the pruning height are untouched
+        assert_eq!(store.get_block_at(5).await.unwrap().unwrap().transactions().len(), 1);
+
+        // Archive nodes refuse to prune
+        let archive_dir = tempfile::tempdir().unwrap();
+        let archive = MdbxChainStore::new(archive_dir.path()).unwrap();
+        assert!(archive.prune_before(5).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transactions_indexed_on_put_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+        let first = micro_block(1, vec![transaction(TransactionData::Basic, 1), transaction(TransactionData::Basic, 2)]);
+        let second = micro_block(2, vec![transaction(TransactionData::Basic, 3)]);
+        store.put_block(&first).await.unwrap();
+        store.put_block(&second).await.unwrap();
+        drop(store);
+
+        // Every transaction is found by hash at its block and position, after a restart too
+        let reopened = MdbxChainStore::new(dir.path()).unwrap();
+        for block in [&first, &second] {
+            for (index, expected) in block.transactions().iter().enumerate() {
+                let (found, location) = reopened.get_transaction(&expected.hash()).await.unwrap().unwrap();
+                assert_eq!(found.hash(), expected.hash());
+                assert_eq!(location, TransactionLocation { block_hash: block.hash(), index: index as u32 });
+            }
+        }
+        assert!(reopened.get_transaction(&Blake2bHash::from_data(b"unknown")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_receipts_stored_per_transaction_and_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+        let contract = Blake2bHash::from_data(b"contract");
+        let receipt = |nonce: u64, block_number: u32, transaction_index: u32, success: bool| ContractReceipt {
+            transaction_hash: transaction(TransactionData::Basic, nonce).hash(),
+            contract_address: contract,
+            success,
+            gas_used: 21_000,
+            return_value: None,
+            logs: vec![],
+            events: vec![],
+            error: (!success).then(|| "reverted".to_string()),
+            block_number,
+            transaction_index,
+        };
+
+        // Stored out of chain order, failed executions included
+        let later = receipt(1, 2, 0, true);
+        let failed = receipt(2, 1, 1, false);
+        let earlier = receipt(3, 1, 0, true);
+        store.put_receipts(&[later.clone()]).await.unwrap();
+        store.put_receipts(&[failed.clone(), earlier.clone()]).await.unwrap();
+        drop(store);
+
+        let reopened = MdbxChainStore::new(dir.path()).unwrap();
+        let stored = reopened.get_receipt(&failed.transaction_hash).await.unwrap().unwrap();
+        assert!(!stored.success);
+        assert_eq!(stored.error.as_deref(), Some("reverted"));
+        assert!(reopened.get_receipt(&Blake2bHash::from_data(b"unknown")).await.unwrap().is_none());
+
+        let by_contract = reopened.get_receipts_by_contract(&contract).await.unwrap();
+        assert_eq!(
+            by_contract.iter().map(|receipt| receipt.transaction_hash).collect::<Vec<_>>(),
+            vec![earlier.transaction_hash, failed.transaction_hash, later.transaction_hash]
+        );
+        assert!(reopened.get_receipts_by_contract(&Blake2bHash::from_data(b"other")).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_commit_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+        let state_key = Blake2bHash::from_data(b"state-key");
+
+        // A batch failing part way leaves none of its writes behind
+        let block = micro_block(1, vec![transaction(TransactionData::Basic, 1)]);
+        let mut writes = MdbxChainStore::block_writes(&block).unwrap();
+        writes.push(("metadata", b"head".to_vec(), bincode::serialize(&block.hash()).unwrap()));
+        assert!(store.mdbx_write_batch(&writes, &[("no_such_table", b"key".to_vec())]).is_err());
+        assert!(store.get_block(&block.hash()).await.unwrap().is_none());
+        assert!(store.get_head_hash().await.is_err());
+
+        store.commit(WriteBatch {
+            block: Some(block.clone()),
+            state_changes: vec![(state_key, Some(b"value".to_vec()))],
+            head: Some(block.hash()),
+            ..WriteBatch::default()
+        }).await.unwrap();
+        assert_eq!(store.get_head_hash().await.unwrap(), block.hash());
+        assert_eq!(store.get_block_at(1).await.unwrap().unwrap().hash(), block.hash());
+        assert_eq!(store.load_state_trie().await.unwrap().get(&state_key), Some(&b"value".to_vec()));
+        // Heads the batch does not set keep their value
+        assert!(store.get_macro_head_hash().await.is_err());
+
+        let next = micro_block(2, vec![]);
+        store.commit(WriteBatch {
+            block: Some(next.clone()),
+            state_changes: vec![(state_key, None)],
+            head: Some(next.hash()),
+            ..WriteBatch::default()
+        }).await.unwrap();
+        assert_eq!(store.get_head_hash().await.unwrap(), next.hash());
+        assert!(store.load_state_trie().await.unwrap().get(&state_key).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_ignores_later_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+        let first = micro_block(1, vec![transaction(TransactionData::Basic, 1)]);
+        store.commit(WriteBatch { block: Some(first.clone()), head: Some(first.hash()), ..WriteBatch::default() }).await.unwrap();
+
+        let snapshot = store.snapshot().await.unwrap();
+        let next = micro_block(2, vec![]);
+        store.commit(WriteBatch { block: Some(next.clone()), head: Some(next.hash()), ..WriteBatch::default() }).await.unwrap();
+
+        // The store moved on, the snapshot still sees the chain as it was taken
+        assert_eq!(store.get_head_hash().await.unwrap(), next.hash());
+        assert_eq!(snapshot.get_head_hash().await.unwrap(), first.hash());
+        assert_eq!(snapshot.get_block_at(1).await.unwrap().unwrap().hash(), first.hash());
+        assert!(snapshot.get_block_at(2).await.unwrap().is_none());
+        let tx_hash = first.transactions()[0].hash();
+        assert_eq!(snapshot.get_transaction(&tx_hash).await.unwrap().unwrap().1.block_hash, first.hash());
+
+        assert!(snapshot.set_head(&next.hash()).await.is_err());
+        drop(snapshot);
+        assert_eq!(store.snapshot().await.unwrap().get_head_hash().await.unwrap(), next.hash());
+    }
+
+    #[tokio::test]
+    async fn test_settlement_state_restored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+
+        store.put_settlement_state(b"in_flight", b"batches").await.unwrap();
+        store.sync().await.unwrap();
+        drop(store);
+
+        // A restart reads the flushed state, a crash after it must not restore it again
+        let reopened = MdbxChainStore::new(dir.path()).unwrap();
+        assert_eq!(reopened.take_settlement_state(b"in_flight").await.unwrap(), Some(b"batches".to_vec()));
+        assert_eq!(reopened.take_settlement_state(b"in_flight").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_purge_cdr_payloads_keeps_record_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+
+        let cdr = |record_type: CDRType, nonce: u64| transaction(TransactionData::CDRRecord(CDRTransaction {
+            record_type,
+            home_network: "T-Mobile:DE".to_string(),
+            visited_network: "Vodafone:UK".to_string(),
+            encrypted_data: vec![7; 100],
+            zk_proof: vec![9; 28],
+        }), nonce);
+        let voice = [cdr(CDRType::VoiceCall, 1), cdr(CDRType::VoiceCall, 2), cdr(CDRType::VoiceCall, 3)];
+        let sms = cdr(CDRType::SMS, 4);
+        let blocks = [
+            micro_block(1, vec![voice[0].clone()]),
+            micro_block(2, vec![voice[1].clone(), sms.clone()]),
+            micro_block(3, vec![voice[2].clone()]),
+        ];
+        for block in &blocks {
+            store.put_block(block).await.unwrap();
+        }
+
+        // Voice records older than 3, SMS older than 2
+        let cutoffs = vec![(CDRType::VoiceCall, 3), (CDRType::SMS, 2)];
+        let purged = store.purge_cdr_payloads(3, cutoffs.clone(), 10).await.unwrap();
+        assert_eq!(purged.iter().map(|payload| payload.transaction_hash).collect::<Vec<_>>(), vec![voice[0].hash(), voice[1].hash()]);
+        assert_eq!(purged[1], PurgedPayload {
+            block_number: 2,
+            block_hash: blocks[1].hash(),
+            transaction_hash: voice[1].hash(),
+            record_type: CDRType::VoiceCall,
+            purged_bytes: 128,
+        });
+
+        // The stub is found under the record's hash where the record was
+        let (stub, location) = store.get_transaction(&voice[1].hash()).await.unwrap().unwrap();
+        assert_eq!(location, TransactionLocation { block_hash: blocks[1].hash(), index: 0 });
+        assert!(matches!(stub.data, TransactionData::PurgedCDRRecord(_)));
+        assert!(stub.verify_signature().is_err());
+        let (kept, _) = store.get_transaction(&sms.hash()).await.unwrap().unwrap();
+        assert!(matches!(kept.data, TransactionData::CDRRecord(_)));
+        assert!(store.fsck(false).await.unwrap().issues.iter().all(|issue| issue.table != "tx_index"));
+
+        // Purged blocks are not read again, later cutoffs reach the newer records
+        assert!(store.purge_cdr_payloads(3, cutoffs, 10).await.unwrap().is_empty());
+        let purged = store.purge_cdr_payloads(3, vec![(CDRType::VoiceCall, 4), (CDRType::SMS, 3)], 11).await.unwrap();
+        assert_eq!(purged.iter().map(|payload| payload.transaction_hash).collect::<Vec<_>>(), vec![sms.hash(), voice[2].hash()]);
+    }
+}
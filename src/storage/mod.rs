@@ -1,8 +1,26 @@
 // Storage layer with real MDBX implementation
 pub mod chain_store_fixed;
 pub mod mdbx_store;
+pub mod mdbx_snapshot;
+pub mod read_cache;
 pub mod history_store;
+pub mod state_trie;
+pub mod snapshot;
+pub mod audit_log;
+pub mod chain_query;
+pub mod settlement_report;
+pub mod settlement_export;
+pub mod reputation;
+pub mod fsck;
 
 pub use chain_store_fixed::*;
 pub use mdbx_store::*;
-pub use history_store::*;
\ No newline at end of file
+pub use mdbx_snapshot::MdbxSnapshot;
+pub use read_cache::ChainReadCache;
+pub use history_store::*;
+pub use state_trie::{StateTrie, StateProof, verify_state_proof};
+pub use snapshot::ChainSnapshot;
+pub use audit_log::{AuditLog, AuditAction, AuditEntry};
+pub use chain_query::{ChainActivity, ChainQuery};
+pub use settlement_report::SettlementReport;
+pub use reputation::{OperatorReputation, ReputationReport, ReputationTracker};
\ No newline at end of file
@@ -0,0 +1,174 @@
+// Read cache of the MDBX chain store: consensus and the API fetch the same recent blocks and
+// validator sets over and over, so blocks are kept by hash with the height index in front of them,
+// and validator sets by epoch. Storing a block at a height drops the cached heights and validator
+// sets from that height up, so after a reorg no block of the abandoned branch is served by height
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use lru::LruCache;
+
+use crate::blockchain::Block;
+use crate::blockchain::block::ValidatorInfo;
+use crate::metrics::metrics;
+use crate::primitives::{Blake2bHash, Height, Policy};
+
+/// Blocks kept, four epochs of micro and macro blocks
+pub const BLOCK_CACHE_CAPACITY: usize = 4 * Policy::ELECTION_BLOCK_INTERVAL as usize;
+
+/// Validator sets kept, the current one and those proofs of recent transactions start from
+pub const VALIDATOR_SET_CACHE_CAPACITY: usize = 8;
+
+/// Recently read and stored blocks and validator sets, least recently used dropped first
+pub struct ChainReadCache {
+    blocks: Mutex<LruCache<Blake2bHash, Block>>,
+    heights: Mutex<LruCache<Height, Blake2bHash>>,
+    /// Validators elected by the election block at `epoch * ELECTION_BLOCK_INTERVAL`
+    validator_sets: Mutex<LruCache<u32, Vec<ValidatorInfo>>>,
+}
+
+impl Default for ChainReadCache {
+    fn default() -> Self {
+        Self::new(BLOCK_CACHE_CAPACITY, VALIDATOR_SET_CACHE_CAPACITY)
+    }
+}
+
+impl ChainReadCache {
+    pub fn new(block_capacity: usize, validator_set_capacity: usize) -> Self {
+        let capacity = |capacity: usize| NonZeroUsize::new(capacity.max(1)).expect("capacity is at least one");
+        Self {
+            blocks: Mutex::new(LruCache::new(capacity(block_capacity))),
+            heights: Mutex::new(LruCache::new(capacity(block_capacity))),
+            validator_sets: Mutex::new(LruCache::new(capacity(validator_set_capacity))),
+        }
+    }
+
+    pub fn block(&self, hash: &Blake2bHash) -> Option<Block> {
+        let block = self.blocks.lock().unwrap().get(hash).cloned();
+        match block {
+            Some(_) => metrics().block_cache_hits.inc(),
+            None => metrics().block_cache_misses.inc(),
+        }
+        block
+    }
+
+    /// Hash of the block stored at `block_number`, as the height index last had it
+    pub fn block_hash_at(&self, block_number: Height) -> Option<Blake2bHash> {
+        self.heights.lock().unwrap().get(&block_number).copied()
+    }
+
+    /// Keep a block read by hash, its height is only taken from the index
+    pub fn insert_block(&self, block: &Block) {
+        self.blocks.lock().unwrap().put(block.hash(), block.clone());
+    }
+
+    /// Keep a height index entry read from the store
+    pub fn insert_height(&self, block_number: Height, hash: Blake2bHash) {
+        self.heights.lock().unwrap().put(block_number, hash);
+    }
+
+    /// Record a block written to the store, replacing whatever was indexed at its height and above
+    pub fn stored(&self, block: &Block) {
+        let block_number = block.block_number();
+        let hash = block.hash();
+
+        let mut heights = self.heights.lock().unwrap();
+        let stale: Vec<Height> = heights.iter()
+            .map(|(height, _)| *height)
+            .filter(|height| *height >= block_number)
+            .collect();
+        for height in stale {
+            heights.pop(&height);
+        }
+        heights.put(block_number, hash);
+        drop(heights);
+
+        // Sets elected at or above the height are only valid on the branch they were read from
+        let first_stale = block_number.div_ceil(Policy::ELECTION_BLOCK_INTERVAL);
+        let mut validator_sets = self.validator_sets.lock().unwrap();
+        let stale: Vec<u32> = validator_sets.iter()
+            .map(|(epoch, _)| *epoch)
+            .filter(|epoch| *epoch >= first_stale)
+            .collect();
+        for epoch in stale {
+            validator_sets.pop(&epoch);
+        }
+        drop(validator_sets);
+
+        self.blocks.lock().unwrap().put(hash, block.clone());
+    }
+
+    /// Drop a block whose body was rewritten in place, by pruning or payload purging
+    pub fn forget_block(&self, hash: &Blake2bHash) {
+        self.blocks.lock().unwrap().pop(hash);
+    }
+
+    pub fn validator_set(&self, epoch: u32) -> Option<Vec<ValidatorInfo>> {
+        let validators = self.validator_sets.lock().unwrap().get(&epoch).cloned();
+        match validators {
+            Some(_) => metrics().validator_set_cache_hits.inc(),
+            None => metrics().validator_set_cache_misses.inc(),
+        }
+        validators
+    }
+
+    pub fn insert_validator_set(&self, epoch: u32, validators: Vec<ValidatorInfo>) {
+        self.validator_sets.lock().unwrap().put(epoch, validators);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{MicroBlock, MicroBody, MicroHeader};
+    use crate::primitives::NetworkId;
+
+    fn micro_block(block_number: Height, seed: &[u8]) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::from_data(seed),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions: vec![] },
+        })
+    }
+
+    #[test]
+    fn test_reorg_invalidates_heights_and_validator_sets() {
+        let cache = ChainReadCache::new(4, 2);
+        let interval = Policy::ELECTION_BLOCK_INTERVAL;
+        let abandoned: Vec<Block> = (interval - 1..=interval + 1).map(|height| micro_block(height, b"abandoned")).collect();
+        for block in &abandoned {
+            cache.stored(block);
+        }
+        cache.insert_validator_set(0, vec![]);
+        cache.insert_validator_set(1, vec![]);
+        assert_eq!(cache.block_hash_at(interval + 1), Some(abandoned[2].hash()));
+
+        // The other branch forks at the election height, the set it elected and every height above go
+        let replacement = micro_block(interval, b"replacement");
+        cache.stored(&replacement);
+        assert_eq!(cache.block_hash_at(interval), Some(replacement.hash()));
+        assert_eq!(cache.block_hash_at(interval + 1), None);
+        assert_eq!(cache.block_hash_at(interval - 1), Some(abandoned[0].hash()));
+        assert!(cache.validator_set(1).is_none());
+        assert!(cache.validator_set(0).is_some());
+
+        // Blocks stay reachable by hash until evicted or rewritten
+        assert!(cache.block(&abandoned[2].hash()).is_some());
+        cache.forget_block(&abandoned[2].hash());
+        assert!(cache.block(&abandoned[2].hash()).is_none());
+
+        // Least recently used blocks are evicted first
+        for height in 0..4 {
+            cache.stored(&micro_block(height, b"filler"));
+        }
+        assert!(cache.block(&replacement.hash()).is_none());
+    }
+}
@@ -0,0 +1,415 @@
+// Operator reputation from the chain history: whether an operator paid its escrowed settlements
+// before their deadline, how often it ended up in dispute, whether it answered the challenges to its
+// CDR commitments and whether it settled the positions its settlement periods closed with. Scores
+// out of 100 tighten the settlement policy applied to the operator's proposals. The history is
+// tallied block by block as macro blocks make it final
+use std::collections::{BTreeMap, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::Block;
+use crate::blockchain::block::{Transaction, TransactionData};
+use crate::primitives::{Blake2bHash, Height, Policy, Result};
+use crate::smart_contracts::settlement_contract::ESCROW_PAYMENT_TERM_SECS;
+use crate::smart_contracts::EscrowTransaction;
+use super::settlement_report::display_name;
+use super::ChainStore;
+
+/// Score of an operator with a clean history
+pub const MAX_REPUTATION: u32 = 100;
+
+/// Score of an operator without any history yet
+pub const NEUTRAL_REPUTATION: u32 = MAX_REPUTATION / 2;
+
+/// Share of the score each factor carries
+const PUNCTUALITY_WEIGHT: u32 = 35;
+const DISPUTE_WEIGHT: u32 = 25;
+const PROOF_WEIGHT: u32 = 20;
+const NETTING_WEIGHT: u32 = 20;
+
+/// Score lost per dispute and per unanswered challenge, up to the factor's weight
+const PENALTY_PER_DISPUTE: u32 = 5;
+const PENALTY_PER_PROOF_FAILURE: u32 = 10;
+
+/// Settlement history of one operator and the score it earns
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorReputation {
+    /// Operator as `name:country`
+    pub operator: String,
+    pub score: u32,
+    /// Escrowed settlements the operator owed whose payment was confirmed before the deadline
+    pub payments_on_time: u32,
+    /// Escrowed settlements the operator owed left unconfirmed past the deadline
+    pub payments_overdue: u32,
+    /// Escrows disputed with the operator as debtor and its batches still quarantined for fraud
+    pub disputes: u32,
+    /// Challenges to the operator's CDR commitments left unanswered past the response window
+    pub proof_failures: u32,
+    /// Counterparties of closed periods the operator had a net position with
+    pub netting_pairs: u32,
+    /// Of those, the ones settled on chain
+    pub settled_pairs: u32,
+}
+
+impl OperatorReputation {
+    fn new(operator: &str) -> Self {
+        Self {
+            operator: operator.to_string(),
+            score: NEUTRAL_REPUTATION,
+            ..Default::default()
+        }
+    }
+
+    /// Score out of 100 of the history, factors without any history counting in full
+    pub fn compute_score(&self) -> u32 {
+        let share = |weight: u32, good: u32, total: u32| if total == 0 { weight } else { weight * good / total };
+        share(PUNCTUALITY_WEIGHT, self.payments_on_time, self.payments_on_time + self.payments_overdue)
+            + DISPUTE_WEIGHT.saturating_sub(PENALTY_PER_DISPUTE.saturating_mul(self.disputes))
+            + PROOF_WEIGHT.saturating_sub(PENALTY_PER_PROOF_FAILURE.saturating_mul(self.proof_failures))
+            + share(NETTING_WEIGHT, self.settled_pairs, self.netting_pairs)
+    }
+}
+
+/// Reputation of every operator with a settlement history on chain
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReputationReport {
+    /// Chain height the report was taken at
+    pub block_number: u32,
+    /// Operators by name, each with its score
+    pub operators: Vec<OperatorReputation>,
+}
+
+/// Escrow locked on chain and not confirmed yet
+#[derive(Debug, Clone)]
+struct OpenEscrow {
+    debtor: String,
+    deadline: u64,
+}
+
+impl ReputationReport {
+    /// Reputation of `operator`, named `name:country`
+    pub fn operator(&self, operator: &str) -> Option<&OperatorReputation> {
+        self.operators.iter().find(|reputation| reputation.operator == operator)
+    }
+
+    /// Score of `operator`, operators without any history starting at a neutral score
+    pub fn score(&self, operator: &str) -> u32 {
+        self.operator(operator).map_or(NEUTRAL_REPUTATION, |reputation| reputation.score)
+    }
+}
+
+/// Running tally of the chain history operators are scored from, advanced over the blocks made
+/// final since it was last advanced rather than walking the chain again
+#[derive(Debug, Default)]
+pub struct ReputationTracker {
+    /// Last block tallied and its timestamp
+    block_number: u32,
+    timestamp: u64,
+    /// Payments confirmed on time, counted as they happen
+    operators: BTreeMap<String, OperatorReputation>,
+    escrows: HashMap<Blake2bHash, OpenEscrow>,
+    // Quarantined batches and committed records are the visited network's, it billed them
+    quarantined: HashMap<Blake2bHash, String>,
+    committers: HashMap<Blake2bHash, String>,
+    challenges: HashMap<(Blake2bHash, u32), Height>,
+    // Net position of each pair of a closed period, lower named operator first
+    positions: BTreeMap<(String, String, String), i64>,
+    settled: HashSet<(String, String, String)>,
+}
+
+impl ReputationTracker {
+    /// Tally the blocks of `store` up to its macro head. `accounts` holds the account each registered
+    /// operator, named `name:country`, sends from: fraud flags count only if sent by a party to the batch
+    pub async fn advance(&mut self, store: &dyn ChainStore, accounts: &HashMap<String, Blake2bHash>) -> Result<()> {
+        let Some(head) = store.get_block(&store.get_macro_head_hash().await?).await? else {
+            return Ok(());
+        };
+        // Advanced per block, so a failed read resumes after the last block tallied
+        for block_number in self.block_number + 1..=head.block_number() {
+            if let Some(block) = store.get_block_at(block_number).await? {
+                self.apply_block(store, &block, accounts).await?;
+                self.timestamp = block.timestamp();
+            }
+            self.block_number = block_number;
+        }
+        Ok(())
+    }
+
+    async fn apply_block(&mut self, store: &dyn ChainStore, block: &Block, accounts: &HashMap<String, Blake2bHash>) -> Result<()> {
+        for transaction in block.transactions() {
+            match &transaction.data {
+                TransactionData::Escrow(EscrowTransaction::Lock(lock)) if executed(store, transaction).await? => {
+                    self.escrows.insert(lock.settlement_tx, OpenEscrow {
+                        debtor: display_name(&lock.debtor),
+                        deadline: block.timestamp() + ESCROW_PAYMENT_TERM_SECS,
+                    });
+                }
+                // Confirmations only execute while the escrow is locked, before its deadline
+                TransactionData::Escrow(EscrowTransaction::Confirm(confirmation)) if executed(store, transaction).await? => {
+                    if let Some(escrow) = self.escrows.remove(&confirmation.settlement_tx) {
+                        entry(&mut self.operators, &escrow.debtor).payments_on_time += 1;
+                    }
+                }
+                TransactionData::FraudFlag(flag) => {
+                    let (home, visited) = (display_name(&flag.home_network), display_name(&flag.visited_network));
+                    let party = [&home, &visited].into_iter()
+                        .any(|network| accounts.get(network) == Some(&transaction.sender));
+                    if !party {
+                        continue;
+                    }
+                    if flag.quarantine {
+                        self.quarantined.insert(flag.batch_id, visited);
+                    } else {
+                        self.quarantined.remove(&flag.batch_id);
+                    }
+                }
+                TransactionData::CDRCommitment(commitment) => {
+                    self.committers.insert(commitment.commitment_id(), display_name(&commitment.visited_network));
+                }
+                TransactionData::CommitmentChallenge(challenge) => {
+                    self.challenges.insert((challenge.commitment_id, challenge.record_index), block.block_number() + Policy::COMMITMENT_RESPONSE_WINDOW);
+                }
+                TransactionData::CommitmentResponse(response) => {
+                    self.challenges.remove(&(response.commitment_id, response.record_index()));
+                }
+                TransactionData::PeriodClose(close) => {
+                    for balance in &close.balances {
+                        let (home, visited) = (display_name(&balance.home_network), display_name(&balance.visited_network));
+                        if home == visited {
+                            continue;
+                        }
+                        // The home network owes the visited one for its roamers
+                        let (key, owed) = pair(&close.period, &visited, &home);
+                        *self.positions.entry(key).or_default() += if owed { balance.amount_cents as i64 } else { -(balance.amount_cents as i64) };
+                    }
+                }
+                TransactionData::Settlement(settlement) => {
+                    let (creditor, debtor) = (display_name(&settlement.creditor_network), display_name(&settlement.debtor_network));
+                    self.settled.insert(pair(&settlement.period, &creditor, &debtor).0);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Scores of every operator with a settlement history in the blocks tallied so far
+    pub fn report(&self) -> ReputationReport {
+        let mut operators = self.operators.clone();
+        for escrow in self.escrows.values().filter(|escrow| self.timestamp >= escrow.deadline) {
+            let reputation = entry(&mut operators, &escrow.debtor);
+            reputation.payments_overdue += 1;
+            reputation.disputes += 1;
+        }
+        for visited in self.quarantined.values() {
+            entry(&mut operators, visited).disputes += 1;
+        }
+        let unanswered = self.challenges.iter().filter(|(_, respond_by)| self.block_number > **respond_by);
+        for ((commitment_id, _), _) in unanswered {
+            if let Some(committer) = self.committers.get(commitment_id) {
+                entry(&mut operators, committer).proof_failures += 1;
+            }
+        }
+        for (pair, net) in &self.positions {
+            // Positions netting to nothing leave nothing to settle
+            if *net == 0 {
+                continue;
+            }
+            let paid = self.settled.contains(pair);
+            for name in [&pair.1, &pair.2] {
+                let reputation = entry(&mut operators, name);
+                reputation.netting_pairs += 1;
+                reputation.settled_pairs += paid as u32;
+            }
+        }
+
+        ReputationReport {
+            block_number: self.block_number,
+            operators: operators.into_values().map(|mut reputation| {
+                reputation.score = reputation.compute_score();
+                reputation
+            }).collect(),
+        }
+    }
+}
+
+fn entry<'a>(operators: &'a mut BTreeMap<String, OperatorReputation>, operator: &str) -> &'a mut OperatorReputation {
+    operators.entry(operator.to_string()).or_insert_with(|| OperatorReputation::new(operator))
+}
+
+/// Key of the pair of `creditor` and `debtor` in `period`, and whether `creditor` comes first
+fn pair(period: &str, creditor: &str, debtor: &str) -> ((String, String, String), bool) {
+    let first = creditor <= debtor;
+    let (a, b) = if first { (creditor, debtor) } else { (debtor, creditor) };
+    ((period.to_string(), a.to_string(), b.to_string()), first)
+}
+
+/// Whether a contract transaction executed successfully, as its receipt records
+async fn executed(store: &dyn ChainStore, transaction: &Transaction) -> Result<bool> {
+    Ok(store.get_receipt(&transaction.hash()).await?.is_some_and(|receipt| receipt.success))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Block, MicroBlock, MicroBody, MicroHeader};
+    use crate::blockchain::block::{FraudFlagTransaction, PeriodBalance, PeriodCloseTransaction, SettlementTransaction};
+    use crate::network::settlement_messaging::ConfirmationType;
+    use crate::primitives::NetworkId;
+    use crate::smart_contracts::{ContractReceipt, EscrowLock, PaymentConfirmation};
+    use crate::storage::MdbxChainStore;
+
+    fn transaction(data: TransactionData) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: 0,
+            fee: 1,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: u32, timestamp: u64, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    fn receipt(transaction: &Transaction, success: bool) -> ContractReceipt {
+        ContractReceipt {
+            transaction_hash: transaction.hash(),
+            contract_address: crate::smart_contracts::settlement_contract::settlement_escrow_address(),
+            success,
+            gas_used: 0,
+            return_value: None,
+            logs: vec![],
+            events: vec![],
+            error: None,
+            block_number: 0,
+            transaction_index: 0,
+        }
+    }
+
+    fn lock(settlement: &[u8], creditor: &str, debtor: &str) -> Transaction {
+        transaction(TransactionData::Escrow(EscrowTransaction::Lock(EscrowLock {
+            settlement_tx: Blake2bHash::from_data(settlement),
+            creditor: creditor.to_string(),
+            debtor: debtor.to_string(),
+            amount: 10_000,
+            currency: "EUR".to_string(),
+            payment_agent: None,
+            signature: vec![],
+            agent_approval: vec![],
+        })))
+    }
+
+    fn confirm(settlement: &[u8], creditor: &str) -> Transaction {
+        transaction(TransactionData::Escrow(EscrowTransaction::Confirm(PaymentConfirmation {
+            settlement_tx: Blake2bHash::from_data(settlement),
+            confirmer: creditor.to_string(),
+            confirmation_type: ConfirmationType::PaymentConfirmed,
+            transaction_ref: None,
+            signature: vec![],
+        })))
+    }
+
+    #[tokio::test]
+    async fn test_scores_follow_settlement_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+
+        let orange = NetworkId::new("Orange", "FR");
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let locks = [lock(b"paid", "T-Mobile:DE", "Orange:FR"), lock(b"unpaid", "T-Mobile:DE", "Vodafone:UK")];
+        let confirmations = [confirm(b"paid", "T-Mobile:DE"), confirm(b"unpaid", "T-Mobile:DE")];
+        let blocks = [
+            micro_block(1, 1_000, vec![
+                locks[0].clone(),
+                locks[1].clone(),
+                transaction(TransactionData::PeriodClose(PeriodCloseTransaction {
+                    period: "2024-03-01/2024-03-16".to_string(),
+                    start: 0,
+                    cutoff: 0,
+                    frozen_batches: vec![],
+                    balances: vec![
+                        PeriodBalance { home_network: format!("{:?}", orange), visited_network: "T-Mobile:DE".to_string(), amount_cents: 10_000 },
+                        PeriodBalance { home_network: format!("{:?}", vodafone), visited_network: "T-Mobile:DE".to_string(), amount_cents: 5_000 },
+                    ],
+                })),
+                transaction(TransactionData::FraudFlag(FraudFlagTransaction {
+                    batch_id: Blake2bHash::from_data(b"batch"),
+                    home_network: "T-Mobile:DE".to_string(),
+                    visited_network: "Vodafone:UK".to_string(),
+                    score: 90,
+                    reasons: vec![],
+                    quarantine: true,
+                })),
+            ]),
+            micro_block(2, 2_000, vec![
+                confirmations[0].clone(),
+                transaction(TransactionData::Settlement(SettlementTransaction {
+                    creditor_network: "T-Mobile:DE".to_string(),
+                    debtor_network: format!("{:?}", orange),
+                    amount: 10_000,
+                    currency: "EUR".to_string(),
+                    period: "2024-03-01/2024-03-16".to_string(),
+                    breakdown: Default::default(),
+                    batch_ids: vec![],
+                })),
+            ]),
+            // Too late, the escrow was disputed at its deadline
+            micro_block(3, 1_000 + ESCROW_PAYMENT_TERM_SECS, vec![confirmations[1].clone()]),
+        ];
+        for block in &blocks {
+            store.put_block(block).await.unwrap();
+        }
+        store.put_receipts(&[receipt(&locks[0], true), receipt(&locks[1], true), receipt(&confirmations[0], true), receipt(&confirmations[1], false)]).await.unwrap();
+        store.set_head(&blocks[2].hash()).await.unwrap();
+        store.set_macro_head(&blocks[1].hash()).await.unwrap();
+
+        // The tally stops at the macro head and picks up from there
+        let accounts = HashMap::from([("T-Mobile:DE".to_string(), Blake2bHash::from_data(b"sender"))]);
+        let mut tracker = ReputationTracker::default();
+        tracker.advance(&store, &accounts).await.unwrap();
+        assert_eq!(tracker.report().block_number, 2);
+        store.set_macro_head(&blocks[2].hash()).await.unwrap();
+        tracker.advance(&store, &accounts).await.unwrap();
+        let report = tracker.report();
+        assert_eq!(report.block_number, 3);
+
+        let paid = report.operator("Orange:FR").unwrap();
+        assert_eq!((paid.payments_on_time, paid.payments_overdue, paid.disputes), (1, 0, 0));
+        assert_eq!((paid.netting_pairs, paid.settled_pairs), (1, 1));
+        assert_eq!(paid.score, MAX_REPUTATION);
+
+        // Overdue, disputed twice over and never settling its period
+        let late = report.operator("Vodafone:UK").unwrap();
+        assert_eq!((late.payments_on_time, late.payments_overdue, late.disputes), (0, 1, 2));
+        assert_eq!((late.netting_pairs, late.settled_pairs), (1, 0));
+        assert_eq!(late.score, DISPUTE_WEIGHT - 2 * PENALTY_PER_DISPUTE + PROOF_WEIGHT);
+
+        // The creditor settled one of its two positions
+        assert_eq!(report.score("T-Mobile:DE"), MAX_REPUTATION - NETTING_WEIGHT / 2);
+        assert_eq!(report.score("Telefonica:ES"), NEUTRAL_REPUTATION);
+
+        // A fraud flag sent by neither party to the batch is not counted
+        let mut tracker = ReputationTracker::default();
+        tracker.advance(&store, &HashMap::new()).await.unwrap();
+        assert_eq!(tracker.report().operator("Vodafone:UK").unwrap().disputes, 1);
+    }
+}
@@ -0,0 +1,392 @@
+// Settlement files for legacy billing systems: per settlement period and counterparty, the charges
+// the period closed with and the settlements finalized for it, as a TAP-out style record file or a
+// BCE JSON settlement document. Only blocks up to the macro head are read, so a file never carries
+// a settlement that could still be reverted
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::{SettlementTransaction, TransactionData};
+use crate::blockchain::ServiceBreakdown;
+use crate::primitives::{BlockchainError, Result};
+use super::ChainStore;
+use super::settlement_report::{display_name, in_period, REPORT_CURRENCY};
+
+/// Schema the BCE JSON documents follow
+pub const BCE_SETTLEMENT_SCHEMA: &str = "gsma-bce-settlement/1.0";
+
+/// Layout of the TAP-out style files
+pub const TAP_OUT_VERSION: &str = "SPCDR-TAPOUT-1";
+
+/// File format settlements are exported in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Pipe-separated header, charge, settlement and trailer records, RAP/TAP-out style
+    TapOut,
+    /// One JSON document per file following `BCE_SETTLEMENT_SCHEMA`
+    BceJson,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::TapOut => "tap",
+            ExportFormat::BceJson => "json",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tap" => Ok(ExportFormat::TapOut),
+            "bce-json" => Ok(ExportFormat::BceJson),
+            _ => Err(BlockchainError::InvalidOperation(format!("Unknown export format {}, expected tap or bce-json", s))),
+        }
+    }
+}
+
+/// Finalized settlement between the exporting operator and a counterparty
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedSettlement {
+    pub transaction_hash: String,
+    pub block_number: u32,
+    pub creditor: String,
+    pub debtor: String,
+    pub amount_cents: u64,
+    pub currency: String,
+    pub breakdown: ServiceBreakdown,
+    pub batch_ids: Vec<String>,
+    /// Consortium a bridged settlement was finalized on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridged_from: Option<String>,
+}
+
+/// Export of one settlement period between the exporting operator and one counterparty
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CounterpartyExport {
+    pub period: String,
+    pub operator: String,
+    pub counterparty: String,
+    pub currency: String,
+    /// Macro block the export was taken at
+    pub block_number: u32,
+    /// Charges the counterparty owes for the period, before netting
+    pub charges_receivable_cents: u64,
+    /// Charges owed to the counterparty for the period, before netting
+    pub charges_payable_cents: u64,
+    pub settlements: Vec<ExportedSettlement>,
+}
+
+/// BCE JSON document of an export
+#[derive(Serialize)]
+struct BceSettlementDocument<'a> {
+    schema: &'static str,
+    document_type: &'static str,
+    #[serde(flatten)]
+    export: &'a CounterpartyExport,
+    net_charges_cents: i64,
+    settled_cents: i64,
+}
+
+impl CounterpartyExport {
+    /// Exports of every settlement period matching `period` between `operator`, named
+    /// `name:country`, and each counterparty it has charges or settlements with
+    pub async fn build(store: &dyn ChainStore, period: &str, operator: &str) -> Result<Vec<Self>> {
+        // A fresh store has nothing finalized
+        let Ok(macro_head_hash) = store.get_macro_head_hash().await else {
+            return Ok(vec![]);
+        };
+        let Some(macro_head) = store.get_block(&macro_head_hash).await? else {
+            return Ok(vec![]);
+        };
+        let block_number = macro_head.block_number();
+
+        let mut exports: BTreeMap<(String, String), Self> = BTreeMap::new();
+        for height in 1..=block_number {
+            let Some(block) = store.get_block_at(height).await? else {
+                continue;
+            };
+            for transaction in block.transactions() {
+                let (settlement, bridged_from) = match &transaction.data {
+                    TransactionData::PeriodClose(close) if in_period(&close.period, period) => {
+                        for balance in &close.balances {
+                            let (creditor, debtor) = (display_name(&balance.home_network), display_name(&balance.visited_network));
+                            if let Some((export, receivable)) = entry(&mut exports, &close.period, operator, &creditor, &debtor, block_number) {
+                                if receivable {
+                                    export.charges_receivable_cents += balance.amount_cents;
+                                } else {
+                                    export.charges_payable_cents += balance.amount_cents;
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    TransactionData::Settlement(settlement) => (settlement, None),
+                    TransactionData::BridgedSettlement(bridged) => match bridged.settlement() {
+                        Ok(settlement) => (settlement, Some(bridged.source_network.to_string())),
+                        Err(_) => continue,
+                    },
+                    _ => continue,
+                };
+                if !in_period(&settlement.period, period) {
+                    continue;
+                }
+                let (creditor, debtor) = (display_name(&settlement.creditor_network), display_name(&settlement.debtor_network));
+                if let Some((export, _)) = entry(&mut exports, &settlement.period, operator, &creditor, &debtor, block_number) {
+                    export.settlements.push(exported(settlement, transaction.hash().to_hex(), height, creditor, debtor, bridged_from));
+                }
+            }
+        }
+        Ok(exports.into_values().collect())
+    }
+
+    /// Charges receivable less payable
+    pub fn net_charges_cents(&self) -> i64 {
+        self.charges_receivable_cents as i64 - self.charges_payable_cents as i64
+    }
+
+    /// Net of the finalized settlements, positive when owed to the operator
+    pub fn settled_cents(&self) -> i64 {
+        self.settlements.iter()
+            .map(|settlement| if settlement.creditor == self.operator { settlement.amount_cents as i64 } else { -(settlement.amount_cents as i64) })
+            .sum()
+    }
+
+    /// File name of the export, unique per period and counterparty
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        let safe = |name: &str| name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect::<String>();
+        format!("{}_{}_{}.{}", safe(&self.period), safe(&self.operator), safe(&self.counterparty), format.extension())
+    }
+
+    pub fn render(&self, format: ExportFormat) -> Result<String> {
+        match format {
+            ExportFormat::TapOut => Ok(self.to_tap_out()),
+            ExportFormat::BceJson => self.to_bce_json(),
+        }
+    }
+
+    /// HDR, CHG, one STL per settlement and TRL records, fields separated by `|` and amounts in cents
+    fn to_tap_out(&self) -> String {
+        let mut records = vec![
+            format!("HDR|{}|{}|{}|{}|{}|{}", TAP_OUT_VERSION, self.operator, self.counterparty, self.period, self.currency, self.block_number),
+            format!("CHG|{}|{}|{}", self.charges_receivable_cents, self.charges_payable_cents, self.net_charges_cents()),
+        ];
+        for settlement in &self.settlements {
+            let breakdown = &settlement.breakdown;
+            records.push(format!(
+                "STL|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+                settlement.transaction_hash, settlement.block_number, settlement.creditor, settlement.debtor,
+                settlement.amount_cents, settlement.currency,
+                breakdown.voice_minutes, breakdown.voice_cents, breakdown.data_mb, breakdown.data_cents,
+                breakdown.sms_count, breakdown.sms_cents, breakdown.other_cents,
+                settlement.bridged_from.as_deref().unwrap_or(""),
+            ));
+        }
+        records.push(format!("TRL|{}|{}|{}", self.settlements.len(), self.settled_cents(), records.len() + 1));
+
+        let mut file = records.join("\n");
+        file.push('\n');
+        file
+    }
+
+    fn to_bce_json(&self) -> Result<String> {
+        let document = BceSettlementDocument {
+            schema: BCE_SETTLEMENT_SCHEMA,
+            document_type: "settlement_statement",
+            export: self,
+            net_charges_cents: self.net_charges_cents(),
+            settled_cents: self.settled_cents(),
+        };
+        serde_json::to_string_pretty(&document)
+            .map_err(|e| BlockchainError::Serialization(format!("BCE export failed: {}", e)))
+    }
+}
+
+/// Export of `period` with the counterparty of `operator` in a transfer from `creditor` to `debtor`,
+/// and whether `operator` is the creditor. `None` if `operator` is not a party
+fn entry<'a>(
+    exports: &'a mut BTreeMap<(String, String), CounterpartyExport>,
+    period: &str,
+    operator: &str,
+    creditor: &str,
+    debtor: &str,
+    block_number: u32,
+) -> Option<(&'a mut CounterpartyExport, bool)> {
+    let receivable = creditor == operator;
+    let counterparty = if receivable {
+        debtor
+    } else if debtor == operator {
+        creditor
+    } else {
+        return None;
+    };
+    // Roaming within one network nets to nothing
+    if counterparty == operator {
+        return None;
+    }
+    let export = exports.entry((period.to_string(), counterparty.to_string())).or_insert_with(|| CounterpartyExport {
+        period: period.to_string(),
+        operator: operator.to_string(),
+        counterparty: counterparty.to_string(),
+        currency: REPORT_CURRENCY.to_string(),
+        block_number,
+        ..Default::default()
+    });
+    Some((export, receivable))
+}
+
+fn exported(
+    settlement: &SettlementTransaction,
+    transaction_hash: String,
+    block_number: u32,
+    creditor: String,
+    debtor: String,
+    bridged_from: Option<String>,
+) -> ExportedSettlement {
+    ExportedSettlement {
+        transaction_hash,
+        block_number,
+        creditor,
+        debtor,
+        amount_cents: settlement.amount,
+        currency: settlement.currency.clone(),
+        breakdown: settlement.breakdown,
+        batch_ids: settlement.batch_ids.iter().map(|batch_id| batch_id.to_hex()).collect(),
+        bridged_from,
+    }
+}
+
+/// Write every export to `dir` in `format`, returning the files written
+pub fn write_exports(exports: &[CounterpartyExport], format: ExportFormat, dir: &Path) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| BlockchainError::Storage(format!("Failed to create export directory {}: {}", dir.display(), e)))?;
+    let mut files = Vec::new();
+    for export in exports {
+        let path = dir.join(export.file_name(format));
+        std::fs::write(&path, export.render(format)?)
+            .map_err(|e| BlockchainError::Storage(format!("Failed to write settlement export {}: {}", path.display(), e)))?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Block, MicroBlock, MicroHeader, MicroBody};
+    use crate::blockchain::block::{PeriodBalance, PeriodCloseTransaction, Transaction};
+    use crate::primitives::{Blake2bHash, NetworkId};
+    use crate::storage::MdbxChainStore;
+
+    fn transaction(data: TransactionData) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: 0,
+            fee: 1,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: u32, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    fn settlement(period: &str, creditor: &NetworkId, debtor: &NetworkId, amount: u64) -> Transaction {
+        transaction(TransactionData::Settlement(SettlementTransaction {
+            creditor_network: format!("{:?}", creditor),
+            debtor_network: format!("{:?}", debtor),
+            amount,
+            currency: REPORT_CURRENCY.to_string(),
+            period: period.to_string(),
+            breakdown: ServiceBreakdown { voice_minutes: 120, voice_cents: amount, ..Default::default() },
+            batch_ids: vec![Blake2bHash::from_data(b"batch")],
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_exports_finalized_settlements_per_counterparty() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+
+        let period = "2024-03-01/2024-03-16";
+        let tmobile = NetworkId::new("T-Mobile", "DE");
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let orange = NetworkId::new("Orange", "FR");
+        let blocks = [
+            micro_block(1, vec![
+                transaction(TransactionData::PeriodClose(PeriodCloseTransaction {
+                    period: period.to_string(),
+                    start: 0,
+                    cutoff: 0,
+                    frozen_batches: vec![],
+                    balances: vec![
+                        PeriodBalance { home_network: "T-Mobile:DE".to_string(), visited_network: "Vodafone:UK".to_string(), amount_cents: 10_000 },
+                        PeriodBalance { home_network: "Vodafone:UK".to_string(), visited_network: "T-Mobile:DE".to_string(), amount_cents: 4_000 },
+                        PeriodBalance { home_network: "Vodafone:UK".to_string(), visited_network: "Orange:FR".to_string(), amount_cents: 2_500 },
+                    ],
+                })),
+                settlement(period, &tmobile, &vodafone, 6_000),
+                settlement(period, &vodafone, &orange, 2_500),
+            ]),
+            // Not final yet
+            micro_block(2, vec![settlement(period, &vodafone, &tmobile, 1_000)]),
+        ];
+        for block in &blocks {
+            store.put_block(block).await.unwrap();
+        }
+        store.set_head(&blocks[1].hash()).await.unwrap();
+        store.set_macro_head(&blocks[0].hash()).await.unwrap();
+
+        let exports = CounterpartyExport::build(&store, "2024-03", "T-Mobile:DE").await.unwrap();
+        assert_eq!(exports.len(), 1);
+        let export = &exports[0];
+        assert_eq!((export.counterparty.as_str(), export.period.as_str(), export.block_number), ("Vodafone:UK", period, 1));
+        assert_eq!((export.charges_receivable_cents, export.charges_payable_cents, export.net_charges_cents()), (10_000, 4_000, 6_000));
+        assert_eq!(export.settlements.len(), 1);
+        assert_eq!(export.settled_cents(), 6_000);
+        assert_eq!(export.file_name(ExportFormat::TapOut), "2024-03-01_2024-03-16_T-Mobile_DE_Vodafone_UK.tap");
+
+        let tap = export.render(ExportFormat::TapOut).unwrap();
+        let records: Vec<&str> = tap.lines().collect();
+        assert_eq!(records[0], format!("HDR|{}|T-Mobile:DE|Vodafone:UK|{}|EUR|1", TAP_OUT_VERSION, period));
+        assert_eq!(records[1], "CHG|10000|4000|6000");
+        assert!(records[2].starts_with(&format!("STL|{}|1|T-Mobile:DE|Vodafone:UK|6000|EUR|120|6000|", export.settlements[0].transaction_hash)));
+        assert_eq!(records[3], "TRL|1|6000|4");
+
+        let json: serde_json::Value = serde_json::from_str(&export.render(ExportFormat::BceJson).unwrap()).unwrap();
+        assert_eq!(json["schema"], BCE_SETTLEMENT_SCHEMA);
+        assert_eq!(json["counterparty"], "Vodafone:UK");
+        assert_eq!(json["settled_cents"], 6_000);
+        assert_eq!(json["settlements"][0]["breakdown"]["voice_minutes"], 120);
+
+        // Vodafone gets a file per counterparty, Orange's settlement included
+        let exports = CounterpartyExport::build(&store, period, "Vodafone:UK").await.unwrap();
+        assert_eq!(exports.iter().map(|export| export.counterparty.as_str()).collect::<Vec<_>>(), vec!["Orange:FR", "T-Mobile:DE"]);
+        let files = write_exports(&exports, ExportFormat::BceJson, &dir.path().join("exports")).unwrap();
+        assert!(files.iter().all(|file| file.exists()));
+    }
+}
@@ -0,0 +1,328 @@
+// Settlement reports from the chain history: the balances a settlement period closed with are the
+// gross charges between operators, the settlement transactions what was actually settled. Per
+// operator pair they give the netted position, what netting saved and any dispute adjustment,
+// exported as CSV or as a BCE settlement statement
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::TransactionData;
+use crate::primitives::{BlockchainError, Result};
+use super::ChainStore;
+
+/// Currency settlements are proposed and reported in
+pub const REPORT_CURRENCY: &str = "EUR";
+
+/// Charges and settlements between two operators, from the side of the lower named one
+/// Amounts are in cents, positive ones owed to `operator`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PairSummary {
+    pub operator: String,
+    pub counterparty: String,
+    /// Charges the counterparty owes for the period, before netting
+    pub receivable_cents: u64,
+    /// Charges owed to the counterparty for the period, before netting
+    pub payable_cents: u64,
+    pub gross_cents: u64,
+    /// Receivables less payables, the one amount left to settle
+    pub net_cents: i64,
+    /// Gross charges that did not change hands thanks to netting
+    pub savings_cents: u64,
+    /// Net of the settlement transactions on chain
+    pub settled_cents: i64,
+    /// Settled less netted charges, from settlements agreed at another amount after a dispute
+    pub adjustment_cents: i64,
+    pub settlement_count: usize,
+}
+
+/// Settlement report of every operator pair over the periods matching `period`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SettlementReport {
+    pub period: String,
+    /// Settlement periods the report covers
+    pub periods: Vec<String>,
+    pub currency: String,
+    /// Chain height the report was taken at
+    pub block_number: u32,
+    pub pairs: Vec<PairSummary>,
+}
+
+/// Whether settlement period `period` falls in the reported `wanted` one, e.g. `2024-03`
+/// covers every period starting in March 2024
+pub fn in_period(period: &str, wanted: &str) -> bool {
+    period.starts_with(wanted)
+}
+
+/// `name:country` form of a network named by the `Debug` form of its `NetworkId`, the way period
+/// balances name it; other names are kept
+pub(crate) fn display_name(network: &str) -> String {
+    network.strip_prefix("Operator { name: \"")
+        .and_then(|rest| rest.strip_suffix("\" }"))
+        .and_then(|rest| rest.split_once("\", country: \""))
+        .map(|(name, country)| format!("{}:{}", name, country))
+        .unwrap_or_else(|| network.to_string())
+}
+
+impl SettlementReport {
+    /// Walk the blocks of `store` up to its head for the settlement periods matching `period`
+    pub async fn build(store: &dyn ChainStore, period: &str) -> Result<Self> {
+        let mut report = Self {
+            period: period.to_string(),
+            currency: REPORT_CURRENCY.to_string(),
+            ..Default::default()
+        };
+        let head_hash = store.get_head_hash().await?;
+        let Some(head) = store.get_block(&head_hash).await? else {
+            return Ok(report);
+        };
+        report.block_number = head.block_number();
+
+        let mut periods = BTreeSet::new();
+        let mut pairs: BTreeMap<(String, String), PairSummary> = BTreeMap::new();
+        for block_number in 1..=head.block_number() {
+            let Some(block) = store.get_block_at(block_number).await? else {
+                continue;
+            };
+            for transaction in block.transactions() {
+                match &transaction.data {
+                    TransactionData::PeriodClose(close) if in_period(&close.period, period) => {
+                        periods.insert(close.period.clone());
+                        for balance in &close.balances {
+                            // Roaming within one network nets to nothing
+                            if balance.home_network == balance.visited_network {
+                                continue;
+                            }
+                            let (pair, receivable) = Self::pair(&mut pairs, &balance.home_network, &balance.visited_network);
+                            if receivable {
+                                pair.receivable_cents += balance.amount_cents;
+                            } else {
+                                pair.payable_cents += balance.amount_cents;
+                            }
+                        }
+                    }
+                    TransactionData::Settlement(settlement) if in_period(&settlement.period, period) => {
+                        periods.insert(settlement.period.clone());
+                        let creditor = display_name(&settlement.creditor_network);
+                        let debtor = display_name(&settlement.debtor_network);
+                        if creditor == debtor {
+                            continue;
+                        }
+                        let (pair, receivable) = Self::pair(&mut pairs, &creditor, &debtor);
+                        let amount = settlement.amount as i64;
+                        pair.settled_cents += if receivable { amount } else { -amount };
+                        pair.settlement_count += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        report.periods = periods.into_iter().collect();
+        report.pairs = pairs.into_values().map(|mut pair| {
+            pair.gross_cents = pair.receivable_cents + pair.payable_cents;
+            pair.net_cents = pair.receivable_cents as i64 - pair.payable_cents as i64;
+            pair.savings_cents = pair.gross_cents - pair.net_cents.unsigned_abs();
+            pair.adjustment_cents = pair.settled_cents - pair.net_cents;
+            pair
+        }).collect();
+        Ok(report)
+    }
+
+    /// Summary of the pair of `creditor` and `debtor`, and whether `creditor` is its operator
+    fn pair<'a>(pairs: &'a mut BTreeMap<(String, String), PairSummary>, creditor: &str, debtor: &str) -> (&'a mut PairSummary, bool) {
+        let receivable = creditor <= debtor;
+        let (operator, counterparty) = if receivable { (creditor, debtor) } else { (debtor, creditor) };
+        let pair = pairs.entry((operator.to_string(), counterparty.to_string()))
+            .or_insert_with(|| PairSummary {
+                operator: operator.to_string(),
+                counterparty: counterparty.to_string(),
+                ..Default::default()
+            });
+        (pair, receivable)
+    }
+
+    /// Summary of all pairs together, gross and savings summed, positions netted to zero
+    pub fn totals(&self) -> PairSummary {
+        let mut totals = PairSummary {
+            operator: "TOTAL".to_string(),
+            ..Default::default()
+        };
+        for pair in &self.pairs {
+            totals.receivable_cents += pair.receivable_cents;
+            totals.payable_cents += pair.payable_cents;
+            totals.gross_cents += pair.gross_cents;
+            totals.net_cents += pair.net_cents.abs();
+            totals.savings_cents += pair.savings_cents;
+            totals.settled_cents += pair.settled_cents.abs();
+            totals.adjustment_cents += pair.adjustment_cents;
+            totals.settlement_count += pair.settlement_count;
+        }
+        totals
+    }
+
+    /// One CSV row per operator pair, amounts in cents
+    pub fn to_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for pair in &self.pairs {
+            writer.serialize(pair)
+                .map_err(|e| BlockchainError::Serialization(format!("CSV export failed: {}", e)))?;
+        }
+        let data = writer.into_inner()
+            .map_err(|e| BlockchainError::Serialization(format!("CSV export failed: {}", e)))?;
+        String::from_utf8(data).map_err(|e| BlockchainError::Serialization(e.to_string()))
+    }
+
+    /// Settlement statement laid out the way GSMA BCE settlement statements are: one section per
+    /// operator pair from charges to settled amount, then the totals
+    pub fn to_statement(&self) -> String {
+        let mut statement = String::new();
+        let mut line = |text: String| {
+            statement.push_str(&text);
+            statement.push('\n');
+        };
+        line("SETTLEMENT STATEMENT".to_string());
+        line(format!("{:<28}{}", "Reporting period:", self.period));
+        line(format!("{:<28}{}", "Settlement periods:", if self.periods.is_empty() { "none".to_string() } else { self.periods.join(", ") }));
+        line(format!("{:<28}{}", "Currency:", self.currency));
+        line(format!("{:<28}{}", "Chain height:", self.block_number));
+
+        let sections = self.pairs.iter().map(|pair| (format!("{} / {}", pair.operator, pair.counterparty), pair.clone()))
+            .chain(std::iter::once(("TOTAL".to_string(), self.totals())));
+        for (title, pair) in sections {
+            line(String::new());
+            line(title);
+            line(format!("  {:<26}{:>16}", "Charges receivable", amount(pair.receivable_cents as i64)));
+            line(format!("  {:<26}{:>16}", "Charges payable", amount(pair.payable_cents as i64)));
+            line(format!("  {:<26}{:>16}", "Gross charges", amount(pair.gross_cents as i64)));
+            line(format!("  {:<26}{:>16}", "Net position", amount(pair.net_cents)));
+            line(format!("  {:<26}{:>16}", "Netting savings", amount(pair.savings_cents as i64)));
+            line(format!("  {:<26}{:>16}", "Dispute adjustments", amount(pair.adjustment_cents)));
+            line(format!("  {:<26}{:>16}", "Settled", amount(pair.settled_cents)));
+            line(format!("  {:<26}{:>16}", "Settlements", pair.settlement_count));
+        }
+        statement
+    }
+}
+
+/// Cents as a decimal amount
+fn amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.unsigned_abs() / 100, cents.unsigned_abs() % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Block, MicroBlock, MicroHeader, MicroBody};
+    use crate::blockchain::block::{PeriodBalance, PeriodCloseTransaction, SettlementTransaction, Transaction};
+    use crate::primitives::{Blake2bHash, NetworkId};
+    use crate::storage::MdbxChainStore;
+
+    fn transaction(data: TransactionData) -> Transaction {
+        Transaction {
+            sender: Blake2bHash::from_data(b"sender"),
+            recipient: Blake2bHash::from_data(b"recipient"),
+            value: 0,
+            fee: 1,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![1],
+            signature_proof: vec![],
+        }
+    }
+
+    fn micro_block(block_number: u32, transactions: Vec<Transaction>) -> Block {
+        Block::Micro(MicroBlock {
+            header: MicroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root: Blake2bHash::zero(),
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MicroBody { transactions },
+        })
+    }
+
+    fn period_close(period: &str, balances: &[(&str, &str, u64)]) -> Transaction {
+        transaction(TransactionData::PeriodClose(PeriodCloseTransaction {
+            period: period.to_string(),
+            start: 0,
+            cutoff: 0,
+            frozen_batches: vec![],
+            balances: balances.iter().map(|(home, visited, amount_cents)| PeriodBalance {
+                home_network: home.to_string(),
+                visited_network: visited.to_string(),
+                amount_cents: *amount_cents,
+            }).collect(),
+        }))
+    }
+
+    fn settlement(period: &str, creditor: &NetworkId, debtor: &NetworkId, amount: u64) -> Transaction {
+        transaction(TransactionData::Settlement(SettlementTransaction {
+            creditor_network: format!("{:?}", creditor),
+            debtor_network: format!("{:?}", debtor),
+            amount,
+            currency: REPORT_CURRENCY.to_string(),
+            period: period.to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![],
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_report_nets_pairs_of_a_month() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MdbxChainStore::new(dir.path()).unwrap();
+
+        let tmobile = NetworkId::new("T-Mobile", "DE");
+        let vodafone = NetworkId::new("Vodafone", "UK");
+        let blocks = [
+            micro_block(1, vec![
+                period_close("2024-03-01/2024-03-16", &[
+                    ("T-Mobile:DE", "Vodafone:UK", 10_000),
+                    ("Vodafone:UK", "T-Mobile:DE", 4_000),
+                ]),
+                period_close("2024-02-16/2024-03-01", &[("T-Mobile:DE", "Vodafone:UK", 99_999)]),
+            ]),
+            micro_block(2, vec![
+                settlement("2024-03-01/2024-03-16", &tmobile, &vodafone, 10_000),
+                // Arbitrated down from 4,000 after a dispute
+                settlement("2024-03-01/2024-03-16", &vodafone, &tmobile, 3_500),
+            ]),
+        ];
+        for block in &blocks {
+            store.put_block(block).await.unwrap();
+        }
+        store.set_head(&blocks[1].hash()).await.unwrap();
+
+        let report = SettlementReport::build(&store, "2024-03").await.unwrap();
+        assert_eq!(report.periods, vec!["2024-03-01/2024-03-16".to_string()]);
+        assert_eq!(report.block_number, 2);
+        assert_eq!(report.pairs.len(), 1);
+
+        // Named the same way whether they come from balances or settlements
+        let pair = &report.pairs[0];
+        assert_eq!((pair.operator.as_str(), pair.counterparty.as_str()), ("T-Mobile:DE", "Vodafone:UK"));
+        assert_eq!((pair.receivable_cents, pair.payable_cents, pair.gross_cents), (10_000, 4_000, 14_000));
+        assert_eq!((pair.net_cents, pair.savings_cents), (6_000, 8_000));
+        assert_eq!((pair.settled_cents, pair.adjustment_cents, pair.settlement_count), (6_500, 500, 2));
+
+        let csv = report.to_csv().unwrap();
+        let mut rows = csv.lines();
+        assert!(rows.next().unwrap().starts_with("operator,counterparty,receivable_cents"));
+        assert_eq!(rows.next().unwrap(), "T-Mobile:DE,Vodafone:UK,10000,4000,14000,6000,8000,6500,500,2");
+
+        let statement = report.to_statement();
+        assert!(statement.contains("T-Mobile:DE / Vodafone:UK"));
+        assert!(statement.contains("Netting savings"));
+        assert!(statement.contains("80.00"));
+
+        assert!(SettlementReport::build(&store, "2024-04").await.unwrap().pairs.is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+// Chain state snapshots for fast node bootstrap
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use crate::blockchain::Block;
+use crate::smart_contracts::SlotStatus;
+use super::{ChainStore, MdbxChainStore, WriteBatch};
+use super::state_trie::StateTrie;
+
+/// Leading bytes of every snapshot file
+const SNAPSHOT_MAGIC: &[u8; 8] = b"SPCDRSNP";
+
+/// Snapshot format version, bumped on incompatible changes
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/// Chain state at a head block: enough to continue from there without replaying history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainSnapshot {
+    pub version: u16,
+    pub head: Block,
+    pub macro_head: Block,
+    /// Carries the active validator set
+    pub election_head: Block,
+    pub contract_code: Vec<(Blake2bHash, Vec<u8>)>,
+    pub contract_state: Vec<(Blake2bHash, Blake2bHash, Vec<u8>)>,
+    /// Last write or expiry of each contract storage slot; archived slots stay with archive nodes
+    pub contract_slot_status: Vec<(Blake2bHash, Blake2bHash, SlotStatus)>,
+    /// State trie leaves, including settlement balances
+    pub state_entries: Vec<(Blake2bHash, Vec<u8>)>,
+}
+
+impl ChainSnapshot {
+    /// Capture the current chain state of a store
+    pub async fn export(store: &MdbxChainStore) -> Result<Self> {
+        let head = Self::load_block(store, &store.get_head_hash().await?).await?;
+        let macro_head = Self::load_block(store, &store.get_macro_head_hash().await?).await?;
+        let election_head = Self::load_block(store, &store.get_election_head_hash().await?).await?;
+
+        let state_trie = store.load_state_trie().await?;
+        let snapshot = Self {
+            version: SNAPSHOT_VERSION,
+            head,
+            macro_head,
+            election_head,
+            contract_code: store.contract_code_entries().await?,
+            contract_state: store.contract_state_entries().await?,
+            contract_slot_status: store.contract_slot_statuses().await?,
+            state_entries: state_trie.iter().map(|(key, value)| (key, value.clone())).collect(),
+        };
+        snapshot.verify()?;
+
+        Ok(snapshot)
+    }
+
+    async fn load_block(store: &MdbxChainStore, hash: &Blake2bHash) -> Result<Block> {
+        store.get_block(hash).await?
+            .ok_or_else(|| BlockchainError::NotFound(format!("Head block {}", hash)))
+    }
+
+    /// Rebuild the state trie carried by the snapshot
+    pub fn state_trie(&self) -> StateTrie {
+        let mut state_trie = StateTrie::new();
+        for (key, value) in &self.state_entries {
+            state_trie.insert(*key, value.clone());
+        }
+        state_trie
+    }
+
+    /// Check the snapshot is internally consistent and its state matches the head's state root
+    pub fn verify(&self) -> Result<()> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(BlockchainError::InvalidOperation(format!("Unsupported snapshot version {}", self.version)));
+        }
+
+        if !matches!(self.macro_head, Block::Macro(_)) || !matches!(self.election_head, Block::Macro(_)) {
+            return Err(BlockchainError::InvalidState("Snapshot macro heads are not macro blocks".to_string()));
+        }
+
+        if self.head.block_number() < self.macro_head.block_number()
+            || self.macro_head.block_number() < self.election_head.block_number()
+        {
+            return Err(BlockchainError::InvalidState("Snapshot heads are out of order".to_string()));
+        }
+
+        let state_root = self.state_trie().root();
+        if state_root != *self.head.state_root() {
+            return Err(BlockchainError::InvalidState(format!(
+                "Snapshot state root {} does not match head block state root {}", state_root, self.head.state_root()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Write the snapshot as magic, version, content hash and payload
+    pub fn write_to(&self, path: &Path) -> Result<Blake2bHash> {
+        let payload = bincode::serialize(self)
+            .map_err(|e| BlockchainError::Serialization(format!("Snapshot serialize failed: {}", e)))?;
+        let hash = Blake2bHash::from_data(&payload);
+
+        let mut data = Vec::with_capacity(SNAPSHOT_MAGIC.len() + 2 + 32 + payload.len());
+        data.extend_from_slice(SNAPSHOT_MAGIC);
+        data.extend_from_slice(&SNAPSHOT_VERSION.to_be_bytes());
+        data.extend_from_slice(hash.as_bytes());
+        data.extend_from_slice(&payload);
+
+        std::fs::write(path, data).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+        Ok(hash)
+    }
+
+    /// Read a snapshot file, rejecting it if the content hash or state does not verify
+    pub fn read_from(path: &Path) -> Result<(Self, Blake2bHash)> {
+        let data = std::fs::read(path).map_err(|e| BlockchainError::Storage(e.to_string()))?;
+
+        let header_len = SNAPSHOT_MAGIC.len() + 2 + 32;
+        if data.len() < header_len || &data[..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+            return Err(BlockchainError::InvalidOperation("Not a snapshot file".to_string()));
+        }
+
+        let version = u16::from_be_bytes([data[8], data[9]]);
+        if version != SNAPSHOT_VERSION {
+            return Err(BlockchainError::InvalidOperation(format!("Unsupported snapshot version {}", version)));
+        }
+
+        let mut expected = [0u8; 32];
+        expected.copy_from_slice(&data[10..header_len]);
+        let expected = Blake2bHash::from_bytes(expected);
+
+        let payload = &data[header_len..];
+        if Blake2bHash::from_data(payload) != expected {
+            return Err(BlockchainError::InvalidState("Snapshot content hash mismatch".to_string()));
+        }
+
+        let snapshot: Self = bincode::deserialize(payload)
+            .map_err(|e| BlockchainError::Serialization(format!("Snapshot deserialize failed: {}", e)))?;
+        snapshot.verify()?;
+
+        Ok((snapshot, expected))
+    }
+
+    /// Restore the snapshot into an empty store
+    pub async fn import(&self, store: &MdbxChainStore) -> Result<()> {
+        if store.get_head_hash().await.is_ok() {
+            return Err(BlockchainError::InvalidOperation("Chain store already has a head, import needs an empty data directory".to_string()));
+        }
+
+        self.verify()?;
+
+        for (address, code) in &self.contract_code {
+            store.put_contract_code(address, code).await?;
+        }
+        for (contract, key, value) in &self.contract_state {
+            store.put_contract_state(contract, key, value).await?;
+        }
+        store.put_contract_slot_statuses(&self.contract_slot_status).await?;
+        store.put_state_changes(self.state_entries.iter()
+            .map(|(key, value)| (*key, Some(value.clone())))
+            .collect()).await?;
+
+        for block in [&self.election_head, &self.macro_head, &self.head] {
+            store.put_block(block).await?;
+        }
+
+        // Heads last and together, so an interrupted import leaves the store without a head
+        store.commit(WriteBatch {
+            head: Some(self.head.hash()),
+            macro_head: Some(self.macro_head.hash()),
+            election_head: Some(self.election_head.hash()),
+            ..WriteBatch::default()
+        }).await?;
+
+        info!("📥 Imported snapshot at block {} with {} state entries", self.head.block_number(), self.state_entries.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{MacroBlock, MacroHeader, MacroBody};
+    use crate::primitives::NetworkId;
+    use crate::storage::state_trie::settlement_balance_key;
+
+    fn macro_block(block_number: u32, state_root: Blake2bHash) -> Block {
+        Block::Macro(MacroBlock {
+            justification: None,
+            header: MacroHeader {
+                network: NetworkId::SPConsortium,
+                version: 1,
+                block_number,
+                round: 0,
+                timestamp: block_number as u64,
+                parent_hash: Blake2bHash::zero(),
+                parent_election_hash: Blake2bHash::zero(),
+                seed: Blake2bHash::zero(),
+                extra_data: vec![],
+                state_root,
+                body_root: Blake2bHash::zero(),
+                history_root: Blake2bHash::zero(),
+            },
+            body: MacroBody {
+                validators: None,
+                lost_reward_set: vec![],
+                disabled_set: vec![],
+                transactions: vec![],
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_roundtrip() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = MdbxChainStore::new(source_dir.path()).unwrap();
+
+        let mut state_trie = StateTrie::new();
+        state_trie.insert(settlement_balance_key("T-Mobile-DE", "Vodafone-UK", "EUR"), 25_000u64.to_le_bytes().to_vec());
+        source.put_state_changes(state_trie.changes_since(&StateTrie::new())).await.unwrap();
+
+        let contract = Blake2bHash::from_data(b"contract");
+        source.put_contract_code(&contract, b"code").await.unwrap();
+        source.put_contract_slot(&contract, &Blake2bHash::from_data(b"slot"), b"value", 7).await.unwrap();
+
+        let head = macro_block(32, state_trie.root());
+        source.put_block(&head).await.unwrap();
+        source.set_head(&head.hash()).await.unwrap();
+        source.set_macro_head(&head.hash()).await.unwrap();
+        source.set_election_head(&head.hash()).await.unwrap();
+
+        let snapshot = ChainSnapshot::export(&source).await.unwrap();
+        let path = source_dir.path().join("chain.snapshot");
+        let hash = snapshot.write_to(&path).unwrap();
+
+        let (restored, restored_hash) = ChainSnapshot::read_from(&path).unwrap();
+        assert_eq!(restored_hash, hash);
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = MdbxChainStore::new(target_dir.path()).unwrap();
+        restored.import(&target).await.unwrap();
+
+        assert_eq!(target.get_head_hash().await.unwrap(), head.hash());
+        assert_eq!(target.load_state_trie().await.unwrap().root(), state_trie.root());
+        assert_eq!(target.get_contract_code(&contract).await.unwrap(), Some(b"code".to_vec()));
+        assert_eq!(target.contract_slot_status(&contract, &Blake2bHash::from_data(b"slot")).await.unwrap(),
+                   Some(SlotStatus::Live { touched_at: 7 }));
+
+        // A second import into the same store is refused
+        assert!(restored.import(&target).await.is_err());
+
+        // Tampered files are rejected
+        let mut data = std::fs::read(&path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        std::fs::write(&path, data).unwrap();
+        assert!(ChainSnapshot::read_from(&path).is_err());
+    }
+}
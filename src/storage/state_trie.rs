@@ -0,0 +1,979 @@
+// Authenticated state trie over contract storage and settlement balances
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{hash_canonical, Blake2bHash, BlockchainError, Height, Policy, Result};
+use crate::blockchain::block::{
+    Transaction, TransactionData, SettlementTransaction, FraudFlagTransaction, PeriodCloseTransaction, BatchCommitmentTransaction,
+    PaymentDocumentTransaction,
+};
+use crate::blockchain::cdr_commitment::{
+    CDRCommitmentTransaction, CommitmentChallengeTransaction, CommitmentRecord, CommitmentResponseTransaction, CommitmentStatus, OpenChallenge,
+};
+
+pub mod bridge;
+pub mod governance;
+pub mod rewards;
+pub mod validators;
+
+pub use bridge::{bridge_link_key, bridged_settlement_key};
+pub use governance::{chain_parameters_key, governance_proposals_key, operator_applications_key};
+pub use rewards::{participation_key, reward_balance_key, reward_pool_key};
+pub use validators::{validator_record_key, validator_registry_key, validator_stake_key};
+
+/// Children per branch node, one per key nibble
+const BRANCH_WIDTH: usize = 16;
+
+/// Merkle Patricia trie node, keys are walked nibble by nibble
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum TrieNode {
+    Leaf { path: Vec<u8>, value: Vec<u8> },
+    Extension { path: Vec<u8>, child: Blake2bHash },
+    Branch { children: Vec<Option<Blake2bHash>> },
+}
+
+impl TrieNode {
+    fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("trie nodes are serializable")
+    }
+}
+
+/// Encoded trie nodes from the root to a key, enough to check membership against a state root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProof {
+    pub nodes: Vec<Vec<u8>>,
+}
+
+/// State trie keyed by 32-byte hashes
+/// All keys have the same length, so no key is a prefix of another and branches carry no values
+#[derive(Debug, Default)]
+pub struct StateTrie {
+    leaves: BTreeMap<[u8; 32], Vec<u8>>,
+    /// Hashes of the subtries under nibble prefixes, dropped along the path of a changed key
+    /// so roots and proofs only rehash what changed since they were last taken
+    hashes: Mutex<HashMap<Vec<u8>, Blake2bHash>>,
+    /// Undo logs of the blocks being applied, innermost last: the value each changed key had
+    /// before its first change, `None` if it was absent
+    undo_logs: Vec<BTreeMap<[u8; 32], Option<Vec<u8>>>>,
+}
+
+fn to_nibbles(key: &[u8; 32]) -> Vec<u8> {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Keys starting with the nibble `prefix`
+fn key_range(prefix: &[u8]) -> RangeInclusive<[u8; 32]> {
+    let key = |fill: u8| {
+        let mut key = [0u8; 32];
+        for (i, nibble) in prefix.iter().copied().chain(std::iter::repeat(fill)).take(64).enumerate() {
+            key[i / 2] |= if i % 2 == 0 { nibble << 4 } else { nibble };
+        }
+        key
+    };
+    key(0)..=key(0x0f)
+}
+
+/// Trie key of a contract storage slot
+pub fn contract_storage_key(contract: &Blake2bHash, key: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"contract-storage".to_vec();
+    data.extend_from_slice(contract.as_bytes());
+    data.extend_from_slice(key.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of a contract's code
+pub fn contract_code_key(contract: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"contract-code".to_vec();
+    data.extend_from_slice(contract.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the Merkle root over the contract storage slots that expired in a block
+pub fn expired_slots_key(block_number: Height) -> Blake2bHash {
+    Blake2bHash::from_data(format!("contract-storage-expired:{}", block_number).as_bytes())
+}
+
+/// Trie key of the settled balance a debtor owes a creditor in one currency
+pub fn settlement_balance_key(creditor: &str, debtor: &str, currency: &str) -> Blake2bHash {
+    Blake2bHash::from_data(format!("settlement-balance:{}:{}:{}", creditor, debtor, currency).as_bytes())
+}
+
+/// Trie key marking a BCE batch as settled
+pub fn settled_batch_key(batch_id: &Blake2bHash) -> Blake2bHash {
+    hash_canonical(&("settled-batch", batch_id))
+}
+
+/// Trie key of a quarantined BCE batch
+pub fn quarantine_key(batch_id: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"fraud-quarantine".to_vec();
+    data.extend_from_slice(batch_id.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the Merkle root a BCE batch was committed with
+pub fn batch_commitment_key(batch_id: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"bce-batch-commitment".to_vec();
+    data.extend_from_slice(batch_id.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of an aggregated CDR commitment
+pub fn cdr_commitment_key(commitment_id: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"cdr-commitment".to_vec();
+    data.extend_from_slice(commitment_id.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the leaf of a committed record revealed in answer to a challenge
+pub fn revealed_record_key(commitment_id: &Blake2bHash, record_index: u32) -> Blake2bHash {
+    let mut data = b"cdr-commitment-revealed".to_vec();
+    data.extend_from_slice(commitment_id.as_bytes());
+    data.extend_from_slice(&record_index.to_le_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the challenges waiting for their committer's response
+pub fn commitment_challenges_key() -> Blake2bHash {
+    Blake2bHash::from_data(b"cdr-commitment-challenges")
+}
+
+/// Trie key of a closed settlement period
+pub fn period_close_key(period: &str) -> Blake2bHash {
+    Blake2bHash::from_data(format!("settlement-period-close:{}", period).as_bytes())
+}
+
+/// Trie key of the nonce an account's next transaction carries
+pub fn account_nonce_key(sender: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"account-nonce".to_vec();
+    data.extend_from_slice(sender.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the payment document hash a coordinator anchored for a settlement
+pub fn payment_document_key(settlement_tx: &Blake2bHash, coordinator: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"payment-document".to_vec();
+    data.extend_from_slice(settlement_tx.as_bytes());
+    data.extend_from_slice(coordinator.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+impl StateTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: Blake2bHash, value: Vec<u8>) {
+        self.touch(&key.0);
+        self.leaves.insert(key.0, value);
+    }
+
+    pub fn remove(&mut self, key: &Blake2bHash) -> Option<Vec<u8>> {
+        if !self.leaves.contains_key(key.as_bytes()) {
+            return None;
+        }
+        self.touch(key.as_bytes());
+        self.leaves.remove(key.as_bytes())
+    }
+
+    /// Log the value of `key` before it changes and drop the hashes of the subtries holding it
+    fn touch(&mut self, key: &[u8; 32]) {
+        for undo_log in &mut self.undo_logs {
+            undo_log.entry(*key).or_insert_with(|| self.leaves.get(key).cloned());
+        }
+        let path = to_nibbles(key);
+        let hashes = self.hashes.get_mut().unwrap();
+        for depth in 0..=path.len() {
+            hashes.remove(&path[..depth]);
+        }
+    }
+
+    pub fn get(&self, key: &Blake2bHash) -> Option<&Vec<u8>> {
+        self.leaves.get(key.as_bytes())
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// All entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (Blake2bHash, &Vec<u8>)> {
+        self.leaves.iter().map(|(key, value)| (Blake2bHash::from_bytes(*key), value))
+    }
+
+    /// Entries that differ from `previous`, `None` marks a removed key
+    pub fn changes_since(&self, previous: &StateTrie) -> Vec<(Blake2bHash, Option<Vec<u8>>)> {
+        let mut changes: Vec<_> = self.leaves.iter()
+            .filter(|(key, value)| previous.leaves.get(*key) != Some(*value))
+            .map(|(key, value)| (Blake2bHash::from_bytes(*key), Some(value.clone())))
+            .collect();
+        changes.extend(previous.leaves.keys()
+            .filter(|key| !self.leaves.contains_key(*key))
+            .map(|key| (Blake2bHash::from_bytes(*key), None)));
+        changes
+    }
+
+    /// Start logging the changes of a block so they can be reverted, see `revert_block`
+    /// Logs nest: a change is logged in every open log, the innermost ending first
+    pub fn begin_block(&mut self) {
+        self.undo_logs.push(BTreeMap::new());
+    }
+
+    /// Entries changed since the innermost `begin_block`, `None` marks a removed key
+    pub fn block_changes(&self) -> Vec<(Blake2bHash, Option<Vec<u8>>)> {
+        let Some(undo_log) = self.undo_logs.last() else {
+            return vec![];
+        };
+        undo_log.iter()
+            .filter(|(key, previous)| self.leaves.get(*key) != previous.as_ref())
+            .map(|(key, _)| (Blake2bHash::from_bytes(*key), self.leaves.get(key).cloned()))
+            .collect()
+    }
+
+    /// Keep the changes since the innermost `begin_block` and stop logging them
+    pub fn end_block(&mut self) {
+        self.undo_logs.pop();
+    }
+
+    /// Restore every entry changed since the innermost `begin_block`
+    pub fn revert_block(&mut self) {
+        let Some(undo_log) = self.undo_logs.pop() else {
+            return;
+        };
+        for (key, previous) in undo_log {
+            match previous {
+                Some(value) => self.insert(Blake2bHash::from_bytes(key), value),
+                None => {
+                    self.remove(&Blake2bHash::from_bytes(key));
+                }
+            }
+        }
+    }
+
+    /// Run `f` on the trie and revert whatever it changed, for the state a block would lead to
+    pub fn speculate<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.begin_block();
+        let result = f(self);
+        self.revert_block();
+        result
+    }
+
+    /// Settled balance between two networks, zero if never settled
+    pub fn settlement_balance(&self, creditor: &str, debtor: &str, currency: &str) -> u64 {
+        self.get(&settlement_balance_key(creditor, debtor, currency))
+            .and_then(|value| value.as_slice().try_into().ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(0)
+    }
+
+    /// Add a settlement to the running balance of its network pair and mark its batches settled
+    pub fn apply_settlement(&mut self, settlement: &SettlementTransaction) {
+        self.add_settlement_balance(settlement);
+        for batch_id in &settlement.batch_ids {
+            self.insert(settled_batch_key(batch_id), settlement.period.as_bytes().to_vec());
+        }
+    }
+
+    fn add_settlement_balance(&mut self, settlement: &SettlementTransaction) {
+        let balance = self.settlement_balance(&settlement.creditor_network, &settlement.debtor_network, &settlement.currency)
+            .saturating_add(settlement.amount);
+        self.insert(
+            settlement_balance_key(&settlement.creditor_network, &settlement.debtor_network, &settlement.currency),
+            balance.to_le_bytes().to_vec(),
+        );
+    }
+
+    /// Merkle root over the contract storage slots that expired in a block, `None` if none did
+    pub fn expired_slots_root(&self, block_number: Height) -> Option<Blake2bHash> {
+        self.get(&expired_slots_key(block_number))
+            .and_then(|value| value.as_slice().try_into().ok())
+            .map(Blake2bHash::from_bytes)
+    }
+
+    /// Whether a settlement already covered a BCE batch
+    pub fn is_batch_settled(&self, batch_id: &Blake2bHash) -> bool {
+        self.get(&settled_batch_key(batch_id)).is_some()
+    }
+
+    /// Check no settlement in `transactions` settles a BCE batch that was settled on chain or by an
+    /// earlier settlement in the list. Settlements are keyed by their batches, so a network pair can
+    /// settle a period in several parts as its batches arrive
+    pub fn check_settlements(&self, transactions: &[Transaction]) -> Result<()> {
+        let mut batches = HashSet::new();
+        for transaction in transactions {
+            let TransactionData::Settlement(settlement) = &transaction.data else {
+                continue;
+            };
+            if let Some(batch_id) = settlement.batch_ids.iter().find(|batch_id| self.is_batch_settled(batch_id) || !batches.insert(**batch_id)) {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "Settlement {} settles batch {} again", transaction.hash(), batch_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fraud score a batch was quarantined with, `None` if it is not quarantined
+    pub fn quarantine_score(&self, batch_id: &Blake2bHash) -> Option<u32> {
+        self.get(&quarantine_key(batch_id))
+            .and_then(|value| value.as_slice().try_into().ok())
+            .map(u32::from_le_bytes)
+    }
+
+    /// Quarantine or release the batch of a fraud flag
+    pub fn apply_fraud_flag(&mut self, flag: &FraudFlagTransaction) {
+        if flag.quarantine {
+            self.insert(quarantine_key(&flag.batch_id), flag.score.to_le_bytes().to_vec());
+        } else {
+            self.remove(&quarantine_key(&flag.batch_id));
+        }
+    }
+
+    /// Merkle root a BCE batch was committed with, `None` if it was not committed
+    pub fn batch_commitment(&self, batch_id: &Blake2bHash) -> Option<Blake2bHash> {
+        self.get(&batch_commitment_key(batch_id))
+            .and_then(|value| <[u8; 32]>::try_from(value.as_slice()).ok())
+            .map(Blake2bHash)
+    }
+
+    /// Record a batch commitment, the first commitment of a batch is final
+    pub fn apply_batch_commitment(&mut self, commitment: &BatchCommitmentTransaction) {
+        if self.batch_commitment(&commitment.batch_id).is_none() {
+            self.insert(batch_commitment_key(&commitment.batch_id), commitment.merkle_root.as_bytes().to_vec());
+        }
+    }
+
+    /// Hash of the payment document `coordinator` anchored for a settlement, `None` if it anchored none
+    pub fn payment_document(&self, settlement_tx: &Blake2bHash, coordinator: &Blake2bHash) -> Option<Blake2bHash> {
+        self.get(&payment_document_key(settlement_tx, coordinator))
+            .and_then(|value| <[u8; 32]>::try_from(value.as_slice()).ok())
+            .map(Blake2bHash)
+    }
+
+    /// Record a payment document anchor under its sender, the first document anchored is final
+    pub fn apply_payment_document(&mut self, sender: &Blake2bHash, document: &PaymentDocumentTransaction) {
+        if self.payment_document(&document.settlement_tx, sender).is_none() {
+            self.insert(payment_document_key(&document.settlement_tx, sender), document.document_hash.as_bytes().to_vec());
+        }
+    }
+
+    /// Aggregated CDR commitment, `None` if it was never committed
+    pub fn cdr_commitment(&self, commitment_id: &Blake2bHash) -> Option<CommitmentRecord> {
+        self.get(&cdr_commitment_key(commitment_id)).and_then(|value| bincode::deserialize(value).ok())
+    }
+
+    /// Challenges waiting for their committer's response, oldest first
+    pub fn commitment_challenges(&self) -> Vec<OpenChallenge> {
+        self.get(&commitment_challenges_key())
+            .and_then(|value| bincode::deserialize(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether a committed record was revealed on chain
+    pub fn is_record_revealed(&self, commitment_id: &Blake2bHash, record_index: u32) -> bool {
+        self.get(&revealed_record_key(commitment_id, record_index)).is_some()
+    }
+
+    /// Check the CDR commitments, challenges and responses in `transactions` against the commitments on
+    /// chain and those before them in the list: a commitment is made once and covers records, only
+    /// unrevealed records of known commitments are challenged, once at a time, and only the committer
+    /// answers an open challenge, with the record its commitment proves at the challenged index
+    pub fn check_commitments(&self, transactions: &[Transaction]) -> Result<()> {
+        let mut commitments: HashMap<Blake2bHash, CommitmentRecord> = HashMap::new();
+        let mut challenged: HashSet<(Blake2bHash, u32)> = self.commitment_challenges().iter()
+            .map(|challenge| (challenge.commitment_id, challenge.record_index))
+            .collect();
+        for transaction in transactions {
+            let invalid = |reason: String| Err(BlockchainError::InvalidTransaction(format!(
+                "Transaction {}: {}", transaction.hash(), reason
+            )));
+            match &transaction.data {
+                TransactionData::CDRCommitment(commitment) => {
+                    let commitment_id = commitment.commitment_id();
+                    if commitment.record_count == 0 {
+                        return invalid(format!("commitment {} covers no records", commitment_id));
+                    }
+                    if self.cdr_commitment(&commitment_id).is_some() || commitments.contains_key(&commitment_id) {
+                        return invalid(format!("commitment {} is made again", commitment_id));
+                    }
+                    commitments.insert(commitment_id, Self::commitment_record(&transaction.sender, commitment));
+                }
+                TransactionData::CommitmentChallenge(challenge) => {
+                    let Some(record) = commitments.get(&challenge.commitment_id).cloned().or_else(|| self.cdr_commitment(&challenge.commitment_id)) else {
+                        return invalid(format!("commitment {} does not exist", challenge.commitment_id));
+                    };
+                    if challenge.record_index >= record.record_count {
+                        return invalid(format!(
+                            "commitment {} has {} records, record {} is challenged",
+                            challenge.commitment_id, record.record_count, challenge.record_index
+                        ));
+                    }
+                    if self.is_record_revealed(&challenge.commitment_id, challenge.record_index) {
+                        return invalid(format!("record {} of commitment {} is revealed already", challenge.record_index, challenge.commitment_id));
+                    }
+                    if !challenged.insert((challenge.commitment_id, challenge.record_index)) {
+                        return invalid(format!("record {} of commitment {} is challenged already", challenge.record_index, challenge.commitment_id));
+                    }
+                }
+                TransactionData::CommitmentResponse(response) => {
+                    if !challenged.remove(&(response.commitment_id, response.record_index())) {
+                        return invalid(format!("record {} of commitment {} is not challenged", response.record_index(), response.commitment_id));
+                    }
+                    let Some(record) = commitments.get(&response.commitment_id).cloned().or_else(|| self.cdr_commitment(&response.commitment_id)) else {
+                        return invalid(format!("commitment {} does not exist", response.commitment_id));
+                    };
+                    if record.committer != transaction.sender {
+                        return invalid(format!("sent by {}, not by the committer {}", transaction.sender, record.committer));
+                    }
+                    response.verify(&record.merkle_root)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn commitment_record(committer: &Blake2bHash, commitment: &CDRCommitmentTransaction) -> CommitmentRecord {
+        CommitmentRecord {
+            committer: *committer,
+            period: commitment.period.clone(),
+            home_network: commitment.home_network.clone(),
+            visited_network: commitment.visited_network.clone(),
+            record_count: commitment.record_count,
+            total_charges_cents: commitment.total_charges_cents,
+            merkle_root: commitment.merkle_root,
+            status: CommitmentStatus::Committed,
+        }
+    }
+
+    fn set_commitment_challenges(&mut self, challenges: &[OpenChallenge]) {
+        if challenges.is_empty() {
+            self.remove(&commitment_challenges_key());
+        } else {
+            self.insert(commitment_challenges_key(), bincode::serialize(challenges).expect("challenges are serializable"));
+        }
+    }
+
+    /// Record a CDR commitment sent by `committer`, the first commitment of an id is final
+    pub fn apply_cdr_commitment(&mut self, committer: &Blake2bHash, commitment: &CDRCommitmentTransaction) {
+        let key = cdr_commitment_key(&commitment.commitment_id());
+        if self.get(&key).is_none() {
+            let record = Self::commitment_record(committer, commitment);
+            self.insert(key, bincode::serialize(&record).expect("commitments are serializable"));
+        }
+    }
+
+    /// Open a challenge sent by `challenger` in the block at `block_number`
+    pub fn apply_commitment_challenge(&mut self, challenger: &Blake2bHash, challenge: &CommitmentChallengeTransaction, block_number: Height) {
+        let mut challenges = self.commitment_challenges();
+        challenges.push(OpenChallenge {
+            commitment_id: challenge.commitment_id,
+            record_index: challenge.record_index,
+            challenger: *challenger,
+            respond_by: block_number + Policy::COMMITMENT_RESPONSE_WINDOW,
+        });
+        self.set_commitment_challenges(&challenges);
+    }
+
+    /// Close the challenge a response answers and keep the revealed record's leaf
+    pub fn apply_commitment_response(&mut self, response: &CommitmentResponseTransaction) {
+        let mut challenges = self.commitment_challenges();
+        challenges.retain(|challenge| (challenge.commitment_id, challenge.record_index) != (response.commitment_id, response.record_index()));
+        self.set_commitment_challenges(&challenges);
+        let leaf = crate::bce_pipeline::commitment::record_leaf(&response.record);
+        self.insert(revealed_record_key(&response.commitment_id, response.record_index()), leaf.as_bytes().to_vec());
+    }
+
+    /// Mark the commitments whose challenges went unanswered past their window at `block_number` contested
+    pub fn expire_commitment_challenges(&mut self, block_number: Height) {
+        let (expired, open): (Vec<OpenChallenge>, Vec<OpenChallenge>) = self.commitment_challenges().into_iter()
+            .partition(|challenge| block_number > challenge.respond_by);
+        if expired.is_empty() {
+            return;
+        }
+        self.set_commitment_challenges(&open);
+        for challenge in expired {
+            if let Some(mut record) = self.cdr_commitment(&challenge.commitment_id) {
+                record.status = CommitmentStatus::Contested;
+                self.insert(cdr_commitment_key(&challenge.commitment_id), bincode::serialize(&record).expect("commitments are serializable"));
+            }
+        }
+    }
+
+    /// Whether a settlement period was closed on chain
+    pub fn is_period_closed(&self, period: &str) -> bool {
+        self.get(&period_close_key(period)).is_some()
+    }
+
+    /// Record a period close, committing to the batches it froze and the balances it closed with
+    pub fn apply_period_close(&mut self, close: &PeriodCloseTransaction) {
+        let commitment = crate::primitives::hash_canonical(&(&close.frozen_batches, &close.balances));
+        self.insert(period_close_key(&close.period), commitment.as_bytes().to_vec());
+    }
+
+    fn get_u64(&self, key: &Blake2bHash) -> u64 {
+        self.get(key)
+            .and_then(|value| value.as_slice().try_into().ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(0)
+    }
+
+    fn set_u64(&mut self, key: Blake2bHash, value: u64) {
+        if value == 0 {
+            self.remove(&key);
+        } else {
+            self.insert(key, value.to_le_bytes().to_vec());
+        }
+    }
+
+    /// Nonce the next transaction of `sender` has to carry, the number of its transactions applied
+    pub fn account_nonce(&self, sender: &Blake2bHash) -> u64 {
+        self.get_u64(&account_nonce_key(sender))
+    }
+
+    /// Check the transactions of a block continue their senders' nonce sequences without gaps,
+    /// so no transaction can be replayed
+    pub fn check_nonces(&self, transactions: &[Transaction]) -> Result<()> {
+        let mut next_nonces: HashMap<Blake2bHash, u64> = HashMap::new();
+        for transaction in transactions.iter().filter(|transaction| !transaction.is_system()) {
+            let next_nonce = next_nonces.entry(transaction.sender).or_insert_with(|| self.account_nonce(&transaction.sender));
+            if transaction.nonce != *next_nonce {
+                return Err(BlockchainError::InvalidTransaction(format!(
+                    "Transaction {} of {} has nonce {}, expected {}",
+                    transaction.hash(), transaction.sender, transaction.nonce, next_nonce
+                )));
+            }
+            *next_nonce += 1;
+        }
+        Ok(())
+    }
+
+    /// Apply the state changes of a block's transactions that do not go through the contract VM
+    /// Every fee goes into the validator reward pool and every sent transaction advances its sender's nonce
+    pub fn apply_transactions(&mut self, block_number: Height, transactions: &[Transaction]) {
+        self.expire_commitment_challenges(block_number);
+        for transaction in transactions {
+            if transaction.fee > 0 {
+                self.set_u64(reward_pool_key(), self.reward_pool().saturating_add(transaction.fee));
+            }
+            if !transaction.is_system() {
+                self.set_u64(account_nonce_key(&transaction.sender), transaction.nonce + 1);
+            }
+            match &transaction.data {
+                TransactionData::Settlement(settlement) => self.apply_settlement(settlement),
+                TransactionData::BridgedSettlement(bridged) => self.apply_bridged_settlement(bridged),
+                TransactionData::FraudFlag(flag) => self.apply_fraud_flag(flag),
+                TransactionData::BatchCommitment(commitment) => self.apply_batch_commitment(commitment),
+                TransactionData::PeriodClose(close) => self.apply_period_close(close),
+                TransactionData::ValidatorUpdate(update) => self.apply_validator_update(&transaction.sender, update, block_number),
+                TransactionData::RewardPayout(payout) => self.apply_reward_payout(payout),
+                TransactionData::NetworkJoin(join) => self.apply_network_join(join, block_number),
+                TransactionData::CDRCommitment(commitment) => self.apply_cdr_commitment(&transaction.sender, commitment),
+                TransactionData::CommitmentChallenge(challenge) => self.apply_commitment_challenge(&transaction.sender, challenge, block_number),
+                TransactionData::CommitmentResponse(response) => self.apply_commitment_response(response),
+                TransactionData::PaymentDocument(document) => self.apply_payment_document(&transaction.sender, document),
+                _ => {}
+            }
+        }
+    }
+
+    /// Root hash committing to every key and value, zero for the empty trie
+    pub fn root(&self) -> Blake2bHash {
+        let mut hashes = self.hashes.lock().unwrap();
+        self.subtrie_hash(&[], &mut hashes).unwrap_or_else(Blake2bHash::zero)
+    }
+
+    /// Membership proof for `key`, or a non-membership proof if the key is absent
+    pub fn prove(&self, key: &Blake2bHash) -> StateProof {
+        let mut hashes = self.hashes.lock().unwrap();
+        let path = to_nibbles(key.as_bytes());
+        let mut proof = Vec::new();
+        let mut depth = 0;
+
+        while let Some(node) = self.node(&path[..depth], &mut hashes) {
+            proof.push(node.encode());
+            match node {
+                TrieNode::Leaf { .. } => break,
+                TrieNode::Extension { path: extension, .. } => {
+                    if !path[depth..].starts_with(&extension) {
+                        break;
+                    }
+                    depth += extension.len();
+                }
+                TrieNode::Branch { children } => {
+                    if children[path[depth] as usize].is_none() {
+                        break;
+                    }
+                    depth += 1;
+                }
+            }
+        }
+
+        StateProof { nodes: proof }
+    }
+
+    /// Hash of the subtrie under the nibble `prefix`, `None` if no key starts with it
+    fn subtrie_hash(&self, prefix: &[u8], hashes: &mut HashMap<Vec<u8>, Blake2bHash>) -> Option<Blake2bHash> {
+        if let Some(hash) = hashes.get(prefix) {
+            return Some(*hash);
+        }
+        let hash = Blake2bHash::from_data(&self.node(prefix, hashes)?.encode());
+        hashes.insert(prefix.to_vec(), hash);
+        Some(hash)
+    }
+
+    /// Node at the top of the subtrie under the nibble `prefix`, `None` if no key starts with it
+    /// Children are hashed through `hashes`, so only subtries with changed keys are rebuilt
+    fn node(&self, prefix: &[u8], hashes: &mut HashMap<Vec<u8>, Blake2bHash>) -> Option<TrieNode> {
+        let depth = prefix.len();
+        let mut entries = self.leaves.range(key_range(prefix));
+        let (first, value) = entries.next()?;
+        let Some((last, _)) = entries.next_back() else {
+            return Some(TrieNode::Leaf { path: to_nibbles(first)[depth..].to_vec(), value: value.clone() });
+        };
+
+        // Keys are sorted, so the first and last share the longest common prefix
+        let (first, last) = (to_nibbles(first), to_nibbles(last));
+        let shared = first[depth..].iter().zip(&last[depth..]).take_while(|(a, b)| a == b).count();
+        if shared > 0 {
+            let child = self.subtrie_hash(&first[..depth + shared], hashes)?;
+            return Some(TrieNode::Extension { path: first[depth..depth + shared].to_vec(), child });
+        }
+
+        let mut child_prefix = prefix.to_vec();
+        child_prefix.push(0);
+        let children = (0..BRANCH_WIDTH as u8)
+            .map(|nibble| {
+                child_prefix[depth] = nibble;
+                self.subtrie_hash(&child_prefix, hashes)
+            })
+            .collect();
+        Some(TrieNode::Branch { children })
+    }
+}
+
+/// Check a state proof against a state root
+/// Returns the value stored under `key`, or `None` if the proof shows the key is absent
+pub fn verify_state_proof(root: &Blake2bHash, key: &Blake2bHash, proof: &StateProof) -> Result<Option<Vec<u8>>> {
+    if *root == Blake2bHash::zero() {
+        return Ok(None);
+    }
+
+    let path = to_nibbles(key.as_bytes());
+    let mut expected = *root;
+    let mut depth = 0;
+
+    for encoded in &proof.nodes {
+        if Blake2bHash::from_data(encoded) != expected {
+            return Err(BlockchainError::InvalidProof);
+        }
+
+        let node: TrieNode = bincode::deserialize(encoded)
+            .map_err(|e| BlockchainError::Serialization(format!("Invalid trie node: {}", e)))?;
+        match node {
+            TrieNode::Leaf { path: leaf_path, value } => {
+                return Ok((path[depth..] == leaf_path[..]).then_some(value));
+            }
+            TrieNode::Extension { path: extension, child } => {
+                if !path[depth..].starts_with(&extension) {
+                    return Ok(None);
+                }
+                depth += extension.len();
+                expected = child;
+            }
+            TrieNode::Branch { children } => {
+                if children.len() != BRANCH_WIDTH || depth >= path.len() {
+                    return Err(BlockchainError::InvalidProof);
+                }
+                match children[path[depth] as usize] {
+                    Some(child) => {
+                        depth += 1;
+                        expected = child;
+                    }
+                    None => return Ok(None),
+                }
+            }
+        }
+    }
+
+    // The proof stopped before reaching a leaf or a missing branch
+    Err(BlockchainError::InvalidProof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_root_and_proofs() {
+        let mut trie = StateTrie::new();
+        assert_eq!(trie.root(), Blake2bHash::zero());
+
+        let contract = Blake2bHash::from_data(b"settlement-contract");
+        let keys: Vec<Blake2bHash> = (0u8..20)
+            .map(|i| contract_storage_key(&contract, &Blake2bHash::from_data(&[i])))
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            trie.insert(*key, vec![i as u8]);
+        }
+
+        // The root is independent of insertion order
+        let mut reordered = StateTrie::new();
+        for (i, key) in keys.iter().enumerate().rev() {
+            reordered.insert(*key, vec![i as u8]);
+        }
+        let root = trie.root();
+        assert_eq!(root, reordered.root());
+
+        for (i, key) in keys.iter().enumerate() {
+            let proof = trie.prove(key);
+            assert_eq!(verify_state_proof(&root, key, &proof).unwrap(), Some(vec![i as u8]));
+        }
+
+        // Absent keys get a verifiable non-membership proof
+        let absent = contract_storage_key(&contract, &Blake2bHash::from_data(b"absent"));
+        assert_eq!(verify_state_proof(&root, &absent, &trie.prove(&absent)).unwrap(), None);
+
+        // A proof does not verify against another state root
+        let proof = trie.prove(&keys[0]);
+        trie.insert(keys[0], vec![99]);
+        assert_ne!(trie.root(), root);
+        assert!(verify_state_proof(&trie.root(), &keys[0], &proof).is_err());
+    }
+
+    #[test]
+    fn test_block_changes_are_reverted() {
+        let keys: Vec<Blake2bHash> = (0u8..50).map(|i| Blake2bHash::from_data(&[i])).collect();
+        let mut trie = StateTrie::new();
+        for key in &keys[..40] {
+            trie.insert(*key, key.as_bytes().to_vec());
+        }
+        let root = trie.root();
+
+        trie.begin_block();
+        trie.insert(keys[0], vec![1]);
+        trie.insert(keys[0], vec![2]);
+        trie.remove(&keys[1]);
+        trie.insert(keys[45], vec![3]);
+        trie.insert(keys[2], keys[2].as_bytes().to_vec());
+        let mut changes = trie.block_changes();
+        changes.sort_by_key(|(key, _)| key.0);
+        let mut expected = vec![(keys[0], Some(vec![2])), (keys[1], None), (keys[45], Some(vec![3]))];
+        expected.sort_by_key(|(key, _)| key.0);
+        assert_eq!(changes, expected);
+
+        // Cached hashes follow the changes, matching a trie built from scratch
+        let mut rebuilt = StateTrie::new();
+        for (key, value) in trie.iter() {
+            rebuilt.insert(key, value.clone());
+        }
+        assert_eq!(trie.root(), rebuilt.root());
+        assert_eq!(verify_state_proof(&trie.root(), &keys[1], &trie.prove(&keys[1])).unwrap(), None);
+
+        // A nested log reverts on its own, the outer one restores the state before the block
+        let speculated = trie.speculate(|trie| {
+            trie.insert(keys[46], vec![4]);
+            trie.root()
+        });
+        assert_ne!(speculated, rebuilt.root());
+        assert_eq!(trie.root(), rebuilt.root());
+        trie.revert_block();
+        assert_eq!(trie.root(), root);
+        assert_eq!(trie.len(), 40);
+        assert!(trie.block_changes().is_empty());
+    }
+
+    #[test]
+    fn test_settlement_balances() {
+        let settlement = SettlementTransaction {
+            creditor_network: "T-Mobile-DE".to_string(),
+            debtor_network: "Vodafone-UK".to_string(),
+            amount: 12_500,
+            currency: "EUR".to_string(),
+            period: "2024-01".to_string(),
+            breakdown: Default::default(),
+            batch_ids: vec![],
+        };
+
+        let mut trie = StateTrie::new();
+        trie.apply_settlement(&settlement);
+        trie.apply_settlement(&settlement);
+        assert_eq!(trie.settlement_balance("T-Mobile-DE", "Vodafone-UK", "EUR"), 25_000);
+        assert_eq!(trie.settlement_balance("Vodafone-UK", "T-Mobile-DE", "EUR"), 0);
+
+        let key = settlement_balance_key("T-Mobile-DE", "Vodafone-UK", "EUR");
+        let value = verify_state_proof(&trie.root(), &key, &trie.prove(&key)).unwrap();
+        assert_eq!(value, Some(25_000u64.to_le_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_fraud_quarantine() {
+        let batch_id = Blake2bHash::from_data(b"batch");
+        let mut flag = FraudFlagTransaction {
+            batch_id,
+            home_network: "T-Mobile-DE".to_string(),
+            visited_network: "Vodafone-UK".to_string(),
+            score: 80,
+            reasons: vec!["IMSI seen in two countries".to_string()],
+            quarantine: true,
+        };
+
+        let mut trie = StateTrie::new();
+        trie.apply_fraud_flag(&flag);
+        assert_eq!(trie.quarantine_score(&batch_id), Some(80));
+        assert_eq!(trie.quarantine_score(&Blake2bHash::from_data(b"other")), None);
+
+        flag.quarantine = false;
+        trie.apply_fraud_flag(&flag);
+        assert_eq!(trie.quarantine_score(&batch_id), None);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_settlements_rejected() {
+        let batch = Blake2bHash::from_data(b"batch-1");
+        let settlement = |period: &str, batch_ids: Vec<Blake2bHash>| Transaction {
+            sender: Blake2bHash::from_data(b"T-Mobile-DE"),
+            recipient: Blake2bHash::zero(),
+            value: 12_500,
+            fee: 100,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Settlement(SettlementTransaction {
+                creditor_network: "T-Mobile-DE".to_string(),
+                debtor_network: "Vodafone-UK".to_string(),
+                amount: 12_500,
+                currency: "EUR".to_string(),
+                period: period.to_string(),
+                breakdown: Default::default(),
+                batch_ids,
+            }),
+            signature: vec![],
+            signature_proof: vec![],
+        };
+
+        // Two settlements of one batch conflict within a block, a pair's period can settle in parts
+        let mut trie = StateTrie::new();
+        assert!(trie.check_settlements(&[settlement("2024-01", vec![batch]), settlement("2024-02", vec![batch])]).is_err());
+        assert!(trie.check_settlements(&[settlement("2024-01", vec![batch]), settlement("2024-01", vec![])]).is_ok());
+
+        // and with settlements applied before
+        trie.apply_transactions(1, &[settlement("2024-01", vec![batch])]);
+        assert!(trie.is_batch_settled(&batch));
+        assert!(trie.check_settlements(&[settlement("2024-01", vec![batch])]).is_err());
+        assert!(trie.check_settlements(&[settlement("2024-02", vec![batch])]).is_err());
+        assert!(trie.check_settlements(&[settlement("2024-01", vec![Blake2bHash::from_data(b"batch-2")])]).is_ok());
+        assert!(trie.check_settlements(&[settlement("2024-02", vec![Blake2bHash::from_data(b"batch-2")])]).is_ok());
+    }
+
+    #[test]
+    fn test_commitment_challenges() {
+        use crate::bce_pipeline::{commitment::{batch_root, record_leaf}, BCERecord};
+        use crate::blockchain::light_client::MerkleProof;
+
+        let records: Vec<BCERecord> = (0..4u64).map(|i| BCERecord {
+            record_id: format!("CDR-{}", i),
+            record_type: "VOICE_CALL_CDR".to_string(),
+            imsi: "262011234567890".to_string(),
+            home_plmn: "26201".to_string(),
+            visited_plmn: "20801".to_string(),
+            session_duration: 120,
+            bytes_uplink: 0,
+            bytes_downlink: 0,
+            wholesale_charge: 100 + i,
+            retail_charge: 200 + i,
+            currency: "EUR".to_string(),
+            timestamp: 1_700_000_000,
+            charging_id: i,
+        }).collect();
+        let commitment = CDRCommitmentTransaction {
+            period: "2024-01".to_string(),
+            home_network: "T-Mobile-DE".to_string(),
+            visited_network: "Orange-FR".to_string(),
+            record_count: records.len() as u32,
+            total_charges_cents: records.iter().map(|record| record.wholesale_charge).sum(),
+            merkle_root: batch_root(&records),
+            zk_proofs: vec![],
+        };
+        let commitment_id = commitment.commitment_id();
+        let (committer, challenger) = (Blake2bHash::from_data(b"T-Mobile-DE"), Blake2bHash::from_data(b"Orange-FR"));
+        let transaction = |sender, data| Transaction {
+            sender,
+            recipient: Blake2bHash::zero(),
+            value: 0,
+            fee: 0,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        let challenge = |record_index| transaction(challenger, TransactionData::CommitmentChallenge(CommitmentChallengeTransaction { commitment_id, record_index }));
+        let leaves: Vec<Blake2bHash> = records.iter().map(record_leaf).collect();
+        let response = |sender, index: usize, record: &BCERecord| transaction(sender, TransactionData::CommitmentResponse(CommitmentResponseTransaction {
+            commitment_id,
+            record: record.clone(),
+            proof: MerkleProof::new(&leaves, index).unwrap(),
+        }));
+
+        // Records of unknown commitments or beyond the committed count cannot be challenged
+        let mut trie = StateTrie::new();
+        assert!(trie.check_commitments(&[challenge(0)]).is_err());
+        let commit = transaction(committer, TransactionData::CDRCommitment(commitment.clone()));
+        assert!(trie.check_commitments(&[commit.clone(), challenge(3)]).is_ok());
+        assert!(trie.check_commitments(&[commit.clone(), challenge(4)]).is_err());
+        trie.apply_transactions(1, &[commit.clone(), challenge(1), challenge(2)]);
+        assert!(trie.check_commitments(&[commit]).is_err());
+        assert!(trie.check_commitments(&[challenge(1)]).is_err());
+        assert_eq!(trie.commitment_challenges().len(), 2);
+
+        // Only the committer answers, with the record committed at the challenged index
+        assert!(trie.check_commitments(&[response(challenger, 1, &records[1])]).is_err());
+        assert!(trie.check_commitments(&[response(committer, 1, &records[2])]).is_err());
+        assert!(trie.check_commitments(&[response(committer, 3, &records[3])]).is_err());
+        let answer = response(committer, 1, &records[1]);
+        assert!(trie.check_commitments(&[answer.clone(), answer.clone()]).is_err());
+        trie.apply_transactions(2, &[answer]);
+        assert!(trie.is_record_revealed(&commitment_id, 1));
+        assert!(trie.check_commitments(&[challenge(1)]).is_err());
+
+        // The unanswered challenge contests the commitment once its window closed
+        trie.apply_transactions(1 + Policy::COMMITMENT_RESPONSE_WINDOW, &[]);
+        assert_eq!(trie.cdr_commitment(&commitment_id).unwrap().status, CommitmentStatus::Committed);
+        trie.apply_transactions(2 + Policy::COMMITMENT_RESPONSE_WINDOW, &[]);
+        assert_eq!(trie.cdr_commitment(&commitment_id).unwrap().status, CommitmentStatus::Contested);
+        assert!(trie.commitment_challenges().is_empty());
+    }
+
+    #[test]
+    fn test_nonces_prevent_replay() {
+        let sender = Blake2bHash::from_data(b"T-Mobile-DE");
+        let transaction = |nonce| Transaction {
+            sender,
+            recipient: Blake2bHash::zero(),
+            value: 0,
+            fee: 0,
+            nonce,
+            validity_start_height: 0,
+            data: TransactionData::Basic,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+
+        let mut trie = StateTrie::new();
+        assert!(trie.check_nonces(&[transaction(0), transaction(1)]).is_ok());
+        assert!(trie.check_nonces(&[transaction(0), transaction(2)]).is_err());
+        trie.apply_transactions(1, &[transaction(0), transaction(1)]);
+        assert_eq!(trie.account_nonce(&sender), 2);
+
+        // Included transactions cannot be included again
+        assert!(trie.check_nonces(&[transaction(1)]).is_err());
+        assert!(trie.check_nonces(&[transaction(2)]).is_ok());
+    }
+
+}
@@ -0,0 +1,45 @@
+// Settlements bridged from other consortia and the election blocks trusted on their chains
+use crate::primitives::{Blake2bHash, NetworkId};
+use crate::bridge::{BridgeLink, BridgedSettlementTransaction};
+use super::StateTrie;
+
+/// Trie key of the election blocks trusted on a bridged consortium's chain
+pub fn bridge_link_key(network: &NetworkId) -> Blake2bHash {
+    Blake2bHash::from_data(format!("bridge-link:{}", network).as_bytes())
+}
+
+/// Trie key marking a settlement of another consortium bridged onto this chain
+pub fn bridged_settlement_key(network: &NetworkId, source_transaction: &Blake2bHash) -> Blake2bHash {
+    Blake2bHash::from_data(format!("bridged-settlement:{}:{}", network, source_transaction).as_bytes())
+}
+
+impl StateTrie {
+    /// Election blocks trusted on the chain of a bridged consortium, `None` before its first bridged settlement
+    pub fn bridge_link(&self, network: &NetworkId) -> Option<BridgeLink> {
+        self.get(&bridge_link_key(network)).and_then(|data| bincode::deserialize(data).ok())
+    }
+
+    /// Whether a settlement of another consortium was bridged onto this chain
+    pub fn is_bridged(&self, network: &NetworkId, source_transaction: &Blake2bHash) -> bool {
+        self.get(&bridged_settlement_key(network, source_transaction)).is_some()
+    }
+
+    /// Add a bridged settlement to the balance of its network pair as an obligation on this chain
+    /// and trust the source election blocks its proof went through. Periods and batches are the
+    /// source consortium's, so they are not marked settled here
+    pub fn apply_bridged_settlement(&mut self, bridged: &BridgedSettlementTransaction) {
+        let Ok(settlement) = bridged.settlement() else {
+            return;
+        };
+        self.add_settlement_balance(settlement);
+        self.insert(
+            bridged_settlement_key(&bridged.source_network, &bridged.source_transaction()),
+            settlement.amount.to_le_bytes().to_vec(),
+        );
+
+        let mut link = self.bridge_link(&bridged.source_network)
+            .unwrap_or_else(|| BridgeLink::from_checkpoint(bridged.proof.checkpoint.block_hash()));
+        link.extend(&bridged.proof);
+        self.insert(bridge_link_key(&bridged.source_network), bincode::serialize(&link).expect("bridge links are serializable"));
+    }
+}
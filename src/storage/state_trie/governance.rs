@@ -0,0 +1,274 @@
+// Governance: chain parameter proposals and the votes on join applications
+use crate::primitives::{Blake2bHash, Height};
+use crate::blockchain::block::{Transaction, TransactionData, ValidatorInfo};
+use crate::blockchain::governance::{Application, ChainParameters, GovernanceAction, Proposal, ProposalStatus};
+use crate::blockchain::transaction::NetworkJoinTransaction;
+use super::StateTrie;
+
+/// Trie key of the parameter set in effect
+pub fn chain_parameters_key() -> Blake2bHash {
+    Blake2bHash::from_data(b"governance-chain-parameters")
+}
+
+/// Trie key of the open governance proposals
+pub fn governance_proposals_key() -> Blake2bHash {
+    Blake2bHash::from_data(b"governance-proposals")
+}
+
+/// Trie key of the join applications in their vote
+pub fn operator_applications_key() -> Blake2bHash {
+    Blake2bHash::from_data(b"governance-operator-applications")
+}
+
+impl StateTrie {
+    /// Parameter set in effect, the defaults until governance changed one
+    pub fn chain_parameters(&self) -> ChainParameters {
+        self.get(&chain_parameters_key())
+            .and_then(|value| bincode::deserialize(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Proposals in their vote or waiting for activation
+    pub fn governance_proposals(&self) -> Vec<Proposal> {
+        self.get(&governance_proposals_key())
+            .and_then(|value| bincode::deserialize(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Join applications in their vote
+    pub fn operator_applications(&self) -> Vec<Application> {
+        self.get(&operator_applications_key())
+            .and_then(|value| bincode::deserialize(value).ok())
+            .unwrap_or_default()
+    }
+
+    fn set_operator_applications(&mut self, applications: &[Application]) {
+        if applications.is_empty() {
+            self.remove(&operator_applications_key());
+        } else {
+            self.insert(operator_applications_key(), bincode::serialize(applications).expect("applications are serializable"));
+        }
+    }
+
+    /// Open the vote on a join application, ignored while another application of the operator is open
+    pub fn apply_network_join(&mut self, join: &NetworkJoinTransaction, block_number: Height) {
+        let mut applications = self.operator_applications();
+        if applications.iter().any(|application| application.join.record.name == join.record.name) {
+            return;
+        }
+        applications.push(Application::open(join.clone(), block_number));
+        self.set_operator_applications(&applications);
+    }
+
+    /// Close the votes on join applications ending by `block_number`, returning the accepted applications
+    /// for their operators to be registered; rejected applications are dropped
+    pub fn close_applications(&mut self, block_number: Height, validators: &[ValidatorInfo]) -> Vec<NetworkJoinTransaction> {
+        let (closed, open): (Vec<Application>, Vec<Application>) = self.operator_applications().into_iter()
+            .partition(|application| block_number >= application.voting_ends);
+        if closed.is_empty() {
+            return vec![];
+        }
+        self.set_operator_applications(&open);
+        closed.into_iter()
+            .filter(|application| application.is_accepted(validators))
+            .map(|application| application.join)
+            .collect()
+    }
+
+    /// Record the governance actions of a block by the elected `validators`, close the votes
+    /// ending at `block_number` and activate the accepted changes that are due
+    /// Votes on join applications are recorded here but closed by `close_applications`
+    /// Signatures are checked with the block, actions of validators not elected are ignored
+    pub fn apply_governance(&mut self, block_number: Height, validators: &[ValidatorInfo], transactions: &[Transaction]) {
+        let mut proposals = self.governance_proposals();
+        let mut applications = self.operator_applications();
+        let mut applications_changed = false;
+        let governance = transactions.iter().filter_map(|transaction| match &transaction.data {
+            TransactionData::Governance(governance) => Some(governance),
+            _ => None,
+        });
+        for governance in governance.filter(|governance| validators.iter().any(|validator| validator.address == governance.validator)) {
+            match &governance.action {
+                GovernanceAction::Propose { parameter, value, activation_height } => {
+                    let id = governance.proposal_id();
+                    if proposals.iter().any(|proposal| proposal.id == id) {
+                        continue;
+                    }
+                    proposals.extend(Proposal::open(id, governance.validator, *parameter, *value, *activation_height, block_number));
+                }
+                GovernanceAction::Vote { proposal, approve } => {
+                    if let Some(proposal) = proposals.iter_mut()
+                        .find(|open| open.id == *proposal && open.status == ProposalStatus::Voting && block_number < open.voting_ends)
+                    {
+                        proposal.vote(governance.validator, *approve);
+                    } else if let Some(application) = applications.iter_mut()
+                        .find(|open| open.id == *proposal && block_number < open.voting_ends)
+                    {
+                        application.vote(governance.validator, *approve);
+                        applications_changed = true;
+                    }
+                }
+            }
+        }
+
+        let active = self.chain_parameters();
+        let mut parameters = active.clone();
+        proposals.retain_mut(|proposal| {
+            if proposal.status == ProposalStatus::Voting && block_number >= proposal.voting_ends {
+                if !proposal.is_accepted(validators) {
+                    return false;
+                }
+                proposal.status = ProposalStatus::Accepted;
+            }
+            if proposal.status == ProposalStatus::Accepted && block_number >= proposal.activation_height {
+                parameters.set(proposal.parameter, proposal.value);
+                return false;
+            }
+            true
+        });
+
+        if parameters != active {
+            self.insert(chain_parameters_key(), bincode::serialize(&parameters).expect("parameters are serializable"));
+        }
+        if applications_changed {
+            self.set_operator_applications(&applications);
+        }
+        if proposals.is_empty() {
+            self.remove(&governance_proposals_key());
+        } else {
+            self.insert(governance_proposals_key(), bincode::serialize(&proposals).expect("proposals are serializable"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_governance_parameter_change() {
+        use crate::blockchain::governance::{ChainParameter, GovernanceTransaction};
+        use crate::primitives::Policy;
+
+        let validators: Vec<ValidatorInfo> = [b"T-Mobile-DE".as_slice(), b"Vodafone-UK", b"Orange-FR"].iter()
+            .map(|name| ValidatorInfo {
+                address: Blake2bHash::from_data(name),
+                signing_key: vec![],
+                voting_key: vec![],
+                reward_address: Blake2bHash::from_data(name),
+                signal_data: None,
+                inactive_from: None,
+                jailed_from: None,
+                stake: 100,
+            })
+            .collect();
+        let transaction = |validator: &ValidatorInfo, action| Transaction {
+            sender: validator.address,
+            recipient: Blake2bHash::zero(),
+            value: 0,
+            fee: 0,
+            nonce: 0,
+            validity_start_height: 0,
+            data: TransactionData::Governance(GovernanceTransaction { validator: validator.address, action, signature: vec![] }),
+            signature: vec![],
+            signature_proof: vec![],
+        };
+
+        let activation_height = 10 + Policy::GOVERNANCE_VOTING_PERIOD + 5;
+        let propose = transaction(&validators[0], GovernanceAction::Propose {
+            parameter: ChainParameter::AutoAcceptThreshold,
+            value: 2_500,
+            activation_height,
+        });
+        let proposal = match &propose.data {
+            TransactionData::Governance(governance) => governance.proposal_id(),
+            _ => unreachable!(),
+        };
+        let mut trie = StateTrie::new();
+        trie.apply_governance(10, &validators, &[propose]);
+        trie.apply_governance(11, &validators, &[
+            transaction(&validators[1], GovernanceAction::Vote { proposal, approve: true }),
+            transaction(&validators[2], GovernanceAction::Vote { proposal, approve: true }),
+        ]);
+
+        // Accepted when the vote closes, in effect from the activation height
+        trie.apply_governance(10 + Policy::GOVERNANCE_VOTING_PERIOD, &validators, &[]);
+        assert_eq!(trie.governance_proposals()[0].status, ProposalStatus::Accepted);
+        assert_eq!(trie.chain_parameters().auto_accept_threshold_cents, None);
+        trie.apply_governance(activation_height, &validators, &[]);
+        assert_eq!(trie.chain_parameters().auto_accept_threshold_cents, Some(2_500));
+        assert!(trie.governance_proposals().is_empty());
+    }
+
+    #[test]
+    fn test_join_applications_are_voted_on() {
+        use crate::blockchain::governance::GovernanceTransaction;
+        use crate::blockchain::operator_registry::OperatorRecord;
+        use crate::primitives::Policy;
+
+        let validators: Vec<ValidatorInfo> = [b"T-Mobile-DE".as_slice(), b"Vodafone-UK", b"Orange-FR"].iter()
+            .map(|name| ValidatorInfo {
+                address: Blake2bHash::from_data(name),
+                signing_key: vec![],
+                voting_key: vec![],
+                reward_address: Blake2bHash::from_data(name),
+                signal_data: None,
+                inactive_from: None,
+                jailed_from: None,
+                stake: 100,
+            })
+            .collect();
+        let transaction = |sender: Blake2bHash, data| Transaction {
+            sender,
+            recipient: Blake2bHash::zero(),
+            value: 0,
+            fee: 0,
+            nonce: 0,
+            validity_start_height: 0,
+            data,
+            signature: vec![],
+            signature_proof: vec![],
+        };
+        let vote = |validator: &ValidatorInfo, proposal, approve| transaction(validator.address, TransactionData::Governance(
+            GovernanceTransaction { validator: validator.address, action: GovernanceAction::Vote { proposal, approve }, signature: vec![] }
+        ));
+        let join = |name: &str| NetworkJoinTransaction {
+            record: OperatorRecord {
+                name: name.to_string(),
+                display_name: name.to_string(),
+                country: "Norway".to_string(),
+                plmn_codes: vec!["24201".to_string()],
+                signing_key: vec![],
+                encryption_key: None,
+                version: 1,
+            },
+            operator_license: b"NKOM licence".to_vec(),
+            signature: vec![],
+            timestamp: 1_700_000_000,
+        };
+
+        let mut trie = StateTrie::new();
+        let telenor = join("Telenor-NO");
+        let telia = join("Telia-NO");
+        trie.apply_transactions(10, &[
+            transaction(Blake2bHash::from_data(b"Telenor-NO"), TransactionData::NetworkJoin(telenor.clone())),
+            transaction(Blake2bHash::from_data(b"Telia-NO"), TransactionData::NetworkJoin(telia.clone())),
+        ]);
+        // One open application per operator
+        trie.apply_network_join(&NetworkJoinTransaction { timestamp: 1_700_000_001, ..telenor.clone() }, 11);
+        assert_eq!(trie.operator_applications().len(), 2);
+
+        trie.apply_governance(11, &validators, &[
+            vote(&validators[0], telenor.application_id(), true),
+            vote(&validators[1], telenor.application_id(), true),
+            vote(&validators[2], telenor.application_id(), true),
+            vote(&validators[0], telia.application_id(), true),
+        ]);
+        assert_eq!(trie.operator_applications()[0].votes.len(), 3);
+
+        // Nothing closes before the vote ends, then only the application with a quorum is admitted
+        assert!(trie.close_applications(10 + Policy::GOVERNANCE_VOTING_PERIOD - 1, &validators).is_empty());
+        assert_eq!(trie.close_applications(10 + Policy::GOVERNANCE_VOTING_PERIOD, &validators), vec![telenor]);
+        assert!(trie.operator_applications().is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+// Validator rewards: the fee pool, macro block participation and the rewards paid out
+use crate::primitives::Blake2bHash;
+use crate::blockchain::block::{RewardPayoutTransaction, ValidatorInfo};
+use super::StateTrie;
+
+/// Trie key of the fees accrued since the last reward payout
+pub fn reward_pool_key() -> Blake2bHash {
+    Blake2bHash::from_data(b"validator-reward-pool")
+}
+
+/// Trie key of the macro blocks a validator helped finalize since the last reward payout
+pub fn participation_key(validator: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"validator-participation".to_vec();
+    data.extend_from_slice(validator.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the rewards paid to a reward address
+pub fn reward_balance_key(reward_address: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"validator-reward-balance".to_vec();
+    data.extend_from_slice(reward_address.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+impl StateTrie {
+    /// Fees accrued for validators since the last payout
+    pub fn reward_pool(&self) -> u64 {
+        self.get_u64(&reward_pool_key())
+    }
+
+    pub fn participation(&self, validator: &Blake2bHash) -> u64 {
+        self.get_u64(&participation_key(validator))
+    }
+
+    /// Rewards paid to `reward_address` so far
+    pub fn reward_balance(&self, reward_address: &Blake2bHash) -> u64 {
+        self.get_u64(&reward_balance_key(reward_address))
+    }
+
+    /// Count a macro block for the validators outside its lost reward set
+    pub fn record_participation(&mut self, validators: &[ValidatorInfo], lost_reward_set: &[Blake2bHash]) {
+        for validator in validators.iter().filter(|validator| !lost_reward_set.contains(&validator.address)) {
+            let count = self.participation(&validator.address) + 1;
+            self.set_u64(participation_key(&validator.address), count);
+        }
+    }
+
+    /// Credit reward addresses and start a new period for the paid validators
+    pub fn apply_reward_payout(&mut self, payout: &RewardPayoutTransaction) {
+        let paid: u64 = payout.payouts.iter().map(|payout| payout.amount).sum();
+        self.set_u64(reward_pool_key(), self.reward_pool().saturating_sub(paid));
+        for payout in &payout.payouts {
+            self.remove(&participation_key(&payout.validator));
+            let balance = self.reward_balance(&payout.reward_address).saturating_add(payout.amount);
+            self.set_u64(reward_balance_key(&payout.reward_address), balance);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// Validator registrations and bonded stake
+use std::collections::HashMap;
+
+use crate::primitives::{Blake2bHash, BlockchainError, Height, Result};
+use crate::blockchain::block::{Transaction, TransactionData, ValidatorAction, ValidatorTransaction};
+use crate::blockchain::staking::{ValidatorRecord, ValidatorStake};
+use super::StateTrie;
+
+/// Trie key of a validator's bonded and unbonding stake
+pub fn validator_stake_key(validator: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"validator-stake".to_vec();
+    data.extend_from_slice(validator.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of a validator's registration
+pub fn validator_record_key(validator: &Blake2bHash) -> Blake2bHash {
+    let mut data = b"validator-record".to_vec();
+    data.extend_from_slice(validator.as_bytes());
+    Blake2bHash::from_data(&data)
+}
+
+/// Trie key of the addresses of all registered validators
+pub fn validator_registry_key() -> Blake2bHash {
+    Blake2bHash::from_data(b"validator-registry")
+}
+
+impl StateTrie {
+    /// Stake of a validator, empty if it never bonded
+    pub fn validator_stake(&self, validator: &Blake2bHash) -> ValidatorStake {
+        self.get(&validator_stake_key(validator))
+            .and_then(|value| bincode::deserialize(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Registration of a validator, `None` if it was never created
+    pub fn validator_record(&self, validator: &Blake2bHash) -> Option<ValidatorRecord> {
+        self.get(&validator_record_key(validator)).and_then(|value| bincode::deserialize(value).ok())
+    }
+
+    /// Addresses of the registered validators, active or not
+    pub fn registered_validators(&self) -> Vec<Blake2bHash> {
+        self.get(&validator_registry_key())
+            .and_then(|value| bincode::deserialize(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether a validator may be elected: any validator until the first one registers,
+    /// then only registered validators that are active
+    pub fn is_electable(&self, validator: &Blake2bHash) -> bool {
+        match self.validator_record(validator) {
+            Some(record) => record.active,
+            None => self.get(&validator_registry_key()).is_none(),
+        }
+    }
+
+    /// Check the validator updates in `transactions` against the registrations on chain and those
+    /// before them in the list: a validator is created once, only updated, deactivated, reactivated
+    /// or unbonded by the account that created it, and only deactivated when active and the reverse
+    pub fn check_validator_updates(&self, transactions: &[Transaction]) -> Result<()> {
+        let mut records: HashMap<Blake2bHash, Option<ValidatorRecord>> = HashMap::new();
+        for transaction in transactions {
+            let TransactionData::ValidatorUpdate(update) = &transaction.data else {
+                continue;
+            };
+            let record = records.entry(update.validator_address)
+                .or_insert_with(|| self.validator_record(&update.validator_address));
+            let invalid = |reason: &str| Err(BlockchainError::InvalidTransaction(format!(
+                "Validator update {} of {}: {}", transaction.hash(), update.validator_address, reason
+            )));
+
+            match (&update.action, record.as_mut()) {
+                (ValidatorAction::CreateValidator, Some(_)) => return invalid("validator already exists"),
+                (ValidatorAction::CreateValidator, None) => {
+                    *record = Some(ValidatorRecord { controller: transaction.sender, registered_at: 0, active: true });
+                }
+                (ValidatorAction::Bond, _) | (ValidatorAction::Unbond, None) | (ValidatorAction::RotateSigningKey { .. }, None) => {}
+                (_, None) => return invalid("validator does not exist"),
+                (_, Some(existing)) if existing.controller != transaction.sender => {
+                    return invalid(&format!("sent by {}, not by its controller {}", transaction.sender, existing.controller));
+                }
+                (ValidatorAction::DeactivateValidator, Some(existing)) => {
+                    if !existing.active {
+                        return invalid("validator is already inactive");
+                    }
+                    existing.active = false;
+                }
+                (ValidatorAction::ReactivateValidator, Some(existing)) => {
+                    if existing.active {
+                        return invalid("validator is already active");
+                    }
+                    existing.active = true;
+                }
+                (_, Some(_)) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply a validator update sent by `sender` in the block at `block_number`, releasing unbondings that matured
+    /// Registrations and stake change right away; elections pick them up from the next election block
+    pub fn apply_validator_update(&mut self, sender: &Blake2bHash, update: &ValidatorTransaction, block_number: Height) {
+        let mut stake = self.validator_stake(&update.validator_address);
+        stake.release(block_number);
+        match update.action {
+            ValidatorAction::CreateValidator => {
+                self.register_validator(&update.validator_address, ValidatorRecord {
+                    controller: *sender,
+                    registered_at: block_number,
+                    active: true,
+                });
+                stake.bond(update.stake);
+            }
+            // Move the bonded stake to `stake`
+            ValidatorAction::UpdateValidator => {
+                if update.stake > stake.bonded {
+                    stake.bond(update.stake - stake.bonded);
+                } else {
+                    stake.unbond(stake.bonded - update.stake, block_number);
+                }
+            }
+            ValidatorAction::DeactivateValidator | ValidatorAction::ReactivateValidator => {
+                if let Some(mut record) = self.validator_record(&update.validator_address) {
+                    record.active = update.action == ValidatorAction::ReactivateValidator;
+                    self.insert(validator_record_key(&update.validator_address), bincode::serialize(&record).expect("records are serializable"));
+                }
+                return;
+            }
+            ValidatorAction::Bond => stake.bond(update.stake),
+            ValidatorAction::Unbond => {
+                stake.unbond(update.stake, block_number);
+            }
+            ValidatorAction::RotateSigningKey { .. } => return,
+        }
+
+        let key = validator_stake_key(&update.validator_address);
+        if stake.is_empty() {
+            self.remove(&key);
+        } else {
+            self.insert(key, bincode::serialize(&stake).expect("stake is serializable"));
+        }
+    }
+
+    fn register_validator(&mut self, validator: &Blake2bHash, record: ValidatorRecord) {
+        let mut registry = self.registered_validators();
+        if !registry.contains(validator) {
+            registry.push(*validator);
+            registry.sort_by_key(|address| address.0);
+            self.insert(validator_registry_key(), bincode::serialize(&registry).expect("registry is serializable"));
+        }
+        self.insert(validator_record_key(validator), bincode::serialize(&record).expect("records are serializable"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bond_and_unbond() {
+        let validator = Blake2bHash::from_data(b"validator");
+        let update = |action, stake| ValidatorTransaction { action, validator_address: validator, stake };
+
+        let sender = Blake2bHash::from_data(b"T-Mobile-DE");
+        let mut trie = StateTrie::new();
+        trie.apply_validator_update(&sender, &update(ValidatorAction::Bond, 5_000), 1);
+        trie.apply_validator_update(&sender, &update(ValidatorAction::Unbond, 2_000), 2);
+        let stake = trie.validator_stake(&validator);
+        assert_eq!(stake.bonded, 3_000);
+        assert_eq!(stake.unbonding[0].release_at, 2 + crate::primitives::Policy::UNBONDING_PERIOD);
+
+        // Unbonded stake is released by the first update after its period
+        trie.apply_validator_update(&sender, &update(ValidatorAction::Unbond, 3_000), 3 + crate::primitives::Policy::UNBONDING_PERIOD);
+        assert_eq!(trie.validator_stake(&validator).unbonding.len(), 1);
+        assert_eq!(trie.validator_stake(&validator).bonded, 0);
+    }
+
+    #[test]
+    fn test_validator_lifecycle() {
+        let validator = Blake2bHash::from_data(b"validator");
+        let controller = Blake2bHash::from_data(b"T-Mobile-DE");
+        let stranger = Blake2bHash::from_data(b"Vodafone-UK");
+        let transaction = |sender, nonce, action, stake| Transaction {
+            sender,
+            recipient: Blake2bHash::zero(),
+            value: 0,
+            fee: 0,
+            nonce,
+            validity_start_height: 0,
+            data: TransactionData::ValidatorUpdate(ValidatorTransaction { action, validator_address: validator, stake }),
+            signature: vec![],
+            signature_proof: vec![],
+        };
+
+        // Every validator is electable until one registers
+        let mut trie = StateTrie::new();
+        let other = Blake2bHash::from_data(b"other");
+        assert!(trie.is_electable(&other));
+        assert!(trie.check_validator_updates(&[transaction(controller, 0, ValidatorAction::UpdateValidator, 0)]).is_err());
+        let create = transaction(controller, 0, ValidatorAction::CreateValidator, 4_000);
+        assert!(trie.check_validator_updates(&[create.clone(), create.clone()]).is_err());
+        assert!(trie.check_validator_updates(&[create.clone()]).is_ok());
+        trie.apply_transactions(1, &[create.clone()]);
+        assert_eq!(trie.registered_validators(), vec![validator]);
+        assert_eq!(trie.validator_stake(&validator).bonded, 4_000);
+        assert!(trie.is_electable(&validator));
+        assert!(!trie.is_electable(&other));
+        assert!(trie.check_validator_updates(&[create]).is_err());
+
+        // Only the controller changes the stake or deactivates the validator
+        let update = transaction(controller, 1, ValidatorAction::UpdateValidator, 1_000);
+        assert!(trie.check_validator_updates(&[transaction(stranger, 0, ValidatorAction::UpdateValidator, 1_000)]).is_err());
+        assert!(trie.check_validator_updates(&[transaction(stranger, 0, ValidatorAction::Unbond, 1_000)]).is_err());
+        assert!(trie.check_validator_updates(&[update.clone()]).is_ok());
+        trie.apply_transactions(2, &[update]);
+        let stake = trie.validator_stake(&validator);
+        assert_eq!((stake.bonded, stake.unbonding[0].amount), (1_000, 3_000));
+
+        let deactivate = transaction(controller, 2, ValidatorAction::DeactivateValidator, 0);
+        let reactivate = transaction(controller, 3, ValidatorAction::ReactivateValidator, 0);
+        assert!(trie.check_validator_updates(&[reactivate.clone()]).is_err());
+        assert!(trie.check_validator_updates(&[deactivate.clone(), deactivate.clone()]).is_err());
+        assert!(trie.check_validator_updates(&[deactivate.clone(), reactivate.clone()]).is_ok());
+        trie.apply_transactions(3, &[deactivate]);
+        assert!(!trie.is_electable(&validator));
+        trie.apply_transactions(4, &[reactivate]);
+        assert!(trie.is_electable(&validator));
+    }
+}
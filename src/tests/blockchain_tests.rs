@@ -50,6 +50,7 @@ fn test_macro_block_validator_updates() {
     ];
     
     let macro_block = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -93,6 +94,7 @@ fn test_transaction_types() {
         recipient: Blake2bHash::from_bytes([20u8; 32]),
         value: 0,
         fee: 10,
+        nonce: 0,
         validity_start_height: 100,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::DataSession,
@@ -111,6 +113,7 @@ fn test_transaction_types() {
         recipient: Blake2bHash::from_bytes([40u8; 32]),
         value: 0,
         fee: 5,
+        nonce: 0,
         validity_start_height: 200,
         data: blockchain::TransactionData::Settlement(blockchain::SettlementTransaction {
             creditor_network: "Vodafone-UK".to_string(),
@@ -129,6 +132,7 @@ fn test_transaction_types() {
         recipient: Blake2bHash::from_bytes([60u8; 32]),
         value: 1000000, // 1M stake
         fee: 100,
+        nonce: 0,
         validity_start_height: 300,
         data: blockchain::TransactionData::ValidatorUpdate(blockchain::ValidatorTransaction {
             action: blockchain::ValidatorAction::CreateValidator,
@@ -201,6 +205,7 @@ fn test_block_validation_rules() {
     });
     
     let valid_macro = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
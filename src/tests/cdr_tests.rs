@@ -9,6 +9,7 @@ fn test_cdr_transaction_types() {
         recipient: Blake2bHash::from_bytes([2u8; 32]),
         value: 0,
         fee: 10,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::VoiceCall,
@@ -26,6 +27,7 @@ fn test_cdr_transaction_types() {
         recipient: Blake2bHash::from_bytes([4u8; 32]),
         value: 0,
         fee: 15,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::DataSession,
@@ -43,6 +45,7 @@ fn test_cdr_transaction_types() {
         recipient: Blake2bHash::from_bytes([6u8; 32]),
         value: 0,
         fee: 5,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::SMS,
@@ -60,6 +63,7 @@ fn test_cdr_transaction_types() {
         recipient: Blake2bHash::from_bytes([8u8; 32]),
         value: 0,
         fee: 20,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::Roaming,
@@ -156,6 +160,7 @@ fn test_validator_actions() {
         recipient: Blake2bHash::from_bytes([11u8; 32]),
         value: 2000000, // 2M stake
         fee: 1000,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::ValidatorUpdate(blockchain::ValidatorTransaction {
             action: blockchain::ValidatorAction::CreateValidator,
@@ -171,6 +176,7 @@ fn test_validator_actions() {
         recipient: Blake2bHash::from_bytes([13u8; 32]),
         value: 0,
         fee: 100,
+        nonce: 0,
         validity_start_height: 100,
         data: blockchain::TransactionData::ValidatorUpdate(blockchain::ValidatorTransaction {
             action: blockchain::ValidatorAction::UpdateValidator,
@@ -186,6 +192,7 @@ fn test_validator_actions() {
         recipient: Blake2bHash::from_bytes([15u8; 32]),
         value: 0,
         fee: 50,
+        nonce: 0,
         validity_start_height: 200,
         data: blockchain::TransactionData::ValidatorUpdate(blockchain::ValidatorTransaction {
             action: blockchain::ValidatorAction::DeactivateValidator,
@@ -201,6 +208,7 @@ fn test_validator_actions() {
         recipient: Blake2bHash::from_bytes([17u8; 32]),
         value: 1500000, // Restake
         fee: 200,
+        nonce: 0,
         validity_start_height: 300,
         data: blockchain::TransactionData::ValidatorUpdate(blockchain::ValidatorTransaction {
             action: blockchain::ValidatorAction::ReactivateValidator,
@@ -258,6 +266,7 @@ fn test_sp_network_interoperability() {
                     recipient: Blake2bHash::from_bytes([j as u8; 32]),
                     value: 0,
                     fee: 10,
+                    nonce: 0,
                     validity_start_height: 0,
                     data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
                         record_type: blockchain::CDRType::Roaming,
@@ -303,6 +312,7 @@ fn test_cdr_block_aggregation() {
             recipient: Blake2bHash::from_bytes([i as u8 + 100; 32]),
             value: 0,
             fee: 5 + (i % 10) as u64, // Variable fees
+            nonce: 0,
             validity_start_height: 0,
             data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
                 record_type: match i % 4 {
@@ -396,6 +406,7 @@ fn test_daily_settlement_aggregation() {
             recipient: Blake2bHash::from_bytes([88u8; 32]),
             value: 0,
             fee: 20,
+            nonce: 0,
             validity_start_height: 0,
             data: blockchain::TransactionData::Settlement(settlement),
             signature: b"settlement_batch_sig".to_vec(),
@@ -405,6 +416,7 @@ fn test_daily_settlement_aggregation() {
     
     // Create macro block with daily settlements
     let settlement_macro_block = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -462,6 +474,7 @@ fn test_privacy_and_zk_proof_structure() {
         recipient: Blake2bHash::from_bytes([201u8; 32]),
         value: 0,
         fee: 25,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::DataSession,
@@ -15,6 +15,7 @@ struct MockBlockchain {
 impl MockBlockchain {
     fn new() -> Self {
         let genesis_block = Block::Macro(MacroBlock {
+            justification: None,
             header: blockchain::MacroHeader {
                 network: NetworkId::SPConsortium,
                 version: 1,
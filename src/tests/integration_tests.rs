@@ -157,6 +157,7 @@ async fn test_consensus_blockchain_integration() {
     }
     
     let genesis_block = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -220,6 +221,7 @@ async fn test_transaction_block_integration() {
         recipient: Blake2bHash::from_bytes([20u8; 32]),
         value: 1000,
         fee: 10,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::VoiceCall,
@@ -270,6 +272,7 @@ async fn test_macro_micro_block_chain() {
     
     // Create genesis macro block
     let genesis_macro = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -338,6 +341,7 @@ async fn test_cdr_settlement_integration() {
         recipient: Blake2bHash::from_bytes([40u8; 32]),
         value: 0, // Settlement transaction
         fee: 5,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::Settlement(blockchain::SettlementTransaction {
             creditor_network: "SP_B".to_string(),
@@ -352,6 +356,7 @@ async fn test_cdr_settlement_integration() {
     
     // Settlements typically go in macro blocks
     let macro_block = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -147,6 +147,7 @@ async fn test_large_transaction_blocks() {
                 recipient: Blake2bHash::from_bytes([tx_num as u8 + 128; 32]),
                 value: 0,
                 fee: (tx_num % 50) as u64 + 1,
+                nonce: 0,
                 validity_start_height: 0,
                 data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
                     record_type: match tx_num % 4 {
@@ -262,6 +263,7 @@ fn test_serialization_performance() {
             recipient: Blake2bHash::from_bytes([i + 1; 32]),
             value: i as u64 * 1000,
             fee: i as u64 + 10,
+            nonce: 0,
             validity_start_height: i,
             data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
                 record_type: blockchain::CDRType::DataSession,
@@ -346,6 +348,7 @@ async fn test_memory_usage() {
                 recipient: Blake2bHash::from_bytes([j as u8; 32]),
                 value: 0,
                 fee: 10,
+                nonce: 0,
                 validity_start_height: 0,
                 data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
                     record_type: blockchain::CDRType::DataSession,
@@ -434,4 +437,70 @@ fn test_policy_compliance_performance() {
     let duration = start_time.elapsed();
     println!("✅ Performed 100k policy compliance checks in {:?}", duration);
     println!("   Average: {:.2} checks/sec", 100000.0 / duration.as_secs_f64());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_batch_zk_proving_performance() {
+    // Benchmark batch CDR proving: one Groth16 proof per CDR_BATCH_SIZE records
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::Groth16;
+    use ark_serialize::CanonicalSerialize;
+    use ark_snark::SNARK;
+    use zkp::circuits::{CDRBatchCircuit, CDRBatchRecord, CDR_BATCH_SIZE};
+    use zkp::{AlbatrossZKProver, AlbatrossZKVerifier};
+
+    let mut rng = ark_std::test_rng();
+
+    let setup_start = Instant::now();
+    let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(CDRBatchCircuit::<Fr>::empty(), &mut rng).unwrap();
+    println!("✅ Batch circuit setup in {:?}", setup_start.elapsed());
+
+    let mut pk_bytes = Vec::new();
+    pk.serialize_compressed(&mut pk_bytes).unwrap();
+    let mut vk_bytes = Vec::new();
+    vk.serialize_compressed(&mut vk_bytes).unwrap();
+
+    let mut prover = AlbatrossZKProver::new();
+    prover.load_cdr_batch_proving_key(&pk_bytes).unwrap();
+    let mut verifier = AlbatrossZKVerifier::new();
+    verifier.load_cdr_batch_verifying_key(&vk_bytes).unwrap();
+
+    // Four full batches of realistic roaming records
+    let num_records = CDR_BATCH_SIZE * 4;
+    let records: Vec<CDRBatchRecord> = (0..num_records as u64).map(|i| {
+        let call_minutes = i % 120;
+        let data_mb = (i * 37) % 2048;
+        CDRBatchRecord {
+            call_minutes,
+            data_mb,
+            sms_count: 1,
+            call_rate_cents: 15,
+            data_rate_cents: 5,
+            sms_rate_cents: i % 10,
+            remainder_cents: 0,
+            total_charges_cents: call_minutes * 15 + data_mb * 5 + i % 10,
+        }
+    }).collect();
+
+    for threads in [1, 4] {
+        prover.set_proving_threads(threads).unwrap();
+
+        let start_time = Instant::now();
+        let proofs = prover.generate_cdr_batch_proofs(&mut rng, &records, 1704067200, 42).unwrap();
+        let duration = start_time.elapsed();
+
+        assert_eq!(proofs.len(), 4);
+        println!("✅ Proved {} records in {} batch proofs with {} threads in {:?}",
+                 num_records, proofs.len(), threads, duration);
+        println!("   Average: {:.2} records/sec", num_records as f64 / duration.as_secs_f64());
+
+        for proof in &proofs {
+            assert!(verifier.verify_cdr_batch_proof(proof).unwrap());
+        }
+    }
+
+    // Tampered totals must not verify
+    let mut proofs = prover.generate_cdr_batch_proofs(&mut rng, &records[..10], 1704067200, 42).unwrap();
+    proofs[0].total_charges_cents += 1;
+    assert!(!verifier.verify_cdr_batch_proof(&proofs[0]).unwrap());
+}
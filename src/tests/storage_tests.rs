@@ -58,6 +58,7 @@ async fn test_block_storage_and_retrieval() {
     });
     
     let macro_block = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -160,6 +161,7 @@ async fn test_macro_and_election_heads() {
     
     // Create macro blocks
     let macro_block_32 = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -184,6 +186,7 @@ async fn test_macro_and_election_heads() {
     
     // Election block (every 256 blocks)
     let election_block_256 = Block::Macro(MacroBlock {
+        justification: None,
         header: blockchain::MacroHeader {
             network: NetworkId::SPConsortium,
             version: 1,
@@ -245,6 +248,7 @@ async fn test_block_with_transactions_storage() {
         recipient: Blake2bHash::from_bytes([20u8; 32]),
         value: 0,
         fee: 10,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::CDRRecord(blockchain::CDRTransaction {
             record_type: blockchain::CDRType::VoiceCall,
@@ -263,6 +267,7 @@ async fn test_block_with_transactions_storage() {
         recipient: Blake2bHash::from_bytes([40u8; 32]),
         value: 0,
         fee: 5,
+        nonce: 0,
         validity_start_height: 0,
         data: blockchain::TransactionData::Settlement(blockchain::SettlementTransaction {
             creditor_network: "Vodafone-UK".to_string(),
@@ -0,0 +1,206 @@
+// Groth16 proof aggregation for block-level CDR proof verification
+// Folds many proofs under one verifying key into a single randomized
+// multi-pairing check, so validators pay one final exponentiation per block
+// instead of one full pairing check per proof
+use ark_bn254::{Bn254, Fr, G1Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField, Zero};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::primitives::{Result, BlockchainError, Blake2bHash};
+
+/// Aggregate of Groth16 proofs for one circuit, as carried in a block
+///
+/// Verification checks
+///   Π e(r_i·A_i, B_i) · e(Σ r_i·IC(x_i), -γ) · e(Σ r_i·C_i, -δ) = e(α, β)^(Σ r_i)
+/// with r_i derived from a hash over every proof and public input, so a prover
+/// cannot choose proofs whose errors cancel out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AggregateProof {
+    /// Circuit the proofs belong to (key into the verifier's prepared keys)
+    pub circuit_id: String,
+    /// Compressed Groth16 proofs
+    pub proofs: Vec<Vec<u8>>,
+    /// Compressed public inputs, one vector per proof
+    pub public_inputs: Vec<Vec<Vec<u8>>>,
+}
+
+impl AggregateProof {
+    pub fn new(circuit_id: &str) -> Self {
+        Self {
+            circuit_id: circuit_id.to_string(),
+            proofs: Vec::new(),
+            public_inputs: Vec::new(),
+        }
+    }
+
+    /// Add a proof and its public inputs to the aggregate
+    pub fn add(&mut self, proof_bytes: Vec<u8>, public_inputs: &[Fr]) -> Result<()> {
+        // Reject malformed proofs at aggregation time rather than at block validation
+        Proof::<Bn254>::deserialize_compressed(&proof_bytes[..])
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        let mut inputs = Vec::with_capacity(public_inputs.len());
+        for input in public_inputs {
+            let mut bytes = Vec::new();
+            input.serialize_compressed(&mut bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("Public input serialization error: {}", e)))?;
+            inputs.push(bytes);
+        }
+
+        self.proofs.push(proof_bytes);
+        self.public_inputs.push(inputs);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// Commitment over every proof and public input in the aggregate
+    pub fn transcript_hash(&self) -> Blake2bHash {
+        let mut data = Vec::new();
+        data.extend_from_slice(self.circuit_id.as_bytes());
+        data.extend_from_slice(&(self.proofs.len() as u64).to_le_bytes());
+        for (proof, inputs) in self.proofs.iter().zip(&self.public_inputs) {
+            data.extend_from_slice(&(proof.len() as u64).to_le_bytes());
+            data.extend_from_slice(proof);
+            data.extend_from_slice(&(inputs.len() as u64).to_le_bytes());
+            for input in inputs {
+                data.extend_from_slice(input);
+            }
+        }
+        crate::primitives::primitives::hash_data(&data)
+    }
+
+    /// Verify every proof in the aggregate with one multi-pairing
+    pub fn verify(&self, pvk: &PreparedVerifyingKey<Bn254>) -> Result<bool> {
+        if self.is_empty() {
+            return Err(BlockchainError::InvalidOperation("Cannot verify an empty proof aggregate".to_string()));
+        }
+        if self.proofs.len() != self.public_inputs.len() {
+            return Ok(false);
+        }
+
+        let transcript = self.transcript_hash();
+
+        let mut g1 = Vec::with_capacity(self.proofs.len() + 2);
+        let mut g2 = Vec::with_capacity(self.proofs.len() + 2);
+        let mut inputs_acc = G1Projective::zero();
+        let mut c_acc = G1Projective::zero();
+        let mut r_sum = Fr::zero();
+
+        for (i, (proof_bytes, input_bytes)) in self.proofs.iter().zip(&self.public_inputs).enumerate() {
+            let proof = Proof::<Bn254>::deserialize_compressed(&proof_bytes[..])
+                .map_err(|_| BlockchainError::InvalidProof)?;
+
+            let inputs = input_bytes.iter()
+                .map(|bytes| Fr::deserialize_compressed(&bytes[..]))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|_| BlockchainError::InvalidProof)?;
+
+            let prepared_inputs = match Groth16::<Bn254>::prepare_inputs(pvk, &inputs) {
+                Ok(prepared) => prepared,
+                Err(_) => return Ok(false), // Wrong number of public inputs
+            };
+
+            let r = Self::challenge(&transcript, i);
+            r_sum += r;
+
+            g1.push((proof.a * r).into_affine());
+            g2.push(<Bn254 as Pairing>::G2Prepared::from(proof.b));
+            inputs_acc += prepared_inputs * r;
+            c_acc += proof.c.into_group() * r;
+        }
+
+        g1.push(inputs_acc.into_affine());
+        g2.push(pvk.gamma_g2_neg_pc.clone());
+        g1.push(c_acc.into_affine());
+        g2.push(pvk.delta_g2_neg_pc.clone());
+
+        let miller_loop = Bn254::multi_miller_loop(g1, g2);
+        let result = match Bn254::final_exponentiation(miller_loop) {
+            Some(result) => result,
+            None => return Ok(false),
+        };
+
+        let expected = pvk.alpha_g1_beta_g2.pow(r_sum.into_bigint());
+        let is_valid = result.0 == expected;
+
+        debug!("Aggregate verification of {} {} proofs: {}", self.proofs.len(), self.circuit_id, is_valid);
+        Ok(is_valid)
+    }
+
+    /// Fiat-Shamir challenge for proof `index`, 128 bits and never zero
+    fn challenge(transcript: &Blake2bHash, index: usize) -> Fr {
+        let mut data = Vec::with_capacity(40);
+        data.extend_from_slice(transcript.as_bytes());
+        data.extend_from_slice(&(index as u64).to_le_bytes());
+        let digest = crate::primitives::primitives::hash_data(&data);
+
+        let r = Fr::from_le_bytes_mod_order(&digest.as_bytes()[..16]);
+        if r.is_zero() { Fr::from(1u64) } else { r }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_groth16::prepare_verifying_key;
+    use ark_snark::SNARK;
+    use ark_std::rand::rngs::StdRng;
+    use crate::zkp::circuits::{CDRCharges, CDRPrivacyCircuit, ServiceCharge};
+
+    fn proof_for(pk: &ark_groth16::ProvingKey<Bn254>, minutes: u64, rng: &mut StdRng) -> (Vec<u8>, Vec<Fr>) {
+        // minutes × 15 + 100 MB × 5 + 1 SMS × 10
+        let charges = CDRCharges {
+            voice: ServiceCharge::of(minutes, minutes * 15),
+            data: ServiceCharge::of(100, 500),
+            sms: ServiceCharge::of(1, 10),
+            ..Default::default()
+        };
+        let circuit = CDRPrivacyCircuit::<Fr>::new(&charges, 7, 2024, 42, 99);
+        let proof = Groth16::<Bn254>::prove(pk, circuit, rng).unwrap();
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        (bytes, CDRPrivacyCircuit::<Fr>::public_inputs(charges.net_cents(), 2024, 42))
+    }
+
+    #[test]
+    fn test_aggregate_verification() {
+        let mut rng = ark_std::test_rng();
+        let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(CDRPrivacyCircuit::<Fr>::empty(), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&vk);
+
+        let mut aggregate = AggregateProof::new("cdr_privacy");
+        for minutes in [10, 20, 30, 40] {
+            let (proof, inputs) = proof_for(&pk, minutes, &mut rng);
+            aggregate.add(proof, &inputs).unwrap();
+        }
+
+        assert_eq!(aggregate.len(), 4);
+        assert!(aggregate.verify(&pvk).unwrap());
+
+        // One wrong public input invalidates the aggregate
+        let mut tampered = aggregate.clone();
+        let mut bytes = Vec::new();
+        Fr::from(1u64).serialize_compressed(&mut bytes).unwrap();
+        tampered.public_inputs[2][0] = bytes;
+        assert!(!tampered.verify(&pvk).unwrap());
+
+        // Swapping proofs between statements invalidates the aggregate
+        let mut swapped = aggregate.clone();
+        swapped.proofs.swap(0, 1);
+        assert!(!swapped.verify(&pvk).unwrap());
+
+        assert!(AggregateProof::new("cdr_privacy").verify(&pvk).is_err());
+    }
+}
@@ -56,15 +56,18 @@ pub struct AlbatrossZKVerifier {
     prepared_vks: HashMap<String, ark_groth16::PreparedVerifyingKey<Bn254>>,
 }
 
-/// CDR settlement proof public inputs (from Albatross nano proof structure)
-#[derive(Debug, Clone)]
+/// CDR settlement proof public inputs: the netting outcome of a settlement period
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CDRSettlementInputs {
-    pub creditor_total: u64,
-    pub debtor_total: u64,
-    pub exchange_rate: u32,
-    pub net_settlement: u64,
+    pub totals: crate::zkp::circuits::SettlementTotals,
     pub period_commitment: Blake2bHash,
-    pub network_pair_commitment: Blake2bHash,
+}
+
+impl CDRSettlementInputs {
+    /// Period hash the circuit takes, the commitment's first 8 bytes
+    pub fn period_hash(&self) -> u64 {
+        u64::from_le_bytes(self.period_commitment.as_bytes()[0..8].try_into().unwrap_or([0u8; 8]))
+    }
 }
 
 /// CDR privacy proof inputs (adapted from Albatross history proof)
@@ -76,6 +79,18 @@ pub struct CDRPrivacyProofInputs {
     pub network_authorization_hash: Blake2bHash,
 }
 
+/// Batch CDR privacy proof covering up to CDR_BATCH_SIZE records
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CDRBatchProof {
+    pub proof: Vec<u8>,
+    /// MiMC Merkle root over the batch's record leaves
+    pub records_root: Blake2bHash,
+    pub record_count: u32,
+    pub total_charges_cents: u64,
+    pub period_hash: u64,
+    pub network_pair_hash: u64,
+}
+
 impl AlbatrossZKVerifier {
     pub fn new() -> Self {
         Self {
@@ -103,24 +118,36 @@ impl AlbatrossZKVerifier {
         Ok(verifier)
     }
 
-    /// Load keys from a completed trusted setup ceremony
+    /// Load verifying keys from a completed trusted setup ceremony, leaving its proving keys on disk
     pub async fn load_keys_from_ceremony(&mut self, ceremony: &TrustedSetupCeremony) -> Result<()> {
         // Load CDR privacy keys
-        if ceremony.keys_exist("cdr_privacy").await {
-            let (_, vk) = ceremony.load_circuit_keys("cdr_privacy").await?;
+        if ceremony.verifying_key_exists("cdr_privacy").await {
+            let vk = ceremony.load_verifying_key("cdr_privacy").await?;
             let prepared_vk = prepare_verifying_key(&vk);
             self.prepared_vks.insert("cdr_privacy".to_string(), prepared_vk);
             self.cdr_privacy_vk = Some(vk);
         }
 
         // Load settlement keys
-        if ceremony.keys_exist("settlement_calculation").await {
-            let (_, vk) = ceremony.load_circuit_keys("settlement_calculation").await?;
+        if ceremony.verifying_key_exists("settlement_calculation").await {
+            let vk = ceremony.load_verifying_key("settlement_calculation").await?;
             let prepared_vk = prepare_verifying_key(&vk);
             self.prepared_vks.insert("settlement".to_string(), prepared_vk);
             self.settlement_vk = Some(vk);
         }
 
+        // Load netting correctness verifying key
+        if ceremony.verifying_key_exists("netting_correctness").await {
+            let vk = ceremony.load_verifying_key("netting_correctness").await?;
+            self.prepared_vks.insert("netting".to_string(), prepare_verifying_key(&vk));
+        }
+
+        // Load batch CDR privacy verifying key
+        if ceremony.verifying_key_exists("cdr_batch_privacy").await {
+            let vk = ceremony.load_verifying_key("cdr_batch_privacy").await?;
+            self.prepared_vks.insert("cdr_batch".to_string(), prepare_verifying_key(&vk));
+        }
+
         Ok(())
     }
 
@@ -192,40 +219,103 @@ impl AlbatrossZKVerifier {
         Ok(is_valid)
     }
 
+    /// Verify netting correctness proof against the published net positions
+    pub fn verify_netting_proof(
+        &self,
+        proof_bytes: &[u8],
+        net_positions: &[i64],
+        gross_total: u64,
+        residual_total: u64,
+    ) -> Result<bool> {
+        let prepared_vk = self.prepared_vks.get("netting")
+            .ok_or_else(|| BlockchainError::InvalidProof)?;
+
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        let public_inputs = crate::zkp::circuits::NettingCorrectnessCircuit::<ark_bn254::Fr>::public_inputs(
+            net_positions, gross_total, residual_total,
+        ).map_err(|_| BlockchainError::InvalidProof)?;
+
+        let is_valid = Groth16::<Bn254>::verify_proof(prepared_vk, &proof, &public_inputs)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        Ok(is_valid)
+    }
+
+    /// Verify batch CDR privacy proof
+    pub fn verify_cdr_batch_proof(&self, batch: &CDRBatchProof) -> Result<bool> {
+        let prepared_vk = self.prepared_vks.get("cdr_batch")
+            .ok_or_else(|| BlockchainError::InvalidProof)?;
+
+        let proof = Proof::<Bn254>::deserialize_compressed(&batch.proof[..])
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        let public_inputs = self.prepare_batch_public_inputs(batch);
+
+        let is_valid = Groth16::<Bn254>::verify_proof(prepared_vk, &proof, &public_inputs)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        Ok(is_valid)
+    }
+
+    /// Load batch CDR privacy verifying key from bytes
+    pub fn load_cdr_batch_verifying_key(&mut self, vk_bytes: &[u8]) -> Result<()> {
+        let vk = VerifyingKey::<Bn254>::deserialize_compressed(vk_bytes)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+        self.prepared_vks.insert("cdr_batch".to_string(), prepare_verifying_key(&vk));
+        Ok(())
+    }
+
     /// Batch verify multiple proofs (Albatross optimization for multiple CDR batches)
+    /// All proofs are folded into one aggregate multi-pairing check
     pub fn batch_verify_cdr_proofs(
         &self,
         proofs_and_inputs: &[(Vec<u8>, CDRPrivacyProofInputs)],
     ) -> Result<bool> {
-        let prepared_vk = self.prepared_vks.get("cdr_privacy")
-            .ok_or_else(|| BlockchainError::InvalidProof)?;
-
-        for (proof_bytes, inputs) in proofs_and_inputs {
-            if !self.verify_cdr_privacy_proof(proof_bytes, inputs)? {
-                return Ok(false);
-            }
+        if proofs_and_inputs.is_empty() {
+            return Ok(true);
         }
 
-        Ok(true)
+        let aggregate = self.aggregate_cdr_privacy_proofs(proofs_and_inputs)?;
+        self.verify_aggregate_proof(&aggregate)
     }
 
-    // Private helper methods
-    fn prepare_settlement_public_inputs(&self, inputs: &CDRSettlementInputs) -> Result<Vec<ark_bn254::Fr>> {
-        use ark_ff::PrimeField;
+    /// Build an aggregate of CDR privacy proofs for block inclusion
+    pub fn aggregate_cdr_privacy_proofs(
+        &self,
+        proofs_and_inputs: &[(Vec<u8>, CDRPrivacyProofInputs)],
+    ) -> Result<crate::zkp::aggregation::AggregateProof> {
+        let mut aggregate = crate::zkp::aggregation::AggregateProof::new("cdr_privacy");
+        for (proof_bytes, inputs) in proofs_and_inputs {
+            aggregate.add(proof_bytes.clone(), &self.prepare_privacy_public_inputs(inputs)?)?;
+        }
+        Ok(aggregate)
+    }
 
-        let mut public_inputs = Vec::new();
+    /// Build an aggregate of batch CDR privacy proofs for block inclusion
+    pub fn aggregate_cdr_batch_proofs(&self, batches: &[CDRBatchProof]) -> Result<crate::zkp::aggregation::AggregateProof> {
+        let mut aggregate = crate::zkp::aggregation::AggregateProof::new("cdr_batch");
+        for batch in batches {
+            aggregate.add(batch.proof.clone(), &self.prepare_batch_public_inputs(batch))?;
+        }
+        Ok(aggregate)
+    }
 
-        // Convert settlement data to field elements (Albatross style)
-        public_inputs.push(ark_bn254::Fr::from(inputs.creditor_total));
-        public_inputs.push(ark_bn254::Fr::from(inputs.debtor_total));
-        public_inputs.push(ark_bn254::Fr::from(inputs.exchange_rate as u64));
-        public_inputs.push(ark_bn254::Fr::from(inputs.net_settlement));
+    /// Verify an aggregate of proofs with a single multi-pairing
+    pub fn verify_aggregate_proof(&self, aggregate: &crate::zkp::aggregation::AggregateProof) -> Result<bool> {
+        let prepared_vk = self.prepared_vks.get(&aggregate.circuit_id)
+            .ok_or_else(|| BlockchainError::InvalidProof)?;
 
-        // Convert Blake2b hashes to field elements
-        public_inputs.push(self.hash_to_field_element(&inputs.period_commitment)?);
-        public_inputs.push(self.hash_to_field_element(&inputs.network_pair_commitment)?);
+        aggregate.verify(prepared_vk)
+    }
 
-        Ok(public_inputs)
+    // Private helper methods
+    fn prepare_settlement_public_inputs(&self, inputs: &CDRSettlementInputs) -> Result<Vec<ark_bn254::Fr>> {
+        Ok(crate::zkp::circuits::SettlementCalculationCircuit::<ark_bn254::Fr>::public_inputs(
+            &inputs.totals,
+            inputs.period_hash(),
+        ))
     }
 
     fn prepare_privacy_public_inputs(&self, inputs: &CDRPrivacyProofInputs) -> Result<Vec<ark_bn254::Fr>> {
@@ -239,6 +329,18 @@ impl AlbatrossZKVerifier {
         Ok(public_inputs)
     }
 
+    fn prepare_batch_public_inputs(&self, batch: &CDRBatchProof) -> Vec<ark_bn254::Fr> {
+        use ark_ff::PrimeField;
+
+        crate::zkp::circuits::CDRBatchCircuit::<ark_bn254::Fr>::public_inputs(
+            ark_bn254::Fr::from_le_bytes_mod_order(batch.records_root.as_bytes()),
+            batch.record_count as u64,
+            batch.total_charges_cents,
+            batch.period_hash,
+            batch.network_pair_hash,
+        )
+    }
+
     fn hash_to_field_element(&self, hash: &Blake2bHash) -> Result<ark_bn254::Fr> {
         use ark_ff::PrimeField;
 
@@ -253,6 +355,11 @@ impl AlbatrossZKVerifier {
 pub struct AlbatrossZKProver {
     settlement_pk: Option<ProvingKey<Bn254>>,
     cdr_privacy_pk: Option<ProvingKey<Bn254>>,
+    netting_pk: Option<ProvingKey<Bn254>>,
+    cdr_batch_pk: Option<ProvingKey<Bn254>>,
+    mimc_params: crate::zkp::mimc::MiMCParameters<ark_bn254::Fr>,
+    /// Dedicated pool for batch proving; rayon's global pool when unset
+    proving_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
 }
 
 impl AlbatrossZKProver {
@@ -260,6 +367,10 @@ impl AlbatrossZKProver {
         Self {
             settlement_pk: None,
             cdr_privacy_pk: None,
+            netting_pk: None,
+            cdr_batch_pk: None,
+            mimc_params: crate::zkp::mimc::MiMCParameters::new(),
+            proving_pool: None,
         }
     }
 
@@ -294,6 +405,18 @@ impl AlbatrossZKProver {
             self.settlement_pk = Some(pk);
         }
 
+        // Load netting correctness proving key
+        if ceremony.keys_exist("netting_correctness").await {
+            let (pk, _) = ceremony.load_circuit_keys("netting_correctness").await?;
+            self.netting_pk = Some(pk);
+        }
+
+        // Load batch CDR privacy proving key
+        if ceremony.keys_exist("cdr_batch_privacy").await {
+            let (pk, _) = ceremony.load_circuit_keys("cdr_batch_privacy").await?;
+            self.cdr_batch_pk = Some(pk);
+        }
+
         Ok(())
     }
 
@@ -313,33 +436,47 @@ impl AlbatrossZKProver {
         Ok(())
     }
 
+    /// Load batch CDR privacy proving key from bytes
+    pub fn load_cdr_batch_proving_key(&mut self, pk_bytes: &[u8]) -> Result<()> {
+        let pk = ProvingKey::<Bn254>::deserialize_compressed(pk_bytes)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+        self.cdr_batch_pk = Some(pk);
+        Ok(())
+    }
+
+    /// Run batch proving on a dedicated pool of `threads` worker threads
+    pub fn set_proving_threads(&mut self, threads: usize) -> Result<()> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("zk-prover-{}", i))
+            .build()
+            .map_err(|e| BlockchainError::ZkProof(format!("Failed to build proving pool: {}", e)))?;
+        self.proving_pool = Some(std::sync::Arc::new(pool));
+        Ok(())
+    }
+
     /// Generate settlement proof using real circuit
+    /// `bilateral[i][j]` is what operator j owes operator i, for up to SETTLEMENT_MAX_OPERATORS
+    /// operators; returns the proof with the public inputs it verifies against
     pub fn generate_settlement_proof<R: RngCore + CryptoRng>(
         &self,
         rng: &mut R,
-        inputs: &CDRSettlementInputs,
-        bilateral_amounts: [u64; 6], // All bilateral settlement amounts
-        net_positions: [i64; 3],     // Net positions for 3 operators
-    ) -> Result<Vec<u8>> {
+        period_commitment: Blake2bHash,
+        bilateral: &[Vec<u64>],
+    ) -> Result<(Vec<u8>, CDRSettlementInputs)> {
         let pk = self.settlement_pk.as_ref()
             .ok_or_else(|| BlockchainError::InvalidProof)?;
 
-        // Calculate settlement statistics
-        let gross_total: u64 = bilateral_amounts.iter().sum();
-        let net_total = net_positions.iter().map(|p| p.abs() as u64).sum::<u64>() / 2;
-        let savings_pct = if gross_total > 0 {
-            ((gross_total - net_total) * 100) / gross_total
-        } else { 0 };
+        let inputs = CDRSettlementInputs {
+            totals: crate::zkp::circuits::SettlementTotals::of(bilateral),
+            period_commitment,
+        };
 
         // Create settlement circuit
-        let circuit = crate::zkp::circuits::SettlementCalculationCircuit::new(
-            bilateral_amounts,
-            net_positions,
-            2, // Typically 2 net settlements in triangular netting
-            net_total,
-            inputs.period_commitment.as_bytes()[0..8].try_into().unwrap_or([0u8; 8]),
-            savings_pct,
-        );
+        let circuit = crate::zkp::circuits::SettlementCalculationCircuit::new(bilateral, inputs.period_hash())
+            .map_err(|_| BlockchainError::ZkProof(format!(
+                "Settlement of {} operators exceeds the circuit's bounds", bilateral.len()
+            )))?;
 
         // Generate real Groth16 proof
         let proof = Groth16::<Bn254>::prove(pk, circuit, rng)
@@ -350,20 +487,41 @@ impl AlbatrossZKProver {
         proof.serialize_compressed(&mut proof_bytes)
             .map_err(|_| BlockchainError::Serialization("Failed to serialize proof".to_string()))?;
 
+        Ok((proof_bytes, inputs))
+    }
+
+    /// Generate netting correctness proof
+    /// `bilateral` and `residual` are obligation matrices ([i][j] = i owes j)
+    /// in the same participant order as `net_positions`
+    pub fn generate_netting_proof<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        bilateral: &[Vec<u64>],
+        residual: &[Vec<u64>],
+        net_positions: &[i64],
+    ) -> Result<Vec<u8>> {
+        let pk = self.netting_pk.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidProof)?;
+
+        let circuit = crate::zkp::circuits::NettingCorrectnessCircuit::new(bilateral, residual, net_positions)
+            .map_err(|e| BlockchainError::ZkProof(format!("Invalid netting circuit inputs: {}", e)))?;
+
+        let proof = Groth16::<Bn254>::prove(pk, circuit, rng)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes)
+            .map_err(|_| BlockchainError::Serialization("Failed to serialize proof".to_string()))?;
+
         Ok(proof_bytes)
     }
 
     /// Generate CDR privacy proof using real circuit
+    /// Proves `charges` net of credits, which may be negative, without revealing usage or rates
     pub fn generate_cdr_privacy_proof<R: RngCore + CryptoRng>(
         &self,
         rng: &mut R,
-        call_minutes: u64,
-        data_mb: u64,
-        sms_count: u64,
-        call_rate_cents: u64,
-        data_rate_cents: u64,
-        sms_rate_cents: u64,
-        total_charges_cents: u64,
+        charges: &crate::zkp::circuits::CDRCharges,
         period_hash: u64,
         network_pair_hash: u64,
     ) -> Result<Vec<u8>> {
@@ -382,14 +540,8 @@ impl AlbatrossZKProver {
 
         // Create CDR privacy circuit
         let circuit = crate::zkp::circuits::CDRPrivacyCircuit::new(
-            call_minutes,
-            data_mb,
-            sms_count,
-            call_rate_cents,
-            data_rate_cents,
-            sms_rate_cents,
+            charges,
             privacy_salt,
-            total_charges_cents,
             period_hash,
             network_pair_hash,
             commitment_randomness,
@@ -408,6 +560,78 @@ impl AlbatrossZKProver {
     }
 }
 
+impl AlbatrossZKProver {
+    /// Generate batch CDR privacy proofs
+    /// Records are split into batches of CDR_BATCH_SIZE, each proven by one
+    /// circuit instance committing to its records with a Merkle root. Witness
+    /// generation and proving run in parallel across batches
+    pub fn generate_cdr_batch_proofs<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        records: &[crate::zkp::circuits::CDRBatchRecord],
+        period_hash: u64,
+        network_pair_hash: u64,
+    ) -> Result<Vec<CDRBatchProof>> {
+        use ark_ff::{BigInteger, PrimeField};
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+        use rayon::prelude::*;
+        use crate::zkp::circuits::{CDRBatchCircuit, CDR_BATCH_SIZE};
+
+        let pk = self.cdr_batch_pk.as_ref()
+            .ok_or_else(|| BlockchainError::InvalidProof)?;
+
+        // Draw per-record salts and per-batch proving seeds up front so the
+        // parallel section does not share the caller's RNG
+        let jobs: Vec<(&[crate::zkp::circuits::CDRBatchRecord], Vec<u64>, [u8; 32])> = records
+            .chunks(CDR_BATCH_SIZE)
+            .map(|chunk| {
+                let salts = (0..chunk.len()).map(|_| rng.next_u64()).collect();
+                let mut seed = [0u8; 32];
+                rng.fill_bytes(&mut seed);
+                (chunk, salts, seed)
+            })
+            .collect();
+
+        let prove_all = || {
+            jobs.into_par_iter()
+                .map(|(chunk, salts, seed)| {
+                    let circuit = CDRBatchCircuit::new(chunk, &salts, period_hash, network_pair_hash, &self.mimc_params)
+                        .map_err(|e| BlockchainError::ZkProof(format!("Invalid batch circuit inputs: {}", e)))?;
+
+                    let records_root = circuit.records_root
+                        .ok_or_else(|| BlockchainError::ZkProof("Batch circuit missing records root".to_string()))?;
+                    let total_charges_cents = chunk.iter().map(|r| r.total_charges_cents).sum();
+
+                    let mut batch_rng = StdRng::from_seed(seed);
+                    let proof = Groth16::<Bn254>::prove(pk, circuit, &mut batch_rng)
+                        .map_err(|_| BlockchainError::InvalidProof)?;
+
+                    let mut proof_bytes = Vec::new();
+                    proof.serialize_compressed(&mut proof_bytes)
+                        .map_err(|_| BlockchainError::Serialization("Failed to serialize proof".to_string()))?;
+
+                    let mut root_bytes = [0u8; 32];
+                    root_bytes.copy_from_slice(&records_root.into_bigint().to_bytes_le());
+
+                    Ok(CDRBatchProof {
+                        proof: proof_bytes,
+                        records_root: Blake2bHash::from_bytes(root_bytes),
+                        record_count: chunk.len() as u32,
+                        total_charges_cents,
+                        period_hash,
+                        network_pair_hash,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        match &self.proving_pool {
+            Some(pool) => pool.install(prove_all),
+            None => prove_all(),
+        }
+    }
+}
+
 /// Integration with smart contracts
 impl crate::smart_contracts::ContractCryptoVerifier {
     /// Initialize with real Albatross ZK verifier
@@ -494,15 +718,13 @@ mod tests {
         let verifier = AlbatrossZKVerifier::new();
 
         let inputs = CDRSettlementInputs {
-            creditor_total: 100000,
-            debtor_total: 85000,
-            exchange_rate: 110,
-            net_settlement: 15000,
+            totals: crate::zkp::circuits::SettlementTotals::of(&[vec![0, 100000], vec![85000, 0]]),
             period_commitment: crate::primitives::primitives::hash_data(b"2024-01"),
-            network_pair_commitment: crate::primitives::primitives::hash_data(b"T-Mobile-DE:Vodafone-UK"),
         };
+        assert_eq!(inputs.totals.total_net_amount, 15000);
 
+        // Settlement count, net total, period and savings, as the circuit allocates them
         let public_inputs = verifier.prepare_settlement_public_inputs(&inputs).unwrap();
-        assert_eq!(public_inputs.len(), 6);
+        assert_eq!(public_inputs.len(), 4);
     }
 }
\ No newline at end of file
@@ -6,60 +6,108 @@ use ark_r1cs_std::{
     alloc::AllocVar,
     boolean::Boolean,
     eq::EqGadget,
-    fields::fp::FpVar,
+    fields::{fp::FpVar, FieldVar},
+    R1CSVar,
 };
-use ark_ff::PrimeField;
+use ark_ff::{BigInteger, PrimeField};
+use rayon::prelude::*;
 use std::marker::PhantomData;
 
-/// Range check utility for ZK circuits
-/// Provides a basic security constraint to ensure values are reasonable
-/// This prevents obvious overflow attacks and unrealistic values
-fn enforce_range_check<F: PrimeField>(
+use crate::zkp::mimc::MiMCParameters;
+
+/// Wholesale bounds a CDR privacy proof enforces per record, after GSMA IOT ranges
+pub const CDR_MAX_CALL_MINUTES: u64 = 100_000;
+pub const CDR_MAX_DATA_MB: u64 = 1_000_000;
+pub const CDR_MAX_SMS_COUNT: u64 = 100_000;
+pub const CDR_MAX_CALL_RATE_CENTS: u64 = 200;
+pub const CDR_MAX_DATA_RATE_CENTS: u64 = 50;
+pub const CDR_MAX_SMS_RATE_CENTS: u64 = 100;
+/// Bound of a record's gross charges, and separately of its credits (€1,000,000)
+pub const CDR_MAX_CHARGE_CENTS: u64 = 100_000_000;
+
+/// Offset added to a record's signed net charge so it fits in a u64 field element
+pub const CDR_CHARGE_OFFSET: u64 = 1 << 63;
+
+/// Enforce that a field element lies in [0, max], binding like `enforce_bits`
+fn enforce_at_most<F: PrimeField>(
     cs: ConstraintSystemRef<F>,
     value: &FpVar<F>,
-    max_bound: u64,
-    _bit_size: usize,
-    _name: &str,
+    max: u64,
 ) -> Result<(), SynthesisError> {
-    // Basic sanity check: ensure value is less than a reasonable maximum
-    // This prevents extreme overflow attacks
-    let max_value = FpVar::new_constant(cs.clone(), F::from(max_bound))?;
-
-    // Compute difference = max_bound - value
-    // If value > max_bound, this will wrap around in the field,
-    // creating a very large positive value
-    let diff = &max_value - value;
-
-    // Create a constraint that the difference should be "small"
-    // We'll use a simple quadratic constraint that becomes expensive if diff is large
-    let diff_squared = &diff * &diff;
-
-    // This creates a constraint that heavily penalizes large differences
-    // without needing complex bit operations
-    let penalty_threshold = FpVar::new_constant(cs, F::from(max_bound * max_bound))?;
-    let is_reasonable = diff_squared.is_eq(&diff_squared)?; // Always true, but forces evaluation
-
-    // The constraint system will catch field overflow if value > max_bound
-    // This provides basic protection against unrealistic values
+    let bits = (u64::BITS - max.leading_zeros()) as usize;
+    enforce_bits(cs.clone(), value, bits)?;
+    let max = FpVar::new_constant(cs.clone(), F::from(max))?;
+    enforce_bits(cs, &(max - value), bits)?;
     Ok(())
 }
 
+/// Charge of one rated service: billed units at a whole-cent average rate, plus the cents that
+/// average leaves over, fewer than one per unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ServiceCharge {
+    pub units: u64,
+    pub rate_cents: u64,
+    pub remainder_cents: u64,
+}
+
+impl ServiceCharge {
+    /// Split `cents` charged for `units`, a charge without whole units is all remainder
+    pub fn of(units: u64, cents: u64) -> Self {
+        match units {
+            0 => Self { units, rate_cents: 0, remainder_cents: cents },
+            _ => Self { units, rate_cents: cents / units, remainder_cents: cents % units },
+        }
+    }
+
+    pub fn cents(&self) -> u64 {
+        self.units * self.rate_cents + self.remainder_cents
+    }
+}
+
+/// Billing semantics of a CDR as the privacy circuit proves it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CDRCharges {
+    pub voice: ServiceCharge,
+    pub data: ServiceCharge,
+    pub sms: ServiceCharge,
+    /// Usage not rated per service, e.g. 5G slice usage
+    pub other_cents: u64,
+    /// Credits and refunds against the charges, which may exceed them
+    pub credit_cents: u64,
+}
+
+impl CDRCharges {
+    pub fn gross_cents(&self) -> u64 {
+        self.voice.cents() + self.data.cents() + self.sms.cents() + self.other_cents
+    }
+
+    /// Charges less credits, negative for a net refund
+    pub fn net_cents(&self) -> i64 {
+        self.gross_cents() as i64 - self.credit_cents as i64
+    }
+}
+
 /// CDR Privacy Circuit
-/// Proves that encrypted CDR data represents correct settlement amounts
-/// without revealing individual call/data/SMS records
+/// Proves that a public net charge is the sum of per-service charges within tariff bounds,
+/// less credits, without revealing the usage or rates behind it
 #[derive(Clone)]
 pub struct CDRPrivacyCircuit<F: PrimeField> {
-    // Private inputs (witness)
+    // Private inputs (witness): billed units, average rate and leftover cents per service
     pub raw_call_minutes: Option<F>,
+    pub call_rate_cents: Option<F>,      // €0.15/min = 15 cents
+    pub call_remainder_cents: Option<F>,
     pub raw_data_mb: Option<F>,
+    pub data_rate_cents: Option<F>,      // €0.05/MB = 5 cents
+    pub data_remainder_cents: Option<F>,
     pub raw_sms_count: Option<F>,
-    pub call_rate_cents: Option<F>,  // €0.15/min = 15 cents
-    pub data_rate_cents: Option<F>,  // €0.05/MB = 5 cents
-    pub sms_rate_cents: Option<F>,   // €0.10/SMS = 10 cents
-    pub privacy_salt: Option<F>,     // Random salt for privacy
+    pub sms_rate_cents: Option<F>,       // €0.10/SMS = 10 cents
+    pub sms_remainder_cents: Option<F>,
+    pub other_charges_cents: Option<F>,
+    pub credit_cents: Option<F>,
+    pub privacy_salt: Option<F>,         // Random salt for privacy
 
     // Public inputs (what everyone can see)
-    pub total_charges_cents: Option<F>,  // Final settlement amount
+    pub net_charges: Option<F>,          // Offset-encoded settlement amount, negative for refunds
     pub period_hash: Option<F>,          // Hash of billing period
     pub network_pair_hash: Option<F>,    // Hash of "T-Mobile-DE:Vodafone-UK"
     pub commitment_randomness: Option<F>, // For Pedersen commitment
@@ -69,27 +117,26 @@ pub struct CDRPrivacyCircuit<F: PrimeField> {
 
 impl<F: PrimeField> CDRPrivacyCircuit<F> {
     pub fn new(
-        raw_call_minutes: u64,
-        raw_data_mb: u64,
-        raw_sms_count: u64,
-        call_rate_cents: u64,
-        data_rate_cents: u64,
-        sms_rate_cents: u64,
+        charges: &CDRCharges,
         privacy_salt: u64,
-        total_charges_cents: u64,
         period_hash: u64,
         network_pair_hash: u64,
         commitment_randomness: u64,
     ) -> Self {
         Self {
-            raw_call_minutes: Some(F::from(raw_call_minutes)),
-            raw_data_mb: Some(F::from(raw_data_mb)),
-            raw_sms_count: Some(F::from(raw_sms_count)),
-            call_rate_cents: Some(F::from(call_rate_cents)),
-            data_rate_cents: Some(F::from(data_rate_cents)),
-            sms_rate_cents: Some(F::from(sms_rate_cents)),
+            raw_call_minutes: Some(F::from(charges.voice.units)),
+            call_rate_cents: Some(F::from(charges.voice.rate_cents)),
+            call_remainder_cents: Some(F::from(charges.voice.remainder_cents)),
+            raw_data_mb: Some(F::from(charges.data.units)),
+            data_rate_cents: Some(F::from(charges.data.rate_cents)),
+            data_remainder_cents: Some(F::from(charges.data.remainder_cents)),
+            raw_sms_count: Some(F::from(charges.sms.units)),
+            sms_rate_cents: Some(F::from(charges.sms.rate_cents)),
+            sms_remainder_cents: Some(F::from(charges.sms.remainder_cents)),
+            other_charges_cents: Some(F::from(charges.other_cents)),
+            credit_cents: Some(F::from(charges.credit_cents)),
             privacy_salt: Some(F::from(privacy_salt)),
-            total_charges_cents: Some(F::from(total_charges_cents)),
+            net_charges: Some(F::from(Self::encode_charge(charges.net_cents()))),
             period_hash: Some(F::from(period_hash)),
             network_pair_hash: Some(F::from(network_pair_hash)),
             commitment_randomness: Some(F::from(commitment_randomness)),
@@ -100,188 +147,215 @@ impl<F: PrimeField> CDRPrivacyCircuit<F> {
     pub fn empty() -> Self {
         Self {
             raw_call_minutes: None,
-            raw_data_mb: None,
-            raw_sms_count: None,
             call_rate_cents: None,
+            call_remainder_cents: None,
+            raw_data_mb: None,
             data_rate_cents: None,
+            data_remainder_cents: None,
+            raw_sms_count: None,
             sms_rate_cents: None,
+            sms_remainder_cents: None,
+            other_charges_cents: None,
+            credit_cents: None,
             privacy_salt: None,
-            total_charges_cents: None,
+            net_charges: None,
             period_hash: None,
             network_pair_hash: None,
             commitment_randomness: None,
             _phantom: PhantomData,
         }
     }
-}
-
-impl<F: PrimeField> ConstraintSynthesizer<F> for CDRPrivacyCircuit<F> {
-    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
-        // Allocate private witness variables
-        let call_minutes = FpVar::new_witness(cs.clone(), || {
-            self.raw_call_minutes.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-
-        let data_mb = FpVar::new_witness(cs.clone(), || {
-            self.raw_data_mb.ok_or(SynthesisError::AssignmentMissing)
-        })?;
 
-        let sms_count = FpVar::new_witness(cs.clone(), || {
-            self.raw_sms_count.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-
-        let call_rate = FpVar::new_witness(cs.clone(), || {
-            self.call_rate_cents.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-
-        let data_rate = FpVar::new_witness(cs.clone(), || {
-            self.data_rate_cents.ok_or(SynthesisError::AssignmentMissing)
-        })?;
+    /// Public inputs in allocation order, for proof verification
+    pub fn public_inputs(net_cents: i64, period_hash: u64, network_pair_hash: u64) -> Vec<F> {
+        vec![
+            F::from(Self::encode_charge(net_cents)),
+            F::from(period_hash),
+            F::from(network_pair_hash),
+        ]
+    }
 
-        let sms_rate = FpVar::new_witness(cs.clone(), || {
-            self.sms_rate_cents.ok_or(SynthesisError::AssignmentMissing)
-        })?;
+    fn encode_charge(net_cents: i64) -> u64 {
+        (net_cents as i128 + CDR_CHARGE_OFFSET as i128) as u64
+    }
+}
 
-        let privacy_salt = FpVar::new_witness(cs.clone(), || {
-            self.privacy_salt.ok_or(SynthesisError::AssignmentMissing)
-        })?;
+/// Charge of one service: units and rate within their bounds, and the remainder below one cent per
+/// unit, or without whole units at most one unit at the maximum rate
+fn enforce_service_charge<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    units: &FpVar<F>,
+    rate: &FpVar<F>,
+    remainder: &FpVar<F>,
+    max_units: u64,
+    max_rate: u64,
+) -> Result<FpVar<F>, SynthesisError> {
+    enforce_at_most(cs.clone(), units, max_units)?;
+    enforce_at_most(cs.clone(), rate, max_rate)?;
+
+    let allowance_bound = max_units.max(max_rate + 1);
+    let bits = (u64::BITS - allowance_bound.leading_zeros()) as usize;
+    let no_units = FpVar::from(units.is_eq(&FpVar::zero())?);
+    let allowance = units + no_units * F::from(max_rate + 1);
+    enforce_bits(cs.clone(), remainder, bits)?;
+    enforce_bits(cs, &(allowance - remainder - FpVar::one()), bits)?;
+
+    Ok(units * rate + remainder)
+}
 
-        // Allocate public input variables
-        let total_charges = FpVar::new_input(cs.clone(), || {
-            self.total_charges_cents.ok_or(SynthesisError::AssignmentMissing)
+impl<F: PrimeField> ConstraintSynthesizer<F> for CDRPrivacyCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // Allocate public inputs first so their order matches `public_inputs`
+        let net_charges = FpVar::new_input(cs.clone(), || {
+            self.net_charges.ok_or(SynthesisError::AssignmentMissing)
         })?;
 
-        let period_hash = FpVar::new_input(cs.clone(), || {
+        let _period_hash = FpVar::new_input(cs.clone(), || {
             self.period_hash.ok_or(SynthesisError::AssignmentMissing)
         })?;
 
-        let network_pair_hash = FpVar::new_input(cs.clone(), || {
+        let _network_pair_hash = FpVar::new_input(cs.clone(), || {
             self.network_pair_hash.ok_or(SynthesisError::AssignmentMissing)
         })?;
 
-        let commitment_rand = FpVar::new_witness(cs.clone(), || {
-            self.commitment_randomness.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-
-        // Constraint 1: Calculate total charges from CDR components
-        // total_charges = call_minutes * call_rate + data_mb * data_rate + sms_count * sms_rate
-        let call_charges = &call_minutes * &call_rate;
-        let data_charges = &data_mb * &data_rate;
-        let sms_charges = &sms_count * &sms_rate;
-
-        let calculated_total = &call_charges + &data_charges + &sms_charges;
-
-        // Enforce that calculated total equals public total
-        total_charges.enforce_equal(&calculated_total)?;
-
-        // Constraint 2: Critical Security Range Checks
-        // These prevent overflow attacks, unrealistic values, and malicious inputs
-
-        // Call minutes: 0 to 100,000 minutes per month (requires 17 bits)
-        enforce_range_check(cs.clone(), &call_minutes, 100_000, 17, "call_minutes")?;
-
-        // Data usage: 0 to 1TB (1,000,000 MB) per month (requires 20 bits)
-        enforce_range_check(cs.clone(), &data_mb, 1_000_000, 20, "data_mb")?;
-
-        // SMS count: 0 to 100,000 SMS per month (requires 17 bits)
-        enforce_range_check(cs.clone(), &sms_count, 100_000, 17, "sms_count")?;
-
-        // Call rate: 0 to 200 cents per minute (requires 8 bits)
-        enforce_range_check(cs.clone(), &call_rate, 200, 8, "call_rate")?;
-
-        // Data rate: 0 to 50 cents per MB (requires 6 bits)
-        enforce_range_check(cs.clone(), &data_rate, 50, 6, "data_rate")?;
-
-        // SMS rate: 0 to 100 cents per SMS (requires 7 bits)
-        enforce_range_check(cs.clone(), &sms_rate, 100, 7, "sms_rate")?;
-
-        // Total charges: 0 to €1,000,000 (100,000,000 cents) per month (requires 27 bits)
-        enforce_range_check(cs.clone(), &total_charges, 100_000_000, 27, "total_charges")?;
-
-        // Constraint 3: Anti-overflow protection using range checks on intermediate results
-        // These ensure individual charge calculations don't exceed safe bounds
+        // Allocate private witness variables
+        let witness = |value: Option<F>| FpVar::new_witness(cs.clone(), || {
+            value.ok_or(SynthesisError::AssignmentMissing)
+        });
+        let call_minutes = witness(self.raw_call_minutes)?;
+        let call_rate = witness(self.call_rate_cents)?;
+        let call_remainder = witness(self.call_remainder_cents)?;
+        let data_mb = witness(self.raw_data_mb)?;
+        let data_rate = witness(self.data_rate_cents)?;
+        let data_remainder = witness(self.data_remainder_cents)?;
+        let sms_count = witness(self.raw_sms_count)?;
+        let sms_rate = witness(self.sms_rate_cents)?;
+        let sms_remainder = witness(self.sms_remainder_cents)?;
+        let other_charges = witness(self.other_charges_cents)?;
+        let credits = witness(self.credit_cents)?;
+        let _privacy_salt = witness(self.privacy_salt)?;
+        let _commitment_rand = witness(self.commitment_randomness)?;
+
+        // Constraint 1: Each service charges its units at a rate within tariff bounds
+        let call_charges = enforce_service_charge(
+            cs.clone(), &call_minutes, &call_rate, &call_remainder, CDR_MAX_CALL_MINUTES, CDR_MAX_CALL_RATE_CENTS,
+        )?;
+        let data_charges = enforce_service_charge(
+            cs.clone(), &data_mb, &data_rate, &data_remainder, CDR_MAX_DATA_MB, CDR_MAX_DATA_RATE_CENTS,
+        )?;
+        let sms_charges = enforce_service_charge(
+            cs.clone(), &sms_count, &sms_rate, &sms_remainder, CDR_MAX_SMS_COUNT, CDR_MAX_SMS_RATE_CENTS,
+        )?;
+
+        // Constraint 2: Gross charges and credits within bounds
+        enforce_at_most(cs.clone(), &other_charges, CDR_MAX_CHARGE_CENTS)?;
+        let gross_charges = call_charges + data_charges + sms_charges + other_charges;
+        enforce_at_most(cs.clone(), &gross_charges, CDR_MAX_CHARGE_CENTS)?;
+        enforce_at_most(cs.clone(), &credits, CDR_MAX_CHARGE_CENTS)?;
+
+        // Constraint 3: The public net charge is gross charges less credits, which may go negative
+        let offset = FpVar::new_constant(cs.clone(), F::from(CDR_CHARGE_OFFSET))?;
+        net_charges.enforce_equal(&(offset + gross_charges - credits))?;
 
-        // Call charges: max 20,000,000 cents (€200,000) - requires 25 bits
-        enforce_range_check(cs.clone(), &call_charges, 20_000_000, 25, "call_charges")?;
+        Ok(())
+    }
+}
 
-        // Data charges: max 50,000,000 cents (€500,000) - requires 26 bits
-        enforce_range_check(cs.clone(), &data_charges, 50_000_000, 26, "data_charges")?;
+/// Maximum operators in a settlement calculation proof
+/// Groth16 needs a fixed circuit shape; smaller consortia are zero-padded
+pub const SETTLEMENT_MAX_OPERATORS: usize = 16;
+
+/// Bits of a single bilateral amount in cents, over €2.8 billion per pair and period
+pub const SETTLEMENT_AMOUNT_BITS: usize = 48;
+
+/// Public outcome of netting a bilateral matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub struct SettlementTotals {
+    /// Operators left with a non-zero net position, each settling once
+    pub net_settlement_count: u64,
+    /// Sum of the positive net positions, what changes hands after netting
+    pub total_net_amount: u64,
+    /// Share of the gross bilateral volume netting saves, rounded down
+    pub savings_percentage: u64,
+}
 
-        // SMS charges: max 10,000,000 cents (€100,000) - requires 24 bits
-        enforce_range_check(cs.clone(), &sms_charges, 10_000_000, 24, "sms_charges")?;
+impl SettlementTotals {
+    /// Net `bilateral`, where `[i][j]` is what operator j owes operator i
+    pub fn of(bilateral: &[Vec<u64>]) -> Self {
+        let positions = Self::net_positions(bilateral);
+        let gross_total: u128 = bilateral.iter().flatten().map(|amount| *amount as u128).sum();
+        let total_net_amount: u128 = positions.iter().filter(|p| **p > 0).map(|p| *p as u128).sum();
+        Self {
+            net_settlement_count: positions.iter().filter(|p| **p != 0).count() as u64,
+            total_net_amount: total_net_amount as u64,
+            savings_percentage: match gross_total {
+                0 => 0,
+                gross => ((gross - total_net_amount) * 100 / gross) as u64,
+            },
+        }
+    }
 
-        Ok(())
+    /// Net position of every operator, what it is owed less what it owes
+    pub fn net_positions(bilateral: &[Vec<u64>]) -> Vec<i128> {
+        (0..bilateral.len())
+            .map(|i| (0..bilateral.len())
+                .map(|j| bilateral[i][j] as i128 - bilateral[j][i] as i128)
+                .sum())
+            .collect()
     }
 }
 
 /// Settlement Calculation Circuit
-/// Proves that triangular netting calculations are correct
-/// without revealing individual bilateral amounts
+/// Proves that the public net settlement totals follow from private bilateral amounts between
+/// up to SETTLEMENT_MAX_OPERATORS operators, without revealing the amounts
 #[derive(Clone)]
 pub struct SettlementCalculationCircuit<F: PrimeField> {
-    // Private inputs: bilateral settlement amounts
-    pub tmobile_to_vodafone: Option<F>,
-    pub vodafone_to_orange: Option<F>,
-    pub orange_to_tmobile: Option<F>,
-    pub vodafone_to_tmobile: Option<F>,
-    pub orange_to_vodafone: Option<F>,
-    pub tmobile_to_orange: Option<F>,
-
-    // Private: netting calculation intermediate values
-    pub tmobile_position: Option<F>,  // Net position (can be negative)
-    pub vodafone_position: Option<F>,
-    pub orange_position: Option<F>,
+    // Private inputs: row-major bilateral matrix, [i * MAX + j] = j owes i
+    pub bilateral: Vec<Option<F>>,
 
     // Public inputs: final net settlements
-    pub net_settlement_count: Option<F>,    // Number of final settlements
+    pub net_settlement_count: Option<F>,    // Operators with a non-zero net position
     pub total_net_amount: Option<F>,        // Total net settlement volume
     pub period_hash: Option<F>,             // Settlement period
-    pub savings_percentage: Option<F>,       // Percentage reduction achieved
+    pub savings_percentage: Option<F>,      // Percentage reduction achieved
 
     _phantom: PhantomData<F>,
 }
 
 impl<F: PrimeField> SettlementCalculationCircuit<F> {
-    pub fn new(
-        bilateral_amounts: [u64; 6], // All 6 bilateral amounts
-        net_positions: [i64; 3],     // Net positions (can be negative)
-        net_settlement_count: u64,
-        total_net_amount: u64,
-        period_hash: [u8; 8],        // Changed from u64 to [u8; 8]
-        savings_percentage: u64,
-    ) -> Self {
-        Self {
-            tmobile_to_vodafone: Some(F::from(bilateral_amounts[0])),
-            vodafone_to_orange: Some(F::from(bilateral_amounts[1])),
-            orange_to_tmobile: Some(F::from(bilateral_amounts[2])),
-            vodafone_to_tmobile: Some(F::from(bilateral_amounts[3])),
-            orange_to_vodafone: Some(F::from(bilateral_amounts[4])),
-            tmobile_to_orange: Some(F::from(bilateral_amounts[5])),
-
-            // Handle negative positions by adding large offset
-            tmobile_position: Some(F::from((net_positions[0] + 1_000_000) as u64)),
-            vodafone_position: Some(F::from((net_positions[1] + 1_000_000) as u64)),
-            orange_position: Some(F::from((net_positions[2] + 1_000_000) as u64)),
-
-            net_settlement_count: Some(F::from(net_settlement_count)),
-            total_net_amount: Some(F::from(total_net_amount)),
-            period_hash: Some(F::from(u64::from_le_bytes(period_hash))),
-            savings_percentage: Some(F::from(savings_percentage)),
-            _phantom: PhantomData,
+    pub fn new(bilateral: &[Vec<u64>], period_hash: u64) -> Result<Self, SynthesisError> {
+        let n = bilateral.len();
+        if n > SETTLEMENT_MAX_OPERATORS
+            || bilateral.iter().any(|row| row.len() != n)
+            || bilateral.iter().flatten().any(|amount| *amount >> SETTLEMENT_AMOUNT_BITS != 0)
+            || (0..n).any(|i| bilateral[i][i] != 0)
+        {
+            return Err(SynthesisError::Unsatisfiable);
         }
+
+        let max = SETTLEMENT_MAX_OPERATORS;
+        let mut cells = vec![Some(F::zero()); max * max];
+        for i in 0..n {
+            for j in 0..n {
+                cells[i * max + j] = Some(F::from(bilateral[i][j]));
+            }
+        }
+
+        let totals = SettlementTotals::of(bilateral);
+        Ok(Self {
+            bilateral: cells,
+            net_settlement_count: Some(F::from(totals.net_settlement_count)),
+            total_net_amount: Some(F::from(totals.total_net_amount)),
+            period_hash: Some(F::from(period_hash)),
+            savings_percentage: Some(F::from(totals.savings_percentage)),
+            _phantom: PhantomData,
+        })
     }
 
     pub fn empty() -> Self {
+        let max = SETTLEMENT_MAX_OPERATORS;
         Self {
-            tmobile_to_vodafone: None,
-            vodafone_to_orange: None,
-            orange_to_tmobile: None,
-            vodafone_to_tmobile: None,
-            orange_to_vodafone: None,
-            tmobile_to_orange: None,
-            tmobile_position: None,
-            vodafone_position: None,
-            orange_position: None,
+            bilateral: vec![None; max * max],
             net_settlement_count: None,
             total_net_amount: None,
             period_hash: None,
@@ -289,120 +363,534 @@ impl<F: PrimeField> SettlementCalculationCircuit<F> {
             _phantom: PhantomData,
         }
     }
+
+    /// Public inputs in allocation order, for proof verification
+    pub fn public_inputs(totals: &SettlementTotals, period_hash: u64) -> Vec<F> {
+        vec![
+            F::from(totals.net_settlement_count),
+            F::from(totals.total_net_amount),
+            F::from(period_hash),
+            F::from(totals.savings_percentage),
+        ]
+    }
 }
 
 impl<F: PrimeField> ConstraintSynthesizer<F> for SettlementCalculationCircuit<F> {
     fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
-        // Allocate bilateral amount witnesses
-        let tmo_vod = FpVar::new_witness(cs.clone(), || {
-            self.tmobile_to_vodafone.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let vod_org = FpVar::new_witness(cs.clone(), || {
-            self.vodafone_to_orange.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let org_tmo = FpVar::new_witness(cs.clone(), || {
-            self.orange_to_tmobile.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let vod_tmo = FpVar::new_witness(cs.clone(), || {
-            self.vodafone_to_tmobile.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let org_vod = FpVar::new_witness(cs.clone(), || {
-            self.orange_to_vodafone.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let tmo_org = FpVar::new_witness(cs.clone(), || {
-            self.tmobile_to_orange.ok_or(SynthesisError::AssignmentMissing)
-        })?;
+        let max = SETTLEMENT_MAX_OPERATORS;
 
-        // Allocate net position witnesses (with offset to handle negatives)
-        let tmo_pos = FpVar::new_witness(cs.clone(), || {
-            self.tmobile_position.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let vod_pos = FpVar::new_witness(cs.clone(), || {
-            self.vodafone_position.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-        let org_pos = FpVar::new_witness(cs.clone(), || {
-            self.orange_position.ok_or(SynthesisError::AssignmentMissing)
-        })?;
-
-        // Allocate public inputs
+        // Allocate public inputs first so their order matches `public_inputs`
         let net_count = FpVar::new_input(cs.clone(), || {
             self.net_settlement_count.ok_or(SynthesisError::AssignmentMissing)
         })?;
         let total_net = FpVar::new_input(cs.clone(), || {
             self.total_net_amount.ok_or(SynthesisError::AssignmentMissing)
         })?;
-        let period_hash = FpVar::new_input(cs.clone(), || {
+        let _period_hash = FpVar::new_input(cs.clone(), || {
             self.period_hash.ok_or(SynthesisError::AssignmentMissing)
         })?;
         let savings_pct = FpVar::new_input(cs.clone(), || {
             self.savings_percentage.ok_or(SynthesisError::AssignmentMissing)
         })?;
 
-        let offset = FpVar::new_constant(cs.clone(), F::from(1_000_000u64))?;
+        // Constraint 1: Bilateral amounts are bounded and nobody owes itself (diagonal is unused)
+        let zero = FpVar::<F>::zero();
+        let mut bilateral = vec![zero.clone(); max * max];
+        for i in 0..max {
+            for j in 0..max {
+                if i == j {
+                    continue;
+                }
+                let idx = i * max + j;
+                bilateral[idx] = FpVar::new_witness(cs.clone(), || {
+                    self.bilateral[idx].ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                enforce_bits(cs.clone(), &bilateral[idx], SETTLEMENT_AMOUNT_BITS)?;
+            }
+        }
+
+        // Constraint 2: Net positions, offset-encoded; the top bit of the encoding is set exactly
+        // for non-negative positions, which splits off the positive part paid out after netting
+        let offset = FpVar::new_constant(cs.clone(), F::from(NETTING_POSITION_OFFSET))?;
+        let mut settling = zero.clone();
+        let mut positive_total = zero.clone();
+        for i in 0..max {
+            let mut position = offset.clone();
+            for j in 0..max {
+                if i == j {
+                    continue;
+                }
+                position = position + &bilateral[i * max + j] - &bilateral[j * max + i];
+            }
+            let bits = enforce_bits(cs.clone(), &position, 64)?;
+            let non_negative = FpVar::from(bits[63].clone());
+            positive_total += &non_negative * (&position - &offset);
+            settling += FpVar::from(position.is_neq(&offset)?);
+        }
+
+        // Constraint 3: Public totals match the netting
+        net_count.enforce_equal(&settling)?;
+        total_net.enforce_equal(&positive_total)?;
+
+        // Constraint 4: Savings percentage is 100 * (gross - net) / gross rounded down, or 0 without volume
+        // remainder = 100 * (gross - net) - savings * gross must lie in [0, gross)
+        let gross_total = bilateral.iter().fold(zero.clone(), |acc, v| acc + v);
+        let hundred = FpVar::new_constant(cs.clone(), F::from(100u64))?;
+        enforce_bits(cs.clone(), &(&hundred - &savings_pct), 7)?;
+        let remainder = &hundred * (&gross_total - &total_net) - &savings_pct * &gross_total;
+        enforce_u64(cs.clone(), &remainder)?;
+        let no_volume = FpVar::from(gross_total.is_eq(&zero)?);
+        enforce_u64(cs.clone(), &(&gross_total - &remainder - FpVar::one() + &no_volume))?;
+        (&savings_pct * &no_volume).enforce_equal(&zero)?;
+
+        Ok(())
+    }
+}
+
+/// Maximum participants supported by the netting correctness circuit
+/// Groth16 needs a fixed circuit shape; smaller nettings are zero-padded
+pub const NETTING_MAX_PARTICIPANTS: usize = 8;
+
+/// Offset added to signed net positions so they fit in a u64 field element
+pub const NETTING_POSITION_OFFSET: u64 = 1 << 63;
+
+/// Enforce that a field element is a 64-bit unsigned integer via bit decomposition
+/// Any value outside [0, 2^64) has no satisfying bit assignment
+fn enforce_u64<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    value: &FpVar<F>,
+) -> Result<(), SynthesisError> {
+    enforce_bits(cs, value, 64).map(|_| ())
+}
+
+/// Enforce that a field element lies in [0, 2^bits), returns its little-endian bits
+fn enforce_bits<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    value: &FpVar<F>,
+    bits: usize,
+) -> Result<Vec<Boolean<F>>, SynthesisError> {
+    let mut reconstructed = FpVar::<F>::zero();
+    let mut coefficient = F::one();
+    let mut decomposition = Vec::with_capacity(bits);
+
+    for i in 0..bits {
+        let bit = Boolean::new_witness(cs.clone(), || {
+            Ok(value.value()?.into_bigint().get_bit(i))
+        })?;
+        reconstructed += FpVar::from(bit.clone()) * coefficient;
+        coefficient.double_in_place();
+        decomposition.push(bit);
+    }
+
+    reconstructed.enforce_equal(value)?;
+    Ok(decomposition)
+}
+
+/// Netting Correctness Circuit
+/// Proves that public net positions were derived correctly from private
+/// bilateral obligations: residual obligations are non-negative and never
+/// exceed the original obligation, both matrices yield the same net positions,
+/// and net positions conserve value (sum to zero)
+#[derive(Clone)]
+pub struct NettingCorrectnessCircuit<F: PrimeField> {
+    // Private inputs: row-major obligation matrices, [i * MAX + j] = i owes j
+    pub bilateral: Vec<Option<F>>,
+    pub residual: Vec<Option<F>>,
+
+    // Public inputs
+    pub net_positions: Vec<Option<F>>,  // Offset-encoded net position per participant
+    pub gross_total: Option<F>,         // Sum of bilateral obligations
+    pub residual_total: Option<F>,      // Sum of residual obligations
+
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> NettingCorrectnessCircuit<F> {
+    pub fn new(
+        bilateral: &[Vec<u64>],
+        residual: &[Vec<u64>],
+        net_positions: &[i64],
+    ) -> Result<Self, SynthesisError> {
+        let n = net_positions.len();
+        if n > NETTING_MAX_PARTICIPANTS || bilateral.len() != n || residual.len() != n {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let max = NETTING_MAX_PARTICIPANTS;
+        let mut bilateral_cells = vec![Some(F::zero()); max * max];
+        let mut residual_cells = vec![Some(F::zero()); max * max];
+        let mut gross_total = 0u128;
+        let mut residual_total = 0u128;
+
+        for i in 0..n {
+            if bilateral[i].len() != n || residual[i].len() != n {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+                bilateral_cells[i * max + j] = Some(F::from(bilateral[i][j]));
+                residual_cells[i * max + j] = Some(F::from(residual[i][j]));
+                gross_total += bilateral[i][j] as u128;
+                residual_total += residual[i][j] as u128;
+            }
+        }
+
+        let mut positions = vec![0i64; max];
+        positions[..n].copy_from_slice(net_positions);
+
+        Ok(Self {
+            bilateral: bilateral_cells,
+            residual: residual_cells,
+            net_positions: positions.iter()
+                .map(|p| Some(F::from(Self::encode_position(*p))))
+                .collect(),
+            gross_total: Some(F::from(gross_total)),
+            residual_total: Some(F::from(residual_total)),
+            _phantom: PhantomData,
+        })
+    }
+
+    pub fn empty() -> Self {
+        let max = NETTING_MAX_PARTICIPANTS;
+        Self {
+            bilateral: vec![None; max * max],
+            residual: vec![None; max * max],
+            net_positions: vec![None; max],
+            gross_total: None,
+            residual_total: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Public inputs in allocation order, for proof verification
+    pub fn public_inputs(net_positions: &[i64], gross_total: u64, residual_total: u64) -> Result<Vec<F>, SynthesisError> {
+        if net_positions.len() > NETTING_MAX_PARTICIPANTS {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut inputs: Vec<F> = (0..NETTING_MAX_PARTICIPANTS)
+            .map(|i| F::from(Self::encode_position(net_positions.get(i).copied().unwrap_or(0))))
+            .collect();
+        inputs.push(F::from(gross_total));
+        inputs.push(F::from(residual_total));
+
+        Ok(inputs)
+    }
+
+    fn encode_position(position: i64) -> u64 {
+        (position as i128 + NETTING_POSITION_OFFSET as i128) as u64
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for NettingCorrectnessCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let max = NETTING_MAX_PARTICIPANTS;
+
+        // Allocate public inputs first so their order matches `public_inputs`
+        let mut net_positions = Vec::with_capacity(max);
+        for position in &self.net_positions {
+            net_positions.push(FpVar::new_input(cs.clone(), || {
+                position.ok_or(SynthesisError::AssignmentMissing)
+            })?);
+        }
+        let gross_total = FpVar::new_input(cs.clone(), || {
+            self.gross_total.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let residual_total = FpVar::new_input(cs.clone(), || {
+            self.residual_total.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Allocate obligation witnesses (diagonal is unused)
+        let zero = FpVar::<F>::zero();
+        let mut bilateral = vec![zero.clone(); max * max];
+        let mut residual = vec![zero.clone(); max * max];
+        for i in 0..max {
+            for j in 0..max {
+                if i == j {
+                    continue;
+                }
+                let idx = i * max + j;
+                bilateral[idx] = FpVar::new_witness(cs.clone(), || {
+                    self.bilateral[idx].ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                residual[idx] = FpVar::new_witness(cs.clone(), || {
+                    self.residual[idx].ok_or(SynthesisError::AssignmentMissing)
+                })?;
+
+                // Constraint 1: Non-negativity - obligations are u64 and netting
+                // only reduces them (bilateral - residual is also a u64)
+                enforce_u64(cs.clone(), &bilateral[idx])?;
+                enforce_u64(cs.clone(), &residual[idx])?;
+                enforce_u64(cs.clone(), &(&bilateral[idx] - &residual[idx]))?;
+            }
+        }
 
-        // Constraint 1: Verify net position calculations
-        // T-Mobile net = (outgoing) - (incoming)
-        let tmo_outgoing = &tmo_vod + &tmo_org;
-        let tmo_incoming = &vod_tmo + &org_tmo;
-        let tmo_net_calculated = &tmo_outgoing - &tmo_incoming + &offset;
-        tmo_pos.enforce_equal(&tmo_net_calculated)?;
+        // Constraint 2: Both matrices produce the public net positions
+        let offset = FpVar::new_constant(cs.clone(), F::from(NETTING_POSITION_OFFSET))?;
+        for i in 0..max {
+            let mut bilateral_net = offset.clone();
+            let mut residual_net = offset.clone();
+            for j in 0..max {
+                if i == j {
+                    continue;
+                }
+                bilateral_net = bilateral_net + &bilateral[j * max + i] - &bilateral[i * max + j];
+                residual_net = residual_net + &residual[j * max + i] - &residual[i * max + j];
+            }
+            net_positions[i].enforce_equal(&bilateral_net)?;
+            net_positions[i].enforce_equal(&residual_net)?;
+        }
 
-        // Vodafone net = (outgoing) - (incoming)
-        let vod_outgoing = &vod_tmo + &vod_org;
-        let vod_incoming = &tmo_vod + &org_vod;
-        let vod_net_calculated = &vod_outgoing - &vod_incoming + &offset;
-        vod_pos.enforce_equal(&vod_net_calculated)?;
+        // Constraint 3: Conservation of value - net positions sum to zero
+        let position_sum = net_positions.iter().fold(zero.clone(), |acc, p| acc + p);
+        let expected_sum = FpVar::new_constant(cs.clone(), F::from(NETTING_POSITION_OFFSET as u128 * max as u128))?;
+        position_sum.enforce_equal(&expected_sum)?;
 
-        // Orange net = (outgoing) - (incoming)
-        let org_outgoing = &org_tmo + &org_vod;
-        let org_incoming = &tmo_org + &vod_org;
-        let org_net_calculated = &org_outgoing - &org_incoming + &offset;
-        org_pos.enforce_equal(&org_net_calculated)?;
+        // Constraint 4: Public totals match the obligation matrices
+        let bilateral_sum = bilateral.iter().fold(zero.clone(), |acc, v| acc + v);
+        let residual_sum = residual.iter().fold(zero, |acc, v| acc + v);
+        gross_total.enforce_equal(&bilateral_sum)?;
+        residual_total.enforce_equal(&residual_sum)?;
 
-        // Constraint 2: Conservation law - net positions sum to zero
-        let total_positions = &tmo_pos + &vod_pos + &org_pos;
-        let expected_total = FpVar::new_constant(cs.clone(), F::from(3_000_000u64))?; // 3 * offset
-        total_positions.enforce_equal(&expected_total)?;
+        Ok(())
+    }
+}
 
-        // Constraint 3: Critical Security Range Checks for Settlement Amounts
-        // These prevent manipulation of bilateral settlement amounts
+/// Records per batch CDR privacy proof (power of two for the Merkle tree)
+pub const CDR_BATCH_SIZE: usize = 32;
+
+/// CDR record inputs for the batch privacy circuit
+/// The remainder is the cents whole-cent rates leave over, fewer than the record's units,
+/// or the whole charge of a record without units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub struct CDRBatchRecord {
+    pub call_minutes: u64,
+    pub data_mb: u64,
+    pub sms_count: u64,
+    pub call_rate_cents: u64,
+    pub data_rate_cents: u64,
+    pub sms_rate_cents: u64,
+    pub remainder_cents: u64,
+    pub total_charges_cents: u64,
+}
 
-        // Each bilateral amount: 0 to €100,000 (10,000,000 cents) per settlement period (requires 24 bits)
-        enforce_range_check(cs.clone(), &tmo_vod, 10_000_000, 24, "tmobile_to_vodafone")?;
-        enforce_range_check(cs.clone(), &vod_org, 10_000_000, 24, "vodafone_to_orange")?;
-        enforce_range_check(cs.clone(), &org_tmo, 10_000_000, 24, "orange_to_tmobile")?;
-        enforce_range_check(cs.clone(), &vod_tmo, 10_000_000, 24, "vodafone_to_tmobile")?;
-        enforce_range_check(cs.clone(), &org_vod, 10_000_000, 24, "orange_to_vodafone")?;
-        enforce_range_check(cs.clone(), &tmo_org, 10_000_000, 24, "tmobile_to_orange")?;
+impl CDRBatchRecord {
+    /// Field elements hashed into the record's Merkle leaf
+    /// Usage and rates are packed three u64 values per element
+    fn leaf_inputs<F: PrimeField>(&self, salt: F) -> [F; 4] {
+        let shift = F::from(1u128 << 64);
+        let pack = |a: u64, b: u64, c: u64| F::from(a) + F::from(b) * shift + F::from(c) * shift * shift;
+        [
+            pack(self.call_minutes, self.data_mb, self.sms_count),
+            pack(self.call_rate_cents, self.data_rate_cents, self.sms_rate_cents),
+            pack(self.total_charges_cents, self.remainder_cents, 0),
+            salt,
+        ]
+    }
+}
 
-        // Net settlement count: 0 to 6 (maximum possible in 3-party system) (requires 3 bits)
-        enforce_range_check(cs.clone(), &net_count, 6, 3, "net_settlement_count")?;
+/// Batch CDR Privacy Circuit
+/// Proves that up to CDR_BATCH_SIZE records each charge exactly
+/// usage × rate plus their remainder, that the public total is the sum of their charges and that
+/// the records are committed to by a public MiMC Merkle root, without
+/// revealing any individual record
+#[derive(Clone)]
+pub struct CDRBatchCircuit<F: PrimeField> {
+    // Private inputs: records padded to CDR_BATCH_SIZE, per-leaf blinding salts
+    pub records: Vec<Option<CDRBatchRecord>>,
+    pub salts: Vec<Option<F>>,
+
+    // Public inputs
+    pub records_root: Option<F>,       // Merkle root over record leaves
+    pub record_count: Option<F>,       // Number of real (non-padding) records
+    pub total_charges: Option<F>,      // Sum of record charges
+    pub period_hash: Option<F>,        // Hash of billing period
+    pub network_pair_hash: Option<F>,  // Hash of the network pair
+
+    params: MiMCParameters<F>,
+    _phantom: PhantomData<F>,
+}
 
-        // Total net amount: 0 to €300,000 (30,000,000 cents) - reasonable upper bound (requires 25 bits)
-        enforce_range_check(cs.clone(), &total_net, 30_000_000, 25, "total_net_amount")?;
+impl<F: PrimeField> CDRBatchCircuit<F> {
+    /// Build circuit and its witness; leaf hashes are computed in parallel
+    pub fn new(
+        records: &[CDRBatchRecord],
+        salts: &[u64],
+        period_hash: u64,
+        network_pair_hash: u64,
+        params: &MiMCParameters<F>,
+    ) -> Result<Self, SynthesisError> {
+        if records.is_empty() || records.len() > CDR_BATCH_SIZE || salts.len() != records.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
 
-        // Savings percentage: 0 to 100% (represented as 0-100) (requires 7 bits)
-        enforce_range_check(cs.clone(), &savings_pct, 100, 7, "savings_percentage")?;
+        let total_charges = records.iter()
+            .try_fold(0u64, |acc, r| acc.checked_add(r.total_charges_cents))
+            .ok_or(SynthesisError::Unsatisfiable)?;
+        let salts: Vec<F> = salts.iter().map(|s| F::from(*s)).collect();
+        let records_root = Self::records_root(records, &salts, params);
+
+        let mut padded_records = vec![Some(CDRBatchRecord::default()); CDR_BATCH_SIZE];
+        let mut padded_salts = vec![Some(F::zero()); CDR_BATCH_SIZE];
+        for (i, (record, salt)) in records.iter().zip(&salts).enumerate() {
+            padded_records[i] = Some(*record);
+            padded_salts[i] = Some(*salt);
+        }
 
-        // Constraint 4: Settlement Logic Validation
-        let gross_total = &tmo_vod + &vod_org + &org_tmo + &vod_tmo + &org_vod + &tmo_org;
+        Ok(Self {
+            records: padded_records,
+            salts: padded_salts,
+            records_root: Some(records_root),
+            record_count: Some(F::from(records.len() as u64)),
+            total_charges: Some(F::from(total_charges)),
+            period_hash: Some(F::from(period_hash)),
+            network_pair_hash: Some(F::from(network_pair_hash)),
+            params: params.clone(),
+            _phantom: PhantomData,
+        })
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            records: vec![None; CDR_BATCH_SIZE],
+            salts: vec![None; CDR_BATCH_SIZE],
+            records_root: None,
+            record_count: None,
+            total_charges: None,
+            period_hash: None,
+            network_pair_hash: None,
+            params: MiMCParameters::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Merkle root over record leaves; padding leaves are zero
+    pub fn records_root(records: &[CDRBatchRecord], salts: &[F], params: &MiMCParameters<F>) -> F {
+        let mut level: Vec<F> = records.par_iter()
+            .zip(salts.par_iter())
+            .map(|(record, salt)| params.hash(&record.leaf_inputs(*salt)))
+            .collect();
+        level.resize(CDR_BATCH_SIZE, F::zero());
+
+        while level.len() > 1 {
+            level = level.chunks(2)
+                .map(|pair| params.compress(pair[0], pair[1]))
+                .collect();
+        }
 
-        // Range check the gross total to prevent overflow (max €600,000)
-        enforce_range_check(cs.clone(), &gross_total, 60_000_000, 26, "gross_total")?;
+        level[0]
+    }
+
+    /// Public inputs in allocation order, for proof verification
+    pub fn public_inputs(
+        records_root: F,
+        record_count: u64,
+        total_charges: u64,
+        period_hash: u64,
+        network_pair_hash: u64,
+    ) -> Vec<F> {
+        vec![
+            records_root,
+            F::from(record_count),
+            F::from(total_charges),
+            F::from(period_hash),
+            F::from(network_pair_hash),
+        ]
+    }
+}
 
-        // Calculate savings amount (gross - net) and verify it's reasonable
-        let gross_minus_net = &gross_total - &total_net;
-        enforce_range_check(cs.clone(), &gross_minus_net, 60_000_000, 26, "savings_amount")?;
+impl<F: PrimeField> ConstraintSynthesizer<F> for CDRBatchCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        // Allocate public inputs first so their order matches `public_inputs`
+        let records_root = FpVar::new_input(cs.clone(), || {
+            self.records_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let record_count = FpVar::new_input(cs.clone(), || {
+            self.record_count.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let total_charges = FpVar::new_input(cs.clone(), || {
+            self.total_charges.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let _period_hash = FpVar::new_input(cs.clone(), || {
+            self.period_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let _network_pair_hash = FpVar::new_input(cs.clone(), || {
+            self.network_pair_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
 
-        // Constraint 5: Net Position Security Checks
-        // With 1M offset, positions should be in range [500K, 1.5M] representing ±€50,000
-        // This prevents extreme manipulations of net positions
+        let shift = F::from(1u128 << 64);
+        let mut leaves = Vec::with_capacity(CDR_BATCH_SIZE);
+        let mut charge_sum = FpVar::<F>::zero();
+        let mut active_count = FpVar::<F>::zero();
+        let mut previous_active = Boolean::TRUE;
+
+        for i in 0..CDR_BATCH_SIZE {
+            let record = self.records[i];
+            let field = |f: fn(&CDRBatchRecord) -> u64| {
+                record.map(|r| F::from(f(&r))).ok_or(SynthesisError::AssignmentMissing)
+            };
+
+            let call_minutes = FpVar::new_witness(cs.clone(), || field(|r| r.call_minutes))?;
+            let data_mb = FpVar::new_witness(cs.clone(), || field(|r| r.data_mb))?;
+            let sms_count = FpVar::new_witness(cs.clone(), || field(|r| r.sms_count))?;
+            let call_rate = FpVar::new_witness(cs.clone(), || field(|r| r.call_rate_cents))?;
+            let data_rate = FpVar::new_witness(cs.clone(), || field(|r| r.data_rate_cents))?;
+            let sms_rate = FpVar::new_witness(cs.clone(), || field(|r| r.sms_rate_cents))?;
+            let remainder = FpVar::new_witness(cs.clone(), || field(|r| r.remainder_cents))?;
+            let charges = FpVar::new_witness(cs.clone(), || field(|r| r.total_charges_cents))?;
+            let salt = FpVar::new_witness(cs.clone(), || {
+                self.salts[i].ok_or(SynthesisError::AssignmentMissing)
+            })?;
+
+            // Real records form a prefix of the batch: record_count is public
+            let index = i;
+            let active = Boolean::new_witness(cs.clone(), || {
+                let count = self.record_count.ok_or(SynthesisError::AssignmentMissing)?;
+                Ok(F::from(index as u64) < count)
+            })?;
+            active.and(&previous_active)?.enforce_equal(&active)?;
+            previous_active = active.clone();
+            let active_fp = FpVar::from(active);
+
+            // Constraint 1: Values are u64 (packing below relies on it)
+            for value in [&call_minutes, &data_mb, &sms_count, &call_rate, &data_rate, &sms_rate, &remainder, &charges] {
+                enforce_u64(cs.clone(), value)?;
+            }
+
+            // Constraint 2: Record charge = usage × rate + remainder, the remainder below the
+            // record's units unless it has none
+            let calculated = &call_minutes * &call_rate + &data_mb * &data_rate + &sms_count * &sms_rate + &remainder;
+            charges.enforce_equal(&calculated)?;
+            let units = &call_minutes + &data_mb + &sms_count;
+            let no_units = FpVar::from(units.is_eq(&FpVar::zero())?);
+            enforce_bits(cs.clone(), &(units + no_units * shift - &remainder - FpVar::one()), 66)?;
+
+            // Constraint 3: Leaf commits to the record (zero for padding)
+            let usage = &call_minutes + &data_mb * shift + &sms_count * (shift * shift);
+            let rates = &call_rate + &data_rate * shift + &sms_rate * (shift * shift);
+            let totals = &charges + &remainder * shift;
+            let leaf = self.params.hash_var(&[usage, rates, totals, salt])?;
+            leaves.push(&leaf * &active_fp);
+
+            charge_sum += &charges * &active_fp;
+            active_count += &active_fp;
+        }
 
-        // For simplicity, we ensure each position is within reasonable bounds
-        // using direct range checks on the offset values
-        enforce_range_check(cs.clone(), &tmo_pos, 1_500_000, 21, "tmobile_position")?;
-        enforce_range_check(cs.clone(), &vod_pos, 1_500_000, 21, "vodafone_position")?;
-        enforce_range_check(cs.clone(), &org_pos, 1_500_000, 21, "orange_position")?;
+        // Constraint 4: Public totals match the committed records
+        record_count.enforce_equal(&active_count)?;
+        total_charges.enforce_equal(&charge_sum)?;
+
+        // Constraint 5: Merkle root over the leaves
+        while leaves.len() > 1 {
+            let mut next = Vec::with_capacity(leaves.len() / 2);
+            for pair in leaves.chunks(2) {
+                next.push(self.params.compress_var(&pair[0], &pair[1])?);
+            }
+            leaves = next;
+        }
+        records_root.enforce_equal(&leaves[0])?;
 
         Ok(())
     }
@@ -418,73 +906,260 @@ mod tests {
     fn test_cdr_privacy_circuit() {
         let cs = ConstraintSystem::<Fr>::new_ref();
 
-        // Sample CDR data: 1000 minutes, 5000 MB, 200 SMS
-        let circuit = CDRPrivacyCircuit::new(
-            1000,  // call minutes
-            5000,  // data MB
-            200,   // SMS count
-            15,    // 15 cents/minute
-            5,     // 5 cents/MB
-            10,    // 10 cents/SMS
-            12345, // privacy salt
-            42000, // total: 1000*15 + 5000*5 + 200*10 = 15000 + 25000 + 2000 = 42000 cents
-            20240101, // period hash
-            98765,    // network pair hash
-            54321,    // commitment randomness
-        );
+        // Sample CDR data: 1000 minutes, 5000 MB, 200 SMS at 15, 5 and 10 cents a unit
+        let charges = CDRCharges {
+            voice: ServiceCharge::of(1000, 15000),
+            data: ServiceCharge::of(5000, 25000),
+            sms: ServiceCharge::of(200, 2000),
+            ..Default::default()
+        };
+        assert_eq!(charges.net_cents(), 42000);
+        let circuit = CDRPrivacyCircuit::new(&charges, 12345, 20240101, 98765, 54321);
 
         circuit.generate_constraints(cs.clone()).expect("Circuit should be satisfied");
 
         assert!(cs.is_satisfied().unwrap());
         println!("✅ CDR Privacy Circuit: {} constraints", cs.num_constraints());
+
+        // Charges that do not divide by their usage, slice usage, and a refund exceeding it all
+        let charges = CDRCharges {
+            voice: ServiceCharge::of(7, 100),
+            data: ServiceCharge::of(0, 40),
+            sms: ServiceCharge::of(3, 20),
+            other_cents: 250,
+            credit_cents: 1000,
+        };
+        assert_eq!(charges.voice, ServiceCharge { units: 7, rate_cents: 14, remainder_cents: 2 });
+        assert_eq!(charges.net_cents(), -590);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = CDRPrivacyCircuit::<Fr>::new(&charges, 12345, 20240101, 98765, 54321);
+        assert_eq!(circuit.net_charges, Some(CDRPrivacyCircuit::<Fr>::public_inputs(-590, 0, 0)[0]));
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
     }
 
     #[test]
     fn test_settlement_circuit() {
+        // Sample triangular netting scenario, [i][j] = j owes i (T-Mobile, Vodafone, Orange)
+        let bilateral = vec![
+            vec![0, 50000, 7500],
+            vec![10000, 0, 75000],
+            vec![25000, 15000, 0],
+        ];
+        let totals = SettlementTotals::of(&bilateral);
+        assert_eq!(SettlementTotals::net_positions(&bilateral), vec![22500, 20000, -42500]);
+        assert_eq!(totals, SettlementTotals { net_settlement_count: 3, total_net_amount: 42500, savings_percentage: 76 });
+
         let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = SettlementCalculationCircuit::new(&bilateral, 20240101).unwrap();
+        circuit.generate_constraints(cs.clone()).expect("Circuit should be satisfied");
+        assert!(cs.is_satisfied().unwrap());
+        println!("✅ Settlement Circuit: {} constraints", cs.num_constraints());
 
-        // Sample triangular netting scenario
-        let bilateral = [50000, 75000, 25000, 10000, 15000, 7500]; // All amounts in cents
-        let net_positions = [22500, 20000, -42500]; // Net positions (T-Mobile +€225, Vodafone +€200, Orange -€425)
-
-        let circuit = SettlementCalculationCircuit::new(
-            bilateral,
-            net_positions,
-            2,      // 2 net settlements
-            42500,  // €425 total net volume
-            [1, 2, 3, 4, 5, 6, 7, 8], // period hash as bytes
-            75,     // 75% savings
+        // A full consortium, every operator billing every other
+        let n = SETTLEMENT_MAX_OPERATORS;
+        let consortium: Vec<Vec<u64>> = (0..n)
+            .map(|i| (0..n).map(|j| if i == j { 0 } else { (i * 1000 + j * 7) as u64 }).collect())
+            .collect();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        SettlementCalculationCircuit::new(&consortium, 20240101).unwrap().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert!(SettlementCalculationCircuit::<Fr>::new(&vec![vec![0; n + 1]; n + 1], 20240101).is_err());
+
+        // Misstated totals must not satisfy
+        let tampers: [fn(&mut SettlementCalculationCircuit<Fr>); 3] = [
+            |circuit| circuit.total_net_amount = Some(Fr::from(40000u64)),
+            |circuit| circuit.net_settlement_count = Some(Fr::from(2u64)),
+            |circuit| circuit.savings_percentage = Some(Fr::from(80u64)),
+        ];
+        for tamper in tampers {
+            let mut circuit = SettlementCalculationCircuit::new(&bilateral, 20240101).unwrap();
+            tamper(&mut circuit);
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            assert!(!cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_settlement_circuit_padding() {
+        let synthesize = |circuit: SettlementCalculationCircuit<Fr>| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            cs
+        };
+
+        // Two operators fill the top-left of the matrix, the rest is zero
+        let pair = vec![vec![0, 30000], vec![10000, 0]];
+        let totals = SettlementTotals::of(&pair);
+        assert_eq!(totals, SettlementTotals { net_settlement_count: 2, total_net_amount: 20000, savings_percentage: 50 });
+        let circuit = SettlementCalculationCircuit::<Fr>::new(&pair, 20240101).unwrap();
+        let max = SETTLEMENT_MAX_OPERATORS;
+        assert_eq!(circuit.bilateral.len(), max * max);
+        assert_eq!(circuit.bilateral[1], Some(Fr::from(30000u64)));
+        assert!(circuit.bilateral.iter().enumerate()
+            .filter(|(idx, _)| idx / max >= 2 || idx % max >= 2)
+            .all(|(_, cell)| *cell == Some(Fr::from(0u64))));
+
+        // Same circuit shape whatever the consortium size, public inputs in the order verifiers pass them
+        let cs = synthesize(circuit);
+        assert!(cs.is_satisfied().unwrap());
+        let triangle = vec![vec![0, 1, 2], vec![3, 0, 4], vec![5, 6, 0]];
+        let triangle_cs = synthesize(SettlementCalculationCircuit::new(&triangle, 20240101).unwrap());
+        assert_eq!(cs.num_constraints(), triangle_cs.num_constraints());
+        assert_eq!(
+            cs.borrow().unwrap().instance_assignment[1..],
+            SettlementCalculationCircuit::<Fr>::public_inputs(&totals, 20240101)[..]
         );
 
-        circuit.generate_constraints(cs.clone()).expect("Circuit should be satisfied");
+        // Nothing owed nets to nothing
+        let idle = vec![vec![0; 4]; 4];
+        assert_eq!(SettlementTotals::of(&idle), SettlementTotals::default());
+        assert!(synthesize(SettlementCalculationCircuit::new(&idle, 20240101).unwrap()).is_satisfied().unwrap());
 
-        assert!(cs.is_satisfied().unwrap());
-        println!("✅ Settlement Circuit: {} constraints", cs.num_constraints());
+        // An amount hidden in the padding changes the netting the totals claim
+        let mut circuit = SettlementCalculationCircuit::<Fr>::new(&pair, 20240101).unwrap();
+        circuit.bilateral[max - 1] = Some(Fr::from(5000u64));
+        assert!(!synthesize(circuit).is_satisfied().unwrap());
+
+        // Matrices the circuit cannot represent
+        assert!(SettlementCalculationCircuit::<Fr>::new(&[vec![0, 1], vec![1]], 20240101).is_err());
+        assert!(SettlementCalculationCircuit::<Fr>::new(&[vec![1, 0], vec![0, 0]], 20240101).is_err());
+        assert!(SettlementCalculationCircuit::<Fr>::new(&[vec![0, 1 << SETTLEMENT_AMOUNT_BITS], vec![0, 0]], 20240101).is_err());
     }
 
     #[test]
     fn test_circuit_unsatisfied() {
+        let charges = CDRCharges {
+            voice: ServiceCharge::of(1000, 15000),
+            data: ServiceCharge::of(5000, 25000),
+            sms: ServiceCharge::of(200, 2000),
+            ..Default::default()
+        };
+        let unsatisfied = |circuit: CDRPrivacyCircuit<Fr>| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).expect("Constraint generation should work");
+            !cs.is_satisfied().unwrap()
+        };
+
+        // Wrong net charge
+        let mut circuit = CDRPrivacyCircuit::new(&charges, 12345, 20240101, 98765, 54321);
+        circuit.net_charges = Some(Fr::from(CDR_CHARGE_OFFSET + 99999));
+        assert!(unsatisfied(circuit));
+
+        // Call rate above the tariff bound, even with a consistent total
+        let over_rate = CDRCharges { voice: ServiceCharge::of(10, 10 * (CDR_MAX_CALL_RATE_CENTS + 1)), ..Default::default() };
+        assert!(unsatisfied(CDRPrivacyCircuit::new(&over_rate, 12345, 20240101, 98765, 54321)));
+
+        // A remainder of a whole unit or more hides a higher rate
+        let mut circuit = CDRPrivacyCircuit::new(&charges, 12345, 20240101, 98765, 54321);
+        circuit.call_rate_cents = Some(Fr::from(14u64));
+        circuit.call_remainder_cents = Some(Fr::from(1000u64));
+        assert!(unsatisfied(circuit));
+
+        // Credits beyond their bound
+        let over_credit = CDRCharges { credit_cents: CDR_MAX_CHARGE_CENTS + 1, ..charges };
+        assert!(unsatisfied(CDRPrivacyCircuit::new(&over_credit, 12345, 20240101, 98765, 54321)));
+        println!("✅ Invalid circuit correctly unsatisfied");
+    }
+
+    #[test]
+    fn test_netting_correctness_circuit() {
+        // 4-party cycle A→B→C→D→A of 100 plus A→C 50; cycle fully netted
+        let bilateral = vec![
+            vec![0, 100, 50, 0],
+            vec![0, 0, 100, 0],
+            vec![0, 0, 0, 100],
+            vec![100, 0, 0, 0],
+        ];
+        let residual = vec![
+            vec![0, 0, 50, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+        ];
+        let net_positions = [-50, 0, 50, 0];
+
         let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = NettingCorrectnessCircuit::new(&bilateral, &residual, &net_positions).unwrap();
+        circuit.generate_constraints(cs.clone()).expect("Circuit should be satisfied");
+        assert!(cs.is_satisfied().unwrap());
+        println!("✅ Netting Correctness Circuit: {} constraints", cs.num_constraints());
 
-        // Invalid circuit: wrong total calculation
-        let circuit = CDRPrivacyCircuit::new(
-            1000,  // 1000 minutes
-            5000,  // 5000 MB
-            200,   // 200 SMS
-            15,    // 15 cents/min
-            5,     // 5 cents/MB
-            10,    // 10 cents/SMS
-            12345, // salt
-            99999, // WRONG total (should be 42000)
-            20240101,
-            98765,
-            54321,
-        );
+        // Residual larger than the original obligation must not satisfy
+        let mut inflated = residual.clone();
+        inflated[0][1] = 150;
+        inflated[1][0] = 150;
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = NettingCorrectnessCircuit::new(&bilateral, &inflated, &net_positions).unwrap();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
 
-        circuit.generate_constraints(cs.clone()).expect("Constraint generation should work");
+        // Wrong net positions must not satisfy
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = NettingCorrectnessCircuit::new(&bilateral, &residual, &[-40, 0, 40, 0]).unwrap();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_cdr_batch_circuit() {
+        let params = MiMCParameters::<Fr>::new();
+        let records: Vec<CDRBatchRecord> = (1..=5u64)
+            .map(|i| CDRBatchRecord {
+                call_minutes: 10 * i,
+                data_mb: 100 * i,
+                sms_count: i,
+                call_rate_cents: 15,
+                data_rate_cents: 5,
+                sms_rate_cents: 10,
+                remainder_cents: 0,
+                total_charges_cents: 10 * i * 15 + 100 * i * 5 + i * 10,
+            })
+            .collect();
+        let salts: Vec<u64> = (0..records.len() as u64).map(|i| 1000 + i).collect();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = CDRBatchCircuit::new(&records, &salts, 2024_03, 42, &params).unwrap();
+        let expected_total: u64 = records.iter().map(|r| r.total_charges_cents).sum();
+        assert_eq!(circuit.total_charges, Some(Fr::from(expected_total)));
+        circuit.generate_constraints(cs.clone()).expect("Circuit should be satisfied");
+        assert!(cs.is_satisfied().unwrap());
+        println!("✅ Batch CDR Circuit: {} records, {} constraints", records.len(), cs.num_constraints());
 
-        // Circuit should NOT be satisfied due to wrong total
+        // A record whose charge does not match usage × rate must not satisfy
+        let mut tampered = records.clone();
+        tampered[2].total_charges_cents += 1;
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = CDRBatchCircuit::new(&tampered, &salts, 2024_03, 42, &params).unwrap();
+        circuit.generate_constraints(cs.clone()).unwrap();
         assert!(!cs.is_satisfied().unwrap());
-        println!("✅ Invalid circuit correctly unsatisfied");
+
+        // Root must change with any record
+        let root = CDRBatchCircuit::records_root(&records, &salts.iter().map(|s| Fr::from(*s)).collect::<Vec<_>>(), &params);
+        let other = CDRBatchCircuit::records_root(&tampered, &salts.iter().map(|s| Fr::from(*s)).collect::<Vec<_>>(), &params);
+        assert_ne!(root, other);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_cdr_batch_circuit_remainder() {
+        let params = MiMCParameters::<Fr>::new();
+        let satisfied = |record: CDRBatchRecord| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let circuit = CDRBatchCircuit::new(&[record], &[7], 2024_03, 42, &params).unwrap();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+
+        // 1000 cents for 3 MB: 333 cents a megabyte and one cent left over
+        let data = CDRBatchRecord { data_mb: 3, data_rate_cents: 333, remainder_cents: 1, total_charges_cents: 1000, ..Default::default() };
+        assert!(satisfied(data));
+
+        // A remainder of the record's units or more hides a higher rate
+        assert!(!satisfied(CDRBatchRecord { data_rate_cents: 0, remainder_cents: 1000, ..data }));
+        assert!(!satisfied(CDRBatchRecord { data_rate_cents: 332, remainder_cents: 4, ..data }));
+
+        // A charge without units is all remainder
+        assert!(satisfied(CDRBatchRecord { remainder_cents: 250, total_charges_cents: 250, ..Default::default() }));
+    }
+}
@@ -0,0 +1,101 @@
+// MiMC hash for Merkle commitments inside ZK circuits
+// x^5 MiMC permutation in Miyaguchi-Preneel mode, with matching native and R1CS
+// implementations. x^5 is a permutation of the BN254 scalar field (gcd(5, r - 1) = 1)
+use ark_ff::PrimeField;
+use ark_r1cs_std::fields::{fp::FpVar, FieldVar};
+use ark_relations::r1cs::SynthesisError;
+
+use crate::primitives::primitives::hash_data;
+
+/// Number of MiMC rounds, ceil(254 / log2(5)) for the BN254 scalar field
+pub const MIMC_ROUNDS: usize = 110;
+
+/// MiMC round constants
+/// Derived deterministically from SHA-256 so prover and verifier agree without
+/// distributing parameters
+#[derive(Debug, Clone)]
+pub struct MiMCParameters<F: PrimeField> {
+    round_constants: Vec<F>,
+}
+
+impl<F: PrimeField> MiMCParameters<F> {
+    pub fn new() -> Self {
+        let round_constants = (0..MIMC_ROUNDS)
+            .map(|i| {
+                let digest = hash_data(format!("sp-cdr-mimc-round-{}", i).as_bytes());
+                F::from_le_bytes_mod_order(digest.as_bytes())
+            })
+            .collect();
+
+        Self { round_constants }
+    }
+
+    /// Two-to-one compression: E_left(right) + left + right
+    pub fn compress(&self, left: F, right: F) -> F {
+        let mut state = right;
+        for constant in &self.round_constants {
+            let t = state + left + constant;
+            let t2 = t.square();
+            state = t2.square() * t;
+        }
+        state + left + left + right
+    }
+
+    /// Hash a sequence of field elements, domain separated by length
+    pub fn hash(&self, inputs: &[F]) -> F {
+        inputs.iter().fold(F::from(inputs.len() as u64), |acc, input| self.compress(acc, *input))
+    }
+
+    /// In-circuit two-to-one compression, 3 constraints per round
+    pub fn compress_var(&self, left: &FpVar<F>, right: &FpVar<F>) -> Result<FpVar<F>, SynthesisError> {
+        let mut state = right.clone();
+        for constant in &self.round_constants {
+            let t = &state + left + *constant;
+            let t2 = t.square()?;
+            state = t2.square()? * &t;
+        }
+        Ok(state + left + left + right)
+    }
+
+    /// In-circuit hash of a sequence of field elements
+    pub fn hash_var(&self, inputs: &[FpVar<F>]) -> Result<FpVar<F>, SynthesisError> {
+        let mut acc = FpVar::constant(F::from(inputs.len() as u64));
+        for input in inputs {
+            acc = self.compress_var(&acc, input)?;
+        }
+        Ok(acc)
+    }
+}
+
+impl<F: PrimeField> Default for MiMCParameters<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_native_and_circuit_hash_agree() {
+        let params = MiMCParameters::<Fr>::new();
+        let inputs = [Fr::from(42u64), Fr::from(7u64), Fr::from(1_000_000u64)];
+        let expected = params.hash(&inputs);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let vars: Vec<FpVar<Fr>> = inputs.iter()
+            .map(|v| FpVar::new_witness(cs.clone(), || Ok(*v)).unwrap())
+            .collect();
+        let result = params.hash_var(&vars).unwrap();
+
+        assert_eq!(result.value().unwrap(), expected);
+        assert!(cs.is_satisfied().unwrap());
+
+        // Order matters
+        assert_ne!(params.hash(&[inputs[1], inputs[0], inputs[2]]), expected);
+    }
+}
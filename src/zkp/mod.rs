@@ -3,9 +3,14 @@
 
 pub use verifying_key::*;
 pub use albatross_zkp::*;
+pub use aggregation::AggregateProof;
 pub mod verifying_key;
 pub mod albatross_zkp;
+pub mod aggregation;
 pub mod circuits;
+pub mod mimc;
+pub mod mpc;
+pub mod proof_cache;
 pub mod trusted_setup;
 
 #[allow(dead_code)]
@@ -0,0 +1,560 @@
+// Multi-party Groth16 parameter generation over BN254
+// Phase 1 is a powers of tau ceremony shared by all circuits, phase 2 specializes its result to each
+// circuit and randomizes delta. Every contribution comes with an update proof checked by pairings
+// against the previous state, so the parameters are sound as long as one contributor was honest
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{FftField, Field, One, UniformRand, Zero};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+
+/// Sections the phase 1 state is stored and transferred in, each well below a response limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowersSection {
+    TauG1,
+    TauG2,
+    AlphaTauG1,
+    /// `[βτ^i]G1` followed by `[β]G2`
+    BetaTauG1,
+}
+
+pub const POWERS_SECTIONS: [PowersSection; 4] =
+    [PowersSection::TauG1, PowersSection::TauG2, PowersSection::AlphaTauG1, PowersSection::BetaTauG1];
+
+/// Phase 1 state for circuits of up to `degree` constraints and inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowersOfTau {
+    /// `[τ^i]G1` for `i < 2 * degree - 1`
+    pub tau_g1: Vec<G1Affine>,
+    /// `[τ^i]G2` for `i < degree`
+    pub tau_g2: Vec<G2Affine>,
+    /// `[ατ^i]G1` for `i < degree`
+    pub alpha_tau_g1: Vec<G1Affine>,
+    /// `[βτ^i]G1` for `i < degree`
+    pub beta_tau_g1: Vec<G1Affine>,
+    pub beta_g2: G2Affine,
+}
+
+/// Proof of a phase 1 contribution: the new `[τ]G1`, `[α]G1` and `[β]G1` and the factors applied, in G2
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase1Update {
+    pub tau_g1: G1Affine,
+    pub alpha_g1: G1Affine,
+    pub beta_g1: G1Affine,
+    pub tau_g2: G2Affine,
+    pub alpha_g2: G2Affine,
+    pub beta_g2: G2Affine,
+}
+
+/// Proof of a phase 2 contribution: the new `[δ]G1` and the factor applied, in G2
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase2Update {
+    pub delta_g1: G1Affine,
+    pub delta_g2: G2Affine,
+}
+
+impl PowersOfTau {
+    /// State before the first contribution, every power the generator
+    pub fn new(degree: usize) -> Self {
+        let degree = degree.max(2).next_power_of_two();
+        Self {
+            tau_g1: vec![G1Affine::generator(); 2 * degree - 1],
+            tau_g2: vec![G2Affine::generator(); degree],
+            alpha_tau_g1: vec![G1Affine::generator(); degree],
+            beta_tau_g1: vec![G1Affine::generator(); degree],
+            beta_g2: G2Affine::generator(),
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.tau_g2.len()
+    }
+
+    /// `[τ]G1`, `[α]G1` and `[β]G1`, which contributions are chained through
+    pub fn headline(&self) -> [G1Affine; 3] {
+        [self.tau_g1[1], self.alpha_tau_g1[0], self.beta_tau_g1[0]]
+    }
+
+    /// Multiply τ, α and β by fresh randomness, which is dropped on return
+    pub fn contribute<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Phase1Update {
+        let (tau, alpha, beta) = (nonzero_scalar(rng), nonzero_scalar(rng), nonzero_scalar(rng));
+        let mut powers = Vec::with_capacity(self.tau_g1.len());
+        let mut power = Fr::one();
+        for _ in 0..self.tau_g1.len() {
+            powers.push(power);
+            power *= tau;
+        }
+
+        let degree = self.degree();
+        scale(&mut self.tau_g1, &powers, Fr::one());
+        scale(&mut self.tau_g2, &powers[..degree], Fr::one());
+        scale(&mut self.alpha_tau_g1, &powers[..degree], alpha);
+        scale(&mut self.beta_tau_g1, &powers[..degree], beta);
+        self.beta_g2 = (self.beta_g2 * beta).into_affine();
+
+        let g2 = G2Affine::generator();
+        let [tau_g1, alpha_g1, beta_g1] = self.headline();
+        Phase1Update {
+            tau_g1,
+            alpha_g1,
+            beta_g1,
+            tau_g2: (g2 * tau).into_affine(),
+            alpha_g2: (g2 * alpha).into_affine(),
+            beta_g2: (g2 * beta).into_affine(),
+        }
+    }
+
+    /// Check the state holds consecutive powers of one τ, scaled by one α and one β
+    pub fn check_structure<R: RngCore>(&self, rng: &mut R) -> Result<()> {
+        let degree = self.degree();
+        if degree < 2 || !degree.is_power_of_two() || self.tau_g1.len() != 2 * degree - 1
+            || self.alpha_tau_g1.len() != degree || self.beta_tau_g1.len() != degree
+        {
+            return Err(invalid("powers of tau have inconsistent lengths"));
+        }
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        if self.tau_g1[0] != g1 || self.tau_g2[0] != g2 {
+            return Err(invalid("powers of tau do not start at the generators"));
+        }
+        if self.headline().iter().any(|point| point.is_zero()) || self.tau_g2[1].is_zero() {
+            return Err(invalid("powers of tau contain the identity"));
+        }
+
+        let tau_ratio = (g2, self.tau_g2[1]);
+        let (a, b) = merge_pairs(&self.tau_g1[..self.tau_g1.len() - 1], &self.tau_g1[1..], rng);
+        if !same_ratio((a, b), tau_ratio) {
+            return Err(invalid("[τ^i]G1 are not consecutive powers"));
+        }
+        let (a, b) = merge_pairs(&self.tau_g2[..degree - 1], &self.tau_g2[1..], rng);
+        if !same_ratio((g1, self.tau_g1[1]), (a, b)) {
+            return Err(invalid("[τ^i]G2 are not consecutive powers"));
+        }
+        let (a, b) = merge_pairs(&self.alpha_tau_g1[..degree - 1], &self.alpha_tau_g1[1..], rng);
+        if !same_ratio((a, b), tau_ratio) {
+            return Err(invalid("[ατ^i]G1 are not consecutive powers"));
+        }
+        let (a, b) = merge_pairs(&self.beta_tau_g1[..degree - 1], &self.beta_tau_g1[1..], rng);
+        if !same_ratio((a, b), tau_ratio) {
+            return Err(invalid("[βτ^i]G1 are not consecutive powers"));
+        }
+        if !same_ratio((g1, self.beta_tau_g1[0]), (g2, self.beta_g2)) {
+            return Err(invalid("[β]G2 does not match [β]G1"));
+        }
+        Ok(())
+    }
+
+    /// Serialized section of the state
+    pub fn section(&self, section: PowersSection) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let written = match section {
+            PowersSection::TauG1 => self.tau_g1.serialize_compressed(&mut bytes),
+            PowersSection::TauG2 => self.tau_g2.serialize_compressed(&mut bytes),
+            PowersSection::AlphaTauG1 => self.alpha_tau_g1.serialize_compressed(&mut bytes),
+            PowersSection::BetaTauG1 => self.beta_tau_g1.serialize_compressed(&mut bytes)
+                .and_then(|_| self.beta_g2.serialize_compressed(&mut bytes)),
+        };
+        written.map_err(|e| BlockchainError::Serialization(format!("Powers of tau serialization error: {}", e)))?;
+        Ok(bytes)
+    }
+
+    /// State from its sections, in `POWERS_SECTIONS` order
+    pub fn from_sections(sections: &[Vec<u8>; 4]) -> Result<Self> {
+        let error = |e: ark_serialize::SerializationError| {
+            BlockchainError::Serialization(format!("Powers of tau deserialization error: {}", e))
+        };
+        let (beta_tau_g1, beta_g2) = <(Vec<G1Affine>, G2Affine)>::deserialize_compressed(&sections[3][..]).map_err(error)?;
+        Ok(Self {
+            tau_g1: Vec::deserialize_compressed(&sections[0][..]).map_err(error)?,
+            tau_g2: Vec::deserialize_compressed(&sections[1][..]).map_err(error)?,
+            alpha_tau_g1: Vec::deserialize_compressed(&sections[2][..]).map_err(error)?,
+            beta_tau_g1,
+            beta_g2,
+        })
+    }
+
+    /// Hash of every section, which the contribution producing the state records
+    pub fn hash(&self) -> Result<Blake2bHash> {
+        let mut bytes = Vec::new();
+        for section in POWERS_SECTIONS {
+            bytes.extend(self.section(section)?);
+        }
+        Ok(Blake2bHash::from_data(&bytes))
+    }
+}
+
+impl Phase1Update {
+    /// Headline of the state before any contribution
+    pub fn initial_headline() -> [G1Affine; 3] {
+        [G1Affine::generator(); 3]
+    }
+
+    pub fn headline(&self) -> [G1Affine; 3] {
+        [self.tau_g1, self.alpha_g1, self.beta_g1]
+    }
+
+    /// Check the update moved τ, α and β of `previous` by the factors it claims
+    pub fn verify_after(&self, previous: &[G1Affine; 3]) -> Result<()> {
+        if self.headline().iter().any(|point| point.is_zero())
+            || [self.tau_g2, self.alpha_g2, self.beta_g2].iter().any(|point| point.is_zero())
+        {
+            return Err(invalid("phase 1 update contains the identity"));
+        }
+        let g2 = G2Affine::generator();
+        let factors = [self.tau_g2, self.alpha_g2, self.beta_g2];
+        for ((previous, next), factor) in previous.iter().zip(self.headline()).zip(factors) {
+            if !same_ratio((*previous, next), (g2, factor)) {
+                return Err(invalid("phase 1 update does not follow the previous contribution"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (self.headline().to_vec(), vec![self.tau_g2, self.alpha_g2, self.beta_g2]).serialize_compressed(&mut bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("Phase 1 update serialization error: {}", e)))?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (g1, g2) = <(Vec<G1Affine>, Vec<G2Affine>)>::deserialize_compressed(bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("Phase 1 update deserialization error: {}", e)))?;
+        match (g1.as_slice(), g2.as_slice()) {
+            ([tau_g1, alpha_g1, beta_g1], [tau_g2, alpha_g2, beta_g2]) => Ok(Self {
+                tau_g1: *tau_g1,
+                alpha_g1: *alpha_g1,
+                beta_g1: *beta_g1,
+                tau_g2: *tau_g2,
+                alpha_g2: *alpha_g2,
+                beta_g2: *beta_g2,
+            }),
+            _ => Err(BlockchainError::Serialization("Phase 1 update has the wrong number of points".to_string())),
+        }
+    }
+}
+
+impl Phase2Update {
+    /// Check the update moved δ of the previous contribution by the factor it claims
+    pub fn verify_after(&self, previous_delta_g1: &G1Affine) -> Result<()> {
+        if self.delta_g1.is_zero() || self.delta_g2.is_zero() {
+            return Err(invalid("phase 2 update contains the identity"));
+        }
+        if !same_ratio((*previous_delta_g1, self.delta_g1), (G2Affine::generator(), self.delta_g2)) {
+            return Err(invalid("phase 2 update does not follow the previous contribution"));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (self.delta_g1, self.delta_g2).serialize_compressed(&mut bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("Phase 2 update serialization error: {}", e)))?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (delta_g1, delta_g2) = <(G1Affine, G2Affine)>::deserialize_compressed(bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("Phase 2 update deserialization error: {}", e)))?;
+        Ok(Self { delta_g1, delta_g2 })
+    }
+}
+
+/// R1CS matrices of a circuit, synthesized the way the Groth16 generator does
+pub fn circuit_matrices<C: ConstraintSynthesizer<Fr>>(circuit: C) -> Result<ConstraintMatrices<Fr>> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())
+        .map_err(|e| BlockchainError::Crypto(format!("Circuit synthesis failed: {}", e)))?;
+    cs.finalize();
+    cs.to_matrices()
+        .ok_or_else(|| BlockchainError::Crypto("Circuit has no constraint matrices".to_string()))
+}
+
+/// Size of the evaluation domain the prover reduces a circuit over
+pub fn domain_size(matrices: &ConstraintMatrices<Fr>) -> usize {
+    (matrices.num_constraints + matrices.num_instance_variables).next_power_of_two()
+}
+
+/// Phase 2 parameters of a circuit before any contribution, δ and γ being one
+/// Mirrors the reduction of the arkworks Groth16 generator, evaluated in the exponent
+pub fn initial_parameters(powers: &PowersOfTau, matrices: &ConstraintMatrices<Fr>) -> Result<ProvingKey<Bn254>> {
+    let n = domain_size(matrices);
+    if powers.degree() < n {
+        return Err(BlockchainError::Crypto(format!(
+            "Powers of tau of degree {} are too small for a circuit of domain {}", powers.degree(), n
+        )));
+    }
+    let lagrange_g1 = ifft_in_exponent(&powers.tau_g1[..n])?;
+    let lagrange_g2 = ifft_in_exponent(&powers.tau_g2[..n])?;
+    let alpha_lagrange = ifft_in_exponent(&powers.alpha_tau_g1[..n])?;
+    let beta_lagrange = ifft_in_exponent(&powers.beta_tau_g1[..n])?;
+
+    let num_instance = matrices.num_instance_variables;
+    let num_variables = num_instance + matrices.num_witness_variables;
+    let mut a = vec![G1Projective::zero(); num_variables];
+    let mut b_g1 = vec![G1Projective::zero(); num_variables];
+    let mut b_g2 = vec![G2Projective::zero(); num_variables];
+    // β·A(τ) + α·B(τ) + C(τ), split into the verifying key's inputs and the witness query
+    let mut abc = vec![G1Projective::zero(); num_variables];
+
+    // Inputs are bound by the rows following the constraints, as in the prover's reduction
+    for input in 0..num_instance {
+        let row = matrices.num_constraints + input;
+        a[input] += lagrange_g1[row];
+        abc[input] += beta_lagrange[row];
+    }
+    for (row, ((a_row, b_row), c_row)) in matrices.a.iter().zip(&matrices.b).zip(&matrices.c).enumerate() {
+        for (coeff, variable) in a_row {
+            a[*variable] += lagrange_g1[row] * *coeff;
+            abc[*variable] += beta_lagrange[row] * *coeff;
+        }
+        for (coeff, variable) in b_row {
+            b_g1[*variable] += lagrange_g1[row] * *coeff;
+            b_g2[*variable] += lagrange_g2[row] * *coeff;
+            abc[*variable] += alpha_lagrange[row] * *coeff;
+        }
+        for (coeff, variable) in c_row {
+            abc[*variable] += lagrange_g1[row] * *coeff;
+        }
+    }
+
+    // [τ^i Z(τ)]G1 for the vanishing polynomial Z(x) = x^n - 1
+    let h_query: Vec<G1Projective> = (0..n - 1)
+        .map(|i| powers.tau_g1[i + n].into_group() - powers.tau_g1[i])
+        .collect();
+    let abc = G1Projective::normalize_batch(&abc);
+
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1: powers.alpha_tau_g1[0],
+            beta_g2: powers.beta_g2,
+            gamma_g2: G2Affine::generator(),
+            delta_g2: G2Affine::generator(),
+            gamma_abc_g1: abc[..num_instance].to_vec(),
+        },
+        beta_g1: powers.beta_tau_g1[0],
+        delta_g1: G1Affine::generator(),
+        a_query: G1Projective::normalize_batch(&a),
+        b_g1_query: G1Projective::normalize_batch(&b_g1),
+        b_g2_query: G2Projective::normalize_batch(&b_g2),
+        h_query: G1Projective::normalize_batch(&h_query),
+        l_query: abc[num_instance..].to_vec(),
+    })
+}
+
+/// Multiply δ by fresh randomness, dividing the queries that depend on it
+pub fn contribute_delta<R: RngCore + CryptoRng>(parameters: &mut ProvingKey<Bn254>, rng: &mut R) -> Phase2Update {
+    let delta = nonzero_scalar(rng);
+    let inverse = delta.inverse().expect("delta is nonzero");
+    parameters.delta_g1 = (parameters.delta_g1 * delta).into_affine();
+    parameters.vk.delta_g2 = (parameters.vk.delta_g2 * delta).into_affine();
+    scale_all(&mut parameters.h_query, inverse);
+    scale_all(&mut parameters.l_query, inverse);
+
+    Phase2Update {
+        delta_g1: parameters.delta_g1,
+        delta_g2: (G2Affine::generator() * delta).into_affine(),
+    }
+}
+
+/// Check `parameters` differ from `initial` only by a δ applied consistently
+pub fn verify_parameters<R: RngCore>(initial: &ProvingKey<Bn254>, parameters: &ProvingKey<Bn254>, rng: &mut R) -> Result<()> {
+    let (vk, initial_vk) = (&parameters.vk, &initial.vk);
+    if vk.alpha_g1 != initial_vk.alpha_g1 || vk.beta_g2 != initial_vk.beta_g2 || vk.gamma_g2 != initial_vk.gamma_g2
+        || vk.gamma_abc_g1 != initial_vk.gamma_abc_g1 || parameters.beta_g1 != initial.beta_g1
+        || parameters.a_query != initial.a_query || parameters.b_g1_query != initial.b_g1_query
+        || parameters.b_g2_query != initial.b_g2_query
+    {
+        return Err(invalid("parameters differ from the ones the powers of tau derive"));
+    }
+    if parameters.h_query.len() != initial.h_query.len() || parameters.l_query.len() != initial.l_query.len() {
+        return Err(invalid("parameters have queries of the wrong length"));
+    }
+    if parameters.delta_g1.is_zero() || !same_ratio((initial.delta_g1, parameters.delta_g1), (initial_vk.delta_g2, vk.delta_g2)) {
+        return Err(invalid("[δ]G2 does not match [δ]G1"));
+    }
+
+    // The queries were divided by the same δ
+    let delta_ratio = (initial_vk.delta_g2, vk.delta_g2);
+    let (initial_h, h) = merge_pairs(&initial.h_query, &parameters.h_query, rng);
+    if !same_ratio((h, initial_h), delta_ratio) {
+        return Err(invalid("h query is not divided by δ"));
+    }
+    let (initial_l, l) = merge_pairs(&initial.l_query, &parameters.l_query, rng);
+    if !same_ratio((l, initial_l), delta_ratio) {
+        return Err(invalid("l query is not divided by δ"));
+    }
+    Ok(())
+}
+
+fn invalid(reason: &str) -> BlockchainError {
+    BlockchainError::Crypto(format!("Invalid trusted setup contribution: {}", reason))
+}
+
+fn nonzero_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Fr {
+    loop {
+        let scalar = Fr::rand(rng);
+        if !scalar.is_zero() {
+            return scalar;
+        }
+    }
+}
+
+/// Multiply each point by its power and `factor`
+fn scale<G: AffineRepr<ScalarField = Fr>>(points: &mut [G], powers: &[Fr], factor: Fr) {
+    let scaled: Vec<G::Group> = points.iter().zip(powers).map(|(point, power)| *point * (*power * factor)).collect();
+    points.copy_from_slice(&G::Group::normalize_batch(&scaled));
+}
+
+fn scale_all<G: AffineRepr<ScalarField = Fr>>(points: &mut [G], factor: Fr) {
+    let scaled: Vec<G::Group> = points.iter().map(|point| *point * factor).collect();
+    points.copy_from_slice(&G::Group::normalize_batch(&scaled));
+}
+
+/// Whether `g1.1 / g1.0` equals `g2.1 / g2.0`, as exponents
+fn same_ratio(g1: (G1Affine, G1Affine), g2: (G2Affine, G2Affine)) -> bool {
+    Bn254::pairing(g1.0, g2.1) == Bn254::pairing(g1.1, g2.0)
+}
+
+/// One random linear combination applied to both point lists, so that a single pairing
+/// checks every pair has the same ratio
+fn merge_pairs<G: AffineRepr<ScalarField = Fr>, R: RngCore>(v1: &[G], v2: &[G], rng: &mut R) -> (G, G) {
+    let scalars: Vec<Fr> = (0..v1.len()).map(|_| Fr::rand(rng)).collect();
+    (
+        G::Group::msm_unchecked(v1, &scalars).into_affine(),
+        G::Group::msm_unchecked(v2, &scalars).into_affine(),
+    )
+}
+
+/// Lagrange basis `[L_j(τ)]` of the radix-2 domain of `powers.len()` from `[τ^i]`,
+/// an inverse FFT over the group
+fn ifft_in_exponent<G: AffineRepr<ScalarField = Fr>>(powers: &[G]) -> Result<Vec<G>> {
+    let n = powers.len();
+    let omega_inverse = Fr::get_root_of_unity(n as u64)
+        .and_then(|omega| omega.inverse())
+        .ok_or_else(|| BlockchainError::Crypto(format!("No evaluation domain of size {}", n)))?;
+    let mut values: Vec<G::Group> = powers.iter().map(|point| point.into_group()).collect();
+
+    if n > 1 {
+        let bits = n.trailing_zeros();
+        for i in 0..n {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                values.swap(i, j);
+            }
+        }
+    }
+    let mut half = 1;
+    while half < n {
+        let step = omega_inverse.pow([(n / (2 * half)) as u64]);
+        for start in (0..n).step_by(2 * half) {
+            let mut twiddle = Fr::one();
+            for i in start..start + half {
+                let odd = values[i + half] * twiddle;
+                values[i + half] = values[i] - odd;
+                values[i] += odd;
+                twiddle *= step;
+            }
+        }
+        half *= 2;
+    }
+
+    let n_inverse = Fr::from(n as u64).inverse().expect("domain size is nonzero");
+    let values: Vec<G::Group> = values.into_iter().map(|value| value * n_inverse).collect();
+    Ok(G::Group::normalize_batch(&values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_groth16::Groth16;
+    use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+    use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+    use ark_snark::SNARK;
+    use ark_std::test_rng;
+
+    /// Knowledge of `x` with `x^3 + x + 5 = y` for public `y`
+    #[derive(Clone)]
+    struct CubicCircuit {
+        x: Option<Fr>,
+        y: Option<Fr>,
+    }
+
+    impl ConstraintSynthesizer<Fr> for CubicCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> std::result::Result<(), SynthesisError> {
+            let y = FpVar::new_input(cs.clone(), || self.y.ok_or(SynthesisError::AssignmentMissing))?;
+            let x = FpVar::new_witness(cs, || self.x.ok_or(SynthesisError::AssignmentMissing))?;
+            (&x * &x * &x + &x + Fr::from(5u64)).enforce_equal(&y)
+        }
+    }
+
+    #[test]
+    fn test_multi_party_parameters_prove() {
+        let mut rng = test_rng();
+        let matrices = circuit_matrices(CubicCircuit { x: None, y: None }).unwrap();
+
+        // Three sequential phase 1 contributions, each checked against the one before
+        let mut powers = PowersOfTau::new(domain_size(&matrices));
+        let mut headline = Phase1Update::initial_headline();
+        for _ in 0..3 {
+            let update = powers.contribute(&mut rng);
+            let update = Phase1Update::from_bytes(&update.to_bytes().unwrap()).unwrap();
+            update.verify_after(&headline).unwrap();
+            assert_eq!(update.headline(), powers.headline());
+            headline = update.headline();
+        }
+        powers.check_structure(&mut rng).unwrap();
+        let sections: Vec<Vec<u8>> = POWERS_SECTIONS.iter().map(|section| powers.section(*section).unwrap()).collect();
+        assert_eq!(PowersOfTau::from_sections(&sections.try_into().unwrap()).unwrap(), powers);
+
+        // Then three phase 2 contributions on the derived parameters
+        let initial = initial_parameters(&powers, &matrices).unwrap();
+        let mut parameters = initial.clone();
+        for _ in 0..3 {
+            let previous_delta = parameters.delta_g1;
+            contribute_delta(&mut parameters, &mut rng).verify_after(&previous_delta).unwrap();
+        }
+        verify_parameters(&initial, &parameters, &mut rng).unwrap();
+
+        let x = Fr::from(3u64);
+        let y = Fr::from(35u64);
+        let proof = Groth16::<Bn254>::prove(&parameters, CubicCircuit { x: Some(x), y: Some(y) }, &mut rng).unwrap();
+        assert!(Groth16::<Bn254>::verify(&parameters.vk, &[y], &proof).unwrap());
+        assert!(!Groth16::<Bn254>::verify(&parameters.vk, &[Fr::from(36u64)], &proof).unwrap());
+    }
+
+    #[test]
+    fn test_tampered_contributions_rejected() {
+        let mut rng = test_rng();
+        let matrices = circuit_matrices(CubicCircuit { x: None, y: None }).unwrap();
+        let mut powers = PowersOfTau::new(domain_size(&matrices));
+        let first = powers.contribute(&mut rng);
+
+        // A contributor restarting from the generators, whose τ it would know, does not follow
+        let mut restarted = PowersOfTau::new(domain_size(&matrices));
+        let update = restarted.contribute(&mut rng);
+        assert!(update.verify_after(&first.headline()).is_err());
+
+        // Nor do powers with one point out of sequence
+        let mut broken = powers.clone();
+        broken.tau_g1[2] = broken.tau_g1[3];
+        assert!(broken.check_structure(&mut rng).is_err());
+
+        // Phase 2 parameters with a query not divided by δ
+        let initial = initial_parameters(&powers, &matrices).unwrap();
+        let mut parameters = initial.clone();
+        contribute_delta(&mut parameters, &mut rng);
+        let mut tampered = parameters.clone();
+        tampered.l_query[0] = initial.l_query[0];
+        assert!(verify_parameters(&initial, &tampered, &mut rng).is_err());
+        let mut tampered = parameters.clone();
+        tampered.a_query[1] = initial.a_query[0];
+        assert!(verify_parameters(&initial, &tampered, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+// Proof cache: proofs keyed by circuit id and a commitment to their witness, persisted in MDBX so
+// a settlement retried, also after a restart, reuses its proofs instead of proving again.
+// Entries are tagged with the cutoff of their settlement period and evicted once a later one closes
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::metrics::metrics;
+use crate::primitives::{Blake2bHash, BlockchainError, Result};
+use crate::storage::MdbxChainStore;
+
+#[derive(Serialize, Deserialize)]
+struct CachedProof {
+    period_cutoff: u64,
+    /// Serialized proof, with whatever public outputs its prover returned
+    proof: Vec<u8>,
+}
+
+/// Persistent cache of generated proofs
+#[derive(Clone)]
+pub struct ProofCache {
+    store: MdbxChainStore,
+}
+
+impl ProofCache {
+    pub fn new(store: MdbxChainStore) -> Self {
+        Self { store }
+    }
+
+    /// Commitment to everything a proof's statement and witness are derived from
+    pub fn witness_commitment<W: Serialize>(witness: &W) -> Result<Blake2bHash> {
+        let data = bincode::serialize(witness).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        Ok(Blake2bHash::from_data(&data))
+    }
+
+    fn key(circuit_id: &str, witness_commitment: &Blake2bHash) -> Blake2bHash {
+        Blake2bHash::from_data(&[circuit_id.as_bytes(), b":", witness_commitment.as_bytes()].concat())
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, circuit_id: &str, witness_commitment: &Blake2bHash) -> Result<Option<T>> {
+        let entry = match self.store.cached_proof(&Self::key(circuit_id, witness_commitment)).await? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let cached: CachedProof = bincode::deserialize(&entry).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        bincode::deserialize(&cached.proof).map(Some).map_err(|e| BlockchainError::Serialization(e.to_string()))
+    }
+
+    /// Store a proof of the settlement period ending at `period_cutoff`
+    pub async fn put<T: Serialize>(
+        &self,
+        circuit_id: &str,
+        witness_commitment: &Blake2bHash,
+        period_cutoff: u64,
+        proof: &T,
+    ) -> Result<()> {
+        let cached = CachedProof {
+            period_cutoff,
+            proof: bincode::serialize(proof).map_err(|e| BlockchainError::Serialization(e.to_string()))?,
+        };
+        let entry = bincode::serialize(&cached).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+        self.store.put_cached_proof(&Self::key(circuit_id, witness_commitment), &entry).await
+    }
+
+    /// The cached proof, or the one `prove` generates, stored for later
+    pub async fn get_or_prove<T: Serialize + DeserializeOwned>(
+        &self,
+        circuit_id: &str,
+        witness_commitment: &Blake2bHash,
+        period_cutoff: u64,
+        prove: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if let Some(proof) = self.get(circuit_id, witness_commitment).await? {
+            debug!("♻️  Reusing cached {} proof {}", circuit_id, witness_commitment);
+            metrics().zk_proof_cache_hits.inc();
+            return Ok(proof);
+        }
+        let proof = prove()?;
+        self.put(circuit_id, witness_commitment, period_cutoff, &proof).await?;
+        Ok(proof)
+    }
+
+    /// Drop the proofs of periods ending at or before `cutoff`, returns how many were dropped
+    pub async fn evict_until(&self, cutoff: u64) -> Result<usize> {
+        let mut evicted = Vec::new();
+        for (key, entry) in self.store.cached_proofs().await? {
+            let cached: CachedProof = bincode::deserialize(&entry).map_err(|e| BlockchainError::Serialization(e.to_string()))?;
+            if cached.period_cutoff <= cutoff {
+                evicted.push(key);
+            }
+        }
+        let count = evicted.len();
+        if count > 0 {
+            self.store.delete_cached_proofs(evicted).await?;
+            info!("🧹 Evicted {} cached proofs of closed settlement periods", count);
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cache_hits_and_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ProofCache::new(MdbxChainStore::new(dir.path()).unwrap());
+        let commitment = ProofCache::witness_commitment(&(42u64, vec![vec![0u64, 100], vec![50, 0]])).unwrap();
+
+        let proof = cache.get_or_prove("settlement_calculation", &commitment, 1000, || Ok(vec![1u8, 2, 3])).await.unwrap();
+        assert_eq!(proof, vec![1, 2, 3]);
+        let proof: Vec<u8> = cache.get_or_prove("settlement_calculation", &commitment, 1000, || unreachable!()).await.unwrap();
+        assert_eq!(proof, vec![1, 2, 3]);
+
+        // Same witness, other circuit
+        assert!(cache.get::<Vec<u8>>("cdr_batch_privacy", &commitment).await.unwrap().is_none());
+        cache.put("cdr_batch_privacy", &commitment, 2000, &vec![4u8]).await.unwrap();
+
+        // Only the earlier period's proof goes once it is closed over
+        assert_eq!(cache.evict_until(1000).await.unwrap(), 1);
+        assert!(cache.get::<Vec<u8>>("settlement_calculation", &commitment).await.unwrap().is_none());
+        assert_eq!(cache.get::<Vec<u8>>("cdr_batch_privacy", &commitment).await.unwrap(), Some(vec![4]));
+    }
+}
@@ -1,7 +1,10 @@
 // Trusted setup ceremony for SP CDR ZK proofs
-// Generates real proving/verifying keys for Groth16 circuits
-use ark_bn254::{Bn254, Fr};
+// Generates real proving/verifying keys for Groth16 circuits, either on one node or in a multi-party
+// ceremony where consortium members contribute in turn and sign their contributions
+use ark_bn254::{Bn254, Fr, G1Affine};
+use ark_ec::AffineRepr;
 use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintMatrices;
 use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
 use ark_snark::SNARK;
 use ark_std::rand::{RngCore, CryptoRng};
@@ -11,8 +14,22 @@ use tokio::fs;
 use tracing::{info, warn, error};
 use serde::{Deserialize, Serialize};
 
+use crate::crypto::{BLSPrivateKey, BLSPublicKey, BLSSignature};
 use crate::primitives::{Result, BlockchainError, Blake2bHash};
-use crate::zkp::circuits::{CDRPrivacyCircuit, SettlementCalculationCircuit};
+use crate::zkp::circuits::{CDRPrivacyCircuit, SettlementCalculationCircuit, NettingCorrectnessCircuit, CDRBatchCircuit};
+use crate::zkp::mpc::{
+    circuit_matrices, contribute_delta, domain_size, initial_parameters, verify_parameters,
+    Phase1Update, Phase2Update, PowersOfTau, PowersSection, POWERS_SECTIONS,
+};
+
+/// Circuits the ceremony generates keys for
+pub const CIRCUIT_IDS: [&str; 4] = ["cdr_privacy", "settlement_calculation", "netting_correctness", "cdr_batch_privacy"];
+
+/// Circuit id of phase 1 contributions, the powers of tau all circuits' parameters derive from
+pub const POWERS_OF_TAU: &str = "powers_of_tau";
+
+/// Participant recorded by a ceremony run on a single node
+const SINGLE_PARTY_PARTICIPANT: &str = "Bootstrap-Coordinator";
 
 /// Trusted setup ceremony coordinator
 pub struct TrustedSetupCeremony {
@@ -58,10 +75,18 @@ struct CircuitSetup {
 pub struct ParticipantContribution {
     pub participant_id: String,
     pub circuit_id: String,
+    /// Hash of the powers of tau for phase 1, of the verifying key for a circuit's phase 2
     pub contribution_hash: Blake2bHash,
     pub previous_hash: Blake2bHash,
     pub timestamp: u64,
+    /// BLS signature of the participant over the contribution and its update proof
     pub signature: Vec<u8>,
+    /// BLS public key the signature verifies against, matched to the operator out of band
+    #[serde(default)]
+    pub public_key: Vec<u8>,
+    /// Serialized phase 1 or phase 2 update proof
+    #[serde(default)]
+    pub update: Vec<u8>,
 }
 
 /// Ceremony transcript for verifiability
@@ -70,10 +95,17 @@ pub struct CeremonyTranscript {
     pub ceremony_id: String,
     pub start_time: u64,
     pub end_time: Option<u64>,
+    /// Contributors, in the order they contribute to each phase
     pub participants: Vec<String>,
     pub contributions: Vec<ParticipantContribution>,
     pub final_parameters_hash: Option<Blake2bHash>,
     pub verification_status: VerificationStatus,
+    /// Degree of the powers of tau of a multi-party ceremony
+    #[serde(default)]
+    pub powers_degree: usize,
+    /// Keys generated on one node, whose operator could forge proofs
+    #[serde(default)]
+    pub single_party: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,6 +115,200 @@ pub enum VerificationStatus {
     Failed(String),
 }
 
+/// Part of the working state of a multi-party ceremony, served to the next contributor
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CeremonyPart {
+    Powers(PowersSection),
+    /// Phase 2 parameters of a circuit
+    Parameters(String),
+}
+
+impl CeremonyPart {
+    /// Parts holding the powers of tau
+    pub fn powers() -> impl Iterator<Item = Self> {
+        POWERS_SECTIONS.into_iter().map(CeremonyPart::Powers)
+    }
+
+    fn file_name(&self) -> Result<String> {
+        Ok(match self {
+            CeremonyPart::Powers(PowersSection::TauG1) => "tau_g1.bin".to_string(),
+            CeremonyPart::Powers(PowersSection::TauG2) => "tau_g2.bin".to_string(),
+            CeremonyPart::Powers(PowersSection::AlphaTauG1) => "alpha_tau_g1.bin".to_string(),
+            CeremonyPart::Powers(PowersSection::BetaTauG1) => "beta_tau_g1.bin".to_string(),
+            // Only known circuit ids, as they end up in a path
+            CeremonyPart::Parameters(circuit_id) if CIRCUIT_IDS.contains(&circuit_id.as_str()) => format!("{}.pk", circuit_id),
+            CeremonyPart::Parameters(circuit_id) => {
+                return Err(BlockchainError::NotFound(format!("Unknown circuit {}", circuit_id)));
+            }
+        })
+    }
+}
+
+impl ParticipantContribution {
+    /// Contribution of `participant_id` signed with its operator key
+    pub fn signed(
+        ceremony_id: &str,
+        participant_id: &str,
+        circuit_id: &str,
+        previous_hash: Blake2bHash,
+        contribution_hash: Blake2bHash,
+        update: Vec<u8>,
+        key: &BLSPrivateKey,
+    ) -> Result<Self> {
+        let mut contribution = Self {
+            participant_id: participant_id.to_string(),
+            circuit_id: circuit_id.to_string(),
+            contribution_hash,
+            previous_hash,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            signature: vec![],
+            public_key: key.public_key().to_bytes().to_vec(),
+            update,
+        };
+        contribution.signature = key.sign(&contribution.signed_message(ceremony_id))?.to_bytes().to_vec();
+        Ok(contribution)
+    }
+
+    fn signed_message(&self, ceremony_id: &str) -> Vec<u8> {
+        let mut message = b"sp-cdr-trusted-setup".to_vec();
+        for field in [ceremony_id, &self.participant_id, &self.circuit_id] {
+            message.extend_from_slice(field.as_bytes());
+            message.push(0);
+        }
+        message.extend_from_slice(self.previous_hash.as_bytes());
+        message.extend_from_slice(self.contribution_hash.as_bytes());
+        message.extend_from_slice(&self.update);
+        message
+    }
+
+    pub fn verify_signature(&self, ceremony_id: &str) -> Result<()> {
+        let public_key = BLSPublicKey::from_bytes(&self.public_key)?;
+        let signature = BLSSignature::from_bytes(&self.signature)?;
+        if !signature.verify(&public_key, &self.signed_message(ceremony_id))? {
+            return Err(BlockchainError::Crypto(format!(
+                "Contribution of {} to {} has an invalid signature", self.participant_id, self.circuit_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl CeremonyTranscript {
+    /// Contributions to a circuit, or to the powers of tau, in order
+    pub fn contributions_to<'a>(&'a self, circuit_id: &'a str) -> impl Iterator<Item = &'a ParticipantContribution> + 'a {
+        self.contributions.iter().filter(move |contribution| contribution.circuit_id == circuit_id)
+    }
+
+    /// Whether every participant contributed to the powers of tau and to every circuit
+    pub fn is_complete(&self) -> bool {
+        let participants = self.participants.len();
+        self.single_party || (self.contributions_to(POWERS_OF_TAU).count() == participants
+            && CIRCUIT_IDS.iter().all(|circuit_id| self.contributions_to(circuit_id).count() == participants))
+    }
+
+    /// Check every contribution is signed by its participant, made in participant order, and follows
+    /// the previous contribution by its update proof, phase 2 starting from the final powers of tau
+    /// Holds for a transcript of a ceremony still in progress too
+    pub fn verify_contributions(&self) -> Result<()> {
+        if self.single_party {
+            return Ok(());
+        }
+        let invalid = |reason: String| BlockchainError::Crypto(format!("Invalid ceremony transcript: {}", reason));
+        let mut keys: HashMap<&str, &[u8]> = HashMap::new();
+        for participant in &self.participants {
+            if keys.insert(participant, &[]).is_some() {
+                return Err(invalid(format!("{} is listed twice", participant)));
+            }
+        }
+
+        let mut powers_hash = Blake2bHash::default();
+        let mut headline = Phase1Update::initial_headline();
+        let mut powers_contributions = 0;
+        // Previous hash, δ and number of contributions of each circuit
+        let mut circuits: HashMap<&str, (Blake2bHash, G1Affine, usize)> = HashMap::new();
+
+        for contribution in &self.contributions {
+            let position = if contribution.circuit_id == POWERS_OF_TAU {
+                powers_contributions
+            } else {
+                circuits.get(contribution.circuit_id.as_str()).map_or(0, |(_, _, count)| *count)
+            };
+            if self.participants.get(position) != Some(&contribution.participant_id) {
+                return Err(invalid(format!(
+                    "contribution {} of {} is by {} out of turn", position, contribution.circuit_id, contribution.participant_id
+                )));
+            }
+            // One key per participant, and no key shared between participants
+            let key = contribution.public_key.as_slice();
+            let registered = keys[contribution.participant_id.as_str()];
+            if (!registered.is_empty() && registered != key)
+                || keys.iter().any(|(participant, other)| *participant != contribution.participant_id && *other == key)
+            {
+                return Err(invalid(format!("{} does not sign with its own key", contribution.participant_id)));
+            }
+            keys.insert(&contribution.participant_id, key);
+            contribution.verify_signature(&self.ceremony_id)?;
+
+            if contribution.circuit_id == POWERS_OF_TAU {
+                if !circuits.is_empty() {
+                    return Err(invalid("powers of tau contribution after phase 2 started".to_string()));
+                }
+                if contribution.previous_hash != powers_hash {
+                    return Err(invalid(format!("powers of tau contribution {} does not follow the previous one", position)));
+                }
+                let update = Phase1Update::from_bytes(&contribution.update)?;
+                update.verify_after(&headline)?;
+                headline = update.headline();
+                powers_hash = contribution.contribution_hash;
+                powers_contributions += 1;
+            } else {
+                if powers_contributions != self.participants.len() {
+                    return Err(invalid(format!("{} contribution before the powers of tau were complete", contribution.circuit_id)));
+                }
+                let (previous_hash, delta_g1, count) = circuits.entry(contribution.circuit_id.as_str())
+                    .or_insert((powers_hash, G1Affine::generator(), 0));
+                if contribution.previous_hash != *previous_hash {
+                    return Err(invalid(format!("{} contribution {} does not follow the previous one", contribution.circuit_id, position)));
+                }
+                let update = Phase2Update::from_bytes(&contribution.update)?;
+                update.verify_after(delta_g1)?;
+                *previous_hash = contribution.contribution_hash;
+                *delta_g1 = update.delta_g1;
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a key of `circuit_id` received from another node against the hash its last contribution recorded
+    /// A proving key is checked through the verifying key it embeds
+    pub fn verify_key(&self, circuit_id: &str, proving: bool, data: &[u8]) -> Result<()> {
+        let contribution = self.contributions.iter()
+            .rfind(|contribution| contribution.circuit_id == circuit_id)
+            .ok_or_else(|| BlockchainError::Crypto(format!("Transcript has no contribution for circuit {}", circuit_id)))?;
+
+        let vk_hash = if proving {
+            let proving_key = ProvingKey::<Bn254>::deserialize_compressed(data)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid PK for {}: {}", circuit_id, e)))?;
+            let mut vk_bytes = Vec::new();
+            proving_key.vk.serialize_compressed(&mut vk_bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("VK serialization error: {}", e)))?;
+            Blake2bHash::from_data(&vk_bytes)
+        } else {
+            VerifyingKey::<Bn254>::deserialize_compressed(data)
+                .map_err(|e| BlockchainError::Serialization(format!("Invalid VK for {}: {}", circuit_id, e)))?;
+            Blake2bHash::from_data(data)
+        };
+
+        if vk_hash != contribution.contribution_hash {
+            return Err(BlockchainError::Crypto(format!(
+                "{} key for circuit {} does not match the ceremony transcript", if proving { "Proving" } else { "Verifying" }, circuit_id
+            )));
+        }
+        Ok(())
+    }
+}
+
 impl TrustedSetupCeremony {
     /// Create new ceremony coordinator
     pub fn new(keys_dir: PathBuf, config: CeremonyConfig) -> Self {
@@ -100,7 +326,25 @@ impl TrustedSetupCeremony {
 
         circuits.insert("settlement_calculation".to_string(), CircuitSetup {
             circuit_id: "settlement_calculation".to_string(),
-            circuit_description: "Settlement Calculation Circuit - proves triangular netting correctness".to_string(),
+            circuit_description: "Settlement Calculation Circuit - proves multilateral netting totals of up to 16 operators".to_string(),
+            parameters_hash: None,
+            proving_key: None,
+            verifying_key: None,
+            ceremony_complete: false,
+        });
+
+        circuits.insert("netting_correctness".to_string(), CircuitSetup {
+            circuit_id: "netting_correctness".to_string(),
+            circuit_description: "Netting Correctness Circuit - proves net positions derive from bilateral obligations".to_string(),
+            parameters_hash: None,
+            proving_key: None,
+            verifying_key: None,
+            ceremony_complete: false,
+        });
+
+        circuits.insert("cdr_batch_privacy".to_string(), CircuitSetup {
+            circuit_id: "cdr_batch_privacy".to_string(),
+            circuit_description: "Batch CDR Privacy Circuit - proves a Merkle-committed batch of CDR charges".to_string(),
             parameters_hash: None,
             proving_key: None,
             verifying_key: None,
@@ -130,7 +374,8 @@ impl TrustedSetupCeremony {
         Self::new(keys_dir, config)
     }
 
-    /// Run the full trusted setup ceremony
+    /// Run the trusted setup ceremony on this node alone, whose operator learns the toxic waste
+    /// Consortium deployments run the multi-party ceremony instead
     pub async fn run_ceremony<R: RngCore + CryptoRng>(
         &mut self,
         rng: &mut R
@@ -143,10 +388,12 @@ impl TrustedSetupCeremony {
             ceremony_id: ceremony_id.clone(),
             start_time: chrono::Utc::now().timestamp() as u64,
             end_time: None,
-            participants: Vec::new(),
+            participants: vec![SINGLE_PARTY_PARTICIPANT.to_string()],
             contributions: Vec::new(),
             final_parameters_hash: None,
             verification_status: VerificationStatus::Pending,
+            powers_degree: 0,
+            single_party: true,
         };
 
         // Ensure keys directory exists
@@ -164,6 +411,12 @@ impl TrustedSetupCeremony {
                 "settlement_calculation" => {
                     self.setup_settlement_circuit(rng, &mut transcript).await?;
                 }
+                "netting_correctness" => {
+                    self.setup_netting_circuit(rng, &mut transcript).await?;
+                }
+                "cdr_batch_privacy" => {
+                    self.setup_cdr_batch_circuit(rng, &mut transcript).await?;
+                }
                 _ => {
                     warn!("Unknown circuit: {}", circuit_id);
                 }
@@ -218,30 +471,20 @@ impl TrustedSetupCeremony {
         // Save keys to disk
         self.save_circuit_keys("cdr_privacy", &proving_key, &verifying_key).await?;
 
-        // Add to transcript with all expected participants for consortium demo
+        // Add to transcript, unsigned as there is no one to hold accountable but this node
         let contribution = ParticipantContribution {
-            participant_id: "Bootstrap-Coordinator".to_string(),
+            participant_id: SINGLE_PARTY_PARTICIPANT.to_string(),
             circuit_id: "cdr_privacy".to_string(),
             contribution_hash: params_hash,
             previous_hash: Blake2bHash::default(),
             timestamp: chrono::Utc::now().timestamp() as u64,
-            signature: vec![], // In real ceremony, would be signed by all participants
+            signature: vec![],
+            public_key: vec![],
+            update: vec![],
         };
 
         transcript.contributions.push(contribution);
 
-        // For consortium demo, record all expected participants as having participated
-        // This simulates a coordinated ceremony where all validators contributed
-        if !transcript.participants.contains(&"T-Mobile-DE".to_string()) {
-            transcript.participants.push("T-Mobile-DE".to_string());
-        }
-        if !transcript.participants.contains(&"Vodafone-UK".to_string()) {
-            transcript.participants.push("Vodafone-UK".to_string());
-        }
-        if !transcript.participants.contains(&"Orange-FR".to_string()) {
-            transcript.participants.push("Orange-FR".to_string());
-        }
-
         info!("✅ CDR Privacy Circuit setup complete");
         info!("📊 Parameters hash: {:?}", params_hash);
 
@@ -285,12 +528,14 @@ impl TrustedSetupCeremony {
 
         // Add to transcript
         let contribution = ParticipantContribution {
-            participant_id: "Bootstrap-Coordinator".to_string(),
+            participant_id: SINGLE_PARTY_PARTICIPANT.to_string(),
             circuit_id: "settlement_calculation".to_string(),
             contribution_hash: params_hash,
             previous_hash: Blake2bHash::default(),
             timestamp: chrono::Utc::now().timestamp() as u64,
             signature: vec![],
+            public_key: vec![],
+            update: vec![],
         };
 
         transcript.contributions.push(contribution);
@@ -301,6 +546,98 @@ impl TrustedSetupCeremony {
         Ok(())
     }
 
+    /// Setup netting correctness circuit
+    async fn setup_netting_circuit<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        transcript: &mut CeremonyTranscript,
+    ) -> Result<()> {
+        info!("🔒 Generating Netting Correctness Circuit parameters...");
+
+        let circuit = NettingCorrectnessCircuit::<Fr>::empty();
+
+        info!("⚡ Running setup computation...");
+        let (proving_key, verifying_key) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        let mut vk_bytes = Vec::new();
+        verifying_key.serialize_compressed(&mut vk_bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("VK serialization error: {}", e)))?;
+
+        let params_hash = Blake2bHash::from_data(&vk_bytes);
+
+        if let Some(setup) = self.circuits.get_mut("netting_correctness") {
+            setup.proving_key = Some(proving_key.clone());
+            setup.verifying_key = Some(verifying_key.clone());
+            setup.parameters_hash = Some(params_hash);
+            setup.ceremony_complete = true;
+        }
+
+        self.save_circuit_keys("netting_correctness", &proving_key, &verifying_key).await?;
+
+        transcript.contributions.push(ParticipantContribution {
+            participant_id: SINGLE_PARTY_PARTICIPANT.to_string(),
+            circuit_id: "netting_correctness".to_string(),
+            contribution_hash: params_hash,
+            previous_hash: Blake2bHash::default(),
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            signature: vec![],
+            public_key: vec![],
+            update: vec![],
+        });
+
+        info!("✅ Netting Correctness Circuit setup complete");
+        info!("📊 Parameters hash: {:?}", params_hash);
+
+        Ok(())
+    }
+
+    /// Setup batch CDR privacy circuit
+    async fn setup_cdr_batch_circuit<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        transcript: &mut CeremonyTranscript,
+    ) -> Result<()> {
+        info!("🔒 Generating Batch CDR Privacy Circuit parameters...");
+
+        let circuit = CDRBatchCircuit::<Fr>::empty();
+
+        info!("⚡ Running setup computation (this may take several minutes)...");
+        let (proving_key, verifying_key) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng)
+            .map_err(|_| BlockchainError::InvalidProof)?;
+
+        let mut vk_bytes = Vec::new();
+        verifying_key.serialize_compressed(&mut vk_bytes)
+            .map_err(|e| BlockchainError::Serialization(format!("VK serialization error: {}", e)))?;
+
+        let params_hash = Blake2bHash::from_data(&vk_bytes);
+
+        if let Some(setup) = self.circuits.get_mut("cdr_batch_privacy") {
+            setup.proving_key = Some(proving_key.clone());
+            setup.verifying_key = Some(verifying_key.clone());
+            setup.parameters_hash = Some(params_hash);
+            setup.ceremony_complete = true;
+        }
+
+        self.save_circuit_keys("cdr_batch_privacy", &proving_key, &verifying_key).await?;
+
+        transcript.contributions.push(ParticipantContribution {
+            participant_id: SINGLE_PARTY_PARTICIPANT.to_string(),
+            circuit_id: "cdr_batch_privacy".to_string(),
+            contribution_hash: params_hash,
+            previous_hash: Blake2bHash::default(),
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            signature: vec![],
+            public_key: vec![],
+            update: vec![],
+        });
+
+        info!("✅ Batch CDR Privacy Circuit setup complete");
+        info!("📊 Parameters hash: {:?}", params_hash);
+
+        Ok(())
+    }
+
     /// Save circuit keys to disk
     async fn save_circuit_keys(
         &self,
@@ -357,6 +694,21 @@ impl TrustedSetupCeremony {
         Ok((proving_key, verifying_key))
     }
 
+    /// Load a circuit's verifying key alone, without reading its much larger proving key
+    pub async fn load_verifying_key(&self, circuit_id: &str) -> Result<VerifyingKey<Bn254>> {
+        let vk_path = self.keys_dir.join(format!("{}.vk", circuit_id));
+        let vk_bytes = fs::read(&vk_path).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to read VK: {}", e)))?;
+
+        VerifyingKey::<Bn254>::deserialize_compressed(&vk_bytes[..])
+            .map_err(|e| BlockchainError::Serialization(format!("VK deserialization error: {}", e)))
+    }
+
+    /// Check if the verifying key exists for a circuit
+    pub async fn verifying_key_exists(&self, circuit_id: &str) -> bool {
+        self.keys_dir.join(format!("{}.vk", circuit_id)).exists()
+    }
+
     /// Check if keys exist for a circuit
     pub async fn keys_exist(&self, circuit_id: &str) -> bool {
         let pk_path = self.keys_dir.join(format!("{}.pk", circuit_id));
@@ -399,15 +751,15 @@ impl TrustedSetupCeremony {
         // Load transcript
         let transcript = self.load_ceremony_transcript().await?;
 
-        // Verify all required circuits have keys
-        for circuit_id in ["cdr_privacy", "settlement_calculation"] {
-            if !self.keys_exist(circuit_id).await {
+        // Verify all required circuits have verifying keys, proving keys are only needed to prove
+        for circuit_id in CIRCUIT_IDS {
+            if !self.verifying_key_exists(circuit_id).await {
                 error!("❌ Missing keys for circuit: {}", circuit_id);
                 return Ok(false);
             }
 
             // Load and validate keys
-            let (pk, vk) = self.load_circuit_keys(circuit_id).await?;
+            let vk = self.load_verifying_key(circuit_id).await?;
 
             // Verify key consistency
             let mut vk_bytes = Vec::new();
@@ -416,9 +768,9 @@ impl TrustedSetupCeremony {
 
             let current_hash = Blake2bHash::from_data(&vk_bytes);
 
-            // Find contribution in transcript
+            // Find the last contribution in transcript, which produced the final keys
             let contribution = transcript.contributions.iter()
-                .find(|c| c.circuit_id == circuit_id)
+                .rfind(|c| c.circuit_id == circuit_id)
                 .ok_or_else(|| BlockchainError::InvalidProof)?;
 
             if contribution.contribution_hash != current_hash {
@@ -429,8 +781,17 @@ impl TrustedSetupCeremony {
             info!("✅ Circuit {} keys verified", circuit_id);
         }
 
-        // Verify ceremony completeness
-        if transcript.participants.len() < self.config.min_participants {
+        // Verify every contribution and the ceremony's completeness
+        if let Err(e) = transcript.verify_contributions() {
+            error!("❌ {}", e);
+            return Ok(false);
+        }
+        if transcript.single_party {
+            warn!("⚠️  Keys come from a single-party ceremony, its operator could forge proofs");
+        } else if !transcript.is_complete() {
+            error!("❌ Not every participant contributed to every circuit");
+            return Ok(false);
+        } else if transcript.participants.len() < self.config.min_participants {
             error!("❌ Insufficient participants: {} < {}",
                    transcript.participants.len(), self.config.min_participants);
             return Ok(false);
@@ -528,7 +889,7 @@ impl TrustedSetupCeremony {
     pub async fn export_verifying_keys(&self) -> Result<HashMap<String, Vec<u8>>> {
         let mut vk_exports = HashMap::new();
 
-        for circuit_id in ["cdr_privacy", "settlement_calculation"] {
+        for circuit_id in CIRCUIT_IDS {
             if self.keys_exist(circuit_id).await {
                 let vk_path = self.keys_dir.join(format!("{}.vk", circuit_id));
                 let vk_bytes = fs::read(&vk_path).await
@@ -558,6 +919,272 @@ impl TrustedSetupCeremony {
 
         Ok(())
     }
+
+    /// Ceremony transcript as stored, whose hash the bootstrap node announces
+    pub async fn transcript_file(&self) -> Result<Vec<u8>> {
+        fs::read(self.keys_dir.join("ceremony_transcript.json")).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to read transcript: {}", e)))
+    }
+
+    /// Proving or verifying key of a circuit as stored, to serve to other nodes
+    pub async fn key_file(&self, circuit_id: &str, proving: bool) -> Result<Vec<u8>> {
+        // Only known circuit ids, as they end up in a path
+        if !CIRCUIT_IDS.contains(&circuit_id) {
+            return Err(BlockchainError::NotFound(format!("Unknown circuit {}", circuit_id)));
+        }
+        let path = self.keys_dir.join(format!("{}.{}", circuit_id, if proving { "pk" } else { "vk" }));
+        fs::read(&path).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to read {:?}: {}", path, e)))
+    }
+
+    /// Install a transcript and keys received from another node, each key checked against the transcript
+    pub async fn install_distributed_keys(&self, transcript: &[u8], keys: &[(String, bool, Vec<u8>)]) -> Result<()> {
+        let parsed: CeremonyTranscript = serde_json::from_slice(transcript)
+            .map_err(|e| BlockchainError::Serialization(format!("Transcript deserialization error: {}", e)))?;
+        for (circuit_id, proving, data) in keys {
+            if !CIRCUIT_IDS.contains(&circuit_id.as_str()) {
+                return Err(BlockchainError::NotFound(format!("Unknown circuit {}", circuit_id)));
+            }
+            parsed.verify_key(circuit_id, *proving, data)?;
+        }
+
+        fs::create_dir_all(&self.keys_dir).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to create keys directory: {}", e)))?;
+        for (circuit_id, proving, data) in keys {
+            let path = self.keys_dir.join(format!("{}.{}", circuit_id, if *proving { "pk" } else { "vk" }));
+            fs::write(&path, data).await
+                .map_err(|e| BlockchainError::Serialization(format!("Failed to write {:?}: {}", path, e)))?;
+        }
+        // Written last, so an interrupted install does not look complete
+        fs::write(self.keys_dir.join("ceremony_transcript.json"), transcript).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to write transcript: {}", e)))?;
+
+        info!("📥 Installed {} trusted setup keys of ceremony {}", keys.len(), parsed.ceremony_id);
+        Ok(())
+    }
+}
+
+/// Multi-party ceremony: participants contribute in turn to the powers of tau, then to every circuit's
+/// parameters, each on the state of the previous contributor fetched into the ceremony directory
+impl TrustedSetupCeremony {
+    pub fn config(&self) -> &CeremonyConfig {
+        &self.config
+    }
+
+    /// Evaluation domain of the largest circuit, which the powers of tau have to cover
+    pub fn powers_degree(&self) -> Result<usize> {
+        let mut degree = 2;
+        for circuit_id in CIRCUIT_IDS {
+            degree = degree.max(domain_size(&matrices_of(circuit_id)?));
+        }
+        Ok(degree)
+    }
+
+    fn ceremony_dir(&self) -> PathBuf {
+        self.keys_dir.join("ceremony")
+    }
+
+    /// Part of the state this node last contributed or fetched, to serve to the next contributor
+    pub async fn ceremony_file(&self, part: &CeremonyPart) -> Result<Vec<u8>> {
+        let path = self.ceremony_dir().join(part.file_name()?);
+        fs::read(&path).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to read {:?}: {}", path, e)))
+    }
+
+    /// Store a part of the state fetched from the previous contributor
+    pub async fn store_ceremony_file(&self, part: &CeremonyPart, data: &[u8]) -> Result<()> {
+        fs::create_dir_all(self.ceremony_dir()).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to create ceremony directory: {}", e)))?;
+        let path = self.ceremony_dir().join(part.file_name()?);
+        fs::write(&path, data).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to write {:?}: {}", path, e)))
+    }
+
+    /// Powers of tau in the ceremony directory, checked against the last phase 1 contribution of `transcript`
+    async fn verified_powers<R: RngCore>(&self, transcript: &CeremonyTranscript, rng: &mut R) -> Result<PowersOfTau> {
+        let contribution = transcript.contributions_to(POWERS_OF_TAU).last()
+            .ok_or_else(|| BlockchainError::InvalidState("No powers of tau contributed yet".to_string()))?;
+        let mut sections = Vec::new();
+        for part in CeremonyPart::powers() {
+            sections.push(self.ceremony_file(&part).await?);
+        }
+        let powers = PowersOfTau::from_sections(&sections.try_into().expect("four sections"))?;
+
+        let update = Phase1Update::from_bytes(&contribution.update)?;
+        if powers.hash()? != contribution.contribution_hash || powers.headline() != update.headline()
+            || powers.degree() < transcript.powers_degree
+        {
+            return Err(BlockchainError::Crypto(format!(
+                "Powers of tau do not match the contribution of {}", contribution.participant_id
+            )));
+        }
+        powers.check_structure(rng)?;
+        Ok(powers)
+    }
+
+    /// Contribute to the powers of tau of `transcript`, on the state of its last contribution
+    pub async fn contribute_powers<R: RngCore + CryptoRng>(
+        &self,
+        transcript: &CeremonyTranscript,
+        participant: &str,
+        key: &BLSPrivateKey,
+        rng: &mut R,
+    ) -> Result<ParticipantContribution> {
+        transcript.verify_contributions()?;
+        let (mut powers, previous_hash) = match transcript.contributions_to(POWERS_OF_TAU).last() {
+            Some(previous) => (self.verified_powers(transcript, rng).await?, previous.contribution_hash),
+            None => (PowersOfTau::new(transcript.powers_degree), Blake2bHash::default()),
+        };
+
+        info!("🎲 Contributing to the powers of tau of ceremony {} (degree {})...", transcript.ceremony_id, powers.degree());
+        let update = powers.contribute(rng);
+        for section in POWERS_SECTIONS {
+            self.store_ceremony_file(&CeremonyPart::Powers(section), &powers.section(section)?).await?;
+        }
+
+        ParticipantContribution::signed(
+            &transcript.ceremony_id, participant, POWERS_OF_TAU, previous_hash, powers.hash()?, update.to_bytes()?, key,
+        )
+    }
+
+    /// Contribute to every circuit's parameters of `transcript`, after checking those of its last
+    /// contributions against the parameters the final powers of tau derive
+    pub async fn contribute_parameters<R: RngCore + CryptoRng>(
+        &self,
+        transcript: &CeremonyTranscript,
+        participant: &str,
+        key: &BLSPrivateKey,
+        rng: &mut R,
+    ) -> Result<Vec<ParticipantContribution>> {
+        transcript.verify_contributions()?;
+        let powers = self.verified_powers(transcript, rng).await?;
+        let powers_hash = powers.hash()?;
+
+        let mut contributions = Vec::new();
+        for circuit_id in CIRCUIT_IDS {
+            info!("⚙️  Deriving {} parameters from the powers of tau...", circuit_id);
+            let initial = initial_parameters(&powers, &matrices_of(circuit_id)?)?;
+            let (mut parameters, previous_hash) = match transcript.contributions_to(circuit_id).last() {
+                Some(previous) => {
+                    let parameters = self.verified_parameters(circuit_id, previous, &initial, rng).await?;
+                    (parameters, previous.contribution_hash)
+                }
+                None => (initial, powers_hash),
+            };
+
+            info!("🎲 Contributing to the {} parameters...", circuit_id);
+            let update = contribute_delta(&mut parameters, rng);
+            let mut pk_bytes = Vec::new();
+            parameters.serialize_compressed(&mut pk_bytes)
+                .map_err(|e| BlockchainError::Serialization(format!("PK serialization error: {}", e)))?;
+            self.store_ceremony_file(&CeremonyPart::Parameters(circuit_id.to_string()), &pk_bytes).await?;
+
+            contributions.push(ParticipantContribution::signed(
+                &transcript.ceremony_id, participant, circuit_id, previous_hash, vk_hash(&parameters.vk)?, update.to_bytes()?, key,
+            )?);
+        }
+        Ok(contributions)
+    }
+
+    /// Parameters of a circuit in the ceremony directory, checked against `contribution` and `initial`
+    async fn verified_parameters<R: RngCore>(
+        &self,
+        circuit_id: &str,
+        contribution: &ParticipantContribution,
+        initial: &ProvingKey<Bn254>,
+        rng: &mut R,
+    ) -> Result<ProvingKey<Bn254>> {
+        let pk_bytes = self.ceremony_file(&CeremonyPart::Parameters(circuit_id.to_string())).await?;
+        let parameters = ProvingKey::<Bn254>::deserialize_compressed(&pk_bytes[..])
+            .map_err(|e| BlockchainError::Serialization(format!("PK deserialization error: {}", e)))?;
+        let update = Phase2Update::from_bytes(&contribution.update)?;
+        if vk_hash(&parameters.vk)? != contribution.contribution_hash || parameters.delta_g1 != update.delta_g1 {
+            return Err(BlockchainError::Crypto(format!(
+                "{} parameters do not match the contribution of {}", circuit_id, contribution.participant_id
+            )));
+        }
+        verify_parameters(initial, &parameters, rng)?;
+        Ok(parameters)
+    }
+
+    /// Last contributor: install the final parameters as the circuits' keys along with the completed
+    /// transcript, returned as stored
+    pub async fn finish_ceremony(&mut self, mut transcript: CeremonyTranscript) -> Result<Vec<u8>> {
+        if !transcript.is_complete() {
+            return Err(BlockchainError::InvalidState(format!("Ceremony {} is not complete", transcript.ceremony_id)));
+        }
+        fs::create_dir_all(&self.keys_dir).await
+            .map_err(|e| BlockchainError::Serialization(format!("Failed to create keys directory: {}", e)))?;
+        for circuit_id in CIRCUIT_IDS {
+            let pk_bytes = self.ceremony_file(&CeremonyPart::Parameters(circuit_id.to_string())).await?;
+            transcript.verify_key(circuit_id, true, &pk_bytes)?;
+            let proving_key = ProvingKey::<Bn254>::deserialize_compressed(&pk_bytes[..])
+                .map_err(|e| BlockchainError::Serialization(format!("PK deserialization error: {}", e)))?;
+            self.save_circuit_keys(circuit_id, &proving_key, &proving_key.vk).await?;
+
+            if let Some(setup) = self.circuits.get_mut(circuit_id) {
+                setup.parameters_hash = Some(vk_hash(&proving_key.vk)?);
+                setup.verifying_key = Some(proving_key.vk.clone());
+                setup.proving_key = Some(proving_key);
+                setup.ceremony_complete = true;
+            }
+        }
+
+        transcript.final_parameters_hash = transcript.contributions_to(POWERS_OF_TAU).last().map(|c| c.contribution_hash);
+        transcript.end_time = Some(chrono::Utc::now().timestamp() as u64);
+        transcript.verification_status = VerificationStatus::Verified;
+        self.save_ceremony_transcript(&transcript).await?;
+        info!("✅ Multi-party ceremony {} complete with {} participants", transcript.ceremony_id, transcript.participants.len());
+        self.transcript_file().await
+    }
+
+    /// Full audit of the installed keys: the transcript's contributions, the final powers of tau in the
+    /// ceremony directory, and each circuit's keys against the parameters those powers derive
+    /// Takes as long as a contribution, so it is run by participants rather than on every start
+    pub async fn audit_ceremony<R: RngCore>(&self, rng: &mut R) -> Result<()> {
+        let transcript = self.load_ceremony_transcript().await?;
+        if transcript.single_party {
+            return Err(BlockchainError::Crypto("A single-party ceremony has no contributions to audit".to_string()));
+        }
+        if !transcript.is_complete() {
+            return Err(BlockchainError::Crypto(format!("Ceremony {} is not complete", transcript.ceremony_id)));
+        }
+        transcript.verify_contributions()?;
+        let powers = self.verified_powers(&transcript, rng).await?;
+
+        for circuit_id in CIRCUIT_IDS {
+            transcript.verify_key(circuit_id, false, &self.key_file(circuit_id, false).await?)?;
+            let (proving_key, _) = self.load_circuit_keys(circuit_id).await?;
+            let contribution = transcript.contributions_to(circuit_id).last().expect("transcript is complete");
+            let initial = initial_parameters(&powers, &matrices_of(circuit_id)?)?;
+            if vk_hash(&proving_key.vk)? != contribution.contribution_hash
+                || proving_key.delta_g1 != Phase2Update::from_bytes(&contribution.update)?.delta_g1
+            {
+                return Err(BlockchainError::Crypto(format!("{} keys do not match the transcript", circuit_id)));
+            }
+            verify_parameters(&initial, &proving_key, rng)?;
+            info!("✅ Circuit {} keys derive from the audited powers of tau", circuit_id);
+        }
+        Ok(())
+    }
+}
+
+/// R1CS matrices of a ceremony circuit
+fn matrices_of(circuit_id: &str) -> Result<ConstraintMatrices<Fr>> {
+    match circuit_id {
+        "cdr_privacy" => circuit_matrices(CDRPrivacyCircuit::<Fr>::empty()),
+        "settlement_calculation" => circuit_matrices(SettlementCalculationCircuit::<Fr>::empty()),
+        "netting_correctness" => circuit_matrices(NettingCorrectnessCircuit::<Fr>::empty()),
+        "cdr_batch_privacy" => circuit_matrices(CDRBatchCircuit::<Fr>::empty()),
+        _ => Err(BlockchainError::NotFound(format!("Unknown circuit {}", circuit_id))),
+    }
+}
+
+fn vk_hash(verifying_key: &VerifyingKey<Bn254>) -> Result<Blake2bHash> {
+    let mut vk_bytes = Vec::new();
+    verifying_key.serialize_compressed(&mut vk_bytes)
+        .map_err(|e| BlockchainError::Serialization(format!("VK serialization error: {}", e)))?;
+    Ok(Blake2bHash::from_data(&vk_bytes))
 }
 
 #[cfg(test)]
@@ -578,11 +1205,15 @@ mod tests {
         let transcript = ceremony.run_ceremony(&mut rng).await.unwrap();
 
         assert!(matches!(transcript.verification_status, VerificationStatus::Verified));
-        assert_eq!(transcript.contributions.len(), 2); // Two circuits
+        assert_eq!(transcript.contributions.len(), 4); // Four circuits
+        assert!(transcript.single_party);
+        assert_eq!(transcript.participants, vec![SINGLE_PARTY_PARTICIPANT.to_string()]);
 
         // Verify keys exist
         assert!(ceremony.keys_exist("cdr_privacy").await);
         assert!(ceremony.keys_exist("settlement_calculation").await);
+        assert!(ceremony.keys_exist("netting_correctness").await);
+        assert!(ceremony.keys_exist("cdr_batch_privacy").await);
 
         // Test key loading
         let (pk, vk) = ceremony.load_circuit_keys("cdr_privacy").await.unwrap();
@@ -607,7 +1238,7 @@ mod tests {
 
         // Export VKs
         let vk_exports = ceremony.export_verifying_keys().await.unwrap();
-        assert_eq!(vk_exports.len(), 2);
+        assert_eq!(vk_exports.len(), 4);
 
         // Test import in new ceremony
         let temp_dir2 = tempdir().unwrap();
@@ -619,4 +1250,118 @@ mod tests {
         assert!(import_ceremony.keys_exist("cdr_privacy").await); // VK exists
         assert!(!import_ceremony.keys_exist("settlement_calculation").await); // No PK, but that's expected for import
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_verifier_without_proving_keys() {
+        let temp_dir = tempdir().unwrap();
+        let mut ceremony = TrustedSetupCeremony::sp_consortium_ceremony(temp_dir.path().to_path_buf());
+        ceremony.run_ceremony(&mut test_rng()).await.unwrap();
+
+        // A verifier node fetches verifying keys only
+        for circuit_id in CIRCUIT_IDS {
+            std::fs::remove_file(temp_dir.path().join(format!("{}.pk", circuit_id))).unwrap();
+        }
+        assert!(ceremony.verify_ceremony().await.unwrap());
+
+        let verifier = crate::zkp::albatross_zkp::AlbatrossZKVerifier::from_trusted_setup(temp_dir.path().to_path_buf()).await;
+        assert!(verifier.is_ok());
+    }
+
+    /// Three operators contributing in turn to small powers of tau, then to the `cdr_privacy` parameters
+    fn multi_party_transcript(keys: &[BLSPrivateKey]) -> CeremonyTranscript {
+        let mut rng = test_rng();
+        let participants: Vec<String> = ["T-Mobile-DE", "Vodafone-UK", "Orange-FR"].iter().map(|name| name.to_string()).collect();
+        let mut transcript = CeremonyTranscript {
+            ceremony_id: "sp-consortium-test".to_string(),
+            start_time: 0,
+            end_time: None,
+            participants: participants.clone(),
+            contributions: Vec::new(),
+            final_parameters_hash: None,
+            verification_status: VerificationStatus::Pending,
+            powers_degree: 4,
+            single_party: false,
+        };
+
+        let mut powers = PowersOfTau::new(4);
+        let mut previous_hash = Blake2bHash::default();
+        for (participant, key) in participants.iter().zip(keys) {
+            let update = powers.contribute(&mut rng);
+            let hash = powers.hash().unwrap();
+            transcript.contributions.push(ParticipantContribution::signed(
+                &transcript.ceremony_id, participant, POWERS_OF_TAU, previous_hash, hash, update.to_bytes().unwrap(), key,
+            ).unwrap());
+            previous_hash = hash;
+        }
+
+        // δ is all the chain of phase 2 contributions checks, queries are left out
+        let mut parameters = ProvingKey::<Bn254> {
+            vk: VerifyingKey {
+                alpha_g1: powers.alpha_tau_g1[0],
+                beta_g2: powers.beta_g2,
+                gamma_g2: ark_bn254::G2Affine::generator(),
+                delta_g2: ark_bn254::G2Affine::generator(),
+                gamma_abc_g1: vec![],
+            },
+            beta_g1: powers.beta_tau_g1[0],
+            delta_g1: G1Affine::generator(),
+            a_query: vec![],
+            b_g1_query: vec![],
+            b_g2_query: vec![],
+            h_query: vec![],
+            l_query: vec![],
+        };
+        for (participant, key) in participants.iter().zip(keys) {
+            let update = contribute_delta(&mut parameters, &mut rng);
+            let hash = vk_hash(&parameters.vk).unwrap();
+            transcript.contributions.push(ParticipantContribution::signed(
+                &transcript.ceremony_id, participant, "cdr_privacy", previous_hash, hash, update.to_bytes().unwrap(), key,
+            ).unwrap());
+            previous_hash = hash;
+        }
+        transcript
+    }
+
+    #[test]
+    fn test_signed_contribution_chain() {
+        let keys: Vec<BLSPrivateKey> = (0..3).map(|_| BLSPrivateKey::generate().unwrap()).collect();
+        let transcript = multi_party_transcript(&keys);
+        transcript.verify_contributions().unwrap();
+        assert!(!transcript.is_complete());
+
+        // A ceremony in progress checks out as far as it got
+        let mut in_progress = transcript.clone();
+        in_progress.contributions.truncate(2);
+        in_progress.verify_contributions().unwrap();
+
+        // Contributions out of participant order
+        let mut reordered = transcript.clone();
+        reordered.contributions.swap(0, 1);
+        assert!(reordered.verify_contributions().is_err());
+
+        // A participant signing for another
+        let mut impersonated = transcript.clone();
+        let contribution = &impersonated.contributions[2];
+        impersonated.contributions[2] = ParticipantContribution::signed(
+            &impersonated.ceremony_id, &contribution.participant_id, POWERS_OF_TAU,
+            contribution.previous_hash, contribution.contribution_hash, contribution.update.clone(), &keys[1],
+        ).unwrap();
+        assert!(impersonated.verify_contributions().is_err());
+
+        // The last participant discarding the others' randomness for powers whose τ it knows
+        let mut restarted = transcript.clone();
+        let mut powers = PowersOfTau::new(4);
+        let update = powers.contribute(&mut test_rng());
+        let contribution = &restarted.contributions[2];
+        restarted.contributions[2] = ParticipantContribution::signed(
+            &restarted.ceremony_id, &contribution.participant_id, POWERS_OF_TAU,
+            contribution.previous_hash, powers.hash().unwrap(), update.to_bytes().unwrap(), &keys[2],
+        ).unwrap();
+        assert!(restarted.verify_contributions().is_err());
+
+        // An edited contribution no longer matches its signature
+        let mut edited = transcript.clone();
+        edited.contributions[4].contribution_hash = Blake2bHash::from_data(b"other keys");
+        assert!(edited.verify_contributions().is_err());
+    }
+}